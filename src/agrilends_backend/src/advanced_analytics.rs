@@ -71,6 +71,25 @@ pub struct Cohort {
     pub metrics: HashMap<String, f64>,
 }
 
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum CohortGranularity {
+    Month,
+    Quarter,
+}
+
+/// Investors grouped by the period of their first deposit
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct CohortStats {
+    pub cohort_id: String,     // "2025-03" or "2025-Q1"
+    pub period_start: u64,     // Nanoseconds, start of the cohort period (UTC)
+    pub investor_count: u64,
+    pub active_count: u64,     // Activity within the churn window
+    pub churned_count: u64,    // No activity for CHURN_INACTIVITY_DAYS
+    pub total_invested_balance: u64,
+    pub average_net_return: i64,     // Satoshi: balance + withdrawn - deposited, averaged per investor
+    pub average_net_return_bps: i64, // Average of each investor's net return over deposits
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct Insight {
     pub insight_id: u64,
@@ -194,6 +213,121 @@ pub enum PredictionType {
 
 // ========== ANALYTICS FUNCTIONS ==========
 
+const CHURN_INACTIVITY_DAYS: u64 = 90;
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Civil (year, month) for a nanosecond UTC timestamp
+fn year_month_from_nanos(timestamp: u64) -> (i64, u32) {
+    // Days-to-civil conversion from Howard Hinnant's date algorithms
+    let z = (timestamp / NANOS_PER_DAY) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month)
+}
+
+/// Nanosecond timestamp of the first day of a month (UTC)
+pub(crate) fn month_start_nanos(year: i64, month: u32) -> u64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    (days.max(0) as u64) * NANOS_PER_DAY
+}
+
+/// Cohort label and period start for a first-deposit timestamp
+pub fn cohort_period(timestamp: u64, granularity: CohortGranularity) -> (String, u64) {
+    let (year, month) = year_month_from_nanos(timestamp);
+    match granularity {
+        CohortGranularity::Month => (format!("{}-{:02}", year, month), month_start_nanos(year, month)),
+        CohortGranularity::Quarter => {
+            let quarter = (month - 1) / 3 + 1;
+            (format!("{}-Q{}", year, quarter), month_start_nanos(year, (quarter - 1) * 3 + 1))
+        },
+    }
+}
+
+/// Group investors by first deposit period, oldest cohort first
+pub fn build_investor_cohorts(
+    balances: &[InvestorBalance],
+    granularity: CohortGranularity,
+    start_time: Option<u64>,
+    end_time: Option<u64>,
+    now: u64,
+) -> Vec<CohortStats> {
+    let churn_window = CHURN_INACTIVITY_DAYS * NANOS_PER_DAY;
+    // period_start -> (label, members)
+    let mut groups: std::collections::BTreeMap<u64, (String, Vec<&InvestorBalance>)> = std::collections::BTreeMap::new();
+    
+    for balance in balances {
+        if start_time.map_or(false, |start| balance.first_deposit_at < start)
+            || end_time.map_or(false, |end| balance.first_deposit_at > end)
+        {
+            continue;
+        }
+        let (label, period_start) = cohort_period(balance.first_deposit_at, granularity);
+        groups.entry(period_start).or_insert_with(|| (label, Vec::new())).1.push(balance);
+    }
+    
+    groups.into_iter().map(|(period_start, (cohort_id, members))| {
+        let investor_count = members.len() as u64;
+        let churned_count = members.iter()
+            .filter(|b| now.saturating_sub(b.last_activity_at) >= churn_window)
+            .count() as u64;
+        let net_returns: Vec<i64> = members.iter()
+            .map(|b| (b.balance as i64 + b.total_withdrawn as i64) - b.total_deposited as i64)
+            .collect();
+        let net_return_bps_sum: i64 = members.iter().zip(&net_returns)
+            .map(|(b, net)| if b.total_deposited > 0 { net * 10_000 / b.total_deposited as i64 } else { 0 })
+            .sum();
+        
+        CohortStats {
+            cohort_id,
+            period_start,
+            investor_count,
+            active_count: investor_count - churned_count,
+            churned_count,
+            total_invested_balance: members.iter().map(|b| b.balance).sum(),
+            average_net_return: net_returns.iter().sum::<i64>() / investor_count as i64,
+            average_net_return_bps: net_return_bps_sum / investor_count as i64,
+        }
+    }).collect()
+}
+
+/// Investor retention by first-deposit cohort, oldest first (admin only)
+#[query]
+pub fn get_investor_cohorts(
+    granularity: Option<CohortGranularity>,
+    start_time: Option<u64>,
+    end_time: Option<u64>,
+) -> Result<Vec<CohortStats>, String> {
+    let caller = caller();
+    
+    if !is_admin(&caller) {
+        return Err("Access denied: Admin privileges required".to_string());
+    }
+    if let (Some(start), Some(end)) = (start_time, end_time) {
+        if start > end {
+            return Err("start_time must not be after end_time".to_string());
+        }
+    }
+    
+    Ok(build_investor_cohorts(
+        &get_all_investor_balances(),
+        granularity.unwrap_or(CohortGranularity::Month),
+        start_time,
+        end_time,
+        time(),
+    ))
+}
+
 /// Generate comprehensive analytics report
 #[query]
 pub async fn generate_analytics_report(
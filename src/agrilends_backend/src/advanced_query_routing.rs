@@ -173,6 +173,7 @@ thread_local! {
         avg_query_time_ms: 0,
         failed_queries: 0,
         active_queries: 0,
+        cache_invalidations: 0,
         last_reset: 0,
     });
 }
@@ -185,6 +186,7 @@ pub struct QueryStatistics {
     pub avg_query_time_ms: u64,
     pub failed_queries: u64,
     pub active_queries: u64,
+    pub cache_invalidations: u64, // Entries dropped because the underlying data changed
     pub last_reset: u64,
 }
 
@@ -459,12 +461,18 @@ async fn execute_distributed_dashboard_query(query_plan: QueryPlan) -> Result<Fa
 
 /// Get data from cache if available and not expired
 fn get_from_cache(key: &str) -> Option<CacheEntry> {
+    lookup_cache_entry(key, time())
+}
+
+/// Cache lookup at `now`; expired entries are dropped on read
+pub fn lookup_cache_entry(key: &str, now: u64) -> Option<CacheEntry> {
     QUERY_CACHE.with(|cache| {
-        if let Some(mut entry) = cache.borrow().get(key) {
-            if entry.expires_at > time() {
+        let entry = cache.borrow().get(key);
+        if let Some(mut entry) = entry {
+            if entry.expires_at > now {
                 // Update access statistics
                 entry.access_count += 1;
-                entry.last_accessed = time();
+                entry.last_accessed = now;
                 cache.borrow_mut().insert(key.to_string(), entry.clone());
                 Some(entry)
             } else {
@@ -493,11 +501,75 @@ fn cache_query_result(key: &str, data: &FarmerDashboardAdvanced, ttl_seconds: u6
         size_bytes: key.len() as u64 + 1000, // Approximate size
     };
     
+    store_cache_entry(cache_entry);
+    Ok(())
+}
+
+pub fn store_cache_entry(entry: CacheEntry) {
     QUERY_CACHE.with(|cache| {
-        cache.borrow_mut().insert(key.to_string(), cache_entry);
+        cache.borrow_mut().insert(entry.key.clone(), entry);
     });
-    
-    Ok(())
+}
+
+/// Prefixes of cache keys that hold one principal's data, suffixed with `_<principal>`
+const PRINCIPAL_CACHE_PREFIXES: [&str; 3] = ["farmer_dashboard", "investor_dashboard", "user_dashboard"];
+
+/// Cache keys holding data for `principal`
+pub fn principal_cache_keys(principal: &Principal) -> Vec<String> {
+    PRINCIPAL_CACHE_PREFIXES.iter()
+        .map(|prefix| format!("{}_{}", prefix, principal.to_text()))
+        .collect()
+}
+
+/// Drop a principal's cached dashboards after their loans or balances change,
+/// leaving every other entry in place. Returns the number of entries removed.
+pub fn invalidate_cache_for(principal: Principal) -> u64 {
+    let removed = QUERY_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        principal_cache_keys(&principal).iter()
+            .filter(|key| cache.remove(*key).is_some())
+            .count() as u64
+    });
+
+    if removed > 0 {
+        QUERY_STATS.with(|stats| stats.borrow_mut().cache_invalidations += removed);
+    }
+    removed
+}
+
+/// Drop every cached entry
+pub fn clear_cache() {
+    let removed = QUERY_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let keys: Vec<String> = cache.iter().map(|(key, _)| key).collect();
+        for key in &keys {
+            cache.remove(key);
+        }
+        keys.len() as u64
+    });
+    QUERY_STATS.with(|stats| stats.borrow_mut().cache_invalidations += removed);
+}
+
+/// Cache occupancy plus hit, miss and invalidation counters
+pub fn get_cache_stats() -> CacheStats {
+    let now = time();
+    let (entries, expired_entries, size_bytes) = QUERY_CACHE.with(|cache| {
+        cache.borrow().iter().fold((0u64, 0u64, 0u64), |(entries, expired, size), (_, entry)| {
+            (entries + 1, expired + (entry.expires_at <= now) as u64, size + entry.size_bytes)
+        })
+    });
+    let stats = QUERY_STATS.with(|stats| stats.borrow().clone());
+    let lookups = stats.cache_hits + stats.cache_misses;
+
+    CacheStats {
+        entries,
+        expired_entries,
+        size_bytes,
+        hits: stats.cache_hits,
+        misses: stats.cache_misses,
+        invalidations: stats.cache_invalidations,
+        hit_rate: if lookups > 0 { stats.cache_hits as f64 / lookups as f64 * 100.0 } else { 0.0 },
+    }
 }
 
 /// Cache management heartbeat
@@ -559,6 +631,7 @@ pub fn get_query_statistics() -> QueryStatistics {
 #[update]
 pub fn reset_query_statistics() -> Result<(), String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "reset_query_statistics")?;
     if !is_admin(&caller) {
         return Err("Only admin can reset query statistics".to_string());
     }
@@ -571,6 +644,7 @@ pub fn reset_query_statistics() -> Result<(), String> {
             avg_query_time_ms: 0,
             failed_queries: 0,
             active_queries: 0,
+            cache_invalidations: 0,
             last_reset: time(),
         };
     });
@@ -632,6 +706,149 @@ pub fn get_cache_statistics() -> CacheStatistics {
     }
 }
 
+// ========== SHARDED LOAN READS ==========
+// Loan lookups that follow records onto `loan_data_canister` shards. A borrower's loans live on
+// the shard `get_shard_for_loan` places them on; a loan looked up by ID is read locally first,
+// then from each active shard in turn. With the sharded_loan_reads flag off (single-canister
+// deployments) only local storage is read.
+
+/// Where one step of a routed loan read looks
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoanReadRoute {
+    Local,
+    Shard { shard_id: u32, canister_id: Principal },
+}
+
+thread_local! {
+    // Active shards by ID. Refreshed by the update paths that change the shard layout, since
+    // anything a (composite) query writes here is discarded when the query returns.
+    static SHARD_OWNERSHIP: RefCell<Option<Vec<(u32, Principal)>>> = RefCell::new(None);
+}
+
+fn load_active_shard_routes() -> Vec<(u32, Principal)> {
+    let mut routes: Vec<(u32, Principal)> = get_all_shards()
+        .into_iter()
+        .filter(|shard| shard.is_active)
+        .map(|shard| (shard.shard_id, shard.canister_id))
+        .collect();
+    routes.sort_by_key(|(shard_id, _)| *shard_id);
+    routes
+}
+
+/// Rebuild the cached shard layout from shard storage. Called from updates that create,
+/// deactivate or migrate shards, and after upgrades.
+pub fn refresh_shard_ownership() {
+    let routes = load_active_shard_routes();
+    SHARD_OWNERSHIP.with(|ownership| *ownership.borrow_mut() = Some(routes));
+}
+
+fn active_shard_routes() -> Vec<(u32, Principal)> {
+    SHARD_OWNERSHIP
+        .with(|ownership| ownership.borrow().clone())
+        .unwrap_or_else(load_active_shard_routes)
+}
+
+/// Owner shard of a borrower with principal hash `user_hash`, then the remaining active shards
+/// (loans placed elsewhere when the owner was full, or before the layout changed), then local
+/// storage for loans not yet migrated
+pub fn plan_borrower_read(user_hash: u64, shards: &[(u32, Principal)], sharding_enabled: bool) -> Vec<LoanReadRoute> {
+    if !sharding_enabled || shards.is_empty() {
+        return vec![LoanReadRoute::Local];
+    }
+    let owner_id = crate::scalability_architecture::owning_shard_id(user_hash, shards.len());
+    let mut ordered: Vec<&(u32, Principal)> = shards.iter().filter(|(shard_id, _)| *shard_id == owner_id).collect();
+    ordered.extend(shards.iter().filter(|(shard_id, _)| *shard_id != owner_id));
+    let mut routes: Vec<LoanReadRoute> = ordered
+        .into_iter()
+        .map(|(shard_id, canister_id)| LoanReadRoute::Shard { shard_id: *shard_id, canister_id: *canister_id })
+        .collect();
+    routes.push(LoanReadRoute::Local);
+    routes
+}
+
+/// Every active shard in ID order, then local storage, so a shard's copy of a migrated loan
+/// wins over a stale local one (as in `merge_routed_loans`)
+pub fn plan_loan_read(shards: &[(u32, Principal)], sharding_enabled: bool) -> Vec<LoanReadRoute> {
+    let mut routes = Vec::new();
+    if sharding_enabled {
+        routes.extend(shards.iter().map(|(shard_id, canister_id)| LoanReadRoute::Shard {
+            shard_id: *shard_id,
+            canister_id: *canister_id,
+        }));
+    }
+    routes.push(LoanReadRoute::Local);
+    routes
+}
+
+/// Shard copies replace local ones with the same ID, since the shard owns a migrated loan
+pub fn merge_routed_loans(shard_loans: Vec<Loan>, local_loans: Vec<Loan>) -> Vec<Loan> {
+    let mut merged: BTreeMap<u64, Loan> = local_loans.into_iter().map(|loan| (loan.id, loan)).collect();
+    merged.extend(shard_loans.into_iter().map(|loan| (loan.id, loan)));
+    merged.into_values().collect()
+}
+
+async fn fetch_shard_loan(canister_id: Principal, loan_id: u64) -> Result<Loan, String> {
+    let (result,): (Result<Loan, String>,) = call(canister_id, "get_loan", (loan_id,))
+        .await
+        .map_err(|(code, message)| format!("Shard call failed: {:?} {}", code, message))?;
+    result
+}
+
+async fn fetch_shard_user_loans(canister_id: Principal, borrower: Principal) -> Result<Vec<Loan>, String> {
+    let (result,): (Result<Vec<Loan>, String>,) = call(canister_id, "get_user_loans", (borrower,))
+        .await
+        .map_err(|(code, message)| format!("Shard call failed: {:?} {}", code, message))?;
+    result
+}
+
+fn sharded_reads_enabled() -> bool {
+    crate::feature_flags::is_feature_enabled(crate::feature_flags::FEATURE_SHARDED_LOAN_READS)
+}
+
+/// A loan wherever it is stored, shard copies ahead of local (borrower or admin). Shards that
+/// fail to answer are skipped.
+#[query(composite = true)]
+pub async fn get_loan_by_id_routed(loan_id: u64) -> Result<Loan, String> {
+    let caller = caller();
+    let mut loan = None;
+    for route in plan_loan_read(&active_shard_routes(), sharded_reads_enabled()) {
+        loan = match route {
+            LoanReadRoute::Local => crate::storage::get_loan_by_id(loan_id),
+            LoanReadRoute::Shard { canister_id, .. } => fetch_shard_loan(canister_id, loan_id).await.ok(),
+        };
+        if loan.is_some() {
+            break;
+        }
+    }
+    let loan = loan.ok_or_else(|| "Loan not found".to_string())?;
+    if loan.borrower != caller && !is_admin(&caller) {
+        return Err("Unauthorized: Only the borrower or an admin can view this loan".to_string());
+    }
+    Ok(loan)
+}
+
+/// A borrower's loans from every active shard, owner shard first, and local storage (the
+/// borrower or an admin). Shards that cannot be reached are skipped.
+#[query(composite = true)]
+pub async fn get_loans_by_borrower_routed(borrower: Principal) -> Result<Vec<Loan>, String> {
+    let caller = caller();
+    if borrower != caller && !is_admin(&caller) {
+        return Err("Unauthorized: Only the borrower or an admin can view these loans".to_string());
+    }
+    let user_hash = crate::scalability_architecture::hash_principal(&borrower);
+    let mut shard_loans = Vec::new();
+    let mut local_loans = Vec::new();
+    for route in plan_borrower_read(user_hash, &active_shard_routes(), sharded_reads_enabled()) {
+        match route {
+            LoanReadRoute::Local => local_loans = crate::storage::get_loans_by_borrower(borrower),
+            LoanReadRoute::Shard { canister_id, .. } => {
+                shard_loans.extend(fetch_shard_user_loans(canister_id, borrower).await.unwrap_or_default());
+            }
+        }
+    }
+    Ok(merge_routed_loans(shard_loans, local_loans))
+}
+
 // ========== HELPER FUNCTIONS ==========
 
 async fn query_shard_for_user_loans(canister_id: Principal, query_params: QueryParams) -> Result<Vec<Loan>, String> {
@@ -757,6 +974,17 @@ pub struct CacheStatistics {
     pub last_cleanup: u64,
 }
 
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CacheStats {
+    pub entries: u64,
+    pub expired_entries: u64,
+    pub size_bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub invalidations: u64,
+    pub hit_rate: f64, // Percent of lookups served from cache
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug, Default)]
 pub struct InvestorShardData {
     pub investments: Vec<Investment>,
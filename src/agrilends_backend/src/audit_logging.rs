@@ -6,18 +6,19 @@
 use ic_cdk::{caller, api::time, api::management_canister::main::{canister_status, CanisterIdRecord}};
 use ic_cdk_macros::{query, update, heartbeat};
 use candid::{CandidType, Deserialize, Principal};
-use ic_stable_structures::{StableBTreeMap, memory::MemoryId};
+use serde::Serialize;
+use ic_stable_structures::{StableBTreeMap, Storable, memory::MemoryId};
 use ic_stable_structures::memory::VirtualMemory;
 use ic_stable_structures::DefaultMemoryImpl;
 use std::cell::RefCell;
 use std::collections::HashMap;
 
 use crate::types::*;
-use crate::storage::{get_memory_by_id, AUDIT_LOG_COUNTER};
+use crate::storage::{get_memory_by_id, next_audit_log_id};
 use crate::helpers::is_admin;
 
 // Enhanced audit log types
-#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum AuditEventLevel {
     Info,     // General information
     Warning,  // Potential issues
@@ -27,7 +28,7 @@ pub enum AuditEventLevel {
     Debug,    // Debug information (production logs)
 }
 
-#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum AuditCategory {
     UserManagement,      // User registration, role changes
     NFTOperations,       // RWA-NFT minting, transfers
@@ -47,11 +48,11 @@ pub enum AuditCategory {
 }
 
 // Enhanced audit log entry with additional metadata
-#[derive(CandidType, Deserialize, Clone, Debug)]
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct EnhancedAuditLog {
     pub id: u64,
     pub timestamp: u64,
-    pub block_height: Option<u64>, // IC block height for immutability verification
+    pub block_height: Option<u64>, // Monotonic chain height, anchored to certified data by the heartbeat
     pub caller: Principal,
     pub category: AuditCategory,
     pub action: String,
@@ -63,9 +64,81 @@ pub struct EnhancedAuditLog {
     pub ip_hash: Option<String>,        // Hashed IP for privacy-compliant tracking
     pub canister_id: Option<Principal>, // Source canister ID
     pub version: String,                // System version
+    pub prev_log_hash: Option<String>,  // Hash of the preceding log entry (hash chain link)
+    pub log_hash: Option<String>,       // SHA-256 over this entry, including prev_log_hash
+}
+
+// Periodic anchor of the audit hash chain into the canister's certified data
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct AuditChainAnchor {
+    pub height: u64,
+    pub log_id: u64,
+    pub chain_hash: String,
+    pub anchored_at: u64,
+}
+
+impl Storable for AuditChainAnchor {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        std::borrow::Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+}
+
+// Proof that a log's chain hash was intact when its PII was scrubbed in place. The hash of
+// the scrubbed entry lets later verification still detect changes to the remaining fields.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct AnonymizationSeal {
+    pub log_id: u64,
+    pub original_log_hash: Option<String>,
+    pub scrubbed_log_hash: String,
+    pub anonymized_at: u64,
+}
+
+impl Storable for AnonymizationSeal {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        std::borrow::Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
 }
 
+// Progress of data-retention enforcement over stored logs
 #[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct RetentionComplianceStatus {
+    pub anonymization_enabled: bool,
+    pub anonymization_after_days: u64,
+    pub data_retention_days: u64,
+    pub total_logs: u64,
+    pub anonymized_logs: u64,
+    pub pending_anonymization: u64, // Past the anonymization window but still holding PII
+    pub pending_deletion: u64,      // Past the retention period but still stored
+    pub oldest_log_timestamp: Option<u64>,
+    pub checked_at: u64,
+}
+
+// Result of verifying a single log against the hash chain
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct AuditIntegrityReport {
+    pub log_id: u64,
+    pub is_valid: bool,
+    pub hash_matches: bool,
+    pub chain_link_valid: bool,
+    pub previous_log_id: Option<u64>,
+    pub expected_hash: String,
+    pub stored_hash: Option<String>,
+    pub issues: Vec<String>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct AuditDetails {
     pub description: String,
     pub entity_type: Option<String>,    // e.g., "loan", "nft", "user"
@@ -79,7 +152,7 @@ pub struct AuditDetails {
     pub user_agent_hash: Option<String>, // User agent hash
 }
 
-#[derive(CandidType, Deserialize, Clone, Debug)]
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct AuditResult {
     pub success: bool,
     pub error_code: Option<String>,
@@ -91,6 +164,54 @@ pub struct AuditResult {
     pub warning_flags: Vec<String>,
 }
 
+// Export projection of an audit log with metadata and tracking hashes stripped
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct AuditLogExportRecord {
+    pub id: u64,
+    pub timestamp: u64,
+    pub block_height: Option<u64>,
+    pub caller: Principal,
+    pub category: AuditCategory,
+    pub action: String,
+    pub level: AuditEventLevel,
+    pub description: String,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub affected_principals: Vec<Principal>,
+    pub risk_score: Option<u32>,
+    pub result: AuditResult,
+    pub correlation_id: Option<String>,
+    pub canister_id: Option<Principal>,
+    pub version: String,
+    pub prev_log_hash: Option<String>,
+    pub log_hash: Option<String>,
+}
+
+impl From<EnhancedAuditLog> for AuditLogExportRecord {
+    fn from(log: EnhancedAuditLog) -> Self {
+        Self {
+            id: log.id,
+            timestamp: log.timestamp,
+            block_height: log.block_height,
+            caller: log.caller,
+            category: log.category,
+            action: log.action,
+            level: log.level,
+            description: log.details.description,
+            entity_type: log.details.entity_type,
+            entity_id: log.details.entity_id,
+            affected_principals: log.details.affected_principals,
+            risk_score: log.details.risk_score,
+            result: log.result,
+            correlation_id: log.correlation_id,
+            canister_id: log.canister_id,
+            version: log.version,
+            prev_log_hash: log.prev_log_hash,
+            log_hash: log.log_hash,
+        }
+    }
+}
+
 // Audit query filters
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct AuditLogFilter {
@@ -213,9 +334,19 @@ pub struct ComplianceStatus {
 type Memory = VirtualMemory<DefaultMemoryImpl>;
 type EnhancedAuditStorage = StableBTreeMap<u64, EnhancedAuditLog, Memory>;
 type AuditConfigStorage = StableBTreeMap<u8, AuditConfiguration, Memory>;
+type AuditAnchorStorage = StableBTreeMap<u64, AuditChainAnchor, Memory>;
+type AnonymizationSealStorage = StableBTreeMap<u64, AnonymizationSeal, Memory>;
+type AuditCleanupCursorStorage = StableBTreeMap<u8, u64, Memory>;
+
+const DEFAULT_ANONYMIZATION_AFTER_DAYS: u64 = 90;
+const MAX_RETENTION_ACTIONS_PER_RUN: usize = 500;
+const MAX_CLEANUP_REMOVALS_PER_RUN: u64 = 2_000;
+const MAX_MANUAL_CLEANUP_REMOVALS: u64 = 10_000;
+const AUDIT_CLEANUP_CURSOR_KEY: u8 = 0;
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
 
 thread_local! {
-    static ENHANCED_AUDIT_LOGS: RefCell<EnhancedAuditStorage> = RefCell::new(
+    pub(crate) static ENHANCED_AUDIT_LOGS: RefCell<EnhancedAuditStorage> = RefCell::new(
         StableBTreeMap::init(get_memory_by_id(MemoryId::new(100)))
     );
     
@@ -223,6 +354,22 @@ thread_local! {
         StableBTreeMap::init(get_memory_by_id(MemoryId::new(101)))
     );
     
+    static AUDIT_CHAIN_ANCHORS: RefCell<AuditAnchorStorage> = RefCell::new(
+        StableBTreeMap::init(get_memory_by_id(MemoryId::new(102)))
+    );
+    
+    static ANONYMIZATION_SEALS: RefCell<AnonymizationSealStorage> = RefCell::new(
+        StableBTreeMap::init(get_memory_by_id(MemoryId::new(103)))
+    );
+    
+    // Next log ID an unfinished cleanup resumes from; absent when cleanup is caught up
+    static AUDIT_CLEANUP_CURSOR: RefCell<AuditCleanupCursorStorage> = RefCell::new(
+        StableBTreeMap::init(get_memory_by_id(MemoryId::new(104)))
+    );
+    
+    // Last log ID examined by the anonymization pass; resets to the start after an upgrade
+    static ANONYMIZATION_CURSOR: RefCell<u64> = RefCell::new(0);
+    
     static SESSION_TRACKER: RefCell<HashMap<Principal, String>> = RefCell::new(HashMap::new());
     static CORRELATION_TRACKER: RefCell<HashMap<String, Vec<u64>>> = RefCell::new(HashMap::new());
     static PERFORMANCE_TRACKER: RefCell<Vec<(u64, PerformanceMetrics)>> = RefCell::new(Vec::new());
@@ -266,6 +413,8 @@ pub struct AuditConfiguration {
     pub security_monitoring: bool,
     pub risk_assessment_enabled: bool,
     pub export_format: ExportFormat,
+    pub anonymization_after_days: Option<u64>, // Stored logs older than this have PII scrubbed in place
+    pub anonymize_caller_principal: Option<bool>, // Also clear the caller on non-security logs
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -293,6 +442,8 @@ impl Default for AuditConfiguration {
             security_monitoring: true,
             risk_assessment_enabled: true,
             export_format: ExportFormat::JSON,
+            anonymization_after_days: Some(DEFAULT_ANONYMIZATION_AFTER_DAYS),
+            anonymize_caller_principal: Some(false),
         }
     }
 }
@@ -361,14 +512,67 @@ impl Default for AuditDetails {
 
 // ========== CORE LOGGING FUNCTIONS ==========
 
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuditCleanupResult {
+    pub removed_count: u64,
+    pub has_more: bool, // Expired logs remain; the next run resumes from the stored cursor
+}
+
+/// One bounded cleanup step over `(log_id, timestamp)` pairs in ID order, starting at the cursor.
+/// Log IDs are issued in time order, so the scan stops at the first log newer than `cutoff`.
+/// Returns the IDs to remove and the cursor to resume from, or None once caught up.
+pub fn plan_audit_cleanup_step<I>(entries: I, cutoff: u64, max_to_remove: u64) -> (Vec<u64>, Option<u64>)
+where
+    I: Iterator<Item = (u64, u64)>,
+{
+    let mut expired = entries.take_while(|(_, timestamp)| *timestamp < cutoff);
+    let to_remove: Vec<u64> = expired.by_ref().take(max_to_remove as usize).map(|(id, _)| id).collect();
+    let next_cursor = expired.next().map(|(id, _)| id);
+    (to_remove, next_cursor)
+}
+
+/// Whether a previous cleanup stopped at its removal cap with expired logs left
+pub fn audit_cleanup_in_progress() -> bool {
+    AUDIT_CLEANUP_CURSOR.with(|cursor| cursor.borrow().contains_key(&AUDIT_CLEANUP_CURSOR_KEY))
+}
+
+/// Remove at most `max_to_remove` logs older than `cutoff`, resuming from the stored cursor
+fn remove_expired_audit_logs(cutoff: u64, max_to_remove: u64) -> AuditCleanupResult {
+    let cursor = AUDIT_CLEANUP_CURSOR.with(|cursor| cursor.borrow().get(&AUDIT_CLEANUP_CURSOR_KEY)).unwrap_or(0);
+    let (to_remove, next_cursor) = ENHANCED_AUDIT_LOGS.with(|logs| {
+        plan_audit_cleanup_step(
+            logs.borrow().range(cursor..).map(|(id, log)| (id, log.timestamp)),
+            cutoff,
+            max_to_remove,
+        )
+    });
+
+    for log_id in &to_remove {
+        ENHANCED_AUDIT_LOGS.with(|logs| logs.borrow_mut().remove(log_id));
+        ANONYMIZATION_SEALS.with(|seals| seals.borrow_mut().remove(log_id));
+    }
+    AUDIT_CLEANUP_CURSOR.with(|cursor| {
+        let mut cursor = cursor.borrow_mut();
+        match next_cursor {
+            Some(next) => { cursor.insert(AUDIT_CLEANUP_CURSOR_KEY, next); },
+            None => { cursor.remove(&AUDIT_CLEANUP_CURSOR_KEY); },
+        }
+    });
+
+    AuditCleanupResult {
+        removed_count: to_remove.len() as u64,
+        has_more: next_cursor.is_some(),
+    }
+}
+
 fn maybe_cleanup_old_logs(config: &AuditConfiguration) {
     // Check if cleanup is needed based on storage usage or age
     let current_time = time();
     static mut LAST_CLEANUP: u64 = 0;
     
     unsafe {
-        // Run cleanup every 24 hours
-        if current_time - LAST_CLEANUP < 24 * 60 * 60 * 1_000_000_000 {
+        // Run cleanup every 24 hours, or straight away while an earlier run is unfinished
+        if current_time - LAST_CLEANUP < 24 * 60 * 60 * 1_000_000_000 && !audit_cleanup_in_progress() {
             return;
         }
         LAST_CLEANUP = current_time;
@@ -377,28 +581,12 @@ fn maybe_cleanup_old_logs(config: &AuditConfiguration) {
     // Check storage usage
     let total_logs = ENHANCED_AUDIT_LOGS.with(|logs| logs.borrow().len());
     
-    if total_logs > config.max_logs_per_category {
+    if total_logs > config.max_logs_per_category || audit_cleanup_in_progress() {
         let cutoff_time = current_time.saturating_sub(config.cleanup_threshold_days * 24 * 60 * 60 * 1_000_000_000);
+        let result = remove_expired_audit_logs(cutoff_time, MAX_CLEANUP_REMOVALS_PER_RUN);
         
-        let removed_count = ENHANCED_AUDIT_LOGS.with(|logs| {
-            let mut logs_map = logs.borrow_mut();
-            let mut to_remove = Vec::new();
-            
-            for (log_id, log) in logs_map.iter() {
-                if log.timestamp < cutoff_time {
-                    to_remove.push(log_id);
-                }
-            }
-            
-            for log_id in &to_remove {
-                logs_map.remove(log_id);
-            }
-            
-            to_remove.len()
-        });
-        
-        if removed_count > 0 {
-            ic_cdk::println!("🧹 Auto cleanup: Removed {} old audit logs", removed_count);
+        if result.removed_count > 0 {
+            ic_cdk::println!("🧹 Auto cleanup: Removed {} old audit logs (more pending: {})", result.removed_count, result.has_more);
         }
     }
 }
@@ -433,10 +621,18 @@ pub fn log_audit_enhanced(
         }
     });
 
-    let log_entry = EnhancedAuditLog {
+    // Link to the head of the hash chain (the most recent stored log)
+    let (prev_height, prev_log_hash) = ENHANCED_AUDIT_LOGS.with(|logs| {
+        logs.borrow()
+            .last_key_value()
+            .map(|(_, last)| (last.block_height.unwrap_or(last.id), last.log_hash))
+            .unwrap_or((0, None))
+    });
+
+    let mut log_entry = EnhancedAuditLog {
         id: get_next_audit_id(),
         timestamp,
-        block_height: None, // TODO: Get IC block height when available
+        block_height: Some(prev_height + 1),
         caller,
         category: category.clone(),
         action: action.clone(),
@@ -448,7 +644,10 @@ pub fn log_audit_enhanced(
         ip_hash: None, // TODO: Implement privacy-compliant IP tracking
         canister_id: Some(ic_cdk::api::id()),
         version: env!("CARGO_PKG_VERSION").to_string(),
+        prev_log_hash,
+        log_hash: None,
     };
+    log_entry.log_hash = Some(compute_log_hash(&log_entry));
 
     // Store the log
     ENHANCED_AUDIT_LOGS.with(|logs| {
@@ -847,6 +1046,8 @@ pub fn log_treasury_operation(
         None,
     );
 }
+
+pub fn log_security_event(
     event_type: &str,
     severity: AuditEventLevel,
     description: String,
@@ -969,7 +1170,7 @@ pub fn log_ckbtc_integration_operation(
     let mut metadata = vec![];
     if let Some(amt) = amount {
         metadata.push(("amount_satoshi".to_string(), amt.to_string()));
-        metadata.push(("amount_btc".to_string(), format!("{:.8}", amt as f64 / 100_000_000.0)));
+        metadata.push(("amount_btc".to_string(), crate::helpers::format_amount(amt, Asset::CkBtc)));
     }
     if let Some(tx_id) = &transaction_id {
         metadata.push(("transaction_id".to_string(), tx_id.clone()));
@@ -1143,7 +1344,7 @@ fn calculate_ckbtc_risk_score(action: &str, amount: Option<u64>, success: bool)
     std::cmp::min(100, base_score + amount_factor + success_factor)
 }
 
-fn calculate_config_risk_score(setting_name: &str) -> u32 {
+pub(crate) fn calculate_config_risk_score(setting_name: &str) -> u32 {
     match setting_name {
         name if name.contains("admin") => 90,
         name if name.contains("emergency") => 85,
@@ -1910,86 +2111,9 @@ pub fn get_audit_logs_filtered(filter: AuditLogFilter) -> Result<Vec<EnhancedAud
         let mut result: Vec<EnhancedAuditLog> = Vec::new();
         
         for (_, log) in logs_map.iter() {
-            // Apply filters
-            if let Some(start_time) = filter.start_time {
-                if log.timestamp < start_time {
-                    continue;
-                }
-            }
-            
-            if let Some(end_time) = filter.end_time {
-                if log.timestamp > end_time {
-                    continue;
-                }
-            }
-            
-            if let Some(filter_caller) = filter.caller {
-                if log.caller != filter_caller {
-                    continue;
-                }
-            }
-            
-            if let Some(category) = &filter.category {
-                if log.category != *category {
-                    continue;
-                }
-            }
-            
-            if let Some(level) = &filter.level {
-                if log.level != *level {
-                    continue;
-                }
-            }
-            
-            if let Some(success_only) = filter.success_only {
-                if success_only && !log.result.success {
-                    continue;
-                }
-            }
-            
-            if let Some(entity_type) = &filter.entity_type {
-                if log.details.entity_type.as_ref() != Some(entity_type) {
-                    continue;
-                }
-            }
-            
-            if let Some(entity_id) = &filter.entity_id {
-                if log.details.entity_id.as_ref() != Some(entity_id) {
-                    continue;
-                }
-            }
-            
-            if let Some(pattern) = &filter.action_pattern {
-                if !log.action.contains(pattern) {
-                    continue;
-                }
-            }
-            
-            if let Some(correlation) = &filter.correlation_id {
-                if log.correlation_id.as_ref() != Some(correlation) {
-                    continue;
-                }
-            }
-            
-            if let Some(session) = &filter.session_id {
-                if log.session_id.as_ref() != Some(session) {
-                    continue;
-                }
-            }
-            
-            // Risk score filtering
-            if let Some(min_risk) = filter.risk_score_min {
-                if log.details.risk_score.unwrap_or(0) < min_risk {
-                    continue;
-                }
+            if !audit_log_matches(&log, &filter) {
+                continue;
             }
-            
-            if let Some(max_risk) = filter.risk_score_max {
-                if log.details.risk_score.unwrap_or(0) > max_risk {
-                    continue;
-                }
-            }
-            
             result.push(log.clone());
         }
         
@@ -2026,6 +2150,90 @@ pub fn get_audit_logs_filtered(filter: AuditLogFilter) -> Result<Vec<EnhancedAud
 }
 }
 
+/// Whether a log entry passes every condition set on `filter`; paging and sort fields are ignored
+fn audit_log_matches(log: &EnhancedAuditLog, filter: &AuditLogFilter) -> bool {
+    if let Some(start_time) = filter.start_time {
+        if log.timestamp < start_time {
+            return false;
+        }
+    }
+    
+    if let Some(end_time) = filter.end_time {
+        if log.timestamp > end_time {
+            return false;
+        }
+    }
+    
+    if let Some(filter_caller) = filter.caller {
+        if log.caller != filter_caller {
+            return false;
+        }
+    }
+    
+    if let Some(category) = &filter.category {
+        if log.category != *category {
+            return false;
+        }
+    }
+    
+    if let Some(level) = &filter.level {
+        if log.level != *level {
+            return false;
+        }
+    }
+    
+    if let Some(success_only) = filter.success_only {
+        if success_only && !log.result.success {
+            return false;
+        }
+    }
+    
+    if let Some(entity_type) = &filter.entity_type {
+        if log.details.entity_type.as_ref() != Some(entity_type) {
+            return false;
+        }
+    }
+    
+    if let Some(entity_id) = &filter.entity_id {
+        if log.details.entity_id.as_ref() != Some(entity_id) {
+            return false;
+        }
+    }
+    
+    if let Some(pattern) = &filter.action_pattern {
+        if !log.action.contains(pattern) {
+            return false;
+        }
+    }
+    
+    if let Some(correlation) = &filter.correlation_id {
+        if log.correlation_id.as_ref() != Some(correlation) {
+            return false;
+        }
+    }
+    
+    if let Some(session) = &filter.session_id {
+        if log.session_id.as_ref() != Some(session) {
+            return false;
+        }
+    }
+    
+    // Risk score filtering
+    if let Some(min_risk) = filter.risk_score_min {
+        if log.details.risk_score.unwrap_or(0) < min_risk {
+            return false;
+        }
+    }
+    
+    if let Some(max_risk) = filter.risk_score_max {
+        if log.details.risk_score.unwrap_or(0) > max_risk {
+            return false;
+        }
+    }
+    
+    true
+}
+
 /// Get audit statistics
 #[query]
 pub fn get_audit_statistics() -> Result<AuditStatistics, String> {
@@ -2140,8 +2348,9 @@ pub fn get_audit_statistics() -> Result<AuditStatistics, String> {
         caller_vec.sort_by(|a, b| b.1.cmp(&a.1));
         stats.most_active_callers = caller_vec.into_iter().take(10).collect();
 
-        // Estimate storage usage (rough calculation)
-        stats.storage_usage_bytes = stats.total_logs * 2048; // Rough estimate: 2KB per log
+        // Stable memory actually allocated to audit log storage
+        let memory = crate::storage::get_memory_breakdown();
+        stats.storage_usage_bytes = memory.audit_logs_bytes + memory.enhanced_audit_logs_bytes;
 
         Ok(stats)
     })
@@ -2179,12 +2388,47 @@ pub fn get_logs_by_correlation(correlation_id: String) -> Result<Vec<EnhancedAud
     })
 }
 
+/// Verify a log entry against the audit hash chain (admin only)
+#[query]
+pub fn verify_log_integrity(log_id: u64) -> Result<AuditIntegrityReport, String> {
+    let caller = caller();
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can verify audit log integrity".to_string());
+    }
+
+    let (log, previous) = ENHANCED_AUDIT_LOGS.with(|logs| {
+        let logs_map = logs.borrow();
+        let log = logs_map.get(&log_id);
+        let previous = logs_map.range(..log_id).next_back().map(|(_, prev)| prev);
+        (log, previous)
+    });
+
+    let log = log.ok_or_else(|| format!("Audit log {} not found", log_id))?;
+    Ok(verify_chain_link(previous.as_ref(), &log))
+}
+
+/// Get the most recent anchor of the audit hash chain together with the IC certificate
+#[query]
+pub fn get_audit_chain_anchor() -> Result<(AuditChainAnchor, Option<Vec<u8>>), String> {
+    let caller = caller();
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can view audit chain anchors".to_string());
+    }
+
+    let anchor = AUDIT_CHAIN_ANCHORS.with(|anchors| {
+        anchors.borrow().last_key_value().map(|(_, anchor)| anchor)
+    }).ok_or_else(|| "Audit chain has not been anchored yet".to_string())?;
+
+    Ok((anchor, ic_cdk::api::data_certificate()))
+}
+
 // ========== CONFIGURATION FUNCTIONS ==========
 
 /// Update audit configuration (admin only)
 #[update]
 pub fn update_audit_config(config: AuditConfiguration) -> Result<(), String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "update_audit_config")?;
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can update audit configuration".to_string());
     }
@@ -2229,33 +2473,24 @@ pub fn get_audit_config() -> AuditConfiguration {
 
 // ========== MAINTENANCE FUNCTIONS ==========
 
-/// Manual cleanup of old logs (admin only)
+/// Manual cleanup of old logs (admin only). Removes at most `max_to_remove` logs per call;
+/// call again while `has_more` is set.
 #[update]
-pub fn cleanup_old_audit_logs(days_to_keep: u64) -> Result<u64, String> {
+pub fn cleanup_old_audit_logs(days_to_keep: u64, max_to_remove: u64) -> Result<AuditCleanupResult, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "cleanup_old_audit_logs")?;
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can cleanup audit logs".to_string());
     }
+    if max_to_remove == 0 || max_to_remove > MAX_MANUAL_CLEANUP_REMOVALS {
+        return Err(format!("max_to_remove must be between 1 and {}", MAX_MANUAL_CLEANUP_REMOVALS));
+    }
 
     let current_time = time();
     let cutoff_time = current_time.saturating_sub(days_to_keep * 24 * 60 * 60 * 1_000_000_000);
     
-    let removed_count = ENHANCED_AUDIT_LOGS.with(|logs| {
-        let mut logs_map = logs.borrow_mut();
-        let mut to_remove = Vec::new();
-        
-        for (log_id, log) in logs_map.iter() {
-            if log.timestamp < cutoff_time {
-                to_remove.push(log_id);
-            }
-        }
-        
-        for log_id in &to_remove {
-            logs_map.remove(log_id);
-        }
-        
-        to_remove.len() as u64
-    });
+    let result = remove_expired_audit_logs(cutoff_time, max_to_remove);
+    let removed_count = result.removed_count;
 
     log_audit_enhanced(
         AuditCategory::Maintenance,
@@ -2271,6 +2506,7 @@ pub fn cleanup_old_audit_logs(days_to_keep: u64) -> Result<u64, String> {
             metadata: vec![
                 ("removed_count".to_string(), removed_count.to_string()),
                 ("days_kept".to_string(), days_to_keep.to_string()),
+                ("has_more".to_string(), result.has_more.to_string()),
             ],
         },
         AuditResult {
@@ -2283,7 +2519,7 @@ pub fn cleanup_old_audit_logs(days_to_keep: u64) -> Result<u64, String> {
         None,
     );
 
-    Ok(removed_count)
+    Ok(result)
 }
 
 /// Export audit logs for compliance (admin only)
@@ -2317,11 +2553,110 @@ pub fn export_audit_logs_for_compliance(
 // ========== HELPER FUNCTIONS ==========
 
 fn get_next_audit_id() -> u64 {
-    AUDIT_LOG_COUNTER.with(|counter| {
-        let current = *counter.borrow();
-        *counter.borrow_mut() = current + 1;
-        current + 1
-    })
+    next_audit_log_id()
+}
+
+/// Largest enhanced audit log ID in use, for counter checks after an upgrade
+pub fn max_enhanced_audit_log_id() -> Option<u64> {
+    ENHANCED_AUDIT_LOGS.with(|logs| logs.borrow().last_key_value().map(|(id, _)| id))
+}
+
+/// Compute the chain hash of a log entry. The stored `log_hash` is excluded so the
+/// hash covers every other field, including the link to the previous entry.
+pub fn compute_log_hash(log: &EnhancedAuditLog) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut unsealed = log.clone();
+    unsealed.log_hash = None;
+    let bytes = candid::encode_one(&unsealed).unwrap_or_default();
+
+    let mut hasher = Sha256::new();
+    hasher.update(&bytes);
+    hex::encode(hasher.finalize())
+}
+
+/// A stored hash is intact when it recomputes, or when the log was sealed at anonymization
+/// and has not changed since
+fn log_hash_intact(log: &EnhancedAuditLog, recomputed: &str) -> bool {
+    if log.log_hash.as_deref() == Some(recomputed) {
+        return true;
+    }
+    ANONYMIZATION_SEALS.with(|seals| seals.borrow().get(&log.id))
+        .map_or(false, |seal| seal.original_log_hash == log.log_hash && seal.scrubbed_log_hash == recomputed)
+}
+
+/// Check a log's own hash and its link to the preceding log
+pub fn verify_chain_link(previous: Option<&EnhancedAuditLog>, log: &EnhancedAuditLog) -> AuditIntegrityReport {
+    let expected_hash = compute_log_hash(log);
+    let hash_matches = log_hash_intact(log, &expected_hash);
+    let mut issues = Vec::new();
+
+    if !hash_matches {
+        issues.push("Stored hash does not match recomputed hash; entry was modified".to_string());
+    }
+
+    let chain_link_valid = match previous {
+        Some(prev) => {
+            let prev_valid = log_hash_intact(prev, &compute_log_hash(prev));
+            if !prev_valid {
+                issues.push(format!("Previous log {} fails its own hash check", prev.id));
+            }
+            let linked = log.prev_log_hash == prev.log_hash;
+            if !linked {
+                issues.push(format!("prev_log_hash does not match hash of log {}", prev.id));
+            }
+            let height_ok = match (prev.block_height, log.block_height) {
+                (Some(prev_height), Some(height)) => height == prev_height + 1,
+                _ => true,
+            };
+            if !height_ok {
+                issues.push("Chain height is not contiguous with previous log".to_string());
+            }
+            prev_valid && linked && height_ok
+        },
+        // First retained log: either the genesis entry or the oldest log left after cleanup
+        None => true,
+    };
+
+    AuditIntegrityReport {
+        log_id: log.id,
+        is_valid: hash_matches && chain_link_valid,
+        hash_matches,
+        chain_link_valid,
+        previous_log_id: previous.map(|prev| prev.id),
+        expected_hash,
+        stored_hash: log.log_hash.clone(),
+        issues,
+    }
+}
+
+/// Anchor the current chain head into certified data so it can be checked against an IC certificate
+fn anchor_audit_chain() {
+    let head = ENHANCED_AUDIT_LOGS.with(|logs| {
+        logs.borrow().last_key_value().map(|(_, log)| log)
+    });
+
+    let Some(head) = head else { return; };
+    let Some(chain_hash) = head.log_hash.clone() else { return; };
+    let height = head.block_height.unwrap_or(head.id);
+
+    let already_anchored = AUDIT_CHAIN_ANCHORS.with(|anchors| anchors.borrow().contains_key(&height));
+    if already_anchored {
+        return;
+    }
+
+    if let Ok(hash_bytes) = hex::decode(&chain_hash) {
+        ic_cdk::api::certified_data_set(&hash_bytes);
+    }
+
+    AUDIT_CHAIN_ANCHORS.with(|anchors| {
+        anchors.borrow_mut().insert(height, AuditChainAnchor {
+            height,
+            log_id: head.id,
+            chain_hash,
+            anchored_at: time(),
+        });
+    });
 }
 
 fn generate_session_id(caller: &Principal, timestamp: u64) -> String {
@@ -2607,7 +2942,7 @@ fn calculate_security_summary() -> SecuritySummary {
     
     SecuritySummary {
         total_security_events: security_events_24h,
-        blacklisted_principals: 0, // TODO: Get from security module
+        blacklisted_principals: crate::storage::get_blacklist_count(),
         failed_authentication_attempts: 0, // TODO: Get from auth module
         suspicious_activity_detected: security_events_24h,
         threat_level,
@@ -2632,6 +2967,134 @@ fn calculate_compliance_status() -> ComplianceStatus {
     })
 }
 
+fn log_has_pii(log: &EnhancedAuditLog, clear_caller: bool) -> bool {
+    log.ip_hash.is_some()
+        || log.details.location_hash.is_some()
+        || log.details.user_agent_hash.is_some()
+        || (clear_caller && log.category != AuditCategory::Security && log.caller != Principal::anonymous())
+}
+
+/// Irreversibly clear personal data from a log, keeping what it records about the action.
+/// Security logs keep their caller. Returns whether anything was cleared.
+pub fn scrub_log_pii(log: &mut EnhancedAuditLog, clear_caller: bool) -> bool {
+    if !log_has_pii(log, clear_caller) {
+        return false;
+    }
+
+    log.ip_hash = None;
+    log.details.location_hash = None;
+    log.details.user_agent_hash = None;
+    if clear_caller && log.category != AuditCategory::Security {
+        log.caller = Principal::anonymous();
+    }
+    true
+}
+
+/// Delete logs past the retention period and scrub PII from stored logs past the
+/// anonymization window, oldest first. Returns (anonymized, deleted).
+pub fn enforce_audit_retention(config: &AuditConfiguration, now: u64, limit: usize) -> (u64, u64) {
+    let delete_before = now.saturating_sub(config.data_retention_days * NANOS_PER_DAY);
+    let expired: Vec<u64> = ENHANCED_AUDIT_LOGS.with(|logs| {
+        logs.borrow().iter()
+            .take_while(|(_, log)| log.timestamp < delete_before)
+            .take(limit)
+            .map(|(id, _)| id)
+            .collect()
+    });
+    for log_id in &expired {
+        ENHANCED_AUDIT_LOGS.with(|logs| logs.borrow_mut().remove(log_id));
+        ANONYMIZATION_SEALS.with(|seals| seals.borrow_mut().remove(log_id));
+    }
+
+    if !config.anonymization_enabled {
+        return (0, expired.len() as u64);
+    }
+
+    let anonymize_before = now.saturating_sub(
+        config.anonymization_after_days.unwrap_or(DEFAULT_ANONYMIZATION_AFTER_DAYS) * NANOS_PER_DAY
+    );
+    let clear_caller = config.anonymize_caller_principal.unwrap_or(false);
+    let cursor = ANONYMIZATION_CURSOR.with(|cursor| *cursor.borrow());
+    let candidates: Vec<EnhancedAuditLog> = ENHANCED_AUDIT_LOGS.with(|logs| {
+        logs.borrow().range((cursor + 1)..)
+            .take_while(|(_, log)| log.timestamp < anonymize_before)
+            .take(limit)
+            .map(|(_, log)| log)
+            .collect()
+    });
+
+    let mut anonymized = 0;
+    for mut log in candidates {
+        ANONYMIZATION_CURSOR.with(|cursor| *cursor.borrow_mut() = log.id);
+        if !scrub_log_pii(&mut log, clear_caller) {
+            continue;
+        }
+
+        // Only vouch for entries whose chain hash still verified before scrubbing
+        let original_intact = log.log_hash.is_none()
+            || ENHANCED_AUDIT_LOGS.with(|logs| logs.borrow().get(&log.id))
+                .map_or(false, |stored| stored.log_hash.as_deref() == Some(compute_log_hash(&stored).as_str()));
+        if original_intact {
+            ANONYMIZATION_SEALS.with(|seals| {
+                seals.borrow_mut().insert(log.id, AnonymizationSeal {
+                    log_id: log.id,
+                    original_log_hash: log.log_hash.clone(),
+                    scrubbed_log_hash: compute_log_hash(&log),
+                    anonymized_at: now,
+                });
+            });
+        }
+        ENHANCED_AUDIT_LOGS.with(|logs| logs.borrow_mut().insert(log.id, log));
+        anonymized += 1;
+    }
+
+    (anonymized, expired.len() as u64)
+}
+
+/// How far stored logs lag behind the configured retention policy (admin only)
+#[query]
+pub fn get_retention_compliance_status() -> Result<RetentionComplianceStatus, String> {
+    if !is_admin(&caller()) {
+        return Err("Unauthorized: Only admins can view retention compliance".to_string());
+    }
+
+    Ok(build_retention_compliance_status(&get_audit_config(), time()))
+}
+
+pub fn build_retention_compliance_status(config: &AuditConfiguration, now: u64) -> RetentionComplianceStatus {
+    let anonymization_after_days = config.anonymization_after_days.unwrap_or(DEFAULT_ANONYMIZATION_AFTER_DAYS);
+    let anonymize_before = now.saturating_sub(anonymization_after_days * NANOS_PER_DAY);
+    let delete_before = now.saturating_sub(config.data_retention_days * NANOS_PER_DAY);
+    let clear_caller = config.anonymize_caller_principal.unwrap_or(false);
+
+    let (total_logs, pending_anonymization, pending_deletion, oldest_log_timestamp) = ENHANCED_AUDIT_LOGS.with(|logs| {
+        let logs = logs.borrow();
+        let mut pending_anonymization = 0;
+        let mut pending_deletion = 0;
+        for (_, log) in logs.iter().take_while(|(_, log)| log.timestamp < anonymize_before.max(delete_before)) {
+            if log.timestamp < delete_before {
+                pending_deletion += 1;
+            } else if log.timestamp < anonymize_before && log_has_pii(&log, clear_caller) {
+                pending_anonymization += 1;
+            }
+        }
+        let oldest = logs.first_key_value().map(|(_, log)| log.timestamp);
+        (logs.len(), pending_anonymization, pending_deletion, oldest)
+    });
+
+    RetentionComplianceStatus {
+        anonymization_enabled: config.anonymization_enabled,
+        anonymization_after_days,
+        data_retention_days: config.data_retention_days,
+        total_logs,
+        anonymized_logs: ANONYMIZATION_SEALS.with(|seals| seals.borrow().len()),
+        pending_anonymization,
+        pending_deletion,
+        oldest_log_timestamp,
+        checked_at: now,
+    }
+}
+
 fn anonymize_log_data(log: &mut EnhancedAuditLog) {
     // Anonymize sensitive data while preserving audit value
     log.ip_hash = None;
@@ -2725,6 +3188,11 @@ pub async fn audit_heartbeat() {
         return;
     }
     
+    // An unfinished cleanup continues on every heartbeat until it catches up
+    if config.auto_cleanup_enabled && audit_cleanup_in_progress() {
+        let _ = perform_automated_cleanup().await;
+    }
+    
     // Run maintenance every hour (approximately)
     let current_time = time();
     static mut LAST_MAINTENANCE: u64 = 0;
@@ -2736,6 +3204,15 @@ pub async fn audit_heartbeat() {
         LAST_MAINTENANCE = current_time;
     }
     
+    // Anchor the hash chain head before cleanup may prune older entries
+    anchor_audit_chain();
+    
+    // Enforce the retention policy on stored logs
+    let (anonymized, expired) = enforce_audit_retention(&config, current_time, MAX_RETENTION_ACTIONS_PER_RUN);
+    if anonymized > 0 || expired > 0 {
+        ic_cdk::println!("🔒 Retention enforcement: anonymized {} logs, deleted {} expired logs", anonymized, expired);
+    }
+    
     // Automated cleanup
     if config.auto_cleanup_enabled {
         let _ = perform_automated_cleanup().await;
@@ -2794,25 +3271,10 @@ async fn perform_automated_cleanup() -> Result<(), String> {
     let config = get_audit_config();
     let cutoff_time = time().saturating_sub(config.cleanup_threshold_days * 24 * 60 * 60 * 1_000_000_000);
     
-    let removed_count = ENHANCED_AUDIT_LOGS.with(|logs| {
-        let mut logs_map = logs.borrow_mut();
-        let mut to_remove = Vec::new();
-        
-        for (log_id, log) in logs_map.iter() {
-            if log.timestamp < cutoff_time {
-                to_remove.push(log_id);
-            }
-        }
-        
-        for log_id in &to_remove {
-            logs_map.remove(log_id);
-        }
-        
-        to_remove.len()
-    });
+    let result = remove_expired_audit_logs(cutoff_time, MAX_CLEANUP_REMOVALS_PER_RUN);
     
-    if removed_count > 0 {
-        ic_cdk::println!("🧹 Automated cleanup: Removed {} old audit logs", removed_count);
+    if result.removed_count > 0 {
+        ic_cdk::println!("🧹 Automated cleanup: Removed {} old audit logs (more pending: {})", result.removed_count, result.has_more);
     }
     
     Ok(())
@@ -2884,7 +3346,8 @@ async fn update_compliance_status() {
 
 // ========== EXPORT AND REPORTING FUNCTIONS ==========
 
-/// Export audit logs in various formats for compliance
+/// Export audit logs as CSV in one response, oldest first like the chunked export.
+/// Returns at most `MAX_CSV_CHUNK_SIZE` rows; use `export_audit_logs_csv_chunk` beyond that.
 #[query]
 pub fn export_audit_logs_csv(
     start_time: u64,
@@ -2903,31 +3366,117 @@ pub fn export_audit_logs_csv(
         ..Default::default()
     };
 
-    let logs = get_audit_logs_filtered(filter)?;
-    
-    let mut csv_content = String::new();
-    
-    // CSV header
-    csv_content.push_str("ID,Timestamp,Caller,Category,Action,Level,Success,Description,EntityType,EntityId,RiskScore\n");
-    
+    Ok(audit_csv_single_shot(&filter))
+}
+
+/// Body of `export_audit_logs_csv` after the admin check
+pub fn audit_csv_single_shot(filter: &AuditLogFilter) -> String {
+    build_audit_csv(&audit_csv_page(filter, None, MAX_CSV_CHUNK_SIZE as usize))
+}
+
+/// Up to `rows` logs matching `filter` in log ID order, starting at `cursor`
+fn audit_csv_page(filter: &AuditLogFilter, cursor: Option<u64>, rows: usize) -> Vec<EnhancedAuditLog> {
+    ENHANCED_AUDIT_LOGS.with(|logs| {
+        logs.borrow()
+            .range(cursor.unwrap_or(0)..)
+            .map(|(_, log)| log)
+            .filter(|log| audit_log_matches(log, filter))
+            .take(rows)
+            .collect()
+    })
+}
+
+/// Recommended rows per chunk for `export_audit_logs_csv_chunk`.
+/// Keeps a single query well inside the instruction and response size limits.
+pub const RECOMMENDED_CSV_CHUNK_SIZE: u64 = 500;
+/// Upper bound on rows per chunk
+pub const MAX_CSV_CHUNK_SIZE: u64 = 2_000;
+
+const AUDIT_CSV_HEADER: &str = "ID,Timestamp,Caller,Category,Action,Level,Success,Description,EntityType,EntityId,RiskScore\n";
+
+/// One page of a streamed CSV export
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CsvChunk {
+    pub header_included: bool,
+    pub rows: String,
+    pub has_more: bool,
+    pub next_cursor: Option<u64>, // Log ID to pass as `cursor` for the next chunk
+}
+
+/// Export audit logs as CSV one chunk at a time, oldest first.
+/// Start with `cursor` None and pass back `next_cursor` until `has_more` is false; only the
+/// first chunk carries the header row. Each chunk walks the log map from the cursor key, so
+/// entries appended during the export cannot shift rows between chunks. `limit`, `offset`
+/// and `sort_order` on the filter are ignored.
+#[query]
+pub fn export_audit_logs_csv_chunk(
+    filter: AuditLogFilter,
+    cursor: Option<u64>,
+    chunk_size: u64,
+) -> Result<CsvChunk, String> {
+    let caller = caller();
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can export audit logs".to_string());
+    }
+    audit_csv_chunk(&filter, cursor, chunk_size)
+}
+
+/// Body of `export_audit_logs_csv_chunk` after the admin check
+pub fn audit_csv_chunk(filter: &AuditLogFilter, cursor: Option<u64>, chunk_size: u64) -> Result<CsvChunk, String> {
+    if chunk_size == 0 || chunk_size > MAX_CSV_CHUNK_SIZE {
+        return Err(format!("Chunk size must be between 1 and {}", MAX_CSV_CHUNK_SIZE));
+    }
+
+    // Fetch one extra row to tell whether another chunk follows
+    let page = audit_csv_page(filter, cursor, chunk_size as usize + 1);
+    Ok(build_audit_csv_chunk(&page, cursor.is_none(), chunk_size))
+}
+
+/// Render a full CSV export, header included
+pub fn build_audit_csv(logs: &[EnhancedAuditLog]) -> String {
+    let mut csv_content = String::from(AUDIT_CSV_HEADER);
     for log in logs {
-        csv_content.push_str(&format!(
-            "{},{},{},{:?},{},{:?},{},{},{},{},{}\n",
-            log.id,
-            log.timestamp,
-            log.caller.to_text(),
-            log.category,
-            log.action,
-            log.level,
-            log.result.success,
-            log.details.description.replace(",", ";"), // Escape commas
-            log.details.entity_type.unwrap_or_default(),
-            log.details.entity_id.unwrap_or_default(),
-            log.details.risk_score.unwrap_or(0)
-        ));
+        csv_content.push_str(&audit_log_to_csv_row(log));
+    }
+    csv_content
+}
+
+/// Render one chunk from a page that starts at the cursor and may hold one look-ahead row
+pub fn build_audit_csv_chunk(page: &[EnhancedAuditLog], header_included: bool, chunk_size: u64) -> CsvChunk {
+    let mut rows = if header_included { String::from(AUDIT_CSV_HEADER) } else { String::new() };
+    
+    for log in page.iter().take(chunk_size as usize) {
+        rows.push_str(&audit_log_to_csv_row(log));
     }
     
-    Ok(csv_content)
+    CsvChunk {
+        header_included,
+        rows,
+        has_more: page.len() as u64 > chunk_size,
+        next_cursor: page.get(chunk_size as usize).map(|log| log.id),
+    }
+}
+
+fn audit_log_to_csv_row(log: &EnhancedAuditLog) -> String {
+    format!(
+        "{},{},{},{:?},{},{:?},{},{},{},{},{}\n",
+        log.id,
+        log.timestamp,
+        log.caller.to_text(),
+        log.category,
+        escape_csv_field(&log.action),
+        log.level,
+        log.result.success,
+        escape_csv_field(&log.details.description),
+        escape_csv_field(log.details.entity_type.as_deref().unwrap_or_default()),
+        escape_csv_field(log.details.entity_id.as_deref().unwrap_or_default()),
+        log.details.risk_score.unwrap_or(0)
+    )
+}
+
+/// Keep free-text fields on a single CSV cell: commas become semicolons, line breaks become spaces
+fn escape_csv_field(value: &str) -> String {
+    value.replace(',', ";").replace(['\r', '\n'], " ")
 }
 
 /// Export audit logs in JSON Lines format for big data processing
@@ -2953,31 +3502,26 @@ pub fn export_audit_logs_jsonl(
     let mut jsonl_content = String::new();
     
     for log in logs {
-        let mut export_log = log.clone();
-        
-        if !include_metadata {
-            anonymize_log_data(&mut export_log);
-        }
-        
-        // Convert to JSON manually for basic serialization
-        let json_str = format!(
-            r#"{{"id":{},"timestamp":{},"caller":"{}","category":"{:?}","action":"{}","level":"{:?}","success":{},"description":"{}"}}"#,
-            export_log.id,
-            export_log.timestamp,
-            export_log.caller.to_text(),
-            export_log.category,
-            export_log.action.replace('"', "'"),
-            export_log.level,
-            export_log.result.success,
-            export_log.details.description.replace('"', "'")
-        );
-        jsonl_content.push_str(&json_str);
+        jsonl_content.push_str(&audit_log_to_jsonl_line(log, include_metadata)?);
         jsonl_content.push('\n');
     }
     
     Ok(jsonl_content)
 }
 
+/// Serialize a single audit log as one JSON Lines record
+pub fn audit_log_to_jsonl_line(log: EnhancedAuditLog, include_metadata: bool) -> Result<String, String> {
+    let serialized = if include_metadata {
+        serde_json::to_string(&log)
+    } else {
+        let mut export_log = log;
+        anonymize_log_data(&mut export_log);
+        serde_json::to_string(&AuditLogExportRecord::from(export_log))
+    };
+    
+    serialized.map_err(|e| format!("Failed to serialize audit log: {}", e))
+}
+
 /// Generate comprehensive audit report
 #[query]
 pub fn generate_audit_report(
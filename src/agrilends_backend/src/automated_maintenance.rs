@@ -13,14 +13,14 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 
 use crate::types::*;
-use crate::storage::{get_memory_by_id, get_canister_config, set_canister_config};
+use crate::storage::{get_memory_by_id, get_canister_config, set_canister_config,
+                     next_maintenance_window_id, store_maintenance_window, get_maintenance_window,
+                     get_pending_maintenance_windows};
 use crate::helpers::{is_admin, is_in_maintenance_mode, get_emergency_stop_status, get_memory_usage, 
                      get_active_loans_count, check_oracle_health, check_ckbtc_health, get_overdue_loans,
                      log_audit_action};
 use crate::oracle;
 use crate::liquidity_management;
-use crate::liquidation::trigger_liquidation;
-use crate::loan_lifecycle::get_loan;
 
 // Memory types for heartbeat storage
 type Memory = VirtualMemory<DefaultMemoryImpl>;
@@ -191,6 +191,10 @@ pub async fn canister_heartbeat() {
         *last_time.borrow_mut() = execution_start;
     });
     
+    // Enter/exit scheduled maintenance windows before the maintenance check below,
+    // otherwise a full-scope window could never be exited automatically
+    process_maintenance_windows();
+    
     // Check if heartbeat is enabled and not in emergency mode
     let config = get_heartbeat_config();
     if !config.enabled || is_in_maintenance_mode() || get_emergency_stop_status() {
@@ -260,7 +264,7 @@ pub async fn canister_heartbeat() {
     if config.auto_liquidation_enabled {
         let task_result = execute_with_circuit_breaker(
             "auto_liquidation",
-            auto_liquidation_monitoring_task(config.auto_liquidation_threshold_days)
+            auto_liquidation_monitoring_task()
         ).await;
         tasks_executed.push(task_result.clone());
         if task_result.success { successful_tasks += 1; } else { failed_tasks += 1; }
@@ -280,6 +284,30 @@ pub async fn canister_heartbeat() {
         if task_result.success { successful_tasks += 1; } else { failed_tasks += 1; }
     }
     
+    // 10. Auto-pay Collection (throttled to hourly inside the task)
+    {
+        let task_result = execute_with_circuit_breaker(
+            "autopay_collection",
+            crate::autopay::autopay_collection_task()
+        ).await;
+        tasks_executed.push(task_result.clone());
+        if task_result.success { successful_tasks += 1; } else { failed_tasks += 1; }
+    }
+    
+    // 11. Collateral Receipt Expiry Alerts (throttled to daily inside the task)
+    {
+        let task_result = execute_task("receipt_expiry_check", crate::rwa_nft::receipt_expiry_check_task()).await;
+        tasks_executed.push(task_result.clone());
+        if task_result.success { successful_tasks += 1; } else { failed_tasks += 1; }
+    }
+    
+    // 12. Operator Broadcast Delivery (bounded batch per run)
+    {
+        let task_result = execute_task("broadcast_delivery", crate::notification_broadcast::broadcast_delivery_task()).await;
+        tasks_executed.push(task_result.clone());
+        if task_result.success { successful_tasks += 1; } else { failed_tasks += 1; }
+    }
+    
     // Update metrics
     let execution_time = time() - execution_start;
     update_heartbeat_metrics(execution_time, successful_tasks > 0, tasks_executed.clone());
@@ -378,7 +406,16 @@ async fn loan_monitoring_task() -> Result<String, String> {
         }
     }
     
-    Ok(format!("Monitored {} overdue loans, {} liquidation candidates", monitored_count, liquidation_candidates))
+    // Reprice variable-rate loans that reached their reset date
+    let rate_resets = crate::loan_repayment::process_variable_rate_resets()?;
+    
+    // Close applications that never received an underwriting decision
+    let expired_applications = crate::loan_lifecycle::expire_stale_applications(time());
+    
+    Ok(format!(
+        "Monitored {} overdue loans, {} liquidation candidates, {} variable rate resets, {} expired applications",
+        monitored_count, liquidation_candidates, rate_resets, expired_applications
+    ))
 }
 
 /// Cycles monitoring task
@@ -450,55 +487,28 @@ async fn auto_cleanup_task() -> Result<String, String> {
 
 /// Pool maintenance task
 async fn pool_maintenance_task() -> Result<String, String> {
+    let expired_reservations = liquidity_management::expire_liquidity_reservations(time());
+    let stale_settlements = liquidity_management::expire_stale_position_settlements(time());
+    let (snapshot_taken, snapshots_pruned) = crate::pool_performance::capture_performance_snapshot(time());
+    
     match liquidity_management::perform_pool_maintenance() {
-        Ok(result) => Ok(result),
+        Ok(result) => Ok(format!(
+            "{}; {} expired liquidity reservations released; {} stuck position settlements failed; performance snapshot {}, {} pruned",
+            result, expired_reservations, stale_settlements, if snapshot_taken { "captured" } else { "not due" }, snapshots_pruned
+        )),
         Err(e) => Err(format!("Pool maintenance failed: {}", e))
     }
 }
 
 /// Auto liquidation monitoring task
-async fn auto_liquidation_monitoring_task(threshold_days: u64) -> Result<String, String> {
-    let eligible_loans = get_loans_eligible_for_liquidation();
-    let mut liquidated_count = 0;
-    let mut errors = Vec::new();
-    
-    for loan_id in eligible_loans {
-        if let Ok(loan) = get_loan(loan_id) {
-            if let Some(due_date) = loan.due_date {
-                let days_overdue = (time() - due_date) / (24 * 60 * 60 * 1_000_000_000);
-                
-                // Auto-liquidation after threshold days
-                if days_overdue > threshold_days {
-                    match trigger_liquidation(loan_id).await {
-                        Ok(_) => {
-                            liquidated_count += 1;
-                            log_audit_action(
-                                id(),
-                                "AUTO_LIQUIDATION_TRIGGERED".to_string(),
-                                format!("Automatically triggered liquidation for loan {} after {} days overdue", loan_id, days_overdue),
-                                true,
-                            );
-                        },
-                        Err(e) => {
-                            errors.push(format!("Loan {}: {}", loan_id, e));
-                            log_audit_action(
-                                id(),
-                                "AUTO_LIQUIDATION_FAILED".to_string(),
-                                format!("Failed to auto-liquidate loan {}: {}", loan_id, e),
-                                false,
-                            );
-                        }
-                    }
-                }
-            }
-        }
-    }
+/// Small loans are liquidated directly; loans above `auto_liquidation_max_amount` are queued for operator review
+async fn auto_liquidation_monitoring_task() -> Result<String, String> {
+    let (liquidated, flagged, cured, failed) = crate::liquidation::process_liquidation_bands().await;
     
-    if errors.is_empty() {
-        Ok(format!("Auto-liquidated {} loans", liquidated_count))
-    } else {
-        Err(format!("Liquidation errors: {:?}", errors))
-    }
+    Ok(format!(
+        "Auto-liquidated {} loans, queued {} for review, {} reviews cured, {} failed",
+        liquidated, flagged, cured, failed
+    ))
 }
 
 /// Oracle health monitoring task
@@ -533,7 +543,12 @@ async fn treasury_monitoring_task() -> Result<String, String> {
             );
         }
         
-        Ok(format!("Treasury balance: {} satoshi", stats.balance_ckbtc))
+        let solvency = crate::liquidity_management::check_solvency_floor();
+        
+        Ok(format!(
+            "Treasury balance: {} satoshi, collateralization ratio: {} bps",
+            stats.balance_ckbtc, solvency.collateralization_ratio_bps
+        ))
     } else {
         Err("Failed to get treasury stats".to_string())
     }
@@ -541,13 +556,6 @@ async fn treasury_monitoring_task() -> Result<String, String> {
 
 // ========== HELPER FUNCTIONS ==========
 
-/// Get loans eligible for liquidation
-fn get_loans_eligible_for_liquidation() -> Vec<u64> {
-    // This would integrate with loan lifecycle to get eligible loans
-    // Placeholder implementation
-    Vec::new()
-}
-
 /// Execute task with circuit breaker protection
 async fn execute_with_circuit_breaker(
     task_name: &str,
@@ -749,6 +757,7 @@ pub fn get_heartbeat_config() -> HeartbeatConfig {
 #[update]
 pub fn update_heartbeat_config(new_config: HeartbeatConfig) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "update_heartbeat_config")?;
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can update heartbeat configuration".to_string());
     }
@@ -772,6 +781,7 @@ pub fn update_heartbeat_config(new_config: HeartbeatConfig) -> Result<String, St
 #[update]
 pub fn emergency_pause_heartbeat() -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "emergency_pause_heartbeat")?;
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can pause heartbeat".to_string());
     }
@@ -794,6 +804,7 @@ pub fn emergency_pause_heartbeat() -> Result<String, String> {
 #[update]
 pub fn resume_heartbeat_operations() -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "resume_heartbeat_operations")?;
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can resume heartbeat".to_string());
     }
@@ -812,6 +823,179 @@ pub fn resume_heartbeat_operations() -> Result<String, String> {
     Ok("Heartbeat operations resumed successfully".to_string())
 }
 
+// ========== SCHEDULED MAINTENANCE WINDOWS ==========
+
+const MAX_MAINTENANCE_WINDOW_DURATION: u64 = 7 * 24 * 60 * 60 * 1_000_000_000; // 7 days
+
+/// Schedule a maintenance window (admin only). Times are nanoseconds since epoch.
+/// `affected_operations` names guarded endpoints (see `MAINTENANCE_GUARDED_OPERATIONS`); leave it
+/// empty to pause everything except repayments.
+#[update]
+pub fn schedule_maintenance_window(
+    start: u64,
+    end: u64,
+    affected_operations: Vec<String>,
+    message: String,
+) -> Result<u64, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "schedule_maintenance_window")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can schedule maintenance".to_string());
+    }
+
+    let now = time();
+    if end <= start {
+        return Err("Maintenance window must end after it starts".to_string());
+    }
+    if end <= now {
+        return Err("Maintenance window must end in the future".to_string());
+    }
+    if end - start > MAX_MAINTENANCE_WINDOW_DURATION {
+        return Err("Maintenance window cannot exceed 7 days".to_string());
+    }
+    let message = message.trim().to_string();
+    if message.is_empty() || message.len() > 500 {
+        return Err("Maintenance message must be between 1 and 500 characters".to_string());
+    }
+
+    let mut affected_operations: Vec<String> = affected_operations
+        .into_iter()
+        .map(|op| op.trim().to_string())
+        .filter(|op| !op.is_empty())
+        .collect();
+    affected_operations.sort();
+    affected_operations.dedup();
+    crate::helpers::validate_maintenance_operations(&affected_operations)?;
+
+    let window = MaintenanceWindow {
+        id: next_maintenance_window_id(),
+        start,
+        end,
+        affected_operations,
+        message,
+        status: MaintenanceWindowStatus::Scheduled,
+        scheduled_by: caller,
+        scheduled_at: now,
+    };
+    store_maintenance_window(window.clone());
+
+    log_audit_action(
+        caller,
+        "MAINTENANCE_WINDOW_SCHEDULED".to_string(),
+        format!(
+            "Maintenance window #{} scheduled from {} to {} affecting {}",
+            window.id,
+            window.start,
+            window.end,
+            if window.is_full_scope() { "all operations".to_string() } else { window.affected_operations.join(", ") }
+        ),
+        true,
+    );
+
+    Ok(window.id)
+}
+
+/// Cancel a scheduled or active maintenance window (admin only)
+#[update]
+pub fn cancel_maintenance_window(window_id: u64) -> Result<String, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "cancel_maintenance_window")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can cancel maintenance".to_string());
+    }
+
+    let mut window = get_maintenance_window(window_id).ok_or("Maintenance window not found")?;
+    if !matches!(window.status, MaintenanceWindowStatus::Scheduled | MaintenanceWindowStatus::Active) {
+        return Err("Only scheduled or active maintenance windows can be cancelled".to_string());
+    }
+
+    let was_active = window.status == MaintenanceWindowStatus::Active;
+    window.status = MaintenanceWindowStatus::Cancelled;
+    store_maintenance_window(window.clone());
+
+    if was_active && window.is_full_scope() {
+        sync_maintenance_flag()?;
+    }
+
+    log_audit_action(
+        caller,
+        "MAINTENANCE_WINDOW_CANCELLED".to_string(),
+        format!("Maintenance window #{} cancelled", window_id),
+        true,
+    );
+
+    Ok(format!("Maintenance window #{} cancelled", window_id))
+}
+
+/// Upcoming and in-progress maintenance windows, ordered by start time
+#[query]
+pub fn get_scheduled_maintenance() -> Vec<MaintenanceWindow> {
+    get_pending_maintenance_windows()
+}
+
+/// Next maintenance window that has not yet ended, for health checks
+pub fn get_upcoming_maintenance() -> Option<MaintenanceWindow> {
+    let now = time();
+    get_pending_maintenance_windows()
+        .into_iter()
+        .find(|window| window.end > now)
+}
+
+/// Move windows between Scheduled, Active and Completed; called from the heartbeat
+pub fn process_maintenance_windows() {
+    let now = time();
+    let mut full_scope_changed = false;
+
+    for mut window in get_pending_maintenance_windows() {
+        let next_status = if now >= window.end {
+            MaintenanceWindowStatus::Completed
+        } else if now >= window.start {
+            MaintenanceWindowStatus::Active
+        } else {
+            continue;
+        };
+        if window.status == next_status {
+            continue;
+        }
+
+        window.status = next_status;
+        full_scope_changed |= window.is_full_scope();
+        store_maintenance_window(window.clone());
+
+        log_audit_action(
+            id(),
+            if window.status == MaintenanceWindowStatus::Active {
+                "MAINTENANCE_WINDOW_STARTED".to_string()
+            } else {
+                "MAINTENANCE_WINDOW_ENDED".to_string()
+            },
+            format!("Maintenance window #{}: {}", window.id, window.message),
+            true,
+        );
+    }
+
+    if full_scope_changed {
+        if let Err(e) = sync_maintenance_flag() {
+            log_audit_action(id(), "MAINTENANCE_FLAG_SYNC_FAILED".to_string(), e, false);
+        }
+    }
+}
+
+/// Full-scope windows drive the global maintenance flag. Ending the last one clears the flag,
+/// including any manual toggle made while it was active.
+fn sync_maintenance_flag() -> Result<(), String> {
+    let any_full_scope_active = get_pending_maintenance_windows()
+        .iter()
+        .any(|window| window.status == MaintenanceWindowStatus::Active && window.is_full_scope());
+
+    let mut config = get_canister_config();
+    if config.maintenance_mode != any_full_scope_active {
+        config.maintenance_mode = any_full_scope_active;
+        set_canister_config(config)?;
+    }
+    Ok(())
+}
+
 /// Get heartbeat metrics (admin only)
 #[query]
 pub fn get_heartbeat_metrics() -> Result<HeartbeatMetrics, String> {
@@ -848,16 +1032,22 @@ pub fn production_health_check_with_heartbeat() -> ProductionHealthStatus {
     // Check if heartbeat is recent (within last 2 minutes)
     let heartbeat_healthy = (current_time - last_heartbeat) < (2 * 60 * 1_000_000_000);
     
+    let is_solvent = crate::liquidity_management::get_solvency_report().is_solvent;
+    
     ProductionHealthStatus {
-        is_healthy: !config.emergency_stop && !config.maintenance_mode && heartbeat_healthy,
+        is_healthy: !config.emergency_stop && !config.maintenance_mode && heartbeat_healthy && is_solvent,
         emergency_stop: config.emergency_stop,
         maintenance_mode: config.maintenance_mode,
+        origination_paused: crate::storage::is_origination_paused(),
         oracle_status: check_oracle_health(),
         ckbtc_integration: check_ckbtc_health(),
         memory_usage: get_memory_usage(),
         total_loans: get_active_loans_count(),
         active_loans: get_active_loans_count(),
         last_heartbeat,
+        upcoming_maintenance: get_upcoming_maintenance(),
+        is_solvent,
+        last_reconciliation: crate::treasury_management::get_last_reconciliation(),
     }
 }
 
@@ -887,6 +1077,7 @@ pub fn get_system_maintenance_report() -> Result<SystemMaintenanceReport, String
 #[update]
 pub fn reset_circuit_breakers() -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "reset_circuit_breakers")?;
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can reset circuit breakers".to_string());
     }
@@ -0,0 +1,292 @@
+// ========== AUTO-PAY MODULE ==========
+// Heartbeat-driven collection of due installments for borrowers who granted the canister an
+// ICRC-2 allowance and opted in with `enable_autopay`. Each run handles a bounded batch; a
+// loan whose collection failed is not retried until the next UTC day.
+
+use candid::Principal;
+use ic_cdk::api::time;
+use ic_cdk_macros::{query, update};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::types::*;
+use crate::storage::{
+    get_loan, calculate_remaining_balance, store_autopay_mandate, get_autopay_mandate,
+    get_enabled_autopay_mandates,
+};
+use crate::helpers::{log_audit_action, is_admin};
+use crate::loan_repayment::amortization_schedule_for;
+use crate::notification_system::{create_notification, NotificationEvent, NotificationPriority};
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+const AUTOPAY_RUN_INTERVAL: u64 = 60 * 60 * 1_000_000_000; // Hourly; installments are due by the day
+const MAX_AUTOPAYS_PER_RUN: usize = 20;
+
+// Auto-pay request IDs live in the upper half of the u64 range so they never collide with
+// borrower-chosen IDs for `repay_loan_via_allowance`
+const AUTOPAY_REQUEST_ID_FLAG: u64 = 1 << 63;
+
+thread_local! {
+    static LAST_AUTOPAY_RUN: RefCell<u64> = RefCell::new(0);
+}
+
+/// One request ID per loan installment, so a collection retried after an ambiguous result
+/// is answered from its receipt instead of pulling the borrower's funds twice
+pub fn autopay_request_id(loan_id: u64, installment_no: u32) -> u64 {
+    AUTOPAY_REQUEST_ID_FLAG | (loan_id << 16) | (installment_no as u64 & 0xFFFF)
+}
+
+/// Whether a request ID falls in the range reserved for auto-pay collections
+pub fn is_autopay_request_id(request_id: u64) -> bool {
+    request_id & AUTOPAY_REQUEST_ID_FLAG != 0
+}
+
+/// First unpaid installment, due or not
+pub fn next_unpaid_installment(schedule: &[AmortizationRow]) -> Option<&AmortizationRow> {
+    schedule.iter().find(|row| !row.is_paid)
+}
+
+/// A failed collection is held off until the next UTC day
+pub fn next_autopay_attempt_after(mandate: &AutopayMandate) -> Option<u64> {
+    match (mandate.last_failure.as_ref(), mandate.last_attempt_at) {
+        (Some(_), Some(attempted_at)) => Some((attempted_at / NANOS_PER_DAY + 1) * NANOS_PER_DAY),
+        _ => None,
+    }
+}
+
+/// Whether the heartbeat should try to collect `installment` for this mandate at `now`
+pub fn is_autopay_due(mandate: &AutopayMandate, installment: Option<&AmortizationRow>, now: u64) -> bool {
+    if !mandate.enabled {
+        return false;
+    }
+    let installment = match installment {
+        Some(row) => row,
+        None => return false,
+    };
+    if installment.due_date > now {
+        return false;
+    }
+    next_autopay_attempt_after(mandate).map_or(true, |retry_at| now >= retry_at)
+}
+
+/// Opt a loan into auto-pay (borrower only). The borrower must already have approved an
+/// allowance covering the next installment that lasts until it falls due.
+#[update]
+pub async fn enable_autopay(loan_id: u64) -> Result<AutopayMandate, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "enable_autopay")?;
+
+    let loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    if loan.borrower != caller {
+        return Err("Unauthorized: Only the borrower can enable auto-pay".to_string());
+    }
+    if loan.status != LoanStatus::Active {
+        return Err("Auto-pay can only be enabled for active loans".to_string());
+    }
+
+    let schedule = amortization_schedule_for(&loan);
+    let installment = next_unpaid_installment(&schedule)
+        .ok_or_else(|| "Loan has no outstanding installments".to_string())?;
+    let required = installment.payment_amount.min(calculate_remaining_balance(loan_id)?);
+
+    let ledger = crate::helpers::ledger_principal_for(&loan.asset());
+    let (allowance, expires_at) = crate::ckbtc_integration::get_allowance_for_canister(ledger, caller).await?;
+    if allowance < required {
+        return Err(format!(
+            "Allowance of {} is below the next installment of {}. Approve at least that amount first",
+            allowance, required
+        ));
+    }
+    if let Some(expires_at) = expires_at {
+        if expires_at < installment.due_date {
+            return Err("Allowance expires before the next installment is due".to_string());
+        }
+    }
+
+    let now = time();
+    let mandate = match get_autopay_mandate(loan_id) {
+        Some(mut mandate) => {
+            mandate.enabled = true;
+            mandate.enabled_at = now;
+            mandate.disabled_at = None;
+            mandate.last_failure = None;
+            mandate.consecutive_failures = 0;
+            mandate
+        }
+        None => AutopayMandate {
+            loan_id,
+            borrower: caller,
+            enabled: true,
+            enabled_at: now,
+            disabled_at: None,
+            last_attempt_at: None,
+            last_success_at: None,
+            last_failure: None,
+            consecutive_failures: 0,
+            payments_collected: 0,
+            total_collected: 0,
+        },
+    };
+    store_autopay_mandate(mandate.clone());
+
+    log_audit_action(
+        caller,
+        "AUTOPAY_ENABLED".to_string(),
+        format!("Auto-pay enabled for loan #{} with an allowance of {}", loan_id, allowance),
+        true,
+    );
+    Ok(mandate)
+}
+
+/// Stop auto-pay for a loan (borrower or admin)
+#[update]
+pub fn disable_autopay(loan_id: u64) -> Result<AutopayMandate, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "disable_autopay")?;
+
+    let mut mandate = get_autopay_mandate(loan_id)
+        .ok_or_else(|| "Auto-pay is not set up for this loan".to_string())?;
+    if mandate.borrower != caller && !is_admin(&caller) {
+        return Err("Unauthorized: Only the borrower or an admin can disable auto-pay".to_string());
+    }
+    if !mandate.enabled {
+        return Err("Auto-pay is already disabled for this loan".to_string());
+    }
+
+    mandate.enabled = false;
+    mandate.disabled_at = Some(time());
+    store_autopay_mandate(mandate.clone());
+
+    log_audit_action(
+        caller,
+        "AUTOPAY_DISABLED".to_string(),
+        format!("Auto-pay disabled for loan #{}", loan_id),
+        true,
+    );
+    Ok(mandate)
+}
+
+/// Mandate, next installment and retry hold-off for a loan (borrower or admin)
+#[query]
+pub fn get_autopay_status(loan_id: u64) -> Result<AutopayStatus, String> {
+    let caller = ic_cdk::caller();
+    let loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    if loan.borrower != caller && !is_admin(&caller) {
+        return Err("Unauthorized: Only the borrower or an admin can view auto-pay status".to_string());
+    }
+
+    let mandate = get_autopay_mandate(loan_id);
+    let schedule = amortization_schedule_for(&loan);
+    Ok(AutopayStatus {
+        loan_id,
+        next_attempt_after: mandate.as_ref().filter(|m| m.enabled).and_then(next_autopay_attempt_after),
+        mandate,
+        next_installment: next_unpaid_installment(&schedule).cloned(),
+    })
+}
+
+/// Heartbeat task: collect due installments for up to `MAX_AUTOPAYS_PER_RUN` loans
+pub async fn autopay_collection_task() -> Result<String, String> {
+    let now = time();
+    let last_run = LAST_AUTOPAY_RUN.with(|last| *last.borrow());
+    if now.saturating_sub(last_run) < AUTOPAY_RUN_INTERVAL {
+        return Ok("Auto-pay collection not due yet".to_string());
+    }
+    LAST_AUTOPAY_RUN.with(|last| *last.borrow_mut() = now);
+
+    let mut collected = 0u64;
+    let mut failed = 0u64;
+    let mut attempted = 0usize;
+
+    for mut mandate in get_enabled_autopay_mandates() {
+        if attempted >= MAX_AUTOPAYS_PER_RUN {
+            break;
+        }
+
+        let loan = match get_loan(mandate.loan_id) {
+            Some(loan) if loan.status == LoanStatus::Active => loan,
+            // Repaid, liquidated or missing loans need no further collection
+            _ => {
+                mandate.enabled = false;
+                mandate.disabled_at = Some(now);
+                store_autopay_mandate(mandate);
+                continue;
+            }
+        };
+
+        let schedule = amortization_schedule_for(&loan);
+        let installment = match next_unpaid_installment(&schedule) {
+            Some(row) if is_autopay_due(&mandate, Some(row), now) => row.clone(),
+            _ => continue,
+        };
+        let amount = match calculate_remaining_balance(loan.id) {
+            Ok(remaining) => installment.payment_amount.min(remaining),
+            Err(_) => continue,
+        };
+        if amount == 0 {
+            continue;
+        }
+
+        attempted += 1;
+        let request_id = autopay_request_id(loan.id, installment.installment_no);
+        let result = crate::ckbtc_integration::collect_allowance_repayment(loan.borrower, loan.id, amount, request_id).await;
+
+        // The borrower may have disabled auto-pay while the transfer was in flight, so record
+        // the outcome on the stored mandate rather than the copy read before the call
+        let mut mandate = get_autopay_mandate(loan.id).unwrap_or(mandate);
+        mandate.last_attempt_at = Some(now);
+        match result {
+            Ok(block_index) => {
+                collected += 1;
+                mandate.last_success_at = Some(time());
+                mandate.last_failure = None;
+                mandate.consecutive_failures = 0;
+                mandate.payments_collected += 1;
+                mandate.total_collected = mandate.total_collected.saturating_add(amount);
+                log_audit_action(
+                    ic_cdk::id(),
+                    "AUTOPAY_COLLECTED".to_string(),
+                    format!(
+                        "Auto-pay collected {} for installment {} of loan #{} (block {})",
+                        amount, installment.installment_no, loan.id, block_index
+                    ),
+                    true,
+                );
+            }
+            Err(error) => {
+                failed += 1;
+                let reason = format!("{:?}", error);
+                mandate.last_failure = Some(reason.clone());
+                mandate.consecutive_failures += 1;
+                log_audit_action(
+                    ic_cdk::id(),
+                    "AUTOPAY_FAILED".to_string(),
+                    format!(
+                        "Auto-pay of {} for installment {} of loan #{} failed: {}",
+                        amount, installment.installment_no, loan.id, reason
+                    ),
+                    false,
+                );
+                notify_autopay_failure(loan.borrower, loan.id, amount, &reason);
+            }
+        }
+        store_autopay_mandate(mandate);
+    }
+
+    Ok(format!("Auto-pay run: {} collected, {} failed", collected, failed))
+}
+
+fn notify_autopay_failure(borrower: Principal, loan_id: u64, amount: u64, reason: &str) {
+    let mut data = HashMap::new();
+    data.insert("loan_id".to_string(), loan_id.to_string());
+    data.insert("amount".to_string(), amount.to_string());
+    data.insert(
+        "message".to_string(),
+        format!(
+            "Auto-pay of {} for loan #{} failed: {}. Check your balance and allowance; we will try again tomorrow.",
+            amount, loan_id, reason
+        ),
+    );
+    let event = NotificationEvent::Custom { event_type: "loan_autopay_failed".to_string(), data };
+    let _ = create_notification(borrower, event, None, Some(NotificationPriority::High));
+}
@@ -0,0 +1,217 @@
+// ========== BORROWER REPUTATION MODULE ==========
+// On-chain repayment reputation. Each loan that closes as repaid or defaulted updates the
+// borrower's record, using the loan's performance metrics to weigh partial repayment before
+// a default. The score moves the APR and LTV offered on new loans within governance bounds:
+// a better reputation earns a lower rate and a higher LTV. New borrowers are neutral.
+
+use candid::Principal;
+use ic_cdk::api::time;
+use ic_cdk_macros::query;
+
+use crate::types::*;
+use crate::storage::{get_borrower_reputation, store_borrower_reputation};
+use crate::loan_repayment::calculate_loan_performance_metrics;
+
+pub const NEUTRAL_REPUTATION_SCORE: u64 = 500;
+pub const MAX_REPUTATION_SCORE: u64 = 1000;
+pub const MAX_VOLUME_POINTS: u64 = 150;
+pub const VOLUME_UNIT_SATOSHI: u64 = 1_000_000; // 0.01 BTC
+// A loan earns on-time points only if its principal is at least this many times the asset's
+// minimum amount (0.01 BTC, 1 ICP), so a run of dust loans cannot farm the score
+pub const ON_TIME_MIN_PRINCIPAL_MULTIPLE: u64 = 10;
+// Reputation never lifts a loan's LTV above this, whatever the bonus
+pub const MAX_REPUTATION_LTV: u64 = 90;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoanOutcome {
+    OnTime,
+    Late,
+    Default,
+}
+
+fn weight(key: &str, default: u64) -> u64 {
+    crate::governance::get_protocol_parameter(key.to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(default)
+}
+
+pub fn reputation_weights() -> ReputationWeights {
+    ReputationWeights {
+        on_time_points: weight("reputation_on_time_points", 50),
+        late_penalty: weight("reputation_late_penalty", 25),
+        default_penalty: weight("reputation_default_penalty", 200),
+        volume_points: weight("reputation_volume_points", 5),
+        max_rate_discount: weight("reputation_max_rate_discount", 2),
+        max_rate_premium: weight("reputation_max_rate_premium", 2),
+        max_ltv_bonus: weight("reputation_max_ltv_bonus", 5),
+        max_ltv_reduction: weight("reputation_max_ltv_reduction", 10),
+    }
+}
+
+pub fn neutral_reputation(borrower: Principal, now: u64) -> BorrowerReputation {
+    BorrowerReputation {
+        borrower,
+        score: NEUTRAL_REPUTATION_SCORE,
+        loans_closed: 0,
+        on_time_repayments: 0,
+        late_repayments: 0,
+        defaults: 0,
+        default_severity_pct: 0,
+        repaid_volume: 0,
+        last_loan_id: None,
+        updated_at: now,
+    }
+}
+
+/// Whether `loan` was closed by a refinance paying it off rather than by the borrower
+pub fn closed_by_refinance(loan: &Loan) -> bool {
+    loan.repayment_history.last()
+        .and_then(|payment| payment.transaction_id.as_deref())
+        .map_or(false, |id| id.starts_with(crate::loan_lifecycle::REFINANCE_TRANSACTION_PREFIX))
+}
+
+/// Repaid loans are on time if the last payment came by the due date; other statuses are
+/// not closures. A refinance payoff is not a repayment: the debt lives on in the new loan,
+/// which is scored when it closes.
+pub fn classify_loan_outcome(loan: &Loan) -> Option<LoanOutcome> {
+    match loan.status {
+        LoanStatus::Repaid if closed_by_refinance(loan) => None,
+        LoanStatus::Repaid => {
+            let paid_at = loan.last_payment_date.unwrap_or(loan.created_at);
+            if loan.due_date.map_or(true, |due_date| paid_at <= due_date) {
+                Some(LoanOutcome::OnTime)
+            } else {
+                Some(LoanOutcome::Late)
+            }
+        }
+        LoanStatus::Defaulted => Some(LoanOutcome::Default),
+        _ => None,
+    }
+}
+
+/// Score on the 0-1000 scale under `weights`
+pub fn reputation_score(reputation: &BorrowerReputation, weights: &ReputationWeights) -> u64 {
+    let volume_points = (reputation.repaid_volume / VOLUME_UNIT_SATOSHI)
+        .saturating_mul(weights.volume_points)
+        .min(MAX_VOLUME_POINTS);
+    let gained = reputation.on_time_repayments.saturating_mul(weights.on_time_points).saturating_add(volume_points);
+    let lost = reputation.late_repayments.saturating_mul(weights.late_penalty)
+        .saturating_add(reputation.default_severity_pct.saturating_mul(weights.default_penalty) / 100);
+    NEUTRAL_REPUTATION_SCORE
+        .saturating_add(gained)
+        .saturating_sub(lost)
+        .min(MAX_REPUTATION_SCORE)
+}
+
+/// Whether repaying this loan on time is worth on-time points
+pub fn earns_on_time_credit(loan: &Loan) -> bool {
+    loan.amount_approved >= loan.asset().min_amount().saturating_mul(ON_TIME_MIN_PRINCIPAL_MULTIPLE)
+}
+
+/// Record a closed loan. A default costs the full penalty only if nothing was repaid, and an
+/// on-time repayment of a loan below the minimum principal closes without earning points.
+pub fn apply_loan_outcome(
+    mut reputation: BorrowerReputation,
+    loan: &Loan,
+    outcome: LoanOutcome,
+    metrics: &LoanPerformanceMetrics,
+    weights: &ReputationWeights,
+    now: u64,
+) -> BorrowerReputation {
+    match outcome {
+        LoanOutcome::OnTime => {
+            if earns_on_time_credit(loan) {
+                reputation.on_time_repayments += 1;
+            }
+        }
+        LoanOutcome::Late => reputation.late_repayments += 1,
+        LoanOutcome::Default => {
+            reputation.defaults += 1;
+            reputation.default_severity_pct += 100 - metrics.repayment_rate.min(100);
+        }
+    }
+    if outcome != LoanOutcome::Default && loan.asset() == Asset::CkBtc {
+        reputation.repaid_volume = reputation.repaid_volume.saturating_add(loan.amount_approved);
+    }
+    reputation.loans_closed += 1;
+    reputation.last_loan_id = Some(loan.id);
+    reputation.updated_at = now;
+    reputation.score = reputation_score(&reputation, weights);
+    reputation
+}
+
+/// (APR, LTV) adjustments in percentage points, scaled linearly from the neutral score to
+/// either end of the scale
+pub fn reputation_term_adjustments(score: u64, weights: &ReputationWeights) -> (i64, i64) {
+    let score = score.min(MAX_REPUTATION_SCORE);
+    let span = MAX_REPUTATION_SCORE - NEUTRAL_REPUTATION_SCORE;
+    if score >= NEUTRAL_REPUTATION_SCORE {
+        let above = score - NEUTRAL_REPUTATION_SCORE;
+        (
+            -((weights.max_rate_discount * above / span) as i64),
+            (weights.max_ltv_bonus * above / span) as i64,
+        )
+    } else {
+        let below = NEUTRAL_REPUTATION_SCORE - score;
+        (
+            (weights.max_rate_premium * below / NEUTRAL_REPUTATION_SCORE) as i64,
+            -((weights.max_ltv_reduction * below / NEUTRAL_REPUTATION_SCORE) as i64),
+        )
+    }
+}
+
+pub fn adjusted_apr(standard_apr: u64, adjustment: i64) -> u64 {
+    if adjustment < 0 {
+        standard_apr.saturating_sub(adjustment.unsigned_abs())
+    } else {
+        standard_apr.saturating_add(adjustment as u64)
+    }
+}
+
+/// LTV bonuses stop at `MAX_REPUTATION_LTV`, or the base LTV if that is already higher
+pub fn adjusted_ltv(base_ltv: u64, adjustment: i64) -> u64 {
+    if adjustment < 0 {
+        base_ltv.saturating_sub(adjustment.unsigned_abs())
+    } else {
+        base_ltv.saturating_add(adjustment as u64).min(MAX_REPUTATION_LTV.max(base_ltv))
+    }
+}
+
+/// Stored record rescored under the current weights, or the neutral baseline
+pub fn current_reputation(borrower: Principal) -> BorrowerReputation {
+    let mut reputation = get_borrower_reputation(borrower).unwrap_or_else(|| neutral_reputation(borrower, time()));
+    reputation.score = reputation_score(&reputation, &reputation_weights());
+    reputation
+}
+
+/// (APR, LTV) adjustments for a new loan to `borrower`
+pub fn reputation_terms_for(borrower: Principal) -> (i64, i64) {
+    reputation_term_adjustments(current_reputation(borrower).score, &reputation_weights())
+}
+
+/// Called on every loan status change; only repayment and default closures count
+pub fn record_loan_outcome(loan: &Loan) {
+    let outcome = match classify_loan_outcome(loan) {
+        Some(outcome) => outcome,
+        None => return,
+    };
+    let now = time();
+    let reputation = get_borrower_reputation(loan.borrower).unwrap_or_else(|| neutral_reputation(loan.borrower, now));
+    let metrics = calculate_loan_performance_metrics(loan);
+    store_borrower_reputation(apply_loan_outcome(reputation, loan, outcome, &metrics, &reputation_weights(), now));
+}
+
+/// The caller's reputation and the rate and LTV adjustments it earns on new loans
+#[query]
+pub fn get_my_reputation() -> Result<ReputationView, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::reject_anonymous(&caller)?;
+    let reputation = current_reputation(caller);
+    let (apr_adjustment, ltv_adjustment) = reputation_term_adjustments(reputation.score, &reputation_weights());
+    Ok(ReputationView { reputation, apr_adjustment, ltv_adjustment })
+}
+
+#[query]
+pub fn get_reputation_weights() -> ReputationWeights {
+    reputation_weights()
+}
@@ -6,14 +6,15 @@ use candid::{CandidType, Deserialize, Principal, Nat};
 use crate::types::*;
 use crate::storage::{
     get_loan, update_loan_status, update_loan_repaid_amount, store_disbursement_record,
-    store_repayment_record, get_disbursement_record
+    store_repayment_record, get_disbursement_record, get_allowance_repayment_receipt,
+    store_allowance_repayment_receipt, remove_allowance_repayment_receipt
+};
+use crate::helpers::{
+    log_audit_action, is_admin, is_loan_manager, get_user_btc_address,
+    record_dependency_call, elapsed_ms_since, CKBTC_LEDGER_DEPENDENCY,
 };
-use crate::helpers::{log_audit_action, is_admin, is_loan_manager, get_user_btc_address};
 use crate::storage::release_collateral_nft;
 
-// ckBTC Ledger Principal (Mainnet)
-const CKBTC_LEDGER_PRINCIPAL: &str = "mxzaz-hqaaa-aaaar-qaada-cai";
-
 // ckBTC Integration structures
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct Account {
@@ -43,11 +44,47 @@ pub enum TransferError {
     GenericError { error_code: Nat, message: String },
 }
 
+#[derive(CandidType, Deserialize)]
+pub struct TransferFromArgs {
+    pub spender_subaccount: Option<Vec<u8>>,
+    pub from: Account,
+    pub to: Account,
+    pub amount: Nat,
+    pub fee: Option<Nat>,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+pub enum TransferFromError {
+    BadFee { expected_fee: Nat },
+    BadBurn { min_burn_amount: Nat },
+    InsufficientFunds { balance: Nat },
+    InsufficientAllowance { allowance: Nat },
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    TemporarilyUnavailable,
+    Duplicate { duplicate_of: Nat },
+    GenericError { error_code: Nat, message: String },
+}
+
 #[derive(CandidType, Deserialize)]
 pub struct BalanceArgs {
     pub account: Account,
 }
 
+#[derive(CandidType, Deserialize)]
+pub struct AllowanceArgs {
+    pub account: Account,
+    pub spender: Account,
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+pub struct Allowance {
+    pub allowance: Nat,
+    pub expires_at: Option<u64>,
+}
+
 // Real ckBTC transfer implementation
 #[update]
 pub async fn transfer_ckbtc_to_borrower(
@@ -57,6 +94,7 @@ pub async fn transfer_ckbtc_to_borrower(
 ) -> Result<u64, String> {
     // Verify caller is authorized (loan manager or admin)
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "transfer_ckbtc_to_borrower")?;
     if !is_admin(&caller) && !is_loan_manager(&caller) {
         return Err("Unauthorized: Only loan manager or admin can transfer ckBTC".to_string());
     }
@@ -80,8 +118,7 @@ pub async fn transfer_ckbtc_to_borrower(
     let borrower_btc_address = get_user_btc_address(&borrower)
         .ok_or("Borrower BTC address not found")?;
 
-    let ckbtc_ledger = Principal::from_text(CKBTC_LEDGER_PRINCIPAL)
-        .map_err(|_| "Invalid ckBTC ledger principal")?;
+    let ckbtc_ledger = crate::helpers::ckbtc_ledger_principal();
 
     // Create transfer arguments
     let transfer_args = TransferArgs {
@@ -92,13 +129,15 @@ pub async fn transfer_ckbtc_to_borrower(
         },
         amount: Nat::from(amount),
         fee: None, // Let ledger determine fee
-        memo: Some(format!("Loan disbursement #{}", loan_id).into_bytes()),
+        memo: Some(crate::helpers::ledger_memo(&format!("Loan disbursement #{}", loan_id))),
         created_at_time: Some(time()),
     };
 
     // Execute the transfer
+    let call_started = time();
     let call_result: CallResult<(Result<Nat, TransferError>,)> = 
         call(ckbtc_ledger, "icrc1_transfer", (transfer_args,)).await;
+    record_dependency_call(CKBTC_LEDGER_DEPENDENCY, elapsed_ms_since(call_started), call_result.is_ok());
 
     match call_result {
         Ok((Ok(block_index),)) => {
@@ -113,6 +152,8 @@ pub async fn transfer_ckbtc_to_borrower(
                 ckbtc_block_index: block_index_u64,
                 disbursed_at: time(),
                 disbursed_by: caller,
+                network_fee: None,
+                fee_bearer: None,
             };
 
             store_disbursement_record(disbursement)?;
@@ -165,6 +206,7 @@ pub async fn process_ckbtc_repayment(
     amount: u64,
 ) -> Result<u64, String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "process_ckbtc_repayment")?;
     
     // Verify loan exists
     let loan = get_loan(loan_id).ok_or("Loan not found")?;
@@ -188,8 +230,8 @@ pub async fn process_ckbtc_repayment(
         ));
     }
 
-    let ckbtc_ledger = Principal::from_text(CKBTC_LEDGER_PRINCIPAL)
-        .map_err(|_| "Invalid ckBTC ledger principal")?;
+    // ICP loans are repaid on the ICP ledger
+    let ckbtc_ledger = crate::helpers::ledger_principal_for(&loan.asset());
 
     // Create transfer arguments (from borrower to protocol)
     let transfer_args = TransferArgs {
@@ -200,15 +242,17 @@ pub async fn process_ckbtc_repayment(
         },
         amount: Nat::from(amount),
         fee: None,
-        memo: Some(format!("Loan repayment #{}", loan_id).into_bytes()),
+        memo: Some(crate::helpers::ledger_memo(&format!("Loan repayment #{}", loan_id))),
         created_at_time: Some(time()),
     };
 
     // Note: In real implementation, borrower would need to approve the transfer first
     // This is a simplified version - actual implementation needs approval workflow
 
+    let call_started = time();
     let call_result: CallResult<(Result<Nat, TransferError>,)> = 
         call(ckbtc_ledger, "icrc1_transfer", (transfer_args,)).await;
+    record_dependency_call(CKBTC_LEDGER_DEPENDENCY, elapsed_ms_since(call_started), call_result.is_ok());
 
     match call_result {
         Ok((Ok(block_index),)) => {
@@ -222,6 +266,7 @@ pub async fn process_ckbtc_repayment(
                 amount,
                 ckbtc_block_index: block_index_u64,
                 timestamp: time(),
+                source: Some(RepaymentSource::CkBtc),
             };
 
             store_repayment_record(repayment)?;
@@ -282,16 +327,265 @@ pub async fn process_ckbtc_repayment(
     }
 }
 
+// Repay a loan by pulling pre-approved ckBTC (ICRC-2 approve + transfer_from).
+// `request_id` makes retries safe: a repeated request returns the original block index.
+#[update]
+pub async fn repay_loan_via_allowance(
+    loan_id: u64,
+    amount: u64,
+    request_id: u64,
+) -> Result<u64, AllowanceRepaymentError> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "repay_loan_via_allowance")
+        .map_err(AllowanceRepaymentError::InvalidRequest)?;
+    if crate::autopay::is_autopay_request_id(request_id) {
+        return Err(AllowanceRepaymentError::InvalidRequest(
+            "Request IDs with the top bit set are reserved for auto-pay".to_string(),
+        ));
+    }
+
+    collect_allowance_repayment(caller, loan_id, amount, request_id).await
+}
+
+/// Pull `amount` from `payer`'s allowance and apply it to the loan. Shared by borrower-initiated
+/// repayments and the auto-pay collector, which passes the borrower as payer.
+pub(crate) async fn collect_allowance_repayment(
+    payer: Principal,
+    loan_id: u64,
+    amount: u64,
+    request_id: u64,
+) -> Result<u64, AllowanceRepaymentError> {
+    // One call per request at a time; a concurrent retry waits for the first to finish
+    let _request_guard = crate::helpers::ResourceGuard::acquire(format!("allowance_repayment:{}:{}", payer, request_id))
+        .map_err(|_| AllowanceRepaymentError::RequestInProgress)?;
+
+    // Idempotency: a completed request is answered from its receipt. A receipt without a block
+    // index is a transfer whose outcome was never learned; it is resubmitted with the same
+    // created_at so the ledger either performs it once or reports it as a duplicate.
+    let pending_receipt = match get_allowance_repayment_receipt(&payer, request_id) {
+        Some(receipt) => {
+            if receipt.loan_id != loan_id || receipt.amount != amount {
+                return Err(AllowanceRepaymentError::InvalidRequest(
+                    "Request ID already used for a different repayment".to_string()
+                ));
+            }
+            if let Some(block_index) = receipt.ckbtc_block_index {
+                return Ok(block_index);
+            }
+            Some(receipt)
+        }
+        None => None,
+    };
+
+    let loan = get_loan(loan_id)
+        .ok_or_else(|| AllowanceRepaymentError::InvalidRequest("Loan not found".to_string()))?;
+
+    if loan.borrower != payer {
+        return Err(AllowanceRepaymentError::InvalidRequest("Only the borrower can repay the loan".to_string()));
+    }
+
+    let (created_at, transfer_amount) = match &pending_receipt {
+        Some(receipt) => (receipt.created_at, receipt.transfer_amount.unwrap_or(receipt.amount)),
+        None => {
+            if loan.status != LoanStatus::Active {
+                return Err(AllowanceRepaymentError::InvalidRequest("Loan is not active for repayment".to_string()));
+            }
+            if amount == 0 {
+                return Err(AllowanceRepaymentError::InvalidRequest("Amount must be greater than zero".to_string()));
+            }
+
+            let remaining_balance = calculate_remaining_balance(loan_id)
+                .map_err(AllowanceRepaymentError::InvalidRequest)?;
+            if amount > remaining_balance {
+                return Err(AllowanceRepaymentError::InvalidRequest(format!(
+                    "Payment amount {} exceeds remaining balance {}",
+                    amount, remaining_balance
+                )));
+            }
+
+            // Paying off the loan inside the penalty window also pulls the prepayment penalty
+            let prepayment_penalty = if amount == remaining_balance {
+                crate::loan_repayment::prepayment_penalty_for(&loan, time())
+                    .map_err(AllowanceRepaymentError::InvalidRequest)?
+            } else {
+                0
+            };
+            (time(), amount + prepayment_penalty)
+        }
+    };
+    let prepayment_penalty = transfer_amount - amount;
+
+    // ICP loans are repaid on the ICP ledger
+    let ckbtc_ledger = crate::helpers::ledger_principal_for(&loan.asset());
+
+    // Reserve the request ID before awaiting so a concurrent retry cannot double-debit
+    store_allowance_repayment_receipt(AllowanceRepaymentReceipt {
+        request_id,
+        loan_id,
+        payer,
+        amount,
+        ckbtc_block_index: None,
+        created_at,
+        transfer_amount: Some(transfer_amount),
+    });
+
+    let transfer_args = TransferFromArgs {
+        spender_subaccount: None,
+        from: Account {
+            owner: payer,
+            subaccount: None,
+        },
+        to: Account {
+            owner: canister_self(),
+            subaccount: None,
+        },
+        amount: Nat::from(transfer_amount),
+        fee: None,
+        memo: Some(crate::helpers::ledger_memo(&format!("Loan repayment #{} request #{}", loan_id, request_id))),
+        created_at_time: Some(created_at),
+    };
+
+    let call_started = time();
+    let call_result: CallResult<(Result<Nat, TransferFromError>,)> =
+        call(ckbtc_ledger, "icrc2_transfer_from", (transfer_args,)).await;
+    record_dependency_call(CKBTC_LEDGER_DEPENDENCY, elapsed_ms_since(call_started), call_result.is_ok());
+
+    let block_index_u64: u64 = match call_result {
+        Ok((Ok(block_index),)) => block_index.0.try_into().unwrap_or(0u64),
+        // The resubmission of a transfer that already went through
+        Ok((Err(TransferFromError::Duplicate { duplicate_of }),)) => duplicate_of.0.try_into().unwrap_or(0u64),
+        Ok((Err(transfer_error),)) => {
+            // The ledger refused the transfer, so nothing moved and the request ID is free again
+            remove_allowance_repayment_receipt(&payer, request_id);
+
+            let error = match transfer_error {
+                TransferFromError::InsufficientAllowance { allowance } => AllowanceRepaymentError::InsufficientAllowance {
+                    allowance: allowance.0.try_into().unwrap_or(u64::MAX),
+                    required: transfer_amount,
+                },
+                TransferFromError::InsufficientFunds { balance } => AllowanceRepaymentError::InsufficientFunds {
+                    balance: balance.0.try_into().unwrap_or(u64::MAX),
+                    required: transfer_amount,
+                },
+                other => AllowanceRepaymentError::LedgerError(format!("{:?}", other)),
+            };
+
+            log_audit_action(
+                payer,
+                "CKBTC_ALLOWANCE_REPAYMENT_FAILED".to_string(),
+                format!("Allowance repayment for loan #{} failed: {:?}", loan_id, error),
+                false,
+            );
+
+            return Err(error);
+        }
+        Err((rejection_code, msg)) => {
+            // The transfer may have happened: keep the receipt so a retry resubmits the same transfer
+            let error_msg = format!("{:?} - {}", rejection_code, msg);
+            log_audit_action(
+                payer,
+                "CKBTC_ALLOWANCE_REPAYMENT_CALL_FAILED".to_string(),
+                format!("Failed to call ckBTC ledger for loan #{} (request #{} kept for retry): {}", loan_id, request_id, error_msg),
+                false,
+            );
+
+            return Err(AllowanceRepaymentError::CallFailed(error_msg));
+        }
+    };
+
+    // Funds are in the canister: record the ledger block before any accounting can fail
+    store_allowance_repayment_receipt(AllowanceRepaymentReceipt {
+        request_id,
+        loan_id,
+        payer,
+        amount,
+        ckbtc_block_index: Some(block_index_u64),
+        created_at,
+        transfer_amount: Some(transfer_amount),
+    });
+
+    // Other payments may have landed while the ledger call was in flight
+    let loan = get_loan(loan_id)
+        .ok_or_else(|| AllowanceRepaymentError::InvalidRequest("Loan not found".to_string()))?;
+    let remaining_balance = calculate_remaining_balance(loan_id)
+        .map_err(AllowanceRepaymentError::InvalidRequest)?;
+
+    let payment_breakdown = crate::loan_repayment::calculate_payment_breakdown(&loan, amount)
+        .unwrap_or_default();
+
+    store_repayment_record(RepaymentRecord {
+        loan_id,
+        payer,
+        amount,
+        ckbtc_block_index: block_index_u64,
+        timestamp: time(),
+        payment_breakdown,
+        source: Some(RepaymentSource::CkBtc),
+    }).map_err(AllowanceRepaymentError::InvalidRequest)?;
+
+    update_loan_repaid_amount(loan_id, amount).map_err(AllowanceRepaymentError::InvalidRequest)?;
+    crate::liquidity_management::apply_repayment_to_asset_pool(&loan.asset(), amount)
+        .map_err(AllowanceRepaymentError::InvalidRequest)?;
+    if prepayment_penalty > 0 {
+        crate::loan_repayment::collect_prepayment_penalty(loan_id, prepayment_penalty, payer);
+    }
+
+    let new_remaining = remaining_balance.saturating_sub(amount);
+    if new_remaining == 0 && loan.status == LoanStatus::Active {
+        update_loan_status(loan_id, LoanStatus::Repaid).map_err(AllowanceRepaymentError::InvalidRequest)?;
+        release_collateral_nft(loan.nft_id).map_err(AllowanceRepaymentError::InvalidRequest)?;
+
+        log_audit_action(
+            payer,
+            "LOAN_FULLY_REPAID".to_string(),
+            format!("Loan #{} fully repaid via allowance (block {}), collateral released", loan_id, block_index_u64),
+            true,
+        );
+    } else {
+        log_audit_action(
+            payer,
+            "LOAN_PARTIAL_REPAYMENT".to_string(),
+            format!("Allowance repayment of {} for loan #{} (block {}), remaining: {}",
+                amount, loan_id, block_index_u64, new_remaining),
+            true,
+        );
+    }
+
+    Ok(block_index_u64)
+}
+
+/// ICRC-2 allowance `owner` has granted this canister on `ledger`, as (amount, expires_at)
+pub async fn get_allowance_for_canister(ledger: Principal, owner: Principal) -> Result<(u64, Option<u64>), String> {
+    let args = AllowanceArgs {
+        account: Account { owner, subaccount: None },
+        spender: Account { owner: canister_self(), subaccount: None },
+    };
+
+    let call_started = time();
+    let call_result: CallResult<(Allowance,)> = call(ledger, "icrc2_allowance", (args,)).await;
+    record_dependency_call(CKBTC_LEDGER_DEPENDENCY, elapsed_ms_since(call_started), call_result.is_ok());
+
+    match call_result {
+        Ok((allowance,)) => Ok((
+            allowance.allowance.0.try_into().unwrap_or(u64::MAX),
+            allowance.expires_at,
+        )),
+        Err((rejection_code, msg)) => Err(format!("Failed to check allowance: {:?} - {}", rejection_code, msg)),
+    }
+}
+
 // Check ckBTC balance of an account
 #[update]
 pub async fn check_ckbtc_balance(account: Account) -> Result<u64, String> {
-    let ckbtc_ledger = Principal::from_text(CKBTC_LEDGER_PRINCIPAL)
-        .map_err(|_| "Invalid ckBTC ledger principal")?;
+    crate::validation::guard_caller(&ic_cdk::caller(), "check_ckbtc_balance")?;
+    let ckbtc_ledger = crate::helpers::ckbtc_ledger_principal();
 
     let balance_args = BalanceArgs { account };
 
+    let call_started = time();
     let call_result: Result<(Nat,), _> = 
         call(ckbtc_ledger, "icrc1_balance_of", (balance_args,)).await;
+    record_dependency_call(CKBTC_LEDGER_DEPENDENCY, elapsed_ms_since(call_started), call_result.is_ok());
 
     match call_result {
         Ok((balance,)) => {
@@ -307,6 +601,7 @@ pub async fn check_ckbtc_balance(account: Account) -> Result<u64, String> {
 // Get canister's ckBTC balance
 #[update]
 pub async fn get_protocol_ckbtc_balance() -> Result<u64, String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "get_protocol_ckbtc_balance")?;
     let account = Account {
         owner: canister_self(),
         subaccount: None,
@@ -322,6 +617,7 @@ pub async fn admin_withdraw_protocol_earnings(
     amount: u64,
 ) -> Result<u64, String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "admin_withdraw_protocol_earnings")?;
     if !is_admin(&caller) {
         return Err("Only admins can withdraw protocol earnings".to_string());
     }
@@ -332,8 +628,7 @@ pub async fn admin_withdraw_protocol_earnings(
         return Err("Insufficient protocol balance".to_string());
     }
 
-    let ckbtc_ledger = Principal::from_text(CKBTC_LEDGER_PRINCIPAL)
-        .map_err(|_| "Invalid ckBTC ledger principal")?;
+    let ckbtc_ledger = crate::helpers::ckbtc_ledger_principal();
 
     let transfer_args = TransferArgs {
         from_subaccount: None,
@@ -347,8 +642,10 @@ pub async fn admin_withdraw_protocol_earnings(
         created_at_time: Some(time()),
     };
 
+    let call_started = time();
     let call_result: CallResult<(Result<Nat, TransferError>,)> = 
         call(ckbtc_ledger, "icrc1_transfer", (transfer_args,)).await;
+    record_dependency_call(CKBTC_LEDGER_DEPENDENCY, elapsed_ms_since(call_started), call_result.is_ok());
 
     match call_result {
         Ok((Ok(block_index),)) => {
@@ -6,7 +6,8 @@ use crate::types::*;
 use crate::user_management::{get_user_by_principal, User, Role};
 use crate::storage::{
     get_loans_by_borrower, get_all_loans_data, get_liquidity_pool, 
-    get_investor_balance_by_principal, get_all_investor_balances
+    get_investor_balance_by_principal, get_all_investor_balances, get_state_version_counter,
+    get_loan_by_id
 };
 use crate::liquidity_management::{get_pool_stats, get_investor_balance};
 use crate::helpers::{is_admin, calculate_loan_health_ratio};
@@ -50,6 +51,7 @@ pub struct FarmerDashboardData {
     pub historical_loans: Vec<LoanSummary>,
     pub owned_nfts: Vec<NFTSummary>,
     pub dashboard_stats: FarmerStats,
+    pub alerts: Vec<FarmerAlert>, // Most severe first; empty when every active loan is healthy
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -64,6 +66,34 @@ pub struct FarmerStats {
     pub total_nfts_locked: u64,
 }
 
+// Alert severity, most severe first
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlertSeverity {
+    Critical, // Liquidation is possible now
+    Warning,  // Needs action before it becomes critical
+    Info,
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FarmerAlertKind {
+    LowHealth,          // Health ratio below the safe band or the liquidation threshold
+    PaymentOverdue,     // Past the due date; critical once the grace period has run out
+    PaymentDueSoon,
+    ReceiptExpired,
+    ReceiptExpiring,
+    RefinanceAvailable, // The base rate is below the loan's rate
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct FarmerAlert {
+    pub loan_id: u64,
+    pub kind: FarmerAlertKind,
+    pub severity: AlertSeverity,
+    pub message: String,
+    pub suggested_action: String,
+    pub deadline: Option<u64>, // Due date, grace period end or receipt expiry, when there is one
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct InvestorDashboardData {
     pub user_details: User,
@@ -95,6 +125,15 @@ pub struct InvestmentRecord {
     pub balance_after: u64,
 }
 
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PortfolioView {
+    pub user_details: User,
+    pub roles_present: Vec<Role>, // Which of the sections below are populated
+    pub farmer: Option<FarmerDashboardData>,
+    pub investor: Option<InvestorDashboardData>,
+    pub generated_at: u64,
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct AdminDashboardData {
     pub system_overview: SystemOverview,
@@ -154,8 +193,12 @@ pub struct RiskMetrics {
     pub total_collateral_value: u64,
     pub average_health_ratio: f64,
     pub concentration_risk_score: f64,
+    pub regional_concentration_score: f64,
     pub liquidity_risk_score: f64,
     pub overdue_loans: u64,
+    pub paused_commodities: Vec<String>,
+    pub loans_on_paused_commodities: u64, // Active loans whose collateral commodity is paused; monitored, not liquidated
+    pub loans_with_expired_receipts: u64,  // Approved/active loans whose warehouse receipt has expired
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -195,6 +238,11 @@ pub fn get_farmer_dashboard() -> Result<FarmerDashboardData, String> {
         return Err("Access denied: This endpoint is only for farmers".to_string());
     }
 
+    build_farmer_dashboard(user_details, caller_principal)
+}
+
+/// Farmer loans, NFTs and statistics for a principal, regardless of its registered role
+fn build_farmer_dashboard(user_details: User, caller_principal: Principal) -> Result<FarmerDashboardData, String> {
     // Get all loans for this farmer
     let all_loans = get_loans_by_borrower(caller_principal);
     
@@ -205,26 +253,9 @@ pub fn get_farmer_dashboard() -> Result<FarmerDashboardData, String> {
     let mut total_amount_repaid = 0u64;
     let mut health_ratios = Vec::new();
 
-    for loan in all_loans {
-        let remaining_balance = loan.amount_approved.saturating_sub(loan.total_repaid);
-        let health_ratio = calculate_loan_health_ratio(&loan).unwrap_or(0.0);
-        let is_overdue = is_loan_overdue(&loan);
-
-        let loan_summary = LoanSummary {
-            id: loan.id,
-            borrower: loan.borrower,
-            nft_id: loan.nft_id,
-            amount_requested: loan.amount_requested,
-            amount_approved: loan.amount_approved,
-            status: loan.status.clone(),
-            interest_rate: loan.interest_rate,
-            total_repaid: loan.total_repaid,
-            remaining_balance,
-            health_ratio,
-            created_at: loan.created_at,
-            due_date: loan.due_date,
-            is_overdue,
-        };
+    for loan in &all_loans {
+        let loan_summary = build_loan_summary(loan);
+        let health_ratio = loan_summary.health_ratio;
 
         total_amount_borrowed += loan.amount_approved;
         total_amount_repaid += loan.total_repaid;
@@ -272,12 +303,15 @@ pub fn get_farmer_dashboard() -> Result<FarmerDashboardData, String> {
         total_nfts_locked,
     };
 
+    let alerts = farmer_alerts(&all_loans, time());
+
     Ok(FarmerDashboardData {
         user_details,
         active_loans,
         historical_loans,
         owned_nfts,
         dashboard_stats,
+        alerts,
     })
 }
 
@@ -300,6 +334,11 @@ pub fn get_investor_dashboard() -> Result<InvestorDashboardData, String> {
     let investor_balance = get_investor_balance()
         .map_err(|e| format!("Failed to get investor balance: {}", e))?;
 
+    Ok(build_investor_dashboard(user_details, investor_balance))
+}
+
+/// Investor balance, yield and pool statistics for a balance, regardless of its owner's registered role
+fn build_investor_dashboard(user_details: User, investor_balance: InvestorBalance) -> InvestorDashboardData {
     // Get pool statistics
     let pool_stats = get_pool_stats();
 
@@ -375,7 +414,7 @@ pub fn get_investor_dashboard() -> Result<InvestorDashboardData, String> {
 
     let estimated_annual_return = pool_stats.apy;
 
-    Ok(InvestorDashboardData {
+    InvestorDashboardData {
         user_details,
         current_balance,
         total_invested,
@@ -384,9 +423,50 @@ pub fn get_investor_dashboard() -> Result<InvestorDashboardData, String> {
         pool_stats,
         investment_history,
         dashboard_stats,
+    }
+}
+
+/// Consolidated view for users who borrow, invest, or both. Sections are included based
+/// on what the caller actually holds rather than their registered role.
+#[query]
+pub fn get_my_portfolio() -> Result<PortfolioView, String> {
+    let caller_principal = caller();
+
+    let user_details = get_user_by_principal(&caller_principal)
+        .ok_or("User not found. Please register first.")?;
+
+    let has_farmer_holdings = !get_loans_by_borrower(caller_principal).is_empty()
+        || !get_farmer_nfts(caller_principal)?.is_empty();
+    let farmer = if has_farmer_holdings {
+        Some(build_farmer_dashboard(user_details.clone(), caller_principal)?)
+    } else {
+        None
+    };
+
+    let investor = get_investor_balance_by_principal(caller_principal)
+        .filter(|balance| balance.total_deposited > 0 || balance.balance > 0)
+        .map(|balance| build_investor_dashboard(user_details.clone(), balance));
+
+    Ok(PortfolioView {
+        roles_present: portfolio_roles_present(farmer.is_some(), investor.is_some()),
+        user_details,
+        farmer,
+        investor,
+        generated_at: time(),
     })
 }
 
+fn portfolio_roles_present(has_farmer: bool, has_investor: bool) -> Vec<Role> {
+    let mut roles = Vec::new();
+    if has_farmer {
+        roles.push(Role::Farmer);
+    }
+    if has_investor {
+        roles.push(Role::Investor);
+    }
+    roles
+}
+
 /// Get comprehensive admin dashboard data (admin only)
 /// Provides system-wide metrics and insights
 #[query]
@@ -478,14 +558,22 @@ pub fn get_admin_dashboard() -> Result<AdminDashboardData, String> {
     let total_collateral_value = calculate_total_collateral_value(&all_loans);
     let average_health_ratio = calculate_average_health_ratio(&all_loans);
     let overdue_loans = all_loans.iter().filter(|l| is_loan_overdue(l)).count() as u64;
+    let loans_on_paused_commodities = all_loans.iter()
+        .filter(|loan| loan.status == LoanStatus::Active)
+        .filter(|loan| crate::helpers::get_loan_commodity(loan).map_or(false, |commodity| crate::helpers::is_commodity_paused(&commodity)))
+        .count() as u64;
 
     let risk_metrics = RiskMetrics {
         loans_at_risk,
         total_collateral_value,
         average_health_ratio,
         concentration_risk_score: calculate_concentration_risk_score(&all_loans),
+        regional_concentration_score: calculate_regional_concentration_score(&all_loans),
         liquidity_risk_score: calculate_liquidity_risk_score(&pool_stats),
         overdue_loans,
+        paused_commodities: crate::governance::get_paused_commodities(),
+        loans_on_paused_commodities,
+        loans_with_expired_receipts: crate::rwa_nft::count_loans_with_expired_receipts(time()),
     };
 
     Ok(AdminDashboardData {
@@ -586,6 +674,186 @@ fn extract_nft_metadata(metadata: &Vec<(String, MetadataValue)>) -> (String, u64
     (title, valuation_idr, commodity_type)
 }
 
+/// Dashboard summary of a single loan
+fn build_loan_summary(loan: &Loan) -> LoanSummary {
+    LoanSummary {
+        id: loan.id,
+        borrower: loan.borrower,
+        nft_id: loan.nft_id,
+        amount_requested: loan.amount_requested,
+        amount_approved: loan.amount_approved,
+        status: loan.status.clone(),
+        interest_rate: loan.interest_rate,
+        total_repaid: loan.total_repaid,
+        remaining_balance: loan.amount_approved.saturating_sub(loan.total_repaid),
+        health_ratio: calculate_loan_health_ratio(loan).unwrap_or(0.0),
+        created_at: loan.created_at,
+        due_date: loan.due_date,
+        is_overdue: is_loan_overdue(loan),
+    }
+}
+
+// ========== FARMER EARLY WARNINGS ==========
+
+const DUE_SOON_WINDOW_DAYS: u64 = 7;
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Everything the alert rules look at for one active loan
+#[derive(Clone, Debug)]
+pub struct LoanAlertSignals {
+    pub health_ratio: Option<f64>,
+    pub liquidation_price: Option<u64>,
+    pub liquidation_threshold: f64,
+    pub safe_band: f64,
+    pub grace_period: u64,
+    pub due_soon_window: u64,
+    pub receipt_status: ReceiptExpiryStatus,
+    pub receipt_expiry: Option<u64>,
+    pub refinance_apr: Option<u64>, // Rate a refinance would get, when refinancing is open
+    pub refinance_payoff: u64,
+    pub refinance_ltv_limit: Option<u64>, // None when the collateral cannot be revalued
+}
+
+fn alert(loan_id: u64, kind: FarmerAlertKind, severity: AlertSeverity, message: String, suggested_action: &str, deadline: Option<u64>) -> FarmerAlert {
+    FarmerAlert { loan_id, kind, severity, message, suggested_action: suggested_action.to_string(), deadline }
+}
+
+/// Alerts for one loan at `now`. Loans that are not active, and active loans with nothing
+/// to act on, produce none.
+pub fn build_loan_alerts(loan: &Loan, signals: &LoanAlertSignals, now: u64) -> Vec<FarmerAlert> {
+    let mut alerts = Vec::new();
+    if loan.status != LoanStatus::Active {
+        return alerts;
+    }
+
+    if let Some(health_ratio) = signals.health_ratio {
+        let trigger = signals.liquidation_price
+            .map(|price| format!(" Liquidation is triggered if the collateral price falls to {} IDR.", price))
+            .unwrap_or_default();
+        if health_ratio < signals.liquidation_threshold {
+            alerts.push(alert(
+                loan.id, FarmerAlertKind::LowHealth, AlertSeverity::Critical,
+                format!("Loan #{} health ratio is {:.2}, below the liquidation threshold of {:.2}.", loan.id, health_ratio, signals.liquidation_threshold),
+                "Repay part of the loan now to avoid liquidation",
+                None,
+            ));
+        } else if health_ratio < signals.safe_band {
+            alerts.push(alert(
+                loan.id, FarmerAlertKind::LowHealth, AlertSeverity::Warning,
+                format!("Loan #{} health ratio is {:.2}, below the safe level of {:.2}.{}", loan.id, health_ratio, signals.safe_band, trigger),
+                "Make a partial repayment to restore a safe margin",
+                None,
+            ));
+        }
+    }
+
+    if let Some(due_date) = loan.due_date {
+        let grace_period_end = due_date.saturating_add(signals.grace_period);
+        if now > grace_period_end {
+            alerts.push(alert(
+                loan.id, FarmerAlertKind::PaymentOverdue, AlertSeverity::Critical,
+                format!("Loan #{} is {} days overdue and its grace period has ended; the collateral can be liquidated.", loan.id, (now - due_date) / NANOS_PER_DAY),
+                "Repay the outstanding balance immediately",
+                Some(grace_period_end),
+            ));
+        } else if now > due_date {
+            alerts.push(alert(
+                loan.id, FarmerAlertKind::PaymentOverdue, AlertSeverity::Warning,
+                format!("Loan #{} is overdue. The grace period ends in {} days.", loan.id, (grace_period_end - now) / NANOS_PER_DAY),
+                "Repay before the grace period ends",
+                Some(grace_period_end),
+            ));
+        } else if due_date - now <= signals.due_soon_window {
+            alerts.push(alert(
+                loan.id, FarmerAlertKind::PaymentDueSoon, AlertSeverity::Info,
+                format!("Loan #{} is due in {} days.", loan.id, (due_date - now) / NANOS_PER_DAY),
+                "Plan your repayment before the due date",
+                Some(due_date),
+            ));
+        }
+    }
+
+    match signals.receipt_status {
+        ReceiptExpiryStatus::Expired => alerts.push(alert(
+            loan.id, FarmerAlertKind::ReceiptExpired, AlertSeverity::Warning,
+            format!("The warehouse receipt backing loan #{} (NFT #{}) has expired.", loan.id, loan.nft_id),
+            "Have the receipt re-attested; the collateral cannot be refinanced or borrowed against until then",
+            signals.receipt_expiry,
+        )),
+        ReceiptExpiryStatus::ExpiringSoon => alerts.push(alert(
+            loan.id, FarmerAlertKind::ReceiptExpiring, AlertSeverity::Info,
+            format!("The warehouse receipt backing loan #{} (NFT #{}) expires soon.", loan.id, loan.nft_id),
+            "Have the receipt re-attested before it expires",
+            signals.receipt_expiry,
+        )),
+        ReceiptExpiryStatus::Current | ReceiptExpiryStatus::Untracked => {}
+    }
+
+    // Refinancing needs a current loan, a valid receipt and a payoff inside the LTV limit
+    let within_ltv = signals.refinance_ltv_limit
+        .map_or(false, |ltv_limit| crate::loan_lifecycle::check_refinance_ltv(signals.refinance_payoff, ltv_limit).is_ok());
+    let refinanceable = loan.due_date.map_or(true, |due_date| now <= due_date)
+        && signals.receipt_status != ReceiptExpiryStatus::Expired
+        && within_ltv;
+    if let Some(refinance_apr) = signals.refinance_apr {
+        if refinanceable && refinance_apr < loan.apr {
+            alerts.push(alert(
+                loan.id, FarmerAlertKind::RefinanceAvailable, AlertSeverity::Info,
+                format!("Loan #{} pays {}% APR; refinancing now would fix it at {}%.", loan.id, loan.apr, refinance_apr),
+                "Refinance the loan at the current base rate",
+                None,
+            ));
+        }
+    }
+
+    alerts
+}
+
+/// Most severe first, then by loan and alert kind
+pub fn sort_farmer_alerts(alerts: &mut [FarmerAlert]) {
+    alerts.sort_by_key(|alert| (alert.severity, alert.loan_id, alert.kind));
+}
+
+fn loan_alert_signals(loan: &Loan, now: u64) -> LoanAlertSignals {
+    // Live prices where available, otherwise the origination valuation
+    let projection = crate::liquidation::projection_for_loan(loan, now);
+    let receipt_expiry = crate::storage::get_nft_data(loan.nft_id)
+        .and_then(|nft| crate::helpers::get_receipt_expiry_from_metadata(&nft.metadata));
+    let refinance_apr = if crate::feature_flags::is_feature_enabled(crate::feature_flags::FEATURE_REFINANCING) {
+        Some(crate::storage::get_protocol_parameters().base_apr)
+    } else {
+        None
+    };
+    let refinance_ltv_limit = refinance_apr
+        .and_then(|_| crate::loan_lifecycle::refinance_collateral_value(loan).ok())
+        .map(|(_, ltv_limit)| ltv_limit);
+    LoanAlertSignals {
+        health_ratio: projection.as_ref()
+            .map(|projection| projection.current_health_ratio)
+            .or_else(|| calculate_loan_health_ratio(loan).ok()),
+        liquidation_price: projection.as_ref().and_then(|projection| projection.liquidation_price),
+        liquidation_threshold: crate::liquidation::liquidation_health_threshold(),
+        safe_band: crate::liquidation::at_risk_health_band(),
+        grace_period: crate::liquidation::grace_period_nanos(),
+        due_soon_window: DUE_SOON_WINDOW_DAYS * NANOS_PER_DAY,
+        receipt_status: crate::helpers::receipt_expiry_status(receipt_expiry, now, crate::helpers::receipt_expiry_warning_window()),
+        receipt_expiry,
+        refinance_apr,
+        refinance_payoff: crate::loan_lifecycle::refinance_payoff(loan).unwrap_or(0),
+        refinance_ltv_limit,
+    }
+}
+
+/// Early warnings across a borrower's active loans, most severe first
+fn farmer_alerts(loans: &[Loan], now: u64) -> Vec<FarmerAlert> {
+    let mut alerts: Vec<FarmerAlert> = loans.iter()
+        .filter(|loan| loan.status == LoanStatus::Active)
+        .flat_map(|loan| build_loan_alerts(loan, &loan_alert_signals(loan, now), now))
+        .collect();
+    sort_farmer_alerts(&mut alerts);
+    alerts
+}
+
 /// Check if a loan is overdue
 fn is_loan_overdue(loan: &Loan) -> bool {
     if let Some(due_date) = loan.due_date {
@@ -696,7 +964,23 @@ fn calculate_concentration_risk_score(loans: &[Loan]) -> f64 {
         .unwrap_or(0);
     
     // Risk score based on largest loan percentage of total exposure
-    (max_loan as f64 / total_exposure as f64) * 100.0
+    let loan_concentration = (max_loan as f64 / total_exposure as f64) * 100.0;
+    
+    // Commodity concentration: largest single-commodity share of outstanding collateral
+    let commodity_concentration = crate::loan_lifecycle::get_commodity_exposure()
+        .iter()
+        .map(|(_, _, percentage)| *percentage)
+        .fold(0.0, f64::max);
+    
+    loan_concentration.max(commodity_concentration)
+}
+
+/// Largest single-region share of outstanding principal (percentage)
+fn calculate_regional_concentration_score(loans: &[Loan]) -> f64 {
+    crate::helpers::build_regional_exposure(loans)
+        .iter()
+        .map(|exposure| exposure.share_bps as f64 / 100.0)
+        .fold(0.0, f64::max)
 }
 
 /// Calculate liquidity risk score
@@ -732,6 +1016,7 @@ fn get_all_investor_balances() -> Vec<InvestorBalance> {
 #[update]
 pub fn refresh_dashboard_cache() -> Result<String, String> {
     let caller_principal = caller();
+    crate::validation::guard_caller(&caller_principal, "refresh_dashboard_cache")?;
     
     if !is_admin(&caller_principal) {
         return Err("Access denied: Admin privileges required".to_string());
@@ -743,6 +1028,55 @@ pub fn refresh_dashboard_cache() -> Result<String, String> {
     Ok("Dashboard cache refreshed successfully".to_string())
 }
 
+/// Get per-domain state version counters
+/// Cheap polling endpoint: frontends refetch a dashboard only when a relevant counter changes.
+/// Counters live in stable memory and never decrease, including across upgrades.
+#[query]
+pub fn get_state_version() -> StateVersion {
+    StateVersion {
+        pool: get_state_version_counter(StateDomain::Pool),
+        loans: get_state_version_counter(StateDomain::Loans),
+        notifications: get_state_version_counter(StateDomain::Notifications),
+        oracle: get_state_version_counter(StateDomain::Oracle),
+    }
+}
+
+/// Maximum number of IDs accepted by the batch loan queries
+pub const MAX_BATCH_LOAN_IDS: usize = 100;
+
+/// Look up loans in input order; entries the viewer may not see (not the borrower, not admin) are None
+pub fn select_viewable_loans(
+    loan_ids: &[u64],
+    lookup: impl Fn(u64) -> Option<Loan>,
+    viewer: Principal,
+    viewer_is_admin: bool,
+) -> Vec<Option<Loan>> {
+    loan_ids.iter()
+        .map(|id| lookup(*id).filter(|loan| viewer_is_admin || loan.borrower == viewer))
+        .collect()
+}
+
+/// Fetch several loans in one call, preserving input order (max 100 IDs).
+/// Missing loans and loans the caller is not authorized to view come back as None.
+#[query]
+pub fn get_loans_by_ids(loan_ids: Vec<u64>) -> Result<Vec<Option<Loan>>, String> {
+    if loan_ids.len() > MAX_BATCH_LOAN_IDS {
+        return Err(format!("Too many loan IDs: maximum is {}", MAX_BATCH_LOAN_IDS));
+    }
+
+    let caller_principal = caller();
+    Ok(select_viewable_loans(&loan_ids, get_loan_by_id, caller_principal, is_admin(&caller_principal)))
+}
+
+/// Batch variant of the dashboard loan summaries, with health ratio and overdue flag
+#[query]
+pub fn get_loan_summaries_by_ids(loan_ids: Vec<u64>) -> Result<Vec<Option<LoanSummary>>, String> {
+    Ok(get_loans_by_ids(loan_ids)?
+        .iter()
+        .map(|loan| loan.as_ref().map(build_loan_summary))
+        .collect())
+}
+
 /// Get dashboard loading status
 /// Useful for frontend to show appropriate loading states
 #[query]
@@ -1182,7 +1516,7 @@ pub async fn get_farmer_dashboard() -> Result<FarmerDashboard, String> {
         let loan_info = create_loan_dashboard_info(&loan).await;
         match loan.status {
             LoanStatus::Active => active_loans.push(loan_info),
-            LoanStatus::Repaid | LoanStatus::Defaulted => historical_loans.push(loan_info),
+            LoanStatus::Repaid | LoanStatus::Defaulted | LoanStatus::Expired | LoanStatus::Withdrawn => historical_loans.push(loan_info),
             _ => active_loans.push(loan_info), // Treat pending/approved as active
         }
     }
@@ -1246,11 +1580,13 @@ pub async fn get_investor_dashboard() -> Result<InvestorDashboard, String> {
         PoolStats {
             total_liquidity: 0,
             available_liquidity: 0,
+            reserved_liquidity: 0,
             total_borrowed: 0,
             total_repaid: 0,
             utilization_rate: 0,
             total_investors: 0,
             apy: 0,
+            apy_bps: Some(0),
             created_at: time(),
             updated_at: time(),
         }
@@ -1583,19 +1919,14 @@ async fn calculate_investor_analytics(investor: &Principal) -> InvestorAnalytics
         0
     };
     
-    let current_apy = if let Ok(pool) = get_liquidity_pool() {
-        pool.calculate_apy()
-    } else {
-        800 // Default 8% APY
-    };
+    let pool_stats = get_pool_stats();
+    let current_apy = pool_stats.apy_bps.unwrap_or(pool_stats.apy * 100);
     
-    // Generate historical APY data points (simplified)
-    for i in 0..12 {
-        let timestamp = time() - (i * 30 * 24 * 60 * 60 * 1_000_000_000); // Monthly points
-        historical_apy.push(APYDataPoint {
-            timestamp,
-            apy: current_apy + (i * 10), // Simplified variation
-        });
+    // Monthly points over the last year, from the APY each daily performance snapshot recorded
+    let now = time();
+    let snapshots = crate::storage::get_performance_snapshots_in_range(now.saturating_sub(365 * 24 * 60 * 60 * 1_000_000_000), now);
+    for (timestamp, apy) in crate::pool_performance::monthly_apy_history(&snapshots, now, 12) {
+        historical_apy.push(APYDataPoint { timestamp, apy });
     }
     
     let investment_tenure_days = if let Some(first_transaction) = processed_transactions.first() {
@@ -2098,6 +2429,9 @@ fn format_loan_status(status: &LoanStatus) -> &'static str {
         LoanStatus::Active => "Active",
         LoanStatus::Repaid => "Repaid",
         LoanStatus::Defaulted => "Defaulted",
+        LoanStatus::Expired => "Expired",
+        LoanStatus::Withdrawn => "Withdrawn",
+        LoanStatus::Liquidating => "Liquidating",
     }
 }
 
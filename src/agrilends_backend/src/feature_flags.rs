@@ -0,0 +1,114 @@
+// ========== FEATURE FLAGS MODULE ==========
+// Named runtime switches for staged rollout and kill-switches of individual features.
+// Gated entry points call `ensure_feature_enabled`; core lending (applications, ckBTC
+// deposits, withdrawals and repayments) is deliberately not behind a flag.
+
+use ic_cdk::api::time;
+use ic_cdk_macros::{query, update};
+
+use crate::types::FeatureFlag;
+use crate::storage::{store_feature_flag, get_feature_flag, get_all_stored_feature_flags};
+use crate::helpers::log_audit_action;
+
+pub const FEATURE_LIQUIDATION_AUCTIONS: &str = "liquidation_auctions";
+pub const FEATURE_POSITION_MARKETPLACE: &str = "position_marketplace";
+pub const FEATURE_PROMO_CAMPAIGNS: &str = "promo_campaigns";
+pub const FEATURE_ICP_POOL: &str = "icp_pool";
+pub const FEATURE_REFINANCING: &str = "refinancing";
+pub const FEATURE_ADMIN_NONCES: &str = "admin_nonces";
+pub const FEATURE_SHARDED_LOAN_READS: &str = "sharded_loan_reads";
+
+/// Built-in flags and their state until a super admin changes them
+pub const DEFAULT_FEATURE_FLAGS: [(&str, bool); 7] = [
+    (FEATURE_LIQUIDATION_AUCTIONS, true),
+    (FEATURE_POSITION_MARKETPLACE, true),
+    (FEATURE_PROMO_CAMPAIGNS, true),
+    (FEATURE_ICP_POOL, true),
+    (FEATURE_REFINANCING, true),
+    (FEATURE_ADMIN_NONCES, false), // Off so existing admin tooling keeps working
+    (FEATURE_SHARDED_LOAN_READS, false), // Single-canister deployments read local storage only
+];
+
+const MAX_FLAG_NAME_LENGTH: usize = 64;
+
+/// Stored value if the flag was ever set, else the built-in default; unknown names are off
+pub fn resolve_feature_flag(stored: Option<&FeatureFlag>, name: &str) -> bool {
+    match stored {
+        Some(flag) => flag.enabled,
+        None => DEFAULT_FEATURE_FLAGS.iter()
+            .find(|(default_name, _)| *default_name == name)
+            .map_or(false, |(_, enabled)| *enabled),
+    }
+}
+
+pub fn is_feature_enabled(name: &str) -> bool {
+    resolve_feature_flag(get_feature_flag(name).as_ref(), name)
+}
+
+/// Entry guard for gated functions
+pub fn ensure_feature_enabled(name: &str) -> Result<(), String> {
+    if is_feature_enabled(name) {
+        Ok(())
+    } else {
+        Err(format!("Feature '{}' is currently disabled", name))
+    }
+}
+
+/// Flag names are lowercase snake_case so typos cannot pass for a different flag
+pub fn validate_feature_flag_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_FLAG_NAME_LENGTH {
+        return Err(format!("Flag name must be 1 to {} characters", MAX_FLAG_NAME_LENGTH));
+    }
+    if !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+        return Err("Flag name may only contain lowercase letters, digits and underscores".to_string());
+    }
+    Ok(())
+}
+
+/// Turn a feature on or off (super admin only). Names outside the default set may be
+/// staged ahead of the code that checks them.
+#[update]
+pub fn set_feature_flag(name: String, enabled: bool) -> Result<FeatureFlag, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "set_feature_flag")?;
+    if !crate::governance::is_super_admin(&caller) {
+        return Err("Unauthorized: Only super admins can change feature flags".to_string());
+    }
+    validate_feature_flag_name(&name)?;
+
+    let previous = is_feature_enabled(&name);
+    let flag = FeatureFlag {
+        name: name.clone(),
+        enabled,
+        updated_at: time(),
+        updated_by: Some(caller),
+    };
+    store_feature_flag(flag.clone());
+
+    log_audit_action(
+        caller,
+        "FEATURE_FLAG_CHANGED".to_string(),
+        format!("Feature '{}' changed from {} to {}", name, previous, enabled),
+        true,
+    );
+
+    Ok(flag)
+}
+
+/// Every built-in flag plus any other flag that has been set, sorted by name
+#[query]
+pub fn get_feature_flags() -> Vec<FeatureFlag> {
+    let mut flags = get_all_stored_feature_flags();
+    for (name, enabled) in DEFAULT_FEATURE_FLAGS.iter() {
+        if !flags.iter().any(|flag| flag.name == *name) {
+            flags.push(FeatureFlag {
+                name: name.to_string(),
+                enabled: *enabled,
+                updated_at: 0,
+                updated_by: None,
+            });
+        }
+    }
+    flags.sort_by(|a, b| a.name.cmp(&b.name));
+    flags
+}
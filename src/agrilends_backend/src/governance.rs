@@ -13,8 +13,12 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 
 use crate::types::*;
-use crate::storage::{get_memory_by_id, log_audit_action, get_canister_config, update_config};
-use crate::helpers::is_admin;
+use crate::storage::{
+    get_memory_by_id, log_audit_action, get_canister_config, update_config,
+    get_pending_loan_manager as get_stored_pending_loan_manager, set_pending_loan_manager,
+    clear_pending_loan_manager
+};
+use crate::helpers::{is_admin, get_commodity_ltv_ratio};
 
 // Memory types
 type Memory = VirtualMemory<DefaultMemoryImpl>;
@@ -23,6 +27,7 @@ type VoteStorage = StableBTreeMap<(u64, Principal), Vote, Memory>; // (proposal_
 type ParameterStorage = StableBTreeMap<String, ProtocolParameter, Memory>;
 type AdminRoleStorage = StableBTreeMap<Principal, AdminRole, Memory>;
 type GovernanceConfigStorage = StableBTreeMap<u8, GovernanceConfig, Memory>;
+type ParameterSnapshotStorage = StableBTreeMap<u64, ParameterSnapshot, Memory>;
 
 // Thread-local storage for governance data
 thread_local! {
@@ -46,6 +51,10 @@ thread_local! {
         StableBTreeMap::init(get_memory_by_id(MemoryId::new(54)))
     );
     
+    static PARAMETER_SNAPSHOTS: RefCell<ParameterSnapshotStorage> = RefCell::new(
+        StableBTreeMap::init(get_memory_by_id(MemoryId::new(55)))
+    );
+    
     static PROPOSAL_COUNTER: RefCell<u64> = RefCell::new(0);
 }
 
@@ -88,10 +97,69 @@ fn initialize_default_parameters() {
         ("protocol_fee_rate", 500, ParameterType::Percentage, Some(100), Some(1000), "Protocol fee as percentage of interest"),
         ("grace_period_days", 30, ParameterType::Duration, Some(7), Some(90), "Grace period before liquidation in days"),
         ("min_collateral_value", 100_000_000, ParameterType::Amount, Some(10_000_000), Some(1_000_000_000), "Minimum collateral value in satoshi"),
+        ("min_collateral_value_idr", crate::helpers::DEFAULT_MIN_COLLATERAL_VALUE_IDR, ParameterType::Amount, Some(0), Some(10_000_000_000), "Minimum NFT valuation in IDR accepted when minting collateral or originating a loan; existing loans are grandfathered"),
         ("max_loan_duration_days", 365, ParameterType::Duration, Some(30), Some(1095), "Maximum loan duration in days"),
         ("emergency_stop", 0, ParameterType::Boolean, Some(0), Some(1), "Emergency stop flag"),
         ("maintenance_mode", 0, ParameterType::Boolean, Some(0), Some(1), "Maintenance mode flag"),
         ("max_utilization_rate", 8000, ParameterType::Percentage, Some(5000), Some(9500), "Maximum pool utilization rate"),
+        ("cycle_target_runway_days", 30, ParameterType::Duration, Some(7), Some(365), "Target cycle runway used for top-up recommendations"),
+        ("cycle_runway_alert_days", 7, ParameterType::Duration, Some(1), Some(90), "Raise a critical alert when a canister's cycle runway falls below this"),
+        ("max_commodity_exposure_bps", 10_000, ParameterType::Percentage, Some(1000), Some(10_000), "Maximum share of outstanding collateral backed by a single commodity"),
+        ("pricing_mode", 0, ParameterType::Boolean, Some(0), Some(1), "Price feed for origination and liquidation: 0 = spot, 1 = TWAP"),
+        ("twap_window_seconds", 21_600, ParameterType::Duration, Some(300), Some(604_800), "Averaging window for TWAP pricing in seconds"),
+        ("oracle_max_price_deviation_bps", 2_000, ParameterType::Percentage, Some(100), Some(10_000), "Largest gap, in basis points of the lower price, between the last accepted commodity price and a single write inside the deviation window"),
+        ("oracle_deviation_window_seconds", 3_600, ParameterType::Duration, Some(60), Some(86_400), "Seconds after the last accepted price during which the deviation band applies"),
+        ("promo_campaign_cap", 10_000_000, ParameterType::Amount, Some(0), Some(1_000_000_000), "Maximum satoshi credited to investors per promotional campaign"),
+        ("emergency_withdraw_threshold", 2, ParameterType::Amount, Some(2), Some(10), "Distinct SuperAdmin approvals required to execute an emergency withdrawal"),
+        ("emergency_withdraw_window_hours", 24, ParameterType::Duration, Some(1), Some(168), "Hours an emergency withdrawal request stays open for approvals"),
+        ("insurance_fund_fee_bps", 2000, ParameterType::Percentage, Some(0), Some(10_000), "Share of each protocol fee routed to the insurance fund"),
+        ("insurance_fund_target", 1_000_000_000, ParameterType::Amount, Some(0), Some(100_000_000_000), "Insurance fund size in satoshi above which fees go entirely to treasury"),
+        ("ckbtc_retry_max_attempts", 3, ParameterType::Amount, Some(1), Some(10), "Maximum attempts for ckBTC ledger and minter calls on transient failures"),
+        ("ckbtc_retry_base_delay_rounds", 1, ParameterType::Amount, Some(0), Some(8), "Base backoff in execution rounds between ckBTC call retries, doubled per attempt"),
+        ("kyc_verified_deposit_threshold", 1_000_000_000, ParameterType::Amount, Some(0), Some(100_000_000_000), "Cumulative investor deposits in satoshi above which Verified KYC is required"),
+        ("auto_liquidation_max_amount", 100_000_000, ParameterType::Amount, Some(0), Some(100_000_000_000), "Outstanding debt in satoshi below which eligible loans are liquidated automatically; larger loans need operator review"),
+        ("solvency_ratio_floor_bps", 10_000, ParameterType::Percentage, Some(5_000), Some(20_000), "Assets-to-liabilities ratio below which a critical solvency alert is raised"),
+        ("solvency_stress_haircut_bps", 3_000, ParameterType::Percentage, Some(0), Some(9_000), "Collateral haircut applied for the stressed solvency figure"),
+        ("repayment_allocation_order", 0, ParameterType::Amount, Some(0), Some(5), "Repayment allocation order: 0 fees/interest/principal, 1 fees/principal/interest, 2 interest/fees/principal, 3 interest/principal/fees, 4 principal/fees/interest, 5 principal/interest/fees"),
+        ("prepayment_penalty_bps", 0, ParameterType::Percentage, Some(0), Some(500), "Penalty on outstanding principal for full repayment inside the prepayment window"),
+        ("prepayment_free_window_days", 0, ParameterType::Duration, Some(0), Some(365), "Days after origination during which full prepayment is penalized"),
+        ("compounding_frequency", 0, ParameterType::Amount, Some(0), Some(2), "Interest accrual model: 0 simple, 1 daily compounding, 2 monthly compounding"),
+        ("variable_rate_slope", 10, ParameterType::Percentage, Some(0), Some(50), "APR points added to the base rate at full pool utilization for variable-rate loans"),
+        ("variable_rate_max_step", 2, ParameterType::Percentage, Some(0), Some(10), "Maximum APR change per variable-rate reset in percentage points"),
+        ("deposit_fee_bps", 0, ParameterType::Percentage, Some(0), Some(MAX_POOL_FEE_BPS), "Liquidity deposit fee routed to treasury"),
+        ("application_validity_days", 30, ParameterType::Duration, Some(1), Some(180), "Days a submitted loan application stays open for an underwriting decision before it expires"),
+        ("loan_approval_reservation_hours", 72, ParameterType::Duration, Some(1), Some(720), "Hours an approved loan keeps its reserved pool liquidity before the reservation expires"),
+        ("withdrawal_fee_bps", 0, ParameterType::Percentage, Some(0), Some(MAX_POOL_FEE_BPS), "Liquidity withdrawal fee routed to treasury"),
+        ("reconciliation_tolerance_satoshi", 10_000, ParameterType::Amount, Some(0), Some(100_000_000), "Difference between the canister's ckBTC ledger balance and internal accounting tolerated before a critical alert"),
+        ("min_holding_period_seconds", 0, ParameterType::Duration, Some(0), Some(2_592_000), "Seconds a deposit must stay in the pool before it earns yield; funds withdrawn sooner keep their principal and earn nothing"),
+        ("reentrancy_guard_enabled", 1, ParameterType::Boolean, Some(0), Some(1), "Reject a pool update while another call holding the same principal, loan or transaction is awaiting a ledger"),
+        ("liquidation_penalty_bps", 500, ParameterType::Percentage, Some(0), Some(2_000), "Penalty on outstanding debt taken from liquidation proceeds once the debt is repaid, routed to treasury"),
+        ("liquidation_lock_timeout_minutes", 60, ParameterType::Duration, Some(5), Some(1_440), "Minutes a loan stays locked in Liquidating before a stuck liquidation attempt may be retried"),
+        ("receipt_expiry_warning_days", 14, ParameterType::Duration, Some(1), Some(180), "Days before a collateral warehouse receipt expires that the borrower is warned to re-attest it"),
+        ("strict_contact_validation", 0, ParameterType::Boolean, Some(0), Some(1), "Reject profile emails outside a recognized TLD list and phone numbers without a country code"),
+        ("at_risk_health_band", 12000, ParameterType::Percentage, Some(9000), Some(30000), "Health ratio below which a current loan appears in the at-risk report for a margin call"),
+        ("reputation_on_time_points", 50, ParameterType::Amount, Some(0), Some(200), "Reputation points a borrower gains for each loan repaid by its due date"),
+        ("reputation_late_penalty", 25, ParameterType::Amount, Some(0), Some(200), "Reputation points a borrower loses for each loan repaid after its due date"),
+        ("reputation_default_penalty", 200, ParameterType::Amount, Some(0), Some(1000), "Reputation points lost for a default with nothing repaid; partial repayment reduces it pro rata"),
+        ("reputation_volume_points", 5, ParameterType::Amount, Some(0), Some(50), "Reputation points per 0.01 BTC of fully repaid ckBTC principal, capped at 150"),
+        ("reputation_max_rate_discount", 2, ParameterType::Amount, Some(0), Some(10), "APR percentage points taken off new loans for a perfect reputation"),
+        ("reputation_max_rate_premium", 2, ParameterType::Amount, Some(0), Some(10), "APR percentage points added to new loans for the lowest reputation"),
+        ("reputation_max_ltv_bonus", 5, ParameterType::Amount, Some(0), Some(15), "LTV percentage points added to new loans for a perfect reputation"),
+        ("reputation_max_ltv_reduction", 10, ParameterType::Amount, Some(0), Some(30), "LTV percentage points taken off new loans for the lowest reputation"),
+        ("max_active_loans_per_borrower", 5, ParameterType::Amount, Some(1), Some(50), "Loans a borrower may hold at once, counting applications and loans not yet closed"),
+        ("btc_network_fee_estimate", 10_000, ParameterType::Amount, Some(0), Some(1_000_000), "Bitcoin network fee in satoshi assumed for a disbursement when the ckBTC minter cannot quote one"),
+        ("max_single_deposit", u64::MAX, ParameterType::Amount, Some(0), Some(u64::MAX), "Largest single liquidity deposit in satoshi for an investor below Verified KYC; ICP counts at its ckBTC value"),
+        ("max_daily_deposit", u64::MAX, ParameterType::Amount, Some(0), Some(u64::MAX), "Liquidity an investor below Verified KYC may deposit in any rolling 24 hours, in satoshi"),
+        ("max_single_withdrawal", u64::MAX, ParameterType::Amount, Some(0), Some(u64::MAX), "Largest single liquidity withdrawal in satoshi for an investor below Verified KYC"),
+        ("max_daily_withdrawal", u64::MAX, ParameterType::Amount, Some(0), Some(u64::MAX), "Liquidity an investor below Verified KYC may withdraw in any rolling 24 hours, in satoshi"),
+        ("verified_transaction_limit_scale_bps", 10_000, ParameterType::Percentage, Some(10_000), Some(100_000), "Multiplier applied to the deposit and withdrawal limits for Verified KYC investors"),
+        ("base_apy_bps", 300, ParameterType::Percentage, Some(0), Some(10_000), "Liquidity pool APY before bonuses, also the floor of the quoted APY; cannot exceed max_apy_bps"),
+        ("max_apy_bps", 1_500, ParameterType::Percentage, Some(0), Some(10_000), "Cap on the quoted liquidity pool APY; cannot be below base_apy_bps"),
+        ("utilization_bonus_per_pct", 5, ParameterType::Percentage, Some(0), Some(100), "Pool APY basis points added per percentage point of pool utilization"),
+        ("repayment_bonus_high_threshold", 90, ParameterType::Percentage, Some(0), Some(100), "Pool repayment rate (%) above which the high repayment APY bonus applies"),
+        ("repayment_bonus_high_bps", 200, ParameterType::Percentage, Some(0), Some(10_000), "Pool APY basis points added when the repayment rate is above the high threshold"),
+        ("repayment_bonus_low_threshold", 75, ParameterType::Percentage, Some(0), Some(100), "Pool repayment rate (%) above which the low repayment APY bonus applies; cannot exceed the high threshold"),
+        ("repayment_bonus_low_bps", 100, ParameterType::Percentage, Some(0), Some(10_000), "Pool APY basis points added when the repayment rate is above the low threshold"),
     ];
     
     PROTOCOL_PARAMETERS.with(|params| {
@@ -122,10 +190,46 @@ pub fn create_proposal(
     title: String,
     description: String,
     execution_payload: Option<Vec<u8>>,
+) -> GovernanceResult<u64> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "create_proposal")
+        .map_err(|_| GovernanceError::Unauthorized)?;
+    submit_proposal(caller(), proposal_type, title, description, execution_payload, None)
+}
+
+/// Create a proposal with a typed action that execute_proposal performs once the vote passes
+#[update]
+pub fn create_action_proposal(
+    title: String,
+    description: String,
+    action: ProposalAction,
 ) -> GovernanceResult<u64> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "create_action_proposal")
+        .map_err(|_| GovernanceError::Unauthorized)?;
+    
+    if let Err(error) = validate_proposal_action(&action) {
+        log_audit_action(
+            caller,
+            "PROPOSAL_ACTION_REJECTED".to_string(),
+            format!("{} proposal rejected at creation: {}", action.audit_action(), error),
+        );
+        return Err(GovernanceError::InvalidProposal);
+    }
     
+    submit_proposal(caller, action.proposal_type(), title, description, None, Some(action))
+}
+
+fn submit_proposal(
+    caller: Principal,
+    proposal_type: ProposalType,
+    title: String,
+    description: String,
+    execution_payload: Option<Vec<u8>>,
+    action: Option<ProposalAction>,
+) -> GovernanceResult<u64> {
     // Check authorization
+    crate::validation::guard_caller(&caller, "create_proposal")
+        .map_err(|_| GovernanceError::Unauthorized)?;
     if !is_authorized_to_propose(&caller) {
         return Err(GovernanceError::Unauthorized);
     }
@@ -175,6 +279,7 @@ pub fn create_proposal(
         approval_threshold,
         executed_at: None,
         executed_by: None,
+        action,
     };
     
     PROPOSALS.with(|proposals| {
@@ -198,6 +303,8 @@ pub fn vote_on_proposal(
     reason: Option<String>,
 ) -> GovernanceResult<String> {
     let voter = caller();
+    crate::validation::guard_caller(&voter, "vote_on_proposal")
+        .map_err(|_| GovernanceError::Unauthorized)?;
     
     // Check if proposal exists and is active
     let mut proposal = PROPOSALS.with(|proposals| {
@@ -261,8 +368,10 @@ pub fn vote_on_proposal(
 
 /// Execute a proposal that has been approved
 #[update]
-pub fn execute_proposal(proposal_id: u64) -> GovernanceResult<String> {
+pub async fn execute_proposal(proposal_id: u64) -> GovernanceResult<String> {
     let executor = caller();
+    crate::validation::guard_caller(&executor, "execute_proposal")
+        .map_err(|_| GovernanceError::Unauthorized)?;
     
     // Check admin permissions for execution
     if !is_admin(&executor) {
@@ -316,20 +425,43 @@ pub fn execute_proposal(proposal_id: u64) -> GovernanceResult<String> {
         return Err(GovernanceError::QuorumNotMet);
     }
     
+    if let Some(action) = &proposal.action {
+        if let Err(error) = validate_proposal_action(action) {
+            log_audit_action(
+                executor,
+                "PROPOSAL_EXECUTION_FAILED".to_string(),
+                format!("Proposal {} preconditions not met: {}", proposal_id, error),
+            );
+            return Err(GovernanceError::ExecutionFailed);
+        }
+    }
+    
+    // Claim the proposal before any await so a concurrent call cannot execute it twice
+    proposal.status = ProposalStatus::Approved;
+    PROPOSALS.with(|proposals| {
+        proposals.borrow_mut().insert(proposal_id, proposal.clone());
+    });
+    
     // Execute the proposal
-    let execution_result = match proposal.proposal_type {
-        ProposalType::ProtocolParameterUpdate => execute_parameter_update(&proposal),
-        ProposalType::AdminRoleUpdate => execute_admin_role_update(&proposal),
-        ProposalType::SystemConfiguration => execute_system_config_update(&proposal),
-        ProposalType::EmergencyAction => execute_emergency_action(&proposal),
-        _ => Err("Proposal type not implemented".to_string()),
+    let execution_result = match proposal.action.clone() {
+        Some(action) => execute_proposal_action(&proposal, &action).await,
+        None => match proposal.proposal_type {
+            ProposalType::ProtocolParameterUpdate => execute_parameter_update(&proposal),
+            ProposalType::AdminRoleUpdate => execute_admin_role_update(&proposal),
+            ProposalType::SystemConfiguration => execute_system_config_update(&proposal),
+            ProposalType::EmergencyAction => execute_emergency_action(&proposal),
+            _ => Err("Proposal type not implemented".to_string()),
+        },
     };
     
     match execution_result {
         Ok(result) => {
-            proposal.status = ProposalStatus::Executed;
-            proposal.executed_at = Some(time());
-            proposal.executed_by = Some(executor);
+            // Signal proposals stay Approved: the vote outcome is the result
+            if !matches!(proposal.action, Some(ProposalAction::Signal)) {
+                proposal.status = ProposalStatus::Executed;
+                proposal.executed_at = Some(time());
+                proposal.executed_by = Some(executor);
+            }
             
             PROPOSALS.with(|proposals| {
                 proposals.borrow_mut().insert(proposal_id, proposal);
@@ -344,6 +476,13 @@ pub fn execute_proposal(proposal_id: u64) -> GovernanceResult<String> {
             Ok(result)
         },
         Err(error) => {
+            // Terminal: a treasury transfer whose outcome was lost must not be sent again
+            // under a new ledger timestamp. A new proposal is needed to retry.
+            proposal.status = ProposalStatus::Failed;
+            PROPOSALS.with(|proposals| {
+                proposals.borrow_mut().insert(proposal_id, proposal);
+            });
+            
             log_audit_action(
                 executor,
                 "PROPOSAL_EXECUTION_FAILED".to_string(),
@@ -354,12 +493,211 @@ pub fn execute_proposal(proposal_id: u64) -> GovernanceResult<String> {
     }
 }
 
+// ========== LOAN MANAGER ROTATION ==========
+
+/// Reject principals that can never prove control of the loan manager role
+pub fn validate_loan_manager_candidate(candidate: &Principal, current: Option<Principal>) -> Result<(), String> {
+    if *candidate == Principal::anonymous() || *candidate == Principal::management_canister() {
+        return Err("Loan manager must be a principal that can sign calls".to_string());
+    }
+    if current == Some(*candidate) {
+        return Err("Principal is already the loan manager".to_string());
+    }
+    Ok(())
+}
+
+fn log_loan_manager_rotation(action: &str, description: String, candidate: Principal, previous: Option<Principal>) {
+    use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+    log_audit_enhanced(
+        AuditCategory::Security,
+        action.to_string(),
+        AuditEventLevel::Critical,
+        AuditDetails {
+            description,
+            entity_type: Some("loan_manager".to_string()),
+            entity_id: Some(candidate.to_text()),
+            before_state: Some(previous.map(|p| p.to_text()).unwrap_or_else(|| "none".to_string())),
+            affected_principals: vec![candidate],
+            ..Default::default()
+        },
+        AuditResult {
+            success: true,
+            error_code: None,
+            error_message: None,
+            execution_time_ms: None,
+            gas_used: None,
+            cycles_consumed: None,
+            memory_used_bytes: None,
+            warning_flags: vec![],
+        },
+        None,
+    );
+}
+
+/// Propose a new loan manager (super admin only). The candidate must call
+/// `accept_loan_manager_role` after the governance execution delay; the current
+/// manager keeps the role until then. A new proposal replaces any pending one.
+#[update]
+pub fn propose_loan_manager(candidate: Principal, nonce: Option<u64>) -> Result<PendingLoanManager, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "propose_loan_manager")?;
+    
+    if !is_super_admin(&caller) {
+        return Err("Unauthorized: Only super admins can propose a loan manager".to_string());
+    }
+    crate::validation::require_admin_nonce(&caller, nonce, "propose_loan_manager")?;
+    
+    let current = get_canister_config().loan_manager_principal;
+    validate_loan_manager_candidate(&candidate, current)?;
+    
+    let now = time();
+    let pending = PendingLoanManager {
+        principal: candidate,
+        proposed_by: caller,
+        proposed_at: now,
+        accepts_after: now + get_governance_config().execution_delay_seconds * 1_000_000_000,
+    };
+    set_pending_loan_manager(pending.clone());
+    
+    log_loan_manager_rotation(
+        "LOAN_MANAGER_PROPOSED",
+        format!(
+            "{} proposed {} as loan manager, acceptable from {}",
+            caller.to_text(), candidate.to_text(), pending.accepts_after
+        ),
+        candidate,
+        current,
+    );
+    
+    Ok(pending)
+}
+
+/// Withdraw a pending loan manager proposal (super admin only)
+#[update]
+pub fn cancel_loan_manager_proposal(nonce: Option<u64>) -> Result<(), String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "cancel_loan_manager_proposal")?;
+    
+    if !is_super_admin(&caller) {
+        return Err("Unauthorized: Only super admins can cancel a loan manager proposal".to_string());
+    }
+    crate::validation::require_admin_nonce(&caller, nonce, "cancel_loan_manager_proposal")?;
+    
+    let pending = get_stored_pending_loan_manager()
+        .ok_or_else(|| "No loan manager proposal is pending".to_string())?;
+    clear_pending_loan_manager();
+    
+    log_audit_action(
+        caller,
+        "LOAN_MANAGER_PROPOSAL_CANCELLED".to_string(),
+        format!("Loan manager proposal for {} cancelled", pending.principal.to_text()),
+    );
+    
+    Ok(())
+}
+
+/// Take up the loan manager role (pending candidate only, after the execution delay).
+/// Calling from the candidate principal is the proof that it is controlled.
+#[update]
+pub fn accept_loan_manager_role() -> Result<Principal, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "accept_loan_manager_role")?;
+    
+    let pending = get_stored_pending_loan_manager()
+        .ok_or_else(|| "No loan manager proposal is pending".to_string())?;
+    if pending.principal != caller {
+        return Err("Unauthorized: Only the proposed loan manager can accept the role".to_string());
+    }
+    let now = time();
+    if now < pending.accepts_after {
+        return Err(format!("Loan manager timelock active until {}", pending.accepts_after));
+    }
+    
+    let mut config = get_canister_config();
+    let previous = config.loan_manager_principal;
+    config.loan_manager_principal = Some(caller);
+    config.updated_at = now;
+    update_config(config)?;
+    crate::helpers::set_loan_manager_principal(caller);
+    clear_pending_loan_manager();
+    
+    log_loan_manager_rotation(
+        "LOAN_MANAGER_ACCEPTED",
+        format!(
+            "{} accepted the loan manager role proposed by {}, replacing {}",
+            caller.to_text(), pending.proposed_by.to_text(),
+            previous.map(|p| p.to_text()).unwrap_or_else(|| "none".to_string())
+        ),
+        caller,
+        previous,
+    );
+    
+    Ok(caller)
+}
+
+/// Active loan manager principal
+#[query]
+pub fn get_loan_manager() -> Option<Principal> {
+    get_canister_config().loan_manager_principal
+}
+
+/// Loan manager proposal awaiting acceptance, if any
+#[query]
+pub fn get_pending_loan_manager() -> Option<PendingLoanManager> {
+    get_stored_pending_loan_manager()
+}
+
+// ========== ADMIN NONCES ==========
+
+/// Require, or stop requiring, nonces on sensitive calls from `admin`. Super admins may change
+/// any admin; an admin may only turn nonces on for themself. Takes effect while the
+/// admin_nonces feature flag is on.
+#[update]
+pub fn set_admin_nonce_required(admin: Principal, required: bool) -> Result<AdminNonceState, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "set_admin_nonce_required")?;
+    let self_enrolment = caller == admin && required && is_admin(&caller);
+    if !is_super_admin(&caller) && !self_enrolment {
+        return Err("Unauthorized: Only super admins can change another admin's nonce requirement or turn it off".to_string());
+    }
+    if !is_admin(&admin) && !is_super_admin(&admin) {
+        return Err("Nonces can only be required for admins".to_string());
+    }
+    
+    let mut state = crate::storage::get_admin_nonce_state(admin).unwrap_or(AdminNonceState {
+        admin,
+        required: false,
+        last_nonce: 0,
+        updated_at: 0,
+    });
+    state.required = required;
+    state.updated_at = time();
+    crate::storage::store_admin_nonce_state(state.clone());
+    
+    log_audit_action(
+        caller,
+        "ADMIN_NONCE_REQUIREMENT_CHANGED".to_string(),
+        format!("Nonces {} for admin {}", if required { "required" } else { "no longer required" }, admin.to_text()),
+    );
+    
+    Ok(state)
+}
+
+/// Next nonce `admin` should send with a sensitive call
+#[query]
+pub fn get_admin_nonce(admin: Principal) -> u64 {
+    crate::storage::get_admin_nonce_state(admin)
+        .map_or(0, |state| state.last_nonce)
+        .saturating_add(1)
+}
+
 // ========== PROTOCOL PARAMETER MANAGEMENT ==========
 
 /// Set or update a protocol parameter (admin only or through governance)
 #[update]
 pub fn set_protocol_parameter(key: String, value: u64) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "set_protocol_parameter")?;
     
     // Check if caller is admin
     if !is_admin(&caller) {
@@ -369,16 +707,57 @@ pub fn set_protocol_parameter(key: String, value: u64) -> Result<String, String>
     // Get existing parameter or create new one
     let mut param = PROTOCOL_PARAMETERS.with(|params| {
         params.borrow().get(&key).cloned()
-    }).unwrap_or_else(|| ProtocolParameter {
-        key: key.clone(),
-        current_value: 0,
-        proposed_value: None,
-        value_type: ParameterType::Amount,
-        min_value: None,
-        max_value: None,
-        description: "Custom parameter".to_string(),
-        last_updated: 0,
-        updated_by: Principal::anonymous(),
+    }).unwrap_or_else(|| match commodity_from_ltv_key(&key) {
+        Some(commodity) => ProtocolParameter {
+            key: key.clone(),
+            current_value: 0,
+            proposed_value: None,
+            value_type: ParameterType::Percentage,
+            min_value: Some(MIN_COMMODITY_LTV_RATIO),
+            max_value: Some(MAX_COMMODITY_LTV_RATIO),
+            description: format!("Loan-to-value ratio override for {} (%)", commodity),
+            last_updated: 0,
+            updated_by: Principal::anonymous(),
+        },
+        None if commodity_from_pause_key(&key).is_some() => ProtocolParameter {
+            key: key.clone(),
+            current_value: 0,
+            proposed_value: None,
+            value_type: ParameterType::Boolean,
+            min_value: Some(0),
+            max_value: Some(1),
+            description: format!(
+                "Pause new loans against {} (1 = paused)",
+                commodity_from_pause_key(&key).unwrap_or_default()
+            ),
+            last_updated: 0,
+            updated_by: Principal::anonymous(),
+        },
+        None if region_from_exposure_cap_key(&key).is_some() => ProtocolParameter {
+            key: key.clone(),
+            current_value: 0,
+            proposed_value: None,
+            value_type: ParameterType::Percentage,
+            min_value: Some(0),
+            max_value: Some(10_000),
+            description: format!(
+                "Maximum share of outstanding principal in region {} (bps)",
+                region_from_exposure_cap_key(&key).unwrap_or_default()
+            ),
+            last_updated: 0,
+            updated_by: Principal::anonymous(),
+        },
+        None => ProtocolParameter {
+            key: key.clone(),
+            current_value: 0,
+            proposed_value: None,
+            value_type: ParameterType::Amount,
+            min_value: None,
+            max_value: None,
+            description: "Custom parameter".to_string(),
+            last_updated: 0,
+            updated_by: Principal::anonymous(),
+        },
     });
     
     // Validate value range
@@ -394,6 +773,8 @@ pub fn set_protocol_parameter(key: String, value: u64) -> Result<String, String>
         }
     }
     
+    validate_pool_apy_change(&key, value)?;
+    
     // Update parameter
     param.current_value = value;
     param.last_updated = time();
@@ -423,6 +804,24 @@ pub fn get_protocol_parameter(key: String) -> Result<ProtocolParameter, String>
     }).ok_or_else(|| format!("Parameter {} not found", key))
 }
 
+/// Get the effective loan-to-value ratio (%) for a commodity
+#[query]
+pub fn get_commodity_ltv(commodity: String) -> u64 {
+    get_commodity_ltv_ratio(&commodity)
+}
+
+/// Commodities currently closed to new loans
+#[query]
+pub fn get_paused_commodities() -> Vec<String> {
+    crate::storage::get_protocol_parameters().paused_commodities.unwrap_or_default()
+}
+
+/// Whether new loans against `commodity` are paused
+#[query]
+pub fn is_commodity_paused(commodity: String) -> bool {
+    crate::helpers::is_commodity_paused(&commodity)
+}
+
 /// Get all protocol parameters
 #[query]
 pub fn get_all_protocol_parameters() -> Vec<ProtocolParameter> {
@@ -431,6 +830,237 @@ pub fn get_all_protocol_parameters() -> Vec<ProtocolParameter> {
     })
 }
 
+/// Write a parameter back exactly as exported, bypassing validation and change effects.
+/// Used only by state snapshot imports.
+pub(crate) fn restore_protocol_parameter(param: ProtocolParameter) {
+    PROTOCOL_PARAMETERS.with(|params| {
+        params.borrow_mut().insert(param.key.clone(), param);
+    });
+}
+
+// ========== PARAMETER SNAPSHOTS ==========
+
+/// Capture the complete protocol, governance and pool configuration (admin only)
+#[update]
+pub fn snapshot_protocol_parameters(label: String) -> Result<u64, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "snapshot_protocol_parameters")?;
+    
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can snapshot protocol parameters".to_string());
+    }
+    
+    if label.trim().is_empty() {
+        return Err("Snapshot label cannot be empty".to_string());
+    }
+    
+    let snapshot_id = PARAMETER_SNAPSHOTS.with(|snapshots| {
+        snapshots.borrow().last_key_value().map(|(id, _)| id + 1).unwrap_or(1)
+    });
+    
+    let snapshot = ParameterSnapshot {
+        id: snapshot_id,
+        label: label.trim().to_string(),
+        parameters: get_all_protocol_parameters(),
+        protocol_parameters: crate::storage::get_protocol_parameters(),
+        governance_config: get_governance_config(),
+        canister_config: get_canister_config(),
+        created_by: caller,
+        created_at: time(),
+        restore_requested_by: None,
+        restore_requested_at: None,
+        last_restored_at: None,
+    };
+    
+    PARAMETER_SNAPSHOTS.with(|snapshots| {
+        snapshots.borrow_mut().insert(snapshot_id, snapshot);
+    });
+    
+    log_audit_action(
+        caller,
+        "PARAMETER_SNAPSHOT_CREATED".to_string(),
+        format!("Parameter snapshot {} created: {}", snapshot_id, label.trim()),
+    );
+    
+    Ok(snapshot_id)
+}
+
+/// List all parameter snapshots, newest first (admin only)
+#[query]
+pub fn list_parameter_snapshots() -> Result<Vec<ParameterSnapshot>, String> {
+    let caller = caller();
+    
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can view parameter snapshots".to_string());
+    }
+    
+    Ok(PARAMETER_SNAPSHOTS.with(|snapshots| {
+        snapshots.borrow().iter().rev().map(|(_, snapshot)| snapshot).collect()
+    }))
+}
+
+/// Start the execution-delay timelock for restoring a snapshot (super admin only)
+#[update]
+pub fn schedule_parameter_restore(snapshot_id: u64) -> Result<u64, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "schedule_parameter_restore")?;
+    
+    if !is_super_admin(&caller) {
+        return Err("Unauthorized: Only super admins can restore protocol parameters".to_string());
+    }
+    
+    let mut snapshot = PARAMETER_SNAPSHOTS.with(|snapshots| {
+        snapshots.borrow().get(&snapshot_id)
+    }).ok_or_else(|| format!("Parameter snapshot {} not found", snapshot_id))?;
+    
+    let now = time();
+    snapshot.restore_requested_by = Some(caller);
+    snapshot.restore_requested_at = Some(now);
+    
+    PARAMETER_SNAPSHOTS.with(|snapshots| {
+        snapshots.borrow_mut().insert(snapshot_id, snapshot);
+    });
+    
+    let executable_at = now + get_governance_config().execution_delay_seconds * 1_000_000_000;
+    
+    log_audit_action(
+        caller,
+        "PARAMETER_RESTORE_SCHEDULED".to_string(),
+        format!("Restore of parameter snapshot {} executable at {}", snapshot_id, executable_at),
+    );
+    
+    Ok(executable_at)
+}
+
+/// Reapply a snapshot's parameter values once its restore timelock has elapsed (super admin only).
+/// Only keys held in the snapshot are written, through the same path as a parameter update;
+/// operational flags, principals and the rest of the live configuration are left alone.
+#[update]
+pub fn restore_protocol_parameters(snapshot_id: u64) -> Result<Vec<ParameterDiff>, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "restore_protocol_parameters")?;
+    
+    if !is_super_admin(&caller) {
+        return Err("Unauthorized: Only super admins can restore protocol parameters".to_string());
+    }
+    
+    let mut snapshot = PARAMETER_SNAPSHOTS.with(|snapshots| {
+        snapshots.borrow().get(&snapshot_id)
+    }).ok_or_else(|| format!("Parameter snapshot {} not found", snapshot_id))?;
+    
+    let requested_at = snapshot.restore_requested_at
+        .ok_or_else(|| "Restore must be scheduled before it can be executed".to_string())?;
+    let now = time();
+    let executable_at = requested_at + get_governance_config().execution_delay_seconds * 1_000_000_000;
+    if now < executable_at {
+        return Err(format!("Restore timelock active until {}", executable_at));
+    }
+    
+    let diff = parameter_restore_plan(
+        &parameter_values(&get_all_protocol_parameters()),
+        &parameter_values(&snapshot.parameters),
+    );
+    
+    // No awaits below, so trapping on a failed key rolls back the keys already written
+    for change in &diff {
+        let Some(mut param) = snapshot.parameters.iter().find(|param| param.key == change.key).cloned() else {
+            continue;
+        };
+        param.last_updated = now;
+        param.updated_by = caller;
+        PROTOCOL_PARAMETERS.with(|params| {
+            params.borrow_mut().insert(param.key.clone(), param.clone());
+        });
+        if let Err(e) = apply_parameter_change(&param.key, param.current_value) {
+            ic_cdk::trap(&format!("Restore of snapshot {} aborted at {}: {}", snapshot_id, param.key, e));
+        }
+    }
+    
+    snapshot.restore_requested_by = None;
+    snapshot.restore_requested_at = None;
+    snapshot.last_restored_at = Some(now);
+    PARAMETER_SNAPSHOTS.with(|snapshots| {
+        snapshots.borrow_mut().insert(snapshot_id, snapshot.clone());
+    });
+    
+    use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+    log_audit_enhanced(
+        AuditCategory::Governance,
+        "PROTOCOL_PARAMETERS_RESTORED".to_string(),
+        AuditEventLevel::Critical,
+        AuditDetails {
+            description: format!(
+                "Parameter snapshot {} ({}) restored by {}; {} parameter(s) changed",
+                snapshot_id, snapshot.label, caller.to_text(), diff.len()
+            ),
+            entity_type: Some("parameter_snapshot".to_string()),
+            entity_id: Some(snapshot_id.to_string()),
+            before_state: Some(format!(
+                "{:?}",
+                diff.iter().map(|d| (&d.key, d.current_value)).collect::<Vec<_>>()
+            )),
+            after_state: Some(format!(
+                "{:?}",
+                diff.iter().map(|d| (&d.key, d.snapshot_value)).collect::<Vec<_>>()
+            )),
+            ..Default::default()
+        },
+        AuditResult {
+            success: true,
+            error_code: None,
+            error_message: None,
+            execution_time_ms: None,
+            gas_used: None,
+            cycles_consumed: None,
+            memory_used_bytes: None,
+            warning_flags: vec!["PARAMETER_ROLLBACK".to_string()],
+        },
+        None,
+    );
+    
+    Ok(diff)
+}
+
+/// Parameter keys that switch the canister on and off rather than tune it; a restore never
+/// touches them
+pub const OPERATIONAL_PARAMETER_KEYS: [&str; 2] = ["emergency_stop", "maintenance_mode"];
+
+/// Changes a snapshot restore writes: keys held in the snapshot whose value differs from the
+/// live one, minus operational flags. Keys added since the snapshot are kept as they are.
+pub fn parameter_restore_plan(current: &[(String, u64)], snapshot: &[(String, u64)]) -> Vec<ParameterDiff> {
+    diff_parameter_values(current, snapshot)
+        .into_iter()
+        .filter(|change| change.snapshot_value.is_some())
+        .filter(|change| !OPERATIONAL_PARAMETER_KEYS.contains(&change.key.as_str()))
+        .collect()
+}
+
+fn parameter_values(parameters: &[ProtocolParameter]) -> Vec<(String, u64)> {
+    parameters.iter().map(|p| (p.key.clone(), p.current_value)).collect()
+}
+
+/// Keys whose value differs between the live parameters and a snapshot, sorted by key
+pub fn diff_parameter_values(current: &[(String, u64)], snapshot: &[(String, u64)]) -> Vec<ParameterDiff> {
+    let current_map: HashMap<&String, u64> = current.iter().map(|(k, v)| (k, *v)).collect();
+    let snapshot_map: HashMap<&String, u64> = snapshot.iter().map(|(k, v)| (k, *v)).collect();
+    
+    let mut keys: Vec<&String> = current_map.keys().chain(snapshot_map.keys()).cloned().collect();
+    keys.sort();
+    keys.dedup();
+    
+    keys.into_iter()
+        .filter_map(|key| {
+            let current_value = current_map.get(key).copied();
+            let snapshot_value = snapshot_map.get(key).copied();
+            if current_value == snapshot_value {
+                None
+            } else {
+                Some(ParameterDiff { key: key.clone(), current_value, snapshot_value })
+            }
+        })
+        .collect()
+}
+
 // ========== ADMIN ROLE MANAGEMENT ==========
 
 /// Grant admin role to a principal (super admin only)
@@ -442,6 +1072,7 @@ pub fn grant_admin_role(
     expires_at: Option<u64>,
 ) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "grant_admin_role")?;
     
     // Check if caller is super admin
     if !is_super_admin(&caller) {
@@ -475,6 +1106,7 @@ pub fn grant_admin_role(
 #[update]
 pub fn revoke_admin_role(principal: Principal) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "revoke_admin_role")?;
     
     // Check if caller is super admin
     if !is_super_admin(&caller) {
@@ -501,6 +1133,7 @@ pub fn revoke_admin_role(principal: Principal) -> Result<String, String> {
 #[update]
 pub fn transfer_admin_role(new_admin: Principal) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "transfer_admin_role")?;
     
     // Check if caller is super admin
     if !is_super_admin(&caller) {
@@ -630,7 +1263,7 @@ fn is_authorized_to_propose(caller: &Principal) -> bool {
     is_admin(caller) || calculate_voting_power(caller) >= get_governance_config().proposal_threshold
 }
 
-fn is_super_admin(caller: &Principal) -> bool {
+pub fn is_super_admin(caller: &Principal) -> bool {
     ADMIN_ROLES.with(|roles| {
         if let Some(role) = roles.borrow().get(caller) {
             role.is_active && matches!(role.role_type, AdminRoleType::SuperAdmin)
@@ -710,6 +1343,91 @@ fn calculate_average_participation_rate() -> u64 {
 
 // ========== PROPOSAL EXECUTION FUNCTIONS ==========
 
+/// Preconditions each action type must satisfy at creation and again at execution
+pub fn validate_proposal_action(action: &ProposalAction) -> Result<(), String> {
+    match action {
+        ProposalAction::SetParameter { key, value } => {
+            validate_parameter_value(key.clone(), *value).map(|_| ())
+        },
+        ProposalAction::TreasurySpend { amount, destination } => {
+            crate::treasury_management::validate_governance_treasury_spend(*amount, *destination)
+        },
+        ProposalAction::GrantRole { principal, role } => {
+            if *principal == Principal::anonymous() {
+                return Err("Cannot grant a role to the anonymous principal".to_string());
+            }
+            match get_admin_role(*principal) {
+                Some(existing) if existing.is_active && existing.role_type == *role => {
+                    Err(format!("{} already holds the {:?} role", principal, role))
+                },
+                _ => Ok(()),
+            }
+        },
+        ProposalAction::Signal => Ok(()),
+    }
+}
+
+async fn execute_proposal_action(proposal: &Proposal, action: &ProposalAction) -> Result<String, String> {
+    let result = match action {
+        ProposalAction::SetParameter { key, value } => {
+            update_parameter_through_governance(key, *value, proposal.proposer)
+        },
+        ProposalAction::TreasurySpend { amount, destination } => {
+            crate::treasury_management::execute_governance_treasury_spend(proposal.id, *amount, *destination)
+                .await
+                .map(|tx_id| format!("Transferred {} satoshi to {}. TX ID: {}", amount, destination, tx_id))
+        },
+        ProposalAction::GrantRole { principal, role } => {
+            let admin_role = AdminRole {
+                admin_principal: *principal,
+                role_type: role.clone(),
+                granted_at: time(),
+                granted_by: ic_cdk::id(),
+                expires_at: None,
+                permissions: default_permissions_for_role(role),
+                is_active: true,
+            };
+            ADMIN_ROLES.with(|roles| {
+                roles.borrow_mut().insert(*principal, admin_role);
+            });
+            Ok(format!("Admin role {:?} granted to {}", role, principal))
+        },
+        ProposalAction::Signal => Ok("Signal proposal approved".to_string()),
+    };
+    
+    log_audit_action(
+        proposal.proposer,
+        action.audit_action().to_string(),
+        match &result {
+            Ok(outcome) => format!("Proposal {}: {}", proposal.id, outcome),
+            Err(error) => format!("Proposal {} failed: {}", proposal.id, error),
+        },
+    );
+    
+    result
+}
+
+fn default_permissions_for_role(role: &AdminRoleType) -> Vec<Permission> {
+    match role {
+        AdminRoleType::SuperAdmin => vec![
+            Permission::ManageParameters,
+            Permission::ManageAdmins,
+            Permission::EmergencyStop,
+            Permission::ManageTreasury,
+            Permission::ManageLiquidation,
+            Permission::ManageOracle,
+            Permission::ViewMetrics,
+            Permission::ExecuteProposals,
+        ],
+        AdminRoleType::ProtocolAdmin => vec![Permission::ManageParameters, Permission::ViewMetrics, Permission::ExecuteProposals],
+        AdminRoleType::TreasuryAdmin => vec![Permission::ManageTreasury, Permission::ViewMetrics],
+        AdminRoleType::RiskAdmin => vec![Permission::ManageParameters, Permission::ManageLiquidation, Permission::ViewMetrics],
+        AdminRoleType::LiquidationAdmin => vec![Permission::ManageLiquidation, Permission::ViewMetrics],
+        AdminRoleType::OracleAdmin => vec![Permission::ManageOracle, Permission::ViewMetrics],
+        AdminRoleType::EmergencyAdmin => vec![Permission::EmergencyStop, Permission::ViewMetrics],
+    }
+}
+
 fn execute_parameter_update(proposal: &Proposal) -> Result<String, String> {
     if let Some(payload) = &proposal.execution_payload {
         // Decode parameter update payload
@@ -724,27 +1442,31 @@ fn execute_parameter_update(proposal: &Proposal) -> Result<String, String> {
         let key = parts[0].to_string();
         let value: u64 = parts[1].parse().map_err(|_| "Invalid parameter value")?;
         
-        // Update parameter directly (bypassing admin check since this is executed through governance)
-        let mut param = PROTOCOL_PARAMETERS.with(|params| {
-            params.borrow().get(&key).cloned()
-        }).ok_or_else(|| format!("Parameter {} not found", key))?;
-        
-        param.current_value = value;
-        param.last_updated = time();
-        param.updated_by = proposal.proposer;
-        
-        PROTOCOL_PARAMETERS.with(|params| {
-            params.borrow_mut().insert(key.clone(), param);
-        });
-        
-        apply_parameter_change(&key, value)?;
-        
-        Ok(format!("Parameter {} updated to {}", key, value))
+        update_parameter_through_governance(&key, value, proposal.proposer)
     } else {
         Err("No execution payload provided".to_string())
     }
 }
 
+/// Update a parameter directly (bypassing admin check since this is executed through governance)
+fn update_parameter_through_governance(key: &str, value: u64, updated_by: Principal) -> Result<String, String> {
+    let mut param = PROTOCOL_PARAMETERS.with(|params| {
+        params.borrow().get(&key.to_string())
+    }).ok_or_else(|| format!("Parameter {} not found", key))?;
+    
+    param.current_value = value;
+    param.last_updated = time();
+    param.updated_by = updated_by;
+    
+    PROTOCOL_PARAMETERS.with(|params| {
+        params.borrow_mut().insert(key.to_string(), param);
+    });
+    
+    apply_parameter_change(key, value)?;
+    
+    Ok(format!("Parameter {} updated to {}", key, value))
+}
+
 fn execute_admin_role_update(proposal: &Proposal) -> Result<String, String> {
     // Implementation for admin role updates through governance
     // This would parse the payload and execute the admin role change
@@ -761,7 +1483,90 @@ fn execute_emergency_action(proposal: &Proposal) -> Result<String, String> {
     Ok("Emergency action executed".to_string())
 }
 
+/// Parameter keys of the form `commodity_ltv:<commodity>` override the LTV for one commodity
+const COMMODITY_LTV_KEY_PREFIX: &str = "commodity_ltv:";
+
+fn commodity_from_ltv_key(key: &str) -> Option<String> {
+    key.strip_prefix(COMMODITY_LTV_KEY_PREFIX)
+        .map(|commodity| commodity.trim().to_lowercase())
+        .filter(|commodity| !commodity.is_empty())
+}
+
+/// Parameter keys of the form `commodity_paused:<commodity>` set to 1 stop new loans against one
+/// commodity; existing loans are left alone
+const COMMODITY_PAUSE_KEY_PREFIX: &str = "commodity_paused:";
+
+fn commodity_from_pause_key(key: &str) -> Option<String> {
+    key.strip_prefix(COMMODITY_PAUSE_KEY_PREFIX)
+        .map(|commodity| commodity.trim().to_lowercase())
+        .filter(|commodity| !commodity.is_empty())
+}
+
+/// Paused set after pausing (`paused` true) or unpausing `commodity`, kept sorted
+pub fn update_paused_commodities(current: Option<Vec<String>>, commodity: &str, paused: bool) -> Vec<String> {
+    let mut commodities = current.unwrap_or_default();
+    commodities.retain(|existing| existing != commodity);
+    if paused {
+        commodities.push(commodity.to_string());
+        commodities.sort();
+    }
+    commodities
+}
+
+/// Parameter keys of the form `region_exposure_cap:<region>` allowlist a region and cap its share (bps)
+const REGION_EXPOSURE_CAP_KEY_PREFIX: &str = "region_exposure_cap:";
+
+fn region_from_exposure_cap_key(key: &str) -> Option<String> {
+    key.strip_prefix(REGION_EXPOSURE_CAP_KEY_PREFIX)
+        .map(|region| region.trim().to_lowercase())
+        .filter(|region| !region.is_empty())
+}
+
 fn apply_parameter_change(key: &str, value: u64) -> Result<(), String> {
+    // Per-commodity LTV overrides live in the core protocol parameters
+    if let Some(commodity) = commodity_from_ltv_key(key) {
+        if value < MIN_COMMODITY_LTV_RATIO || value > MAX_COMMODITY_LTV_RATIO {
+            return Err(format!(
+                "Commodity LTV {}% must be between {}% and {}%",
+                value, MIN_COMMODITY_LTV_RATIO, MAX_COMMODITY_LTV_RATIO
+            ));
+        }
+        let mut params = crate::storage::get_protocol_parameters();
+        params.commodity_ltv_overrides.get_or_insert_with(Default::default).insert(commodity, value);
+        crate::storage::set_protocol_parameters(params)?;
+        return Ok(());
+    }
+
+    // Commodity pauses only gate origination; loans already on the book are unaffected
+    if let Some(commodity) = commodity_from_pause_key(key) {
+        if value > 1 {
+            return Err("Commodity pause must be 0 (active) or 1 (paused)".to_string());
+        }
+        let mut params = crate::storage::get_protocol_parameters();
+        let was_paused = params.paused_commodities.as_ref().map_or(false, |paused| paused.contains(&commodity));
+        params.paused_commodities = Some(update_paused_commodities(params.paused_commodities.take(), &commodity, value == 1));
+        crate::storage::set_protocol_parameters(params)?;
+        if was_paused != (value == 1) {
+            log_audit_action(
+                caller(),
+                if value == 1 { "COMMODITY_PAUSED" } else { "COMMODITY_UNPAUSED" }.to_string(),
+                format!("New loans against {} {}", commodity, if value == 1 { "paused" } else { "resumed" }),
+            );
+        }
+        return Ok(());
+    }
+
+    // Per-region exposure caps double as the region allowlist
+    if let Some(region) = region_from_exposure_cap_key(key) {
+        if value > 10_000 {
+            return Err(format!("Region exposure cap {} bps cannot exceed 10000 bps", value));
+        }
+        let mut params = crate::storage::get_protocol_parameters();
+        params.region_exposure_caps.get_or_insert_with(Default::default).insert(region, value);
+        crate::storage::set_protocol_parameters(params)?;
+        return Ok(());
+    }
+
     // Apply the parameter change to the relevant system components
     match key {
         "emergency_stop" => {
@@ -792,6 +1597,79 @@ fn apply_parameter_change(key: &str, value: u64) -> Result<(), String> {
             config.updated_at = time();
             update_config(config);
         },
+        "max_commodity_exposure_bps" => {
+            // Update commodity concentration limit in protocol parameters
+            let mut params = crate::storage::get_protocol_parameters();
+            params.max_commodity_exposure_bps = Some(value);
+            crate::storage::set_protocol_parameters(params)?;
+        },
+        "pricing_mode" => {
+            // Switch the price feed used by origination and liquidation math
+            let mut params = crate::storage::get_protocol_parameters();
+            params.pricing_mode = Some(if value == 1 { crate::types::PricingMode::Twap } else { crate::types::PricingMode::Spot });
+            crate::storage::set_protocol_parameters(params)?;
+        },
+        "twap_window_seconds" => {
+            let mut params = crate::storage::get_protocol_parameters();
+            params.twap_window_seconds = Some(value);
+            crate::storage::set_protocol_parameters(params)?;
+        },
+        "prepayment_penalty_bps" | "prepayment_free_window_days" => {
+            let mut params = crate::storage::get_protocol_parameters();
+            if key == "prepayment_penalty_bps" {
+                params.prepayment_penalty_bps = Some(value);
+            } else {
+                params.prepayment_free_window_days = Some(value);
+            }
+            crate::storage::set_protocol_parameters(params)?;
+        },
+        "compounding_frequency" => {
+            // Switch the accrual model used by debt, forecast and amortization calculations from
+            // now on; interest already accrued keeps the model it accrued under
+            let mut params = crate::storage::get_protocol_parameters();
+            params.activate_compounding(match value {
+                1 => crate::types::CompoundingFrequency::Daily,
+                2 => crate::types::CompoundingFrequency::Monthly,
+                _ => crate::types::CompoundingFrequency::Simple,
+            }, time());
+            crate::storage::set_protocol_parameters(params)?;
+        },
+        "base_apy_bps" | "max_apy_bps" | "utilization_bonus_per_pct"
+        | "repayment_bonus_high_threshold" | "repayment_bonus_high_bps"
+        | "repayment_bonus_low_threshold" | "repayment_bonus_low_bps" => {
+            // Update the pool APY model read by pool stats, dashboards and snapshots
+            let mut params = crate::storage::get_protocol_parameters();
+            if let Some(pool_apy) = params.pool_apy().with_parameter(key, value) {
+                pool_apy.validate()?;
+                params.pool_apy = Some(pool_apy);
+                crate::storage::set_protocol_parameters(params)?;
+            }
+        },
+        "ckbtc_retry_max_attempts" | "ckbtc_retry_base_delay_rounds" => {
+            // Update ckBTC call retry policy in config
+            let mut config = get_canister_config();
+            if key == "ckbtc_retry_max_attempts" {
+                config.ckbtc_retry_max_attempts = Some(value);
+            } else {
+                config.ckbtc_retry_base_delay_rounds = Some(value);
+            }
+            config.updated_at = time();
+            update_config(config);
+        },
+        "deposit_fee_bps" | "withdrawal_fee_bps" => {
+            // Update liquidity pool fee in config
+            if value > MAX_POOL_FEE_BPS {
+                return Err(format!("Pool fee cannot exceed {} basis points", MAX_POOL_FEE_BPS));
+            }
+            let mut config = get_canister_config();
+            if key == "deposit_fee_bps" {
+                config.deposit_fee_bps = Some(value);
+            } else {
+                config.withdrawal_fee_bps = Some(value);
+            }
+            config.updated_at = time();
+            update_config(config);
+        },
         _ => {
             // For other parameters, they are stored in the parameter storage
             // and retrieved by other modules when needed
@@ -807,6 +1685,7 @@ fn apply_parameter_change(key: &str, value: u64) -> Result<(), String> {
 #[update]
 pub fn emergency_stop() -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "emergency_stop")?;
     
     // Check if caller has emergency admin permission
     if !has_permission(&caller, Permission::EmergencyStop) {
@@ -828,6 +1707,7 @@ pub fn emergency_stop() -> Result<String, String> {
 #[update]
 pub fn resume_operations() -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "resume_operations")?;
     
     // Check if caller is super admin
     if !is_super_admin(&caller) {
@@ -862,6 +1742,7 @@ fn has_permission(principal: &Principal, permission: Permission) -> bool {
 #[update]
 pub fn update_governance_config(config: GovernanceConfig) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "update_governance_config")?;
     
     if !is_super_admin(&caller) {
         return Err("Unauthorized: Only super admins can update governance config".to_string());
@@ -903,6 +1784,9 @@ pub fn create_batch_proposals(proposals: Vec<(ProposalType, String, String, Opti
 #[update]
 pub fn set_multiple_protocol_parameters(parameters: Vec<(String, u64)>) -> Vec<Result<String, String>> {
     let caller = caller();
+    if let Err(e) = crate::validation::guard_caller(&caller, "set_multiple_protocol_parameters") {
+        return vec![Err(e)];
+    }
     
     if !is_admin(&caller) {
         return vec![Err("Unauthorized: Only admins can set parameters".to_string())];
@@ -930,6 +1814,7 @@ pub fn get_protocol_parameters_by_category(category: String) -> Vec<ProtocolPara
                     "liquidation" => key.contains("liquidation") || key.contains("grace"),
                     "system" => key.contains("emergency") || key.contains("maintenance"),
                     "pool" => key.contains("utilization") || key.contains("reserve"),
+                    "collateral" => key.contains("collateral"),
                     _ => true,
                 }
             })
@@ -941,6 +1826,30 @@ pub fn get_protocol_parameters_by_category(category: String) -> Vec<ProtocolPara
 /// Validate parameter value before setting
 #[query]
 pub fn validate_parameter_value(key: String, value: u64) -> Result<String, String> {
+    if commodity_from_ltv_key(&key).is_some() {
+        if value < MIN_COMMODITY_LTV_RATIO || value > MAX_COMMODITY_LTV_RATIO {
+            return Err(format!(
+                "Commodity LTV {}% must be between {}% and {}%",
+                value, MIN_COMMODITY_LTV_RATIO, MAX_COMMODITY_LTV_RATIO
+            ));
+        }
+        return Ok("Parameter value is valid".to_string());
+    }
+    
+    if commodity_from_pause_key(&key).is_some() {
+        if value > 1 {
+            return Err("Commodity pause must be 0 (active) or 1 (paused)".to_string());
+        }
+        return Ok("Parameter value is valid".to_string());
+    }
+    
+    if region_from_exposure_cap_key(&key).is_some() {
+        if value > 10_000 {
+            return Err(format!("Region exposure cap {} bps cannot exceed 10000 bps", value));
+        }
+        return Ok("Parameter value is valid".to_string());
+    }
+    
     let param = PROTOCOL_PARAMETERS.with(|params| {
         params.borrow().get(&key).cloned()
     }).ok_or_else(|| format!("Parameter {} not found", key))?;
@@ -958,9 +1867,20 @@ pub fn validate_parameter_value(key: String, value: u64) -> Result<String, Strin
         }
     }
     
+    validate_pool_apy_change(&key, value)?;
+    
     Ok("Parameter value is valid".to_string())
 }
 
+/// The APY floor must stay at or below the cap, and the repayment bonus tiers in order,
+/// against the other live APY parameters
+fn validate_pool_apy_change(key: &str, value: u64) -> Result<(), String> {
+    match crate::storage::get_protocol_parameters().pool_apy().with_parameter(key, value) {
+        Some(pool_apy) => pool_apy.validate(),
+        None => Ok(()),
+    }
+}
+
 /// Get parameter history (if implemented)
 #[query]
 pub fn get_parameter_history(key: String) -> Vec<(u64, u64, Principal)> {
@@ -973,6 +1893,123 @@ pub fn get_parameter_history(key: String) -> Vec<(u64, u64, Principal)> {
     }
 }
 
+// ========== PARAMETER DIFFS ==========
+
+/// Any active admin role or configured admin may review parameter diffs
+fn can_review_parameter_diffs(caller: &Principal) -> bool {
+    is_admin(caller) || get_admin_role(*caller).map_or(false, |role| role.is_active)
+}
+
+/// Entries whose value changes, highest risk first and then by key
+pub fn build_parameter_diffs(changes: Vec<(String, Option<u64>, Option<u64>)>) -> Vec<ProposedParameterDiff> {
+    let mut diffs: Vec<ProposedParameterDiff> = changes.into_iter()
+        .filter(|(_, current, proposed)| current != proposed)
+        .map(|(key, current_value, proposed_value)| ProposedParameterDiff {
+            risk_score: crate::audit_logging::calculate_config_risk_score(&key),
+            key,
+            current_value,
+            proposed_value,
+        })
+        .collect();
+    diffs.sort_by(|a, b| b.risk_score.cmp(&a.risk_score).then_with(|| a.key.cmp(&b.key)));
+    diffs
+}
+
+/// Flatten a parameter set into governance keys and values. Per-commodity and per-region maps
+/// use the same prefixed keys `set_protocol_parameter` accepts.
+pub fn flatten_protocol_parameters(params: &ProtocolParameters) -> Vec<(String, u64)> {
+    let mut entries = vec![
+        ("loan_to_value_ratio".to_string(), params.loan_to_value_ratio),
+        ("base_apr".to_string(), params.base_apr),
+        ("max_loan_duration_days".to_string(), params.max_loan_duration_days),
+        ("grace_period_days".to_string(), params.grace_period_days),
+        ("max_commodity_exposure_bps".to_string(), params.max_commodity_exposure_bps()),
+        ("pricing_mode".to_string(), if params.pricing_mode() == PricingMode::Twap { 1 } else { 0 }),
+        ("twap_window_seconds".to_string(), params.twap_window_seconds()),
+        ("prepayment_penalty_bps".to_string(), params.prepayment_penalty_bps()),
+        ("prepayment_free_window_days".to_string(), params.prepayment_free_window_days()),
+        ("compounding_frequency".to_string(), match params.compounding() {
+            CompoundingFrequency::Simple => 0,
+            CompoundingFrequency::Daily => 1,
+            CompoundingFrequency::Monthly => 2,
+        }),
+    ];
+    let pool_apy = params.pool_apy();
+    entries.extend([
+        ("base_apy_bps", pool_apy.base_apy_bps),
+        ("max_apy_bps", pool_apy.max_apy_bps),
+        ("utilization_bonus_per_pct", pool_apy.utilization_bonus_per_pct),
+        ("repayment_bonus_high_threshold", pool_apy.repayment_bonus_high_threshold),
+        ("repayment_bonus_high_bps", pool_apy.repayment_bonus_high_bps),
+        ("repayment_bonus_low_threshold", pool_apy.repayment_bonus_low_threshold),
+        ("repayment_bonus_low_bps", pool_apy.repayment_bonus_low_bps),
+    ].into_iter().map(|(key, value)| (key.to_string(), value)));
+    entries.extend(params.commodity_ltv_overrides().iter()
+        .map(|(commodity, ltv)| (format!("{}{}", COMMODITY_LTV_KEY_PREFIX, commodity), *ltv)));
+    entries.extend(params.region_exposure_caps().iter()
+        .map(|(region, cap)| (format!("{}{}", REGION_EXPOSURE_CAP_KEY_PREFIX, region), *cap)));
+    entries.extend(params.paused_commodities.iter().flatten()
+        .map(|commodity| (format!("{}{}", COMMODITY_PAUSE_KEY_PREFIX, commodity), 1)));
+    entries
+}
+
+/// Pair up current and candidate entries by key; a key missing on one side is None there
+pub fn pair_parameter_entries(current: Vec<(String, u64)>, candidate: Vec<(String, u64)>) -> Vec<(String, Option<u64>, Option<u64>)> {
+    let mut paired: std::collections::BTreeMap<String, (Option<u64>, Option<u64>)> = std::collections::BTreeMap::new();
+    for (key, value) in current {
+        paired.entry(key).or_default().0 = Some(value);
+    }
+    for (key, value) in candidate {
+        paired.entry(key).or_default().1 = Some(value);
+    }
+    paired.into_iter().map(|(key, (current, proposed))| (key, current, proposed)).collect()
+}
+
+/// Parameter changes a proposal would make against live values (Observer+ admins)
+#[query]
+pub fn diff_proposal_parameters(proposal_id: u64) -> Result<Vec<ProposedParameterDiff>, String> {
+    let caller = caller();
+    if !can_review_parameter_diffs(&caller) {
+        return Err("Unauthorized: Only admins can review parameter diffs".to_string());
+    }
+
+    let proposal = PROPOSALS.with(|proposals| proposals.borrow().get(&proposal_id))
+        .ok_or_else(|| "Proposal not found".to_string())?;
+
+    // Typed actions first; older proposals carry a "key:value" payload
+    let change = match &proposal.action {
+        Some(ProposalAction::SetParameter { key, value }) => Some((key.clone(), *value)),
+        Some(_) => None,
+        None if matches!(proposal.proposal_type, ProposalType::ProtocolParameterUpdate) => proposal.execution_payload.as_ref()
+            .and_then(|payload| String::from_utf8(payload.clone()).ok())
+            .and_then(|payload| {
+                let (key, value) = payload.split_once(':')?;
+                Some((key.to_string(), value.parse().ok()?))
+            }),
+        None => None,
+    };
+
+    Ok(match change {
+        Some((key, value)) => {
+            let current = get_protocol_parameter(key.clone()).ok().map(|param| param.current_value);
+            build_parameter_diffs(vec![(key, current, Some(value))])
+        }
+        None => Vec::new(),
+    })
+}
+
+/// Differences between a candidate parameter set and the live one (Observer+ admins)
+#[query]
+pub fn diff_parameters(candidate: ProtocolParameters) -> Result<Vec<ProposedParameterDiff>, String> {
+    let caller = caller();
+    if !can_review_parameter_diffs(&caller) {
+        return Err("Unauthorized: Only admins can review parameter diffs".to_string());
+    }
+
+    let current = flatten_protocol_parameters(&crate::storage::get_protocol_parameters());
+    Ok(build_parameter_diffs(pair_parameter_entries(current, flatten_protocol_parameters(&candidate))))
+}
+
 /// Check if a proposal can be executed
 #[query]
 pub fn can_execute_proposal(proposal_id: u64) -> Result<bool, String> {
@@ -1009,7 +2046,14 @@ pub fn can_execute_proposal(proposal_id: u64) -> Result<bool, String> {
         0
     };
     
-    Ok(approval_rate >= proposal.approval_threshold)
+    if approval_rate < proposal.approval_threshold {
+        return Ok(false);
+    }
+    
+    match &proposal.action {
+        Some(action) => validate_proposal_action(action).map(|_| true),
+        None => Ok(true),
+    }
 }
 
 /// Get proposals by status
@@ -1041,6 +2085,7 @@ pub fn get_active_admin_count() -> u64 {
 #[update]
 pub fn set_maintenance_mode(enabled: bool) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "set_maintenance_mode")?;
     
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can set maintenance mode".to_string());
@@ -1077,6 +2122,9 @@ pub fn get_system_status() -> HashMap<String, bool> {
         status.insert("maintenance_mode".to_string(), maintenance_param.current_value == 1);
     }
     
+    status.insert("emergency_paused".to_string(), crate::storage::is_emergency_paused());
+    status.insert("origination_paused".to_string(), crate::storage::is_origination_paused());
+    
     status
 }
 
@@ -1084,6 +2132,7 @@ pub fn get_system_status() -> HashMap<String, bool> {
 #[update]
 pub fn initialize_super_admin(admin_principal: Principal) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "initialize_super_admin")?;
     
     // Check if any super admin already exists
     let existing_super_admin = ADMIN_ROLES.with(|roles| {
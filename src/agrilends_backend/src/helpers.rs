@@ -37,6 +37,21 @@ pub fn validate_nft_metadata(metadata: &Vec<(String, MetadataValue)>) -> Result<
                     has_description = true;
                 }
             },
+            "rwa:receipt_expiry" => {
+                match value {
+                    MetadataValue::Nat(expiry) if *expiry > 0 => {},
+                    _ => return Err("Receipt expiry must be a timestamp in nanoseconds".to_string()),
+                }
+            },
+            "rwa:region" => {
+                match value {
+                    MetadataValue::Text(region) if is_region_allowed(region) => {},
+                    MetadataValue::Text(region) => {
+                        return Err(format!("Region '{}' is not on the region allowlist", region));
+                    },
+                    _ => return Err("Invalid region format".to_string()),
+                }
+            },
             _ => {}
         }
     }
@@ -149,10 +164,63 @@ pub fn check_rate_limit_with_operation(caller: &Principal, operation: &str) -> b
     })
 }
 
+// Resources (principals, loans, deposit transactions) held by update calls that are awaiting
+// an inter-canister call. Heap only: an upgrade cannot happen while a call is in flight.
+thread_local! {
+    static IN_FLIGHT_RESOURCES: RefCell<std::collections::HashSet<String>> = RefCell::new(std::collections::HashSet::new());
+}
+
+/// Exclusive hold on a resource across the awaits of one update call. Dropping the guard
+/// releases it, which also happens when a call traps after an await: the system cleans up
+/// the call's future and its locals are dropped.
+pub struct ResourceGuard {
+    resource: Option<String>,
+}
+
+impl ResourceGuard {
+    /// Take `resource`, or fail with an "operation in progress" error if another call holds it.
+    /// With the `reentrancy_guard_enabled` parameter off, returns a guard that holds nothing.
+    pub fn acquire(resource: String) -> Result<Self, String> {
+        let enabled = crate::governance::get_protocol_parameter("reentrancy_guard_enabled".to_string())
+            .map(|p| p.current_value)
+            .unwrap_or(1);
+        if enabled == 0 {
+            return Ok(ResourceGuard { resource: None });
+        }
+
+        IN_FLIGHT_RESOURCES.with(|resources| {
+            if !resources.borrow_mut().insert(resource.clone()) {
+                return Err(format!("Operation in progress for {}. Please retry shortly.", resource));
+            }
+            Ok(ResourceGuard { resource: Some(resource) })
+        })
+    }
+}
+
+impl Drop for ResourceGuard {
+    fn drop(&mut self) {
+        if let Some(resource) = self.resource.take() {
+            IN_FLIGHT_RESOURCES.with(|resources| {
+                resources.borrow_mut().remove(&resource);
+            });
+        }
+    }
+}
+
+/// Whether an update call currently holds `resource`
+pub fn is_resource_in_flight(resource: &str) -> bool {
+    IN_FLIGHT_RESOURCES.with(|resources| resources.borrow().contains(resource))
+}
+
+/// Number of calls currently holding a resource guard across a ledger await
+pub fn in_flight_resource_count() -> u64 {
+    IN_FLIGHT_RESOURCES.with(|resources| resources.borrow().len() as u64)
+}
+
 /// Extract metadata values for collateral record
-pub fn extract_metadata_values(metadata: &Vec<(String, MetadataValue)>) -> (String, u64, String) {
+pub fn extract_metadata_values(metadata: &Vec<(String, MetadataValue)>) -> (String, Idr, String) {
     let mut legal_doc_hash = String::new();
-    let mut valuation_idr = 0u64;
+    let mut valuation_idr = Idr(0);
     let mut asset_description = String::new();
     
     for (key, value) in metadata {
@@ -164,7 +232,7 @@ pub fn extract_metadata_values(metadata: &Vec<(String, MetadataValue)>) -> (Stri
             },
             "rwa:valuation_idr" => {
                 if let MetadataValue::Nat(val) = value {
-                    valuation_idr = *val;
+                    valuation_idr = Idr(*val);
                 }
             },
             "rwa:asset_description" => {
@@ -179,6 +247,40 @@ pub fn extract_metadata_values(metadata: &Vec<(String, MetadataValue)>) -> (Stri
     (legal_doc_hash, valuation_idr, asset_description)
 }
 
+/// Warehouse receipt expiry (nanoseconds) from NFT metadata, if the receipt has one
+pub fn get_receipt_expiry_from_metadata(metadata: &Vec<(String, MetadataValue)>) -> Option<u64> {
+    metadata.iter().find_map(|(key, value)| match (key.as_str(), value) {
+        ("rwa:receipt_expiry", MetadataValue::Nat(expiry)) => Some(*expiry),
+        _ => None,
+    })
+}
+
+/// Governance `receipt_expiry_warning_days` in nanoseconds
+pub fn receipt_expiry_warning_window() -> u64 {
+    crate::governance::get_protocol_parameter("receipt_expiry_warning_days".to_string())
+        .map(|p| p.current_value)
+        .unwrap_or(14)
+        .saturating_mul(24 * 60 * 60 * 1_000_000_000)
+}
+
+/// Classify a receipt expiry at `now`; expiring soon means within `warning_window` of it
+pub fn receipt_expiry_status(expiry: Option<u64>, now: u64, warning_window: u64) -> ReceiptExpiryStatus {
+    match expiry {
+        None => ReceiptExpiryStatus::Untracked,
+        Some(expiry) if expiry <= now => ReceiptExpiryStatus::Expired,
+        Some(expiry) if expiry - now <= warning_window => ReceiptExpiryStatus::ExpiringSoon,
+        Some(_) => ReceiptExpiryStatus::Current,
+    }
+}
+
+/// Collateral with an expired warehouse receipt cannot be borrowed against until re-attested
+pub fn ensure_receipt_not_expired(metadata: &Vec<(String, MetadataValue)>, now: u64) -> Result<(), String> {
+    if receipt_expiry_status(get_receipt_expiry_from_metadata(metadata), now, 0) == ReceiptExpiryStatus::Expired {
+        return Err("The collateral's warehouse receipt has expired. Have it re-attested before borrowing against it".to_string());
+    }
+    Ok(())
+}
+
 /// Validate SHA-256 hash format
 pub fn validate_sha256_hash(hash: &str) -> bool {
     hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())
@@ -273,6 +375,86 @@ pub fn log_liquidation_audit(action: &str, loan_id: u64, borrower: Principal, co
     );
 }
 
+pub fn log_investor_data_access(accessor: Principal, target: Option<Principal>, endpoint: &str, tier: Option<crate::types::InvestorDataAccessTier>, success: bool) {
+    use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+
+    let target_label = target.map(|p| p.to_text()).unwrap_or_else(|| "all_investors".to_string());
+    let audit_details = AuditDetails {
+        description: format!("Investor data access via {} by {} targeting {}", endpoint, accessor.to_text(), target_label),
+        entity_type: Some("investor_balance".to_string()),
+        entity_id: Some(target_label),
+        before_state: None,
+        after_state: None,
+        affected_principals: target.into_iter().collect(),
+        metadata: vec![
+            ("accessor".to_string(), accessor.to_text()),
+            ("endpoint".to_string(), endpoint.to_string()),
+            ("tier".to_string(), tier.map(|t| format!("{:?}", t)).unwrap_or_else(|| "None".to_string())),
+        ],
+        ..Default::default()
+    };
+
+    let result = AuditResult {
+        success,
+        error_code: if success { None } else { Some("UNAUTHORIZED".to_string()) },
+        error_message: None,
+        execution_time_ms: None,
+        gas_used: None,
+        cycles_consumed: None,
+        memory_used_bytes: None,
+        warning_flags: vec![],
+    };
+
+    let level = if success { AuditEventLevel::Info } else { AuditEventLevel::Warning };
+
+    log_audit_enhanced(
+        AuditCategory::Compliance,
+        "INVESTOR_DATA_ACCESS".to_string(),
+        level,
+        audit_details,
+        result,
+        None,
+    );
+}
+
+/// Repair any ID counter left at or below an existing key and log a warning for each.
+/// Returns the number of counters repaired.
+pub fn verify_id_counters_after_upgrade() -> usize {
+    use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+
+    let repairs = repair_id_counters();
+    for repair in &repairs {
+        log_audit_enhanced(
+            AuditCategory::Maintenance,
+            "ID_COUNTER_REPAIRED".to_string(),
+            AuditEventLevel::Warning,
+            AuditDetails {
+                description: format!(
+                    "{:?} counter was at {} with ID {} already in use; moved to {}",
+                    repair.counter, repair.previous_next_id, repair.max_existing_id, repair.repaired_next_id
+                ),
+                entity_type: Some("id_counter".to_string()),
+                entity_id: Some(format!("{:?}", repair.counter)),
+                before_state: Some(repair.previous_next_id.to_string()),
+                after_state: Some(repair.repaired_next_id.to_string()),
+                ..Default::default()
+            },
+            AuditResult {
+                success: true,
+                error_code: None,
+                error_message: None,
+                execution_time_ms: None,
+                gas_used: None,
+                cycles_consumed: None,
+                memory_used_bytes: None,
+                warning_flags: vec!["ID_COUNTER_REPAIR".to_string()],
+            },
+            None,
+        );
+    }
+    repairs.len()
+}
+
 /// Get canister configuration
 pub fn get_canister_config() -> CanisterConfig {
     get_config()
@@ -301,14 +483,209 @@ pub fn remove_admin(admin: Principal) -> Result<(), String> {
     Ok(())
 }
 
-/// Calculate loan health ratio (collateral value vs debt)
+/// Resolve the commodity type backing a loan from its collateral NFT metadata
+pub fn get_loan_commodity(loan: &Loan) -> Option<String> {
+    get_nft_data(loan.nft_id)
+        .and_then(|nft| crate::loan_lifecycle::extract_commodity_info_from_metadata(&nft.metadata).ok())
+        .map(|info| info.commodity_type)
+}
+
+/// Outstanding collateral value (base units of `asset`) per commodity across approved and active
+/// loans in that asset
+pub fn get_commodity_exposures(asset: &Asset) -> Vec<(String, u64)> {
+    let mut exposures: std::collections::BTreeMap<String, u64> = std::collections::BTreeMap::new();
+    for loan in get_all_loans_data() {
+        if !matches!(loan.status, LoanStatus::Approved | LoanStatus::Active) || loan.asset() != *asset {
+            continue;
+        }
+        let commodity = get_loan_commodity(&loan)
+            .map(|c| c.to_lowercase())
+            .unwrap_or_else(|| "unknown".to_string());
+        *exposures.entry(commodity).or_insert(0) += loan.collateral_value_btc;
+    }
+    exposures.into_iter().collect()
+}
+
+/// Normalized region tag from NFT metadata, if present
+pub fn get_region_from_metadata(metadata: &Vec<(String, MetadataValue)>) -> Option<String> {
+    metadata.iter().find_map(|(key, value)| match (key.as_str(), value) {
+        ("rwa:region", MetadataValue::Text(region)) if !region.trim().is_empty() => {
+            Some(region.trim().to_lowercase())
+        },
+        _ => None,
+    })
+}
+
+/// A region is allowed once governance has configured an exposure cap for it
+pub fn is_region_allowed(region: &str) -> bool {
+    get_protocol_parameters().region_exposure_caps()
+        .contains_key(&region.trim().to_lowercase())
+}
+
+/// Whether governance has closed a commodity to new loans
+pub fn is_commodity_paused(commodity: &str) -> bool {
+    get_protocol_parameters().paused_commodities
+        .map_or(false, |paused| paused.contains(&commodity.trim().to_lowercase()))
+}
+
+/// Reject a new loan against a commodity governance has paused
+pub fn ensure_commodity_not_paused(commodity: &str) -> Result<(), String> {
+    if is_commodity_paused(commodity) {
+        return Err(format!(
+            "New loans against {} are paused by governance. Existing loans are not affected",
+            commodity
+        ));
+    }
+    Ok(())
+}
+
+/// Outstanding principal (base units of `asset`) per region across approved and active loans
+/// in that asset
+pub fn get_regional_outstanding(asset: &Asset) -> Vec<(String, u64)> {
+    let mut exposures: std::collections::BTreeMap<String, u64> = std::collections::BTreeMap::new();
+    for loan in get_all_loans_data() {
+        if !matches!(loan.status, LoanStatus::Approved | LoanStatus::Active) || loan.asset() != *asset {
+            continue;
+        }
+        let region = loan.region.clone().unwrap_or_else(|| "unknown".to_string());
+        *exposures.entry(region).or_insert(0) += loan.amount_approved.saturating_sub(loan.total_repaid);
+    }
+    exposures.into_iter().collect()
+}
+
+/// Per-region exposure, default rate and average health ratio for a set of loans
+pub fn build_regional_exposure(loans: &[Loan]) -> Vec<RegionalExposure> {
+    let mut by_region: std::collections::BTreeMap<String, Vec<&Loan>> = std::collections::BTreeMap::new();
+    for loan in loans {
+        let region = loan.region.clone().unwrap_or_else(|| "unknown".to_string());
+        by_region.entry(region).or_insert_with(Vec::new).push(loan);
+    }
+
+    let total_outstanding: u128 = loans.iter()
+        .filter(|loan| matches!(loan.status, LoanStatus::Approved | LoanStatus::Active))
+        .map(|loan| loan.amount_approved.saturating_sub(loan.total_repaid) as u128)
+        .sum();
+
+    by_region.into_iter()
+        .filter_map(|(region, loans)| {
+            let outstanding: Vec<&&Loan> = loans.iter()
+                .filter(|loan| matches!(loan.status, LoanStatus::Approved | LoanStatus::Active))
+                .collect();
+            let funded = loans.iter()
+                .filter(|loan| matches!(loan.status, LoanStatus::Active | LoanStatus::Repaid | LoanStatus::Defaulted))
+                .count();
+            if outstanding.is_empty() && funded == 0 {
+                return None;
+            }
+            let defaulted = loans.iter().filter(|loan| loan.status == LoanStatus::Defaulted).count();
+            let outstanding_amount: u64 = outstanding.iter()
+                .map(|loan| loan.amount_approved.saturating_sub(loan.total_repaid))
+                .sum();
+            let health_ratios: Vec<f64> = loans.iter()
+                .filter(|loan| loan.status == LoanStatus::Active)
+                .filter_map(|loan| calculate_loan_health_ratio(loan).ok())
+                .filter(|ratio| ratio.is_finite())
+                .collect();
+
+            Some(RegionalExposure {
+                region,
+                outstanding_amount,
+                loan_count: outstanding.len() as u64,
+                default_rate: if funded > 0 { defaulted as f64 / funded as f64 * 100.0 } else { 0.0 },
+                average_health_ratio: if health_ratios.is_empty() {
+                    0.0
+                } else {
+                    health_ratios.iter().sum::<f64>() / health_ratios.len() as f64
+                },
+                share_bps: if total_outstanding > 0 {
+                    ((outstanding_amount as u128 * 10_000) / total_outstanding) as u64
+                } else {
+                    0
+                },
+            })
+        })
+        .collect()
+}
+
+/// Share of the book (basis points) a commodity would hold after adding `additional` collateral
+pub fn commodity_share_after_bps(exposures: &[(String, u64)], commodity: &str, additional: u64) -> u64 {
+    let commodity = commodity.to_lowercase();
+    let total: u128 = exposures.iter().map(|(_, amount)| *amount as u128).sum::<u128>() + additional as u128;
+    if total == 0 {
+        return 0;
+    }
+    let current: u128 = exposures.iter()
+        .filter(|(name, _)| *name == commodity)
+        .map(|(_, amount)| *amount as u128)
+        .sum();
+    (((current + additional as u128) * 10_000) / total) as u64
+}
+
+/// Whether a concentration cap binds on this book. Any loan on an empty book is 100% of it,
+/// so caps only apply once something is outstanding.
+pub fn exposure_cap_applies(exposures: &[(String, u64)]) -> bool {
+    exposures.iter().any(|(_, amount)| *amount > 0)
+}
+
+/// Get the LTV ratio (percentage) for a commodity, falling back to the global default
+pub fn get_commodity_ltv_ratio(commodity: &str) -> u64 {
+    let params = get_protocol_parameters();
+    params.commodity_ltv_overrides()
+        .get(&commodity.to_lowercase())
+        .copied()
+        .unwrap_or(params.loan_to_value_ratio)
+}
+
+/// Collateral floor until governance sets `min_collateral_value_idr`
+pub const DEFAULT_MIN_COLLATERAL_VALUE_IDR: u64 = 1_000_000;
+
+/// Governance floor on collateral valuation, in IDR
+pub fn get_min_collateral_value_idr() -> u64 {
+    crate::governance::get_protocol_parameter("min_collateral_value_idr".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_MIN_COLLATERAL_VALUE_IDR)
+}
+
+/// Reject collateral valued below the floor. A valuation exactly at the floor is accepted.
+pub fn check_min_collateral_value(valuation_idr: u64, min_collateral_value_idr: u64) -> Result<(), CollateralValueError> {
+    if valuation_idr < min_collateral_value_idr {
+        return Err(CollateralValueError::BelowMinimum { valuation_idr, min_collateral_value_idr });
+    }
+    Ok(())
+}
+
+/// Floor check for new mints and originations only. Loans already backed by an NFT that falls
+/// below a later, tighter floor are grandfathered: health and liquidation never consult it.
+pub fn ensure_min_collateral_value(valuation: Idr) -> Result<(), CollateralValueError> {
+    check_min_collateral_value(valuation.0, get_min_collateral_value_idr())
+}
+
+/// Scale a raw collateral/debt ratio by the commodity's LTV relative to the global LTV.
+/// A commodity lent at half the global LTV must stay twice as collateralized to look
+/// equally healthy, so the liquidation threshold scales proportionally per commodity.
+pub fn risk_adjust_health_ratio(raw_ratio: f64, commodity: Option<&str>) -> f64 {
+    let global_ltv = get_protocol_parameters().loan_to_value_ratio;
+    let commodity_ltv = match commodity {
+        Some(commodity) => get_commodity_ltv_ratio(commodity),
+        None => global_ltv,
+    };
+    
+    if global_ltv == 0 || commodity_ltv == global_ltv {
+        return raw_ratio;
+    }
+    
+    raw_ratio * (commodity_ltv as f64) / (global_ltv as f64)
+}
+
+/// Calculate loan health ratio (collateral value vs debt), adjusted for commodity-specific LTV
 pub fn calculate_loan_health_ratio(loan: &Loan) -> Result<f64, String> {
     if loan.amount_approved == 0 {
         return Ok(f64::INFINITY);
     }
     
-    let health_ratio = (loan.collateral_value_btc as f64) / (loan.amount_approved as f64);
-    Ok(health_ratio)
+    let raw_ratio = (loan.collateral_value_btc as f64) / (loan.amount_approved as f64);
+    let commodity = get_loan_commodity(loan);
+    Ok(risk_adjust_health_ratio(raw_ratio, commodity.as_deref()))
 }
 
 /// Check if loan is at risk of liquidation
@@ -365,19 +742,203 @@ pub fn get_active_loans_count() -> u64 {
 }
 
 pub fn get_memory_usage() -> u64 {
-    // Placeholder - would need actual memory calculation
-    // In real implementation, use ic_cdk::api::canister_status
-    0
+    get_memory_breakdown().total_bytes
+}
+
+// ========== CKBTC PRINCIPALS ==========
+
+pub const MAINNET_CKBTC_LEDGER_PRINCIPAL: &str = "mxzaz-hqaaa-aaaar-qaada-cai";
+pub const MAINNET_CKBTC_MINTER_PRINCIPAL: &str = "mqygn-kiaaa-aaaar-qaadq-cai";
+
+pub fn mainnet_ckbtc_ledger() -> Principal {
+    Principal::from_text(MAINNET_CKBTC_LEDGER_PRINCIPAL).unwrap()
+}
+
+pub fn mainnet_ckbtc_minter() -> Principal {
+    Principal::from_text(MAINNET_CKBTC_MINTER_PRINCIPAL).unwrap()
+}
+
+/// Configured ckBTC ledger, falling back to mainnet
+pub fn ckbtc_ledger_principal() -> Principal {
+    get_canister_config().ckbtc_ledger_principal.unwrap_or_else(mainnet_ckbtc_ledger)
+}
+
+/// Configured ckBTC minter, falling back to mainnet
+pub fn ckbtc_minter_principal() -> Principal {
+    get_canister_config().ckbtc_minter_principal.unwrap_or_else(mainnet_ckbtc_minter)
+}
+
+pub const MAINNET_ICP_LEDGER_PRINCIPAL: &str = "ryjl3-tyaaa-aaaaa-aaaba-cai";
+
+pub fn mainnet_icp_ledger() -> Principal {
+    Principal::from_text(MAINNET_ICP_LEDGER_PRINCIPAL).unwrap()
+}
+
+/// Ledger holding `asset`, falling back to mainnet. Both ledgers speak ICRC-1/ICRC-2.
+pub fn ledger_principal_for(asset: &Asset) -> Principal {
+    match asset {
+        Asset::CkBtc => ckbtc_ledger_principal(),
+        Asset::Icp => get_canister_config().icp_ledger_principal.unwrap_or_else(mainnet_icp_ledger),
+    }
+}
+
+/// ICRC-1 ledgers reject transfers whose memo is longer than this
+pub const MAX_LEDGER_MEMO_BYTES: usize = 32;
+
+/// `text` as a transfer memo, cut on a char boundary to fit `MAX_LEDGER_MEMO_BYTES`
+pub fn ledger_memo(text: &str) -> Vec<u8> {
+    let mut end = text.len().min(MAX_LEDGER_MEMO_BYTES);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.as_bytes()[..end].to_vec()
+}
+
+// ========== AMOUNT FORMATTING AND ROUNDING ==========
+
+/// A share rounded by `round_satoshi` is never more than this far from its exact value, so
+/// splitting an amount into n rounded shares can be off by at most n times this
+pub const ROUNDING_DUST_BOUND_SATOSHI: u64 = 1;
+
+/// Configured rounding for fee and yield shares
+pub fn rounding_mode() -> RoundingMode {
+    get_canister_config().rounding_mode.unwrap_or_default()
+}
+
+/// `numerator / denominator` in base units, rounded by `mode` and capped at u64::MAX.
+/// A zero denominator yields zero.
+pub fn round_satoshi(numerator: u128, denominator: u128, mode: RoundingMode) -> u64 {
+    if denominator == 0 {
+        return 0;
+    }
+    let quotient = numerator / denominator;
+    let remainder = numerator % denominator;
+    let round_up = match mode {
+        RoundingMode::Down => false,
+        RoundingMode::Up => remainder > 0,
+        RoundingMode::HalfUp => remainder * 2 >= denominator,
+        RoundingMode::HalfEven => {
+            remainder * 2 > denominator || (remainder * 2 == denominator && quotient % 2 == 1)
+        }
+    };
+    u64::try_from(quotient + round_up as u128).unwrap_or(u64::MAX)
+}
+
+/// `amount * rate / scale` under the configured rounding mode, e.g. a basis-point fee
+pub fn rounded_share(amount: u64, rate: u64, scale: u64) -> u64 {
+    rounded_share_with(amount, rate, scale, rounding_mode())
+}
+
+/// `rounded_share` under a given mode; loops read `rounding_mode()` once and pass it here
+pub fn rounded_share_with(amount: u64, rate: u64, scale: u64, mode: RoundingMode) -> u64 {
+    round_satoshi(amount as u128 * rate as u128, scale as u128, mode)
+}
+
+/// Canonical display of a base-unit amount: whole tokens with all eight decimals and the
+/// asset symbol, e.g. "0.00150000 ckBTC"
+pub fn format_amount(value: u64, asset: Asset) -> String {
+    format!("{}.{:08} {}", value / SATOSHI_PER_BTC, value % SATOSHI_PER_BTC, asset.symbol())
+}
+
+// ========== DEPENDENCY HEALTH ==========
+
+pub const ORACLE_DEPENDENCY: &str = "oracle";
+pub const CKBTC_LEDGER_DEPENDENCY: &str = "ckbtc_ledger";
+
+const DEPENDENCY_LATENCY_WINDOW: usize = 50; // Recent calls kept for p95
+const DEPENDENCY_DOWN_AFTER_FAILURES: u32 = 3;
+const ORACLE_DEGRADED_LATENCY_MS: u64 = 10_000; // HTTPS outcalls go through consensus
+const CKBTC_DEGRADED_LATENCY_MS: u64 = 5_000;
+
+#[derive(Clone, Debug, Default)]
+pub struct DependencyCallStats {
+    pub last_success: Option<u64>,
+    pub recent_latencies_ms: Vec<u64>,
+    pub consecutive_failures: u32,
+}
+
+thread_local! {
+    static DEPENDENCY_STATS: RefCell<std::collections::HashMap<String, DependencyCallStats>> =
+        RefCell::new(std::collections::HashMap::new());
+}
+
+/// Record the outcome and round-trip latency of a call to an external dependency
+pub fn record_dependency_call(name: &str, latency_ms: u64, success: bool) {
+    DEPENDENCY_STATS.with(|stats| {
+        let mut stats = stats.borrow_mut();
+        let entry = stats.entry(name.to_string()).or_default();
+        
+        entry.recent_latencies_ms.push(latency_ms);
+        if entry.recent_latencies_ms.len() > DEPENDENCY_LATENCY_WINDOW {
+            entry.recent_latencies_ms.remove(0);
+        }
+        
+        if success {
+            entry.last_success = Some(time());
+            entry.consecutive_failures = 0;
+        } else {
+            entry.consecutive_failures += 1;
+        }
+    });
+}
+
+/// Milliseconds elapsed since a `time()` reading
+pub fn elapsed_ms_since(start: u64) -> u64 {
+    time().saturating_sub(start) / 1_000_000
+}
+
+/// 95th percentile of the given latencies (nearest-rank)
+pub fn calculate_p95_latency(latencies_ms: &[u64]) -> u64 {
+    if latencies_ms.is_empty() {
+        return 0;
+    }
+    let mut sorted = latencies_ms.to_vec();
+    sorted.sort_unstable();
+    let rank = (sorted.len() * 95).div_ceil(100);
+    sorted[rank.max(1) - 1]
+}
+
+/// Down after repeated failures; Degraded when slow or recently failing
+pub fn classify_dependency_status(stats: &DependencyCallStats, degraded_latency_ms: u64) -> DependencyStatus {
+    if stats.consecutive_failures >= DEPENDENCY_DOWN_AFTER_FAILURES {
+        DependencyStatus::Down
+    } else if stats.consecutive_failures > 0
+        || calculate_p95_latency(&stats.recent_latencies_ms) > degraded_latency_ms
+    {
+        DependencyStatus::Degraded
+    } else {
+        DependencyStatus::Up
+    }
+}
+
+fn dependency_health(name: &str, degraded_latency_ms: u64) -> DependencyHealth {
+    let stats = DEPENDENCY_STATS.with(|stats| stats.borrow().get(name).cloned().unwrap_or_default());
+    
+    DependencyHealth {
+        name: name.to_string(),
+        status: classify_dependency_status(&stats, degraded_latency_ms),
+        last_success: stats.last_success,
+        p95_latency_ms: calculate_p95_latency(&stats.recent_latencies_ms),
+        consecutive_failures: stats.consecutive_failures,
+    }
+}
+
+/// Health of every tracked external dependency
+pub fn get_all_dependency_health() -> Vec<DependencyHealth> {
+    vec![
+        dependency_health(ORACLE_DEPENDENCY, ORACLE_DEGRADED_LATENCY_MS),
+        dependency_health(CKBTC_LEDGER_DEPENDENCY, CKBTC_DEGRADED_LATENCY_MS),
+    ]
 }
 
 pub fn check_oracle_health() -> bool {
-    // Check if oracle prices are recent and available
-    true
+    // Oracle is usable unless price fetches keep failing
+    dependency_health(ORACLE_DEPENDENCY, ORACLE_DEGRADED_LATENCY_MS).status != DependencyStatus::Down
 }
 
 pub fn check_ckbtc_health() -> bool {
-    // Check if ckBTC integration is working
-    true
+    // ckBTC integration is usable unless ledger calls keep failing
+    dependency_health(CKBTC_LEDGER_DEPENDENCY, CKBTC_DEGRADED_LATENCY_MS).status != DependencyStatus::Down
 }
 
 pub fn get_last_heartbeat_time() -> u64 {
@@ -395,6 +956,55 @@ pub fn is_in_maintenance_mode() -> bool {
     config.maintenance_mode
 }
 
+/// Endpoints guarded by `ensure_operation_available`, and so the names a scoped maintenance
+/// window may list
+pub const MAINTENANCE_GUARDED_OPERATIONS: &[&str] = &[
+    "accept_loan_offer",
+    "deposit_liquidity",
+    "refinance_loan",
+    "submit_loan_application",
+    "sweep_dust_balance",
+    "withdraw_liquidity",
+];
+
+/// Repayments stay open during maintenance so borrowers can always reduce their debt
+pub const MAINTENANCE_EXEMPT_OPERATIONS: &[&str] = &["repay_loan", "repay_loan_via_allowance"];
+
+/// Check the operation names given to a scoped maintenance window
+pub fn validate_maintenance_operations(operations: &[String]) -> Result<(), String> {
+    for operation in operations {
+        if MAINTENANCE_EXEMPT_OPERATIONS.contains(&operation.as_str()) {
+            return Err(format!("{} stays open during maintenance and cannot be paused", operation));
+        }
+        if !MAINTENANCE_GUARDED_OPERATIONS.contains(&operation.as_str()) {
+            return Err(format!(
+                "Unknown operation {}. Operations that can be paused: {}",
+                operation,
+                MAINTENANCE_GUARDED_OPERATIONS.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Per-operation maintenance guard. Scheduled windows are checked by time, so scoped windows
+/// take effect even if the heartbeat has not yet marked them active.
+pub fn ensure_operation_available(operation: &str) -> Result<(), String> {
+    let now = time();
+    if let Some(window) = get_pending_maintenance_windows()
+        .into_iter()
+        .find(|window| window.blocks_operation(operation, now))
+    {
+        return Err(format!("{} is unavailable during scheduled maintenance: {}", operation, window.message));
+    }
+
+    if is_in_maintenance_mode() {
+        return Err("System is in maintenance mode. Please try again later.".to_string());
+    }
+
+    Ok(())
+}
+
 pub fn get_emergency_stop_status() -> bool {
     let config = get_canister_config();
     config.emergency_stop
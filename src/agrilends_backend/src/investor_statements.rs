@@ -0,0 +1,239 @@
+// ========== INVESTOR STATEMENTS MODULE ==========
+// Calendar-year statements of an investor's ckBTC and ICP pool activity for tax and accounting.
+// Deposits, withdrawals and fees come from the investor's own records; yield and losses are
+// the investor's pro-rata share of each daily ckBTC pool performance snapshot in the year,
+// counting only funds that had passed the minimum holding period. Withdrawals made by lot also
+// get the yield and losses realized on each lot drawn. ICP balances and activity are reported
+// alongside; the ICP pool has no performance snapshots to derive yield from.
+
+use candid::Principal;
+use ic_cdk::api::time;
+use ic_cdk_macros::query;
+
+use crate::types::*;
+use crate::storage::{get_asset_investor_balance, get_performance_snapshots_in_range};
+use crate::liquidity_management::{
+    authorize_investor_data_access, holding_period_status, min_holding_period_nanos, realized_lot_gains,
+};
+
+const MIN_STATEMENT_YEAR: u32 = 1970;
+const MAX_STATEMENT_YEAR: u32 = 9999;
+
+const STATEMENT_CSV_HEADER: &str = "Timestamp,Type,Amount,Fee,Reference\n";
+
+/// [1 January of `year`, 1 January of the next year) in UTC nanoseconds
+pub fn statement_year_bounds(year: u32) -> Result<(u64, u64), String> {
+    if !(MIN_STATEMENT_YEAR..=MAX_STATEMENT_YEAR).contains(&year) {
+        return Err(format!("Year must be between {} and {}", MIN_STATEMENT_YEAR, MAX_STATEMENT_YEAR));
+    }
+    Ok((
+        crate::advanced_analytics::month_start_nanos(year as i64, 1),
+        crate::advanced_analytics::month_start_nanos(year as i64 + 1, 1),
+    ))
+}
+
+/// Balance implied by the deposit and withdrawal records made before `at`
+pub fn balance_at(balance: &InvestorBalance, at: u64) -> u64 {
+    let deposited: u64 = balance.deposits.iter()
+        .filter(|deposit| deposit.timestamp < at)
+        .map(|deposit| deposit.amount)
+        .sum();
+    let withdrawn: u64 = balance.withdrawals.iter()
+        .filter(|withdrawal| withdrawal.timestamp < at)
+        .map(|withdrawal| withdrawal.amount)
+        .sum();
+    deposited.saturating_sub(withdrawn)
+}
+
+/// Opening and closing balances and the deposits, withdrawals and fees recorded in
+/// [period_start, period_end)
+pub fn build_asset_activity(asset: Asset, balance: &InvestorBalance, period_start: u64, period_end: u64) -> AssetStatementActivity {
+    let in_year = |timestamp: u64| timestamp >= period_start && timestamp < period_end;
+    let mut activity = AssetStatementActivity {
+        asset,
+        opening_balance: balance_at(balance, period_start),
+        closing_balance: balance_at(balance, period_end),
+        total_deposits: 0,
+        total_withdrawals: 0,
+        deposit_count: 0,
+        withdrawal_count: 0,
+        fees_paid: 0,
+    };
+    for deposit in balance.deposits.iter().filter(|deposit| in_year(deposit.timestamp)) {
+        activity.total_deposits = activity.total_deposits.saturating_add(deposit.amount);
+        activity.fees_paid = activity.fees_paid.saturating_add(deposit.fee.unwrap_or(0));
+        activity.deposit_count += 1;
+    }
+    for withdrawal in balance.withdrawals.iter().filter(|withdrawal| in_year(withdrawal.timestamp)) {
+        activity.total_withdrawals = activity.total_withdrawals.saturating_add(withdrawal.amount);
+        activity.fees_paid = activity.fees_paid.saturating_add(withdrawal.fee.unwrap_or(0));
+        activity.withdrawal_count += 1;
+    }
+    activity
+}
+
+/// Statement for `investor` over `year`. An investor with no records, or none in the year,
+/// gets a statement whose activity totals are zero.
+pub fn build_annual_statement(
+    investor: Principal,
+    balance: Option<&InvestorBalance>,
+    icp_balance: Option<&InvestorBalance>,
+    snapshots: &[PerformanceSnapshot],
+    min_holding_period: u64,
+    year: u32,
+    now: u64,
+) -> Result<AnnualStatement, String> {
+    let (period_start, period_end) = statement_year_bounds(year)?;
+    let mut statement = AnnualStatement {
+        investor,
+        year,
+        period_start,
+        period_end,
+        opening_balance: 0,
+        closing_balance: 0,
+        total_deposits: 0,
+        total_withdrawals: 0,
+        deposit_count: 0,
+        withdrawal_count: 0,
+        fees_paid: 0,
+        yield_earned: 0,
+        losses_allocated: 0,
+        net_realized_return: 0,
+        realized_lot_gains: Vec::new(),
+        generated_at: now,
+        icp_activity: icp_balance.map(|icp_balance| build_asset_activity(Asset::Icp, icp_balance, period_start, period_end)),
+    };
+    let balance = match balance {
+        Some(balance) => balance,
+        None => return Ok(statement),
+    };
+    let in_year = |timestamp: u64| timestamp >= period_start && timestamp < period_end;
+
+    let activity = build_asset_activity(Asset::CkBtc, balance, period_start, period_end);
+    statement.opening_balance = activity.opening_balance;
+    statement.closing_balance = activity.closing_balance;
+    statement.total_deposits = activity.total_deposits;
+    statement.total_withdrawals = activity.total_withdrawals;
+    statement.deposit_count = activity.deposit_count;
+    statement.withdrawal_count = activity.withdrawal_count;
+    statement.fees_paid = activity.fees_paid;
+
+    // Each snapshot's interest and losses are shared by what the investor held when its period
+    // began; deposits still inside the holding period earn no share
+    let rounding = crate::helpers::rounding_mode();
+    for snapshot in snapshots.iter().filter(|snapshot| in_year(snapshot.captured_at)) {
+        if snapshot.total_liquidity == 0 {
+            continue;
+        }
+        let (held, _, _) = holding_period_status(
+            balance,
+            balance_at(balance, snapshot.period_start),
+            snapshot.period_start,
+            min_holding_period,
+        );
+        let share = |amount: u64| crate::helpers::rounded_share_with(amount, held, snapshot.total_liquidity, rounding);
+        statement.yield_earned = statement.yield_earned.saturating_add(share(snapshot.period_interest));
+        statement.losses_allocated = statement.losses_allocated.saturating_add(share(snapshot.period_losses));
+    }
+
+    // Per-lot detail for withdrawals that named their lots (FIFO/LIFO)
+    statement.realized_lot_gains = realized_lot_gains(balance, snapshots, min_holding_period, period_start, period_end);
+
+    statement.net_realized_return = statement.yield_earned as i64
+        - statement.losses_allocated as i64
+        - statement.fees_paid as i64;
+    Ok(statement)
+}
+
+/// Each deposit and withdrawal in the statement year, oldest first, with `prefix` on the row type
+fn push_ledger_rows(csv_content: &mut String, statement: &AnnualStatement, balance: &InvestorBalance, prefix: &str) {
+    let in_year = |timestamp: u64| timestamp >= statement.period_start && timestamp < statement.period_end;
+    let mut rows: Vec<(u64, &str, u64, u64, u64)> = balance.deposits.iter()
+        .filter(|deposit| in_year(deposit.timestamp))
+        .map(|deposit| (deposit.timestamp, "DEPOSIT", deposit.amount, deposit.fee.unwrap_or(0), deposit.ckbtc_block_index))
+        .chain(balance.withdrawals.iter()
+            .filter(|withdrawal| in_year(withdrawal.timestamp))
+            .map(|withdrawal| (withdrawal.timestamp, "WITHDRAWAL", withdrawal.amount, withdrawal.fee.unwrap_or(0), withdrawal.ckbtc_block_index)))
+        .collect();
+    rows.sort_by_key(|row| row.0);
+    for (timestamp, kind, amount, fee, block_index) in rows {
+        csv_content.push_str(&format!("{},{}{},{},{},{}\n", timestamp, prefix, kind, amount, fee, block_index));
+    }
+}
+
+/// Ledger-style CSV: opening balance, each deposit and withdrawal in the year, then the
+/// yield, losses and closing balance; ICP activity follows as ICP_-prefixed rows
+pub fn build_statement_csv(
+    statement: &AnnualStatement,
+    balance: Option<&InvestorBalance>,
+    icp_balance: Option<&InvestorBalance>,
+) -> String {
+    let mut csv_content = String::from(STATEMENT_CSV_HEADER);
+    csv_content.push_str(&format!("{},OPENING_BALANCE,{},0,\n", statement.period_start, statement.opening_balance));
+
+    if let Some(balance) = balance {
+        push_ledger_rows(&mut csv_content, statement, balance, "");
+    }
+
+    let closing_at = statement.period_end.saturating_sub(1);
+    csv_content.push_str(&format!("{},YIELD,{},0,\n", closing_at, statement.yield_earned));
+    csv_content.push_str(&format!("{},LOSS,{},0,\n", closing_at, statement.losses_allocated));
+    csv_content.push_str(&format!("{},CLOSING_BALANCE,{},0,\n", closing_at, statement.closing_balance));
+
+    if let (Some(activity), Some(icp_balance)) = (&statement.icp_activity, icp_balance) {
+        csv_content.push_str(&format!("{},ICP_OPENING_BALANCE,{},0,\n", statement.period_start, activity.opening_balance));
+        push_ledger_rows(&mut csv_content, statement, icp_balance, "ICP_");
+        csv_content.push_str(&format!("{},ICP_CLOSING_BALANCE,{},0,\n", closing_at, activity.closing_balance));
+    }
+    csv_content
+}
+
+/// The caller's own statement, or another investor's for admins with operator access to investor data
+fn resolve_statement_investor(investor: Option<Principal>, endpoint: &str) -> Result<Principal, String> {
+    let caller = ic_cdk::caller();
+    match investor {
+        Some(investor) if investor != caller => {
+            authorize_investor_data_access(caller, Some(investor), endpoint, InvestorDataAccessTier::Operator)?;
+            Ok(investor)
+        }
+        _ => {
+            if caller == Principal::anonymous() {
+                return Err("Anonymous users cannot request statements".to_string());
+            }
+            Ok(caller)
+        }
+    }
+}
+
+/// The statement with the ckBTC and ICP balance records it was built from
+type StatementWithRecords = (AnnualStatement, Option<InvestorBalance>, Option<InvestorBalance>);
+
+fn statement_for(investor: Principal, year: u32) -> Result<StatementWithRecords, String> {
+    let (period_start, period_end) = statement_year_bounds(year)?;
+    let balance = get_asset_investor_balance(&Asset::CkBtc, investor);
+    let icp_balance = get_asset_investor_balance(&Asset::Icp, investor);
+    // Lots withdrawn this year may have been earning since an earlier year
+    let history_start = balance.as_ref().map_or(period_start, |balance| balance.first_deposit_at.min(period_start));
+    let snapshots = get_performance_snapshots_in_range(history_start, period_end.saturating_sub(1));
+    let statement = build_annual_statement(
+        investor, balance.as_ref(), icp_balance.as_ref(), &snapshots, min_holding_period_nanos(), year, time(),
+    )?;
+    Ok((statement, balance, icp_balance))
+}
+
+/// Year-end statement of deposits, withdrawals, fees and realized yield in the ckBTC pool,
+/// with the investor's ICP pool balances and activity.
+/// Pass `investor` to generate another investor's statement (admin support).
+#[query]
+pub fn get_investor_annual_statement(year: u32, investor: Option<Principal>) -> Result<AnnualStatement, String> {
+    let investor = resolve_statement_investor(investor, "get_investor_annual_statement")?;
+    statement_for(investor, year).map(|(statement, _, _)| statement)
+}
+
+/// `get_investor_annual_statement` as CSV, one row per ledger movement
+#[query]
+pub fn export_investor_statement_csv(year: u32, investor: Option<Principal>) -> Result<String, String> {
+    let investor = resolve_statement_investor(investor, "export_investor_statement_csv")?;
+    let (statement, balance, icp_balance) = statement_for(investor, year)?;
+    Ok(build_statement_csv(&statement, balance.as_ref(), icp_balance.as_ref()))
+}
@@ -24,6 +24,18 @@ mod ckbtc_integration;
 mod liquidity_management;
 mod oracle;          // Oracle module for production
 mod oracle_integration; // Oracle integration helper
+mod outbound_events; // Signed webhook outcalls for liquidation events
+mod operational_tags; // Operations labels on loans and investor accounts
+mod promo_campaigns; // Reduced-rate lending campaigns
+mod pool_performance; // Daily pool performance snapshots
+mod investor_statements; // Calendar-year investor statements
+mod feature_flags; // Runtime feature switches
+mod validation; // Shared guards for public update methods
+mod autopay; // Heartbeat collection of due installments via ICRC-2 allowances
+mod state_snapshot; // Versioned, hash-verified backups of critical state
+mod borrower_reputation; // Repayment reputation that adjusts terms on new loans
+mod notification_broadcast; // Targeted operator announcements delivered in batches
+mod schema_migration; // Versioned stable data migrations run on upgrade
 mod production_config;
 mod production_security;
 mod monitoring;
@@ -58,14 +70,15 @@ pub use rwa_nft::*;
 pub use types::{
     Account as TypesAccount, MetadataValue, TransferRequest, TransferResult, RWANFTData, RWANFTResult,
     CollateralStatus, CollateralRecord, NFTStats, StorageStats, AuditLog, CanisterConfig,
-    LoanStatus, Loan, LoanApplication, CommodityPrice, NFTMetadata, ProtocolParameters,
-    DisbursementRecord, RepaymentRecord, ProductionHealthStatus, CommodityPriceData,
+    LoanStatus, Loan, LoanApplication, RestructureProposal, RepaymentStructure, AmortizationRow, RestructureStatus, LoanRestructureRequest, UnderwritingState, ReviewNote, LoanApplicationReview, MaxBorrowable, BorrowLimitFactor, CommodityPrice, NFTMetadata, AttestationRecord, NFTAttestationHistory, ProtocolParameters, PricingMode, PriceSample, TwapPrice,
+    DisbursementRecord, RepaymentRecord, ProductionHealthStatus, MemoryBreakdown, DependencyHealth, DependencyStatus, CommodityPriceData,
     LiquidityPool, InvestorBalance, DepositRecord, WithdrawalRecord, ProcessedTransaction,
     PoolStats, InvestorTransactionHistory, PoolHealthMetrics, PoolConfiguration,
     Payment, PaymentType, PaymentBreakdown, LoanRepaymentSummary, RepaymentPlan, RepaymentResponse,
     LiquidationRecord, LiquidationReason, LiquidationSummary, LiquidationEligibilityCheck,
     LiquidationResult, LiquidationStatistics, ComprehensiveRepaymentAnalytics, LoanPerformanceMetrics,
-    BatchRepaymentRequest, BatchRepaymentResult, RepaymentStatistics, RepaymentForecast,
+    LiquidationAuction, LiquidationBid, AuctionSettlement, AuctionStatus,
+    BatchRepaymentRequest, BatchRepaymentResult, RepaymentStatistics, RepaymentForecast, EarlyRepaymentBenefits,
     // Oracle Types
     PriceFetchRecord, OracleConfig, OracleStatistics, PriceAlert, PriceThresholdType,
     // Treasury Management Types
@@ -87,12 +100,12 @@ pub use storage::{
     count_processed_transactions, get_pool_utilization_history, get_investor_count,
     get_active_investor_count, get_total_investor_deposits, get_total_investor_withdrawals,
     get_largest_investor_deposit, get_average_investor_deposit, get_pool_concentration_risk,
-    cleanup_old_processed_transactions, get_storage_statistics, get_storage_stats,
+    cleanup_old_processed_transactions, get_storage_statistics, get_storage_stats, get_memory_breakdown,
     store_commodity_price, get_stored_commodity_price, get_all_stored_commodity_prices,
     update_last_price_fetch, get_last_price_fetch, get_liquidity_pool, store_liquidity_pool,
     get_investor_balance_by_principal, store_investor_balance, get_all_investor_balances,
     is_transaction_processed, mark_transaction_processed, has_investor_deposited_before,
-    set_emergency_pause, is_emergency_paused, get_processed_transaction, remove_processed_transaction,
+    set_emergency_pause, is_emergency_paused, set_origination_pause, is_origination_paused, get_processed_transaction, remove_processed_transaction,
     next_nft_token_id, next_collateral_id, next_loan_id, next_disbursement_id, update_loan,
     update_price_fetch_failure, get_price_fetch_statistics
 };
@@ -100,8 +113,17 @@ pub use oracle::{
     fetch_commodity_price, get_commodity_price, admin_set_commodity_price, get_all_commodity_prices,
     is_price_stale, get_oracle_statistics, configure_oracle, get_oracle_config,
     add_price_alert, get_price_alerts, enable_emergency_mode, disable_emergency_mode,
-    oracle_health_check, heartbeat_price_update
+    oracle_health_check, heartbeat_price_update, get_commodity_twap,
+    subscribe_price_alert, get_my_price_alerts, cancel_price_alert
 };
+pub use outbound_events::{
+    configure_outbound_webhook, get_outbound_webhook_status, get_outbound_event_deliveries
+};
+pub use operational_tags::{add_tag, remove_tag, get_tags};
+pub use promo_campaigns::{create_promo_campaign, end_promo_campaign, get_active_promos, get_promo_usage};
+pub use pool_performance::get_performance_snapshots;
+pub use investor_statements::{get_investor_annual_statement, export_investor_statement_csv};
+pub use feature_flags::{set_feature_flag, get_feature_flags};
 pub use helpers::{
     validate_nft_metadata, init_admin_principals, set_loan_manager_principal, is_admin, is_loan_manager_canister,
     is_authorized_to_mint, check_rate_limit, extract_metadata_values, validate_sha256_hash, log_audit_action,
@@ -115,17 +137,24 @@ pub use loan_lifecycle::*;
 pub use loan_repayment::{
     repay_loan, get_loan_repayment_summary, get_repayment_plan, get_loan_payment_history,
     get_loan_repayment_records, check_repayment_eligibility, calculate_early_repayment_benefits,
+    retry_unrouted_prepayment_penalties,
     emergency_repayment, get_repayment_statistics, calculate_total_debt_with_interest,
-    calculate_payment_breakdown, get_comprehensive_repayment_analytics, calculate_loan_performance_metrics,
+    calculate_payment_breakdown, get_repayment_allocation_order, allocate_payment, get_comprehensive_repayment_analytics, calculate_loan_performance_metrics,
     process_batch_repayments, schedule_automatic_repayment, get_repayment_forecast,
-    collect_protocol_fees_from_repayment, validate_repayment_amount
+    collect_protocol_fees_from_repayment, validate_repayment_amount, get_amortization_schedule,
+    get_uncollected_fees, collect_missing_fees, reconcile_collected_fees
 };
 pub use liquidation::{
     trigger_liquidation, check_liquidation_eligibility, get_loans_eligible_for_liquidation,
     get_liquidation_record, get_all_liquidation_records, get_liquidation_statistics,
     trigger_bulk_liquidation, emergency_liquidation, automated_liquidation_check,
     get_liquidation_metrics, assess_liquidation_risk, get_loan_liquidation_history,
-    list_all_liquidations, LiquidationMetrics, LiquidationRiskAssessment, LiquidationStatistics
+    list_all_liquidations, LiquidationMetrics, LiquidationRiskAssessment, LiquidationStatistics,
+    start_liquidation_auction, place_liquidation_bid, finalize_auction, get_active_auctions,
+    get_liquidation_auction, get_borrower_surplus_balance, claim_liquidation_surplus, add_liquidation_bidder,
+    remove_liquidation_bidder, approve_liquidation_review, get_pending_liquidation_reviews,
+    project_liquidation_price, simulate_health_at_price, get_at_risk_loans_report, get_liquidation_settlement,
+    settle_offchain_liquidation
 };
 pub use governance::{
     create_proposal, vote_on_proposal, execute_proposal, set_protocol_parameter,
@@ -136,14 +165,19 @@ pub use governance::{
     set_multiple_protocol_parameters, get_protocol_parameters_by_category,
     validate_parameter_value, get_parameter_history, can_execute_proposal,
     get_proposals_by_status, get_active_admin_count, set_maintenance_mode,
-    get_system_status, initialize_super_admin, get_governance_dashboard
+    get_system_status, initialize_super_admin, get_governance_dashboard, get_commodity_ltv,
+    snapshot_protocol_parameters, list_parameter_snapshots, schedule_parameter_restore,
+    restore_protocol_parameters, create_action_proposal,
+    propose_loan_manager, cancel_loan_manager_proposal, accept_loan_manager_role,
+    get_loan_manager, get_pending_loan_manager
 };
 
 // Add dashboard support exports
 pub use dashboard_support::{
     get_farmer_dashboard, get_investor_dashboard, get_admin_dashboard, get_public_stats,
-    refresh_dashboard_cache, get_dashboard_status,
-    FarmerDashboardData, InvestorDashboardData, AdminDashboardData, PublicStats,
+    refresh_dashboard_cache, get_dashboard_status, get_state_version,
+    get_loans_by_ids, get_loan_summaries_by_ids, get_my_portfolio,
+    FarmerDashboardData, InvestorDashboardData, AdminDashboardData, PublicStats, PortfolioView,
     NFTSummary, LoanSummary, FarmerStats, InvestorStats, InvestmentRecord,
     SystemOverview, LiquidityMetrics, LoanMetrics, UserMetrics, RiskMetrics,
     DashboardStatus
@@ -151,32 +185,43 @@ pub use dashboard_support::{
 pub use oracle::{fetch_commodity_price, get_commodity_price, admin_set_commodity_price, 
     get_all_commodity_prices, is_price_stale, heartbeat_price_update};
 pub use ckbtc_integration::{transfer_ckbtc_to_borrower, process_ckbtc_repayment, 
-    check_ckbtc_balance, get_protocol_ckbtc_balance, admin_withdraw_protocol_earnings};
+    check_ckbtc_balance, get_protocol_ckbtc_balance, admin_withdraw_protocol_earnings,
+    repay_loan_via_allowance};
 pub use production_config::*;
 pub use production_security::*;
 pub use monitoring::*;
 pub use liquidity_management::{
     deposit_liquidity, disburse_loan, withdraw_liquidity, 
     get_pool_stats, get_investor_balance, get_pool_details, get_all_investor_balances_admin,
-    process_loan_repayment, collect_protocol_fees, emergency_pause_pool, resume_pool_operations,
+    get_all_investor_balances_redacted, get_investor_balance_redacted, get_investor_aggregate_stats,
+    get_solvency_report, get_my_kyc_status, DepositError, KycStatus, WithdrawalError,
+    get_my_transaction_limits, TransactionLimitsView,
+    sweep_dust_balance, sweep_all_dust, get_dust_balances_report, approve_disbursement_address_override,
+    process_loan_repayment, emergency_pause_pool, resume_pool_operations,
     get_investor_transaction_history, get_all_disbursements, get_loan_disbursements,
-    refresh_pool_statistics, set_pool_parameters, get_pool_health_metrics,
+    refresh_pool_statistics, set_pool_parameters, admin_credit_investors, get_pool_health_metrics,
     perform_pool_maintenance, emergency_halt_operations, is_pool_paused,
+    set_origination_paused, is_pool_origination_paused, ensure_origination_allowed,
+    get_reserve_requirement, required_reserve,
     get_pool_configuration, get_processed_transactions_admin, get_my_processed_transactions,
-    get_disbursement_records_by_loan
+    get_disbursement_records_by_loan, reconcile_pool_state, repair_pool_state,
+    PoolReconciliationReport, PoolFieldReconciliation
 };
 pub use treasury_management::{
     collect_fees, top_up_canister_cycles, get_treasury_stats, register_canister,
-    update_canister_config, get_canister_cycle_status, get_revenue_log, emergency_withdraw,
+    update_canister_config, get_canister_cycle_status, get_revenue_log, get_treasury_expenses, TreasuryExpense, emergency_withdraw,
     init_treasury, treasury_heartbeat, get_cycle_transactions, trigger_cycle_distribution,
-    get_treasury_health_report, process_loan_fee_collection, process_liquidation_penalty,
-    set_treasury_configuration
+    get_treasury_health_report, process_liquidation_penalty,
+    set_treasury_configuration, propose_emergency_withdraw, approve_emergency_withdraw,
+    get_pending_emergency_withdrawals, EmergencyWithdrawalRequest, EmergencyWithdrawalStatus,
+    get_insurance_fund_balance, get_insurance_fund_history, InsuranceFundEntry, InsuranceFundEntryKind,
+    reconcile_ckbtc_balance
 };
 
 // Export advanced analytics functions
 pub use advanced_analytics::{
     generate_analytics_report, get_predictive_analysis, get_portfolio_optimization,
-    get_stress_test_results, get_market_intelligence
+    get_stress_test_results, get_market_intelligence, get_investor_cohorts, CohortStats, CohortGranularity
 };
 
 // System functions
@@ -228,11 +273,28 @@ fn pre_upgrade() {
 // Post-upgrade hook
 #[post_upgrade]
 fn post_upgrade() {
+    // Bring stable data up to this build's schema before anything reads it. Trapping rolls the
+    // upgrade back, so a downgrade or failed migration leaves the previous build running.
+    match schema_migration::run_schema_migrations() {
+        Ok(applied) => ic_cdk::println!(
+            "Post-upgrade: Schema at v{} ({} migration(s) applied)",
+            schema_migration::SCHEMA_VERSION, applied.len()
+        ),
+        Err(e) => ic_cdk::trap(&format!("Post-upgrade aborted: {}", e)),
+    }
+    
     ic_cdk::println!("Post-upgrade: User management system restored");
     
     // Initialize treasury management system
     treasury_management::init_treasury();
     ic_cdk::println!("Post-upgrade: Treasury management system initialized");
+    
+    // Make sure no ID counter can reissue an existing key
+    let repairs = helpers::verify_id_counters_after_upgrade();
+    ic_cdk::println!("Post-upgrade: ID counters verified ({} repaired)", repairs);
+    
+    // Routed loan reads run as queries and cannot fill the shard layout cache themselves
+    advanced_query_routing::refresh_shard_ownership();
 }
 
 // Generate Candid interface
@@ -257,19 +319,36 @@ pub fn is_emergency_stopped() -> bool {
 // Production health check
 #[query]
 pub fn production_health_check() -> ProductionHealthStatus {
+    let is_solvent = liquidity_management::get_solvency_report().is_solvent;
     ProductionHealthStatus {
-        is_healthy: !is_emergency_stopped() && !is_in_maintenance_mode(),
+        is_healthy: !is_emergency_stopped() && !is_in_maintenance_mode() && is_solvent,
         emergency_stop: is_emergency_stopped(),
         maintenance_mode: is_in_maintenance_mode(),
+        origination_paused: crate::storage::is_origination_paused(),
         oracle_status: check_oracle_health(),
         ckbtc_integration: check_ckbtc_health(),
         memory_usage: get_memory_usage(),
         total_loans: get_active_loans_count(),
         active_loans: get_active_loans_count(),
         last_heartbeat: get_last_heartbeat_time(),
+        upcoming_maintenance: automated_maintenance::get_upcoming_maintenance(),
+        is_solvent,
+        last_reconciliation: treasury_management::get_last_reconciliation(),
     }
 }
 
+// Per-dependency status with latency for monitoring
+#[query]
+pub fn get_dependency_health() -> Vec<DependencyHealth> {
+    helpers::get_all_dependency_health()
+}
+
+// Stable memory usage per storage region
+#[query]
+pub fn get_memory_usage_breakdown() -> MemoryBreakdown {
+    get_memory_breakdown()
+}
+
 // === SCALABILITY AND SHARDING FUNCTIONS ===
 
 /// Get factory pattern statistics
@@ -299,6 +378,7 @@ pub async fn get_farmer_dashboard_advanced(farmer_id: Principal) -> advanced_que
 /// Force shard migration (admin only)
 #[update]
 pub async fn migrate_shard_data(from_shard: u64, to_shard: u64) -> Result<String, String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "migrate_shard_data")?;
     // Check admin permissions
     if !is_admin(&caller()) {
         return Err("Unauthorized: Admin access required".to_string());
@@ -309,6 +389,7 @@ pub async fn migrate_shard_data(from_shard: u64, to_shard: u64) -> Result<String
 /// Create a new data shard
 #[update]
 pub async fn create_data_shard() -> Result<u64, String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "create_data_shard")?;
     if !is_admin(&caller()) {
         return Err("Unauthorized: Admin access required".to_string());
     }
@@ -351,9 +432,20 @@ pub fn get_query_cache_stats() -> advanced_query_routing::CacheStats {
 /// Clear query routing cache (admin only)
 #[update]
 pub fn clear_query_cache() -> Result<String, String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "clear_query_cache")?;
     if !is_admin(&caller()) {
         return Err("Unauthorized: Admin access required".to_string());
     }
     advanced_query_routing::clear_cache();
     Ok("Query cache cleared successfully".to_string())
 }
+
+/// Drop one principal's cached dashboard entries (admin only)
+#[update]
+pub fn invalidate_cache_for(principal: Principal) -> Result<u64, String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "invalidate_cache_for")?;
+    if !is_admin(&caller()) {
+        return Err("Unauthorized: Admin access required".to_string());
+    }
+    Ok(advanced_query_routing::invalidate_cache_for(principal))
+}
@@ -4,7 +4,7 @@ use candid::{CandidType, Deserialize, Principal};
 use ic_stable_structures::{Storable, storable::Bound};
 use crate::types::*;
 use crate::storage::*;
-use crate::helpers::{log_audit_action, is_admin, get_canister_config};
+use crate::helpers::{log_audit_action, is_admin, get_canister_config, get_loan_commodity, risk_adjust_health_ratio};
 use crate::loan_repayment::calculate_total_debt_with_interest;
 
 // Production constants untuk liquidation system
@@ -13,6 +13,9 @@ const MINIMUM_HEALTH_RATIO: f64 = 1.2; // 120% collateralization minimum
 const LIQUIDATION_PENALTY_RATE: u64 = 5; // 5% penalty untuk liquidation
 const MAX_BULK_LIQUIDATION_SIZE: usize = 50; // Maximum 50 loans per bulk operation
 const LIQUIDATION_PROCESSING_FEE: u64 = 100_000; // 100k satoshi processing fee
+const LIQUIDATION_AUCTION_DURATION: u64 = 72 * 60 * 60 * 1_000_000_000; // 72 jam bidding window
+const DEFAULT_AUTO_LIQUIDATION_MAX_AMOUNT: u64 = 100_000_000; // 1 BTC; pinjaman lebih besar perlu review operator
+const MAX_AUTO_LIQUIDATIONS_PER_RUN: usize = 10;
 
 /// Enhanced liquidation metrics type untuk comprehensive dashboard
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -43,15 +46,60 @@ thread_local! {
             get_liquidation_memory()
         )
     );
+
+    static LIQUIDATION_AUCTIONS: RefCell<StableBTreeMap<u64, LiquidationAuction, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            get_memory_by_id(MemoryId::new(67))
+        )
+    );
+
+    // Whitelisted auction bidders -> timestamp added
+    static LIQUIDATION_BIDDERS: RefCell<StableBTreeMap<Principal, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            get_memory_by_id(MemoryId::new(68))
+        )
+    );
+
+    // Auction surplus owed back to borrowers, in ckBTC satoshi
+    static BORROWER_SURPLUS_BALANCES: RefCell<StableBTreeMap<Principal, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            get_memory_by_id(MemoryId::new(69))
+        )
+    );
+
+    // Auction surplus from ICP loans owed back to borrowers, in e8s
+    static ICP_BORROWER_SURPLUS_BALANCES: RefCell<StableBTreeMap<Principal, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            get_memory_by_id(MemoryId::new(81))
+        )
+    );
+
+    // Eligible loans above the auto-liquidation amount, awaiting operator approval
+    static PENDING_LIQUIDATION_REVIEWS: RefCell<StableBTreeMap<u64, PendingLiquidationReview, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            get_memory_by_id(MemoryId::new(78))
+        )
+    );
+
+    // Loans locked in Liquidating while an attempt is in flight
+    static LIQUIDATION_ATTEMPTS: RefCell<StableBTreeMap<u64, LiquidationAttempt, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            get_memory_by_id(MemoryId::new(79))
+        )
+    );
 }
 
 fn get_liquidation_memory() -> Memory {
+    get_liquidation_memory_with_id(10)
+}
+
+fn get_liquidation_memory_with_id(memory_id: u8) -> Memory {
     use ic_stable_structures::memory::MemoryManager;
     thread_local! {
         static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
             RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
     }
-    MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(10)))
+    MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(memory_id)))
 }
 
 /// Main liquidation trigger function - Production-ready implementation
@@ -60,12 +108,18 @@ fn get_liquidation_memory() -> Memory {
 #[update]
 pub async fn trigger_liquidation(loan_id: u64) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "trigger_liquidation")?;
     
     // Step 1: Verify admin access or automated system (sesuai README: hanya admin atau heartbeat)
     if !is_admin(&caller) && !is_automated_system(&caller) {
         return Err("Unauthorized: Only admin or automated system can trigger liquidation".to_string());
     }
 
+    liquidate_loan(loan_id, caller).await
+}
+
+/// Liquidation steps 2-16, shared by the admin trigger, the heartbeat and approved reviews
+async fn liquidate_loan(loan_id: u64, caller: Principal) -> Result<String, String> {
     // Step 2: Get and validate loan data
     let mut loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
 
@@ -79,6 +133,10 @@ pub async fn trigger_liquidation(loan_id: u64) -> Result<String, String> {
     let (_, _, _, total_debt) = calculate_total_debt_with_interest(&loan)?;
     let remaining_debt = total_debt.saturating_sub(loan.total_repaid);
 
+    // Step 4b: Lock the loan in Liquidating before the first await so a concurrent
+    // trigger, heartbeat or bulk run cannot start a second attempt
+    begin_liquidation_attempt(&mut loan, caller)?;
+
     // Step 5: Update loan status to Defaulted (sesuai README)
     loan.status = LoanStatus::Defaulted;
     
@@ -97,6 +155,7 @@ pub async fn trigger_liquidation(loan_id: u64) -> Result<String, String> {
             );
         }
         Err(e) => {
+            abort_liquidation_attempt(loan_id, caller, &e);
             return Err(format!("Failed to transfer collateral to liquidation wallet: {}", e));
         }
     }
@@ -134,28 +193,8 @@ pub async fn trigger_liquidation(loan_id: u64) -> Result<String, String> {
         }
     };
 
-    // Step 9: Penyeimbangan Akuntansi - Catat kerugian pada liquidity pool
-    // Sesuai README: "Catat kerugian pada liquidity pool. Nilai kerugian adalah sisa utang pokok"
-    let principal_loss = loan.amount_approved.saturating_sub(loan.total_repaid.min(loan.amount_approved));
-    match record_liquidation_loss(loan_id, principal_loss, remaining_debt).await {
-        Ok(_) => {
-            log_audit_action(
-                caller,
-                "LIQUIDATION_LOSS_RECORDED".to_string(),
-                format!("Principal loss of {} satoshi recorded in liquidity pool for loan #{}", principal_loss, loan_id),
-                true,
-            );
-        }
-        Err(e) => {
-            log_audit_action(
-                caller,
-                "LIQUIDATION_LOSS_RECORDING_FAILED".to_string(),
-                format!("Failed to record liquidation loss for loan #{}: {}", loan_id, e),
-                false,
-            );
-            // Continue with liquidation process even if loss recording fails
-        }
-    }
+    // Step 9: Penyeimbangan Akuntansi - kerugian pada liquidity pool dicatat saat settlement,
+    // setelah hasil penjualan agunan diketahui (lihat settle_liquidation_proceeds)
 
     // Step 10: Create comprehensive liquidation record
     let liquidation_record = LiquidationRecord {
@@ -164,13 +203,14 @@ pub async fn trigger_liquidation(loan_id: u64) -> Result<String, String> {
         liquidated_by: caller,
         collateral_nft_id: loan.nft_id,
         outstanding_debt: remaining_debt,
-        principal_loss,
+        principal_loss: 0, // Set at settlement from the actual shortfall
         collateral_value: loan.collateral_value_btc,
         liquidation_reason: determine_liquidation_reason(&eligibility),
         ecdsa_signature,
         liquidation_wallet,
         processing_fee: LIQUIDATION_PROCESSING_FEE,
         recovery_expected: estimate_recovery_amount(loan.collateral_value_btc),
+        settlement: None,
     };
 
     // Step 11: Store liquidation record dalam stable storage
@@ -178,8 +218,9 @@ pub async fn trigger_liquidation(loan_id: u64) -> Result<String, String> {
         records.borrow_mut().insert(loan_id, liquidation_record);
     });
 
-    // Step 12: Update loan record
+    // Step 12: Update loan record and release the liquidation lock
     store_loan(loan.clone())?;
+    complete_liquidation_attempt(loan_id);
 
     // Step 13: Collect liquidation processing fee
     if let Err(e) = collect_liquidation_processing_fee(loan_id, LIQUIDATION_PROCESSING_FEE).await {
@@ -193,16 +234,21 @@ pub async fn trigger_liquidation(loan_id: u64) -> Result<String, String> {
 
     // Step 14: Trigger off-chain liquidation process integration
     initiate_off_chain_liquidation_process(loan_id, loan.nft_id, loan.collateral_value_btc).await;
+    crate::outbound_events::publish_liquidation_event(
+        loan_id,
+        loan.borrower,
+        loan.collateral_value_btc,
+        remaining_debt,
+    ).await;
 
     // Step 15: Log comprehensive audit trail
     log_audit_action(
         caller,
         "LOAN_LIQUIDATED".to_string(),
         format!(
-            "Loan #{} liquidated successfully: Outstanding debt: {}, Principal loss: {}, Collateral value: {}, Reason: {:?}, ECDSA signed: {}",
+            "Loan #{} liquidated successfully: Outstanding debt: {}, Collateral value: {}, Reason: {:?}, ECDSA signed: {}",
             loan_id, 
             remaining_debt, 
-            principal_loss,
             loan.collateral_value_btc, 
             determine_liquidation_reason(&eligibility),
             ecdsa_signature.is_some()
@@ -212,23 +258,318 @@ pub async fn trigger_liquidation(loan_id: u64) -> Result<String, String> {
 
     // Step 16: Return success response (sesuai README)
     Ok(format!(
-        "Liquidation process initiated successfully for loan #{}. Outstanding debt: {} satoshi transferred to loss reserves. Principal loss: {} satoshi. Collateral NFT #{} secured in liquidation wallet with cryptographic attestation.",
-        loan_id, remaining_debt, principal_loss, loan.nft_id
+        "Liquidation process initiated successfully for loan #{}. Outstanding debt: {} satoshi to be settled from collateral proceeds. Collateral NFT #{} secured in liquidation wallet with cryptographic attestation.",
+        loan_id, remaining_debt, loan.nft_id
     ))
 }
 
+// ========== LIQUIDATION LOCK ==========
+
+const DEFAULT_LIQUIDATION_LOCK_TIMEOUT_MINUTES: u64 = 60;
+
+fn get_liquidation_lock_timeout() -> u64 {
+    crate::governance::get_protocol_parameter("liquidation_lock_timeout_minutes".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_LIQUIDATION_LOCK_TIMEOUT_MINUTES)
+        * 60 * 1_000_000_000
+}
+
+/// Active loans may be liquidated; a Liquidating loan only once its attempt has timed out.
+/// A Liquidating loan without an attempt record is treated as stuck and may be retried.
+pub fn liquidation_status_allows(status: &LoanStatus, attempt: Option<&LiquidationAttempt>, now: u64) -> bool {
+    match status {
+        LoanStatus::Active => true,
+        LoanStatus::Liquidating => attempt.map_or(true, |attempt| now >= attempt.expires_at),
+        _ => false,
+    }
+}
+
+fn get_liquidation_attempt_record(loan_id: u64) -> Option<LiquidationAttempt> {
+    LIQUIDATION_ATTEMPTS.with(|attempts| attempts.borrow().get(&loan_id))
+}
+
+/// Move the loan to Liquidating and take its lock. Re-checks the lock so an attempt started
+/// since the caller's eligibility check is not duplicated.
+fn begin_liquidation_attempt(loan: &mut Loan, caller: Principal) -> Result<(), String> {
+    let now = time();
+    let existing = get_liquidation_attempt_record(loan.id);
+    if !liquidation_status_allows(&loan.status, existing.as_ref(), now) {
+        return Err(format!("Liquidation already in progress for loan #{}", loan.id));
+    }
+    let retried = loan.status == LoanStatus::Liquidating;
+
+    let attempt = LiquidationAttempt {
+        loan_id: loan.id,
+        started_at: now,
+        started_by: caller,
+        expires_at: now + get_liquidation_lock_timeout(),
+    };
+    LIQUIDATION_ATTEMPTS.with(|attempts| {
+        attempts.borrow_mut().insert(loan.id, attempt);
+    });
+    loan.status = LoanStatus::Liquidating;
+    store_loan(loan.clone())?;
+
+    log_audit_action(
+        caller,
+        "LIQUIDATION_STARTED".to_string(),
+        if retried {
+            format!("Liquidation of loan #{} restarted after the previous attempt timed out", loan.id)
+        } else {
+            format!("Liquidation of loan #{} started", loan.id)
+        },
+        true,
+    );
+    Ok(())
+}
+
+/// Return a loan whose liquidation failed before collateral moved to Active and release its lock
+fn abort_liquidation_attempt(loan_id: u64, caller: Principal, error: &str) {
+    LIQUIDATION_ATTEMPTS.with(|attempts| {
+        attempts.borrow_mut().remove(&loan_id);
+    });
+    if let Some(mut loan) = get_loan(loan_id) {
+        if loan.status == LoanStatus::Liquidating {
+            loan.status = LoanStatus::Active;
+            let _ = store_loan(loan);
+        }
+    }
+    log_audit_action(
+        caller,
+        "LIQUIDATION_ABORTED".to_string(),
+        format!("Liquidation of loan #{} aborted: {}", loan_id, error),
+        false,
+    );
+}
+
+/// Release the lock of a liquidation whose collateral has moved, along with any pending review
+fn complete_liquidation_attempt(loan_id: u64) {
+    PENDING_LIQUIDATION_REVIEWS.with(|reviews| {
+        reviews.borrow_mut().remove(&loan_id);
+    });
+    LIQUIDATION_ATTEMPTS.with(|attempts| {
+        attempts.borrow_mut().remove(&loan_id);
+    });
+}
+
+/// Current liquidation lock for a loan, if an attempt is in flight (admin only)
+#[query]
+pub fn get_liquidation_attempt(loan_id: u64) -> Result<Option<LiquidationAttempt>, String> {
+    if !is_admin(&caller()) {
+        return Err("Unauthorized: Only admins can view liquidation locks".to_string());
+    }
+    Ok(get_liquidation_attempt_record(loan_id))
+}
+
+// ========== LIQUIDATION REVIEW BANDS ==========
+
+/// Loans owing less than `auto_max_amount` are liquidated automatically; the rest need review
+pub fn liquidation_band_for(outstanding_debt: u64, auto_max_amount: u64) -> LiquidationBand {
+    if outstanding_debt < auto_max_amount {
+        LiquidationBand::Automatic
+    } else {
+        LiquidationBand::ManualReview
+    }
+}
+
+fn get_auto_liquidation_max_amount() -> u64 {
+    crate::governance::get_protocol_parameter("auto_liquidation_max_amount".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_AUTO_LIQUIDATION_MAX_AMOUNT)
+}
+
+/// Liquidation review operators: super admins, liquidation admins, or holders of ManageLiquidation
+fn is_liquidation_operator(caller: &Principal) -> bool {
+    match crate::governance::get_admin_role(*caller) {
+        Some(role) if role.is_active => {
+            matches!(role.role_type, AdminRoleType::SuperAdmin | AdminRoleType::LiquidationAdmin)
+                || role.permissions.iter().any(|p| matches!(p, Permission::ManageLiquidation))
+        }
+        _ => false,
+    }
+}
+
+/// Drop queued reviews for loans that are no longer eligible (repaid, topped up, or cured)
+fn prune_cured_liquidation_reviews() -> u64 {
+    let queued: Vec<u64> = PENDING_LIQUIDATION_REVIEWS.with(|reviews| {
+        reviews.borrow().iter().map(|(loan_id, _)| loan_id).collect()
+    });
+
+    let mut cured = 0u64;
+    for loan_id in queued {
+        let still_eligible = check_liquidation_eligibility(loan_id)
+            .map(|check| check.is_eligible)
+            .unwrap_or(false);
+        if !still_eligible {
+            PENDING_LIQUIDATION_REVIEWS.with(|reviews| {
+                reviews.borrow_mut().remove(&loan_id);
+            });
+            cured += 1;
+            log_audit_action(
+                ic_cdk::id(),
+                "LIQUIDATION_REVIEW_CURED".to_string(),
+                format!("Loan #{} is no longer eligible for liquidation and left the review queue", loan_id),
+                true,
+            );
+        }
+    }
+    cured
+}
+
+/// Heartbeat entry point: prune cured reviews, auto-liquidate the small band and queue the rest.
+/// A loan that fails is logged and skipped so it cannot stall the rest of the book.
+/// Returns (liquidated, newly flagged, cured, failed).
+pub async fn process_liquidation_bands() -> (u64, u64, u64, u64) {
+    let cured = prune_cured_liquidation_reviews();
+    let auto_max_amount = get_auto_liquidation_max_amount();
+    let system = ic_cdk::id();
+
+    let mut liquidated = 0u64;
+    let mut flagged = 0u64;
+    let mut failed = 0u64;
+
+    for eligibility in get_loans_eligible_for_liquidation() {
+        let already_queued = PENDING_LIQUIDATION_REVIEWS.with(|reviews| reviews.borrow().contains_key(&eligibility.loan_id));
+        if already_queued {
+            continue;
+        }
+        let loan = match get_loan(eligibility.loan_id) {
+            Some(loan) => loan,
+            None => continue,
+        };
+        let total_debt = match calculate_total_debt_with_interest(&loan) {
+            Ok((_, _, _, total_debt)) => total_debt,
+            Err(e) => {
+                failed += 1;
+                log_liquidation_band_failure(system, loan.id, &e);
+                continue;
+            }
+        };
+        let outstanding_debt = total_debt.saturating_sub(loan.total_repaid);
+
+        match liquidation_band_for(outstanding_debt, auto_max_amount) {
+            LiquidationBand::Automatic => {
+                if liquidated as usize >= MAX_AUTO_LIQUIDATIONS_PER_RUN {
+                    continue;
+                }
+                match liquidate_loan(loan.id, system).await {
+                    Ok(_) => liquidated += 1,
+                    Err(e) => {
+                        failed += 1;
+                        log_liquidation_band_failure(system, loan.id, &e);
+                    }
+                }
+            }
+            LiquidationBand::ManualReview => {
+                PENDING_LIQUIDATION_REVIEWS.with(|reviews| {
+                    reviews.borrow_mut().insert(loan.id, PendingLiquidationReview {
+                        loan_id: loan.id,
+                        borrower: loan.borrower,
+                        outstanding_debt,
+                        collateral_value: loan.collateral_value_btc,
+                        health_ratio: eligibility.health_ratio,
+                        days_overdue: eligibility.days_overdue,
+                        reason: eligibility.reason.clone(),
+                        flagged_at: time(),
+                    });
+                });
+                flagged += 1;
+                log_audit_action(
+                    system,
+                    "LIQUIDATION_REVIEW_FLAGGED".to_string(),
+                    format!(
+                        "Loan #{} ({} satoshi outstanding) queued for liquidation review: {}",
+                        loan.id, outstanding_debt, eligibility.reason
+                    ),
+                    true,
+                );
+            }
+        }
+    }
+
+    (liquidated, flagged, cured, failed)
+}
+
+fn log_liquidation_band_failure(system: Principal, loan_id: u64, error: &str) {
+    log_audit_action(
+        system,
+        "AUTO_LIQUIDATION_FAILED".to_string(),
+        format!("Skipped loan #{} during liquidation sweep: {}", loan_id, error),
+        false,
+    );
+}
+
+/// Approve and execute a queued liquidation (liquidation operators only)
+#[update]
+pub async fn approve_liquidation_review(loan_id: u64) -> Result<String, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "approve_liquidation_review")?;
+    if !is_liquidation_operator(&caller) {
+        return Err("Unauthorized: Only liquidation operators can approve liquidation reviews".to_string());
+    }
+
+    let review = PENDING_LIQUIDATION_REVIEWS.with(|reviews| reviews.borrow().get(&loan_id))
+        .ok_or_else(|| format!("Loan #{} is not awaiting liquidation review", loan_id))?;
+
+    // The loan may have cured since it was flagged
+    let eligibility = check_liquidation_eligibility(loan_id)?;
+    if !eligibility.is_eligible {
+        PENDING_LIQUIDATION_REVIEWS.with(|reviews| {
+            reviews.borrow_mut().remove(&loan_id);
+        });
+        return Err(format!("Loan #{} is no longer eligible for liquidation: {}", loan_id, eligibility.reason));
+    }
+
+    log_audit_action(
+        caller,
+        "LIQUIDATION_REVIEW_APPROVED".to_string(),
+        format!(
+            "Liquidation review approved for loan #{} (flagged at {}, {} satoshi outstanding)",
+            loan_id, review.flagged_at, review.outstanding_debt
+        ),
+        true,
+    );
+
+    liquidate_loan(loan_id, caller).await
+}
+
+/// Loans awaiting liquidation review, oldest first (admins and liquidation operators)
+#[query]
+pub fn get_pending_liquidation_reviews() -> Result<Vec<PendingLiquidationReview>, String> {
+    let caller = caller();
+    if !is_admin(&caller) && !is_liquidation_operator(&caller) {
+        return Err("Unauthorized: Only admins or liquidation operators can view liquidation reviews".to_string());
+    }
+
+    let mut reviews: Vec<PendingLiquidationReview> = PENDING_LIQUIDATION_REVIEWS.with(|reviews| {
+        reviews.borrow().iter().map(|(_, review)| review).collect()
+    });
+    reviews.sort_by_key(|review| review.flagged_at);
+    Ok(reviews)
+}
+
 /// Enhanced eligibility check sesuai spesifikasi README
 /// Verifikasi bahwa pinjaman sudah melewati periode gagal bayar (30 hari setelah jatuh tempo)
 #[query]
 pub fn check_liquidation_eligibility(loan_id: u64) -> Result<LiquidationEligibilityCheck, String> {
     let loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
 
-    // Step 1: Can only liquidate active loans (sesuai README: statusnya adalah #Active)
-    if loan.status != LoanStatus::Active {
+    // Step 1: Can only liquidate active loans (sesuai README: statusnya adalah #Active),
+    // or a Liquidating loan whose previous attempt has timed out
+    let attempt = get_liquidation_attempt_record(loan_id);
+    let status_allows = liquidation_status_allows(&loan.status, attempt.as_ref(), time());
+    if !status_allows {
+        let reason = match (&loan.status, &attempt) {
+            (LoanStatus::Liquidating, Some(attempt)) => format!(
+                "Liquidation already in progress since {}; retry allowed after {}",
+                attempt.started_at, attempt.expires_at
+            ),
+            _ => format!("Loan status is {:?}, only Active loans can be liquidated", loan.status),
+        };
         return Ok(LiquidationEligibilityCheck {
             loan_id,
             is_eligible: false,
-            reason: format!("Loan status is {:?}, only Active loans can be liquidated", loan.status),
+            reason,
             days_overdue: 0,
             health_ratio: 0.0,
             grace_period_expired: false,
@@ -273,20 +614,13 @@ pub fn check_liquidation_eligibility(loan_id: u64) -> Result<LiquidationEligibil
     let grace_period_expired = current_time > due_date + grace_period;
 
     // Step 5: Calculate health ratio (collateral value vs outstanding debt)
-    let (_, _, _, total_debt) = calculate_total_debt_with_interest(&loan)
-        .unwrap_or((loan.amount_approved, 0, 0, loan.amount_approved));
-    let remaining_debt = total_debt.saturating_sub(loan.total_repaid);
-    
-    let health_ratio = if remaining_debt > 0 {
-        loan.collateral_value_btc as f64 / remaining_debt as f64
-    } else {
-        f64::INFINITY
-    };
+    let remaining_debt = remaining_debt_at(&loan, current_time);
+    let health_ratio = remaining_debt_health_ratio(&loan, current_time);
 
     // Step 6: Determine eligibility based on comprehensive criteria
     let is_eligible = grace_period_expired && 
                      remaining_debt > 0 && 
-                     loan.status == LoanStatus::Active;
+                     status_allows;
 
     let reason = if is_eligible {
         "Loan is eligible for liquidation - grace period expired and debt remains outstanding".to_string()
@@ -299,7 +633,7 @@ pub fn check_liquidation_eligibility(loan_id: u64) -> Result<LiquidationEligibil
         )
     } else if remaining_debt == 0 {
         "Loan is already fully repaid - no liquidation needed".to_string()
-    } else if loan.status != LoanStatus::Active {
+    } else if !status_allows {
         format!("Loan status is {:?} - only Active loans can be liquidated", loan.status)
     } else {
         "Loan does not meet liquidation criteria".to_string()
@@ -315,6 +649,270 @@ pub fn check_liquidation_eligibility(loan_id: u64) -> Result<LiquidationEligibil
     })
 }
 
+// ========== LIQUIDATION PRICE PROJECTION ==========
+
+const DEFAULT_LIQUIDATION_THRESHOLD_BPS: u64 = 8500;
+const MAX_PRICE_MULTIPLE: u64 = 1_000_000;
+
+/// Health ratio below which a loan is liquidatable, from the governance collateral-to-debt threshold
+pub fn liquidation_health_threshold() -> f64 {
+    crate::governance::get_protocol_parameter("liquidation_threshold".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_LIQUIDATION_THRESHOLD_BPS) as f64 / BASIS_POINTS_SCALE as f64
+}
+
+/// Principal, accrued interest and late penalty as of `now`, less what has been repaid
+pub fn remaining_debt_at(loan: &Loan, now: u64) -> u64 {
+    let (_, _, _, total_debt) = crate::loan_repayment::calculate_total_debt_at(loan, now)
+        .unwrap_or((loan.amount_approved, 0, 0, loan.amount_approved));
+    total_debt.saturating_sub(loan.total_repaid)
+}
+
+/// Collateral over remaining debt, risk-adjusted for the commodity; the ratio liquidation
+/// eligibility is decided on
+pub fn remaining_debt_health_ratio(loan: &Loan, now: u64) -> f64 {
+    let remaining_debt = remaining_debt_at(loan, now);
+    let raw_health_ratio = if remaining_debt > 0 {
+        loan.collateral_value_btc as f64 / remaining_debt as f64
+    } else {
+        f64::INFINITY
+    };
+    risk_adjust_health_ratio(raw_health_ratio, get_loan_commodity(loan).as_deref())
+}
+
+/// Health ratio with the collateral commodity at `price` per unit. The collateral is revalued
+/// as at origination and scored against the remaining debt at `now`, the same ratio
+/// `check_liquidation_eligibility` uses.
+pub fn health_at_price(
+    loan: &Loan,
+    collateral: &CollateralPriceComponent,
+    btc_rate: &BtcIdrRate,
+    price: u64,
+    now: u64,
+) -> Result<f64, String> {
+    let mut price_data = collateral.price.clone();
+    price_data.price_per_unit = price;
+    let mut repriced = loan.clone();
+    repriced.collateral_value_btc = crate::loan_lifecycle::calculate_collateral_value_btc(
+        collateral.valuation_idr,
+        collateral.quantity,
+        &price_data,
+        btc_rate,
+    )?.0;
+    Ok(remaining_debt_health_ratio(&repriced, now))
+}
+
+/// Lowest commodity price that keeps the loan at `threshold`; below it the loan is liquidatable.
+/// Health never decreases as the price rises, so the price is found by bisection. None when
+/// no price can lift the loan to the threshold (the appraisal cap binds).
+pub fn solve_liquidation_price(
+    loan: &Loan,
+    collateral: &CollateralPriceComponent,
+    btc_rate: &BtcIdrRate,
+    threshold: f64,
+    now: u64,
+) -> Option<u64> {
+    if remaining_debt_at(loan, now) == 0 || btc_rate.idr_per_btc == 0 {
+        return None;
+    }
+    // A price too large to value is treated as out of reach
+    let health = |price: u64| health_at_price(loan, collateral, btc_rate, price, now).unwrap_or(0.0);
+
+    let ceiling = collateral.price.price_per_unit.max(1).saturating_mul(MAX_PRICE_MULTIPLE);
+    let mut high = collateral.price.price_per_unit.max(1);
+    while health(high) < threshold {
+        if high >= ceiling {
+            return None;
+        }
+        high = high.saturating_mul(2);
+    }
+    let mut low = 0u64;
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if health(mid) < threshold {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some(high)
+}
+
+/// Price inputs for the NFT backing the loan
+fn loan_collateral_component(loan: &Loan) -> Result<CollateralPriceComponent, String> {
+    let nft = get_nft_data(loan.nft_id).ok_or_else(|| format!("Collateral NFT #{} not found", loan.nft_id))?;
+    let valuation_idr = crate::loan_lifecycle::extract_valuation_from_metadata(&nft.metadata)?;
+    let commodity = crate::loan_lifecycle::extract_commodity_info_from_metadata(&nft.metadata)?;
+    let price = crate::oracle::get_effective_commodity_price(&commodity.commodity_type)
+        .ok_or_else(|| format!("No price available for {}", commodity.commodity_type))?;
+
+    Ok(CollateralPriceComponent {
+        nft_id: loan.nft_id,
+        commodity_type: commodity.commodity_type,
+        quantity: commodity.quantity,
+        valuation_idr,
+        price,
+    })
+}
+
+/// Projection from already-gathered inputs
+pub fn build_liquidation_projection(
+    loan: &Loan,
+    collateral: &CollateralPriceComponent,
+    btc_rate: &BtcIdrRate,
+    threshold: f64,
+    now: u64,
+) -> Result<LiquidationProjection, String> {
+    let current_price = collateral.price.price_per_unit;
+    let liquidation_price = solve_liquidation_price(loan, collateral, btc_rate, threshold, now);
+    Ok(LiquidationProjection {
+        loan_id: loan.id,
+        nft_id: collateral.nft_id,
+        commodity_type: collateral.commodity_type.clone(),
+        current_health_ratio: health_at_price(loan, collateral, btc_rate, current_price, now)?,
+        liquidation_threshold: threshold,
+        current_price,
+        liquidation_price,
+        cushion_percent: liquidation_price
+            .filter(|_| current_price > 0)
+            .map(|price| (1.0 - price as f64 / current_price as f64) * 100.0),
+        generated_at: now,
+    })
+}
+
+fn loan_projection_inputs(loan: &Loan) -> Result<(CollateralPriceComponent, BtcIdrRate), String> {
+    Ok((loan_collateral_component(loan)?, crate::oracle::get_asset_idr_rate(&loan.asset())?))
+}
+
+/// Projection for a loaded loan at the current threshold, or None when prices are unavailable
+pub fn projection_for_loan(loan: &Loan, now: u64) -> Option<LiquidationProjection> {
+    let (collateral, btc_rate) = loan_projection_inputs(loan).ok()?;
+    build_liquidation_projection(loan, &collateral, &btc_rate, liquidation_health_threshold(), now).ok()
+}
+
+fn projection_inputs(loan_id: u64) -> Result<(Loan, CollateralPriceComponent, BtcIdrRate), String> {
+    let caller = caller();
+    let loan = get_loan(loan_id).ok_or_else(|| format!("Loan #{} not found", loan_id))?;
+    if loan.borrower != caller && !is_admin(&caller) {
+        return Err("Unauthorized: Only borrower or admin can project loan health".to_string());
+    }
+    let (collateral, btc_rate) = loan_projection_inputs(&loan)?;
+    Ok((loan, collateral, btc_rate))
+}
+
+/// Commodity price at which the loan becomes liquidatable on health, with the cushion from
+/// today's price (borrower or admin)
+#[query]
+pub fn project_liquidation_price(loan_id: u64) -> Result<LiquidationProjection, String> {
+    let (loan, collateral, btc_rate) = projection_inputs(loan_id)?;
+    build_liquidation_projection(&loan, &collateral, &btc_rate, liquidation_health_threshold(), time())
+}
+
+/// Health ratio if the collateral commodity traded at `hypothetical_price` (borrower or admin)
+#[query]
+pub fn simulate_health_at_price(loan_id: u64, hypothetical_price: u64) -> Result<f64, String> {
+    let (loan, collateral, btc_rate) = projection_inputs(loan_id)?;
+    health_at_price(&loan, &collateral, &btc_rate, hypothetical_price, time())
+}
+
+// ========== AT-RISK LOAN REPORT ==========
+
+const DEFAULT_AT_RISK_HEALTH_BAND_BPS: u64 = 12_000;
+const DEFAULT_AT_RISK_PAGE_SIZE: u64 = 50;
+const MAX_AT_RISK_PAGE_SIZE: u64 = 200;
+
+/// Time after the due date before an unpaid loan can be liquidated, in nanoseconds
+pub fn grace_period_nanos() -> u64 {
+    let params = get_protocol_parameters();
+    let grace_period_days = if params.grace_period_days > 0 { params.grace_period_days } else { DEFAULT_GRACE_PERIOD_DAYS };
+    grace_period_days * 24 * 60 * 60 * 1_000_000_000
+}
+
+/// Health ratio below which a current loan needs a margin call
+pub fn at_risk_health_band() -> f64 {
+    crate::governance::get_protocol_parameter("at_risk_health_band".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_AT_RISK_HEALTH_BAND_BPS) as f64 / BASIS_POINTS_SCALE as f64
+}
+
+/// None when the loan is current and inside the safe band
+pub fn suggest_at_risk_action(
+    health_ratio: f64,
+    liquidation_threshold: f64,
+    safe_band: f64,
+    past_due: bool,
+    grace_period_expired: bool,
+) -> Option<AtRiskAction> {
+    if health_ratio < liquidation_threshold || grace_period_expired {
+        Some(AtRiskAction::LiquidateNow)
+    } else if past_due {
+        Some(AtRiskAction::GracePeriod)
+    } else if health_ratio < safe_band {
+        Some(AtRiskAction::MarginCall)
+    } else {
+        None
+    }
+}
+
+/// Most urgent action first, then closest to liquidation, then longest overdue
+pub fn sort_at_risk_loans(entries: &mut [AtRiskLoan]) {
+    entries.sort_by(|a, b| {
+        a.suggested_action.cmp(&b.suggested_action)
+            .then(a.health_ratio.partial_cmp(&b.health_ratio).unwrap_or(std::cmp::Ordering::Equal))
+            .then(b.days_overdue.cmp(&a.days_overdue))
+            .then(a.loan.id.cmp(&b.loan.id))
+    });
+}
+
+/// The operator's daily triage queue: every active loan that is past due or below the safe
+/// health band, most urgent first (admin only)
+#[query]
+pub fn get_at_risk_loans_report(offset: Option<u64>, limit: Option<u64>) -> Result<Vec<AtRiskLoan>, String> {
+    let caller = caller();
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can view the at-risk loan report".to_string());
+    }
+
+    let now = time();
+    let threshold = liquidation_health_threshold();
+    let safe_band = at_risk_health_band();
+    let grace_period = grace_period_nanos();
+
+    let mut entries: Vec<AtRiskLoan> = get_all_loans_data()
+        .into_iter()
+        .filter(|loan| loan.status == LoanStatus::Active)
+        .filter_map(|loan| {
+            // Live prices where available, otherwise the origination valuation; both against
+            // the remaining debt
+            let projection = projection_for_loan(&loan, now);
+            let health_ratio = match &projection {
+                Some(projection) => projection.current_health_ratio,
+                None => remaining_debt_health_ratio(&loan, now),
+            };
+            let past_due = loan.due_date.map_or(false, |due_date| now > due_date);
+            let grace_period_expired = loan.due_date.map_or(false, |due_date| now > due_date + grace_period);
+            let suggested_action = suggest_at_risk_action(health_ratio, threshold, safe_band, past_due, grace_period_expired)?;
+
+            let borrower = crate::user_management::get_user_by_principal(&loan.borrower);
+            let has_contact = |field: Option<&String>| field.map_or(false, |value| !value.trim().is_empty());
+            Some(AtRiskLoan {
+                health_ratio,
+                liquidation_price: projection.as_ref().and_then(|projection| projection.liquidation_price),
+                cushion_percent: projection.as_ref().and_then(|projection| projection.cushion_percent),
+                days_overdue: loan.due_date.map_or(0, |due_date| now.saturating_sub(due_date) / (24 * 60 * 60 * 1_000_000_000)),
+                borrower_has_email: borrower.as_ref().map_or(false, |user| has_contact(user.email.as_ref())),
+                borrower_has_phone: borrower.as_ref().map_or(false, |user| has_contact(user.phone.as_ref())),
+                suggested_action,
+                loan,
+            })
+        })
+        .collect();
+    sort_at_risk_loans(&mut entries);
+
+    let limit = limit.unwrap_or(DEFAULT_AT_RISK_PAGE_SIZE).min(MAX_AT_RISK_PAGE_SIZE) as usize;
+    Ok(entries.into_iter().skip(offset.unwrap_or(0) as usize).take(limit).collect())
+}
+
 /// Helper function untuk mengecek apakah caller adalah automated system
 fn is_automated_system(caller: &Principal) -> bool {
     // Check if caller is the canister itself (for heartbeat operations)
@@ -478,6 +1076,13 @@ pub fn get_all_liquidation_records() -> Result<Vec<LiquidationRecord>, String> {
     Ok(records)
 }
 
+/// Principal written off across all liquidations, for pool performance snapshots
+pub fn total_liquidation_losses() -> u64 {
+    LIQUIDATION_RECORDS.with(|records| {
+        records.borrow().iter().fold(0u64, |total, (_, record)| total.saturating_add(record.principal_loss))
+    })
+}
+
 /// Get comprehensive liquidation statistics untuk production monitoring
 #[query]
 pub fn get_liquidation_statistics() -> Result<LiquidationStatistics, String> {
@@ -533,9 +1138,11 @@ pub fn get_liquidation_statistics() -> Result<LiquidationStatistics, String> {
 
 /// Bulk liquidation untuk processing multiple loans sekaligus
 /// Production feature untuk automated liquidation processing
+/// Loans already locked in Liquidating are reported as failed, not attempted again
 #[update]
 pub async fn trigger_bulk_liquidation(loan_ids: Vec<u64>) -> Result<Vec<LiquidationResult>, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "trigger_bulk_liquidation")?;
     
     // Only admin can trigger bulk liquidations
     if !is_admin(&caller) {
@@ -596,12 +1203,22 @@ pub async fn emergency_liquidation(
     emergency_reason: String
 ) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "emergency_liquidation")?;
     
     // Only admin can trigger emergency liquidations
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admin can trigger emergency liquidation".to_string());
     }
 
+    emergency_liquidate_loan(loan_id, emergency_reason, caller).await
+}
+
+/// Emergency liquidation after authorization, holding the loan's liquidation lock throughout
+async fn emergency_liquidate_loan(
+    loan_id: u64,
+    emergency_reason: String,
+    caller: Principal,
+) -> Result<String, String> {
     // Force liquidation tanpa normal eligibility checks
     let mut loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
 
@@ -609,16 +1226,23 @@ pub async fn emergency_liquidation(
         return Err("Loan is already liquidated".to_string());
     }
 
-    // Force status change
-    loan.status = LoanStatus::Defaulted;
-    
     // Calculate debt
     let (_, _, _, total_debt) = calculate_total_debt_with_interest(&loan)?;
     let remaining_debt = total_debt.saturating_sub(loan.total_repaid);
 
+    // Lock the loan in Liquidating before the first await so a concurrent
+    // trigger, heartbeat or bulk run cannot start a second attempt
+    begin_liquidation_attempt(&mut loan, caller)?;
+
     // Transfer collateral
     let liquidation_wallet = get_liquidation_wallet();
-    transfer_collateral_to_liquidation_wallet(loan.nft_id, loan_id, liquidation_wallet).await?;
+    if let Err(e) = transfer_collateral_to_liquidation_wallet(loan.nft_id, loan_id, liquidation_wallet).await {
+        abort_liquidation_attempt(loan_id, caller, &e);
+        return Err(e);
+    }
+
+    // Force status change
+    loan.status = LoanStatus::Defaulted;
 
     // Create emergency liquidation record
     let liquidation_record = LiquidationRecord {
@@ -627,19 +1251,21 @@ pub async fn emergency_liquidation(
         liquidated_by: caller,
         collateral_nft_id: loan.nft_id,
         outstanding_debt: remaining_debt,
-        principal_loss: loan.amount_approved.saturating_sub(loan.total_repaid),
+        principal_loss: 0, // Set at settlement from the actual shortfall
         collateral_value: loan.collateral_value_btc,
         liquidation_reason: LiquidationReason::EmergencyLiquidation,
         ecdsa_signature: None, // Skip ECDSA for emergency
         liquidation_wallet,
         processing_fee: 0, // No fee for emergency
         recovery_expected: estimate_recovery_amount(loan.collateral_value_btc),
+        settlement: None,
     };
 
-    // Store records
+    // Store records and release the liquidation lock
     LIQUIDATION_RECORDS.with(|records| {
         records.borrow_mut().insert(loan_id, liquidation_record);
     });
+    complete_liquidation_attempt(loan_id);
     
     store_loan(loan)?;
 
@@ -661,6 +1287,7 @@ pub async fn emergency_liquidation(
 /// Production automation feature
 #[update]
 pub async fn automated_liquidation_check() -> Result<Vec<u64>, String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "automated_liquidation_check")?;
     let caller = ic_cdk::id(); // Only self-calls allowed for automation
     
     if !is_automated_system(&caller) {
@@ -818,9 +1445,11 @@ fn calculate_liquidation_success_rate() -> f64 {
 }
 
 /// Enhanced bulk liquidation dengan comprehensive error handling
+/// Loans already locked in Liquidating are reported as failed, not attempted again
 #[update]
 pub async fn trigger_bulk_liquidation(loan_ids: Vec<u64>) -> Result<Vec<(u64, Result<String, String>)>, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "trigger_bulk_liquidation")?;
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admin can trigger bulk liquidation".to_string());
     }
@@ -867,10 +1496,16 @@ pub async fn trigger_bulk_liquidation(loan_ids: Vec<u64>) -> Result<Vec<(u64, Re
 #[update]
 pub async fn emergency_liquidation(loan_id: u64, reason: String) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "emergency_liquidation")?;
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admin can trigger emergency liquidation".to_string());
     }
 
+    emergency_liquidate_loan(loan_id, reason, caller).await
+}
+
+/// Emergency liquidation after authorization, holding the loan's liquidation lock throughout
+async fn emergency_liquidate_loan(loan_id: u64, reason: String, caller: Principal) -> Result<String, String> {
     let mut loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
 
     if loan.status == LoanStatus::Repaid || loan.status == LoanStatus::Defaulted {
@@ -881,10 +1516,10 @@ pub async fn emergency_liquidation(loan_id: u64, reason: String) -> Result<Strin
     let (_, _, _, total_debt) = calculate_total_debt_with_interest(&loan)
         .unwrap_or((loan.amount_approved, 0, 0, loan.amount_approved));
     let remaining_debt = total_debt.saturating_sub(loan.total_repaid);
-    let principal_loss = loan.amount_approved.saturating_sub(loan.total_repaid.min(loan.amount_approved));
 
-    // Update loan status
-    loan.status = LoanStatus::Defaulted;
+    // Lock the loan in Liquidating before the first await so a concurrent
+    // trigger, heartbeat or bulk run cannot start a second attempt
+    begin_liquidation_attempt(&mut loan, caller)?;
 
     // Get liquidation wallet
     let liquidation_wallet = get_liquidation_wallet();
@@ -892,6 +1527,9 @@ pub async fn emergency_liquidation(loan_id: u64, reason: String) -> Result<Strin
     // Transfer collateral
     match transfer_collateral_to_liquidation_wallet(loan.nft_id, loan_id, liquidation_wallet).await {
         Ok(_) => {
+            // Update loan status
+            loan.status = LoanStatus::Defaulted;
+
             // Generate emergency attestation
             let attestation_message = format!(
                 "EMERGENCY_LIQUIDATION:loan_id={}:reason={}:debt={}:timestamp={}:admin={}",
@@ -906,39 +1544,41 @@ pub async fn emergency_liquidation(loan_id: u64, reason: String) -> Result<Strin
                 liquidated_by: caller,
                 collateral_nft_id: loan.nft_id,
                 outstanding_debt: remaining_debt,
-                principal_loss,
+                principal_loss: 0, // Set at settlement from the actual shortfall
                 collateral_value: loan.collateral_value_btc,
                 liquidation_reason: LiquidationReason::AdminForced,
                 ecdsa_signature,
                 liquidation_wallet,
                 processing_fee: 0, // Waived for emergency
                 recovery_expected: estimate_recovery_amount(loan.collateral_value_btc),
+                settlement: None,
             };
 
             LIQUIDATION_RECORDS.with(|records| {
                 records.borrow_mut().insert(loan_id, liquidation_record);
             });
+            complete_liquidation_attempt(loan_id);
 
-            // Update loan and record loss
+            // The pool loss is booked once, when the collateral proceeds are settled
             store_loan(loan.clone())?;
-            let _ = record_liquidation_loss(loan_id, principal_loss, remaining_debt).await;
 
             log_audit_action(
                 caller,
                 "EMERGENCY_LIQUIDATION_COMPLETED".to_string(),
                 format!(
-                    "Emergency liquidation completed for loan #{}: Reason: {}, Debt: {}, Principal Loss: {}",
-                    loan_id, reason, remaining_debt, principal_loss
+                    "Emergency liquidation completed for loan #{}: Reason: {}, Debt: {}",
+                    loan_id, reason, remaining_debt
                 ),
                 true,
             );
 
             Ok(format!(
-                "Emergency liquidation completed for loan #{}. Reason: {}. Outstanding debt: {} satoshi. Principal loss: {} satoshi.",
-                loan_id, reason, remaining_debt, principal_loss
+                "Emergency liquidation completed for loan #{}. Reason: {}. Outstanding debt: {} satoshi to be settled from collateral proceeds.",
+                loan_id, reason, remaining_debt
             ))
         }
         Err(e) => {
+            abort_liquidation_attempt(loan_id, caller, &e);
             log_audit_action(
                 caller,
                 "EMERGENCY_LIQUIDATION_FAILED".to_string(),
@@ -1053,10 +1693,16 @@ pub fn get_liquidation_metrics() -> Result<LiquidationMetrics, String> {
 #[update]
 pub async fn emergency_liquidation(loan_id: u64, reason: String) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "emergency_liquidation")?;
     if !is_admin(&caller) {
         return Err("Access denied: Only admins can trigger emergency liquidation".to_string());
     }
 
+    emergency_liquidate_loan(loan_id, reason, caller).await
+}
+
+/// Emergency liquidation after authorization, holding the loan's liquidation lock throughout
+async fn emergency_liquidate_loan(loan_id: u64, reason: String, caller: Principal) -> Result<String, String> {
     let mut loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
 
     if loan.status == LoanStatus::Repaid || loan.status == LoanStatus::Defaulted {
@@ -1067,10 +1713,10 @@ pub async fn emergency_liquidation(loan_id: u64, reason: String) -> Result<Strin
     let (_, _, _, total_debt) = calculate_total_debt_with_interest(&loan)
         .unwrap_or((loan.amount_approved, 0, 0, loan.amount_approved));
     let remaining_debt = total_debt.saturating_sub(loan.total_repaid);
-    let principal_loss = loan.amount_approved.saturating_sub(loan.total_repaid.min(loan.amount_approved));
 
-    // Update loan status
-    loan.status = LoanStatus::Defaulted;
+    // Lock the loan in Liquidating before the first await so a concurrent
+    // trigger, heartbeat or bulk run cannot start a second attempt
+    begin_liquidation_attempt(&mut loan, caller)?;
 
     // Get liquidation wallet
     let liquidation_wallet = get_liquidation_wallet();
@@ -1078,6 +1724,9 @@ pub async fn emergency_liquidation(loan_id: u64, reason: String) -> Result<Strin
     // Transfer collateral
     match transfer_collateral_to_liquidation_wallet(loan.nft_id, loan_id, liquidation_wallet).await {
         Ok(_) => {
+            // Update loan status
+            loan.status = LoanStatus::Defaulted;
+
             // Generate attestation
             let attestation_message = format!("EMERGENCY_LIQUIDATION:{}:{}:{}", loan_id, remaining_debt, time());
             let ecdsa_signature = generate_liquidation_attestation(&attestation_message).await.ok();
@@ -1098,10 +1747,10 @@ pub async fn emergency_liquidation(loan_id: u64, reason: String) -> Result<Strin
             LIQUIDATION_RECORDS.with(|records| {
                 records.borrow_mut().insert(loan_id, liquidation_record);
             });
+            complete_liquidation_attempt(loan_id);
 
-            // Update loan and record loss
+            // The pool loss is booked once, when the collateral proceeds are settled
             store_loan(loan)?;
-            let _ = record_liquidation_loss(loan_id, remaining_debt).await;
 
             log_audit_action(
                 caller,
@@ -1112,7 +1761,10 @@ pub async fn emergency_liquidation(loan_id: u64, reason: String) -> Result<Strin
 
             Ok(format!("Emergency liquidation completed for loan #{}", loan_id))
         }
-        Err(e) => Err(format!("Emergency liquidation failed: {}", e))
+        Err(e) => {
+            abort_liquidation_attempt(loan_id, caller, &e);
+            Err(format!("Emergency liquidation failed: {}", e))
+        }
     }
 }
 
@@ -1389,15 +2041,8 @@ pub fn assess_liquidation_risk(loan_id: u64) -> Result<LiquidationRiskAssessment
     };
     
     // Calculate current health ratio
-    let (_, _, _, total_debt) = calculate_total_debt_with_interest(&loan)
-        .unwrap_or((loan.amount_approved, 0, 0, loan.amount_approved));
-    let remaining_debt = total_debt.saturating_sub(loan.total_repaid);
-    
-    let health_ratio = if remaining_debt > 0 {
-        loan.collateral_value_btc as f64 / remaining_debt as f64
-    } else {
-        f64::INFINITY
-    };
+    let remaining_debt = remaining_debt_at(&loan, current_time);
+    let health_ratio = remaining_debt_health_ratio(&loan, current_time);
     
     // Determine risk level berdasarkan health ratio
     let risk_level = if health_ratio < 1.1 {
@@ -1432,6 +2077,8 @@ pub fn assess_liquidation_risk(loan_id: u64) -> Result<LiquidationRiskAssessment
         assessment_timestamp: current_time,
         collateral_value: loan.collateral_value_btc,
         outstanding_debt: remaining_debt,
+        liquidation_band: liquidation_band_for(remaining_debt, get_auto_liquidation_max_amount()),
+        pending_review: PENDING_LIQUIDATION_REVIEWS.with(|reviews| reviews.borrow().contains_key(&loan_id)),
     })
 }
 
@@ -1550,6 +2197,8 @@ pub struct LiquidationRiskAssessment {
     pub assessment_timestamp: u64,
     pub collateral_value: u64,
     pub outstanding_debt: u64,
+    pub liquidation_band: LiquidationBand,
+    pub pending_review: bool,
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -1565,7 +2214,837 @@ pub struct LiquidationStatistics {
     pub total_principal_loss: u64,
 }
 
-// Test module untuk liquidation system
+// ========== LIQUIDATION AUCTIONS ==========
+
+/// Liquidation penalty on outstanding debt in basis points (governance parameter)
+pub fn liquidation_penalty_bps() -> u64 {
+    crate::governance::get_protocol_parameter("liquidation_penalty_bps".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(LIQUIDATION_PENALTY_RATE * 100)
+}
+
+/// Apply sale proceeds: debt first, then the penalty, remainder back to the borrower.
+/// The penalty only comes out of what is left after the debt, so it can never exceed proceeds.
+/// Proceeds cover principal before accrued interest; only uncovered principal is a pool loss.
+pub fn compute_liquidation_settlement(
+    loan_id: u64,
+    auction_id: Option<u64>,
+    proceeds: u64,
+    outstanding_debt: u64,
+    principal_outstanding: u64,
+    penalty_bps: u64,
+    now: u64,
+) -> LiquidationSettlement {
+    let penalty = ((outstanding_debt as u128 * penalty_bps as u128) / 10_000) as u64;
+    let debt_repaid = proceeds.min(outstanding_debt);
+    let penalty_paid = (proceeds - debt_repaid).min(penalty);
+
+    LiquidationSettlement {
+        loan_id,
+        auction_id,
+        proceeds,
+        outstanding_debt,
+        debt_repaid,
+        penalty_bps,
+        penalty_paid,
+        residual_returned: proceeds - debt_repaid - penalty_paid,
+        shortfall: outstanding_debt - debt_repaid,
+        principal_loss: principal_outstanding.saturating_sub(debt_repaid),
+        settled_at: now,
+        proceeds_block_index: None,
+    }
+}
+
+/// Settle a liquidated loan from the proceeds of selling its collateral: repaid debt goes back
+/// to the pool, the penalty to treasury, any residual to the borrower's surplus balance, and
+/// a principal shortfall is recorded as a pool loss. This is the only place a liquidation
+/// loss is booked. Proceeds are only distributed once the ledger has confirmed receiving them.
+async fn settle_liquidation_proceeds(
+    loan_id: u64,
+    auction_id: Option<u64>,
+    proceeds: u64,
+    proceeds_block_index: Option<u64>,
+    caller: Principal,
+) -> Result<LiquidationSettlement, String> {
+    if proceeds > 0 && proceeds_block_index.is_none() {
+        return Err(format!("Proceeds of {} for loan #{} have not been received on the ledger", proceeds, loan_id));
+    }
+    let mut record = LIQUIDATION_RECORDS.with(|records| records.borrow().get(&loan_id))
+        .ok_or_else(|| "Liquidation record not found for loan".to_string())?;
+    if record.settlement.is_some() {
+        return Err(format!("Liquidation of loan #{} has already been settled", loan_id));
+    }
+    let loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    let principal_outstanding = loan.amount_approved.saturating_sub(loan.total_repaid.min(loan.amount_approved));
+
+    let mut settlement = compute_liquidation_settlement(
+        loan_id,
+        auction_id,
+        proceeds,
+        record.outstanding_debt,
+        principal_outstanding,
+        liquidation_penalty_bps(),
+        time(),
+    );
+    settlement.proceeds_block_index = proceeds_block_index;
+
+    // Mark the record settled before any inter-canister call so it cannot be settled twice
+    record.principal_loss = settlement.principal_loss;
+    record.settlement = Some(settlement.clone());
+    LIQUIDATION_RECORDS.with(|records| {
+        records.borrow_mut().insert(loan_id, record);
+    });
+
+    if settlement.debt_repaid > 0 {
+        if let Err(e) = crate::liquidity_management::apply_repayment_to_asset_pool(&loan.asset(), settlement.debt_repaid) {
+            log_audit_action(
+                caller,
+                "LIQUIDATION_RECOVERY_FAILED".to_string(),
+                format!("Failed to return {} satoshi of proceeds to the pool for loan #{}: {}", settlement.debt_repaid, loan_id, e),
+                false,
+            );
+        }
+    }
+
+    if settlement.penalty_paid > 0 && loan.asset() != Asset::CkBtc {
+        if let Err(e) = crate::liquidity_management::retain_fee_in_asset_pool(&loan.asset(), settlement.penalty_paid) {
+            log_audit_action(
+                caller,
+                "LIQUIDATION_PENALTY_COLLECTION_FAILED".to_string(),
+                format!("Failed to keep penalty for loan #{} in the {} pool: {}", loan_id, loan.asset().symbol(), e),
+                false,
+            );
+        }
+    } else if settlement.penalty_paid > 0 {
+        if let Err(e) = crate::treasury_management::process_liquidation_penalty(
+            loan_id,
+            settlement.penalty_paid,
+            format!("Liquidation settlement at {} bps", settlement.penalty_bps),
+        ).await {
+            log_audit_action(
+                caller,
+                "LIQUIDATION_PENALTY_COLLECTION_FAILED".to_string(),
+                format!("Failed to collect penalty for loan #{}: {}", loan_id, e),
+                false,
+            );
+        }
+    }
+
+    if settlement.residual_returned > 0 {
+        with_surplus_balances(&loan.asset(), |balances| {
+            let current = balances.get(&loan.borrower).unwrap_or(0);
+            balances.insert(loan.borrower, current + settlement.residual_returned);
+        });
+    }
+
+    if settlement.principal_loss > 0 {
+        if let Err(e) = record_liquidation_loss(loan_id, settlement.principal_loss, settlement.outstanding_debt).await {
+            log_audit_action(
+                caller,
+                "LIQUIDATION_LOSS_RECORDING_FAILED".to_string(),
+                format!("Failed to record liquidation loss for loan #{}: {}", loan_id, e),
+                false,
+            );
+        }
+    }
+
+    log_audit_action(
+        caller,
+        "LIQUIDATION_SETTLED".to_string(),
+        format!(
+            "Loan #{} settled from {} satoshi of proceeds: debt repaid {}, penalty {}, residual to borrower {}, shortfall {}, principal loss {}",
+            loan_id, settlement.proceeds, settlement.debt_repaid, settlement.penalty_paid,
+            settlement.residual_returned, settlement.shortfall, settlement.principal_loss
+        ),
+        true,
+    );
+
+    Ok(settlement)
+}
+
+/// Settle a liquidation whose collateral was sold outside the auction (admin only). The
+/// proceeds are pulled from `payer`'s ICRC-2 allowance before anything is credited; zero
+/// proceeds settle without a transfer and write off the whole principal outstanding.
+/// `created_at_time` is the ledger deduplication timestamp: retry a call whose outcome is
+/// unknown with the same value so the proceeds cannot be pulled twice.
+#[update]
+pub async fn settle_offchain_liquidation(
+    loan_id: u64,
+    proceeds: u64,
+    payer: Principal,
+    created_at_time: u64,
+) -> Result<LiquidationSettlement, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "settle_offchain_liquidation")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admin can settle liquidations".to_string());
+    }
+    let _guard = crate::helpers::ResourceGuard::acquire(format!("liquidation_settlement:{}", loan_id))?;
+
+    let record = LIQUIDATION_RECORDS.with(|records| records.borrow().get(&loan_id))
+        .ok_or_else(|| "Liquidation record not found for loan".to_string())?;
+    if record.settlement.is_some() {
+        return Err(format!("Liquidation of loan #{} has already been settled", loan_id));
+    }
+    if is_collateral_in_active_liquidation(record.collateral_nft_id) {
+        return Err(format!("Collateral for loan #{} is in an active auction", loan_id));
+    }
+    let asset = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?.asset();
+
+    let proceeds_block_index = if proceeds > 0 {
+        let memo = format!("Liquidation proceeds for loan #{}", loan_id);
+        match pull_liquidation_funds(&asset, payer, proceeds, &memo, created_at_time).await {
+            Ok(block_index) => Some(block_index),
+            Err(BidCollectionFailure::Rejected(reason)) => {
+                return Err(format!("Liquidation proceeds could not be collected from {}: {}", payer.to_text(), reason));
+            }
+            Err(BidCollectionFailure::Unknown(reason)) => {
+                return Err(format!(
+                    "Liquidation proceeds collection outcome unknown ({}); retry with the same created_at_time",
+                    reason
+                ));
+            }
+        }
+    } else {
+        None
+    };
+
+    settle_liquidation_proceeds(loan_id, None, proceeds, proceeds_block_index, caller).await
+}
+
+/// Every final liquidation settlement, for pool reconciliation
+pub fn get_all_liquidation_settlements() -> Vec<LiquidationSettlement> {
+    LIQUIDATION_RECORDS.with(|records| {
+        records.borrow().iter().filter_map(|(_, record)| record.settlement).collect()
+    })
+}
+
+/// Breakdown of how a liquidated loan's collateral proceeds were applied (borrower or admin)
+#[query]
+pub fn get_liquidation_settlement(loan_id: u64) -> Result<LiquidationSettlement, String> {
+    let caller = caller();
+    let loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    if loan.borrower != caller && !is_admin(&caller) {
+        return Err("Unauthorized: Only the borrower or admin can view this settlement".to_string());
+    }
+
+    LIQUIDATION_RECORDS.with(|records| records.borrow().get(&loan_id))
+        .ok_or_else(|| "Liquidation record not found for loan".to_string())?
+        .settlement
+        .ok_or_else(|| format!("Liquidation of loan #{} has not been settled yet", loan_id))
+}
+
+/// Whitelist a principal as a liquidation auction bidder (admin only)
+#[update]
+pub fn add_liquidation_bidder(bidder: Principal) -> Result<String, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "add_liquidation_bidder")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admin can manage liquidation bidders".to_string());
+    }
+
+    LIQUIDATION_BIDDERS.with(|bidders| {
+        bidders.borrow_mut().insert(bidder, time());
+    });
+
+    log_audit_action(
+        caller,
+        "LIQUIDATION_BIDDER_ADDED".to_string(),
+        format!("Bidder {} whitelisted for liquidation auctions", bidder.to_text()),
+        true,
+    );
+
+    Ok(format!("Bidder {} whitelisted", bidder.to_text()))
+}
+
+/// Remove a principal from the liquidation bidder whitelist (admin only)
+#[update]
+pub fn remove_liquidation_bidder(bidder: Principal) -> Result<String, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "remove_liquidation_bidder")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admin can manage liquidation bidders".to_string());
+    }
+
+    let removed = LIQUIDATION_BIDDERS.with(|bidders| bidders.borrow_mut().remove(&bidder));
+    if removed.is_none() {
+        return Err("Bidder is not whitelisted".to_string());
+    }
+
+    log_audit_action(
+        caller,
+        "LIQUIDATION_BIDDER_REMOVED".to_string(),
+        format!("Bidder {} removed from liquidation auctions", bidder.to_text()),
+        true,
+    );
+
+    Ok(format!("Bidder {} removed", bidder.to_text()))
+}
+
+/// Whitelisted liquidation bidders with the time each was added
+#[query]
+pub fn get_liquidation_bidders() -> Vec<(Principal, u64)> {
+    LIQUIDATION_BIDDERS.with(|bidders| bidders.borrow().iter().collect())
+}
+
+/// Whether an NFT is currently being auctioned off
+pub fn is_collateral_in_active_liquidation(nft_id: u64) -> bool {
+    LIQUIDATION_AUCTIONS.with(|auctions| {
+        auctions.borrow().iter().any(|(_, auction)| {
+            auction.nft_id == nft_id && auction.status == AuctionStatus::Active
+        })
+    })
+}
+
+fn is_whitelisted_bidder(bidder: &Principal) -> bool {
+    LIQUIDATION_BIDDERS.with(|bidders| bidders.borrow().contains_key(bidder))
+}
+
+/// List seized collateral for auction with the outstanding debt as reserve price
+#[update]
+pub fn start_liquidation_auction(loan_id: u64) -> Result<LiquidationAuction, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "start_liquidation_auction")?;
+    crate::feature_flags::ensure_feature_enabled(crate::feature_flags::FEATURE_LIQUIDATION_AUCTIONS)?;
+    if !is_admin(&caller) && !is_automated_system(&caller) {
+        return Err("Unauthorized: Only admin or automated system can start liquidation auctions".to_string());
+    }
+
+    let loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    if loan.status != LoanStatus::Defaulted {
+        return Err("Collateral must be seized through liquidation before auction".to_string());
+    }
+
+    let record = LIQUIDATION_RECORDS.with(|records| records.borrow().get(&loan_id))
+        .ok_or_else(|| "Liquidation record not found for loan".to_string())?;
+    if record.settlement.is_some() {
+        return Err(format!("Liquidation of loan #{} has already been settled", loan_id));
+    }
+
+    // Collateral from a cancelled or unsold auction may be listed again
+    let already_listed = LIQUIDATION_AUCTIONS.with(|auctions| {
+        auctions.borrow().iter().any(|(_, auction)| {
+            auction.loan_id == loan_id
+                && !matches!(auction.status, AuctionStatus::Cancelled | AuctionStatus::Unsold)
+        })
+    });
+    if already_listed {
+        return Err(format!("Collateral for loan #{} is already auctioned", loan_id));
+    }
+
+    let auction_id = LIQUIDATION_AUCTIONS.with(|auctions| {
+        auctions.borrow().last_key_value().map(|(id, _)| id + 1).unwrap_or(1)
+    });
+    let now = time();
+
+    let auction = LiquidationAuction {
+        auction_id,
+        loan_id,
+        nft_id: record.collateral_nft_id,
+        borrower: loan.borrower,
+        reserve_price: record.outstanding_debt,
+        started_at: now,
+        ends_at: now + LIQUIDATION_AUCTION_DURATION,
+        highest_bid: None,
+        bids: Vec::new(),
+        status: AuctionStatus::Active,
+        settlement: None,
+        bid_collection: None,
+    };
+
+    LIQUIDATION_AUCTIONS.with(|auctions| {
+        auctions.borrow_mut().insert(auction_id, auction.clone());
+    });
+
+    log_audit_action(
+        caller,
+        "LIQUIDATION_AUCTION_STARTED".to_string(),
+        format!(
+            "Auction #{} started for NFT #{} (loan #{}), reserve price {} satoshi",
+            auction_id, auction.nft_id, loan_id, auction.reserve_price
+        ),
+        true,
+    );
+
+    Ok(auction)
+}
+
+/// Place a bid on an active liquidation auction (whitelisted bidders only)
+#[update]
+pub fn place_liquidation_bid(auction_id: u64, amount: u64) -> Result<String, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "place_liquidation_bid")?;
+    crate::feature_flags::ensure_feature_enabled(crate::feature_flags::FEATURE_LIQUIDATION_AUCTIONS)?;
+    if !is_whitelisted_bidder(&caller) {
+        return Err("Unauthorized: Bidder is not whitelisted for liquidation auctions".to_string());
+    }
+
+    let mut auction = LIQUIDATION_AUCTIONS.with(|auctions| auctions.borrow().get(&auction_id))
+        .ok_or_else(|| "Auction not found".to_string())?;
+
+    if auction.status != AuctionStatus::Active {
+        return Err("Auction is not active".to_string());
+    }
+    if time() > auction.ends_at {
+        return Err("Auction bidding window has closed".to_string());
+    }
+    if amount < auction.reserve_price {
+        return Err(format!("Bid {} is below reserve price {}", amount, auction.reserve_price));
+    }
+    // Matching the highest bid is allowed; ties are broken at finalization, not by arrival order
+    if let Some(highest) = &auction.highest_bid {
+        if amount < highest.amount {
+            return Err(format!("Bid must be at least the current highest bid of {}", highest.amount));
+        }
+        if amount == highest.amount && auction.bids.iter().any(|bid| bid.bidder == caller && bid.amount == amount) {
+            return Err("You have already bid this amount".to_string());
+        }
+    }
+
+    let bid = LiquidationBid {
+        bidder: caller,
+        amount,
+        placed_at: time(),
+    };
+    auction.bids.push(bid.clone());
+    if auction.highest_bid.as_ref().map_or(true, |highest| amount > highest.amount) {
+        auction.highest_bid = Some(bid);
+    }
+
+    LIQUIDATION_AUCTIONS.with(|auctions| {
+        auctions.borrow_mut().insert(auction_id, auction);
+    });
+
+    log_audit_action(
+        caller,
+        "LIQUIDATION_BID_PLACED".to_string(),
+        format!("Bid of {} satoshi placed on auction #{}", amount, auction_id),
+        true,
+    );
+
+    Ok(format!("Bid of {} placed on auction #{}", amount, auction_id))
+}
+
+/// Distinct bidders tied at the highest amount among bids from `eligible` bidders, sorted by
+/// principal so the order does not depend on when each bid arrived
+pub fn top_bid_ties(bids: &[LiquidationBid], eligible: impl Fn(&Principal) -> bool) -> (Option<u64>, Vec<Principal>) {
+    let top = bids.iter()
+        .filter(|bid| eligible(&bid.bidder))
+        .map(|bid| bid.amount)
+        .max();
+    let mut tied: Vec<Principal> = match top {
+        Some(top) => bids.iter()
+            .filter(|bid| bid.amount == top && eligible(&bid.bidder))
+            .map(|bid| bid.bidder)
+            .collect(),
+        None => Vec::new(),
+    };
+    tied.sort_by(|a, b| a.as_slice().cmp(b.as_slice()));
+    tied.dedup();
+    (top, tied)
+}
+
+/// Index into `tied` picked by the first eight bytes of `seed`
+pub fn seeded_tie_break_index(seed: &[u8], tied: usize) -> usize {
+    if tied <= 1 {
+        return 0;
+    }
+    let mut bytes = [0u8; 8];
+    for (slot, byte) in bytes.iter_mut().zip(seed.iter()) {
+        *slot = *byte;
+    }
+    (u64::from_le_bytes(bytes) % tied as u64) as usize
+}
+
+/// Outcome of pulling a winning bid that did not produce a ledger block
+enum BidCollectionFailure {
+    /// The ledger refused the transfer; the bidder's funds did not move
+    Rejected(String),
+    /// The call failed or the ledger was unavailable; the transfer may or may not have happened
+    Unknown(String),
+}
+
+/// Pull the winning bid from the bidder's ICRC-2 allowance on the loan asset's ledger
+async fn pull_winning_bid(
+    auction_id: u64,
+    asset: &Asset,
+    collection: &BidCollection,
+) -> Result<u64, BidCollectionFailure> {
+    let memo = format!("Liquidation auction #{}", auction_id);
+    pull_liquidation_funds(asset, collection.bidder, collection.amount, &memo, collection.created_at).await
+}
+
+/// Pull `amount` of `asset` from `from`'s ICRC-2 allowance into the canister, returning the
+/// ledger block. Amounts are in the asset's base unit, like the loan's debt.
+async fn pull_liquidation_funds(
+    asset: &Asset,
+    from: Principal,
+    amount: u64,
+    memo: &str,
+    created_at: u64,
+) -> Result<u64, BidCollectionFailure> {
+    use crate::ckbtc_integration::{Account, TransferFromArgs, TransferFromError};
+    use candid::Nat;
+
+    let transfer_args = TransferFromArgs {
+        spender_subaccount: None,
+        from: Account {
+            owner: from,
+            subaccount: None,
+        },
+        to: Account {
+            owner: ic_cdk::api::canister_self(),
+            subaccount: None,
+        },
+        amount: Nat::from(amount),
+        fee: None,
+        memo: Some(crate::helpers::ledger_memo(memo)),
+        created_at_time: Some(created_at),
+    };
+
+    let call_started = time();
+    let call_result: ic_cdk::call::CallResult<(Result<Nat, TransferFromError>,)> =
+        ic_cdk::call(crate::helpers::ledger_principal_for(asset), "icrc2_transfer_from", (transfer_args,)).await;
+    crate::helpers::record_dependency_call(
+        crate::helpers::CKBTC_LEDGER_DEPENDENCY,
+        crate::helpers::elapsed_ms_since(call_started),
+        call_result.is_ok(),
+    );
+
+    match call_result {
+        Ok((Ok(block_index),)) => Ok(block_index.0.try_into().unwrap_or(0u64)),
+        // A retry of a transfer that already went through
+        Ok((Err(TransferFromError::Duplicate { duplicate_of }),)) => Ok(duplicate_of.0.try_into().unwrap_or(0u64)),
+        Ok((Err(TransferFromError::TemporarilyUnavailable),)) => {
+            Err(BidCollectionFailure::Unknown("ckBTC ledger temporarily unavailable".to_string()))
+        }
+        Ok((Err(transfer_error),)) => Err(BidCollectionFailure::Rejected(format!("{:?}", transfer_error))),
+        Err((rejection_code, msg)) => Err(BidCollectionFailure::Unknown(format!("{:?} - {}", rejection_code, msg))),
+    }
+}
+
+/// Settle an auction after its window closes. Only bids from bidders still whitelisted count;
+/// a tie at the top is broken with a `raw_rand` seed stored on the settlement so the draw can
+/// be replayed. The winning bid is pulled from the bidder's ICRC-2 allowance before any
+/// proceeds are distributed; a bidder whose transfer is refused is disqualified and the next
+/// finalization settles with the remaining bids. An auction without eligible bids closes as
+/// Unsold and books nothing: the collateral stays with the liquidation wallet until its sale
+/// is settled with `settle_offchain_liquidation`.
+#[update]
+pub async fn finalize_auction(auction_id: u64) -> Result<AuctionSettlement, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "finalize_auction")?;
+    if !is_admin(&caller) && !is_automated_system(&caller) {
+        return Err("Unauthorized: Only admin or automated system can finalize auctions".to_string());
+    }
+    let _guard = crate::helpers::ResourceGuard::acquire(format!("auction:{}", auction_id))?;
+
+    let mut auction = LIQUIDATION_AUCTIONS.with(|auctions| auctions.borrow().get(&auction_id))
+        .ok_or_else(|| "Auction not found".to_string())?;
+
+    if auction.status != AuctionStatus::Active {
+        return Err("Auction is not active".to_string());
+    }
+    if time() <= auction.ends_at {
+        return Err("Auction bidding window is still open".to_string());
+    }
+
+    // A collection started by an earlier finalization is resumed rather than redrawn
+    let mut collection = match auction.bid_collection.clone() {
+        Some(collection) => Some(collection),
+        None => {
+            let (top_amount, tied) = top_bid_ties(&auction.bids, is_whitelisted_bidder);
+            let (tie_break_seed, winner) = match tied.len() {
+                0 => (None, None),
+                1 => (None, Some(tied[0])),
+                _ => {
+                    let (seed,) = ic_cdk::api::management_canister::main::raw_rand()
+                        .await
+                        .map_err(|(code, message)| format!("Failed to draw tie-break seed: {:?} - {}", code, message))?;
+                    // Another call may have settled the auction while the seed was drawn
+                    auction = LIQUIDATION_AUCTIONS.with(|auctions| auctions.borrow().get(&auction_id))
+                        .ok_or_else(|| "Auction not found".to_string())?;
+                    if auction.status != AuctionStatus::Active {
+                        return Err("Auction is not active".to_string());
+                    }
+                    let winner = tied[seeded_tie_break_index(&seed, tied.len())];
+                    (Some(seed), Some(winner))
+                }
+            };
+            match (winner, top_amount) {
+                (Some(bidder), Some(amount)) => Some(BidCollection {
+                    bidder,
+                    amount,
+                    created_at: time(),
+                    ckbtc_block_index: None,
+                    tied_bidders: if tie_break_seed.is_some() { Some(tied.clone()) } else { None },
+                    tie_break_seed,
+                }),
+                _ => None,
+            }
+        }
+    };
+
+    if let Some(pending) = collection.as_mut().filter(|c| c.ckbtc_block_index.is_none()) {
+        // Persist the attempt first so a retry reuses its created_at and cannot pull twice
+        auction.bid_collection = Some(pending.clone());
+        LIQUIDATION_AUCTIONS.with(|auctions| {
+            auctions.borrow_mut().insert(auction_id, auction.clone());
+        });
+
+        let asset = get_loan(auction.loan_id).ok_or_else(|| "Loan not found".to_string())?.asset();
+        let pulled = pull_winning_bid(auction_id, &asset, pending).await;
+        auction = LIQUIDATION_AUCTIONS.with(|auctions| auctions.borrow().get(&auction_id))
+            .ok_or_else(|| "Auction not found".to_string())?;
+
+        match pulled {
+            Ok(block_index) => {
+                pending.ckbtc_block_index = Some(block_index);
+                auction.bid_collection = Some(pending.clone());
+                LIQUIDATION_AUCTIONS.with(|auctions| {
+                    auctions.borrow_mut().insert(auction_id, auction.clone());
+                });
+            }
+            Err(BidCollectionFailure::Rejected(reason)) => {
+                let bidder = pending.bidder;
+                auction.bids.retain(|bid| bid.bidder != bidder);
+                auction.highest_bid = auction.bids.iter().max_by_key(|bid| bid.amount).cloned();
+                auction.bid_collection = None;
+                LIQUIDATION_AUCTIONS.with(|auctions| {
+                    auctions.borrow_mut().insert(auction_id, auction.clone());
+                });
+                log_audit_action(
+                    caller,
+                    "LIQUIDATION_BID_COLLECTION_FAILED".to_string(),
+                    format!("Winning bid of {} from {} on auction #{} was refused by the ledger: {}; bidder disqualified",
+                        pending.amount, bidder.to_text(), auction_id, reason),
+                    false,
+                );
+                return Err(format!("Winning bid could not be collected ({}); finalize again to settle with the remaining bids", reason));
+            }
+            Err(BidCollectionFailure::Unknown(reason)) => {
+                log_audit_action(
+                    caller,
+                    "LIQUIDATION_BID_COLLECTION_UNKNOWN".to_string(),
+                    format!("Collection of {} from {} on auction #{} has an unknown outcome: {}",
+                        pending.amount, pending.bidder.to_text(), auction_id, reason),
+                    false,
+                );
+                return Err(format!("Winning bid collection outcome unknown ({}); finalize again to retry", reason));
+            }
+        }
+    }
+
+    // Proceeds are only booked once a winning bid has actually been collected
+    let collected = match collection {
+        Some(collected) => collected,
+        None => {
+            auction.status = AuctionStatus::Unsold;
+            LIQUIDATION_AUCTIONS.with(|auctions| {
+                auctions.borrow_mut().insert(auction_id, auction.clone());
+            });
+            log_audit_action(
+                caller,
+                "LIQUIDATION_AUCTION_UNSOLD".to_string(),
+                format!(
+                    "Auction #{} for loan #{} closed without eligible bids; NFT #{} remains with the liquidation wallet",
+                    auction_id, auction.loan_id, auction.nft_id
+                ),
+                true,
+            );
+            return Err(format!(
+                "Auction #{} closed without eligible bids; settle the collateral sale with settle_offchain_liquidation",
+                auction_id
+            ));
+        }
+    };
+    let (buyer, sale_price) = (collected.bidder, collected.amount);
+    let tie_break_seed = collected.tie_break_seed.clone();
+    let tied_bidders = collected.tied_bidders.clone();
+
+    // Nothing is booked for a sale whose collateral cannot be handed to the buyer
+    crate::rwa_nft::ensure_collateral_settleable(auction.nft_id, auction.loan_id)?;
+
+    let liquidation_settlement = settle_liquidation_proceeds(
+        auction.loan_id,
+        Some(auction_id),
+        sale_price,
+        collected.ckbtc_block_index,
+        caller,
+    ).await?;
+    let (debt_repaid, penalty_paid, borrower_surplus) = (
+        liquidation_settlement.debt_repaid,
+        liquidation_settlement.penalty_paid,
+        liquidation_settlement.residual_returned,
+    );
+    auction = LIQUIDATION_AUCTIONS.with(|auctions| auctions.borrow().get(&auction_id))
+        .ok_or_else(|| "Auction not found".to_string())?;
+
+    let settlement = AuctionSettlement {
+        buyer,
+        sale_price,
+        debt_repaid,
+        penalty_paid,
+        borrower_surplus,
+        settled_at: liquidation_settlement.settled_at,
+        tied_bidders: tied_bidders.clone(),
+        tie_break_seed: tie_break_seed.clone(),
+    };
+    if let Err(e) = crate::rwa_nft::settle_collateral_transfer(auction.nft_id, buyer, auction.loan_id) {
+        log_audit_action(
+            caller,
+            "LIQUIDATION_COLLATERAL_TRANSFER_FAILED".to_string(),
+            format!("Failed to transfer NFT #{} for auction #{}: {}", auction.nft_id, auction_id, e),
+            false,
+        );
+        return Err(format!("Proceeds settled but NFT #{} could not be transferred to the buyer: {}", auction.nft_id, e));
+    }
+
+    auction.status = AuctionStatus::Settled;
+    auction.settlement = Some(settlement.clone());
+
+    LIQUIDATION_AUCTIONS.with(|auctions| {
+        auctions.borrow_mut().insert(auction_id, auction.clone());
+    });
+
+    log_audit_action(
+        caller,
+        "LIQUIDATION_AUCTION_SETTLED".to_string(),
+        format!(
+            "Auction #{} for loan #{} settled at {} satoshi to {}: debt repaid {}, penalty {}, borrower surplus {}{}",
+            auction_id, auction.loan_id, sale_price, buyer.to_text(), debt_repaid, penalty_paid, borrower_surplus,
+            match (&tie_break_seed, &tied_bidders) {
+                (Some(seed), Some(tied)) => format!(", drawn from {} tied bidders with seed {}", tied.len(), hex::encode(seed)),
+                _ => String::new(),
+            }
+        ),
+        true,
+    );
+
+    Ok(settlement)
+}
+
+/// Get all auctions currently accepting bids
+#[query]
+pub fn get_active_auctions() -> Vec<LiquidationAuction> {
+    LIQUIDATION_AUCTIONS.with(|auctions| {
+        auctions.borrow()
+            .iter()
+            .filter(|(_, auction)| auction.status == AuctionStatus::Active)
+            .map(|(_, auction)| auction)
+            .collect()
+    })
+}
+
+/// Get a single liquidation auction
+#[query]
+pub fn get_liquidation_auction(auction_id: u64) -> Option<LiquidationAuction> {
+    LIQUIDATION_AUCTIONS.with(|auctions| auctions.borrow().get(&auction_id))
+}
+
+/// Run `f` on the surplus balances owed in `asset`; surplus is paid in the liquidated loan's asset
+fn with_surplus_balances<R>(
+    asset: &Asset,
+    f: impl FnOnce(&mut StableBTreeMap<Principal, u64, Memory>) -> R,
+) -> R {
+    match asset {
+        Asset::CkBtc => BORROWER_SURPLUS_BALANCES.with(|balances| f(&mut balances.borrow_mut())),
+        Asset::Icp => ICP_BORROWER_SURPLUS_BALANCES.with(|balances| f(&mut balances.borrow_mut())),
+    }
+}
+
+/// Get auction surplus in `asset` (ckBTC when omitted) credited to a borrower
+#[query]
+pub fn get_borrower_surplus_balance(borrower: Principal, asset: Option<Asset>) -> u64 {
+    with_surplus_balances(&asset.unwrap_or_default(), |balances| balances.get(&borrower).unwrap_or(0))
+}
+
+/// Withdraw the caller's auction surplus in `asset` (ckBTC when omitted) to their account on
+/// that asset's ledger. The balance is cleared before the transfer and restored only when the
+/// ledger refuses it; an unknown outcome leaves it cleared for an operator to reconcile against
+/// the ledger.
+#[update]
+pub async fn claim_liquidation_surplus(asset: Option<Asset>) -> Result<u64, String> {
+    use crate::ckbtc_integration::{Account, TransferArgs, TransferError};
+    use candid::Nat;
+
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "claim_liquidation_surplus")?;
+    let asset = asset.unwrap_or_default();
+    let _guard = crate::helpers::ResourceGuard::acquire(format!("surplus:{}", caller.to_text()))?;
+
+    let amount = with_surplus_balances(&asset, |balances| balances.get(&caller).unwrap_or(0));
+    if amount == 0 {
+        return Err("No liquidation surplus to claim".to_string());
+    }
+    with_surplus_balances(&asset, |balances| {
+        balances.remove(&caller);
+    });
+
+    let transfer_args = TransferArgs {
+        from_subaccount: None,
+        to: Account {
+            owner: caller,
+            subaccount: None,
+        },
+        amount: Nat::from(amount),
+        fee: None,
+        memo: Some("Liquidation surplus claim".as_bytes().to_vec()),
+        created_at_time: Some(time()),
+    };
+
+    let call_started = time();
+    let call_result: ic_cdk::call::CallResult<(Result<Nat, TransferError>,)> =
+        ic_cdk::call(crate::helpers::ledger_principal_for(&asset), "icrc1_transfer", (transfer_args,)).await;
+    crate::helpers::record_dependency_call(
+        crate::helpers::CKBTC_LEDGER_DEPENDENCY,
+        crate::helpers::elapsed_ms_since(call_started),
+        call_result.is_ok(),
+    );
+
+    match call_result {
+        Ok((Ok(block_index),)) => {
+            let block_index_u64: u64 = block_index.0.try_into().unwrap_or(0u64);
+            log_audit_action(
+                caller,
+                "LIQUIDATION_SURPLUS_CLAIMED".to_string(),
+                format!("Borrower claimed {} {} of liquidation surplus, block: {}", amount, asset.base_unit(), block_index_u64),
+                true,
+            );
+            Ok(block_index_u64)
+        }
+        Ok((Err(transfer_error),)) => {
+            // The ledger did not move the funds: the borrower keeps the balance
+            with_surplus_balances(&asset, |balances| {
+                let current = balances.get(&caller).unwrap_or(0);
+                balances.insert(caller, current + amount);
+            });
+            log_audit_action(
+                caller,
+                "LIQUIDATION_SURPLUS_CLAIM_FAILED".to_string(),
+                format!("Surplus claim of {} {} refused by the ledger: {:?}", amount, asset.base_unit(), transfer_error),
+                false,
+            );
+            Err(format!("Surplus transfer failed: {:?}", transfer_error))
+        }
+        Err((rejection_code, msg)) => {
+            log_audit_action(
+                caller,
+                "LIQUIDATION_SURPLUS_CLAIM_UNKNOWN".to_string(),
+                format!("Surplus claim of {} satoshi has an unknown outcome: {:?} - {}; balance held for reconciliation",
+                    amount, rejection_code, msg),
+                false,
+            );
+            Err(format!("Surplus transfer outcome unknown: {:?} - {}. Contact support to reconcile.", rejection_code, msg))
+        }
+    }
+}
+
+/// ckBTC auction surplus owed to all borrowers, held in the canister until claimed
+pub fn total_borrower_surplus_owed() -> u64 {
+    BORROWER_SURPLUS_BALANCES.with(|balances| balances.borrow().iter().map(|(_, amount)| amount).sum())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1591,6 +3070,9 @@ mod tests {
             total_repaid: 0,
             repayment_history: Vec::new(),
             last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
         }
     }
 
@@ -1611,6 +3093,9 @@ mod tests {
             total_repaid: 0,
             repayment_history: Vec::new(),
             last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
         }
     }
 
@@ -1656,6 +3141,7 @@ mod tests {
             liquidation_wallet: Principal::from_slice(&[2u8; 29]),
             processing_fee: LIQUIDATION_PROCESSING_FEE,
             recovery_expected: 70_000_000,
+            settlement: None,
         };
 
         assert_eq!(record.loan_id, 1);
@@ -1745,4 +3231,78 @@ mod tests {
         let unhealthy_ratio = collateral_value as f64 / large_debt as f64;
         assert!(unhealthy_ratio < MINIMUM_HEALTH_RATIO);
     }
+
+    #[test]
+    fn test_auction_proceeds_split() {
+        let split = |proceeds: u64| {
+            let s = compute_liquidation_settlement(1, Some(1), proceeds, 1_000_000, 900_000, LIQUIDATION_PENALTY_RATE * 100, 0);
+            (s.debt_repaid, s.penalty_paid, s.residual_returned)
+        };
+        // Debt 1_000_000 -> penalty 50_000
+        assert_eq!(split(1_000_000), (1_000_000, 0, 0));
+        assert_eq!(split(1_030_000), (1_000_000, 30_000, 0));
+        assert_eq!(split(1_200_000), (1_000_000, 50_000, 150_000));
+
+        // Shortfall never produces a surplus
+        assert_eq!(split(800_000), (800_000, 0, 0));
+    }
+
+    #[test]
+    fn test_settlement_shortfall_writes_off_uncovered_principal() {
+        // 1_000_000 debt of which 900_000 principal; proceeds cover principal before interest
+        let covered = compute_liquidation_settlement(1, None, 950_000, 1_000_000, 900_000, 500, 0);
+        assert_eq!(covered.shortfall, 50_000);
+        assert_eq!(covered.principal_loss, 0);
+
+        let short = compute_liquidation_settlement(1, None, 600_000, 1_000_000, 900_000, 500, 0);
+        assert_eq!(short.shortfall, 400_000);
+        assert_eq!(short.principal_loss, 300_000);
+        assert_eq!(short.penalty_paid, 0);
+
+        // Penalty is capped by what is left after the debt, and legs always sum to proceeds
+        let s = compute_liquidation_settlement(1, None, 1_100_000, 1_000_000, 900_000, 2_000, 0);
+        assert_eq!((s.penalty_paid, s.residual_returned), (100_000, 0));
+        assert_eq!(s.debt_repaid + s.penalty_paid + s.residual_returned, s.proceeds);
+    }
+
+    #[test]
+    fn test_settlement_requires_received_proceeds() {
+        // Proceeds without a ledger block are refused before anything is booked
+        let result = tokio_test::block_on(settle_liquidation_proceeds(1, None, 500_000, None, Principal::anonymous()));
+        assert!(result.unwrap_err().contains("not been received"));
+        assert!(LIQUIDATION_RECORDS.with(|records| records.borrow().get(&1)).is_none());
+    }
+
+    #[test]
+    fn test_emergency_liquidation_respects_liquidation_lock() {
+        let admin = Principal::from_slice(&[9u8; 29]);
+
+        // An attempt already in flight keeps the loan locked
+        let mut locked = create_overdue_test_loan();
+        locked.id = 41;
+        locked.status = LoanStatus::Liquidating;
+        store_loan(locked.clone()).unwrap();
+        LIQUIDATION_ATTEMPTS.with(|attempts| {
+            attempts.borrow_mut().insert(41, LiquidationAttempt {
+                loan_id: 41,
+                started_at: time(),
+                started_by: admin,
+                expires_at: time() + get_liquidation_lock_timeout(),
+            });
+        });
+        let result = tokio_test::block_on(emergency_liquidate_loan(41, "test".to_string(), admin));
+        assert!(result.unwrap_err().contains("already in progress"));
+        assert_eq!(get_loan(41).unwrap().status, LoanStatus::Liquidating);
+
+        // A failed collateral transfer returns the loan to Active and releases the lock
+        let mut active = create_overdue_test_loan();
+        active.id = 42;
+        active.nft_id = 4_242; // No such NFT
+        store_loan(active).unwrap();
+        let result = tokio_test::block_on(emergency_liquidate_loan(42, "test".to_string(), admin));
+        assert!(result.is_err());
+        assert_eq!(get_loan(42).unwrap().status, LoanStatus::Active);
+        assert!(get_liquidation_attempt_record(42).is_none());
+        assert!(LIQUIDATION_RECORDS.with(|records| records.borrow().get(&42)).is_none());
+    }
 }
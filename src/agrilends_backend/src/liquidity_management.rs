@@ -1,5 +1,6 @@
 use candid::{CandidType, Deserialize, Principal, Nat};
 use ic_cdk::call::CallResult; // Fix CallResult import
+use ic_cdk::api::call::RejectionCode;
 use ic_cdk::api::{time, canister_self};
 use ic_cdk::{call}; // Import call function
 use ic_cdk_macros::{query, update};
@@ -8,17 +9,31 @@ use crate::types::*;
 use crate::storage::{
     get_liquidity_pool, store_liquidity_pool, get_investor_balance_by_principal,
     store_investor_balance, is_transaction_processed, mark_transaction_processed,
-    has_investor_deposited_before, set_emergency_pause, is_emergency_paused, get_processed_transaction,
+    has_investor_deposited_before, set_emergency_pause, is_emergency_paused, set_origination_pause,
+    is_origination_paused, get_processed_transaction,
     remove_processed_transaction, store_disbursement_record, get_all_disbursement_records, 
-    get_all_processed_transactions
+    get_all_processed_transactions, store_liquidity_reservation, get_liquidity_reservation,
+    remove_liquidity_reservation, get_all_liquidity_reservations, next_position_offer_id,
+    store_position_offer, get_position_offer, get_all_position_offers, get_asset_pool, store_asset_pool,
+    get_asset_investor_balance, store_asset_investor_balance, get_transaction_usage, store_transaction_usage,
+    get_promo_credit_total, set_promo_credit_total
 };
 use crate::helpers::{check_rate_limit, check_rate_limit_with_operation, is_loan_manager_canister, is_admin, log_audit_action,
-    get_canister_config, set_canister_config};
-use crate::user_management::get_user_by_principal;
+    log_investor_data_access, get_canister_config, set_canister_config, ResourceGuard};
+use crate::user_management::{get_user_by_principal, KycTier};
+use crate::treasury_management::{
+    record_pool_fee_revenue, fund_promotional_credit, cover_loss_from_insurance_fund,
+    record_dust_donation, RevenueType
+};
+
+// A single loan may not take more than this share of total pool liquidity
+pub const MAX_SINGLE_LOAN_POOL_PERCENTAGE: u64 = 80;
 
-// ckBTC Ledger and Minter Constants
-const CKBTC_LEDGER_PRINCIPAL: &str = "mxzaz-hqaaa-aaaar-qaada-cai";
-const CKBTC_MINTER_PRINCIPAL: &str = "mqygn-kiaaa-aaaar-qaadq-cai";
+// Minimum withdrawal amount (1000 satoshi = 0.00001 BTC); smaller balances are dust
+pub const MIN_WITHDRAWAL_AMOUNT: u64 = 1000;
+
+// Admin dust sweeps leave balances touched within this window alone (30 days)
+const DUST_SWEEP_MIN_INACTIVITY_NANOS: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
 
 // ckBTC Integration structures
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -27,7 +42,7 @@ pub struct Account {
     pub subaccount: Option<Vec<u8>>,
 }
 
-#[derive(CandidType, Deserialize)]
+#[derive(CandidType, Deserialize, Clone)]
 pub struct TransferFromArgs {
     pub spender_subaccount: Option<Vec<u8>>,
     pub from: Account,
@@ -38,7 +53,7 @@ pub struct TransferFromArgs {
     pub created_at_time: Option<u64>,
 }
 
-#[derive(CandidType, Deserialize)]
+#[derive(CandidType, Deserialize, Clone)]
 pub struct TransferArgs {
     pub from_subaccount: Option<Vec<u8>>,
     pub to: Account,
@@ -48,7 +63,7 @@ pub struct TransferArgs {
     pub created_at_time: Option<u64>,
 }
 
-#[derive(CandidType, Deserialize)]
+#[derive(CandidType, Deserialize, Clone)]
 pub struct ApproveArgs {
     pub from_subaccount: Option<Vec<u8>>,
     pub spender: Account,
@@ -60,12 +75,23 @@ pub struct ApproveArgs {
     pub created_at_time: Option<u64>,
 }
 
-#[derive(CandidType, Deserialize)]
+#[derive(CandidType, Deserialize, Clone)]
 pub struct RetrieveBtcArgs {
     pub address: String,
     pub amount: u64,
 }
 
+#[derive(CandidType, Deserialize, Clone)]
+pub struct EstimateWithdrawalFeeArgs {
+    pub amount: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct WithdrawalFee {
+    pub minter_fee: u64,
+    pub bitcoin_fee: u64,
+}
+
 #[derive(CandidType, Deserialize, Debug)]
 pub enum TransferError {
     BadFee { expected_fee: Nat },
@@ -114,26 +140,725 @@ pub enum RetrieveBtcError {
     InsufficientFunds { balance: u64 },
 }
 
-/// Deposit liquidity to the pool
-/// This function handles incoming ckBTC deposits from investors
-/// Implements idempotency, strict validation, and comprehensive audit logging
+// Pool reconciliation structures
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PoolFieldReconciliation {
+    pub field: String,
+    pub stored_value: u64,
+    pub computed_value: u64,
+    pub delta: i64, // computed - stored
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PoolReconciliationReport {
+    pub fields: Vec<PoolFieldReconciliation>,
+    pub is_consistent: bool,
+    pub investors_scanned: u64,
+    pub disbursements_scanned: u64,
+    pub repayments_scanned: u64,
+    pub generated_at: u64,
+}
+
+// ========== CKBTC CALL RETRY ==========
+
+// Hard ceilings regardless of configuration
+pub const MAX_CKBTC_RETRY_ATTEMPTS: u64 = 10;
+const MAX_CKBTC_BACKOFF_ROUNDS: u64 = 32;
+
+/// Ledger/minter error variants that are safe to retry
+pub trait RetryableLedgerError {
+    /// What the call returns on success (the block index for ledger calls)
+    type Success;
+    
+    fn is_retryable(&self) -> bool;
+    
+    /// Block of an identical earlier call the ledger deduplicated this one against
+    fn duplicate_of(&self) -> Option<Self::Success> {
+        None
+    }
+}
+
+impl RetryableLedgerError for TransferError {
+    type Success = Nat;
+    
+    fn is_retryable(&self) -> bool {
+        matches!(self, TransferError::TemporarilyUnavailable)
+    }
+    
+    fn duplicate_of(&self) -> Option<Nat> {
+        match self {
+            TransferError::Duplicate { duplicate_of } => Some(duplicate_of.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl RetryableLedgerError for TransferFromError {
+    type Success = Nat;
+    
+    fn is_retryable(&self) -> bool {
+        matches!(self, TransferFromError::TemporarilyUnavailable)
+    }
+    
+    fn duplicate_of(&self) -> Option<Nat> {
+        match self {
+            TransferFromError::Duplicate { duplicate_of } => Some(duplicate_of.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl RetryableLedgerError for ApproveError {
+    type Success = Nat;
+    
+    fn is_retryable(&self) -> bool {
+        matches!(self, ApproveError::TemporarilyUnavailable)
+    }
+    
+    fn duplicate_of(&self) -> Option<Nat> {
+        match self {
+            ApproveError::Duplicate { duplicate_of } => Some(duplicate_of.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl RetryableLedgerError for RetrieveBtcError {
+    type Success = u64;
+    
+    fn is_retryable(&self) -> bool {
+        matches!(self, RetrieveBtcError::TemporarilyUnavailable(_))
+    }
+}
+
+/// Network-level rejects worth retrying. Unknown outcomes are safe to retry
+/// because every retried call reuses the same deduplicated arguments.
+pub fn is_retryable_reject(code: &RejectionCode) -> bool {
+    matches!(code, RejectionCode::SysTransient | RejectionCode::SysUnknown)
+}
+
+/// Exponential backoff in execution rounds for the given (1-based) attempt
+pub fn retry_backoff_rounds(base_delay_rounds: u64, attempt: u64) -> u64 {
+    let exponent = attempt.saturating_sub(1).min(16) as u32;
+    base_delay_rounds.saturating_mul(2u64.pow(exponent)).min(MAX_CKBTC_BACKOFF_ROUNDS)
+}
+
+/// Canisters cannot sleep; yield for roughly one round per cheap management call
+async fn wait_rounds(rounds: u64) {
+    for _ in 0..rounds {
+        let _ = ic_cdk::api::management_canister::main::raw_rand().await;
+    }
+}
+
+/// Call a ckBTC ledger/minter method with bounded retries on transient failures.
+/// `make_call` must issue the call with identical arguments (same created_at_time
+/// and memo) on every attempt so the ledger deduplicates and a retry can't double-spend.
+pub async fn call_ckbtc_with_retry<T, E, F, Fut>(
+    operation: &str,
+    make_call: F,
+) -> Result<(Result<T, E>,), (RejectionCode, String)>
+where
+    E: RetryableLedgerError<Success = T> + std::fmt::Debug,
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<(Result<T, E>,), (RejectionCode, String)>>,
+{
+    let config = get_canister_config();
+    let max_attempts = config.ckbtc_retry_max_attempts().clamp(1, MAX_CKBTC_RETRY_ATTEMPTS);
+    let base_delay_rounds = config.ckbtc_retry_base_delay_rounds();
+    
+    let (result, attempts) = retry_ledger_call(max_attempts, make_call, |attempt, reason| {
+        let delay = retry_backoff_rounds(base_delay_rounds, attempt);
+        log_audit_action(
+            ic_cdk::caller(),
+            "CKBTC_CALL_RETRY".to_string(),
+            format!(
+                "{} attempt {}/{} failed with retryable error ({}); retrying after {} round(s)",
+                operation, attempt, max_attempts, reason, delay
+            ),
+            false,
+        );
+        wait_rounds(delay)
+    }).await;
+    
+    if attempts > 1 {
+        log_audit_action(
+            ic_cdk::caller(),
+            "CKBTC_CALL_RETRY_FINISHED".to_string(),
+            format!("{} finished after {} attempt(s), success: {}", operation, attempts, matches!(result, Ok((Ok(_),)))),
+            matches!(result, Ok((Ok(_),))),
+        );
+    }
+    result
+}
+
+/// Retry loop behind `call_ckbtc_with_retry`; `before_retry` is awaited between attempts with the
+/// failed attempt's number and error. Returns the final result and the number of attempts made.
+/// A `Duplicate` answer to a retry means an earlier attempt whose outcome was unknown did land,
+/// so it is returned as that attempt's success with the original block.
+pub async fn retry_ledger_call<T, E, F, Fut, W, WFut>(
+    max_attempts: u64,
+    mut make_call: F,
+    mut before_retry: W,
+) -> (Result<(Result<T, E>,), (RejectionCode, String)>, u64)
+where
+    E: RetryableLedgerError<Success = T> + std::fmt::Debug,
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<(Result<T, E>,), (RejectionCode, String)>>,
+    W: FnMut(u64, String) -> WFut,
+    WFut: std::future::Future<Output = ()>,
+{
+    let mut attempt = 1;
+    loop {
+        let result = match make_call().await {
+            Ok((Err(e),)) if attempt > 1 => match e.duplicate_of() {
+                Some(original) => Ok((Ok(original),)),
+                None => Ok((Err(e),)),
+            },
+            other => other,
+        };
+        
+        let retry_reason = match &result {
+            Ok((Err(e),)) if e.is_retryable() => Some(format!("{:?}", e)),
+            Err((code, message)) if is_retryable_reject(code) => Some(format!("{:?}: {}", code, message)),
+            _ => None,
+        };
+        match retry_reason {
+            Some(reason) if attempt < max_attempts => {
+                before_retry(attempt, reason).await;
+                attempt += 1;
+            }
+            _ => return (result, attempt),
+        }
+    }
+}
+
+/// Canisters cannot sleep; yield for roughly one round per cheap management call
+async fn wait_rounds(rounds: u64) {
+    for _ in 0..rounds {
+        let _ = ic_cdk::api::management_canister::main::raw_rand().await;
+    }
+}
+
+/// Call a ckBTC ledger/minter method with bounded retries on transient failures.
+/// `make_call` must issue the call with identical arguments (same created_at_time
+/// and memo) on every attempt so the ledger deduplicates and a retry can't double-spend.
+pub async fn call_ckbtc_with_retry<T, E, F, Fut>(
+    operation: &str,
+    mut make_call: F,
+) -> Result<(Result<T, E>,), (RejectionCode, String)>
+where
+    E: RetryableLedgerError + std::fmt::Debug,
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<(Result<T, E>,), (RejectionCode, String)>>,
+{
+    let config = get_canister_config();
+    let max_attempts = config.ckbtc_retry_max_attempts().clamp(1, MAX_CKBTC_RETRY_ATTEMPTS);
+    let mut attempt = 1;
+    
+    loop {
+        let result = make_call().await;
+        
+        let retry_reason = match &result {
+            Ok((Err(e),)) if e.is_retryable() => Some(format!("{:?}", e)),
+            Err((code, message)) if is_retryable_reject(code) => Some(format!("{:?}: {}", code, message)),
+            _ => None,
+        };
+        
+        let reason = match retry_reason {
+            Some(reason) if attempt < max_attempts => reason,
+            _ => {
+                if attempt > 1 {
+                    log_audit_action(
+                        ic_cdk::caller(),
+                        "CKBTC_CALL_RETRY_FINISHED".to_string(),
+                        format!("{} finished after {} attempt(s), success: {}", operation, attempt, matches!(result, Ok((Ok(_),)))),
+                        matches!(result, Ok((Ok(_),))),
+                    );
+                }
+                return result;
+            }
+        };
+        
+        let delay = retry_backoff_rounds(config.ckbtc_retry_base_delay_rounds(), attempt);
+        log_audit_action(
+            ic_cdk::caller(),
+            "CKBTC_CALL_RETRY".to_string(),
+            format!(
+                "{} attempt {}/{} failed with retryable error ({}); retrying after {} round(s)",
+                operation, attempt, max_attempts, reason, delay
+            ),
+            false,
+        );
+        
+        wait_rounds(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Calculate a basis-point pool fee for the given amount
+pub fn calculate_pool_fee(amount: u64, fee_bps: u64) -> u64 {
+    crate::helpers::rounded_share(amount, fee_bps, BASIS_POINTS_SCALE)
+}
+
+// ========== INVESTOR KYC GATING ==========
+
+const DEFAULT_KYC_VERIFIED_DEPOSIT_THRESHOLD: u64 = 1_000_000_000; // 10 BTC cumulative
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum DepositError {
+    /// The deposit needs a higher KYC tier; the frontend should prompt for verification
+    KycRequired {
+        current_tier: KycTier,
+        required_tier: KycTier,
+        cumulative_deposits_after: u64,
+        verified_deposit_threshold: u64,
+    },
+    /// The deposit breaks a per-transaction or rolling 24h limit
+    LimitExceeded(TransactionLimitBreach),
+    Rejected(String),
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct KycStatus {
+    pub tier: KycTier,
+    pub updated_at: Option<u64>,
+    pub cumulative_deposits: u64,
+    pub basic_tier_required: bool,          // Any deposit needs at least Basic, unless grandfathered
+    pub verified_deposit_threshold: u64,    // Cumulative deposits above this need Verified
+    pub remaining_before_verified: u64,
+}
+
+pub fn get_kyc_verified_deposit_threshold() -> u64 {
+    crate::governance::get_protocol_parameter("kyc_verified_deposit_threshold".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_KYC_VERIFIED_DEPOSIT_THRESHOLD)
+}
+
+/// Minimum KYC tier for a deposit that brings cumulative deposits to `cumulative_after`
+pub fn required_kyc_tier_for_deposit(cumulative_after: u64, verified_threshold: u64) -> KycTier {
+    if cumulative_after > verified_threshold {
+        KycTier::Verified
+    } else {
+        KycTier::Basic
+    }
+}
+
+/// Investors who deposited before KYC gating and were never assigned a tier keep depositing as
+/// Basic; the Verified threshold still applies to them. A tier set by an admin, even None,
+/// always takes precedence.
+pub fn is_grandfathered_investor(kyc_tier: &Option<KycTier>, cumulative_deposits_before: u64) -> bool {
+    kyc_tier.is_none() && cumulative_deposits_before > 0
+}
+
+/// Tier a deposit is checked against: the user's own, or Basic for a grandfathered investor
+pub fn deposit_kyc_tier(kyc_tier: &Option<KycTier>, cumulative_deposits_before: u64) -> KycTier {
+    if is_grandfathered_investor(kyc_tier, cumulative_deposits_before) {
+        KycTier::Basic
+    } else {
+        kyc_tier.clone().unwrap_or(KycTier::None)
+    }
+}
+
+/// KYC tier and deposit limits for the calling investor
+#[query]
+pub fn get_my_kyc_status() -> Result<KycStatus, String> {
+    let caller = ic_cdk::caller();
+    let user = get_user_by_principal(&caller).ok_or("User not registered. Please register first")?;
+    let cumulative_deposits = get_investor_balance_by_principal(caller)
+        .map(|balance| balance.total_deposited)
+        .unwrap_or(0);
+    let verified_deposit_threshold = get_kyc_verified_deposit_threshold();
+
+    Ok(KycStatus {
+        tier: user.effective_kyc_tier(),
+        updated_at: user.kyc_updated_at,
+        cumulative_deposits,
+        basic_tier_required: !is_grandfathered_investor(&user.kyc_tier, cumulative_deposits),
+        verified_deposit_threshold,
+        remaining_before_verified: verified_deposit_threshold.saturating_sub(cumulative_deposits),
+    })
+}
+
+/// Value of `amount` base units of `asset` in satoshi, converted through both rupiah rates
+fn asset_amount_in_satoshi(asset: &Asset, amount: u64) -> Result<u64, String> {
+    if *asset == Asset::CkBtc || amount == 0 {
+        return Ok(amount);
+    }
+    let rupiah = crate::oracle::get_asset_idr_rate(asset)?.satoshi_to_idr(Satoshi(amount))?;
+    Ok(crate::oracle::get_asset_idr_rate(&Asset::CkBtc)?.idr_to_satoshi(rupiah)?.0)
+}
+
+/// Lifetime deposits across all assets valued in satoshi, counting `pending` more of `asset`
+fn cumulative_deposits_in_satoshi(investor: Principal, asset: &Asset, pending: u64) -> Result<u64, String> {
+    let mut total: u64 = 0;
+    for held in Asset::ALL.iter() {
+        let mut deposited = get_asset_investor_balance(held, investor)
+            .map(|balance| balance.total_deposited)
+            .unwrap_or(0);
+        // Positions are only traded in the ckBTC pool
+        if *held == Asset::CkBtc {
+            deposited = deposited.saturating_add(purchased_position_amount(investor));
+        }
+        if held == asset {
+            deposited = deposited.saturating_add(pending);
+        }
+        total = total.saturating_add(asset_amount_in_satoshi(held, deposited)?);
+    }
+    Ok(total)
+}
+
+// ========== INVESTOR TRANSACTION LIMITS ==========
+
+pub const TRANSACTION_LIMIT_WINDOW_NANOS: u64 = 24 * 60 * 60 * 1_000_000_000;
+// u64::MAX leaves a limit off, which is the default
+pub const UNLIMITED: u64 = u64::MAX;
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionDirection {
+    Deposit,
+    Withdrawal,
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionLimitKind {
+    SingleDeposit,
+    DailyDeposit,
+    SingleWithdrawal,
+    DailyWithdrawal,
+}
+
+/// Which limit a deposit or withdrawal hit. Amounts are in satoshi.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct TransactionLimitBreach {
+    pub kind: TransactionLimitKind,
+    pub limit: u64,
+    pub attempted: u64,
+    pub used_in_window: u64,  // Zero for single-transaction limits
+    pub remaining: u64,
+    pub resets_at: Option<u64>, // When enough of the window ages out for the attempt to fit; None if it never can
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum WithdrawalError {
+    LimitExceeded(TransactionLimitBreach),
+    Rejected(String),
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct DirectionLimits {
+    pub max_single: u64,
+    pub max_daily: u64,
+    pub used_last_24h: u64,
+    pub remaining_today: u64,
+    pub next_release_at: Option<u64>, // When the oldest counted transaction leaves the window
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct TransactionLimitsView {
+    pub kyc_tier: KycTier,
+    pub deposit: DirectionLimits,
+    pub withdrawal: DirectionLimits,
+}
+
+fn limit_parameter(key: &str, default: u64) -> u64 {
+    crate::governance::get_protocol_parameter(key.to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(default)
+}
+
+/// Limit for `tier`; Verified investors get the base limit scaled by `verified_scale_bps`
+pub fn scaled_transaction_limit(limit: u64, tier: &KycTier, verified_scale_bps: u64) -> u64 {
+    if limit == UNLIMITED || *tier != KycTier::Verified {
+        return limit;
+    }
+    (limit as u128 * verified_scale_bps as u128 / BASIS_POINTS_SCALE as u128).min(UNLIMITED as u128) as u64
+}
+
+/// (single, daily) limits in satoshi for `direction` at `tier`
+pub fn transaction_limits_for(direction: TransactionDirection, tier: &KycTier) -> (u64, u64) {
+    let (single_key, daily_key) = match direction {
+        TransactionDirection::Deposit => ("max_single_deposit", "max_daily_deposit"),
+        TransactionDirection::Withdrawal => ("max_single_withdrawal", "max_daily_withdrawal"),
+    };
+    let scale = limit_parameter("verified_transaction_limit_scale_bps", BASIS_POINTS_SCALE);
+    (
+        scaled_transaction_limit(limit_parameter(single_key, UNLIMITED), tier, scale),
+        scaled_transaction_limit(limit_parameter(daily_key, UNLIMITED), tier, scale),
+    )
+}
+
+fn in_limit_window(timestamp: u64, now: u64) -> bool {
+    timestamp.saturating_add(TRANSACTION_LIMIT_WINDOW_NANOS) > now
+}
+
+/// (timestamp, satoshi) entries still inside the window at `now`
+pub fn entries_in_window(entries: &[(u64, u64)], now: u64) -> Vec<(u64, u64)> {
+    entries.iter()
+        .copied()
+        .filter(|(timestamp, _)| in_limit_window(*timestamp, now))
+        .collect()
+}
+
+/// Earliest time the window has room for `attempted` under `limit`, as its oldest entries age out
+pub fn daily_capacity_resets_at(window: &[(u64, u64)], limit: u64, attempted: u64) -> Option<u64> {
+    if attempted > limit {
+        return None;
+    }
+    let mut sorted = window.to_vec();
+    sorted.sort_by_key(|(timestamp, _)| *timestamp);
+    let mut used: u64 = sorted.iter().map(|(_, amount)| *amount).fold(0, u64::saturating_add);
+    for (timestamp, amount) in sorted {
+        used = used.saturating_sub(amount);
+        if used.saturating_add(attempted) <= limit {
+            return Some(timestamp.saturating_add(TRANSACTION_LIMIT_WINDOW_NANOS));
+        }
+    }
+    None
+}
+
+/// The limit `attempted` satoshi would break given the `window` already used, if any
+pub fn check_transaction_limits(
+    direction: TransactionDirection,
+    attempted: u64,
+    max_single: u64,
+    max_daily: u64,
+    window: &[(u64, u64)],
+) -> Option<TransactionLimitBreach> {
+    let (single_kind, daily_kind) = match direction {
+        TransactionDirection::Deposit => (TransactionLimitKind::SingleDeposit, TransactionLimitKind::DailyDeposit),
+        TransactionDirection::Withdrawal => (TransactionLimitKind::SingleWithdrawal, TransactionLimitKind::DailyWithdrawal),
+    };
+    if attempted > max_single {
+        return Some(TransactionLimitBreach {
+            kind: single_kind,
+            limit: max_single,
+            attempted,
+            used_in_window: 0,
+            remaining: max_single,
+            resets_at: None,
+        });
+    }
+    let used: u64 = window.iter().map(|(_, amount)| *amount).fold(0, u64::saturating_add);
+    if used.saturating_add(attempted) > max_daily {
+        return Some(TransactionLimitBreach {
+            kind: daily_kind,
+            limit: max_daily,
+            attempted,
+            used_in_window: used,
+            remaining: max_daily.saturating_sub(used),
+            resets_at: daily_capacity_resets_at(window, max_daily, attempted),
+        });
+    }
+    None
+}
+
+fn usage_entries(usage: &Option<TransactionUsage>, direction: TransactionDirection) -> Vec<TransactionUsageEntry> {
+    usage.as_ref()
+        .map(|usage| match direction {
+            TransactionDirection::Deposit => usage.deposits.clone(),
+            TransactionDirection::Withdrawal => usage.withdrawals.clone(),
+        })
+        .unwrap_or_default()
+}
+
+/// The investor's entries for `direction` inside the window, valued in satoshi
+fn usage_window_in_satoshi(investor: Principal, direction: TransactionDirection, now: u64) -> Result<Vec<(u64, u64)>, String> {
+    let mut valued = Vec::new();
+    for entry in usage_entries(&get_transaction_usage(investor), direction) {
+        if in_limit_window(entry.timestamp, now) {
+            valued.push((entry.timestamp, asset_amount_in_satoshi(&entry.asset, entry.amount)?));
+        }
+    }
+    Ok(valued)
+}
+
+/// The limit a transaction of `amount` in `asset` would break. Nothing is valued while both
+/// limits for the direction are off.
+fn transaction_limit_breach(
+    investor: Principal,
+    direction: TransactionDirection,
+    asset: &Asset,
+    amount: u64,
+    tier: &KycTier,
+    now: u64,
+) -> Result<Option<TransactionLimitBreach>, String> {
+    let (max_single, max_daily) = transaction_limits_for(direction, tier);
+    if max_single == UNLIMITED && max_daily == UNLIMITED {
+        return Ok(None);
+    }
+    let attempted = asset_amount_in_satoshi(asset, amount)?;
+    let window = usage_window_in_satoshi(investor, direction, now)?;
+    Ok(check_transaction_limits(direction, attempted, max_single, max_daily, &window))
+}
+
+/// Count a transaction toward the investor's window before its ledger call, dropping entries
+/// that aged out. Returns the entry's timestamp for `release_transaction_usage`.
+pub fn reserve_transaction_usage(investor: Principal, direction: TransactionDirection, asset: Asset, amount: u64, now: u64) -> u64 {
+    let mut usage = get_transaction_usage(investor).unwrap_or(TransactionUsage {
+        investor,
+        deposits: Vec::new(),
+        withdrawals: Vec::new(),
+    });
+    let in_window = |entry: &TransactionUsageEntry| in_limit_window(entry.timestamp, now);
+    usage.deposits.retain(in_window);
+    usage.withdrawals.retain(in_window);
+    let entry = TransactionUsageEntry { asset, amount, timestamp: now };
+    match direction {
+        TransactionDirection::Deposit => usage.deposits.push(entry),
+        TransactionDirection::Withdrawal => usage.withdrawals.push(entry),
+    }
+    store_transaction_usage(usage);
+    now
+}
+
+/// Take back a reserved entry when its transaction failed
+pub fn release_transaction_usage(investor: Principal, direction: TransactionDirection, asset: Asset, amount: u64, reserved_at: u64) {
+    let Some(mut usage) = get_transaction_usage(investor) else {
+        return;
+    };
+    let entries = match direction {
+        TransactionDirection::Deposit => &mut usage.deposits,
+        TransactionDirection::Withdrawal => &mut usage.withdrawals,
+    };
+    let reserved = TransactionUsageEntry { asset, amount, timestamp: reserved_at };
+    if let Some(position) = entries.iter().rposition(|entry| *entry == reserved) {
+        entries.remove(position);
+        store_transaction_usage(usage);
+    }
+}
+
+/// The investor's counted usage for `direction` in the asset's base unit (reserved entries included)
+pub fn transaction_usage_entries(investor: Principal, direction: TransactionDirection) -> Vec<TransactionUsageEntry> {
+    usage_entries(&get_transaction_usage(investor), direction)
+}
+
+fn direction_limits(investor: Principal, direction: TransactionDirection, tier: &KycTier, now: u64) -> Result<DirectionLimits, String> {
+    let (max_single, max_daily) = transaction_limits_for(direction, tier);
+    let window = usage_window_in_satoshi(investor, direction, now)?;
+    let used_last_24h = window.iter().map(|(_, amount)| *amount).fold(0, u64::saturating_add);
+    Ok(DirectionLimits {
+        max_single,
+        max_daily,
+        used_last_24h,
+        remaining_today: max_daily.saturating_sub(used_last_24h),
+        next_release_at: window.iter().map(|(timestamp, _)| timestamp + TRANSACTION_LIMIT_WINDOW_NANOS).min(),
+    })
+}
+
+/// The caller's deposit and withdrawal limits and what is left of them in the rolling 24 hours
+#[query]
+pub fn get_my_transaction_limits() -> Result<TransactionLimitsView, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::reject_anonymous(&caller)?;
+    let kyc_tier = get_user_by_principal(&caller).map(|user| user.effective_kyc_tier()).unwrap_or(KycTier::None);
+    let now = time();
+    Ok(TransactionLimitsView {
+        deposit: direction_limits(caller, TransactionDirection::Deposit, &kyc_tier, now)?,
+        withdrawal: direction_limits(caller, TransactionDirection::Withdrawal, &kyc_tier, now)?,
+        kyc_tier,
+    })
+}
+
+/// Deposit liquidity to the pool of `asset` (ckBTC when omitted)
+/// Rejects deposits from investors below the required KYC tier with `DepositError::KycRequired`
+/// and deposits over a transaction limit with `DepositError::LimitExceeded`; every other failure
+/// is reported as `DepositError::Rejected`.
 #[update]
-pub async fn deposit_liquidity(amount: u64, tx_id: u64) -> Result<String, String> {
+pub async fn deposit_liquidity(amount: Satoshi, tx_id: u64, asset: Option<Asset>) -> Result<String, DepositError> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "deposit_liquidity").map_err(DepositError::Rejected)?;
+    let asset = asset.unwrap_or_default();
+
+    // Held until the deposit settles so neither the investor's balance nor the tx_id can be
+    // processed twice while the ledger call is awaited
+    let _investor_guard = ResourceGuard::acquire(investor_resource(caller)).map_err(DepositError::Rejected)?;
+    let _tx_guard = ResourceGuard::acquire(format!("deposit_tx:{}", tx_id)).map_err(DepositError::Rejected)?;
+
+    // Replays of an already processed transaction are answered by process_deposit
+    let is_new_transaction = !is_transaction_processed(tx_id);
+    if is_new_transaction {
+        if let Some(user) = get_user_by_principal(&caller) {
+            // KYC thresholds are in satoshi, so ICP deposits count at their ckBTC value
+            let cumulative_deposits_before = cumulative_deposits_in_satoshi(caller, &asset, 0)
+                .map_err(DepositError::Rejected)?;
+            let current_tier = deposit_kyc_tier(&user.kyc_tier, cumulative_deposits_before);
+            let cumulative_deposits_after = cumulative_deposits_in_satoshi(caller, &asset, amount.0)
+                .map_err(DepositError::Rejected)?;
+            let verified_deposit_threshold = get_kyc_verified_deposit_threshold();
+            let required_tier = required_kyc_tier_for_deposit(cumulative_deposits_after, verified_deposit_threshold);
+
+            if current_tier < required_tier {
+                log_audit_action(
+                    caller,
+                    "LIQUIDITY_DEPOSIT_KYC_REQUIRED".to_string(),
+                    format!(
+                        "Deposit of {} blocked: KYC tier {:?}, {:?} required for cumulative deposits of {}",
+                        amount, current_tier, required_tier, cumulative_deposits_after
+                    ),
+                    false,
+                );
+                return Err(DepositError::KycRequired {
+                    current_tier,
+                    required_tier,
+                    cumulative_deposits_after,
+                    verified_deposit_threshold,
+                });
+            }
+
+            let breach = transaction_limit_breach(caller, TransactionDirection::Deposit, &asset, amount.0, &current_tier, time())
+                .map_err(DepositError::Rejected)?;
+            if let Some(breach) = breach {
+                log_audit_action(
+                    caller,
+                    "LIQUIDITY_DEPOSIT_LIMIT_EXCEEDED".to_string(),
+                    format!(
+                        "Deposit of {} blocked by {:?} limit of {} satoshi ({} used in the last 24h)",
+                        amount, breach.kind, breach.limit, breach.used_in_window
+                    ),
+                    false,
+                );
+                return Err(DepositError::LimitExceeded(breach));
+            }
+        }
+    }
+
+    if asset == Asset::Icp {
+        crate::feature_flags::ensure_feature_enabled(crate::feature_flags::FEATURE_ICP_POOL)
+            .map_err(DepositError::Rejected)?;
+    }
+
+    // Counted before the ledger call so a deposit checked meanwhile sees it; taken back on failure
+    let reserved_at = is_new_transaction
+        .then(|| reserve_transaction_usage(caller, TransactionDirection::Deposit, asset, amount.0, time()));
+    let result = process_deposit(amount, tx_id, asset).await;
+    if let (Err(_), Some(reserved_at)) = (&result, reserved_at) {
+        release_transaction_usage(caller, TransactionDirection::Deposit, asset, amount.0, reserved_at);
+    }
+    result.map_err(DepositError::Rejected)
+}
+
+/// Deposit processing: handles incoming ckBTC or ICP deposits from investors
+/// Implements idempotency, strict validation, and comprehensive audit logging
+async fn process_deposit(amount: Satoshi, tx_id: u64, asset: Asset) -> Result<String, String> {
+    let Satoshi(amount) = amount;
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "deposit_liquidity")?;
+    crate::helpers::ensure_operation_available("deposit_liquidity")?;
     
     // Check if emergency pause is active
     if is_emergency_paused() {
         return Err("Pool operations are currently paused".to_string());
     }
     
+    // Verify caller is a registered, active investor
+    crate::validation::require_role(&caller, crate::validation::CallerRole::Investor)?;
+    
     // Validate input parameters
-    if amount == 0 {
-        return Err("Amount must be greater than zero".to_string());
-    }
+    crate::validation::require_positive(amount, "Amount")?;
     
-    // Check minimum deposit amount (0.001 BTC = 100,000 satoshi)
-    if amount < 100_000 {
-        return Err("Amount must be at least 0.001 BTC (100,000 satoshi)".to_string());
+    // Check minimum deposit amount (0.001 BTC = 100,000 satoshi, 0.1 ICP = 10,000,000 e8s)
+    if amount < asset.min_amount() {
+        return Err(format!("Amount must be at least {} {} base units", asset.min_amount(), asset.symbol()));
     }
     
     // Check for idempotency - prevent duplicate transactions
@@ -149,25 +874,11 @@ pub async fn deposit_liquidity(amount: u64, tx_id: u64) -> Result<String, String
         return Ok("Transaction already processed".to_string());
     }
     
-    // Verify caller is registered as investor
-    match get_user_by_principal(&caller) {
-        Some(user) => {
-            if !user.is_active {
-                return Err("Account is not active".to_string());
-            }
-            if user.role != crate::user_management::Role::Investor {
-                return Err("Only investors can deposit liquidity".to_string());
-            }
-        }
-        None => return Err("User not registered. Please register first".to_string()),
-    }
-    
     // Rate limiting check
     check_rate_limit(&caller, 10)?; // Max 10 calls per minute
     
-    // Prepare ckBTC transfer from caller to this canister
-    let ckbtc_ledger = Principal::from_text(CKBTC_LEDGER_PRINCIPAL)
-        .map_err(|_| "Invalid ckBTC ledger principal")?;
+    // Prepare the ledger transfer from caller to this canister
+    let ledger = crate::helpers::ledger_principal_for(&asset);
     
     let canister_account = Account {
         owner: canister_self(),
@@ -185,35 +896,38 @@ pub async fn deposit_liquidity(amount: u64, tx_id: u64) -> Result<String, String
         to: canister_account,
         amount: Nat::from(amount),
         fee: None,
-        memo: Some(format!("Liquidity deposit - tx_id: {}", tx_id).as_bytes().to_vec()),
+        memo: Some(crate::helpers::ledger_memo(&format!("Liquidity deposit {}", tx_id))),
         created_at_time: Some(time()),
     };
     
     // Execute the transfer
-    let call_result: Result<(Result<Nat, TransferFromError>,), _> = 
-        call(ckbtc_ledger, "icrc2_transfer_from", (transfer_args,)).await;
+    // The ICP ledger speaks ICRC-2 as well, so the same retrying call covers both assets
+    let call_result: Result<(Result<Nat, TransferFromError>,), _> = call_ckbtc_with_retry(
+        "icrc2_transfer_from",
+        || call(ledger, "icrc2_transfer_from", (transfer_args.clone(),)),
+    ).await;
     
     match call_result {
         Ok((Ok(block_index),)) => {
             // Transfer successful, update pool state
             let block_idx = block_index.0.try_into().unwrap_or(0u64);
             
-            // Update total liquidity
-            let mut pool = get_liquidity_pool();
-            pool.total_liquidity += amount;
-            pool.available_liquidity += amount;
-            pool.updated_at = time();
-            
-            // Update investor count if this is first deposit
-            let is_first_deposit = !has_investor_deposited_before(caller);
-            if is_first_deposit {
-                pool.total_investors += 1;
-            }
+            // Deduct deposit fee (if configured) and route it to treasury.
+            // The treasury is ckBTC-denominated, so ICP deposits are not charged a fee.
+            let deposit_fee = match asset {
+                Asset::CkBtc => calculate_pool_fee(amount, get_canister_config().deposit_fee_bps()),
+                Asset::Icp => 0,
+            };
+            let net_amount = Satoshi(amount).checked_sub(Satoshi(deposit_fee))?;
             
-            store_liquidity_pool(pool)?;
+            // Update total liquidity and, on the first deposit into this asset's pool, the investor count
+            let existing_balance = get_asset_investor_balance(&asset, caller);
+            let is_first_deposit = existing_balance.is_none();
+            credit_deposit_to_pool(&asset, net_amount.0, is_first_deposit)?;
+            let net_amount = net_amount.0;
             
             // Update investor balance
-            let mut investor_balance = get_investor_balance_for_principal(caller).unwrap_or(InvestorBalance {
+            let mut investor_balance = existing_balance.unwrap_or(InvestorBalance {
                 investor: caller,
                 balance: 0,
                 deposits: Vec::new(),
@@ -222,18 +936,21 @@ pub async fn deposit_liquidity(amount: u64, tx_id: u64) -> Result<String, String
                 total_withdrawn: 0,
                 first_deposit_at: time(),
                 last_activity_at: time(),
+                total_fees_paid: Some(0),
             });
             
             // Add deposit record
             let deposit_record = DepositRecord {
                 investor: caller,
-                amount,
+                amount: net_amount,
                 ckbtc_block_index: block_idx,
                 timestamp: time(),
+                fee: Some(deposit_fee),
             };
             
-            investor_balance.balance += amount;
-            investor_balance.total_deposited += amount;
+            investor_balance.balance = Satoshi(investor_balance.balance).checked_add(Satoshi(net_amount))?.0;
+            investor_balance.total_deposited = Satoshi(investor_balance.total_deposited).checked_add(Satoshi(net_amount))?.0;
+            investor_balance.add_fee_paid(deposit_fee);
             investor_balance.deposits.push(deposit_record);
             investor_balance.last_activity_at = time();
             
@@ -243,16 +960,30 @@ pub async fn deposit_liquidity(amount: u64, tx_id: u64) -> Result<String, String
             }
             
             // Store updated investor balance
-            store_investor_balance(investor_balance)?;
+            store_asset_investor_balance(&asset, investor_balance)?;
+            
+            // Route deposit fee to treasury. The funds are already in the canister and the investor
+            // is credited, so a booking failure is logged for reconciliation rather than returned.
+            if deposit_fee > 0 {
+                if let Err(e) = record_pool_fee_revenue(deposit_fee, RevenueType::DepositFee) {
+                    log_audit_action(
+                        caller,
+                        "LIQUIDITY_DEPOSIT_FEE_RECORD_FAILED".to_string(),
+                        format!("Failed to record deposit fee of {} satoshi for tx_id {}: {}", deposit_fee, tx_id, e),
+                        false,
+                    );
+                }
+            }
             
             // Mark transaction as processed
             mark_transaction_processed(tx_id)?;
+            crate::advanced_query_routing::invalidate_cache_for(caller);
             
             // Log audit action
             log_audit_action(
                 caller,
                 "LIQUIDITY_DEPOSIT".to_string(),
-                format!("Deposited {} ckBTC satoshi, tx_id: {}, block: {}", amount, tx_id, block_idx),
+                format!("Deposited {} {} base units (fee: {}), tx_id: {}, block: {}", net_amount, asset.symbol(), deposit_fee, tx_id, block_idx),
                 true,
             );
             
@@ -263,17 +994,17 @@ pub async fn deposit_liquidity(amount: u64, tx_id: u64) -> Result<String, String
             log_audit_action(
                 caller,
                 "LIQUIDITY_DEPOSIT_FAILED".to_string(),
-                format!("Failed to deposit {} ckBTC satoshi: {}", amount, error_msg),
+                format!("Failed to deposit {} {} base units: {}", amount, asset.symbol(), error_msg),
                 false,
             );
             Err(error_msg)
         }
         Err(call_error) => {
-            let error_msg = format!("Call to ckBTC ledger failed: {:?}", call_error);
+            let error_msg = format!("Call to {} ledger failed: {:?}", asset.symbol(), call_error);
             log_audit_action(
                 caller,
                 "LIQUIDITY_DEPOSIT_FAILED".to_string(),
-                format!("Failed to deposit {} ckBTC satoshi: {}", amount, error_msg),
+                format!("Failed to deposit {} {} base units: {}", amount, asset.symbol(), error_msg),
                 false,
             );
             Err(error_msg)
@@ -281,21 +1012,224 @@ pub async fn deposit_liquidity(amount: u64, tx_id: u64) -> Result<String, String
     }
 }
 
-/// Disburse loan to borrower's Bitcoin address
+/// Lock key for an investor's balance in every pool
+pub fn investor_resource(investor: Principal) -> String {
+    format!("investor:{}", investor)
+}
+
+/// Add a settled deposit to the asset's pool. The pool is read after the ledger call returns,
+/// so deposits that settle out of order each add to the latest totals.
+pub fn credit_deposit_to_pool(asset: &Asset, net_amount: u64, is_first_deposit: bool) -> Result<(), String> {
+    let mut pool = get_asset_pool(asset);
+    pool.total_liquidity = Satoshi(pool.total_liquidity).checked_add(Satoshi(net_amount))?.0;
+    pool.available_liquidity = Satoshi(pool.available_liquidity).checked_add(Satoshi(net_amount))?.0;
+    if is_first_deposit {
+        pool.total_investors += 1;
+    }
+    pool.updated_at = time();
+    store_asset_pool(asset, pool)
+}
+
+/// Remove a withdrawal from the asset's pool before its ledger call, so withdrawals checked
+/// while the transfer is in flight see the liquidity as already gone
+pub fn debit_withdrawal_from_pool(asset: &Asset, amount: u64) -> Result<(), String> {
+    let mut pool = get_asset_pool(asset);
+    pool.total_liquidity = Satoshi(pool.total_liquidity).checked_sub(Satoshi(amount))?.0;
+    pool.available_liquidity = Satoshi(pool.available_liquidity).checked_sub(Satoshi(amount))?.0;
+    pool.total_withdrawals = pool.total_withdrawals.saturating_add(1);
+    pool.total_withdrawn_amount = pool.total_withdrawn_amount.saturating_add(amount);
+    pool.updated_at = time();
+    store_asset_pool(asset, pool)
+}
+
+/// Put back a withdrawal whose ledger transfer failed, undoing `debit_withdrawal_from_pool`
+pub fn restore_withdrawal_to_pool(asset: &Asset, amount: u64) -> Result<(), String> {
+    let mut pool = get_asset_pool(asset);
+    pool.total_liquidity = Satoshi(pool.total_liquidity).checked_add(Satoshi(amount))?.0;
+    pool.available_liquidity = Satoshi(pool.available_liquidity).checked_add(Satoshi(amount))?.0;
+    pool.total_withdrawals = pool.total_withdrawals.saturating_sub(1);
+    pool.total_withdrawn_amount = pool.total_withdrawn_amount.saturating_sub(amount);
+    pool.updated_at = time();
+    store_asset_pool(asset, pool)
+}
+
+/// Take a pending withdrawal out of the pool and the investor's balance together
+fn reserve_withdrawal(asset: &Asset, investor: Principal, amount: u64) -> Result<(), String> {
+    let mut balance = get_asset_investor_balance(asset, investor)
+        .ok_or_else(|| "No investment balance found. Please deposit first".to_string())?;
+    balance.balance = Satoshi(balance.balance).checked_sub(Satoshi(amount))?.0;
+    debit_withdrawal_from_pool(asset, amount)?;
+    store_asset_investor_balance(asset, balance)
+}
+
+/// Return a reserved withdrawal to the pool and the investor after the transfer failed
+fn release_withdrawal(asset: &Asset, investor: Principal, amount: u64) {
+    let released = restore_withdrawal_to_pool(asset, amount).and_then(|_| {
+        let mut balance = get_asset_investor_balance(asset, investor)
+            .ok_or_else(|| format!("Balance for {} disappeared", investor))?;
+        balance.balance = Satoshi(balance.balance).checked_add(Satoshi(amount))?.0;
+        store_asset_investor_balance(asset, balance)
+    });
+    if let Err(e) = released {
+        log_audit_action(
+            investor,
+            "LIQUIDITY_WITHDRAWAL_RELEASE_FAILED".to_string(),
+            format!("Failed to return {} {} from a failed withdrawal: {}", amount, asset.base_unit(), e),
+            false,
+        );
+    }
+}
+
+/// Pool checks shared by every disbursement: unreserved liquidity (plus the loan's own
+/// reservation) must cover `amount`, and one loan may not take more than its share of the pool
+fn ensure_disbursable(pool: &LiquidityPool, loan_id: u64, amount: u64) -> Result<(), String> {
+    let own_reservation = get_liquidity_reservation(loan_id).map(|r| r.amount).unwrap_or(0);
+    let disbursable = pool.unreserved_liquidity() + own_reservation.min(pool.reserved());
+    if disbursable < amount {
+        return Err(format!(
+            "Insufficient liquidity in the pool. Available: {} satoshi, Required: {} satoshi",
+            disbursable, amount
+        ));
+    }
+    
+    // Additional safety check: ensure we don't exceed 80% of total liquidity for a single loan
+    let max_single_loan = (pool.total_liquidity * MAX_SINGLE_LOAN_POOL_PERCENTAGE) / 100;
+    if amount > max_single_loan {
+        return Err(format!(
+            "Loan amount too large. Maximum allowed: {} satoshi ({}% of total liquidity)",
+            max_single_loan, MAX_SINGLE_LOAN_POOL_PERCENTAGE
+        ));
+    }
+    Ok(())
+}
+
+/// Move a disbursed amount from available to borrowed in the asset's pool; the loan's
+/// reservation becomes borrowed funds
+fn record_disbursement_in_pool(asset: &Asset, loan_id: u64, amount: u64) -> Result<(), String> {
+    let mut pool = get_asset_pool(asset);
+    if let Some(reservation) = remove_liquidity_reservation(loan_id) {
+        release_reserved_liquidity(&mut pool, reservation.amount);
+    }
+    pool.available_liquidity = Satoshi(pool.available_liquidity).checked_sub(Satoshi(amount))?.0;
+    pool.total_borrowed = Satoshi(pool.total_borrowed).checked_add(Satoshi(amount))?.0;
+    pool.updated_at = time();
+    store_asset_pool(asset, pool)
+}
+
+const DEFAULT_BTC_NETWORK_FEE_ESTIMATE: u64 = 10_000;
+
+/// Minter and Bitcoin fee for withdrawing `amount`, falling back to the governance estimate
+/// when the minter cannot be asked
+async fn estimate_disbursement_fee(ckbtc_minter: Principal, amount: u64) -> u64 {
+    let args = EstimateWithdrawalFeeArgs { amount: Some(amount) };
+    let result: Result<(WithdrawalFee,), _> = call(ckbtc_minter, "estimate_withdrawal_fee", (args,)).await;
+    match result {
+        Ok((fee,)) => fee.minter_fee.saturating_add(fee.bitcoin_fee),
+        Err(_) => crate::governance::get_protocol_parameter("btc_network_fee_estimate".to_string())
+            .map(|p| p.current_value)
+            .unwrap_or(DEFAULT_BTC_NETWORK_FEE_ESTIMATE),
+    }
+}
+
+/// (amount to withdraw through the minter, amount the borrower receives) for a principal of
+/// `principal` under `bearer`
+pub fn disbursement_amounts(principal: u64, network_fee: u64, bearer: FeeBearer) -> Result<(u64, u64), String> {
+    match bearer {
+        FeeBearer::Borrower => {
+            if network_fee >= principal {
+                return Err(format!(
+                    "Network fee of {} satoshi would consume the whole disbursement of {} satoshi",
+                    network_fee, principal
+                ));
+            }
+            Ok((principal, principal - network_fee))
+        }
+        FeeBearer::Protocol => Satoshi(principal).checked_add(Satoshi(network_fee)).map(|gross| (gross.0, principal)),
+    }
+}
+
+/// Disburse an ICP loan as an ICRC-1 transfer to the borrower's principal; there is no
+/// on-chain Bitcoin leg, so no address or minter is involved
+async fn disburse_icp_loan(loan: &Loan, amount: u64, caller: Principal) -> Result<String, String> {
+    let loan_id = loan.id;
+    if amount < Asset::Icp.min_amount() {
+        return Err(format!("Amount must be at least {} ICP e8s", Asset::Icp.min_amount()));
+    }
+    ensure_disbursable(&get_asset_pool(&Asset::Icp), loan_id, amount)?;
+    
+    let transfer_args = TransferArgs {
+        from_subaccount: None,
+        to: Account {
+            owner: loan.borrower,
+            subaccount: None,
+        },
+        amount: Nat::from(amount),
+        fee: None,
+        memo: Some(crate::helpers::ledger_memo(&format!("Loan disbursement #{}", loan_id))),
+        created_at_time: Some(time()),
+    };
+    
+    let icp_ledger = crate::helpers::ledger_principal_for(&Asset::Icp);
+    let call_result: Result<(Result<Nat, TransferError>,), _> = call_ckbtc_with_retry(
+        "icrc1_transfer",
+        || call(icp_ledger, "icrc1_transfer", (transfer_args.clone(),)),
+    ).await;
+    
+    let error_msg = match call_result {
+        Ok((Ok(block_index),)) => {
+            let block_idx = block_index.0.try_into().unwrap_or(0u64);
+            record_disbursement_in_pool(&Asset::Icp, loan_id, amount)?;
+            
+            // The destination is the borrower's principal and the block is on the ICP ledger
+            store_disbursement_record(DisbursementRecord {
+                loan_id,
+                borrower_btc_address: loan.borrower.to_text(),
+                amount,
+                ckbtc_block_index: block_idx,
+                disbursed_at: time(),
+                disbursed_by: caller,
+                network_fee: None,
+                fee_bearer: None,
+            })?;
+            
+            log_audit_action(
+                caller,
+                "LOAN_DISBURSEMENT".to_string(),
+                format!("Disbursed {} ICP e8s to {} for loan #{}, ICP block: {}", amount, loan.borrower, loan_id, block_idx),
+                true,
+            );
+            return Ok("Disbursement completed successfully".to_string());
+        }
+        Ok((Err(transfer_error),)) => format!("ICP transfer failed: {:?}", transfer_error),
+        Err(call_error) => format!("Call to ICP ledger failed: {:?}", call_error),
+    };
+    
+    log_audit_action(
+        caller,
+        "LOAN_DISBURSEMENT_FAILED".to_string(),
+        format!("Failed to disburse {} ICP e8s to {} for loan #{}: {}", amount, loan.borrower, loan_id, error_msg),
+        false,
+    );
+    Err(error_msg)
+}
+
+/// Disburse loan to the borrower: ckBTC loans go to the borrower's Bitcoin address through the
+/// ckBTC minter, ICP loans to the borrower's principal (`borrower_btc_address` is ignored)
 /// This function is CRITICAL and must be protected - only callable by loan management canister
 /// Implements comprehensive security checks, Bitcoin address validation, and audit logging
 #[update]
 pub async fn disburse_loan(
     loan_id: u64,
     borrower_btc_address: String, 
-    amount: u64
+    amount: Satoshi,
+    asset: Option<Asset>
 ) -> Result<String, String> {
+    let Satoshi(amount) = amount;
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "disburse_loan")?;
     
-    // Check if emergency pause is active
-    if is_emergency_paused() {
-        return Err("Pool operations are currently paused".to_string());
-    }
+    // Full emergency pause supersedes the origination-only pause
+    ensure_origination_allowed()?;
     
     // CRITICAL ACCESS CONTROL: Only loan management canister can disburse funds
     if !is_loan_manager_canister(&caller) {
@@ -307,6 +1241,27 @@ pub async fn disburse_loan(
         return Err("Amount must be greater than zero".to_string());
     }
     
+    // The requested asset must be the one the loan was originated in
+    let loan = crate::storage::get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    let asset = asset.unwrap_or_default();
+    if loan.asset() != asset {
+        return Err(format!(
+            "Loan #{} is denominated in {}, not {}",
+            loan_id, loan.asset().symbol(), asset.symbol()
+        ));
+    }
+    
+    // An expired warehouse receipt cannot back new funds, whichever path approved the loan
+    let collateral = crate::storage::get_nft_data(loan.nft_id)
+        .ok_or_else(|| format!("Collateral NFT #{} not found", loan.nft_id))?;
+    crate::helpers::ensure_receipt_not_expired(&collateral.metadata, time())?;
+    
+    // One disbursement per loan at a time; the pool itself is re-read after the ledger call
+    let _loan_guard = ResourceGuard::acquire(format!("loan:{}", loan_id))?;
+    if asset == Asset::Icp {
+        return disburse_icp_loan(&loan, amount, caller).await;
+    }
+    
     if borrower_btc_address.is_empty() {
         return Err("Bitcoin address cannot be empty".to_string());
     }
@@ -316,35 +1271,36 @@ pub async fn disburse_loan(
         return Err("Invalid Bitcoin address format".to_string());
     }
     
+    // Compliance: destination must not be denied and must be the borrower's own address
+    crate::production_security::ensure_btc_address_allowed(&borrower_btc_address, "disburse_loan", Some(loan.borrower))?;
+    verify_disbursement_destination(loan_id, loan.borrower, &borrower_btc_address)?;
+    
     // Check minimum disbursement amount (0.001 BTC = 100,000 satoshi)
     if amount < 100_000 {
         return Err("Amount must be at least 0.001 BTC (100,000 satoshi)".to_string());
     }
     
-    // Check if pool has sufficient available liquidity
-    let pool = get_liquidity_pool();
-    if pool.available_liquidity < amount {
-        return Err(format!(
-            "Insufficient liquidity in the pool. Available: {} satoshi, Required: {} satoshi",
-            pool.available_liquidity, amount
-        ));
-    }
-    
-    // Additional safety check: ensure we don't exceed 80% of total liquidity for a single loan
-    let max_single_loan = (pool.total_liquidity * 80) / 100;
-    if amount > max_single_loan {
-        return Err(format!(
-            "Loan amount too large. Maximum allowed: {} satoshi (80% of total liquidity)",
-            max_single_loan
-        ));
-    }
+    // Check if pool has sufficient available liquidity, excluding funds reserved for other loans
+    ensure_disbursable(&get_liquidity_pool(), loan_id, amount)?;
     
     // Prepare for Bitcoin withdrawal via ckBTC Minter
-    let ckbtc_ledger = Principal::from_text(CKBTC_LEDGER_PRINCIPAL)
-        .map_err(|_| "Invalid ckBTC ledger principal")?;
-    
-    let ckbtc_minter = Principal::from_text(CKBTC_MINTER_PRINCIPAL)
-        .map_err(|_| "Invalid ckBTC minter principal")?;
+    let ckbtc_ledger = crate::helpers::ckbtc_ledger_principal();
+    
+    let ckbtc_minter = crate::helpers::ckbtc_minter_principal();
+    
+    // The minter deducts its fee from the withdrawal. The loan's debt stays `amount`; the fee
+    // bearer decides whether the treasury tops the withdrawal up so the borrower nets it all.
+    let fee_bearer = get_canister_config().disbursement_fee_bearer.unwrap_or_default();
+    let network_fee = estimate_disbursement_fee(ckbtc_minter, amount).await;
+    let (withdrawal_amount, borrower_receives) = disbursement_amounts(amount, network_fee, fee_bearer)?;
+    // A protocol-borne fee comes out of the treasury, not the pool. It is taken before the
+    // ledger calls so nothing can fail once the BTC is on its way, and handed back if they fail.
+    let reserved_fee = if fee_bearer == FeeBearer::Protocol && network_fee > 0 {
+        Some(crate::treasury_management::fund_disbursement_network_fee(loan_id, network_fee)
+            .map_err(|e| format!("Treasury cannot cover the {} satoshi network fee: {}", network_fee, e))?)
+    } else {
+        None
+    };
     
     let _canister_account = Account {
         owner: canister_self(),
@@ -360,36 +1316,37 @@ pub async fn disburse_loan(
     let approve_args = ApproveArgs {
         from_subaccount: None,
         spender: minter_account.clone(),
-        amount: Nat::from(amount),
+        amount: Nat::from(withdrawal_amount),
         expected_allowance: None,
         expires_at: Some(time() + 600_000_000_000), // 10 minutes expiry
         fee: None,
-        memo: Some(format!("Loan disbursement approval - Loan ID: {}", loan_id).as_bytes().to_vec()),
+        memo: Some(crate::helpers::ledger_memo(&format!("Disbursement approval #{}", loan_id))),
         created_at_time: Some(time()),
     };
     
-    let approve_result: Result<(Result<Nat, ApproveError>,), _> = 
-        call(ckbtc_ledger, "icrc2_approve", (approve_args,)).await;
+    let approve_result: Result<(Result<Nat, ApproveError>,), _> = call_ckbtc_with_retry(
+        "icrc2_approve",
+        || call(ckbtc_ledger, "icrc2_approve", (approve_args.clone(),)),
+    ).await;
     
     match approve_result {
         Ok((Ok(approve_block),)) => {
             // Step 2: Call retrieve_btc_with_approval on the minter
             let retrieve_args = RetrieveBtcArgs {
                 address: borrower_btc_address.clone(),
-                amount,
+                amount: withdrawal_amount,
             };
             
-            let retrieve_result: Result<(Result<u64, RetrieveBtcError>,), _> = 
-                call(ckbtc_minter, "retrieve_btc_with_approval", (retrieve_args,)).await;
+            // The approved allowance caps what the minter can pull, so a retry cannot overspend
+            let retrieve_result: Result<(Result<u64, RetrieveBtcError>,), _> = call_ckbtc_with_retry(
+                "retrieve_btc_with_approval",
+                || call(ckbtc_minter, "retrieve_btc_with_approval", (retrieve_args.clone(),)),
+            ).await;
             
             match retrieve_result {
                 Ok((Ok(block_index),)) => {
                     // Disbursement successful, update pool state
-                    let mut pool = get_liquidity_pool();
-                    pool.available_liquidity -= amount;
-                    pool.total_borrowed += amount;
-                    pool.updated_at = time();
-                    store_liquidity_pool(pool)?;
+                    record_disbursement_in_pool(&Asset::CkBtc, loan_id, amount)?;
                     
                     // Create disbursement record
                     let disbursement_record = DisbursementRecord {
@@ -399,6 +1356,8 @@ pub async fn disburse_loan(
                         ckbtc_block_index: block_index,
                         disbursed_at: time(),
                         disbursed_by: caller,
+                        network_fee: Some(network_fee),
+                        fee_bearer: Some(fee_bearer),
                     };
                     
                     // Store disbursement record
@@ -409,8 +1368,9 @@ pub async fn disburse_loan(
                         caller,
                         "LOAN_DISBURSEMENT".to_string(),
                         format!(
-                            "Disbursed {} ckBTC satoshi to {} for loan #{}, approve_block: {}, btc_block: {}",
-                            amount, borrower_btc_address, loan_id, 
+                            "Disbursed {} ckBTC satoshi to {} for loan #{} (withdrawn {}, network fee {} paid by {:?}, borrower receives about {}; a borrower-paid fee is charged to the principal), approve_block: {}, btc_block: {}",
+                            amount, borrower_btc_address, loan_id,
+                            withdrawal_amount, network_fee, fee_bearer, borrower_receives,
                             approve_block.0.try_into().unwrap_or(0u64), 
                             block_index
                         ),
@@ -430,6 +1390,7 @@ pub async fn disburse_loan(
                         ),
                         false,
                     );
+                    release_reserved_network_fee(loan_id, reserved_fee);
                     Err(error_msg)
                 }
                 Err(call_error) => {
@@ -443,6 +1404,7 @@ pub async fn disburse_loan(
                         ),
                         false,
                     );
+                    release_reserved_network_fee(loan_id, reserved_fee);
                     Err(error_msg)
                 }
             }
@@ -458,6 +1420,7 @@ pub async fn disburse_loan(
                 ),
                 false,
             );
+            release_reserved_network_fee(loan_id, reserved_fee);
             Err(error_msg)
         }
         Err(call_error) => {
@@ -471,11 +1434,116 @@ pub async fn disburse_loan(
                 ),
                 false,
             );
+            release_reserved_network_fee(loan_id, reserved_fee);
             Err(error_msg)
         }
     }
 }
 
+/// Hand a treasury-reserved network fee back after a failed withdrawal. A failed release only
+/// leaves the treasury short by the fee, so it is logged rather than masking the ledger error.
+fn release_reserved_network_fee(loan_id: u64, reserved_fee: Option<u64>) {
+    if let Some(expense_id) = reserved_fee {
+        if let Err(e) = crate::treasury_management::release_disbursement_network_fee(expense_id) {
+            log_audit_action(
+                canister_self(),
+                "DISBURSEMENT_FEE_RELEASE_FAILED".to_string(),
+                format!("Could not release network fee expense #{} for loan #{}: {}", expense_id, loan_id, e),
+                false,
+            );
+        }
+    }
+}
+
+/// Hold `amount` of unreserved liquidity for a pool
+pub fn apply_liquidity_reservation(pool: &mut LiquidityPool, amount: u64) -> Result<(), String> {
+    if amount > pool.unreserved_liquidity() {
+        return Err(format!(
+            "Insufficient unreserved liquidity. Available: {} satoshi, Required: {} satoshi",
+            pool.unreserved_liquidity(), amount
+        ));
+    }
+    pool.reserved_liquidity = Some(pool.reserved() + amount);
+    Ok(())
+}
+
+pub fn release_reserved_liquidity(pool: &mut LiquidityPool, amount: u64) {
+    pool.reserved_liquidity = Some(pool.reserved().saturating_sub(amount));
+}
+
+fn reservation_window_nanos() -> u64 {
+    let hours = crate::governance::get_protocol_parameter("loan_approval_reservation_hours".to_string())
+        .map(|p| p.current_value)
+        .unwrap_or(72);
+    hours * 60 * 60 * 1_000_000_000
+}
+
+/// Reserve pool liquidity for an approved loan so later approvals cannot promise the same funds
+pub fn reserve_liquidity_for_loan(loan_id: u64, amount: u64) -> Result<LiquidityReservation, String> {
+    if get_liquidity_reservation(loan_id).is_some() {
+        return Err(format!("Liquidity is already reserved for loan #{}", loan_id));
+    }
+
+    let asset = crate::storage::get_loan(loan_id).map(|loan| loan.asset()).unwrap_or_default();
+    let mut pool = get_asset_pool(&asset);
+    apply_liquidity_reservation(&mut pool, amount)?;
+    pool.updated_at = time();
+    store_asset_pool(&asset, pool)?;
+
+    let now = time();
+    let reservation = LiquidityReservation {
+        loan_id,
+        amount,
+        reserved_at: now,
+        expires_at: now + reservation_window_nanos(),
+    };
+    store_liquidity_reservation(reservation.clone());
+
+    log_audit_action(
+        ic_cdk::caller(),
+        "LIQUIDITY_RESERVED".to_string(),
+        format!("Reserved {} satoshi for approved loan #{} until {}", amount, loan_id, reservation.expires_at),
+        true,
+    );
+
+    Ok(reservation)
+}
+
+/// Return a loan's reservation to the pool; no-op when the loan holds none
+pub fn release_liquidity_reservation(loan_id: u64, reason: &str) -> Option<u64> {
+    let reservation = remove_liquidity_reservation(loan_id)?;
+
+    let asset = crate::storage::get_loan(loan_id).map(|loan| loan.asset()).unwrap_or_default();
+    let mut pool = get_asset_pool(&asset);
+    release_reserved_liquidity(&mut pool, reservation.amount);
+    pool.updated_at = time();
+    let _ = store_asset_pool(&asset, pool);
+
+    log_audit_action(
+        ic_cdk::caller(),
+        "LIQUIDITY_RESERVATION_RELEASED".to_string(),
+        format!("Released {} satoshi reserved for loan #{}: {}", reservation.amount, loan_id, reason),
+        true,
+    );
+
+    Some(reservation.amount)
+}
+
+/// Release reservations for approvals that were not disbursed within the reservation window
+pub fn expire_liquidity_reservations(now: u64) -> u64 {
+    let expired: Vec<u64> = get_all_liquidity_reservations()
+        .into_iter()
+        .filter(|reservation| reservation.expires_at <= now)
+        .map(|reservation| reservation.loan_id)
+        .collect();
+
+    for loan_id in &expired {
+        release_liquidity_reservation(*loan_id, "approval expired before disbursement");
+    }
+
+    expired.len() as u64
+}
+
 /// Withdraw liquidity from the pool
 /// Allows investors to withdraw their funds (principal + accumulated yield)
 /// Implements comprehensive security checks, validation, and audit logging
@@ -493,15 +1561,97 @@ pub async fn disburse_loan(
 /// - Implements rate limiting and emergency pause checks
 /// - Comprehensive audit logging for all actions
 #[update]
-pub async fn withdraw_liquidity(amount: u64) -> Result<String, String> {
+pub async fn withdraw_liquidity(amount: Satoshi, asset: Option<Asset>) -> Result<String, WithdrawalError> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "withdraw_liquidity").map_err(WithdrawalError::Rejected)?;
+    withdraw_within_limits(caller, amount, asset.unwrap_or_default(), LotMethod::Aggregate, ledger_transfer).await
+}
+
+/// Withdraw liquidity drawing on deposit lots oldest-first (FIFO) or newest-first (LIFO).
+/// The lots and the amount taken from each are recorded on the withdrawal for tax-lot
+/// accounting; otherwise identical to `withdraw_liquidity`.
+#[update]
+pub async fn withdraw_liquidity_lots(amount: Satoshi, method: LotMethod, asset: Option<Asset>) -> Result<String, WithdrawalError> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "withdraw_liquidity_lots").map_err(WithdrawalError::Rejected)?;
+    withdraw_within_limits(caller, amount, asset.unwrap_or_default(), method, ledger_transfer).await
+}
+
+type LedgerTransferResult = Result<(Result<Nat, TransferError>,), (RejectionCode, String)>;
+
+/// Withdrawal transfer on the asset's ledger, retried on transient errors
+async fn ledger_transfer(asset: Asset, transfer_args: TransferArgs) -> LedgerTransferResult {
+    let ledger = crate::helpers::ledger_principal_for(&asset);
+    call_ckbtc_with_retry(
+        "icrc1_transfer",
+        || call(ledger, "icrc1_transfer", (transfer_args.clone(),)),
+    ).await
+}
+
+/// Withdrawals over a transaction limit fail with `WithdrawalError::LimitExceeded`; every other
+/// failure is reported as `WithdrawalError::Rejected`. `transfer` performs the ledger call.
+pub async fn withdraw_within_limits<F, Fut>(
+    caller: Principal,
+    amount: Satoshi,
+    asset: Asset,
+    method: LotMethod,
+    transfer: F,
+) -> Result<String, WithdrawalError>
+where
+    F: FnOnce(Asset, TransferArgs) -> Fut,
+    Fut: std::future::Future<Output = LedgerTransferResult>,
+{
+    let tier = get_user_by_principal(&caller).map(|user| user.effective_kyc_tier()).unwrap_or(KycTier::None);
+    let breach = transaction_limit_breach(caller, TransactionDirection::Withdrawal, &asset, amount.0, &tier, time())
+        .map_err(WithdrawalError::Rejected)?;
+    if let Some(breach) = breach {
+        log_audit_action(
+            caller,
+            "LIQUIDITY_WITHDRAWAL_LIMIT_EXCEEDED".to_string(),
+            format!(
+                "Withdrawal of {} blocked by {:?} limit of {} satoshi ({} used in the last 24h)",
+                amount, breach.kind, breach.limit, breach.used_in_window
+            ),
+            false,
+        );
+        return Err(WithdrawalError::LimitExceeded(breach));
+    }
+
+    // Counted before the ledger call so a withdrawal checked meanwhile sees it; taken back on failure
+    let reserved_at = reserve_transaction_usage(caller, TransactionDirection::Withdrawal, asset, amount.0, time());
+    let result = withdraw_from_pool(caller, amount, asset, method, transfer).await;
+    if result.is_err() {
+        release_transaction_usage(caller, TransactionDirection::Withdrawal, asset, amount.0, reserved_at);
+    }
+    result.map_err(WithdrawalError::Rejected)
+}
+
+async fn withdraw_from_pool<F, Fut>(
+    caller: Principal,
+    amount: Satoshi,
+    asset: Asset,
+    method: LotMethod,
+    transfer: F,
+) -> Result<String, String>
+where
+    F: FnOnce(Asset, TransferArgs) -> Fut,
+    Fut: std::future::Future<Output = LedgerTransferResult>,
+{
+    let Satoshi(amount) = amount;
+    let unit = asset.base_unit();
+    // No role check: a deactivated investor can still take their funds out
+    crate::validation::guard_caller(&caller, "withdraw_liquidity")?;
+    crate::helpers::ensure_operation_available("withdraw_liquidity")?;
+    
+    // Held until the transfer settles so the same balance cannot be withdrawn twice
+    let _investor_guard = ResourceGuard::acquire(investor_resource(caller))?;
     
     // Security: Check if system is paused
     if is_emergency_paused() {
         log_audit_action(
             caller,
             "LIQUIDITY_WITHDRAWAL_BLOCKED".to_string(),
-            format!("Withdrawal attempt during emergency pause: {} ckBTC satoshi", amount),
+            format!("Withdrawal attempt during emergency pause: {} {unit}", amount),
             false,
         );
         return Err("System is currently paused for maintenance".to_string());
@@ -512,108 +1662,123 @@ pub async fn withdraw_liquidity(amount: u64) -> Result<String, String> {
         log_audit_action(
             caller,
             "LIQUIDITY_WITHDRAWAL_RATE_LIMITED".to_string(),
-            format!("Rate limited withdrawal attempt: {} ckBTC satoshi", amount),
+            format!("Rate limited withdrawal attempt: {} {unit}", amount),
             false,
         );
         return Err("Rate limit exceeded. Please try again later".to_string());
     }
     
     // Input validation
-    if amount == 0 {
+    if let Err(e) = crate::validation::require_positive(amount, "Amount") {
         log_audit_action(
             caller,
             "LIQUIDITY_WITHDRAWAL_INVALID_INPUT".to_string(),
             "Attempted withdrawal with zero amount".to_string(),
             false,
         );
-        return Err("Amount must be greater than zero".to_string());
+        return Err(e);
     }
     
-    // Minimum withdrawal amount (1000 satoshi = 0.00001 BTC)
-    const MIN_WITHDRAWAL_AMOUNT: u64 = 1000;
-    if amount < MIN_WITHDRAWAL_AMOUNT {
+    let min_amount = asset.min_withdrawal_amount();
+    if amount < min_amount {
         log_audit_action(
             caller,
             "LIQUIDITY_WITHDRAWAL_BELOW_MINIMUM".to_string(),
-            format!("Attempted withdrawal below minimum: {} < {}", amount, MIN_WITHDRAWAL_AMOUNT),
+            format!("Attempted withdrawal below minimum: {} < {}", amount, min_amount),
             false,
         );
-        return Err(format!("Minimum withdrawal amount is {} ckBTC satoshi", MIN_WITHDRAWAL_AMOUNT));
+        return Err(format!("Minimum withdrawal amount is {} {unit}", min_amount));
     }
     
     // Get investor balance with comprehensive error handling
-    let investor_balance = match get_investor_balance_for_principal(caller) {
-        Ok(balance) => balance,
-        Err(_) => {
+    let investor_balance = match get_asset_investor_balance(&asset, caller) {
+        Some(balance) => balance,
+        None => {
             log_audit_action(
                 caller,
                 "LIQUIDITY_WITHDRAWAL_NO_BALANCE".to_string(),
-                format!("Withdrawal attempt by investor with no balance: {} ckBTC satoshi", amount),
+                format!("Withdrawal attempt by investor with no balance: {} {unit}", amount),
                 false,
             );
             return Err("No investment balance found. Please deposit first".to_string());
         }
     };
     
-    // Check if investor has sufficient balance
-    if investor_balance.balance < amount {
+    // Check if investor has sufficient balance, excluding balance committed to position offers
+    // (positions are only traded in the ckBTC pool)
+    let offered = match asset {
+        Asset::CkBtc => offered_position_amount(caller),
+        Asset::Icp => 0,
+    };
+    let withdrawable = investor_balance.balance.saturating_sub(offered);
+    if withdrawable < amount {
         log_audit_action(
             caller,
             "LIQUIDITY_WITHDRAWAL_INSUFFICIENT_BALANCE".to_string(),
             format!(
-                "Insufficient balance: attempted {} ckBTC satoshi, available {} ckBTC satoshi", 
-                amount, investor_balance.balance
+                "Insufficient balance: attempted {} {unit}, available {} {unit}", 
+                amount, withdrawable
             ),
             false,
         );
         return Err(format!(
-            "Withdrawal amount exceeds your balance. Available: {} ckBTC satoshi", 
-            investor_balance.balance
+            "Withdrawal amount exceeds your balance. Available: {} {unit}", 
+            withdrawable
         ));
     }
     
     // Get current pool state
-    let pool = get_liquidity_pool();
+    let pool = get_asset_pool(&asset);
     
-    // Check if pool has sufficient available liquidity
-    if pool.available_liquidity < amount {
+    // Liquidity reserved for approved loans is not available to withdraw
+    let unreserved = pool.unreserved_liquidity();
+    if unreserved < amount {
         log_audit_action(
             caller,
             "LIQUIDITY_WITHDRAWAL_INSUFFICIENT_POOL".to_string(),
             format!(
-                "Insufficient pool liquidity: requested {} ckBTC satoshi, available {} ckBTC satoshi", 
-                amount, pool.available_liquidity
+                "Insufficient pool liquidity: requested {} {unit}, unreserved {} {unit}", 
+                amount, unreserved
             ),
             false,
         );
         return Err(format!(
-            "Withdrawal failed due to insufficient available liquidity. Available: {} ckBTC satoshi", 
-            pool.available_liquidity
+            "Withdrawal failed due to insufficient available liquidity. Available: {} {unit}", 
+            unreserved
         ));
     }
     
     // Additional safety check: ensure pool maintains emergency reserve
-    let emergency_reserve_ratio = 5; // 5% emergency reserve
-    let required_reserve = (pool.total_liquidity * emergency_reserve_ratio) / 100;
-    let liquidity_after_withdrawal = pool.available_liquidity - amount;
+    let reserve = required_reserve(&pool);
+    let liquidity_after_withdrawal = unreserved - amount;
     
-    if liquidity_after_withdrawal < required_reserve {
+    if liquidity_after_withdrawal < reserve {
         log_audit_action(
             caller,
             "LIQUIDITY_WITHDRAWAL_RESERVE_VIOLATION".to_string(),
             format!(
                 "Withdrawal would violate emergency reserve: {} < {} required", 
-                liquidity_after_withdrawal, required_reserve
+                liquidity_after_withdrawal, reserve
             ),
             false,
         );
         return Err("Withdrawal would violate emergency reserve requirements".to_string());
     }
     
-    // Prepare ckBTC transfer from canister to investor
-    let ckbtc_ledger = Principal::from_text(CKBTC_LEDGER_PRINCIPAL)
-        .map_err(|_| "Invalid ckBTC ledger principal configuration")?;
+    // Principal stays withdrawable inside the holding period. Unseasoned deposits never earned
+    // a yield share, so the amount drawn from them is only recorded in the audit trail.
+    let (_, unseasoned, _) = holding_period_status(&investor_balance, investor_balance.balance, time(), min_holding_period_nanos());
+    let unseasoned_amount = amount.min(unseasoned);
+    
+    // Deduct withdrawal fee (if configured); investor receives the net amount.
+    // The treasury is ckBTC-denominated, so ICP withdrawals are not charged a fee.
+    let withdrawal_fee = match asset {
+        Asset::CkBtc => calculate_pool_fee(amount, get_canister_config().withdrawal_fee_bps()),
+        Asset::Icp => 0,
+    };
+    let net_amount = Satoshi(amount).checked_sub(Satoshi(withdrawal_fee))?.0;
     
+    // Prepare the ledger transfer from canister to investor
     let investor_account = Account {
         owner: caller,
         subaccount: None,
@@ -622,9 +1787,9 @@ pub async fn withdraw_liquidity(amount: u64) -> Result<String, String> {
     let transfer_args = TransferArgs {
         from_subaccount: None,
         to: investor_account,
-        amount: Nat::from(amount),
+        amount: Nat::from(net_amount),
         fee: None,
-        memo: Some(format!("Agrilends liquidity withdrawal: {} satoshi", amount).as_bytes().to_vec()),
+        memo: Some(crate::helpers::ledger_memo(&format!("Liquidity withdrawal {} {unit}", net_amount))),
         created_at_time: Some(time()),
     };
     
@@ -633,65 +1798,81 @@ pub async fn withdraw_liquidity(amount: u64) -> Result<String, String> {
         caller,
         "LIQUIDITY_WITHDRAWAL_INITIATED".to_string(),
         format!(
-            "Initiating withdrawal: {} ckBTC satoshi from balance {} ckBTC satoshi", 
-            amount, investor_balance.balance
+            "Initiating withdrawal: {} {unit} from balance {} {unit}, lot method {:?}", 
+            amount, investor_balance.balance, method
         ),
         true,
     );
     
-    // Execute the ckBTC transfer
-    let call_result: Result<(Result<Nat, TransferError>,), _> = 
-        call(ckbtc_ledger, "icrc1_transfer", (transfer_args,)).await;
+    // Reserved before the ledger call so a withdrawal checked meanwhile sees the pool and
+    // balance without it; released if the transfer fails
+    reserve_withdrawal(&asset, caller, amount)?;
+    
+    // Execute the ledger transfer
+    let call_result = transfer(asset, transfer_args).await;
     
     match call_result {
         Ok((Ok(block_index),)) => {
-            // Transfer successful, update all states atomically
+            // Transfer successful; the pool and balance were debited by the reservation
             let block_idx = block_index.0.try_into().unwrap_or(0u64);
             
-            // Update pool state
-            let mut updated_pool = pool;
-            updated_pool.total_liquidity -= amount;
-            updated_pool.available_liquidity -= amount;
-            updated_pool.updated_at = time();
-            
-            // Add pool stats tracking
-            updated_pool.total_withdrawals = updated_pool.total_withdrawals.saturating_add(1);
-            updated_pool.total_withdrawn_amount = updated_pool.total_withdrawn_amount.saturating_add(amount);
-            
-            store_liquidity_pool(updated_pool)?;
-            
-            // Update investor balance
-            let mut updated_investor_balance = investor_balance;
-            updated_investor_balance.balance -= amount;
-            updated_investor_balance.total_withdrawn += amount;
+            // Update investor balance from current state
+            let mut updated_investor_balance = get_asset_investor_balance(&asset, caller).unwrap_or(investor_balance);
+            updated_investor_balance.total_withdrawn = Satoshi(updated_investor_balance.total_withdrawn).checked_add(Satoshi(amount))?.0;
             updated_investor_balance.last_activity_at = time();
             
+            // Assign deposit lots against the balance as it stands now
+            let lots = match method {
+                LotMethod::Aggregate => None,
+                _ => Some(allocate_withdrawal_lots(
+                    &open_deposit_lots(&updated_investor_balance, u64::MAX),
+                    amount,
+                    method,
+                )),
+            };
+            
             // Create detailed withdrawal record
             let withdrawal_record = WithdrawalRecord {
                 investor: caller,
                 amount,
                 ckbtc_block_index: block_idx,
                 timestamp: time(),
+                fee: Some(withdrawal_fee),
+                lots,
             };
+            updated_investor_balance.add_fee_paid(withdrawal_fee);
             updated_investor_balance.withdrawals.push(withdrawal_record);
             
             // Store updated investor balance
-            store_investor_balance(updated_investor_balance)?;
+            store_asset_investor_balance(&asset, updated_investor_balance.clone())?;
+            crate::advanced_query_routing::invalidate_cache_for(caller);
+            
+            // Route withdrawal fee to treasury
+            if withdrawal_fee > 0 {
+                if let Err(e) = record_pool_fee_revenue(withdrawal_fee, RevenueType::WithdrawalFee) {
+                    log_audit_action(
+                        caller,
+                        "LIQUIDITY_WITHDRAWAL_FEE_RECORD_FAILED".to_string(),
+                        format!("Failed to record withdrawal fee of {} {unit}: {}", withdrawal_fee, e),
+                        false,
+                    );
+                }
+            }
             
             // Comprehensive audit logging
             log_audit_action(
                 caller,
                 "LIQUIDITY_WITHDRAWAL_SUCCESS".to_string(),
                 format!(
-                    "Successfully withdrew {} ckBTC satoshi, ckBTC block: {}, remaining balance: {} ckBTC satoshi", 
-                    amount, block_idx, updated_investor_balance.balance
+                    "Successfully withdrew {} {unit} (fee: {}), {} block: {}, remaining balance: {} {unit}, held under minimum period: {} {unit}", 
+                    net_amount, withdrawal_fee, asset.symbol(), block_idx, updated_investor_balance.balance, unseasoned_amount
                 ),
                 true,
             );
             
             Ok(format!(
-                "Withdrawal successful. Amount: {} ckBTC satoshi, Transaction Block: {}", 
-                amount, block_idx
+                "Withdrawal successful. Amount: {} {unit}, Transaction Block: {}", 
+                net_amount, block_idx
             ))
         }
         Ok((Err(transfer_error),)) => {
@@ -703,7 +1884,7 @@ pub async fn withdraw_liquidity(amount: u64) -> Result<String, String> {
                     format!("Invalid fee. Expected: {:?}", expected_fee)
                 }
                 TransferError::InsufficientFunds { balance } => {
-                    format!("Canister has insufficient ckBTC funds. Available: {:?}", balance)
+                    format!("Canister has insufficient {} funds. Available: {:?}", asset.symbol(), balance)
                 }
                 TransferError::TooOld => {
                     "Transaction timestamp too old".to_string()
@@ -715,30 +1896,32 @@ pub async fn withdraw_liquidity(amount: u64) -> Result<String, String> {
                     format!("Duplicate transaction. Original: {:?}", duplicate_of)
                 }
                 TransferError::TemporarilyUnavailable => {
-                    "ckBTC ledger temporarily unavailable".to_string()
+                    format!("{} ledger temporarily unavailable", asset.symbol())
                 }
                 TransferError::GenericError { error_code, message } => {
-                    format!("ckBTC transfer error {}: {}", error_code, message)
+                    format!("{} transfer error {}: {}", asset.symbol(), error_code, message)
                 }
             };
             
             log_audit_action(
                 caller,
                 "LIQUIDITY_WITHDRAWAL_TRANSFER_FAILED".to_string(),
-                format!("ckBTC transfer failed for {} ckBTC satoshi: {}", amount, error_msg),
+                format!("{} transfer failed for {} {unit}: {}", asset.symbol(), amount, error_msg),
                 false,
             );
+            release_withdrawal(&asset, caller, amount);
             
             Err(format!("Withdrawal failed: {}", error_msg))
         }
         Err(call_error) => {
-            let error_msg = format!("Failed to communicate with ckBTC ledger: {:?}", call_error);
+            let error_msg = format!("Failed to communicate with {} ledger: {:?}", asset.symbol(), call_error);
             log_audit_action(
                 caller,
                 "LIQUIDITY_WITHDRAWAL_NETWORK_ERROR".to_string(),
-                format!("Network error during withdrawal of {} ckBTC satoshi: {}", amount, error_msg),
+                format!("Network error during withdrawal of {} {unit}: {}", amount, error_msg),
                 false,
             );
+            release_withdrawal(&asset, caller, amount);
             Err(format!("Network error: {}", error_msg))
         }
     }
@@ -757,8 +1940,8 @@ pub fn get_pool_stats() -> PoolStats {
         0
     };
     
-    // Calculate APY based on utilization and pool performance
-    let apy = calculate_pool_apy(&pool);
+    // Calculate APY based on utilization and pool performance, in basis points
+    let apy_bps = calculate_pool_apy(&pool);
     
     // Calculate total return rate (including repayments)
     let _total_return_rate = if pool.total_borrowed > 0 {
@@ -770,16 +1953,162 @@ pub fn get_pool_stats() -> PoolStats {
     PoolStats {
         total_liquidity: pool.total_liquidity,
         available_liquidity: pool.available_liquidity,
+        reserved_liquidity: pool.reserved(),
         total_borrowed: pool.total_borrowed,
         total_repaid: pool.total_repaid,
         utilization_rate: utilization_rate as u64,
         total_investors: pool.total_investors,
-        apy: apy as u64,
+        apy: apy_bps / 100,
+        apy_bps: Some(apy_bps),
         created_at: pool.created_at,
         updated_at: pool.updated_at,
     }
 }
 
+// ========== PROTOCOL SOLVENCY ==========
+
+const DEFAULT_SOLVENCY_RATIO_FLOOR_BPS: u64 = 10_000;
+const DEFAULT_SOLVENCY_STRESS_HAIRCUT_BPS: u64 = 3_000;
+const SOLVENCY_ALERT_COOLDOWN_NANOS: u64 = 24 * 60 * 60 * 1_000_000_000; // Repeat a below-floor alert daily
+
+// The report from the last maintenance run, so queries and health checks do not walk every
+// loan and balance. Heap only: after an upgrade the first read rebuilds it.
+thread_local! {
+    static LATEST_SOLVENCY_REPORT: std::cell::RefCell<Option<SolvencyReport>> = std::cell::RefCell::new(None);
+    static LAST_SOLVENCY_ALERT_AT: std::cell::RefCell<Option<u64>> = std::cell::RefCell::new(None);
+}
+
+/// Asset value of a performing loan: what is owed, but never more than the (haircut) collateral
+pub fn performing_loan_asset_value(outstanding_debt: u64, collateral_value: u64, haircut_bps: u64) -> u64 {
+    let haircut_collateral = (collateral_value as u128 * 10_000u128.saturating_sub(haircut_bps as u128) / 10_000) as u64;
+    outstanding_debt.min(haircut_collateral)
+}
+
+/// Assets over liabilities in basis points; u64::MAX when nothing is owed
+pub fn collateralization_ratio_bps(assets: u64, liabilities: u64) -> u64 {
+    if liabilities == 0 {
+        return u64::MAX;
+    }
+    ((assets as u128 * 10_000) / liabilities as u128).min(u64::MAX as u128) as u64
+}
+
+fn build_solvency_report() -> SolvencyReport {
+    let floor_bps = crate::governance::get_protocol_parameter("solvency_ratio_floor_bps".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_SOLVENCY_RATIO_FLOOR_BPS);
+    let haircut_bps = crate::governance::get_protocol_parameter("solvency_stress_haircut_bps".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_SOLVENCY_STRESS_HAIRCUT_BPS);
+
+    let pool_liquidity = get_pool_stats().available_liquidity;
+    let insurance_fund_balance = crate::treasury_management::get_insurance_fund_balance();
+    let investor_balances: u64 = crate::storage::get_all_investor_balances().iter().map(|b| b.balance).sum();
+
+    let mut performing_loan_value = 0u64;
+    let mut stressed_loan_value = 0u64;
+    let mut performing_loan_count = 0u64;
+    let mut unpaid_interest = 0u64;
+    for loan in crate::storage::get_all_loans_data().iter().filter(|loan| loan.status == LoanStatus::Active) {
+        let (_, accrued_interest, late_penalty, total_debt) = match crate::loan_repayment::calculate_total_debt_with_interest(loan) {
+            Ok(debt) => debt,
+            Err(_) => continue,
+        };
+        let outstanding = total_debt.saturating_sub(loan.total_repaid);
+
+        performing_loan_count += 1;
+        performing_loan_value += performing_loan_asset_value(outstanding, loan.collateral_value_btc, 0);
+        stressed_loan_value += performing_loan_asset_value(outstanding, loan.collateral_value_btc, haircut_bps);
+        // Repayments settle penalty and interest before principal
+        unpaid_interest += (accrued_interest + late_penalty).saturating_sub(loan.total_repaid);
+    }
+
+    let accrued_investor_yield = unpaid_interest * (100 - crate::loan_repayment::PROTOCOL_FEE_PERCENTAGE) / 100;
+    let total_assets = pool_liquidity + performing_loan_value + insurance_fund_balance;
+    let stressed_total_assets = pool_liquidity + stressed_loan_value + insurance_fund_balance;
+    let total_liabilities = investor_balances + accrued_investor_yield;
+    let collateralization_ratio = collateralization_ratio_bps(total_assets, total_liabilities);
+
+    SolvencyReport {
+        pool_liquidity,
+        performing_loan_value,
+        performing_loan_count,
+        insurance_fund_balance,
+        total_assets,
+        investor_balances,
+        accrued_investor_yield,
+        total_liabilities,
+        collateralization_ratio_bps: collateralization_ratio,
+        stress_haircut_bps: haircut_bps,
+        stressed_total_assets,
+        stressed_collateralization_ratio_bps: collateralization_ratio_bps(stressed_total_assets, total_liabilities),
+        solvency_floor_bps: floor_bps,
+        is_solvent: total_assets >= total_liabilities,
+        is_above_floor: collateralization_ratio >= floor_bps,
+        generated_at: time(),
+    }
+}
+
+/// Do protocol assets (pool liquidity, performing loans, insurance fund) cover
+/// investor balances plus accrued yield? As of the last maintenance run; see `generated_at`.
+#[query]
+pub fn get_solvency_report() -> SolvencyReport {
+    LATEST_SOLVENCY_REPORT.with(|latest| latest.borrow().clone()).unwrap_or_else(build_solvency_report)
+}
+
+/// Whether a below-floor report is due an alert: on first crossing, then once per cooldown
+/// while the ratio stays below the floor
+pub fn solvency_alert_due(is_above_floor: bool, last_alert_at: Option<u64>, now: u64) -> bool {
+    !is_above_floor
+        && last_alert_at.map_or(true, |alerted_at| now.saturating_sub(alerted_at) >= SOLVENCY_ALERT_COOLDOWN_NANOS)
+}
+
+/// Maintenance check: refresh the cached report and raise a critical audit alert when the
+/// ratio drops below the governance floor
+pub fn check_solvency_floor() -> SolvencyReport {
+    use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+
+    let report = build_solvency_report();
+    LATEST_SOLVENCY_REPORT.with(|latest| *latest.borrow_mut() = Some(report.clone()));
+
+    let last_alert_at = LAST_SOLVENCY_ALERT_AT.with(|last| *last.borrow());
+    if report.is_above_floor {
+        // Recovered: the next drop alerts straight away
+        LAST_SOLVENCY_ALERT_AT.with(|last| *last.borrow_mut() = None);
+    } else if solvency_alert_due(report.is_above_floor, last_alert_at, report.generated_at) {
+        LAST_SOLVENCY_ALERT_AT.with(|last| *last.borrow_mut() = Some(report.generated_at));
+        log_audit_enhanced(
+            AuditCategory::Treasury,
+            "SOLVENCY_BELOW_FLOOR".to_string(),
+            AuditEventLevel::Critical,
+            AuditDetails {
+                description: format!(
+                    "Collateralization ratio {} bps is below the {} bps floor (assets {}, liabilities {}, stressed ratio {} bps)",
+                    report.collateralization_ratio_bps,
+                    report.solvency_floor_bps,
+                    report.total_assets,
+                    report.total_liabilities,
+                    report.stressed_collateralization_ratio_bps
+                ),
+                entity_type: Some("protocol".to_string()),
+                entity_id: Some("solvency".to_string()),
+                ..Default::default()
+            },
+            AuditResult {
+                success: false,
+                error_code: Some("SOLVENCY_BELOW_FLOOR".to_string()),
+                error_message: None,
+                execution_time_ms: None,
+                gas_used: None,
+                cycles_consumed: None,
+                memory_used_bytes: None,
+                warning_flags: vec!["SOLVENCY".to_string()],
+            },
+            None,
+        );
+    }
+    report
+}
+
 /// Get investor balance for the calling investor
 /// Returns comprehensive balance information including deposits, withdrawals, and activity history
 /// 
@@ -855,6 +2184,153 @@ pub fn get_investor_balance_for_principal(investor: Principal) -> Result<Investo
     }
 }
 
+// ========== MINIMUM HOLDING PERIOD ==========
+
+/// Governance `min_holding_period_seconds` in nanoseconds; 0 makes every deposit yield-eligible at once
+pub fn min_holding_period_nanos() -> u64 {
+    crate::governance::get_protocol_parameter("min_holding_period_seconds".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(0)
+        .saturating_mul(1_000_000_000)
+}
+
+/// Deposit lots made before `at` that are still inside the holding period, as
+/// (yield-eligible-at, amount). Aggregate withdrawals draw on the newest lots first, so
+/// funds pulled out early are the ones that never earn yield.
+pub fn unseasoned_deposit_lots(balance: &InvestorBalance, at: u64, min_holding_period: u64) -> Vec<(u64, u64)> {
+    if min_holding_period == 0 {
+        return Vec::new();
+    }
+
+    open_deposit_lots(balance, at).into_iter()
+        .map(|lot| (lot.deposited_at.saturating_add(min_holding_period), lot.amount))
+        .filter(|(eligible_at, _)| *eligible_at > at)
+        .collect()
+}
+
+/// Split `current_balance` into (yield-eligible, pending) at `at`, with the time the next
+/// pending lot qualifies. Balances received outside deposits (position purchases) count as eligible.
+pub fn holding_period_status(
+    balance: &InvestorBalance,
+    current_balance: u64,
+    at: u64,
+    min_holding_period: u64,
+) -> (u64, u64, Option<u64>) {
+    let lots = unseasoned_deposit_lots(balance, at, min_holding_period);
+    let pending = lots.iter().map(|(_, amount)| *amount).sum::<u64>().min(current_balance);
+    let next_eligible_at = lots.iter().map(|(eligible_at, _)| *eligible_at).min();
+    (current_balance - pending, pending, next_eligible_at)
+}
+
+// ========== DEPOSIT LOTS ==========
+// Each deposit record is a lot, identified by its index in `InvestorBalance.deposits`.
+// FIFO/LIFO withdrawals record the lots they drew; aggregate withdrawals record none and
+// are replayed against the newest lots first.
+
+/// What remains of each deposit lot made before `at`, oldest first
+pub fn open_deposit_lots(balance: &InvestorBalance, at: u64) -> Vec<LotDraw> {
+    let mut lots: Vec<LotDraw> = balance.deposits.iter()
+        .enumerate()
+        .filter(|(_, deposit)| deposit.timestamp < at)
+        .map(|(index, deposit)| LotDraw { deposit_index: index as u64, deposited_at: deposit.timestamp, amount: deposit.amount })
+        .collect();
+
+    let mut withdrawals: Vec<&WithdrawalRecord> = balance.withdrawals.iter()
+        .filter(|withdrawal| withdrawal.timestamp < at)
+        .collect();
+    withdrawals.sort_by_key(|withdrawal| withdrawal.timestamp);
+
+    for withdrawal in withdrawals {
+        match &withdrawal.lots {
+            Some(draws) => {
+                for draw in draws {
+                    if let Some(lot) = lots.iter_mut().find(|lot| lot.deposit_index == draw.deposit_index) {
+                        lot.amount = lot.amount.saturating_sub(draw.amount);
+                    }
+                }
+            }
+            None => {
+                // Only lots deposited no later than the withdrawal can fund it
+                let mut remaining = withdrawal.amount;
+                for lot in lots.iter_mut().rev().filter(|lot| lot.deposited_at <= withdrawal.timestamp) {
+                    let drawn = lot.amount.min(remaining);
+                    lot.amount -= drawn;
+                    remaining -= drawn;
+                    if remaining == 0 {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    lots.retain(|lot| lot.amount > 0);
+    lots
+}
+
+/// Lots consumed by withdrawing `amount` under `method`. Aggregate assigns none; a shortfall
+/// is balance that did not come from deposits (position purchases) and is left unassigned.
+pub fn allocate_withdrawal_lots(open_lots: &[LotDraw], amount: u64, method: LotMethod) -> Vec<LotDraw> {
+    let ordered: Vec<&LotDraw> = match method {
+        LotMethod::Aggregate => return Vec::new(),
+        LotMethod::Fifo => open_lots.iter().collect(),
+        LotMethod::Lifo => open_lots.iter().rev().collect(),
+    };
+
+    let mut remaining = amount;
+    let mut draws = Vec::new();
+    for lot in ordered {
+        if remaining == 0 {
+            break;
+        }
+        let drawn = lot.amount.min(remaining);
+        draws.push(LotDraw { deposit_index: lot.deposit_index, deposited_at: lot.deposited_at, amount: drawn });
+        remaining -= drawn;
+    }
+    draws
+}
+
+/// Yield and losses realized by each lot drawn in withdrawals made in [from, to). A withdrawn
+/// unit earns its pro-rata share of every snapshot period it was held through from its start,
+/// once past the minimum holding period, as in the annual statement.
+pub fn realized_lot_gains(
+    balance: &InvestorBalance,
+    snapshots: &[PerformanceSnapshot],
+    min_holding_period: u64,
+    from: u64,
+    to: u64,
+) -> Vec<RealizedLotGain> {
+    let rounding = crate::helpers::rounding_mode();
+    let mut gains = Vec::new();
+    for withdrawal in balance.withdrawals.iter().filter(|withdrawal| withdrawal.timestamp >= from && withdrawal.timestamp < to) {
+        for draw in withdrawal.lots.iter().flatten() {
+            let eligible_from = draw.deposited_at.saturating_add(min_holding_period);
+            let mut yield_earned = 0u64;
+            let mut losses_allocated = 0u64;
+            for snapshot in snapshots.iter().filter(|snapshot| {
+                snapshot.total_liquidity > 0
+                    && snapshot.period_start > draw.deposited_at
+                    && snapshot.period_start >= eligible_from
+                    && snapshot.period_start <= withdrawal.timestamp
+            }) {
+                let share = |amount: u64| crate::helpers::rounded_share_with(amount, draw.amount, snapshot.total_liquidity, rounding);
+                yield_earned = yield_earned.saturating_add(share(snapshot.period_interest));
+                losses_allocated = losses_allocated.saturating_add(share(snapshot.period_losses));
+            }
+            gains.push(RealizedLotGain {
+                withdrawn_at: withdrawal.timestamp,
+                deposit_index: draw.deposit_index,
+                deposited_at: draw.deposited_at,
+                amount: draw.amount,
+                yield_earned,
+                losses_allocated,
+                net_gain: yield_earned as i64 - losses_allocated as i64,
+            });
+        }
+    }
+    gains
+}
+
 /// Get detailed pool information (admin only)
 #[query]
 pub fn get_pool_details() -> Result<LiquidityPool, String> {
@@ -867,16 +2343,364 @@ pub fn get_pool_details() -> Result<LiquidityPool, String> {
     Ok(get_liquidity_pool())
 }
 
-/// Get all investor balances (admin only)
-#[query]
-pub fn get_all_investor_balances_admin() -> Result<Vec<InvestorBalance>, String> {
+/// Resolve how much investor data an admin may see.
+/// Super admins get full records, treasury/protocol admins get redacted balances,
+/// any other admin gets aggregate statistics only.
+pub fn resolve_investor_data_tier(role: Option<&AdminRole>, is_config_admin: bool) -> Option<InvestorDataAccessTier> {
+    match role.filter(|r| r.is_active) {
+        Some(r) if matches!(r.role_type, AdminRoleType::SuperAdmin) => Some(InvestorDataAccessTier::Full),
+        Some(r) if matches!(r.role_type, AdminRoleType::TreasuryAdmin | AdminRoleType::ProtocolAdmin)
+            || r.permissions.iter().any(|p| matches!(p, Permission::ManageTreasury)) => {
+            Some(InvestorDataAccessTier::Operator)
+        },
+        Some(_) => Some(InvestorDataAccessTier::Observer),
+        None if is_config_admin => Some(InvestorDataAccessTier::Observer),
+        None => None,
+    }
+}
+
+fn investor_data_tier(caller: &Principal) -> Option<InvestorDataAccessTier> {
+    let role = crate::governance::get_admin_role(*caller);
+    resolve_investor_data_tier(role.as_ref(), is_admin(caller))
+}
+
+/// Check the caller's tier and record the access attempt in the compliance log
+pub(crate) fn authorize_investor_data_access(
+    caller: Principal,
+    target: Option<Principal>,
+    endpoint: &str,
+    required: InvestorDataAccessTier,
+) -> Result<InvestorDataAccessTier, String> {
+    let tier = investor_data_tier(&caller);
+    let allowed = match (&tier, &required) {
+        (Some(InvestorDataAccessTier::Full), _) => true,
+        (Some(InvestorDataAccessTier::Operator), InvestorDataAccessTier::Operator | InvestorDataAccessTier::Observer) => true,
+        (Some(InvestorDataAccessTier::Observer), InvestorDataAccessTier::Observer) => true,
+        _ => false,
+    };
+
+    log_investor_data_access(caller, target, endpoint, tier.clone(), allowed);
+
+    match tier {
+        Some(tier) if allowed => Ok(tier),
+        _ => Err(format!("Unauthorized: {:?} access to investor data required", required)),
+    }
+}
+
+pub fn redact_investor_balance(balance: &InvestorBalance) -> RedactedInvestorBalance {
+    RedactedInvestorBalance {
+        investor: balance.investor,
+        balance: balance.balance,
+        total_deposited: balance.total_deposited,
+        total_withdrawn: balance.total_withdrawn,
+        first_deposit_at: balance.first_deposit_at,
+        last_activity_at: balance.last_activity_at,
+    }
+}
+
+pub fn aggregate_investor_stats(balances: &[InvestorBalance]) -> InvestorAggregateStats {
+    let investor_count = balances.len() as u64;
+    let total_balance: u64 = balances.iter().map(|b| b.balance).sum();
+    let largest_balance = balances.iter().map(|b| b.balance).max().unwrap_or(0);
+
+    InvestorAggregateStats {
+        investor_count,
+        active_investor_count: balances.iter().filter(|b| b.balance > 0).count() as u64,
+        total_balance,
+        total_deposited: balances.iter().map(|b| b.total_deposited).sum(),
+        total_withdrawn: balances.iter().map(|b| b.total_withdrawn).sum(),
+        average_balance: if investor_count > 0 { total_balance / investor_count } else { 0 },
+        largest_balance_share_bps: if total_balance > 0 {
+            ((largest_balance as u128 * 10_000) / total_balance as u128) as u64
+        } else {
+            0
+        },
+    }
+}
+
+/// Get all investor balances with full history (super admin only).
+/// Update call so the compliance audit entry is persisted.
+#[update]
+pub fn get_all_investor_balances_admin(tag: Option<String>) -> Result<Vec<InvestorBalance>, String> {
     let caller = ic_cdk::caller();
-    
+    crate::validation::guard_caller(&caller, "get_all_investor_balances_admin")?;
+
+    authorize_investor_data_access(caller, None, "get_all_investor_balances_admin", InvestorDataAccessTier::Full)?;
+
+    investor_balances_filtered_by_tag(tag)
+}
+
+/// Get all investor balances without deposit/withdrawal history (operator tier and above)
+#[update]
+pub fn get_all_investor_balances_redacted(tag: Option<String>) -> Result<Vec<RedactedInvestorBalance>, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "get_all_investor_balances_redacted")?;
+
+    authorize_investor_data_access(caller, None, "get_all_investor_balances_redacted", InvestorDataAccessTier::Operator)?;
+
+    Ok(investor_balances_filtered_by_tag(tag)?.iter().map(redact_investor_balance).collect())
+}
+
+fn investor_balances_filtered_by_tag(tag: Option<String>) -> Result<Vec<InvestorBalance>, String> {
+    match tag {
+        Some(tag) => Ok(crate::operational_tags::investors_with_tag(&tag)?
+            .into_iter()
+            .filter_map(get_investor_balance_by_principal)
+            .collect()),
+        None => Ok(crate::storage::get_all_investor_balances()),
+    }
+}
+
+/// Get one investor's balance without deposit/withdrawal history (operator tier and above)
+#[update]
+pub fn get_investor_balance_redacted(investor: Principal) -> Result<RedactedInvestorBalance, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "get_investor_balance_redacted")?;
+
+    authorize_investor_data_access(caller, Some(investor), "get_investor_balance_redacted", InvestorDataAccessTier::Operator)?;
+
+    get_investor_balance_by_principal(investor)
+        .map(|balance| redact_investor_balance(&balance))
+        .ok_or_else(|| "Investor balance not found".to_string())
+}
+
+/// Get anonymized investor statistics (any admin)
+#[update]
+pub fn get_investor_aggregate_stats() -> Result<InvestorAggregateStats, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "get_investor_aggregate_stats")?;
+
+    authorize_investor_data_access(caller, None, "get_investor_aggregate_stats", InvestorDataAccessTier::Observer)?;
+
+    Ok(aggregate_investor_stats(&crate::storage::get_all_investor_balances()))
+}
+
+// ========== DUST SWEEP ==========
+
+/// A non-zero balance below the threshold, which cannot be withdrawn on its own
+pub fn is_dust_balance(balance: u64, threshold: u64) -> bool {
+    balance > 0 && balance < threshold
+}
+
+/// Zero out a dust balance and remove it from the pool totals.
+/// The residual is counted as withdrawn so balance == deposited - withdrawn still holds.
+/// Returns the swept amount; the caller credits it to the insurance fund.
+pub fn apply_dust_sweep(
+    investor_balance: &mut InvestorBalance,
+    pool: &mut LiquidityPool,
+    threshold: u64,
+    now: u64,
+) -> Result<u64, String> {
+    let dust = investor_balance.balance;
+    if !is_dust_balance(dust, threshold) {
+        return Err(format!(
+            "Balance of {} ckBTC satoshi is not dust (threshold {})",
+            dust, threshold
+        ));
+    }
+    if pool.available_liquidity < dust || pool.total_liquidity < dust {
+        return Err("Insufficient available liquidity to sweep this balance".to_string());
+    }
+
+    investor_balance.balance = 0;
+    investor_balance.total_withdrawn += dust;
+    investor_balance.last_activity_at = now;
+    // No ledger transfer: the balance moves to the insurance fund inside the canister
+    investor_balance.withdrawals.push(WithdrawalRecord {
+        investor: investor_balance.investor,
+        amount: dust,
+        ckbtc_block_index: 0,
+        timestamp: now,
+        fee: None,
+        lots: None,
+    });
+
+    pool.total_liquidity -= dust;
+    pool.available_liquidity -= dust;
+    pool.updated_at = now;
+
+    Ok(dust)
+}
+
+fn sweep_investor_dust(mut investor_balance: InvestorBalance, threshold: u64) -> Result<u64, String> {
+    let mut pool = get_liquidity_pool();
+    let dust = apply_dust_sweep(&mut investor_balance, &mut pool, threshold, time())?;
+
+    store_liquidity_pool(pool)?;
+    store_investor_balance(investor_balance)?;
+    record_dust_donation(dust);
+
+    Ok(dust)
+}
+
+/// Donate a below-minimum residual balance to the insurance fund.
+/// To reclaim the residual instead, deposit enough to bring the balance up to the
+/// minimum withdrawal amount and withdraw normally.
+#[update]
+pub fn sweep_dust_balance() -> Result<String, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "sweep_dust_balance")?;
+
+    if caller == Principal::anonymous() {
+        return Err("Anonymous users cannot sweep balances".to_string());
+    }
+    crate::helpers::ensure_operation_available("sweep_dust_balance")?;
+
+    let investor_balance = get_investor_balance_by_principal(caller)
+        .ok_or_else(|| "No investment balance found".to_string())?;
+
+    if investor_balance.balance >= MIN_WITHDRAWAL_AMOUNT {
+        return Err(format!(
+            "Balance of {} ckBTC satoshi can be withdrawn normally",
+            investor_balance.balance
+        ));
+    }
+
+    match sweep_investor_dust(investor_balance, MIN_WITHDRAWAL_AMOUNT) {
+        Ok(dust) => {
+            log_audit_action(
+                caller,
+                "LIQUIDITY_DUST_DONATED".to_string(),
+                format!("Donated dust balance of {} ckBTC satoshi to the insurance fund", dust),
+                true,
+            );
+            Ok(format!("Donated {} ckBTC satoshi to the insurance fund", dust))
+        },
+        Err(e) => {
+            log_audit_action(
+                caller,
+                "LIQUIDITY_DUST_DONATION_FAILED".to_string(),
+                e.clone(),
+                false,
+            );
+            Err(e)
+        }
+    }
+}
+
+/// Sweep every inactive balance below the threshold into the insurance fund (admin only).
+/// The threshold defaults to, and may not exceed, the minimum withdrawal amount.
+#[update]
+pub fn sweep_all_dust(threshold: Option<u64>) -> Result<DustSweepSummary, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "sweep_all_dust")?;
+
     if !is_admin(&caller) {
-        return Err("Unauthorized: Only admins can view all investor balances".to_string());
+        return Err("Unauthorized: Only admins can sweep dust balances".to_string());
     }
-    
-    Ok(crate::storage::get_all_investor_balances())
+
+    let threshold = threshold.unwrap_or(MIN_WITHDRAWAL_AMOUNT);
+    if threshold == 0 || threshold > MIN_WITHDRAWAL_AMOUNT {
+        return Err(format!("Threshold must be between 1 and {}", MIN_WITHDRAWAL_AMOUNT));
+    }
+
+    let now = time();
+    let mut swept_count = 0;
+    let mut total_swept = 0;
+    let mut skipped_count = 0;
+
+    for investor_balance in crate::storage::get_all_investor_balances() {
+        if !is_dust_balance(investor_balance.balance, threshold) {
+            continue;
+        }
+        if now.saturating_sub(investor_balance.last_activity_at) < DUST_SWEEP_MIN_INACTIVITY_NANOS {
+            skipped_count += 1;
+            continue;
+        }
+
+        let investor = investor_balance.investor;
+        match sweep_investor_dust(investor_balance, threshold) {
+            Ok(dust) => {
+                swept_count += 1;
+                total_swept += dust;
+                log_audit_action(
+                    caller,
+                    "LIQUIDITY_DUST_SWEPT".to_string(),
+                    format!("Swept {} ckBTC satoshi of dust from investor {} to the insurance fund", dust, investor.to_text()),
+                    true,
+                );
+            },
+            Err(e) => {
+                skipped_count += 1;
+                log_audit_action(
+                    caller,
+                    "LIQUIDITY_DUST_SWEEP_SKIPPED".to_string(),
+                    format!("Skipped dust sweep for investor {}: {}", investor.to_text(), e),
+                    false,
+                );
+            }
+        }
+    }
+
+    Ok(DustSweepSummary {
+        threshold,
+        swept_count,
+        total_swept,
+        skipped_count,
+        insurance_fund_balance: crate::treasury_management::get_insurance_fund_balance(),
+    })
+}
+
+pub fn build_dust_balances_report(balances: &[InvestorBalance], threshold: u64, now: u64) -> DustBalanceReport {
+    let entries: Vec<DustBalanceEntry> = balances.iter()
+        .filter(|b| is_dust_balance(b.balance, threshold))
+        .map(|b| DustBalanceEntry {
+            investor: b.investor,
+            balance: b.balance,
+            last_activity_at: b.last_activity_at,
+        })
+        .collect();
+
+    DustBalanceReport {
+        threshold,
+        investor_count: entries.len() as u64,
+        total_dust: entries.iter().map(|e| e.balance).sum(),
+        entries,
+        generated_at: now,
+    }
+}
+
+/// Investor balances below the minimum withdrawal amount (operator tier and above)
+#[query]
+pub fn get_dust_balances_report() -> Result<DustBalanceReport, String> {
+    let caller = ic_cdk::caller();
+
+    authorize_investor_data_access(caller, None, "get_dust_balances_report", InvestorDataAccessTier::Operator)?;
+
+    Ok(build_dust_balances_report(
+        &crate::storage::get_all_investor_balances(),
+        MIN_WITHDRAWAL_AMOUNT,
+        time(),
+    ))
+}
+
+/// Return repaid funds to the pool of the asset the loan was made in
+/// Shared by the loan manager entry point and in-canister repayment flows
+pub fn apply_repayment_to_asset_pool(asset: &Asset, amount: u64) -> Result<(), String> {
+    let mut pool = get_asset_pool(asset);
+    pool.available_liquidity += amount;
+    pool.total_repaid += amount;
+    pool.updated_at = time();
+    store_asset_pool(asset, pool)
+}
+
+/// Keep a fee charged on a loan in `asset`'s pool as available liquidity. Treasury and the
+/// insurance fund hold ckBTC only, so fees on other assets accrue to that asset's investors.
+pub fn retain_fee_in_asset_pool(asset: &Asset, amount: u64) -> Result<(), String> {
+    let mut pool = get_asset_pool(asset);
+    pool.available_liquidity += amount;
+    pool.updated_at = time();
+    store_asset_pool(asset, pool)
+}
+
+/// A refinance disburses the payoff into the old loan: no cash moves, but the payoff is both
+/// borrowed by the new loan and repaid on the old one
+pub fn apply_refinancing_to_pool(asset: &Asset, payoff: u64) -> Result<(), String> {
+    let mut pool = get_asset_pool(asset);
+    pool.total_borrowed += payoff;
+    pool.total_repaid += payoff;
+    pool.updated_at = time();
+    store_asset_pool(asset, pool)
 }
 
 /// Process loan repayment and update pool
@@ -884,24 +2708,21 @@ pub fn get_all_investor_balances_admin() -> Result<Vec<InvestorBalance>, String>
 #[update]
 pub fn process_loan_repayment(loan_id: u64, amount: u64) -> Result<String, String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "process_loan_repayment")?;
     
     // Only loan management canister can process repayments
     if !is_loan_manager(&caller) {
         return Err("Unauthorized: Only loan manager can process repayments".to_string());
     }
     
-    // Update pool state
-    let mut pool = get_liquidity_pool();
-    pool.available_liquidity += amount;
-    pool.total_repaid += amount;
-    pool.updated_at = time();
-    store_liquidity_pool(pool)?;
+    let asset = crate::storage::get_loan(loan_id).map(|loan| loan.asset()).unwrap_or_default();
+    apply_repayment_to_asset_pool(&asset, amount)?;
     
     // Log audit action
     log_audit_action(
         caller,
         "LOAN_REPAYMENT_PROCESSED".to_string(),
-        format!("Processed repayment of {} ckBTC satoshi for loan #{}", amount, loan_id),
+        format!("Processed repayment of {} {} base units for loan #{}", amount, asset.symbol(), loan_id),
         true,
     );
     
@@ -917,31 +2738,42 @@ pub async fn record_liquidation_loss(
     total_debt: u64
 ) -> Result<String, String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "record_liquidation_loss")?;
     
     // Only liquidation system can record losses
     if !is_admin(&caller) && !is_loan_manager(&caller) {
         return Err("Unauthorized: Only admin or loan manager can record liquidation losses".to_string());
     }
 
-    // Update pool state to reflect the loss
-    let mut pool = get_liquidity_pool();
+    // Update the pool the loan was made from to reflect the loss
+    let asset = crate::storage::get_loan(loan_id).map(|loan| loan.asset()).unwrap_or_default();
+    let mut pool = get_asset_pool(&asset);
     
     // Record the principal loss (affects investor returns)
     pool.total_borrowed = pool.total_borrowed.saturating_sub(principal_loss);
     
+    // Insurance fund absorbs the loss first; only the remainder hits investors.
+    // The fund holds ckBTC, so losses on other assets are socialized in full.
+    let covered_by_insurance = match asset {
+        Asset::CkBtc => cover_loss_from_insurance_fund(loan_id, principal_loss),
+        _ => 0,
+    };
+    pool.available_liquidity += covered_by_insurance;
+    let socialized_loss = principal_loss - covered_by_insurance;
+    
     // Update pool metrics untuk reflect liquidation impact
     pool.updated_at = time();
     
     // Store updated pool state
-    store_liquidity_pool(pool)?;
+    store_asset_pool(&asset, pool)?;
 
     // Log comprehensive audit trail
     log_audit_action(
         caller,
         "LIQUIDATION_LOSS_RECORDED".to_string(),
         format!(
-            "Liquidation loss recorded for loan #{}: Principal loss: {} satoshi, Total debt: {} satoshi. Pool adjusted accordingly.",
-            loan_id, principal_loss, total_debt
+            "Liquidation loss recorded for loan #{}: Principal loss: {} satoshi, Total debt: {} satoshi. Insurance fund covered {} satoshi, {} satoshi socialized to investors.",
+            loan_id, principal_loss, total_debt, covered_by_insurance, socialized_loss
         ),
         true,
     );
@@ -952,42 +2784,11 @@ pub async fn record_liquidation_loss(
     ))
 }
 
-/// Collect protocol fees from loan repayments
-#[update]
-pub async fn collect_protocol_fees(loan_id: u64, fee_amount: u64) -> Result<String, String> {
-    let caller = ic_cdk::caller();
-    
-    // Only loan management canister can collect fees
-    if !is_loan_manager(&caller) {
-        return Err("Unauthorized: Only loan manager can collect protocol fees".to_string());
-    }
-    
-    if fee_amount == 0 {
-        return Ok("No fees to collect".to_string());
-    }
-    
-    // Update pool state with protocol earnings
-    let mut pool = get_liquidity_pool();
-    // In a real implementation, you might have a separate treasury balance
-    // For now, we'll just track it in the pool
-    pool.updated_at = time();
-    store_liquidity_pool(pool)?;
-    
-    // Log audit action
-    log_audit_action(
-        caller,
-        "PROTOCOL_FEE_COLLECTED".to_string(),
-        format!("Collected {} satoshi protocol fee from loan #{}", fee_amount, loan_id),
-        true,
-    );
-    
-    Ok(format!("Successfully collected {} satoshi in protocol fees", fee_amount))
-}
-
 /// Emergency pause function (admin only)
 #[update]
 pub fn emergency_pause_pool() -> Result<String, String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "emergency_pause_pool")?;
     
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can pause the pool".to_string());
@@ -1010,6 +2811,7 @@ pub fn emergency_pause_pool() -> Result<String, String> {
 #[update]
 pub fn resume_pool_operations() -> Result<String, String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "resume_pool_operations")?;
     
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can resume pool operations".to_string());
@@ -1028,30 +2830,70 @@ pub fn resume_pool_operations() -> Result<String, String> {
     Ok("Pool operations resumed successfully".to_string())
 }
 
+/// Pause or resume new loan originations only (admin only). Deposits, withdrawals and
+/// repayments keep working; a full emergency pause still takes precedence.
+#[update]
+pub fn set_origination_paused(paused: bool) -> Result<String, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "set_origination_paused")?;
+    
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can pause loan originations".to_string());
+    }
+    
+    set_origination_pause(paused)?;
+    
+    log_audit_action(
+        caller,
+        if paused { "ORIGINATION_PAUSE" } else { "ORIGINATION_RESUME" }.to_string(),
+        format!(
+            "New loan originations {}{}",
+            if paused { "paused" } else { "resumed" },
+            if is_emergency_paused() { " (full emergency pause still active)" } else { "" }
+        ),
+        true,
+    );
+    
+    Ok(format!("Loan originations {} successfully", if paused { "paused" } else { "resumed" }))
+}
+
+/// Reason new originations are blocked, if any. The full pause is reported first since it supersedes.
+pub fn origination_block_reason(emergency_paused: bool, origination_paused: bool) -> Option<String> {
+    if emergency_paused {
+        Some("Pool operations are currently paused".to_string())
+    } else if origination_paused {
+        Some("New loan originations are currently paused".to_string())
+    } else {
+        None
+    }
+}
+
+/// Guard for loan applications and disbursements
+pub fn ensure_origination_allowed() -> Result<(), String> {
+    match origination_block_reason(is_emergency_paused(), is_origination_paused()) {
+        Some(reason) => Err(reason),
+        None => Ok(()),
+    }
+}
+
 // Helper functions for liquidity management
 
-/// Calculate pool APY based on utilization rate and historical performance
-fn calculate_pool_apy(pool: &LiquidityPool) -> u64 {
-    // Calculate utilization rate
-    let utilization_rate = if pool.total_liquidity > 0 {
-        ((pool.total_liquidity - pool.available_liquidity) * 100) / pool.total_liquidity
+/// Pool APY in basis points under `params`: the base rate plus utilization and repayment
+/// performance bonuses, kept between the base rate and the cap
+pub fn pool_apy_bps(pool: &LiquidityPool, params: &PoolApyParameters) -> u64 {
+    let utilization_pct = if pool.total_liquidity > 0 {
+        (pool.total_liquidity.saturating_sub(pool.available_liquidity) as u128 * 100 / pool.total_liquidity as u128) as u64
     } else {
         0
     };
+    let utilization_bonus = utilization_pct.saturating_mul(params.utilization_bonus_per_pct);
     
-    // Base APY starts at 3%
-    let base_apy = 3;
-    
-    // Add utilization bonus: 0.05% per 1% utilization
-    let utilization_bonus = (utilization_rate * 5) / 100;
-    
-    // Performance bonus based on repayment rate
     let performance_bonus = if pool.total_borrowed > 0 {
-        let repayment_rate = (pool.total_repaid * 100) / pool.total_borrowed;
-        if repayment_rate > 90 {
-            2 // 2% bonus for >90% repayment rate
-        } else if repayment_rate > 75 {
-            1 // 1% bonus for >75% repayment rate
+        let repayment_rate = pool.total_repaid as u128 * 100 / pool.total_borrowed as u128;
+        if repayment_rate > params.repayment_bonus_high_threshold as u128 {
+            params.repayment_bonus_high_bps
+        } else if repayment_rate > params.repayment_bonus_low_threshold as u128 {
+            params.repayment_bonus_low_bps
         } else {
             0
         }
@@ -1059,9 +2901,13 @@ fn calculate_pool_apy(pool: &LiquidityPool) -> u64 {
         0
     };
     
-    // Cap maximum APY at 15%
-    let total_apy = base_apy + utilization_bonus + performance_bonus;
-    std::cmp::min(total_apy, 15)
+    params.clamp_apy(params.base_apy_bps.saturating_add(utilization_bonus).saturating_add(performance_bonus))
+}
+
+/// Calculate pool APY (basis points) from utilization and repayment performance under the
+/// governance APY parameters
+pub fn calculate_pool_apy(pool: &LiquidityPool) -> u64 {
+    pool_apy_bps(pool, &crate::storage::get_protocol_parameters().pool_apy())
 }
 
 /// Calculate pool health score (0-100)
@@ -1104,7 +2950,78 @@ fn is_loan_manager(principal: &Principal) -> bool {
     is_loan_manager_canister(principal) || is_admin(principal)
 }
 
-/// Validate Bitcoin address format (basic validation)
+/// Validate Bitcoin address format (basic validation)
+/// The destination must be the borrower's registered address unless an admin approved another one for this loan
+pub fn disbursement_destination_allowed(
+    destination: &str,
+    registered_address: Option<&str>,
+    approved_override: Option<&str>,
+) -> bool {
+    use crate::production_security::normalize_btc_address;
+    
+    let destination = normalize_btc_address(destination);
+    registered_address.map_or(false, |address| normalize_btc_address(address) == destination)
+        || approved_override.map_or(false, |address| normalize_btc_address(address) == destination)
+}
+
+fn verify_disbursement_destination(loan_id: u64, borrower: Principal, destination: &str) -> Result<(), String> {
+    let registered = crate::helpers::get_user_btc_address(&borrower);
+    let approved = crate::storage::get_disbursement_address_override(loan_id).map(|entry| entry.btc_address);
+    
+    if disbursement_destination_allowed(destination, registered.as_deref(), approved.as_deref()) {
+        return Ok(());
+    }
+    
+    crate::audit_logging::log_security_event(
+        "DISBURSEMENT_ADDRESS_MISMATCH",
+        crate::audit_logging::AuditEventLevel::Warning,
+        format!(
+            "Disbursement for loan #{} to {} rejected: borrower's registered address is {:?}",
+            loan_id, destination, registered
+        ),
+        Some(borrower),
+        vec!["DISBURSEMENT_ADDRESS_MISMATCH".to_string()],
+    );
+    Err("Disbursement address does not match the borrower's registered Bitcoin address".to_string())
+}
+
+/// Approve disbursing a loan to an address other than the borrower's registered one (admin only).
+/// The address is still subject to the denylist.
+#[update]
+pub fn approve_disbursement_address_override(loan_id: u64, btc_address: String, reason: String) -> Result<String, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "approve_disbursement_address_override")?;
+    
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can override disbursement addresses".to_string());
+    }
+    if !is_valid_bitcoin_address(&btc_address) {
+        return Err("Invalid Bitcoin address format".to_string());
+    }
+    if reason.trim().is_empty() {
+        return Err("A reason is required to override the disbursement address".to_string());
+    }
+    crate::storage::get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    crate::production_security::ensure_btc_address_allowed(&btc_address, "approve_disbursement_address_override", None)?;
+    
+    crate::storage::store_disbursement_address_override(DisbursementAddressOverride {
+        loan_id,
+        btc_address: btc_address.clone(),
+        reason: reason.clone(),
+        approved_by: caller,
+        approved_at: time(),
+    });
+    
+    log_audit_action(
+        caller,
+        "DISBURSEMENT_ADDRESS_OVERRIDE".to_string(),
+        format!("Loan #{} may be disbursed to {}: {}", loan_id, btc_address, reason),
+        true,
+    );
+    
+    Ok(format!("Disbursement address override approved for loan #{}", loan_id))
+}
+
 fn is_valid_bitcoin_address(address: &str) -> bool {
     // Basic Bitcoin address validation
     // This is a simplified check - in production, use a proper Bitcoin address library
@@ -1148,6 +3065,16 @@ pub fn get_disbursement_records_by_loan(loan_id: u64) -> Vec<DisbursementRecord>
         .collect()
 }
 
+/// Network fee the borrower bore on a loan's disbursements. It is deducted from what the
+/// borrower received but stays part of the principal owed, so it is shown as its own charge.
+pub fn borrower_network_fee_charge(records: &[DisbursementRecord]) -> Option<u64> {
+    let charged: u64 = records.iter()
+        .filter(|record| record.fee_bearer.unwrap_or_default() == FeeBearer::Borrower)
+        .filter_map(|record| record.network_fee)
+        .sum();
+    if charged > 0 { Some(charged) } else { None }
+}
+
 /// Validate withdrawal request with comprehensive checks
 /// This function performs all validation logic for withdrawal requests
 /// Used both for actual withdrawals and for UI validation
@@ -1165,7 +3092,6 @@ pub fn validate_withdrawal_request(amount: u64) -> Result<WithdrawalValidation,
         return Err("Amount must be greater than zero".to_string());
     }
     
-    const MIN_WITHDRAWAL_AMOUNT: u64 = 1000;
     if amount < MIN_WITHDRAWAL_AMOUNT {
         return Err(format!("Minimum withdrawal amount is {} ckBTC satoshi", MIN_WITHDRAWAL_AMOUNT));
     }
@@ -1176,29 +3102,29 @@ pub fn validate_withdrawal_request(amount: u64) -> Result<WithdrawalValidation,
         None => return Err("No investment balance found".to_string()),
     };
     
-    // Check investor balance sufficiency
-    if investor_balance.balance < amount {
+    // Check investor balance sufficiency; balance committed to position offers is not withdrawable
+    let withdrawable = investor_balance.balance.saturating_sub(offered_position_amount(caller));
+    if withdrawable < amount {
         return Err(format!(
             "Insufficient balance. Available: {} ckBTC satoshi", 
-            investor_balance.balance
+            withdrawable
         ));
     }
     
-    // Check pool liquidity
+    // Check pool liquidity; liquidity reserved for approved loans is not available to withdraw
     let pool = get_liquidity_pool();
-    if pool.available_liquidity < amount {
+    let unreserved = pool.unreserved_liquidity();
+    if unreserved < amount {
         return Err(format!(
             "Insufficient pool liquidity. Available: {} ckBTC satoshi", 
-            pool.available_liquidity
+            unreserved
         ));
     }
     
     // Check emergency reserve
-    let emergency_reserve_ratio = 5; // 5%
-    let required_reserve = (pool.total_liquidity * emergency_reserve_ratio) / 100;
-    let liquidity_after_withdrawal = pool.available_liquidity - amount;
+    let liquidity_after_withdrawal = unreserved - amount;
     
-    if liquidity_after_withdrawal < required_reserve {
+    if liquidity_after_withdrawal < required_reserve(&pool) {
         return Err("Withdrawal would violate emergency reserve requirements".to_string());
     }
     
@@ -1212,13 +3138,13 @@ pub fn validate_withdrawal_request(amount: u64) -> Result<WithdrawalValidation,
         return Err("Rate limit exceeded. Please try again later".to_string());
     }
     
-    // Calculate fees and final amount (if any fees are implemented)
-    let withdrawal_fee = 0u64; // Currently no withdrawal fees
+    // Calculate fees and final amount
+    let withdrawal_fee = calculate_pool_fee(amount, get_canister_config().withdrawal_fee_bps());
     let net_amount = amount.saturating_sub(withdrawal_fee);
     
     // Calculate new balance after withdrawal
     let new_balance = investor_balance.balance - amount;
-    let new_pool_liquidity = pool.available_liquidity - amount;
+    let new_pool_liquidity = unreserved - amount;
     
     Ok(WithdrawalValidation {
         is_valid: true,
@@ -1227,7 +3153,7 @@ pub fn validate_withdrawal_request(amount: u64) -> Result<WithdrawalValidation,
         net_amount,
         current_balance: investor_balance.balance,
         new_balance,
-        current_pool_liquidity: pool.available_liquidity,
+        current_pool_liquidity: unreserved,
         new_pool_liquidity,
         estimated_confirmation_time: 300, // 5 minutes in seconds
         warnings: vec![], // Add any warnings here
@@ -1286,6 +3212,10 @@ pub fn get_investor_statistics() -> Result<InvestorStatistics, String> {
         0
     };
     
+    let min_holding_period = min_holding_period_nanos();
+    let (yield_eligible_balance, yield_pending_balance, next_yield_eligible_at) =
+        holding_period_status(&investor_balance, investor_balance.balance, time(), min_holding_period);
+    
     Ok(InvestorStatistics {
         investor: caller,
         current_balance: investor_balance.balance,
@@ -1301,22 +3231,24 @@ pub fn get_investor_statistics() -> Result<InvestorStatistics, String> {
         days_since_last_activity,
         is_active_investor: days_since_last_activity <= 30, // Active if activity within 30 days
         risk_level: if investor_balance.balance > 10_000_000 { "HIGH" } else if investor_balance.balance > 1_000_000 { "MEDIUM" } else { "LOW" }.to_string(),
+        yield_eligible_balance,
+        yield_pending_balance,
+        next_yield_eligible_at,
+        min_holding_period_seconds: min_holding_period / 1_000_000_000,
     })
 }
 
 /// Get withdrawal fee estimate
-/// Calculates estimated fees for a withdrawal (currently zero)
+/// Calculates estimated fees for a withdrawal using the configured fee rate
 #[query]
 pub fn get_withdrawal_fee_estimate(amount: u64) -> Result<WithdrawalFeeEstimate, String> {
     if amount == 0 {
         return Err("Amount must be greater than zero".to_string());
     }
     
-    // Currently no withdrawal fees implemented
-    // This function is prepared for future fee implementation
     let base_fee = 0u64;
-    let percentage_fee = 0u64; // 0% fee
-    let total_fee = base_fee + ((amount * percentage_fee) / 10000);
+    let percentage_fee = get_canister_config().withdrawal_fee_bps();
+    let total_fee = base_fee + calculate_pool_fee(amount, percentage_fee);
     let net_amount = amount.saturating_sub(total_fee);
     
     Ok(WithdrawalFeeEstimate {
@@ -1335,14 +3267,17 @@ pub fn get_withdrawal_fee_estimate(amount: u64) -> Result<WithdrawalFeeEstimate,
 pub async fn emergency_admin_withdrawal(
     investor: Principal, 
     amount: u64, 
-    reason: String
+    reason: String,
+    nonce: Option<u64>,
 ) -> Result<String, String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "emergency_admin_withdrawal")?;
     
     // Only admins can perform emergency withdrawals
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can perform emergency withdrawals".to_string());
     }
+    crate::validation::require_admin_nonce(&caller, nonce, "emergency_admin_withdrawal")?;
     
     // Validate inputs
     if amount == 0 {
@@ -1379,8 +3314,7 @@ pub async fn emergency_admin_withdrawal(
     );
     
     // Prepare ckBTC transfer
-    let ckbtc_ledger = Principal::from_text(CKBTC_LEDGER_PRINCIPAL)
-        .map_err(|_| "Invalid ckBTC ledger principal configuration")?;
+    let ckbtc_ledger = crate::helpers::ckbtc_ledger_principal();
     
     let investor_account = Account {
         owner: investor,
@@ -1392,13 +3326,15 @@ pub async fn emergency_admin_withdrawal(
         to: investor_account,
         amount: Nat::from(amount),
         fee: None,
-        memo: Some(format!("EMERGENCY WITHDRAWAL by admin: {}", reason).as_bytes().to_vec()),
+        memo: Some(crate::helpers::ledger_memo(&format!("Emergency withdrawal: {}", reason))),
         created_at_time: Some(time()),
     };
     
     // Execute transfer
-    let call_result: Result<(Result<Nat, TransferError>,), _> = 
-        call(ckbtc_ledger, "icrc1_transfer", (transfer_args,)).await;
+    let call_result: Result<(Result<Nat, TransferError>,), _> = call_ckbtc_with_retry(
+        "icrc1_transfer",
+        || call(ckbtc_ledger, "icrc1_transfer", (transfer_args.clone(),)),
+    ).await;
     
     match call_result {
         Ok((Ok(block_index),)) => {
@@ -1423,6 +3359,8 @@ pub async fn emergency_admin_withdrawal(
                 amount,
                 ckbtc_block_index: block_idx,
                 timestamp: time(),
+                fee: Some(0),
+                lots: None,
             };
             updated_investor_balance.withdrawals.push(withdrawal_record);
             
@@ -1431,166 +3369,968 @@ pub async fn emergency_admin_withdrawal(
             // Comprehensive audit logging
             log_audit_action(
                 caller,
-                "EMERGENCY_WITHDRAWAL_SUCCESS".to_string(),
-                format!(
-                    "Emergency withdrawal completed: {} ckBTC satoshi for investor {}, block: {}, reason: {}", 
-                    amount, investor, block_idx, reason
-                ),
-                true,
+                "EMERGENCY_WITHDRAWAL_SUCCESS".to_string(),
+                format!(
+                    "Emergency withdrawal completed: {} ckBTC satoshi for investor {}, block: {}, reason: {}", 
+                    amount, investor, block_idx, reason
+                ),
+                true,
+            );
+            
+            Ok(format!("Emergency withdrawal successful. Block: {}", block_idx))
+        }
+        Ok((Err(transfer_error),)) => {
+            let error_msg = format!("Emergency withdrawal failed: {:?}", transfer_error);
+            log_audit_action(
+                caller,
+                "EMERGENCY_WITHDRAWAL_FAILED".to_string(),
+                format!("Emergency withdrawal failed for investor {}: {}", investor, error_msg),
+                false,
+            );
+            Err(error_msg)
+        }
+        Err(call_error) => {
+            let error_msg = format!("Network error during emergency withdrawal: {:?}", call_error);
+            log_audit_action(
+                caller,
+                "EMERGENCY_WITHDRAWAL_NETWORK_ERROR".to_string(),
+                format!("Emergency withdrawal network error for investor {}: {}", investor, error_msg),
+                false,
+            );
+            Err(error_msg)
+        }
+    }
+}
+
+/// Get investor's transaction history
+#[query]
+pub fn get_investor_transaction_history() -> Result<InvestorTransactionHistory, String> {
+    let caller = ic_cdk::caller();
+    
+    // Get investor balance
+    let investor_balance = get_investor_balance_for_principal(caller)
+        .map_err(|e| format!("No balance found for investor: {}", e))?;
+    
+    Ok(InvestorTransactionHistory {
+        investor: caller,
+        deposits: investor_balance.deposits,
+        withdrawals: investor_balance.withdrawals,
+        total_deposited: investor_balance.total_deposited,
+        total_withdrawn: investor_balance.total_withdrawn,
+        net_balance: investor_balance.balance,
+        first_activity: investor_balance.first_deposit_at,
+        last_activity: investor_balance.last_activity_at,
+    })
+}
+
+/// Get all disbursement records (admin only)
+#[query]
+pub fn get_all_disbursements() -> Result<Vec<DisbursementRecord>, String> {
+    let caller = ic_cdk::caller();
+    
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can view all disbursements".to_string());
+    }
+    
+    Ok(get_all_disbursement_records())
+}
+
+/// Get disbursement records for a specific loan
+#[query]
+pub fn get_loan_disbursements(loan_id: u64) -> Result<Vec<DisbursementRecord>, String> {
+    let caller = ic_cdk::caller();
+    
+    // Allow admins and loan managers to view disbursements
+    if !is_admin(&caller) && !is_loan_manager_canister(&caller) {
+        return Err("Unauthorized: Only admins and loan managers can view disbursements".to_string());
+    }
+    
+    Ok(get_disbursement_records_by_loan(loan_id))
+}
+
+/// Force refresh pool statistics (admin only)
+#[update]
+pub fn refresh_pool_statistics() -> Result<String, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "refresh_pool_statistics")?;
+    
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can refresh pool statistics".to_string());
+    }
+    
+    // Recalculate pool statistics
+    let mut pool = get_liquidity_pool();
+    pool.updated_at = time();
+    
+    // Recalculate investor count
+    let all_balances = crate::storage::get_all_investor_balances();
+    let active_investors = all_balances.iter()
+        .filter(|balance| balance.balance > 0)
+        .count() as u64;
+    
+    pool.total_investors = active_investors;
+    
+    store_liquidity_pool(pool)?;
+    
+    log_audit_action(
+        caller,
+        "POOL_STATISTICS_REFRESH".to_string(),
+        "Pool statistics refreshed manually".to_string(),
+        true,
+    );
+    
+    Ok("Pool statistics refreshed successfully".to_string())
+}
+
+/// Set liquidity pool parameters (admin only)
+#[update]
+pub fn set_pool_parameters(
+    min_deposit_amount: Option<u64>,
+    max_utilization_rate: Option<u64>,
+    emergency_reserve_ratio: Option<u64>,
+    deposit_fee_bps: Option<u64>,
+    withdrawal_fee_bps: Option<u64>,
+    reserve_min_absolute: Option<u64>,
+    reserve_taper_threshold: Option<u64>,
+    reserve_taper_ratio: Option<u64>
+) -> Result<String, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "set_pool_parameters")?;
+    
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can set pool parameters".to_string());
+    }
+    
+    // Store parameters in canister configuration
+    let mut config = get_canister_config();
+    
+    if let Some(min_deposit) = min_deposit_amount {
+        if min_deposit < 10_000 { // Minimum 0.0001 BTC
+            return Err("Minimum deposit amount too small".to_string());
+        }
+        config.min_deposit_amount = min_deposit;
+    }
+    
+    if let Some(max_util) = max_utilization_rate {
+        if max_util > 95 {
+            return Err("Maximum utilization rate cannot exceed 95%".to_string());
+        }
+        config.max_utilization_rate = max_util * 100; // Convert to basis points
+    }
+    
+    if let Some(reserve_ratio) = emergency_reserve_ratio {
+        if reserve_ratio < 5 || reserve_ratio > 50 {
+            return Err("Emergency reserve ratio must be between 5% and 50%".to_string());
+        }
+        config.pool_reserve_ratio_bps = Some(reserve_ratio * 100); // Convert to basis points
+    }
+    
+    if let Some(min_absolute) = reserve_min_absolute {
+        config.reserve_min_absolute = Some(min_absolute);
+    }
+    
+    if let Some(threshold) = reserve_taper_threshold {
+        // Zero removes the taper
+        config.reserve_taper_threshold = if threshold == 0 { None } else { Some(threshold) };
+    }
+    
+    if let Some(taper_ratio) = reserve_taper_ratio {
+        if taper_ratio < 1 || taper_ratio * 100 > config.pool_reserve_ratio_bps() {
+            return Err("Tapered reserve ratio must be at least 1% and no higher than the emergency reserve ratio".to_string());
+        }
+        config.reserve_taper_ratio_bps = Some(taper_ratio * 100); // Convert to basis points
+    }
+    
+    if let Some(fee) = deposit_fee_bps {
+        if fee > MAX_POOL_FEE_BPS {
+            return Err(format!("Deposit fee cannot exceed {} basis points", MAX_POOL_FEE_BPS));
+        }
+        config.deposit_fee_bps = Some(fee);
+    }
+    
+    if let Some(fee) = withdrawal_fee_bps {
+        if fee > MAX_POOL_FEE_BPS {
+            return Err(format!("Withdrawal fee cannot exceed {} basis points", MAX_POOL_FEE_BPS));
+        }
+        config.withdrawal_fee_bps = Some(fee);
+    }
+    
+    set_canister_config(config)?;
+    
+    log_audit_action(
+        caller,
+        "POOL_PARAMETERS_UPDATE".to_string(),
+        format!("Pool parameters updated: min_deposit={:?}, max_util={:?}, reserve_ratio={:?}, deposit_fee_bps={:?}, withdrawal_fee_bps={:?}, reserve_min_absolute={:?}, reserve_taper_threshold={:?}, reserve_taper_ratio={:?}", 
+                min_deposit_amount, max_utilization_rate, emergency_reserve_ratio, deposit_fee_bps, withdrawal_fee_bps,
+                reserve_min_absolute, reserve_taper_threshold, reserve_taper_ratio),
+        true,
+    );
+    
+    Ok("Pool parameters updated successfully".to_string())
+}
+
+// ========== POSITION TRANSFERS ==========
+// Investors can sell part of their pool position to another investor instead of withdrawing.
+// The buyer pays the seller directly in ckBTC (pulled via the buyer's ICRC-2 approval to this
+// canister); the pool's liquidity is untouched and only ownership of the balance moves.
+
+pub const MAX_OPEN_POSITION_OFFERS: usize = 5;
+// A payment older than the ledger's 24h deduplication window can no longer be resumed
+pub const POSITION_SETTLEMENT_TIMEOUT_NANOS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Balance the investor has bought from other investors, which KYC tiers count like deposits
+pub fn purchased_position_amount(investor: Principal) -> u64 {
+    get_all_position_offers()
+        .iter()
+        .filter(|offer| offer.status == PositionOfferStatus::Accepted && offer.accepted_by == Some(investor))
+        .map(|offer| offer.amount)
+        .sum()
+}
+
+/// Whether a settling offer's payment has outlived the window in which the buyer can resume it
+pub fn position_settlement_stale(offer: &PositionOffer, now: u64) -> bool {
+    offer.status == PositionOfferStatus::Settling
+        && offer.payment_created_at.map_or(true, |created_at| now.saturating_sub(created_at) >= POSITION_SETTLEMENT_TIMEOUT_NANOS)
+}
+
+/// Move offers stuck in Settling past the timeout to Failed for manual review, releasing the
+/// seller's committed balance
+pub fn expire_stale_position_settlements(now: u64) -> u64 {
+    let stale: Vec<PositionOffer> = get_all_position_offers()
+        .into_iter()
+        .filter(|offer| position_settlement_stale(offer, now))
+        .collect();
+
+    for mut offer in stale.iter().cloned() {
+        offer.status = PositionOfferStatus::Failed;
+        log_audit_action(
+            offer.seller,
+            "POSITION_OFFER_SETTLEMENT_TIMED_OUT".to_string(),
+            format!(
+                "Position offer #{} accepted by {:?} was still settling after the timeout; marked failed for manual review",
+                offer.id, offer.accepted_by
+            ),
+            false,
+        );
+        store_position_offer(offer);
+    }
+
+    stale.len() as u64
+}
+
+/// Balance the investor has committed to open or settling offers
+pub fn offered_position_amount(investor: Principal) -> u64 {
+    get_all_position_offers()
+        .iter()
+        .filter(|offer| offer.seller == investor)
+        .filter(|offer| matches!(offer.status, PositionOfferStatus::Open | PositionOfferStatus::Settling))
+        .map(|offer| offer.amount)
+        .sum()
+}
+
+/// Validate a new offer against the seller's uncommitted balance and open offer cap
+pub fn validate_position_offer(
+    balance: u64,
+    already_offered: u64,
+    open_offers: usize,
+    amount: u64,
+    price: u64,
+) -> Result<(), String> {
+    if amount < MIN_WITHDRAWAL_AMOUNT {
+        return Err(format!("Offered amount must be at least {} ckBTC satoshi", MIN_WITHDRAWAL_AMOUNT));
+    }
+    if price == 0 {
+        return Err("Price must be greater than zero".to_string());
+    }
+    if open_offers >= MAX_OPEN_POSITION_OFFERS {
+        return Err(format!("An investor can have at most {} open position offers", MAX_OPEN_POSITION_OFFERS));
+    }
+    let uncommitted = balance.saturating_sub(already_offered);
+    if amount > uncommitted {
+        return Err(format!(
+            "Offered amount exceeds your uncommitted balance. Available: {} ckBTC satoshi",
+            uncommitted
+        ));
+    }
+    Ok(())
+}
+
+/// Buyer must be a registered, active investor with the KYC tier a deposit of the same size would need
+fn ensure_eligible_position_buyer(buyer: Principal, amount: u64) -> Result<(), String> {
+    crate::validation::guard_caller(&buyer, "accept_position_offer")?;
+    crate::validation::require_role(&buyer, crate::validation::CallerRole::Investor)?;
+    let user = crate::validation::require_registered_active_user(&buyer)?;
+
+    let cumulative_before = get_investor_balance_by_principal(buyer)
+        .map(|balance| balance.total_deposited)
+        .unwrap_or(0)
+        .saturating_add(purchased_position_amount(buyer));
+    let cumulative_after = cumulative_before.saturating_add(amount);
+    let required_tier = required_kyc_tier_for_deposit(cumulative_after, get_kyc_verified_deposit_threshold());
+    let current_tier = deposit_kyc_tier(&user.kyc_tier, cumulative_before);
+    if current_tier < required_tier {
+        return Err(format!(
+            "KYC tier {:?} is required to hold this position; current tier is {:?}",
+            required_tier, current_tier
+        ));
+    }
+    Ok(())
+}
+
+/// Offer `amount` of the caller's pool balance for `price` ckBTC, optionally to a single buyer
+#[update]
+pub fn offer_position_for_sale(amount: u64, price: u64, buyer: Option<Principal>) -> Result<PositionOffer, String> {
+    let caller = ic_cdk::caller();
+    crate::feature_flags::ensure_feature_enabled(crate::feature_flags::FEATURE_POSITION_MARKETPLACE)?;
+    crate::validation::guard_caller(&caller, "offer_position_for_sale")?;
+    if is_emergency_paused() {
+        return Err("Pool operations are currently paused".to_string());
+    }
+    if buyer == Some(caller) {
+        return Err("Cannot offer a position to yourself".to_string());
+    }
+
+    let balance = get_investor_balance_for_principal(caller)
+        .map_err(|_| "No investment balance found. Please deposit first".to_string())?;
+    let open_offers = get_all_position_offers()
+        .iter()
+        .filter(|offer| offer.seller == caller && offer.status == PositionOfferStatus::Open)
+        .count();
+    validate_position_offer(balance.balance, offered_position_amount(caller), open_offers, amount, price)?;
+
+    let offer = PositionOffer {
+        id: next_position_offer_id(),
+        seller: caller,
+        buyer,
+        amount,
+        price,
+        status: PositionOfferStatus::Open,
+        created_at: time(),
+        accepted_by: None,
+        settlement_block_index: None,
+        settled_at: None,
+        payment_created_at: None,
+    };
+    store_position_offer(offer.clone());
+
+    log_audit_action(
+        caller,
+        "POSITION_OFFER_CREATED".to_string(),
+        format!(
+            "Position offer #{}: {} satoshi of pool balance for {} satoshi{}",
+            offer.id, amount, price,
+            buyer.map(|b| format!(" to {}", b.to_text())).unwrap_or_default()
+        ),
+        true,
+    );
+
+    Ok(offer)
+}
+
+/// Withdraw an open offer (seller only)
+#[update]
+pub fn cancel_position_offer(offer_id: u64) -> Result<PositionOffer, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "cancel_position_offer")?;
+    let mut offer = get_position_offer(offer_id).ok_or("Position offer not found")?;
+    if offer.seller != caller {
+        return Err("Unauthorized: Only the seller can cancel this offer".to_string());
+    }
+    if offer.status != PositionOfferStatus::Open {
+        return Err(format!("Offer is not open. Current status: {:?}", offer.status));
+    }
+
+    offer.status = PositionOfferStatus::Cancelled;
+    store_position_offer(offer.clone());
+
+    log_audit_action(
+        caller,
+        "POSITION_OFFER_CANCELLED".to_string(),
+        format!("Position offer #{} cancelled", offer_id),
+        true,
+    );
+
+    Ok(offer)
+}
+
+/// Buy an open offer. The caller must have approved this canister to spend `price` ckBTC
+/// (plus the ledger fee); the payment goes straight to the seller.
+/// If the ledger call's outcome is unknown the offer stays claimed; the same buyer calling
+/// again resumes settlement with the original payment, which the ledger deduplicates.
+#[update]
+pub async fn accept_position_offer(offer_id: u64) -> Result<PositionOffer, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "accept_position_offer")?;
+    crate::feature_flags::ensure_feature_enabled(crate::feature_flags::FEATURE_POSITION_MARKETPLACE)?;
+    if is_emergency_paused() {
+        return Err("Pool operations are currently paused".to_string());
+    }
+    let _offer_guard = ResourceGuard::acquire(format!("position_offer:{}", offer_id))?;
+
+    let mut offer = get_position_offer(offer_id).ok_or("Position offer not found")?;
+    let resuming = offer.status == PositionOfferStatus::Settling
+        && offer.accepted_by == Some(caller)
+        && offer.payment_created_at.is_some();
+    if !resuming {
+        if offer.status != PositionOfferStatus::Open {
+            return Err(format!("Offer is not open. Current status: {:?}", offer.status));
+        }
+        if offer.seller == caller {
+            return Err("Cannot accept your own offer".to_string());
+        }
+        if offer.buyer.map_or(false, |buyer| buyer != caller) {
+            return Err("Unauthorized: This offer is reserved for another buyer".to_string());
+        }
+        ensure_eligible_position_buyer(caller, offer.amount)?;
+
+        let seller_balance = get_investor_balance_for_principal(offer.seller)?;
+        if seller_balance.balance < offer.amount {
+            return Err("Seller no longer holds the offered balance".to_string());
+        }
+
+        // Claim the offer before awaiting so a concurrent accept or cancel cannot race the payment
+        offer.status = PositionOfferStatus::Settling;
+        offer.accepted_by = Some(caller);
+        offer.payment_created_at = Some(time());
+        store_position_offer(offer.clone());
+    }
+
+    let transfer_args = TransferFromArgs {
+        spender_subaccount: None,
+        from: Account { owner: caller, subaccount: None },
+        to: Account { owner: offer.seller, subaccount: None },
+        amount: Nat::from(offer.price),
+        fee: None,
+        memo: Some(crate::helpers::ledger_memo(&format!("Agrilends position offer #{}", offer_id))),
+        created_at_time: offer.payment_created_at,
+    };
+    let ckbtc_ledger = crate::helpers::ckbtc_ledger_principal();
+    let call_result: Result<(Result<Nat, TransferFromError>,), _> = call_ckbtc_with_retry(
+        "icrc2_transfer_from",
+        || call(ckbtc_ledger, "icrc2_transfer_from", (transfer_args.clone(),)),
+    ).await;
+
+    let block_index: u64 = match call_result {
+        Ok((Ok(block_index),)) => block_index.0.try_into().unwrap_or(0u64),
+        // An earlier attempt already paid the seller
+        Ok((Err(TransferFromError::Duplicate { duplicate_of }),)) => duplicate_of.0.try_into().unwrap_or(0u64),
+        Ok((Err(TransferFromError::TooOld),)) if resuming => {
+            // Past the ledger's dedup window the first attempt's outcome cannot be confirmed
+            offer.status = PositionOfferStatus::Failed;
+            store_position_offer(offer);
+            log_audit_action(
+                caller,
+                "POSITION_OFFER_PAYMENT_UNCONFIRMED".to_string(),
+                format!("Payment for position offer #{} could not be confirmed within the ledger window", offer_id),
+                false,
             );
-            
-            Ok(format!("Emergency withdrawal successful. Block: {}", block_idx))
+            return Err("Payment could not be confirmed; the transfer needs manual review".to_string());
         }
         Ok((Err(transfer_error),)) => {
-            let error_msg = format!("Emergency withdrawal failed: {:?}", transfer_error);
+            // The ledger rejected the payment, so nothing moved and the offer can reopen
+            offer.status = PositionOfferStatus::Open;
+            offer.accepted_by = None;
+            offer.payment_created_at = None;
+            store_position_offer(offer);
             log_audit_action(
                 caller,
-                "EMERGENCY_WITHDRAWAL_FAILED".to_string(),
-                format!("Emergency withdrawal failed for investor {}: {}", investor, error_msg),
+                "POSITION_OFFER_PAYMENT_FAILED".to_string(),
+                format!("Payment for position offer #{} failed: {:?}", offer_id, transfer_error),
                 false,
             );
-            Err(error_msg)
+            return Err(format!("Payment failed: {:?}", transfer_error));
         }
-        Err(call_error) => {
-            let error_msg = format!("Network error during emergency withdrawal: {:?}", call_error);
+        Err(e) => {
+            // Outcome unknown: the payment may have landed, so keep the claim for a retry
             log_audit_action(
                 caller,
-                "EMERGENCY_WITHDRAWAL_NETWORK_ERROR".to_string(),
-                format!("Emergency withdrawal network error for investor {}: {}", investor, error_msg),
+                "POSITION_OFFER_PAYMENT_UNKNOWN".to_string(),
+                format!("Ledger call for position offer #{} failed with unknown outcome: {:?}", offer_id, e),
                 false,
             );
-            Err(error_msg)
+            return Err(format!(
+                "Ledger call failed: {:?}. Call accept_position_offer again to complete settlement",
+                e
+            ));
         }
+    };
+
+    // Seller leg: the payment is final, so any shortfall now is left for manual review
+    let now = time();
+    offer.settlement_block_index = Some(block_index);
+    offer.settled_at = Some(now);
+    let mut seller_balance = get_investor_balance_for_principal(offer.seller)?;
+    if seller_balance.balance < offer.amount {
+        offer.status = PositionOfferStatus::Failed;
+        store_position_offer(offer.clone());
+        log_audit_action(
+            offer.seller,
+            "POSITION_TRANSFER_SETTLEMENT_FAILED".to_string(),
+            format!(
+                "Position offer #{} paid at block {} but seller balance {} is below {}",
+                offer_id, block_index, seller_balance.balance, offer.amount
+            ),
+            false,
+        );
+        return Err("Payment received but the seller's balance changed; the transfer needs manual review".to_string());
+    }
+    seller_balance.balance -= offer.amount;
+    seller_balance.last_activity_at = now;
+    store_investor_balance(seller_balance)?;
+
+    // Buyer leg: accrued yield is already part of the balance, so it moves with the slice
+    let is_new_investor = !has_investor_deposited_before(caller);
+    let mut buyer_balance = get_investor_balance_by_principal(caller).unwrap_or(InvestorBalance {
+        investor: caller,
+        balance: 0,
+        deposits: Vec::new(),
+        withdrawals: Vec::new(),
+        total_deposited: 0,
+        total_withdrawn: 0,
+        first_deposit_at: now,
+        last_activity_at: now,
+        total_fees_paid: Some(0),
+    });
+    // Not a deposit: KYC tiers count it through `purchased_position_amount` once accepted
+    buyer_balance.balance += offer.amount;
+    buyer_balance.last_activity_at = now;
+    store_investor_balance(buyer_balance)?;
+
+    if is_new_investor {
+        let mut pool = get_liquidity_pool();
+        pool.total_investors += 1;
+        pool.updated_at = now;
+        store_liquidity_pool(pool)?;
     }
+
+    offer.status = PositionOfferStatus::Accepted;
+    store_position_offer(offer.clone());
+
+    log_audit_action(
+        offer.seller,
+        "POSITION_TRANSFER_SOLD".to_string(),
+        format!(
+            "Position offer #{}: {} satoshi of pool balance sold to {} for {} satoshi (block {})",
+            offer_id, offer.amount, caller.to_text(), offer.price, block_index
+        ),
+        true,
+    );
+    log_audit_action(
+        caller,
+        "POSITION_TRANSFER_BOUGHT".to_string(),
+        format!(
+            "Position offer #{}: {} satoshi of pool balance bought from {} for {} satoshi (block {})",
+            offer_id, offer.amount, offer.seller.to_text(), offer.price, block_index
+        ),
+        true,
+    );
+
+    Ok(offer)
 }
 
-/// Get investor's transaction history
+/// Open offers the caller can act on: public offers, offers reserved for them, and their own
 #[query]
-pub fn get_investor_transaction_history() -> Result<InvestorTransactionHistory, String> {
+pub fn get_open_position_offers() -> Vec<PositionOffer> {
     let caller = ic_cdk::caller();
-    
-    // Get investor balance
-    let investor_balance = get_investor_balance_for_principal(caller)
-        .map_err(|e| format!("No balance found for investor: {}", e))?;
-    
-    Ok(InvestorTransactionHistory {
-        investor: caller,
-        deposits: investor_balance.deposits,
-        withdrawals: investor_balance.withdrawals,
-        total_deposited: investor_balance.total_deposited,
-        total_withdrawn: investor_balance.total_withdrawn,
-        net_balance: investor_balance.balance,
-        first_activity: investor_balance.first_deposit_at,
-        last_activity: investor_balance.last_activity_at,
-    })
+    get_all_position_offers()
+        .into_iter()
+        .filter(|offer| offer.status == PositionOfferStatus::Open)
+        .filter(|offer| offer.seller == caller || offer.buyer.map_or(true, |buyer| buyer == caller))
+        .collect()
 }
 
-/// Get all disbursement records (admin only)
+// ========== RESERVE REQUIREMENT ==========
+
+/// No reserve tier may lock up more than half of the pool
+const MAX_RESERVE_SHARE_BPS: u64 = 5_000;
+
+/// Reserve for a pool of `total_liquidity`: `base_ratio_bps` up to the taper threshold and
+/// `taper_ratio_bps` above it, raised to `min_absolute` for small pools, capped at half the pool
+pub fn compute_required_reserve(
+    total_liquidity: u64,
+    base_ratio_bps: u64,
+    min_absolute: u64,
+    taper_threshold: Option<u64>,
+    taper_ratio_bps: Option<u64>,
+) -> u64 {
+    let total = total_liquidity as u128;
+    let tiered = match (taper_threshold, taper_ratio_bps) {
+        (Some(threshold), Some(taper_bps)) if total > threshold as u128 => {
+            let threshold = threshold as u128;
+            threshold * base_ratio_bps as u128 / 10_000 + (total - threshold) * taper_bps as u128 / 10_000
+        },
+        _ => total * base_ratio_bps as u128 / 10_000,
+    };
+    let cap = total * MAX_RESERVE_SHARE_BPS as u128 / 10_000;
+    tiered.max(min_absolute as u128).min(cap) as u64
+}
+
+/// Reserve the pool must keep in available liquidity after any withdrawal
+pub fn required_reserve(pool: &LiquidityPool) -> u64 {
+    let config = get_canister_config();
+    compute_required_reserve(
+        pool.total_liquidity,
+        config.pool_reserve_ratio_bps(),
+        config.reserve_min_absolute.unwrap_or(0),
+        config.reserve_taper_threshold,
+        config.reserve_taper_ratio_bps,
+    )
+}
+
+/// Current reserve requirement and withdrawal headroom
 #[query]
-pub fn get_all_disbursements() -> Result<Vec<DisbursementRecord>, String> {
-    let caller = ic_cdk::caller();
-    
-    if !is_admin(&caller) {
-        return Err("Unauthorized: Only admins can view all disbursements".to_string());
+pub fn get_reserve_requirement() -> ReserveInfo {
+    let config = get_canister_config();
+    let pool = get_liquidity_pool();
+    let required = required_reserve(&pool);
+
+    ReserveInfo {
+        total_liquidity: pool.total_liquidity,
+        available_liquidity: pool.available_liquidity,
+        required_reserve: required,
+        effective_ratio_bps: if pool.total_liquidity > 0 {
+            ((required as u128 * 10_000) / pool.total_liquidity as u128) as u64
+        } else {
+            0
+        },
+        headroom: pool.available_liquidity.saturating_sub(required),
+        base_ratio_bps: config.pool_reserve_ratio_bps(),
+        min_absolute: config.reserve_min_absolute.unwrap_or(0),
+        taper_threshold: config.reserve_taper_threshold,
+        taper_ratio_bps: config.reserve_taper_ratio_bps,
+        is_below_requirement: pool.available_liquidity < required,
     }
-    
-    Ok(get_all_disbursement_records())
 }
 
-/// Get disbursement records for a specific loan
-#[query]
-pub fn get_loan_disbursements(loan_id: u64) -> Result<Vec<DisbursementRecord>, String> {
-    let caller = ic_cdk::caller();
+const DEFAULT_PROMO_CAMPAIGN_CAP: u64 = 10_000_000; // 0.1 BTC per campaign
+
+/// Validate a promotional credit batch against what its campaign has already credited and
+/// return the batch total
+pub fn validate_promotional_credits(
+    credits: &[(Principal, u64)],
+    reason: &str,
+    already_credited: u64,
+    campaign_cap: u64,
+) -> Result<u64, String> {
+    if reason.trim().is_empty() {
+        return Err("A reason is required for promotional credits".to_string());
+    }
+    if credits.is_empty() {
+        return Err("No investors to credit".to_string());
+    }
     
-    // Allow admins and loan managers to view disbursements
-    if !is_admin(&caller) && !is_loan_manager_canister(&caller) {
-        return Err("Unauthorized: Only admins and loan managers can view disbursements".to_string());
+    let mut seen = std::collections::HashSet::new();
+    let mut total: u64 = 0;
+    for (investor, amount) in credits {
+        if *amount == 0 {
+            return Err(format!("Credit for {} must be greater than zero", investor.to_text()));
+        }
+        if !seen.insert(*investor) {
+            return Err(format!("Investor {} appears more than once", investor.to_text()));
+        }
+        total = total.checked_add(*amount).ok_or_else(|| "Credit total overflow".to_string())?;
     }
     
-    Ok(get_disbursement_records_by_loan(loan_id))
+    let campaign_total = already_credited.checked_add(total).ok_or_else(|| "Credit total overflow".to_string())?;
+    if campaign_total > campaign_cap {
+        return Err(format!(
+            "Campaign total {} ({} already credited) exceeds cap of {} satoshi",
+            campaign_total, already_credited, campaign_cap
+        ));
+    }
+    
+    Ok(total)
 }
 
-/// Force refresh pool statistics (admin only)
+/// Credit promotional ckBTC bonuses to investors, funded from the treasury (super admin only).
+/// `promo_campaign_cap` bounds the total credited under one `campaign_id` across all batches.
 #[update]
-pub fn refresh_pool_statistics() -> Result<String, String> {
+pub fn admin_credit_investors(campaign_id: String, credits: Vec<(Principal, u64)>, reason: String) -> Result<u64, String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "admin_credit_investors")?;
     
-    if !is_admin(&caller) {
-        return Err("Unauthorized: Only admins can refresh pool statistics".to_string());
+    if !crate::governance::is_super_admin(&caller) {
+        return Err("Unauthorized: Only super admins can credit investors".to_string());
+    }
+    let campaign_id = campaign_id.trim().to_string();
+    if campaign_id.is_empty() {
+        return Err("A campaign ID is required for promotional credits".to_string());
     }
     
-    // Recalculate pool statistics
-    let mut pool = get_liquidity_pool();
-    pool.updated_at = time();
+    let campaign_cap = crate::governance::get_protocol_parameter("promo_campaign_cap".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_PROMO_CAMPAIGN_CAP);
+    let already_credited = get_promo_credit_total(&campaign_id);
+    let total = validate_promotional_credits(&credits, &reason, already_credited, campaign_cap)?;
     
-    // Recalculate investor count
-    let all_balances = crate::storage::get_all_investor_balances();
-    let active_investors = all_balances.iter()
-        .filter(|balance| balance.balance > 0)
-        .count() as u64;
+    for (investor, _) in &credits {
+        match get_user_by_principal(investor) {
+            Some(user) if user.role == crate::user_management::Role::Investor => {},
+            _ => return Err(format!("Principal {} is not a registered investor", investor.to_text())),
+        }
+    }
     
-    pool.total_investors = active_investors;
+    // Build every updated record first so nothing fallible runs after the treasury is debited
+    let now = time();
+    let mut pool = get_liquidity_pool();
+    pool.total_liquidity = Satoshi(pool.total_liquidity).checked_add(Satoshi(total))?.0;
+    pool.available_liquidity = Satoshi(pool.available_liquidity).checked_add(Satoshi(total))?.0;
+    pool.updated_at = now;
+    
+    let mut updated_balances = Vec::with_capacity(credits.len());
+    for (investor, amount) in &credits {
+        if !has_investor_deposited_before(*investor) {
+            pool.total_investors += 1;
+        }
+        
+        let mut investor_balance = get_investor_balance_for_principal(*investor).unwrap_or(InvestorBalance {
+            investor: *investor,
+            balance: 0,
+            deposits: Vec::new(),
+            withdrawals: Vec::new(),
+            total_deposited: 0,
+            total_withdrawn: 0,
+            first_deposit_at: now,
+            last_activity_at: now,
+            total_fees_paid: Some(0),
+        });
+        
+        investor_balance.balance = Satoshi(investor_balance.balance).checked_add(Satoshi(*amount))?.0;
+        investor_balance.total_deposited = Satoshi(investor_balance.total_deposited).checked_add(Satoshi(*amount))?.0;
+        investor_balance.deposits.push(DepositRecord {
+            investor: *investor,
+            amount: *amount,
+            ckbtc_block_index: 0, // Internal credit, no ledger transfer
+            timestamp: now,
+            fee: Some(0),
+        });
+        investor_balance.last_activity_at = now;
+        updated_balances.push(investor_balance);
+    }
     
+    // Back the credits with treasury funds, then apply the prepared records
+    fund_promotional_credit(total, &format!("{}: {}", campaign_id, reason))?;
+    set_promo_credit_total(&campaign_id, already_credited + total);
+    for investor_balance in updated_balances {
+        store_investor_balance(investor_balance)?;
+    }
     store_liquidity_pool(pool)?;
     
+    // One correlated audit batch for the whole campaign
+    use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+    let correlation_id = format!("promo-credit-{}-{}", campaign_id, now);
+    for (investor, amount) in &credits {
+        log_audit_enhanced(
+            AuditCategory::LiquidityManagement,
+            "PROMOTIONAL_CREDIT".to_string(),
+            AuditEventLevel::Warning,
+            AuditDetails {
+                description: format!("Credited {} satoshi to {} under campaign {}. Reason: {}", amount, investor.to_text(), campaign_id, reason),
+                entity_type: Some("investor".to_string()),
+                entity_id: Some(investor.to_text()),
+                affected_principals: vec![*investor],
+                ..Default::default()
+            },
+            AuditResult {
+                success: true,
+                error_code: None,
+                error_message: None,
+                execution_time_ms: None,
+                gas_used: None,
+                cycles_consumed: None,
+                memory_used_bytes: None,
+                warning_flags: vec![],
+            },
+            Some(correlation_id.clone()),
+        );
+    }
+    
     log_audit_action(
         caller,
-        "POOL_STATISTICS_REFRESH".to_string(),
-        "Pool statistics refreshed manually".to_string(),
+        "PROMOTIONAL_CREDIT_BATCH".to_string(),
+        format!(
+            "Credited {} satoshi to {} investors under campaign {} ({} of {} cap used, correlation {}). Reason: {}",
+            total, credits.len(), campaign_id, already_credited + total, campaign_cap, correlation_id, reason
+        ),
         true,
     );
     
-    Ok("Pool statistics refreshed successfully".to_string())
+    Ok(total)
 }
 
-/// Set liquidity pool parameters (admin only)
-#[update]
-pub fn set_pool_parameters(
-    min_deposit_amount: Option<u64>,
-    max_utilization_rate: Option<u64>,
-    emergency_reserve_ratio: Option<u64>
-) -> Result<String, String> {
+/// Recompute the `asset` pool's aggregates from that asset's investor deposits and withdrawals
+/// (dust sweeps included), disbursements, each loan's repaid total and liquidation settlements,
+/// and compare them to the stored pool
+pub fn compute_pool_reconciliation(
+    asset: &Asset,
+    pool: &LiquidityPool,
+    balances: &[InvestorBalance],
+    disbursements: &[DisbursementRecord],
+    loans: &[Loan],
+    settlements: &[LiquidationSettlement],
+) -> PoolReconciliationReport {
+    // Only loans in `asset` move this pool; loans missing from `loans` are assumed to be ckBTC
+    let other_asset: std::collections::HashSet<u64> = loans.iter()
+        .filter(|loan| loan.asset() != *asset)
+        .map(|loan| loan.id)
+        .collect();
+    let in_pool = |loan_id: &u64| {
+        !other_asset.contains(loan_id)
+            && (*asset == Asset::CkBtc || loans.iter().any(|loan| loan.id == *loan_id))
+    };
+    let pool_loans = || loans.iter().filter(|loan| loan.asset() == *asset);
+    
+    let deposited: u64 = balances.iter()
+        .flat_map(|b| b.deposits.iter())
+        .fold(0u64, |acc, d| acc.saturating_add(d.amount));
+    let withdrawn: u64 = balances.iter()
+        .flat_map(|b| b.withdrawals.iter())
+        .fold(0u64, |acc, w| acc.saturating_add(w.amount));
+    
+    // A refinance payoff is borrowed by the new loan and repaid on the old one without cash moving
+    let refinanced: u64 = pool_loans()
+        .flat_map(|loan| loan.repayment_history.iter())
+        .filter(|payment| {
+            payment.transaction_id.as_deref()
+                .map_or(false, |id| id.starts_with(crate::loan_lifecycle::REFINANCE_TRANSACTION_PREFIX))
+        })
+        .fold(0u64, |acc, payment| acc.saturating_add(payment.amount));
+    let borrowed = disbursements.iter()
+        .filter(|d| in_pool(&d.loan_id))
+        .fold(0u64, |acc, d| acc.saturating_add(d.amount))
+        .saturating_add(refinanced);
+    
+    // Every payment path adds to the loan's total_repaid; off-chain repayments bring nothing
+    // into the pool, and liquidation proceeds return debt without touching the loan
+    let offchain: u64 = pool_loans()
+        .flat_map(|loan| loan.repayment_history.iter())
+        .filter(|payment| payment.source == Some(RepaymentSource::OffChain))
+        .fold(0u64, |acc, payment| acc.saturating_add(payment.amount));
+    let recovered = settlements.iter()
+        .filter(|settlement| in_pool(&settlement.loan_id))
+        .fold(0u64, |acc, settlement| acc.saturating_add(settlement.debt_repaid));
+    let repaid = pool_loans()
+        .fold(0u64, |acc, loan| acc.saturating_add(loan.total_repaid))
+        .saturating_sub(offchain)
+        .saturating_add(recovered);
+    
+    let field = |name: &str, stored: u64, computed: u64| PoolFieldReconciliation {
+        field: name.to_string(),
+        stored_value: stored,
+        computed_value: computed,
+        delta: (computed as i128 - stored as i128).clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+    };
+    
+    let fields = vec![
+        field("total_liquidity", pool.total_liquidity, deposited.saturating_sub(withdrawn)),
+        field("total_borrowed", pool.total_borrowed, borrowed),
+        field("total_repaid", pool.total_repaid, repaid),
+    ];
+    let is_consistent = fields.iter().all(|f| f.delta == 0);
+    
+    PoolReconciliationReport {
+        fields,
+        is_consistent,
+        investors_scanned: balances.len() as u64,
+        disbursements_scanned: disbursements.len() as u64,
+        repayments_scanned: pool_loans().map(|loan| loan.repayment_history.len() as u64).sum::<u64>()
+            + settlements.len() as u64,
+        generated_at: time(),
+    }
+}
+
+fn build_current_reconciliation(asset: &Asset) -> (LiquidityPool, PoolReconciliationReport) {
+    let pool = get_asset_pool(asset);
+    let report = compute_pool_reconciliation(
+        asset,
+        &pool,
+        &crate::storage::get_all_asset_investor_balances(asset),
+        &get_all_disbursement_records(),
+        &crate::storage::get_all_loans_data(),
+        &crate::liquidation::get_all_liquidation_settlements(),
+    );
+    (pool, report)
+}
+
+/// Report drift between the `asset` pool's stored aggregates and the underlying records
+/// (ckBTC when omitted; admin only, read-only)
+#[query]
+pub fn reconcile_pool_state(asset: Option<Asset>) -> Result<PoolReconciliationReport, String> {
     let caller = ic_cdk::caller();
     
     if !is_admin(&caller) {
-        return Err("Unauthorized: Only admins can set pool parameters".to_string());
+        return Err("Unauthorized: Only admins can reconcile pool state".to_string());
     }
     
-    // Store parameters in canister configuration
-    let mut config = get_canister_config();
+    Ok(build_current_reconciliation(&asset.unwrap_or_default()).1)
+}
+
+/// Overwrite the `asset` pool's drifted aggregates with the recomputed values (ckBTC when
+/// omitted; super admin only)
+#[update]
+pub fn repair_pool_state(asset: Option<Asset>) -> Result<PoolReconciliationReport, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "repair_pool_state")?;
     
-    if let Some(min_deposit) = min_deposit_amount {
-        if min_deposit < 10_000 { // Minimum 0.0001 BTC
-            return Err("Minimum deposit amount too small".to_string());
-        }
-        config.min_deposit_amount = min_deposit;
+    if !crate::governance::is_super_admin(&caller) {
+        return Err("Unauthorized: Only super admins can repair pool state".to_string());
     }
     
-    if let Some(max_util) = max_utilization_rate {
-        if max_util > 95 {
-            return Err("Maximum utilization rate cannot exceed 95%".to_string());
-        }
-        config.max_utilization_rate = max_util * 100; // Convert to basis points
+    let asset = asset.unwrap_or_default();
+    let (pool, report) = build_current_reconciliation(&asset);
+    if report.is_consistent {
+        return Ok(report);
     }
     
-    if let Some(reserve_ratio) = emergency_reserve_ratio {
-        if reserve_ratio < 5 || reserve_ratio > 50 {
-            return Err("Emergency reserve ratio must be between 5% and 50%".to_string());
+    let before_state = format!(
+        "total_liquidity={}, total_borrowed={}, total_repaid={}",
+        pool.total_liquidity, pool.total_borrowed, pool.total_repaid
+    );
+    
+    let mut repaired = pool;
+    for field in &report.fields {
+        match field.field.as_str() {
+            "total_liquidity" => repaired.total_liquidity = field.computed_value,
+            "total_borrowed" => repaired.total_borrowed = field.computed_value,
+            "total_repaid" => repaired.total_repaid = field.computed_value,
+            _ => {}
         }
-        config.emergency_reserve_ratio = reserve_ratio * 100; // Convert to basis points
     }
+    repaired.updated_at = time();
     
-    set_canister_config(config)?;
+    let after_state = format!(
+        "total_liquidity={}, total_borrowed={}, total_repaid={}",
+        repaired.total_liquidity, repaired.total_borrowed, repaired.total_repaid
+    );
     
-    log_audit_action(
-        caller,
-        "POOL_PARAMETERS_UPDATE".to_string(),
-        format!("Pool parameters updated: min_deposit={:?}, max_util={:?}, reserve_ratio={:?}", 
-                min_deposit_amount, max_utilization_rate, emergency_reserve_ratio),
-        true,
+    store_asset_pool(&asset, repaired)?;
+    
+    use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+    log_audit_enhanced(
+        AuditCategory::LiquidityManagement,
+        "POOL_STATE_REPAIRED".to_string(),
+        AuditEventLevel::Critical,
+        AuditDetails {
+            description: format!("{} pool aggregates overwritten from reconciliation by {}", asset.symbol(), caller.to_text()),
+            entity_type: Some("liquidity_pool".to_string()),
+            before_state: Some(before_state),
+            after_state: Some(after_state),
+            ..Default::default()
+        },
+        AuditResult {
+            success: true,
+            error_code: None,
+            error_message: None,
+            execution_time_ms: None,
+            gas_used: None,
+            cycles_consumed: None,
+            memory_used_bytes: None,
+            warning_flags: vec!["MANUAL_STATE_REPAIR".to_string()],
+        },
+        None,
     );
     
-    Ok("Pool parameters updated successfully".to_string())
+    Ok(report)
 }
 
 /// Get pool health metrics (admin only)
@@ -1690,6 +4430,7 @@ fn calculate_liquidity_trend(pool: &LiquidityPool) -> String {
 #[update]
 pub fn perform_pool_maintenance() -> Result<String, String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "perform_pool_maintenance")?;
     
     // Only allow system calls or admin calls
     if caller != canister_self() && !is_admin(&caller) {
@@ -1775,6 +4516,7 @@ pub fn get_my_processed_transactions() -> Vec<ProcessedTransaction> {
 #[update]
 pub fn emergency_halt_operations() -> Result<String, String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "emergency_halt_operations")?;
     
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can halt operations".to_string());
@@ -1798,6 +4540,12 @@ pub fn is_pool_paused() -> bool {
     is_emergency_paused()
 }
 
+/// Check if new loan originations are blocked, either by the origination pause or a full pause
+#[query]
+pub fn is_pool_origination_paused() -> bool {
+    origination_block_reason(is_emergency_paused(), is_origination_paused()).is_some()
+}
+
 /// Get pool configuration (admin only)
 #[query]
 pub fn get_pool_configuration() -> Result<PoolConfiguration, String> {
@@ -1815,10 +4563,11 @@ pub fn get_pool_configuration() -> Result<PoolConfiguration, String> {
         max_deposit_amount: u64::MAX, // No current limit
         min_withdrawal_amount: 10_000, // 0.0001 BTC
         max_utilization_rate: config.max_utilization_rate,
-        emergency_reserve_ratio: config.emergency_reserve_ratio,
-        base_apy: 300, // 3% base APY in basis points
+        emergency_reserve_ratio: config.pool_reserve_ratio_bps(),
+        base_apy: crate::storage::get_protocol_parameters().pool_apy().base_apy_bps,
         performance_fee: 100, // 1% performance fee in basis points
-        withdrawal_fee: 0, // No withdrawal fee
+        deposit_fee: config.deposit_fee_bps(),
+        withdrawal_fee: config.withdrawal_fee_bps(),
         is_paused: is_emergency_paused(),
         created_at: pool.created_at,
         updated_at: pool.updated_at,
@@ -1856,6 +4605,7 @@ mod tests {
         assert_eq!(stats.utilization_rate, 0);
         assert_eq!(stats.total_investors, 0);
         assert!(stats.apy >= 3); // Base APY should be at least 3%
+        assert!(stats.apy_bps.unwrap() >= 300);
     }
     
     #[test]
@@ -1884,6 +4634,7 @@ mod tests {
             apy: 0,
             created_at: 0,
             updated_at: 0,
+            reserved_liquidity: None,
         };
         
         let health_score = calculate_pool_health_score(&pool);
@@ -1906,13 +4657,14 @@ mod tests {
             apy: 0,
             created_at: 0,
             updated_at: 0,
+            reserved_liquidity: None,
         };
         
         let apy = calculate_pool_apy(&pool);
         
-        // Should be base APY (3%) + utilization bonus + performance bonus
-        assert!(apy >= 6); // 3% base + 3.5% utilization + 2% performance
-        assert!(apy <= 15); // Should not exceed maximum APY
+        // Should be base APY (3%) + utilization bonus + performance bonus, in basis points
+        assert!(apy >= 600); // 3% base + 3.5% utilization + 2% performance
+        assert!(apy <= 1_500); // Should not exceed maximum APY
     }
     
     #[test]
@@ -1930,6 +4682,7 @@ mod tests {
             apy: 0,
             created_at: 0,
             updated_at: 0,
+            reserved_liquidity: None,
         };
         
         // Simulate largest investor with 8 BTC deposit
@@ -1937,6 +4690,248 @@ mod tests {
         
         assert_eq!(concentration_risk, 80); // 80% concentration risk
     }
+    
+    #[test]
+    fn test_ckbtc_retry_classification() {
+        // Transient failures are retried
+        assert!(TransferError::TemporarilyUnavailable.is_retryable());
+        assert!(TransferFromError::TemporarilyUnavailable.is_retryable());
+        assert!(ApproveError::TemporarilyUnavailable.is_retryable());
+        assert!(RetrieveBtcError::TemporarilyUnavailable("busy".to_string()).is_retryable());
+        assert!(is_retryable_reject(&RejectionCode::SysTransient));
+        
+        // Funds and dedup errors are terminal
+        assert!(!TransferError::InsufficientFunds { balance: Nat::from(0u64) }.is_retryable());
+        assert!(!TransferFromError::Duplicate { duplicate_of: Nat::from(1u64) }.is_retryable());
+        assert!(!RetrieveBtcError::InsufficientFunds { balance: 0 }.is_retryable());
+        assert!(!is_retryable_reject(&RejectionCode::CanisterReject));
+    }
+    
+    #[test]
+    fn test_duplicate_after_unknown_outcome_is_success() {
+        // The first transfer times out with an unknown outcome but lands; the retry is deduplicated
+        let mut answers = vec![
+            Err((RejectionCode::SysUnknown, "timed out".to_string())),
+            Ok((Err(TransferError::Duplicate { duplicate_of: Nat::from(42u64) }),)),
+        ].into_iter();
+        let mut retries = Vec::new();
+        let (result, attempts) = tokio_test::block_on(retry_ledger_call(
+            3,
+            || std::future::ready(answers.next().unwrap()),
+            |attempt, _reason| {
+                retries.push(attempt);
+                std::future::ready(())
+            },
+        ));
+        assert_eq!(attempts, 2);
+        assert_eq!(retries, vec![1]);
+        assert!(matches!(result, Ok((Ok(block),)) if block == Nat::from(42u64)));
+        
+        // A duplicate on the first attempt was not caused by our retry and stays an error
+        let (result, attempts) = tokio_test::block_on(retry_ledger_call(
+            3,
+            || std::future::ready(Ok::<_, (RejectionCode, String)>((Err::<Nat, _>(TransferError::Duplicate {
+                duplicate_of: Nat::from(7u64),
+            }),))),
+            |_, _| std::future::ready(()),
+        ));
+        assert_eq!(attempts, 1);
+        assert!(matches!(result, Ok((Err(TransferError::Duplicate { .. }),))));
+    }
+    
+    #[test]
+    fn test_retry_backoff_is_exponential_and_bounded() {
+        assert_eq!(retry_backoff_rounds(1, 1), 1);
+        assert_eq!(retry_backoff_rounds(1, 2), 2);
+        assert_eq!(retry_backoff_rounds(1, 3), 4);
+        assert_eq!(retry_backoff_rounds(2, 3), 8);
+        assert_eq!(retry_backoff_rounds(0, 5), 0);
+        assert_eq!(retry_backoff_rounds(8, 10), MAX_CKBTC_BACKOFF_ROUNDS);
+    }
+    
+    #[test]
+    fn test_pool_reconciliation_detects_drift() {
+        let investor = Principal::from_slice(&[7u8; 29]);
+        let balance = InvestorBalance {
+            investor,
+            balance: 700_000,
+            total_deposited: 1_000_000,
+            total_withdrawn: 300_000,
+            deposits: vec![
+                DepositRecord { investor, amount: 600_000, ckbtc_block_index: 1, timestamp: 0, fee: None },
+                DepositRecord { investor, amount: 400_000, ckbtc_block_index: 2, timestamp: 0, fee: None },
+            ],
+            withdrawals: vec![
+                WithdrawalRecord { investor, amount: 300_000, ckbtc_block_index: 3, timestamp: 0, fee: None, lots: None },
+            ],
+            first_deposit_at: 0,
+            last_activity_at: 0,
+            total_fees_paid: None,
+        };
+        let disbursement = DisbursementRecord {
+            loan_id: 1,
+            borrower_btc_address: "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_string(),
+            amount: 500_000,
+            ckbtc_block_index: 4,
+            disbursed_at: 0,
+            disbursed_by: investor,
+            network_fee: None,
+            fee_bearer: None,
+        };
+        // Three repayments on the same loan, one of them recorded off-chain
+        let payment = |amount: u64, source: Option<RepaymentSource>| Payment {
+            amount,
+            timestamp: 0,
+            payment_type: PaymentType::Mixed,
+            transaction_id: None,
+            allocation: None,
+            source,
+        };
+        let loan = Loan {
+            id: 1,
+            borrower: investor,
+            nft_id: 1,
+            collateral_value_btc: 1_000_000,
+            amount_requested: 500_000,
+            amount_approved: 500_000,
+            apr: 10,
+            status: LoanStatus::Active,
+            created_at: 0,
+            due_date: None,
+            total_repaid: 260_000,
+            repayment_history: vec![
+                payment(120_000, Some(RepaymentSource::CkBtc)),
+                payment(80_000, Some(RepaymentSource::CkBtc)),
+                payment(60_000, Some(RepaymentSource::OffChain)),
+            ],
+            last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
+        };
+        let loans = vec![loan];
+        
+        let mut pool = LiquidityPool {
+            total_liquidity: 700_000,
+            available_liquidity: 400_000,
+            total_borrowed: 500_000,
+            total_repaid: 200_000,
+            utilization_rate: 0,
+            total_investors: 1,
+            apy: 0,
+            created_at: 0,
+            updated_at: 0,
+            reserved_liquidity: None,
+        };
+        
+        let report = compute_pool_reconciliation(&Asset::CkBtc, &pool, &[balance.clone()], &[disbursement.clone()], &loans, &[]);
+        assert!(report.is_consistent);
+        assert!(report.fields.iter().all(|f| f.delta == 0));
+        assert_eq!(report.repayments_scanned, 3);
+        
+        // The same loan made in ICP reconciles against the ICP pool and drops out of ckBTC
+        let icp_loans = vec![Loan { asset: Some(Asset::Icp), ..loans[0].clone() }];
+        let icp_pool = LiquidityPool { total_liquidity: 0, available_liquidity: 0, ..pool.clone() };
+        let report = compute_pool_reconciliation(&Asset::Icp, &icp_pool, &[], &[disbursement.clone()], &icp_loans, &[]);
+        assert!(report.is_consistent);
+        let report = compute_pool_reconciliation(&Asset::CkBtc, &pool, &[balance.clone()], &[disbursement.clone()], &icp_loans, &[]);
+        let borrowed = report.fields.iter().find(|f| f.field == "total_borrowed").unwrap();
+        assert_eq!(borrowed.computed_value, 0);
+        
+        // Seed a deliberate drift from a half-applied disbursement and a lost repayment
+        pool.total_borrowed = 750_000;
+        pool.total_repaid = 150_000;
+        let report = compute_pool_reconciliation(&Asset::CkBtc, &pool, &[balance], &[disbursement], &loans, &[]);
+        assert!(!report.is_consistent);
+        
+        let borrowed = report.fields.iter().find(|f| f.field == "total_borrowed").unwrap();
+        assert_eq!(borrowed.computed_value, 500_000);
+        assert_eq!(borrowed.delta, -250_000);
+        let repaid = report.fields.iter().find(|f| f.field == "total_repaid").unwrap();
+        assert_eq!(repaid.delta, 50_000);
+        let liquidity = report.fields.iter().find(|f| f.field == "total_liquidity").unwrap();
+        assert_eq!(liquidity.delta, 0);
+    }
+    
+    #[test]
+    fn test_pool_reconciliation_counts_refinance_dust_and_liquidation() {
+        let investor = Principal::from_slice(&[8u8; 29]);
+        let balance = InvestorBalance {
+            investor,
+            balance: 990_000,
+            total_deposited: 1_000_000,
+            total_withdrawn: 10_000,
+            deposits: vec![DepositRecord { investor, amount: 1_000_000, ckbtc_block_index: 1, timestamp: 0, fee: None }],
+            // A dust sweep, recorded without a ledger block
+            withdrawals: vec![WithdrawalRecord { investor, amount: 10_000, ckbtc_block_index: 0, timestamp: 0, fee: None, lots: None }],
+            first_deposit_at: 0,
+            last_activity_at: 0,
+            total_fees_paid: None,
+        };
+        let disbursement = DisbursementRecord {
+            loan_id: 1,
+            borrower_btc_address: "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_string(),
+            amount: 400_000,
+            ckbtc_block_index: 2,
+            disbursed_at: 0,
+            disbursed_by: investor,
+            network_fee: None,
+            fee_bearer: None,
+        };
+        // Loan 1 was paid down by 100_000 and then refinanced into loan 2 for 330_000
+        let old_loan = Loan {
+            id: 1,
+            borrower: investor,
+            nft_id: 1,
+            collateral_value_btc: 1_000_000,
+            amount_requested: 400_000,
+            amount_approved: 400_000,
+            apr: 10,
+            status: LoanStatus::Repaid,
+            created_at: 0,
+            due_date: None,
+            total_repaid: 430_000,
+            repayment_history: vec![
+                Payment { amount: 100_000, timestamp: 0, payment_type: PaymentType::Mixed, transaction_id: Some("7".to_string()), allocation: None, source: Some(RepaymentSource::CkBtc) },
+                Payment { amount: 330_000, timestamp: 1, payment_type: PaymentType::Mixed, transaction_id: Some("refinance:2".to_string()), allocation: None, source: None },
+            ],
+            last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
+        };
+        let new_loan = Loan { id: 2, total_repaid: 0, repayment_history: vec![], status: LoanStatus::Liquidating, ..old_loan.clone() };
+        // Loan 2 was liquidated and 250_000 of its debt recovered
+        let settlement = LiquidationSettlement {
+            loan_id: 2,
+            auction_id: None,
+            proceeds: 260_000,
+            outstanding_debt: 340_000,
+            debt_repaid: 250_000,
+            penalty_bps: 0,
+            penalty_paid: 0,
+            residual_returned: 0,
+            shortfall: 90_000,
+            principal_loss: 80_000,
+            settled_at: 2,
+            proceeds_block_index: Some(11),
+        };
+        let pool = LiquidityPool {
+            total_liquidity: 990_000, // 10_000 of dust swept
+            available_liquidity: 0,
+            total_borrowed: 730_000,
+            total_repaid: 680_000,
+            utilization_rate: 0,
+            total_investors: 1,
+            apy: 0,
+            created_at: 0,
+            updated_at: 0,
+            reserved_liquidity: None,
+        };
+        
+        let report = compute_pool_reconciliation(&Asset::CkBtc, &pool, &[balance], &[disbursement], &[old_loan, new_loan], &[settlement]);
+        assert!(report.is_consistent, "{:?}", report.fields);
+    }
 }
 
 // Integration tests for liquidity management workflows
@@ -2012,4 +5007,40 @@ mod security_tests {
         // Test boundary conditions
         // Test overflow protection
     }
+
+    #[test]
+    fn test_pool_fee_calculation() {
+        // Zero-fee config leaves the amount untouched
+        assert_eq!(calculate_pool_fee(1_000_000, 0), 0);
+
+        // 50 bps on 1_000_000 satoshi
+        assert_eq!(calculate_pool_fee(1_000_000, 50), 5_000);
+
+        // Capped rate never exceeds 2%
+        assert_eq!(calculate_pool_fee(1_000_000, MAX_POOL_FEE_BPS), 20_000);
+
+        // Large amounts do not overflow
+        assert_eq!(calculate_pool_fee(u64::MAX, 100), u64::MAX / 100);
+    }
+
+    #[test]
+    fn test_promotional_credit_validation() {
+        let alice = Principal::from_slice(&[1u8; 29]);
+        let bob = Principal::from_slice(&[2u8; 29]);
+        
+        assert_eq!(validate_promotional_credits(&[(alice, 1_000), (bob, 2_000)], "Launch bonus", 0, 5_000), Ok(3_000));
+        
+        // Reason and recipients are required
+        assert!(validate_promotional_credits(&[(alice, 1_000)], "  ", 0, 5_000).is_err());
+        assert!(validate_promotional_credits(&[], "Launch bonus", 0, 5_000).is_err());
+        
+        // Zero amounts, duplicates and over-cap batches are rejected
+        assert!(validate_promotional_credits(&[(alice, 0)], "Launch bonus", 0, 5_000).is_err());
+        assert!(validate_promotional_credits(&[(alice, 1_000), (alice, 1_000)], "Launch bonus", 0, 5_000).is_err());
+        assert!(validate_promotional_credits(&[(alice, 3_000), (bob, 3_000)], "Launch bonus", 0, 5_000).is_err());
+        
+        // The cap covers the whole campaign, not each batch
+        assert_eq!(validate_promotional_credits(&[(alice, 1_000)], "Launch bonus", 4_000, 5_000), Ok(1_000));
+        assert!(validate_promotional_credits(&[(alice, 2_000)], "Launch bonus", 4_000, 5_000).is_err());
+    }
 }
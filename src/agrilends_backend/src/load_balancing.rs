@@ -407,6 +407,7 @@ pub fn can_execute_request(shard_id: u32) -> bool {
 /// Record request result and update circuit breaker state
 #[update]
 pub fn record_request_result(shard_id: u32, success: bool, response_time_ms: u64, error_type: Option<String>) -> Result<(), String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "record_request_result")?;
     let current_time = time();
     
     CIRCUIT_BREAKERS.with(|breakers| {
@@ -670,6 +671,7 @@ fn update_shard_response_time(shard_id: u32, response_time_ms: u64) {
 #[update]
 pub fn add_shard_to_balancer(shard_info: ShardInfo, weight: f64, max_connections: u64) -> Result<(), String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "add_shard_to_balancer")?;
     if !is_admin(&caller) {
         return Err("Only admin can add shards to load balancer".to_string());
     }
@@ -716,6 +718,7 @@ pub fn add_shard_to_balancer(shard_info: ShardInfo, weight: f64, max_connections
 #[update]
 pub fn remove_shard_from_balancer(shard_id: u32) -> Result<(), String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "remove_shard_from_balancer")?;
     if !is_admin(&caller) {
         return Err("Only admin can remove shards from load balancer".to_string());
     }
@@ -745,6 +748,7 @@ pub fn remove_shard_from_balancer(shard_id: u32) -> Result<(), String> {
 #[update]
 pub fn update_load_balancing_algorithm(algorithm: LoadBalancingAlgorithm) -> Result<(), String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "update_load_balancing_algorithm")?;
     if !is_admin(&caller) {
         return Err("Only admin can update load balancing algorithm".to_string());
     }
@@ -153,6 +153,7 @@ fn update_performance_metrics(operation_type: &str, duration_ms: u64) {
 /// Store a new loan in this shard
 #[update]
 pub fn store_loan(loan: Loan) -> Result<(), String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "store_loan")?;
     let start_time = time();
     
     // Access control
@@ -222,6 +223,7 @@ pub fn get_loan(loan_id: u64) -> Result<Loan, String> {
 /// Update an existing loan
 #[update]
 pub fn update_loan(loan_id: u64, updated_loan: Loan) -> Result<(), String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "update_loan")?;
     let start_time = time();
     
     // Access control
@@ -411,6 +413,7 @@ pub fn get_shard_info() -> Result<(DataCanisterInfo, ShardStatistics), String> {
 /// Set shard to read-only mode
 #[update]
 pub fn set_read_only(read_only: bool) -> Result<(), String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "set_read_only")?;
     // Access control
     is_authorized_caller()?;
     
@@ -425,6 +428,7 @@ pub fn set_read_only(read_only: bool) -> Result<(), String> {
 /// Add authorized caller
 #[update]
 pub fn add_authorized_caller(caller_principal: Principal) -> Result<(), String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "add_authorized_caller")?;
     // Access control - only existing authorized callers can add new ones
     is_authorized_caller()?;
     
@@ -441,6 +445,7 @@ pub fn add_authorized_caller(caller_principal: Principal) -> Result<(), String>
 /// Remove authorized caller
 #[update]
 pub fn remove_authorized_caller(caller_principal: Principal) -> Result<(), String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "remove_authorized_caller")?;
     // Access control
     is_authorized_caller()?;
     
@@ -489,6 +494,7 @@ pub fn export_loans(loan_ids: Vec<u64>) -> Result<Vec<Loan>, String> {
 /// Import loans from migration (authorized callers only)
 #[update]
 pub fn import_loans(loans: Vec<Loan>) -> Result<u64, String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "import_loans")?;
     let start_time = time();
     
     // Access control
@@ -548,6 +554,7 @@ pub fn import_loans(loans: Vec<Loan>) -> Result<u64, String> {
 /// Delete loans (for cleanup after migration)
 #[update]
 pub fn delete_loans(loan_ids: Vec<u64>) -> Result<u64, String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "delete_loans")?;
     let start_time = time();
     
     // Access control
@@ -3,12 +3,14 @@ use ic_cdk_macros::{query, update};
 use crate::types::*;
 use crate::storage::{
     get_loan, store_loan, get_next_loan_id, get_loans_by_borrower,
-    get_all_loans_data, get_nft_data, lock_nft_for_loan, get_stored_commodity_price,
+    get_all_loans_data, get_nft_data, lock_nft_for_loan,
     get_protocol_parameters, liquidate_collateral, unlock_nft, store_repayment_record,
-    release_collateral_nft
+    release_collateral_nft, get_next_restructure_request_id, store_restructure_request,
+    get_restructure_request, get_all_restructure_requests, store_application_review,
+    get_application_review
 };
-use crate::user_management::{get_user, Role, UserResult};
-use crate::helpers::{get_user_btc_address, log_audit_action, get_canister_config};
+use crate::helpers::{get_user_btc_address, log_audit_action, get_canister_config, get_commodity_ltv_ratio, is_admin, get_commodity_exposures, commodity_share_after_bps, exposure_cap_applies, get_region_from_metadata, get_regional_outstanding, build_regional_exposure};
+use crate::loan_repayment::calculate_total_debt_with_interest;
 // Production integrations  
 use crate::oracle::{is_price_stale};
 use crate::ckbtc_integration::{process_ckbtc_repayment};
@@ -21,18 +23,23 @@ use std::collections::HashMap;
 pub async fn submit_loan_application(
     nft_id: u64,
     amount_requested: u64,
+    asset: Option<Asset>,
 ) -> Result<Loan, String> {
     let caller = ic_cdk::caller();
+    let asset = asset.unwrap_or_default();
+    if asset == Asset::Icp {
+        crate::feature_flags::ensure_feature_enabled(crate::feature_flags::FEATURE_ICP_POOL)?;
+    }
+    crate::validation::guard_caller(&caller, "submit_loan_application")?;
+    crate::helpers::ensure_operation_available("submit_loan_application")?;
+    crate::liquidity_management::ensure_origination_allowed()?;
     
     // 1. Verifikasi pengguna terdaftar sebagai petani
-    match get_user() {
-        UserResult::Ok(user) => {
-            if user.role != Role::Farmer {
-                return Err("Only farmers can apply for loans".to_string());
-            }
-        }
-        UserResult::Err(e) => return Err(format!("User verification failed: {}", e)),
-    }
+    crate::validation::require_role(&caller, crate::validation::CallerRole::Farmer)?;
+    crate::validation::require_positive(amount_requested, "Loan amount")?;
+
+    // 1a. Batasi jumlah pinjaman aktif per peminjam
+    ensure_loan_capacity(&borrower_loan_capacity(caller))?;
 
     // 2. Verifikasi kepemilikan NFT
     let nft_data = get_nft_data(nft_id).ok_or_else(|| "NFT not found".to_string())?;
@@ -49,8 +56,15 @@ pub async fn submit_loan_application(
     let valuation_idr = extract_valuation_from_metadata(&nft_data.metadata)?;
     let commodity_info = extract_commodity_info_from_metadata(&nft_data.metadata)?;
 
+    // 4a. Tolak resi gudang yang kedaluwarsa dan komoditas yang sedang dijeda oleh governance
+    crate::helpers::ensure_receipt_not_expired(&nft_data.metadata, time())?;
+    crate::helpers::ensure_commodity_not_paused(&commodity_info.commodity_type)?;
+
+    // 4b. Tolak agunan di bawah nilai minimum
+    crate::helpers::ensure_min_collateral_value(valuation_idr).map_err(|e| e.to_string())?;
+
     // 5. Ambil harga komoditas real dari Oracle
-    let commodity_price_data = get_stored_commodity_price(&commodity_info.commodity_type)
+    let commodity_price_data = crate::oracle::get_effective_commodity_price(&commodity_info.commodity_type)
         .ok_or_else(|| "Commodity price not available. Please contact admin to update price feeds.".to_string())?;
     
     // Check if price is stale (older than 24 hours)
@@ -58,18 +72,26 @@ pub async fn submit_loan_application(
         return Err("Commodity price data is stale. Please wait for price update.".to_string());
     }
 
-    // 6. Hitung nilai agunan dalam ckBTC
+    // 6. Hitung nilai agunan dalam aset pinjaman (ckBTC atau ICP)
+    let btc_rate = crate::oracle::get_asset_idr_rate(&asset)?;
     let collateral_value_btc = calculate_collateral_value_btc(
         valuation_idr,
         commodity_info.quantity,
         &commodity_price_data,
-    )?;
+        &btc_rate,
+    )?.0;
 
     // 7. Ambil parameter protokol
     let params = get_protocol_parameters();
     
-    // 8. Hitung jumlah yang disetujui (LTV ratio)
-    let amount_approved = (collateral_value_btc * params.loan_to_value_ratio) / 100;
+    // 8. Hitung jumlah yang disetujui (LTV ratio per komoditas, fallback ke LTV global),
+    // disesuaikan dengan reputasi pembayaran peminjam
+    let (reputation_apr_adjustment, reputation_ltv_adjustment) = crate::borrower_reputation::reputation_terms_for(caller);
+    let ltv_ratio = crate::borrower_reputation::adjusted_ltv(
+        get_commodity_ltv_ratio(&commodity_info.commodity_type),
+        reputation_ltv_adjustment,
+    );
+    let amount_approved = (collateral_value_btc * ltv_ratio) / 100;
 
     // 9. Validasi jumlah yang diminta
     if amount_requested > amount_approved {
@@ -79,8 +101,54 @@ pub async fn submit_loan_application(
         ));
     }
 
-    // 10. Buat loan baru
+    // 9b. Batasi konsentrasi agunan per komoditas (tidak berlaku saat buku masih kosong)
+    let commodity_exposures = get_commodity_exposures(&asset);
+    let commodity_share_bps = commodity_share_after_bps(
+        &commodity_exposures,
+        &commodity_info.commodity_type,
+        collateral_value_btc,
+    );
+    if exposure_cap_applies(&commodity_exposures) && commodity_share_bps > params.max_commodity_exposure_bps() {
+        return Err(format!(
+            "Loan would raise {} exposure to {} bps of outstanding collateral, above the {} bps limit",
+            commodity_info.commodity_type, commodity_share_bps, params.max_commodity_exposure_bps()
+        ));
+    }
+
+    // 9c. Batasi konsentrasi pinjaman per wilayah (tidak berlaku saat buku masih kosong)
+    let region = get_region_from_metadata(&nft_data.metadata);
+    if let Some(region) = &region {
+        let region_cap_bps = params.region_exposure_caps().get(region).copied()
+            .ok_or_else(|| format!("Region '{}' is not on the region allowlist", region))?;
+        let regional_outstanding = get_regional_outstanding(&asset);
+        let region_share_bps = commodity_share_after_bps(&regional_outstanding, region, amount_requested);
+        if exposure_cap_applies(&regional_outstanding) && region_share_bps > region_cap_bps {
+            return Err(format!(
+                "Loan would raise region {} exposure to {} bps of outstanding principal, above the {} bps limit",
+                region, region_share_bps, region_cap_bps
+            ));
+        }
+    }
+
+    // 10. Buat loan baru, dengan suku bunga promo bila ada kampanye yang berlaku
+    // (anggaran kampanye dalam satoshi, jadi promo hanya untuk pinjaman ckBTC;
+    // bila fitur promo dimatikan, pinjaman memakai suku bunga standar)
     let loan_id = get_next_loan_id();
+    let promo_eligible = asset == Asset::CkBtc
+        && crate::feature_flags::is_feature_enabled(crate::feature_flags::FEATURE_PROMO_CAMPAIGNS);
+    let promo = if promo_eligible {
+        crate::promo_campaigns::claim_promo_rate(caller, &commodity_info.commodity_type, loan_id, amount_approved, time())
+    } else {
+        None
+    };
+    let standard_apr = crate::borrower_reputation::adjusted_apr(params.base_apr, reputation_apr_adjustment);
+    let (apr, rate_type) = match promo {
+        Some(campaign) => (
+            crate::promo_campaigns::promo_apr(&campaign),
+            RateType::Promotional(PromoRateTerms { campaign_id: campaign.id, standard_apr }),
+        ),
+        None => (standard_apr, RateType::Fixed),
+    };
 
     let loan = Loan {
         id: loan_id,
@@ -89,17 +157,36 @@ pub async fn submit_loan_application(
         collateral_value_btc,
         amount_requested,
         amount_approved,
-        apr: params.base_apr,
+        apr,
         status: LoanStatus::PendingApproval,
         created_at: time(),
         due_date: None,
         total_repaid: 0,
         repayment_history: Vec::new(),
         last_payment_date: None,
+        region,
+        rate_type: Some(rate_type),
+        asset: Some(asset),
     };
 
-    // 11. Simpan loan
-    store_loan(loan.clone())?;
+    // 11. Simpan loan dan masukkan ke antrian underwriting
+    let validity_days = crate::governance::get_protocol_parameter("application_validity_days".to_string())
+        .map(|p| p.current_value)
+        .unwrap_or(DEFAULT_APPLICATION_VALIDITY_DAYS);
+    if let Err(e) = store_loan(loan.clone()) {
+        crate::promo_campaigns::release_promo_usage(&loan);
+        return Err(e);
+    }
+    store_application_review(LoanApplicationReview {
+        loan_id,
+        borrower: caller,
+        state: UnderwritingState::Submitted,
+        underwriter: None,
+        thread: Vec::new(),
+        submitted_at: loan.created_at,
+        updated_at: loan.created_at,
+        expires_at: Some(loan.created_at + validity_days * 24 * 60 * 60 * 1_000_000_000),
+    });
 
     // 12. Send notification to borrower about loan application
     let mut additional_data = HashMap::new();
@@ -117,7 +204,51 @@ pub async fn submit_loan_application(
     log_audit_action(
         caller,
         "LOAN_APPLICATION_SUBMITTED".to_string(),
-        format!("Loan #{} submitted for NFT #{} with amount {}", loan_id, nft_id, amount_requested),
+        format!("Loan #{} submitted for NFT #{} with amount {} at {}%", loan_id, nft_id, amount_requested, loan.apr),
+        true,
+    );
+
+    Ok(loan)
+}
+
+// Switch a pending loan offer to a variable rate that resets every `reset_interval_days`
+#[update]
+pub fn choose_variable_rate(loan_id: u64, reset_interval_days: u64) -> Result<Loan, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "choose_variable_rate")?;
+    let mut loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+
+    if loan.borrower != caller {
+        return Err("Unauthorized: You are not the borrower of this loan".to_string());
+    }
+    if loan.status != LoanStatus::PendingApproval {
+        return Err("Rate type can only be changed before the loan offer is accepted".to_string());
+    }
+    if matches!(loan.rate_type, Some(RateType::Promotional(_))) {
+        return Err("Loans priced under a promo campaign keep their fixed promo rate".to_string());
+    }
+
+    let params = get_protocol_parameters();
+    if reset_interval_days < 30 || reset_interval_days >= params.max_loan_duration_days {
+        return Err(format!(
+            "Reset interval must be at least 30 days and shorter than the {} day loan term",
+            params.max_loan_duration_days
+        ));
+    }
+
+    let reset_interval_seconds = reset_interval_days * 24 * 60 * 60;
+    loan.rate_type = Some(RateType::Variable(VariableRateTerms {
+        initial_apr: loan.apr,
+        reset_interval_seconds,
+        next_reset_at: loan.created_at + reset_interval_seconds * 1_000_000_000,
+        resets: Vec::new(),
+    }));
+    store_loan(loan.clone())?;
+
+    log_audit_action(
+        caller,
+        "LOAN_VARIABLE_RATE_SELECTED".to_string(),
+        format!("Loan #{} switched to variable rate, resetting every {} days", loan_id, reset_interval_days),
         true,
     );
 
@@ -128,6 +259,8 @@ pub async fn submit_loan_application(
 #[update]
 pub async fn accept_loan_offer(loan_id: u64) -> Result<String, String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "accept_loan_offer")?;
+    crate::helpers::ensure_operation_available("accept_loan_offer")?;
 
     // 1. Ambil data pinjaman
     let mut loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
@@ -142,6 +275,21 @@ pub async fn accept_loan_offer(loan_id: u64) -> Result<String, String> {
         return Err("Loan is not in pending approval status".to_string());
     }
 
+    // 3b. Aplikasi harus lolos underwriting terlebih dahulu
+    if let Some(review) = get_application_review(loan_id) {
+        if review.state != UnderwritingState::Approved {
+            return Err(format!("Loan application is not approved by underwriting (state: {:?})", review.state));
+        }
+    }
+
+    // 3c. Resi gudang bisa kedaluwarsa selama aplikasi menunggu persetujuan
+    let nft_data = get_nft_data(loan.nft_id).ok_or_else(|| "NFT not found".to_string())?;
+    crate::helpers::ensure_receipt_not_expired(&nft_data.metadata, time())?;
+
+    // 3d. Komoditas bisa dijeda oleh governance setelah aplikasi diajukan
+    let commodity_info = extract_commodity_info_from_metadata(&nft_data.metadata)?;
+    crate::helpers::ensure_commodity_not_paused(&commodity_info.commodity_type)?;
+
     // 4. Lock NFT sebagai escrow
     match lock_nft_for_loan(loan.nft_id, loan_id) {
         Ok(_) => {
@@ -157,11 +305,15 @@ pub async fn accept_loan_offer(loan_id: u64) -> Result<String, String> {
     );
 
     // 6. Coba cairkan dana via liquidity management
-    // First, get the borrower's Bitcoin address (this would need to be stored in user profile)
-    let borrower_btc_address = get_user_btc_address(&caller)
-        .ok_or("Borrower Bitcoin address not found. Please update your profile.".to_string())?;
+    // First, get the borrower's Bitcoin address (this would need to be stored in user profile);
+    // ICP loans are paid to the borrower's principal and need no address
+    let borrower_btc_address = match loan.asset() {
+        Asset::CkBtc => get_user_btc_address(&caller)
+            .ok_or("Borrower Bitcoin address not found. Please update your profile.".to_string())?,
+        Asset::Icp => String::new(),
+    };
     
-    match crate::liquidity_management::disburse_loan(loan_id, borrower_btc_address, loan.amount_approved).await {
+    match crate::liquidity_management::disburse_loan(loan_id, borrower_btc_address, Satoshi(loan.amount_approved), Some(loan.asset())).await {
         Ok(_) => {
             loan.status = LoanStatus::Active;
             
@@ -231,6 +383,65 @@ pub fn get_loan_status(loan_id: u64) -> Option<Loan> {
     get_loan(loan_id)
 }
 
+pub fn collateral_nft_summary(nft: &RWANFTData) -> CollateralNftSummary {
+    let (legal_doc_hash, valuation_idr, asset_description) = crate::helpers::extract_metadata_values(&nft.metadata);
+    CollateralNftSummary {
+        token_id: nft.token_id,
+        owner: nft.owner,
+        is_locked: nft.is_locked,
+        locked_for_loan: nft.loan_id,
+        valuation_idr,
+        asset_description,
+        legal_doc_hash,
+    }
+}
+
+// Loan with its collateral, disbursements, repayments, health, schedule and liquidation
+// record in one call (borrower or admin)
+#[query]
+pub fn get_loan_full_details(loan_id: u64) -> Result<LoanFullDetails, String> {
+    let caller = ic_cdk::caller();
+    let loan = get_loan(loan_id).ok_or_else(|| format!("Loan #{} not found", loan_id))?;
+
+    if loan.borrower != caller && !is_admin(&caller) {
+        return Err("Unauthorized: Only borrower or admin can view loan details".to_string());
+    }
+
+    let has_principal = loan.amount_approved > 0;
+    let health_ratio = if has_principal {
+        crate::helpers::calculate_loan_health_ratio(&loan).ok()
+    } else {
+        None
+    };
+    let repayment_summary = if has_principal {
+        calculate_loan_repayment_summary(&loan).ok()
+    } else {
+        None
+    };
+    let amortization_schedule = if has_principal {
+        crate::loan_repayment::build_amortization_schedule(
+            &loan,
+            &crate::loan_repayment::loan_repayment_structure(&loan),
+            &loan.repayment_history,
+            &get_protocol_parameters().compounding_schedule(),
+        )
+    } else {
+        Vec::new()
+    };
+
+    Ok(LoanFullDetails {
+        collateral: get_nft_data(loan.nft_id).map(|nft| collateral_nft_summary(&nft)),
+        disbursements: crate::liquidity_management::get_disbursement_records_by_loan(loan_id),
+        repayments: crate::loan_repayment::get_repayment_records_by_loan(loan_id),
+        health_ratio,
+        repayment_summary,
+        amortization_schedule,
+        liquidation: crate::liquidation::get_liquidation_record(loan_id),
+        generated_at: time(),
+        loan,
+    })
+}
+
 // Get user loans
 #[query]
 pub fn get_user_loans() -> Vec<Loan> {
@@ -238,22 +449,128 @@ pub fn get_user_loans() -> Vec<Loan> {
     get_loans_by_borrower(caller)
 }
 
-// Get all loans (admin only)
+// Get all loans (admin only), optionally only those carrying an operational tag
 #[query]
-pub fn get_all_loans() -> Vec<Loan> {
+pub fn get_all_loans(tag: Option<String>) -> Result<Vec<Loan>, String> {
     // Dalam implementasi nyata, tambahkan verifikasi admin
-    get_all_loans_data()
+    match tag {
+        Some(tag) => {
+            let loan_ids = crate::operational_tags::loan_ids_with_tag(&ic_cdk::caller(), &tag)?;
+            Ok(loan_ids.into_iter().filter_map(get_loan).collect())
+        },
+        None => Ok(get_all_loans_data()),
+    }
+}
+
+const MAX_LOAN_EVENTS_PER_PAGE: u64 = 500;
+
+// Loan state-change feed for integrators (admin only). Returns events with a sequence number
+// above `seq`, oldest first, and the latest sequence issued. Poll with the last sequence
+// received; delivery is at-least-once, so consumers should ignore sequences already applied.
+// Only the newest MAX_LOAN_EVENTS events are retained: if the first event returned is above
+// `seq + 1`, older events have been pruned and the consumer must resync from get_all_loans
+// before continuing from the returned sequence.
+#[query]
+pub fn get_loan_events_since(seq: u64, limit: u64) -> (Vec<LoanEvent>, u64) {
+    let caller = ic_cdk::caller();
+    if !is_admin(&caller) {
+        ic_cdk::trap("Unauthorized: Only admins can read the loan event feed");
+    }
+
+    let limit = limit.clamp(1, MAX_LOAN_EVENTS_PER_PAGE) as usize;
+    crate::storage::get_loan_events_page(seq, limit)
+}
+
+/// Replay `events` up to `at`. Fails for times before the loan existed, or when the loan's
+/// creation event is no longer in the log. `recorded_disbursement` is the loan's disbursement
+/// record timestamp: loans disbursed before status writes were logged have no Active event,
+/// so the record dates their disbursement instead.
+pub fn reconstruct_loan_state(
+    loan: &Loan,
+    events: &[LoanEvent],
+    recorded_disbursement: Option<u64>,
+    at: u64,
+) -> Result<LoanStateSnapshot, String> {
+    if at < loan.created_at {
+        return Err(format!("Loan #{} did not exist at {}; it was created at {}", loan.id, at, loan.created_at));
+    }
+    let mut events: Vec<&LoanEvent> = events.iter().filter(|event| event.loan_id == loan.id).collect();
+    events.sort_by_key(|event| event.sequence);
+    let created = events.first()
+        .filter(|event| event.old_status.is_none())
+        .ok_or_else(|| format!("Event history for loan #{} has been pruned; its state cannot be replayed", loan.id))?;
+
+    let applied: Vec<&&LoanEvent> = events.iter().filter(|event| event.timestamp <= at).collect();
+    let mut status = applied.last().map_or_else(|| created.new_status.clone(), |event| event.new_status.clone());
+    let disbursed_at = match events.iter().find(|event| event.new_status == LoanStatus::Active) {
+        Some(event) => Some(event.timestamp),
+        None => recorded_disbursement,
+    }.filter(|disbursed_at| *disbursed_at <= at);
+    // An unlogged disbursement leaves the replay at Approved; the loan was in fact active
+    if disbursed_at.is_some() && status == LoanStatus::Approved {
+        status = LoanStatus::Active;
+    }
+    let total_repaid: u64 = loan.repayment_history.iter()
+        .filter(|payment| payment.timestamp <= at)
+        .map(|payment| payment.amount)
+        .sum();
+
+    let (accrued_interest, late_penalty, outstanding_balance) = match disbursed_at {
+        Some(_) if status != LoanStatus::Repaid => {
+            let (_, interest, penalty, total_debt) = crate::loan_repayment::calculate_total_debt_at(loan, at)?;
+            (interest, penalty, total_debt.saturating_sub(total_repaid))
+        }
+        _ => (0, 0, 0),
+    };
+    let due_date = disbursed_at.and(loan.due_date);
+
+    Ok(LoanStateSnapshot {
+        loan_id: loan.id,
+        as_of: at,
+        status,
+        events_applied: applied.len() as u64,
+        last_event_sequence: applied.last().map(|event| event.sequence),
+        amount_approved: loan.amount_approved,
+        disbursed_at,
+        due_date,
+        total_repaid,
+        accrued_interest,
+        late_penalty,
+        outstanding_balance,
+        collateral_value_btc: loan.collateral_value_btc,
+        health_ratio: if outstanding_balance > 0 {
+            Some(loan.collateral_value_btc as f64 / outstanding_balance as f64)
+        } else {
+            None
+        },
+        days_overdue: if outstanding_balance > 0 {
+            due_date.map_or(0, |due_date| at.saturating_sub(due_date) / (24 * 60 * 60 * 1_000_000_000))
+        } else {
+            0
+        },
+    })
+}
+
+/// A loan's status and balances as they were at `timestamp` (borrower or admin)
+#[query]
+pub fn get_loan_state_at(loan_id: u64, timestamp: u64) -> Result<LoanStateSnapshot, String> {
+    let caller = ic_cdk::caller();
+    let loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    if loan.borrower != caller && !is_admin(&caller) {
+        return Err("Unauthorized: Only the borrower or an admin can view loan history".to_string());
+    }
+    let recorded_disbursement = crate::storage::get_disbursement_record(loan_id).map(|record| record.disbursed_at);
+    reconstruct_loan_state(&loan, &crate::storage::get_loan_events_for_loan(loan_id), recorded_disbursement, timestamp)
 }
 
 // Repay loan - Enhanced implementation with comprehensive payment tracking
 #[update]
 pub async fn repay_loan(loan_id: u64, amount: u64) -> Result<RepaymentResponse, String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "repay_loan")?;
 
     // 1. Validasi dasar
-    if amount == 0 {
-        return Err("Payment amount must be greater than zero".to_string());
-    }
+    crate::validation::require_positive(amount, "Payment amount")?;
 
     // 2. Ambil data pinjaman
     let mut loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
@@ -301,6 +618,8 @@ pub async fn repay_loan(loan_id: u64, amount: u64) -> Result<RepaymentResponse,
             PaymentType::Interest
         },
         transaction_id: transaction_id.clone(),
+        allocation: Some(payment_breakdown.clone()),
+        source: Some(RepaymentSource::CkBtc),
     };
 
     loan.total_repaid += amount;
@@ -360,6 +679,7 @@ pub async fn repay_loan(loan_id: u64, amount: u64) -> Result<RepaymentResponse,
         ckbtc_block_index: transaction_id.as_ref().map(|s| s.parse().unwrap_or(0)).unwrap_or(0),
         timestamp: time(),
         payment_breakdown: payment_breakdown.clone(),
+        source: Some(RepaymentSource::CkBtc),
     };
     
     store_repayment_record(repayment_record)?;
@@ -367,17 +687,8 @@ pub async fn repay_loan(loan_id: u64, amount: u64) -> Result<RepaymentResponse,
     // 11. Simpan perubahan loan
     store_loan(loan.clone())?;
 
-    // 12. Kirim fee ke protocol treasury jika ada
-    if payment_breakdown.protocol_fee_amount > 0 {
-        // Ini akan diimplementasikan ketika treasury management tersedia
-        log_audit_action(
-            caller,
-            "PROTOCOL_FEE_COLLECTED".to_string(),
-            format!("Protocol fee {} collected from loan #{} payment", 
-                   payment_breakdown.protocol_fee_amount, loan_id),
-            true,
-        );
-    }
+    // 12. Kirim fee ke protocol treasury, sekali per pembayaran
+    crate::loan_repayment::collect_latest_repayment_fee(&loan, caller);
 
     // 13. Buat response
     Ok(RepaymentResponse {
@@ -397,6 +708,7 @@ pub async fn repay_loan(loan_id: u64, amount: u64) -> Result<RepaymentResponse,
 // Trigger liquidation (admin only)
 #[update]
 pub async fn trigger_liquidation(loan_id: u64) -> Result<String, String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "trigger_liquidation")?;
     // Verifikasi admin access
     verify_admin_access()?;
 
@@ -442,11 +754,11 @@ pub async fn trigger_liquidation(loan_id: u64) -> Result<String, String> {
 }
 
 // Helper functions
-pub fn extract_valuation_from_metadata(metadata: &Vec<(String, MetadataValue)>) -> Result<u64, String> {
+pub fn extract_valuation_from_metadata(metadata: &Vec<(String, MetadataValue)>) -> Result<Idr, String> {
     for (key, value) in metadata {
         if key == "rwa:valuation_idr" {
             if let MetadataValue::Nat(val) = value {
-                return Ok(*val);
+                return Ok(Idr(*val));
             }
         }
     }
@@ -497,21 +809,19 @@ pub fn extract_commodity_info_from_metadata(metadata: &Vec<(String, MetadataValu
 }
 
 pub fn calculate_collateral_value_btc(
-    valuation_idr: u64,
+    valuation_idr: Idr,
     quantity: u64,
-    commodity_price: &CommodityPrice,
-) -> Result<u64, String> {
+    commodity_price: &CommodityPriceData,
+    btc_rate: &BtcIdrRate,
+) -> Result<Satoshi, String> {
     // Hitung nilai total berdasarkan kuantitas dan harga pasar
-    let market_value_idr = quantity * commodity_price.price_per_unit;
+    let market_value_idr = Idr(commodity_price.price_per_unit).checked_mul_quantity(quantity)?;
     
     // Gunakan nilai yang lebih konservatif (minimum antara valuasi dan harga pasar)
     let conservative_value_idr = std::cmp::min(valuation_idr, market_value_idr);
     
-    // Konversi ke satoshi (asumsi 1 BTC = 600,000,000 IDR)
-    let btc_price_idr = 600_000_000u64;
-    let collateral_value_satoshi = (conservative_value_idr * 100_000_000) / btc_price_idr;
-    
-    Ok(collateral_value_satoshi)
+    // Konversi ke satoshi dengan kurs BTC/IDR dari oracle
+    btc_rate.idr_to_satoshi(conservative_value_idr)
 }
 
 pub fn calculate_total_debt(loan: &Loan) -> Result<u64, String> {
@@ -530,11 +840,8 @@ pub fn calculate_total_debt(loan: &Loan) -> Result<u64, String> {
         current_time.saturating_sub(loan.created_at)
     };
     
-    // Convert nanoseconds to years (approximate)
-    let years = loan_duration as f64 / (365.25 * 24.0 * 60.0 * 60.0 * 1_000_000_000.0);
-    
-    // Calculate interest: principal * (apr/100) * years
-    let interest = (loan.amount_approved as f64 * (loan.apr as f64 / 100.0) * years) as u64;
+    // Calculate interest: principal * (apr/100) * years, per rate period for variable loans
+    let interest = crate::loan_repayment::accrue_interest(loan, loan.created_at + loan_duration);
     
     let total_debt = loan.amount_approved + interest;
     Ok(total_debt)
@@ -596,6 +903,9 @@ pub fn calculate_loan_repayment_summary(loan: &Loan) -> Result<LoanRepaymentSumm
         next_payment_due: loan.due_date,
         is_overdue,
         days_overdue,
+        current_apr: loan.apr,
+        is_variable_rate: matches!(loan.rate_type, Some(RateType::Variable(_))),
+        upcoming_rate_resets: crate::loan_repayment::upcoming_rate_resets(loan, current_time, 3),
     })
 }
 
@@ -613,31 +923,23 @@ pub fn calculate_payment_breakdown(loan: &Loan, payment_amount: u64) -> Result<P
     // Protocol fee (e.g., 2% of interest portion)
     let protocol_fee_rate = 200; // 2% in basis points (2/100 * 10000)
     
-    let mut breakdown = PaymentBreakdown {
-        principal_amount: 0,
-        interest_amount: 0,
-        protocol_fee_amount: 0,
-        total_amount: payment_amount,
-    };
-    
-    let mut remaining_payment = payment_amount;
+    // Allocate in the governance-configured order; this debt has no separate fee bucket
+    let (penalty_payment, interest_payment, principal_payment) = crate::loan_repayment::allocate_payment(
+        payment_amount,
+        &crate::loan_repayment::get_repayment_allocation_order(),
+        0,
+        interest_remaining,
+        principal_remaining,
+    );
     
-    // First pay interest
-    if interest_remaining > 0 && remaining_payment > 0 {
-        let interest_payment = remaining_payment.min(interest_remaining);
-        breakdown.interest_amount = interest_payment;
-        remaining_payment = remaining_payment.saturating_sub(interest_payment);
-        
+    Ok(PaymentBreakdown {
+        principal_amount: principal_payment,
+        interest_amount: interest_payment,
         // Calculate protocol fee on interest
-        breakdown.protocol_fee_amount = (interest_payment * protocol_fee_rate) / 10000;
-    }
-    
-    // Then pay principal
-    if principal_remaining > 0 && remaining_payment > 0 {
-        breakdown.principal_amount = remaining_payment.min(principal_remaining);
-    }
-    
-    Ok(breakdown)
+        protocol_fee_amount: crate::helpers::rounded_share(interest_payment, protocol_fee_rate, BASIS_POINTS_SCALE),
+        penalty_amount: penalty_payment,
+        total_amount: payment_amount,
+    })
 }
 
 /// Release collateral NFT back to borrower after full repayment
@@ -694,9 +996,12 @@ pub fn get_repayment_plan(loan_id: u64) -> Result<RepaymentPlan, String> {
         total_amount_due: summary.remaining_balance,
         principal_amount: summary.principal_outstanding,
         interest_amount: summary.interest_outstanding,
-        protocol_fee: (summary.interest_outstanding * 200) / 10000, // 2% protocol fee
+        protocol_fee: crate::helpers::rounded_share(summary.interest_outstanding, 200, BASIS_POINTS_SCALE), // 2% protocol fee
         due_date: loan.due_date.unwrap_or(time() + (params.max_loan_duration_days * 24 * 60 * 60 * 1_000_000_000)),
         minimum_payment,
+        network_fee_charged: crate::liquidity_management::borrower_network_fee_charge(
+            &crate::liquidity_management::get_disbursement_records_by_loan(loan_id),
+        ),
     })
 }
 
@@ -741,3 +1046,845 @@ pub fn calculate_early_repayment_amount(loan_id: u64) -> Result<u64, String> {
     // For now, just return the full amount
     Ok(summary.remaining_balance)
 }
+
+// ========== LOAN RESTRUCTURING ==========
+
+const MAX_RESTRUCTURE_EXTENSION_DAYS: u64 = 180; // Perpanjangan maksimal dari jatuh tempo saat ini
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Resolve the due date requested by a restructure proposal and validate it against the loan
+pub fn resolve_restructured_due_date(
+    loan: &Loan,
+    proposal: &RestructureProposal,
+    now: u64,
+) -> Result<Option<u64>, String> {
+    if proposal.new_due_date.is_none() && proposal.new_term_days.is_none() && proposal.new_apr.is_none() {
+        return Err("Restructure proposal must change the due date, term, or rate".to_string());
+    }
+
+    if let Some(new_apr) = proposal.new_apr {
+        if new_apr >= loan.apr {
+            return Err("Restructured APR must be lower than the current APR".to_string());
+        }
+    }
+
+    let new_due_date = match (proposal.new_due_date, proposal.new_term_days) {
+        (Some(due_date), _) => Some(due_date),
+        (None, Some(term_days)) => Some(loan.created_at + term_days * NANOS_PER_DAY),
+        (None, None) => None,
+    };
+
+    if let Some(due_date) = new_due_date {
+        if due_date <= now {
+            return Err("New due date must be in the future".to_string());
+        }
+
+        let current_due_date = loan.due_date.unwrap_or(now);
+        if due_date <= current_due_date {
+            return Err("New due date must extend the current due date".to_string());
+        }
+
+        if due_date - current_due_date > MAX_RESTRUCTURE_EXTENSION_DAYS * NANOS_PER_DAY {
+            return Err(format!(
+                "Extension cannot exceed {} days beyond the current due date",
+                MAX_RESTRUCTURE_EXTENSION_DAYS
+            ));
+        }
+    }
+
+    Ok(new_due_date)
+}
+
+/// Submit a restructure request for an active loan owned by the caller
+#[update]
+pub fn request_loan_restructure(loan_id: u64, proposal: RestructureProposal) -> Result<LoanRestructureRequest, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "request_loan_restructure")?;
+
+    let loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    if loan.borrower != caller {
+        return Err("Unauthorized: You are not the borrower of this loan".to_string());
+    }
+    if loan.status != LoanStatus::Active {
+        return Err("Only active loans can be restructured".to_string());
+    }
+    if crate::liquidation::get_liquidation_record(loan_id).is_some() {
+        return Err("Loan is under liquidation and cannot be restructured".to_string());
+    }
+
+    let has_open_request = get_all_restructure_requests()
+        .iter()
+        .any(|request| request.loan_id == loan_id && request.status == RestructureStatus::Pending);
+    if has_open_request {
+        return Err("A restructure request for this loan is already pending".to_string());
+    }
+
+    resolve_restructured_due_date(&loan, &proposal, time())?;
+
+    let request = LoanRestructureRequest {
+        request_id: get_next_restructure_request_id(),
+        loan_id,
+        borrower: caller,
+        proposal,
+        status: RestructureStatus::Pending,
+        requested_at: time(),
+        reviewed_by: None,
+        reviewed_at: None,
+        previous_due_date: loan.due_date,
+        previous_apr: loan.apr,
+        debt_before: None,
+        debt_after: None,
+    };
+    store_restructure_request(request.clone());
+
+    log_audit_action(
+        caller,
+        "LOAN_RESTRUCTURE_REQUESTED".to_string(),
+        format!("Restructure request #{} submitted for loan #{}", request.request_id, loan_id),
+        true,
+    );
+
+    Ok(request)
+}
+
+/// Get all pending restructure requests (admin only)
+#[query]
+pub fn get_pending_restructures() -> Result<Vec<LoanRestructureRequest>, String> {
+    if !is_admin(&ic_cdk::caller()) {
+        return Err("Unauthorized: Admin access required".to_string());
+    }
+
+    Ok(get_all_restructure_requests()
+        .into_iter()
+        .filter(|request| request.status == RestructureStatus::Pending)
+        .collect())
+}
+
+/// Approve a restructure request and rewrite the loan schedule (admin only)
+#[update]
+pub fn approve_restructure(request_id: u64) -> Result<LoanRestructureRequest, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "approve_restructure")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Admin access required".to_string());
+    }
+
+    let mut request = get_restructure_request(request_id)
+        .ok_or_else(|| "Restructure request not found".to_string())?;
+    if request.status != RestructureStatus::Pending {
+        return Err("Restructure request is not pending".to_string());
+    }
+
+    let mut loan = get_loan(request.loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    if loan.status != LoanStatus::Active {
+        return Err("Only active loans can be restructured".to_string());
+    }
+
+    // Re-validate against the loan as it stands now
+    let new_due_date = resolve_restructured_due_date(&loan, &request.proposal, time())?;
+
+    let (_, _, _, debt_before) = calculate_total_debt_with_interest(&loan)?;
+
+    if let Some(due_date) = new_due_date {
+        loan.due_date = Some(due_date);
+    }
+    if let Some(new_apr) = request.proposal.new_apr {
+        loan.apr = new_apr;
+    }
+
+    let (_, _, _, debt_after) = calculate_total_debt_with_interest(&loan)?;
+    store_loan(loan.clone())?;
+
+    request.status = RestructureStatus::Approved;
+    request.reviewed_by = Some(caller);
+    request.reviewed_at = Some(time());
+    request.debt_before = Some(debt_before);
+    request.debt_after = Some(debt_after);
+    store_restructure_request(request.clone());
+
+    log_audit_action(
+        caller,
+        "LOAN_RESTRUCTURE_APPROVED".to_string(),
+        format!(
+            "Restructure request #{} approved for loan #{}: due date {:?} -> {:?}, APR {} -> {}, debt {} -> {}",
+            request_id, loan.id, request.previous_due_date, loan.due_date,
+            request.previous_apr, loan.apr, debt_before, debt_after
+        ),
+        true,
+    );
+
+    Ok(request)
+}
+
+/// Reject a restructure request (admin only)
+#[update]
+pub fn reject_restructure(request_id: u64, reason: String) -> Result<LoanRestructureRequest, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "reject_restructure")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Admin access required".to_string());
+    }
+
+    let mut request = get_restructure_request(request_id)
+        .ok_or_else(|| "Restructure request not found".to_string())?;
+    if request.status != RestructureStatus::Pending {
+        return Err("Restructure request is not pending".to_string());
+    }
+
+    request.status = RestructureStatus::Rejected(reason.clone());
+    request.reviewed_by = Some(caller);
+    request.reviewed_at = Some(time());
+    store_restructure_request(request.clone());
+
+    log_audit_action(
+        caller,
+        "LOAN_RESTRUCTURE_REJECTED".to_string(),
+        format!("Restructure request #{} for loan #{} rejected: {}", request_id, request.loan_id, reason),
+        true,
+    );
+
+    Ok(request)
+}
+
+// ========== REFINANCING ==========
+
+const MIN_REFINANCE_TERM_DAYS: u64 = 30;
+/// Transaction ID prefix of the payoff a refinance books on the loan it replaces
+pub const REFINANCE_TRANSACTION_PREFIX: &str = "refinance:";
+
+/// Check a refinance request against the old loan and the collateral's current LTV limit
+pub fn validate_refinance(
+    old_status: &LoanStatus,
+    payoff: u64,
+    ltv_limit: u64,
+    new_term_days: u64,
+    max_term_days: u64,
+) -> Result<(), String> {
+    if *old_status != LoanStatus::Active {
+        return Err("Only active loans can be refinanced".to_string());
+    }
+    if payoff == 0 {
+        return Err("Loan has no outstanding balance to refinance".to_string());
+    }
+    if new_term_days < MIN_REFINANCE_TERM_DAYS || new_term_days > max_term_days {
+        return Err(format!(
+            "New term must be between {} and {} days",
+            MIN_REFINANCE_TERM_DAYS, max_term_days
+        ));
+    }
+    check_refinance_ltv(payoff, ltv_limit)
+}
+
+/// A refinance may not borrow more than the collateral's LTV limit at current prices
+pub fn check_refinance_ltv(payoff: u64, ltv_limit: u64) -> Result<(), String> {
+    if payoff > ltv_limit {
+        return Err(format!(
+            "Payoff {} exceeds the {} satoshi allowed against the collateral at current prices",
+            payoff, ltv_limit
+        ));
+    }
+    Ok(())
+}
+
+/// Outstanding payoff a refinance of `loan` would borrow
+pub fn refinance_payoff(loan: &Loan) -> Result<u64, String> {
+    let (_, _, _, total_debt) = calculate_total_debt_with_interest(loan)?;
+    Ok(total_debt.saturating_sub(loan.total_repaid))
+}
+
+/// The loan's collateral revalued at current prices, and the LTV limit a refinance is held to.
+/// The collateral floor is not re-applied: the NFT already backs this debt and is grandfathered
+/// like any existing loan.
+pub fn refinance_collateral_value(loan: &Loan) -> Result<(u64, u64), String> {
+    let nft_data = get_nft_data(loan.nft_id).ok_or_else(|| "NFT not found".to_string())?;
+    crate::helpers::ensure_receipt_not_expired(&nft_data.metadata, time())?;
+    let valuation_idr = extract_valuation_from_metadata(&nft_data.metadata)?;
+    let commodity_info = extract_commodity_info_from_metadata(&nft_data.metadata)?;
+    let commodity_price_data = crate::oracle::get_effective_commodity_price(&commodity_info.commodity_type)
+        .ok_or_else(|| "Commodity price not available. Please contact admin to update price feeds.".to_string())?;
+    if is_price_stale(commodity_info.commodity_type.clone()) {
+        return Err("Commodity price data is stale. Please wait for price update.".to_string());
+    }
+    let btc_rate = crate::oracle::get_asset_idr_rate(&loan.asset())?;
+    let collateral_value_btc = calculate_collateral_value_btc(
+        valuation_idr,
+        commodity_info.quantity,
+        &commodity_price_data,
+        &btc_rate,
+    )?.0;
+    let ltv_limit = (collateral_value_btc * get_commodity_ltv_ratio(&commodity_info.commodity_type)) / 100;
+    Ok((collateral_value_btc, ltv_limit))
+}
+
+/// Refinance an active loan into a new fixed-rate loan at the current base rate (borrower only).
+/// The new loan's proceeds close the old loan and the NFT lock moves straight to the new loan.
+#[update]
+pub fn refinance_loan(old_loan_id: u64, new_term_days: u64) -> Result<Loan, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "refinance_loan")?;
+    crate::helpers::ensure_operation_available("refinance_loan")?;
+    crate::feature_flags::ensure_feature_enabled(crate::feature_flags::FEATURE_REFINANCING)?;
+    crate::liquidity_management::ensure_origination_allowed()?;
+
+    let mut old_loan = get_loan(old_loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    if old_loan.borrower != caller {
+        return Err("Unauthorized: You are not the borrower of this loan".to_string());
+    }
+
+    // Payoff on the old loan, against the same collateral revalued at current prices
+    let payoff = refinance_payoff(&old_loan)?;
+    let (collateral_value_btc, ltv_limit) = refinance_collateral_value(&old_loan)?;
+
+    let params = get_protocol_parameters();
+    validate_refinance(&old_loan.status, payoff, ltv_limit, new_term_days, params.max_loan_duration_days)?;
+
+    let now = time();
+    let new_loan_id = get_next_loan_id();
+    let new_loan = Loan {
+        id: new_loan_id,
+        borrower: caller,
+        nft_id: old_loan.nft_id,
+        collateral_value_btc,
+        amount_requested: payoff,
+        amount_approved: payoff,
+        apr: params.base_apr,
+        status: LoanStatus::Active,
+        created_at: now,
+        due_date: Some(now + new_term_days * 24 * 60 * 60 * 1_000_000_000),
+        total_repaid: 0,
+        repayment_history: Vec::new(),
+        last_payment_date: None,
+        region: old_loan.region.clone(),
+        rate_type: Some(RateType::Fixed),
+        asset: old_loan.asset,
+    };
+
+    // Move the lock before closing the old loan so the NFT is never unencumbered
+    crate::storage::transfer_nft_lock(old_loan.nft_id, old_loan_id, new_loan_id)?;
+    store_loan(new_loan.clone())?;
+
+    old_loan.repayment_history.push(Payment {
+        amount: payoff,
+        timestamp: now,
+        payment_type: PaymentType::Mixed,
+        transaction_id: Some(format!("{}{}", REFINANCE_TRANSACTION_PREFIX, new_loan_id)),
+        allocation: None,
+        source: None,
+    });
+    old_loan.total_repaid += payoff;
+    old_loan.last_payment_date = Some(now);
+    old_loan.status = LoanStatus::Repaid;
+    store_loan(old_loan.clone())?;
+
+    crate::liquidity_management::apply_refinancing_to_pool(&new_loan.asset(), payoff)?;
+
+    let correlation = format!("refinance {} -> {}", old_loan_id, new_loan_id);
+    log_audit_action(
+        caller,
+        "LOAN_REFINANCE_PAYOFF".to_string(),
+        format!("[{}] Loan #{} closed with payoff {} satoshi from loan #{}", correlation, old_loan_id, payoff, new_loan_id),
+        true,
+    );
+    log_audit_action(
+        caller,
+        "LOAN_REFINANCE_ORIGINATED".to_string(),
+        format!(
+            "[{}] Loan #{} originated for {} satoshi at {}% fixed over {} days against NFT #{} (LTV limit {})",
+            correlation, new_loan_id, payoff, new_loan.apr, new_term_days, new_loan.nft_id, ltv_limit
+        ),
+        true,
+    );
+
+    Ok(new_loan)
+}
+
+// ========== UNDERWRITING WORKFLOW ==========
+
+/// Allowed underwriting transitions
+pub fn validate_underwriting_transition(from: &UnderwritingState, to: &UnderwritingState) -> Result<(), String> {
+    let allowed = matches!(
+        (from, to),
+        (UnderwritingState::Submitted, UnderwritingState::UnderReview)
+            | (UnderwritingState::UnderReview, UnderwritingState::NeedsInfo)
+            | (UnderwritingState::UnderReview, UnderwritingState::Approved)
+            | (UnderwritingState::UnderReview, UnderwritingState::Rejected)
+            | (UnderwritingState::NeedsInfo, UnderwritingState::UnderReview)
+            | (UnderwritingState::Submitted | UnderwritingState::UnderReview | UnderwritingState::NeedsInfo, UnderwritingState::Expired)
+            | (UnderwritingState::Submitted | UnderwritingState::UnderReview | UnderwritingState::NeedsInfo | UnderwritingState::Approved, UnderwritingState::Withdrawn)
+    );
+
+    if allowed {
+        Ok(())
+    } else {
+        Err(format!("Invalid underwriting transition from {:?} to {:?}", from, to))
+    }
+}
+
+fn transition_application(
+    loan_id: u64,
+    actor: Principal,
+    to_state: UnderwritingState,
+    note: String,
+    action: &str,
+) -> Result<LoanApplicationReview, String> {
+    let mut review = get_application_review(loan_id)
+        .ok_or_else(|| "Loan application review not found".to_string())?;
+
+    let from_state = review.state.clone();
+    if let Err(e) = validate_underwriting_transition(&from_state, &to_state) {
+        crate::audit_logging::log_loan_operation(
+            action, loan_id, review.borrower, None, false, Some(e.clone()), None,
+            Some(format!("{:?}", from_state)), Some(format!("{:?}", to_state)),
+        );
+        return Err(e);
+    }
+
+    let now = time();
+    review.thread.push(ReviewNote {
+        actor,
+        note,
+        from_state: from_state.clone(),
+        to_state: to_state.clone(),
+        timestamp: now,
+    });
+    review.state = to_state.clone();
+    review.updated_at = now;
+    store_application_review(review.clone());
+
+    crate::audit_logging::log_loan_operation(
+        action, loan_id, review.borrower, None, true, None, None,
+        Some(format!("{:?}", from_state)), Some(format!("{:?}", to_state)),
+    );
+
+    Ok(review)
+}
+
+fn ensure_assigned_underwriter(loan_id: u64, caller: &Principal) -> Result<(), String> {
+    let review = get_application_review(loan_id)
+        .ok_or_else(|| "Loan application review not found".to_string())?;
+    if review.underwriter != Some(*caller) {
+        return Err("Unauthorized: Only the assigned underwriter can act on this application".to_string());
+    }
+    Ok(())
+}
+
+/// Take a submitted application for review (admin only)
+#[update]
+pub fn assign_underwriter(loan_id: u64) -> Result<LoanApplicationReview, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "assign_underwriter")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can underwrite loan applications".to_string());
+    }
+
+    let mut review = transition_application(
+        loan_id,
+        caller,
+        UnderwritingState::UnderReview,
+        "Underwriter assigned".to_string(),
+        "APPLICATION_UNDERWRITER_ASSIGNED",
+    )?;
+    review.underwriter = Some(caller);
+    store_application_review(review.clone());
+
+    Ok(review)
+}
+
+/// Ask the borrower for more information (assigned underwriter only)
+#[update]
+pub fn request_more_info(loan_id: u64, note: String) -> Result<LoanApplicationReview, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "request_more_info")?;
+    ensure_assigned_underwriter(loan_id, &caller)?;
+    if note.trim().is_empty() {
+        return Err("Describe the information required".to_string());
+    }
+
+    transition_application(loan_id, caller, UnderwritingState::NeedsInfo, note, "APPLICATION_INFO_REQUESTED")
+}
+
+/// Respond to an information request (borrower only)
+#[update]
+pub fn submit_additional_info(loan_id: u64, note: String) -> Result<LoanApplicationReview, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "submit_additional_info")?;
+    let review = get_application_review(loan_id)
+        .ok_or_else(|| "Loan application review not found".to_string())?;
+    if review.borrower != caller {
+        return Err("Unauthorized: You are not the borrower of this loan".to_string());
+    }
+    if note.trim().is_empty() {
+        return Err("Additional information cannot be empty".to_string());
+    }
+
+    transition_application(loan_id, caller, UnderwritingState::UnderReview, note, "APPLICATION_INFO_SUBMITTED")
+}
+
+/// Approve or reject an application under review (assigned underwriter only)
+#[update]
+pub fn decide_application(loan_id: u64, approve: bool, note: String) -> Result<LoanApplicationReview, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "decide_application")?;
+    ensure_assigned_underwriter(loan_id, &caller)?;
+    if !approve && note.trim().is_empty() {
+        return Err("A reason is required when rejecting an application".to_string());
+    }
+
+    if !approve {
+        let review = transition_application(loan_id, caller, UnderwritingState::Rejected, note, "APPLICATION_REJECTED")?;
+        crate::liquidity_management::release_liquidity_reservation(loan_id, "application rejected");
+        if let Some(loan) = get_loan(loan_id) {
+            crate::promo_campaigns::release_promo_usage(&loan);
+        }
+        return Ok(review);
+    }
+
+    // Hold the approved amount so it is still available when the borrower accepts
+    let loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    crate::liquidity_management::reserve_liquidity_for_loan(loan_id, loan.amount_approved)?;
+
+    transition_application(loan_id, caller, UnderwritingState::Approved, note, "APPLICATION_APPROVED")
+        .map_err(|e| {
+            crate::liquidity_management::release_liquidity_reservation(loan_id, "approval did not complete");
+            e
+        })
+}
+
+// ========== APPLICATION EXPIRY ==========
+
+const DEFAULT_APPLICATION_VALIDITY_DAYS: u64 = 30;
+const MAX_APPLICATION_EXPIRIES_PER_RUN: usize = 100;
+
+/// An application expires once its deadline passes while it is still awaiting an underwriting
+/// decision. Underwriting-approved offers are covered by the liquidity reservation expiry instead.
+pub fn is_application_expirable(loan_status: &LoanStatus, review: &LoanApplicationReview, now: u64) -> bool {
+    matches!(loan_status, LoanStatus::PendingApplication | LoanStatus::PendingApproval)
+        && matches!(review.state, UnderwritingState::Submitted | UnderwritingState::UnderReview | UnderwritingState::NeedsInfo)
+        && review.expires_at.map_or(false, |expires_at| now >= expires_at)
+}
+
+/// Move a pending loan to a closed status, releasing its reservation, promo capacity and any NFT locked for it
+fn close_pending_application(mut loan: Loan, status: LoanStatus, reason: &str) -> Result<Loan, String> {
+    crate::liquidity_management::release_liquidity_reservation(loan.id, reason);
+    crate::promo_campaigns::release_promo_usage(&loan);
+    if let Some(nft) = get_nft_data(loan.nft_id) {
+        if nft.is_locked && nft.loan_id == Some(loan.id) {
+            unlock_nft(loan.nft_id)?;
+        }
+    }
+    loan.status = status;
+    store_loan(loan.clone())?;
+    Ok(loan)
+}
+
+/// Cancel a pending application and reclaim its NFT (borrower only)
+#[update]
+pub fn withdraw_application(loan_id: u64) -> Result<Loan, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "withdraw_application")?;
+    let loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+
+    if loan.borrower != caller {
+        return Err("Unauthorized: You are not the borrower of this loan".to_string());
+    }
+    if !matches!(loan.status, LoanStatus::PendingApplication | LoanStatus::PendingApproval) {
+        return Err(format!("Only pending applications can be withdrawn (status: {:?})", loan.status));
+    }
+
+    if get_application_review(loan_id).is_some() {
+        transition_application(
+            loan_id, caller, UnderwritingState::Withdrawn,
+            "Withdrawn by borrower".to_string(), "APPLICATION_WITHDRAWN",
+        )?;
+    }
+    let loan = close_pending_application(loan, LoanStatus::Withdrawn, "application withdrawn")?;
+
+    log_audit_action(
+        caller,
+        "LOAN_APPLICATION_WITHDRAWN".to_string(),
+        format!("Loan application #{} withdrawn by borrower, NFT #{} released", loan_id, loan.nft_id),
+        true,
+    );
+
+    Ok(loan)
+}
+
+/// Expire undecided applications past their deadline. Called from the heartbeat.
+pub fn expire_stale_applications(now: u64) -> u64 {
+    let due: Vec<(Loan, LoanApplicationReview)> = crate::storage::get_all_application_reviews()
+        .into_iter()
+        .filter_map(|review| get_loan(review.loan_id).map(|loan| (loan, review)))
+        .filter(|(loan, review)| is_application_expirable(&loan.status, review, now))
+        .take(MAX_APPLICATION_EXPIRIES_PER_RUN)
+        .collect();
+
+    let mut expired = 0;
+    for (loan, _) in due {
+        let loan_id = loan.id;
+        let result = transition_application(
+            loan_id, ic_cdk::id(), UnderwritingState::Expired,
+            "Expired without an underwriting decision".to_string(), "APPLICATION_EXPIRED",
+        ).and_then(|_| close_pending_application(loan, LoanStatus::Expired, "application expired"));
+
+        log_audit_action(
+            ic_cdk::id(),
+            "LOAN_APPLICATION_EXPIRED".to_string(),
+            match &result {
+                Ok(loan) => format!("Loan application #{} expired, NFT #{} released", loan_id, loan.nft_id),
+                Err(e) => format!("Failed to expire loan application #{}: {}", loan_id, e),
+            },
+            result.is_ok(),
+        );
+        if result.is_ok() {
+            expired += 1;
+        }
+    }
+    expired
+}
+
+/// Get the underwriting review thread for an application (borrower or admin)
+#[query]
+pub fn get_application_review_thread(loan_id: u64) -> Result<LoanApplicationReview, String> {
+    let caller = ic_cdk::caller();
+    let review = get_application_review(loan_id)
+        .ok_or_else(|| "Loan application review not found".to_string())?;
+
+    if review.borrower != caller && !is_admin(&caller) {
+        return Err("Unauthorized: You can only view your own applications".to_string());
+    }
+
+    Ok(review)
+}
+
+// ========== BORROWING CAPACITY ==========
+
+/// Combine the LTV bound with pool limits and report which one binds
+pub fn compute_max_borrowable(
+    ltv_limit: u64,
+    existing_exposure: u64,
+    pool_single_loan_cap: u64,
+    pool_available_liquidity: u64,
+) -> (u64, BorrowLimitFactor) {
+    let ltv_room = ltv_limit.saturating_sub(existing_exposure);
+
+    let mut max_borrowable = ltv_room;
+    let mut limiting_factor = BorrowLimitFactor::LtvBound;
+
+    if pool_single_loan_cap < max_borrowable {
+        max_borrowable = pool_single_loan_cap;
+        limiting_factor = BorrowLimitFactor::PoolSingleLoanCap;
+    }
+    if pool_available_liquidity < max_borrowable {
+        max_borrowable = pool_available_liquidity;
+        limiting_factor = BorrowLimitFactor::PoolAvailableLiquidity;
+    }
+
+    (max_borrowable, limiting_factor)
+}
+
+/// Preview how much the caller can borrow against an NFT they own, in `asset`'s base unit
+/// (ckBTC when omitted)
+#[query]
+pub fn get_max_borrowable(nft_id: u64, asset: Option<Asset>) -> Result<MaxBorrowable, String> {
+    let caller = ic_cdk::caller();
+    let asset = asset.unwrap_or_default();
+
+    let nft_data = get_nft_data(nft_id).ok_or_else(|| "NFT not found".to_string())?;
+    if nft_data.owner != caller {
+        return Err("You don't own this NFT".to_string());
+    }
+    if nft_data.is_locked {
+        return Err("NFT is already locked in another loan".to_string());
+    }
+
+    let valuation_idr = extract_valuation_from_metadata(&nft_data.metadata)?;
+    let commodity_info = extract_commodity_info_from_metadata(&nft_data.metadata)?;
+
+    let commodity_price_data = crate::oracle::get_effective_commodity_price(&commodity_info.commodity_type)
+        .ok_or_else(|| "Commodity price not available. Please contact admin to update price feeds.".to_string())?;
+    if is_price_stale(commodity_info.commodity_type.clone()) {
+        return Err("Commodity price data is stale. Please wait for price update.".to_string());
+    }
+
+    let btc_rate = crate::oracle::get_asset_idr_rate(&asset)?;
+    let collateral_value_btc = calculate_collateral_value_btc(
+        valuation_idr,
+        commodity_info.quantity,
+        &commodity_price_data,
+        &btc_rate,
+    )?.0;
+
+    let (_, reputation_ltv_adjustment) = crate::borrower_reputation::reputation_terms_for(caller);
+    let ltv_ratio = crate::borrower_reputation::adjusted_ltv(
+        get_commodity_ltv_ratio(&commodity_info.commodity_type),
+        reputation_ltv_adjustment,
+    );
+    let ltv_limit = (collateral_value_btc * ltv_ratio) / 100;
+
+    // Outstanding amounts of loans in `asset` against this NFT that have not been closed out
+    let existing_exposure: u64 = get_all_loans_data()
+        .iter()
+        .filter(|loan| loan.nft_id == nft_id && loan.asset() == asset)
+        .filter(|loan| matches!(
+            loan.status,
+            LoanStatus::PendingApplication | LoanStatus::PendingApproval | LoanStatus::Approved | LoanStatus::Active
+        ))
+        .map(|loan| loan.amount_approved.saturating_sub(loan.total_repaid))
+        .sum();
+
+    let pool = crate::storage::get_asset_pool(&asset);
+    let pool_single_loan_cap =
+        (pool.total_liquidity * crate::liquidity_management::MAX_SINGLE_LOAN_POOL_PERCENTAGE) / 100;
+
+    let (max_borrowable, limiting_factor) = compute_max_borrowable(
+        ltv_limit,
+        existing_exposure,
+        pool_single_loan_cap,
+        pool.available_liquidity,
+    );
+
+    Ok(MaxBorrowable {
+        nft_id,
+        commodity_type: commodity_info.commodity_type,
+        collateral_value_btc,
+        ltv_ratio,
+        ltv_limit,
+        existing_exposure,
+        pool_single_loan_cap,
+        pool_available_liquidity: pool.available_liquidity,
+        max_borrowable,
+        limiting_factor,
+    })
+}
+
+/// Outstanding principal, loan count, default rate and average health ratio per region
+#[query]
+pub fn get_regional_exposure() -> Vec<RegionalExposure> {
+    build_regional_exposure(&get_all_loans_data())
+}
+
+/// Outstanding collateral per commodity as (commodity, amount in base units, percentage of book)
+/// for the loan book in `asset` (ckBTC when omitted)
+#[query]
+pub fn get_commodity_exposure(asset: Option<Asset>) -> Vec<(String, u64, f64)> {
+    let exposures = get_commodity_exposures(&asset.unwrap_or_default());
+    let total: u64 = exposures.iter().map(|(_, amount)| *amount).sum();
+
+    exposures
+        .into_iter()
+        .map(|(commodity, amount)| {
+            let percentage = if total > 0 { amount as f64 / total as f64 * 100.0 } else { 0.0 };
+            (commodity, amount, percentage)
+        })
+        .collect()
+}
+
+// ========== ACTIVE LOAN LIMIT ==========
+
+const DEFAULT_MAX_ACTIVE_LOANS_PER_BORROWER: u64 = 5;
+const MAX_LOAN_LIMIT_REASON_LENGTH: usize = 200;
+
+/// Every status except the closed ones holds a slot, including pending applications and
+/// loans in liquidation
+pub fn counts_toward_loan_limit(status: &LoanStatus) -> bool {
+    !matches!(
+        status,
+        LoanStatus::Repaid | LoanStatus::Defaulted | LoanStatus::Expired | LoanStatus::Withdrawn
+    )
+}
+
+pub fn build_loan_capacity(
+    borrower: Principal,
+    loans: &[Loan],
+    governance_limit: u64,
+    limit_override: Option<&BorrowerLoanLimitOverride>,
+) -> BorrowerLoanCapacity {
+    let used = loans.iter().filter(|loan| counts_toward_loan_limit(&loan.status)).count() as u64;
+    let limit = limit_override.map_or(governance_limit, |o| o.max_active_loans);
+    BorrowerLoanCapacity {
+        borrower,
+        limit,
+        used,
+        remaining: limit.saturating_sub(used),
+        override_reason: limit_override.map(|o| o.reason.clone()),
+    }
+}
+
+pub fn ensure_loan_capacity(capacity: &BorrowerLoanCapacity) -> Result<(), String> {
+    if capacity.remaining == 0 {
+        return Err(format!(
+            "Active loan limit reached: you hold {} of {} allowed loans. Repay or withdraw one before applying again",
+            capacity.used, capacity.limit
+        ));
+    }
+    Ok(())
+}
+
+pub fn borrower_loan_capacity(borrower: Principal) -> BorrowerLoanCapacity {
+    let governance_limit = crate::governance::get_protocol_parameter("max_active_loans_per_borrower".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_MAX_ACTIVE_LOANS_PER_BORROWER);
+    build_loan_capacity(
+        borrower,
+        &get_loans_by_borrower(borrower),
+        governance_limit,
+        crate::storage::get_loan_limit_override(borrower).as_ref(),
+    )
+}
+
+/// Used and remaining loan slots (the borrower or an admin)
+#[query]
+pub fn get_borrower_loan_capacity(borrower: Principal) -> Result<BorrowerLoanCapacity, String> {
+    let caller = ic_cdk::caller();
+    if borrower != caller && !is_admin(&caller) {
+        return Err("Unauthorized: Only the borrower or an admin can view loan capacity".to_string());
+    }
+    Ok(borrower_loan_capacity(borrower))
+}
+
+/// Give one borrower a different active loan limit, or pass `None` to return them to the
+/// governance limit (admin only). A reason is required either way.
+#[update]
+pub fn set_borrower_loan_limit_override(
+    borrower: Principal,
+    max_active_loans: Option<u64>,
+    reason: String,
+) -> Result<BorrowerLoanCapacity, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "set_borrower_loan_limit_override")?;
+    crate::validation::require_role(&caller, crate::validation::CallerRole::Admin)?;
+    let reason = reason.trim().to_string();
+    if reason.is_empty() || reason.len() > MAX_LOAN_LIMIT_REASON_LENGTH {
+        return Err(format!("Reason must be 1 to {} characters", MAX_LOAN_LIMIT_REASON_LENGTH));
+    }
+
+    match max_active_loans {
+        Some(max_active_loans) => {
+            crate::validation::require_positive(max_active_loans, "Loan limit")?;
+            crate::storage::store_loan_limit_override(BorrowerLoanLimitOverride {
+                borrower,
+                max_active_loans,
+                reason: reason.clone(),
+                set_by: caller,
+                set_at: time(),
+            });
+        }
+        None => {
+            crate::storage::remove_loan_limit_override(borrower)
+                .ok_or_else(|| "Borrower has no loan limit override".to_string())?;
+        }
+    }
+
+    log_audit_action(
+        caller,
+        "LOAN_LIMIT_OVERRIDE_SET".to_string(),
+        format!(
+            "Active loan limit for {} set to {}: {}",
+            borrower.to_text(),
+            max_active_loans.map_or("the governance default".to_string(), |limit| limit.to_string()),
+            reason
+        ),
+        true,
+    );
+    Ok(borrower_loan_capacity(borrower))
+}
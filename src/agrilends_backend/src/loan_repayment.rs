@@ -10,7 +10,7 @@ use crate::notification_system::{notify_loan_event, notify_collateral_event};
 use std::collections::HashMap;
 
 // Constants for loan repayment - Production ready values
-const PROTOCOL_FEE_PERCENTAGE: u64 = 10; // 10% dari bunga untuk protokol
+pub(crate) const PROTOCOL_FEE_PERCENTAGE: u64 = 10; // 10% dari bunga untuk protokol
 const GRACE_PERIOD_FACTOR: f64 = 1.1; // 10% tambahan waktu grace
 const MINIMUM_PAYMENT_AMOUNT: u64 = 1000; // Minimum 1000 satoshi
 const EARLY_REPAYMENT_DISCOUNT_RATE: u64 = 5; // 5% discount untuk early repayment
@@ -22,20 +22,16 @@ const LATE_PAYMENT_PENALTY_RATE: u64 = 2; // 2% penalty per bulan keterlambatan
 /// Calculate total debt including principal, accrued interest, and late payment penalties
 /// Implementasi sesuai dengan production requirements untuk menghitung utang total
 pub fn calculate_total_debt_with_interest(loan: &Loan) -> Result<(u64, u64, u64, u64), String> {
-    let current_time = time();
-    
-    // Calculate time elapsed since loan creation
-    let time_elapsed = current_time.saturating_sub(loan.created_at);
-    
-    // Convert nanoseconds to years (365.25 days per year untuk akurasi)
-    let years = time_elapsed as f64 / (365.25 * 24.0 * 60.0 * 60.0 * 1_000_000_000.0);
-    
+    calculate_total_debt_at(loan, time())
+}
+
+/// Total debt as of `current_time`; used directly by forecasts to project future balances
+pub fn calculate_total_debt_at(loan: &Loan, current_time: u64) -> Result<(u64, u64, u64, u64), String> {
     let principal = loan.amount_approved;
-    let annual_rate = loan.apr as f64 / 100.0;
     
-    // Simple interest calculation: Interest = Principal * Rate * Time
-    // Sesuai dengan spesifikasi README untuk akumulasi bunga
-    let accrued_interest = (principal as f64 * annual_rate * years) as u64;
+    // Bunga mengikuti model akumulasi dari parameter protokol (simple, harian, atau bulanan);
+    // pinjaman variabel dihitung per periode suku bunga
+    let accrued_interest = accrue_interest(loan, current_time);
     
     // Calculate late payment penalty if loan is overdue
     // Implementasi sesuai dengan kebutuhan production untuk penalty keterlambatan
@@ -59,6 +55,249 @@ pub fn calculate_total_debt_with_interest(loan: &Loan) -> Result<(u64, u64, u64,
     Ok((principal, accrued_interest, late_penalty, total_debt))
 }
 
+// ========== VARIABLE RATE SUPPORT ==========
+
+const DEFAULT_VARIABLE_RATE_SLOPE: u64 = 10; // Poin persentase tambahan pada utilisasi 100%
+const DEFAULT_VARIABLE_RATE_MAX_STEP: u64 = 2; // Maksimum perubahan APR per reset (poin persentase)
+const MAX_RESETS_PER_LOAN_PER_RUN: usize = 12;
+
+/// Rate periods of a loan as (start time, APR) in chronological order
+pub fn rate_periods(loan: &Loan) -> Vec<(u64, u64)> {
+    match &loan.rate_type {
+        Some(RateType::Variable(terms)) => {
+            let mut periods = vec![(loan.created_at, terms.initial_apr)];
+            periods.extend(terms.resets.iter().map(|reset| (reset.effective_from, reset.new_apr)));
+            periods
+        },
+        _ => vec![(loan.created_at, loan.apr)],
+    }
+}
+
+/// Simple interest on `principal` between `start` and `end`, accrued separately in each rate period
+pub fn accrue_interest_piecewise(principal: u64, start: u64, end: u64, periods: &[(u64, u64)]) -> u64 {
+    accrue_interest_compounded(principal, start, end, periods, &CompoundingFrequency::Simple)
+}
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+const NANOS_PER_YEAR: u64 = 31_557_600 * 1_000_000_000; // 365.25 days
+// Fixed-point scales: balances carry 9 extra digits so per-period truncation stays below a satoshi
+const BALANCE_SCALE: u128 = 1_000_000_000;
+const RATE_SCALE: u128 = 1_000_000_000_000;
+
+/// Length of one compounding period; simple interest never compounds
+fn compounding_period_nanos(frequency: &CompoundingFrequency, start: u64, end: u64) -> u64 {
+    match frequency {
+        CompoundingFrequency::Simple => end.saturating_sub(start).max(1),
+        CompoundingFrequency::Daily => NANOS_PER_DAY,
+        CompoundingFrequency::Monthly => NANOS_PER_YEAR / 12,
+    }
+}
+
+/// Growth rate (scaled by RATE_SCALE) between `from` and `to`, weighting each APR by its overlap
+fn segment_rate_scaled(from: u64, to: u64, periods: &[(u64, u64)]) -> u128 {
+    let mut apr_nanos: u128 = 0;
+    for (i, (period_start, apr)) in periods.iter().enumerate() {
+        let period_end = periods.get(i + 1).map(|(next_start, _)| *next_start).unwrap_or(to);
+        let overlap_start = (*period_start).max(from);
+        let overlap_end = period_end.min(to);
+        if overlap_end > overlap_start {
+            apr_nanos += *apr as u128 * (overlap_end - overlap_start) as u128;
+        }
+    }
+    apr_nanos * RATE_SCALE / (100 * NANOS_PER_YEAR as u128)
+}
+
+/// Interest on `principal` between `start` and `end` under the given compounding model, accrued
+/// separately in each rate period. Integer fixed-point throughout, rounded half up to the satoshi.
+pub fn accrue_interest_compounded(
+    principal: u64,
+    start: u64,
+    end: u64,
+    periods: &[(u64, u64)],
+    frequency: &CompoundingFrequency,
+) -> u64 {
+    accrue_interest_scheduled(principal, start, end, periods, &[(0, frequency.clone())])
+}
+
+/// Interest on `principal` between `start` and `end` where each accrual model in `schedule`
+/// (activation time, model; oldest first, the first at or before `start`) applies only from its
+/// activation until the next. Simple spans earn on principal alone; compounding spans earn on
+/// principal plus everything accrued so far, in periods counted from the span's start.
+pub fn accrue_interest_scheduled(
+    principal: u64,
+    start: u64,
+    end: u64,
+    periods: &[(u64, u64)],
+    schedule: &[(u64, CompoundingFrequency)],
+) -> u64 {
+    if end <= start || principal == 0 {
+        return 0;
+    }
+    
+    let base = principal as u128 * BALANCE_SCALE;
+    let mut interest: u128 = 0;
+    for (i, (activated_at, frequency)) in schedule.iter().enumerate() {
+        let span_start = (*activated_at).max(start);
+        let span_end = schedule.get(i + 1).map(|(next, _)| *next).unwrap_or(end).min(end);
+        if span_end <= span_start {
+            continue;
+        }
+        
+        let step = compounding_period_nanos(frequency, span_start, span_end);
+        let mut from = span_start;
+        while from < span_end {
+            let to = from.saturating_add(step).min(span_end);
+            let earning = match frequency {
+                CompoundingFrequency::Simple => base,
+                CompoundingFrequency::Daily | CompoundingFrequency::Monthly => base + interest,
+            };
+            interest += earning * segment_rate_scaled(from, to, periods) / RATE_SCALE;
+            from = to;
+        }
+    }
+    
+    ((interest + BALANCE_SCALE / 2) / BALANCE_SCALE) as u64
+}
+
+/// Interest accrued on a loan from origination until `end`
+pub fn accrue_interest(loan: &Loan, end: u64) -> u64 {
+    let schedule = get_protocol_parameters().compounding_schedule();
+    accrue_interest_scheduled(loan.amount_approved, loan.created_at, end, &rate_periods(loan), &schedule)
+}
+
+/// Utilization model: base APR plus `slope` percentage points at full utilization
+pub fn variable_rate_target(base_apr: u64, utilization_bps: u64, slope: u64) -> u64 {
+    base_apr + (slope * utilization_bps.min(10_000)) / 10_000
+}
+
+/// Move from `current_apr` toward `target_apr` by at most `max_step` percentage points
+pub fn capped_rate_step(current_apr: u64, target_apr: u64, max_step: u64) -> u64 {
+    if target_apr > current_apr {
+        current_apr + (target_apr - current_apr).min(max_step)
+    } else {
+        current_apr - (current_apr - target_apr).min(max_step)
+    }
+}
+
+/// Reset dates still ahead of `now`, bounded by the due date
+pub fn upcoming_rate_resets(loan: &Loan, now: u64, limit: usize) -> Vec<u64> {
+    let terms = match &loan.rate_type {
+        Some(RateType::Variable(terms)) if terms.reset_interval_seconds > 0 => terms,
+        _ => return Vec::new(),
+    };
+    let interval = terms.reset_interval_seconds * 1_000_000_000;
+    let mut next = terms.next_reset_at;
+    while next <= now {
+        next += interval;
+    }
+    let mut dates = Vec::new();
+    while dates.len() < limit && loan.due_date.map_or(true, |due_date| next < due_date) {
+        dates.push(next);
+        next += interval;
+    }
+    dates
+}
+
+/// Reprice variable-rate loans whose reset date has passed. Called from the heartbeat.
+pub fn process_variable_rate_resets() -> Result<u64, String> {
+    let now = time();
+    let base_apr = get_protocol_parameters().base_apr;
+    let slope = crate::governance::get_protocol_parameter("variable_rate_slope".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_VARIABLE_RATE_SLOPE);
+    let max_step = crate::governance::get_protocol_parameter("variable_rate_max_step".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_VARIABLE_RATE_MAX_STEP);
+    let pool = get_liquidity_pool();
+    let utilization_bps = if pool.total_liquidity > 0 {
+        (pool.total_liquidity.saturating_sub(pool.available_liquidity) * 10_000) / pool.total_liquidity
+    } else {
+        0
+    };
+    let target_apr = variable_rate_target(base_apr, utilization_bps, slope);
+    
+    let mut reset_count = 0u64;
+    for mut loan in get_all_loans_data() {
+        if loan.status != LoanStatus::Active {
+            continue;
+        }
+        let mut terms = match loan.rate_type.clone() {
+            Some(RateType::Variable(terms)) if terms.reset_interval_seconds > 0 => terms,
+            _ => continue,
+        };
+        
+        let previous_apr = loan.apr;
+        let mut applied = 0;
+        // Catch up on missed resets one capped step at a time
+        while terms.next_reset_at <= now && applied < MAX_RESETS_PER_LOAN_PER_RUN {
+            let new_apr = capped_rate_step(loan.apr, target_apr, max_step);
+            terms.resets.push(RateResetRecord {
+                effective_from: terms.next_reset_at,
+                previous_apr: loan.apr,
+                new_apr,
+                target_apr,
+                utilization_bps,
+                processed_at: now,
+            });
+            loan.apr = new_apr;
+            terms.next_reset_at += terms.reset_interval_seconds * 1_000_000_000;
+            applied += 1;
+        }
+        if applied == 0 {
+            continue;
+        }
+        
+        loan.rate_type = Some(RateType::Variable(terms));
+        update_loan(loan.clone())?;
+        reset_count += applied as u64;
+        
+        log_audit_action(
+            loan.borrower,
+            "VARIABLE_RATE_RESET".to_string(),
+            format!(
+                "Loan #{} repriced from {}% to {}% ({} reset(s), utilization {} bps, model rate {}%)",
+                loan.id, previous_apr, loan.apr, applied, utilization_bps, target_apr
+            ),
+            true,
+        );
+    }
+    
+    Ok(reset_count)
+}
+
+/// Current repayment allocation order from governance, falling back to fees/interest/principal
+#[query]
+pub fn get_repayment_allocation_order() -> RepaymentAllocationOrder {
+    crate::governance::get_protocol_parameter("repayment_allocation_order".to_string())
+        .and_then(|param| RepaymentAllocationOrder::from_code(param.current_value))
+        .unwrap_or_default()
+}
+
+/// Apply a payment to each bucket in order, cascading what is left to the next one.
+/// Anything beyond all three buckets reduces principal. Returns (fees, interest, principal).
+pub fn allocate_payment(
+    payment: u64,
+    order: &RepaymentAllocationOrder,
+    remaining_fees: u64,
+    remaining_interest: u64,
+    remaining_principal: u64,
+) -> (u64, u64, u64) {
+    let mut left = payment;
+    let (mut fees, mut interest, mut principal) = (0, 0, 0);
+    
+    for bucket in &order.buckets {
+        let (applied, outstanding) = match bucket {
+            RepaymentBucket::Fees => (&mut fees, remaining_fees),
+            RepaymentBucket::Interest => (&mut interest, remaining_interest),
+            RepaymentBucket::Principal => (&mut principal, remaining_principal),
+        };
+        *applied = left.min(outstanding);
+        left -= *applied;
+    }
+    
+    (fees, interest, principal + left)
+}
+
 /// Enhanced payment breakdown calculation with detailed allocation
 pub fn calculate_payment_breakdown(
     loan: &Loan, 
@@ -102,15 +341,17 @@ pub fn calculate_payment_breakdown(
         std::cmp::min(already_paid, principal_outstanding)
     );
     
-    // Allocate payment
-    let penalty_payment = std::cmp::min(actual_payment, remaining_penalty);
-    let remaining_after_penalty = actual_payment.saturating_sub(penalty_payment);
-    
-    let interest_payment = std::cmp::min(remaining_after_penalty, remaining_interest);
-    let principal_payment = remaining_after_penalty.saturating_sub(interest_payment);
+    // Allocate payment in the configured order
+    let (penalty_payment, interest_payment, principal_payment) = allocate_payment(
+        actual_payment,
+        &get_repayment_allocation_order(),
+        remaining_penalty,
+        remaining_interest,
+        remaining_principal,
+    );
     
     // Calculate protocol fee (percentage of interest payment only)
-    let protocol_fee = (interest_payment * PROTOCOL_FEE_PERCENTAGE) / 100;
+    let protocol_fee = crate::helpers::rounded_share(interest_payment, PROTOCOL_FEE_PERCENTAGE, 100);
     
     Ok(PaymentBreakdown {
         principal_amount: principal_payment,
@@ -162,6 +403,9 @@ pub fn get_loan_repayment_summary(loan_id: u64) -> Result<LoanRepaymentSummary,
         next_payment_due: loan.due_date,
         is_overdue,
         days_overdue,
+        current_apr: loan.apr,
+        is_variable_rate: matches!(loan.rate_type, Some(RateType::Variable(_))),
+        upcoming_rate_resets: upcoming_rate_resets(&loan, current_time, 3),
     })
 }
 
@@ -189,6 +433,9 @@ pub fn get_repayment_plan(loan_id: u64) -> Result<RepaymentPlan, String> {
         protocol_fee: breakdown.protocol_fee_amount,
         due_date: loan.due_date.unwrap_or(time() + (30 * 24 * 60 * 60 * 1_000_000_000)), // Default 30 days if no due date
         minimum_payment: MINIMUM_PAYMENT_AMOUNT,
+        network_fee_charged: crate::liquidity_management::borrower_network_fee_charge(
+            &crate::liquidity_management::get_disbursement_records_by_loan(loan.id),
+        ),
     })
 }
 
@@ -198,11 +445,10 @@ pub fn get_repayment_plan(loan_id: u64) -> Result<RepaymentPlan, String> {
 #[update]
 pub async fn repay_loan(loan_id: u64, amount: u64) -> Result<RepaymentResponse, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "repay_loan")?;
     
     // 1. Validate input - Sesuai spesifikasi keamanan production
-    if amount == 0 {
-        return Err("Payment amount must be greater than zero".to_string());
-    }
+    crate::validation::require_positive(amount, "Payment amount")?;
     
     if amount < MINIMUM_PAYMENT_AMOUNT {
         return Err(format!("Payment amount must be at least {} satoshi", MINIMUM_PAYMENT_AMOUNT));
@@ -233,8 +479,21 @@ pub async fn repay_loan(loan_id: u64, amount: u64) -> Result<RepaymentResponse,
     let actual_payment = std::cmp::min(amount, remaining_debt);
     let payment_breakdown = calculate_payment_breakdown(&loan, actual_payment)?;
     
+    // 6b. Full repayment inside the penalty window owes the prepayment penalty on top of the debt
+    let prepayment_penalty = if actual_payment >= remaining_debt {
+        prepayment_penalty_for(&loan, time())?
+    } else {
+        0
+    };
+    if prepayment_penalty > 0 && amount < remaining_debt + prepayment_penalty {
+        return Err(format!(
+            "Full repayment requires {} satoshi, including a {} satoshi prepayment penalty",
+            remaining_debt + prepayment_penalty, prepayment_penalty
+        ));
+    }
+    
     // 7. Process ckBTC transfer - Panggilan Antar-Canister sesuai README
-    match crate::ckbtc_integration::process_ckbtc_repayment(loan_id, actual_payment).await {
+    match crate::ckbtc_integration::process_ckbtc_repayment(loan_id, actual_payment + prepayment_penalty).await {
         Ok(block_index) => {
             // 8. Update loan with payment information
             loan.total_repaid += actual_payment;
@@ -252,6 +511,8 @@ pub async fn repay_loan(loan_id: u64, amount: u64) -> Result<RepaymentResponse,
                     PaymentType::Interest
                 },
                 transaction_id: Some(block_index.to_string()),
+                allocation: Some(payment_breakdown.clone()),
+                source: Some(RepaymentSource::CkBtc),
             };
             
             loan.repayment_history.push(payment);
@@ -297,36 +558,17 @@ pub async fn repay_loan(loan_id: u64, amount: u64) -> Result<RepaymentResponse,
                 ckbtc_block_index: block_index,
                 timestamp: time(),
                 payment_breakdown: payment_breakdown.clone(),
+                source: Some(RepaymentSource::CkBtc),
             };
             
             store_repayment_record(repayment_record)?;
             
-            // 14. Send protocol fees to treasury - Panggilan Antar-Canister sesuai README
-            // "Panggil collect_fees di Canister_Kas_Protokol"
-            if payment_breakdown.protocol_fee_amount > 0 {
-                match crate::treasury_management::collect_fees(
-                    loan_id, 
-                    payment_breakdown.protocol_fee_amount,
-                    crate::types::RevenueType::ProtocolFee
-                ).await {
-                    Ok(_) => {
-                        log_audit_action(
-                            caller,
-                            "PROTOCOL_FEE_COLLECTED".to_string(),
-                            format!("Collected {} satoshi protocol fee from loan #{} repayment", 
-                                payment_breakdown.protocol_fee_amount, loan_id),
-                            true,
-                        );
-                    }
-                    Err(e) => {
-                        log_audit_action(
-                            caller,
-                            "PROTOCOL_FEE_COLLECTION_FAILED".to_string(),
-                            format!("Failed to collect protocol fee for loan #{}: {}", loan_id, e),
-                            false,
-                        );
-                    }
-                }
+            // 14. Send protocol fees to treasury, once per repayment
+            collect_latest_repayment_fee(&loan, caller);
+            
+            // 14b. Prepayment penalty goes to the insurance fund and treasury, not the pool
+            if prepayment_penalty > 0 {
+                collect_prepayment_penalty(loan_id, prepayment_penalty, caller);
             }
             
             // 15. Update liquidity pool
@@ -339,6 +581,9 @@ pub async fn repay_loan(loan_id: u64, amount: u64) -> Result<RepaymentResponse,
                 );
             }
             
+            // 15b. Drop the borrower's cached dashboards so the next read reflects this payment
+            crate::advanced_query_routing::invalidate_cache_for(caller);
+            
             // 16. Log successful repayment - Audit logging
             log_audit_action(
                 caller,
@@ -432,7 +677,7 @@ pub async fn repay_loan(loan_id: u64, amount: u64) -> Result<RepaymentResponse,
     }
 }
 
-/// Get payment history for a loan
+/// Get payment history for a loan, with the allocation of each payment where recorded
 #[query]
 pub fn get_loan_payment_history(loan_id: u64) -> Result<Vec<Payment>, String> {
     let caller = caller();
@@ -459,6 +704,173 @@ pub fn get_loan_repayment_records(loan_id: u64) -> Result<Vec<RepaymentRecord>,
     Ok(get_repayment_records_by_loan(loan_id))
 }
 
+/// Validate an operator-recorded off-chain repayment before any state changes
+pub fn validate_offchain_repayment(
+    amount: u64,
+    remaining_debt: u64,
+    external_ref: &str,
+    reason: &str,
+) -> Result<(), String> {
+    if reason.trim().is_empty() {
+        return Err("A reason is required for off-chain repayments".to_string());
+    }
+    if external_ref.trim().is_empty() {
+        return Err("An external reference is required for off-chain repayments".to_string());
+    }
+    if amount == 0 {
+        return Err("Payment amount must be greater than zero".to_string());
+    }
+    if remaining_debt == 0 {
+        return Err("Loan is already fully repaid".to_string());
+    }
+    if amount > remaining_debt {
+        return Err(format!(
+            "Off-chain repayment of {} exceeds the remaining balance of {}",
+            amount, remaining_debt
+        ));
+    }
+    Ok(())
+}
+
+/// Whether an off-chain payment with this external reference is already on the loan
+pub fn offchain_reference_recorded(loan: &Loan, external_ref: &str) -> bool {
+    loan.repayment_history.iter().any(|payment| {
+        payment.source == Some(RepaymentSource::OffChain)
+            && payment.transaction_id.as_deref() == Some(external_ref)
+    })
+}
+
+/// Record a repayment received outside ckBTC (bank transfer, cash at a cooperative) on the
+/// borrower's behalf (operator and above). The payment is recorded against the loan only: no
+/// ckBTC reached the canister, so pool liquidity and repayment records are left untouched and
+/// reconciliation excludes off-chain payments from the ledger-backed totals.
+/// Each external reference is recorded at most once per loan, so a replay cannot credit twice.
+#[update]
+pub fn admin_record_offchain_repayment(
+    loan_id: u64,
+    amount: u64,
+    external_ref: String,
+    reason: String,
+) -> Result<RepaymentResponse, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "admin_record_offchain_repayment")?;
+
+    let role = crate::governance::get_admin_role(caller);
+    match crate::liquidity_management::resolve_investor_data_tier(role.as_ref(), is_admin(&caller)) {
+        Some(InvestorDataAccessTier::Operator) | Some(InvestorDataAccessTier::Full) => {},
+        _ => return Err("Unauthorized: Operator access required to record off-chain repayments".to_string()),
+    }
+
+    let mut loan = get_loan(loan_id).ok_or("Loan not found")?;
+    if loan.status != LoanStatus::Active {
+        return Err(format!("Loan is not active for repayment. Current status: {:?}", loan.status));
+    }
+
+    let (_, _, _, total_debt) = calculate_total_debt_with_interest(&loan)?;
+    let remaining_debt = total_debt.saturating_sub(loan.total_repaid);
+    validate_offchain_repayment(amount, remaining_debt, &external_ref, &reason)?;
+
+    let external_ref = external_ref.trim().to_string();
+    let reason = reason.trim().to_string();
+    if offchain_reference_recorded(&loan, &external_ref) {
+        return Err(format!("Off-chain repayment {} is already recorded for loan #{}", external_ref, loan_id));
+    }
+    let payment_breakdown = calculate_payment_breakdown(&loan, amount)?;
+    let before_state = format!("status={:?}, total_repaid={}", loan.status, loan.total_repaid);
+    let now = time();
+
+    loan.total_repaid += amount;
+    loan.last_payment_date = Some(now);
+    loan.repayment_history.push(Payment {
+        amount,
+        timestamp: now,
+        payment_type: if payment_breakdown.principal_amount > 0 && payment_breakdown.interest_amount > 0 {
+            PaymentType::Mixed
+        } else if payment_breakdown.principal_amount > 0 {
+            PaymentType::Principal
+        } else {
+            PaymentType::Interest
+        },
+        transaction_id: Some(external_ref.clone()),
+        allocation: Some(payment_breakdown.clone()),
+        source: Some(RepaymentSource::OffChain),
+    });
+
+    let is_fully_repaid = loan.total_repaid >= total_debt;
+    if is_fully_repaid {
+        loan.status = LoanStatus::Repaid;
+    }
+
+    // The loan is the only record written, so nothing is left half-applied if it fails;
+    // collateral is released only once the repaid status is stored
+    store_loan(loan.clone())?;
+    let mut collateral_released = false;
+    if is_fully_repaid {
+        match unlock_nft(loan.nft_id) {
+            Ok(_) => collateral_released = true,
+            Err(e) => {
+                log_audit_action(
+                    caller,
+                    "COLLATERAL_RELEASE_FAILED".to_string(),
+                    format!("Failed to release NFT #{} for loan #{}: {}", loan.nft_id, loan_id, e),
+                    false,
+                );
+            }
+        }
+    }
+
+    let after_state = format!("status={:?}, total_repaid={}", loan.status, loan.total_repaid);
+
+    use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+    log_audit_enhanced(
+        AuditCategory::LoanLifecycle,
+        "OFFCHAIN_REPAYMENT_RECORDED".to_string(),
+        AuditEventLevel::Critical,
+        AuditDetails {
+            description: format!(
+                "Off-chain repayment of {} recorded for loan #{} by {}: {}",
+                amount, loan_id, caller.to_text(), reason
+            ),
+            entity_type: Some("loan".to_string()),
+            entity_id: Some(loan_id.to_string()),
+            before_state: Some(before_state),
+            after_state: Some(after_state),
+            affected_principals: vec![loan.borrower],
+            metadata: vec![
+                ("external_ref".to_string(), external_ref.clone()),
+                ("reason".to_string(), reason.clone()),
+                ("amount".to_string(), amount.to_string()),
+            ],
+            ..Default::default()
+        },
+        AuditResult {
+            success: true,
+            error_code: None,
+            error_message: None,
+            execution_time_ms: None,
+            gas_used: None,
+            cycles_consumed: None,
+            memory_used_bytes: None,
+            warning_flags: vec!["ACTED_ON_BEHALF_OF_BORROWER".to_string()],
+        },
+        None,
+    );
+
+    let remaining_balance = total_debt.saturating_sub(loan.total_repaid);
+    Ok(RepaymentResponse {
+        success: true,
+        message: if is_fully_repaid {
+            format!("Off-chain repayment recorded; loan #{} is fully repaid", loan_id)
+        } else {
+            format!("Off-chain repayment recorded. Remaining balance: {}", remaining_balance)
+        },
+        transaction_id: Some(external_ref),
+        new_loan_status: loan.status,
+        remaining_balance,
+        collateral_released,
+    })
+}
+
 /// Check if a loan is eligible for repayment
 #[query]
 pub fn check_repayment_eligibility(loan_id: u64) -> Result<bool, String> {
@@ -468,29 +880,313 @@ pub fn check_repayment_eligibility(loan_id: u64) -> Result<bool, String> {
         LoanStatus::Active => Ok(true),
         LoanStatus::Repaid => Err("Loan is already fully repaid".to_string()),
         LoanStatus::Defaulted => Err("Loan is in default status".to_string()),
+        LoanStatus::Liquidating => Err("Loan is being liquidated".to_string()),
         _ => Err("Loan is not eligible for repayment in current status".to_string()),
     }
 }
 
-/// Collect protocol fees and send to treasury
-/// Implementasi untuk Panggilan Antar-Canister ke Canister_Kas_Protokol
-pub async fn collect_protocol_fees_from_repayment(
-    loan_id: u64, 
-    fee_amount: u64
-) -> Result<String, String> {
-    // Implementation untuk mengirim protocol fee ke treasury
-    // Sesuai spek README: "panggil collect_fees di Canister_Kas_Protokol"
-    
-    if fee_amount == 0 {
-        return Ok("No fees to collect".to_string());
+// ========== PROTOCOL FEE COLLECTION ==========
+// Each ckBTC repayment's protocol fee is routed to the insurance fund and treasury exactly once.
+// A repayment is identified by its loan and its index in the loan's repayment history; a stored
+// collection record marks its fee as taken, so retries and sweeps skip it. Off-chain repayments
+// bring no ckBTC into the canister and carry no collectable fee. Fees from repayments made before
+// collections were recorded are matched to the revenue already booked for them by the v3 schema
+// migration; any left unmatched show up as uncollected until swept.
+
+const DEFAULT_FEE_SWEEP_LIMIT: u64 = 100;
+
+/// Protocol fee owed to the treasury by the payment at `payment_index`, if any
+pub fn repayment_fee_due(loan: &Loan, payment_index: usize) -> Option<u64> {
+    if loan.asset() != Asset::CkBtc {
+        return None;
     }
-    
-    // In production, this would call the treasury canister
-    // For now, we'll use the liquidity management integration
-    match crate::liquidity_management::collect_protocol_fees(loan_id, fee_amount).await {
-        Ok(result) => Ok(result),
-        Err(e) => Err(format!("Failed to collect protocol fees: {}", e))
+    let payment = loan.repayment_history.get(payment_index)?;
+    if payment.source == Some(RepaymentSource::OffChain) {
+        return None;
+    }
+    payment.allocation.as_ref()
+        .map(|allocation| allocation.protocol_fee_amount)
+        .filter(|fee| *fee > 0)
+}
+
+/// Fee-bearing repayments of `loans` not yet marked collected, oldest first
+pub fn uncollected_repayment_fees(
+    loans: &[Loan],
+    is_collected: impl Fn(u64, u64) -> bool,
+) -> Vec<UncollectedRepaymentFee> {
+    let mut uncollected: Vec<UncollectedRepaymentFee> = loans.iter()
+        .flat_map(|loan| {
+            (0..loan.repayment_history.len()).filter_map(|index| {
+                let fee_amount = repayment_fee_due(loan, index)?;
+                if is_collected(loan.id, index as u64) {
+                    return None;
+                }
+                let payment = &loan.repayment_history[index];
+                Some(UncollectedRepaymentFee {
+                    loan_id: loan.id,
+                    payment_index: index as u64,
+                    borrower: loan.borrower,
+                    fee_amount,
+                    paid_at: payment.timestamp,
+                    transaction_id: payment.transaction_id.clone(),
+                })
+            }).collect::<Vec<_>>()
+        })
+        .collect();
+    uncollected.sort_by_key(|fee| (fee.paid_at, fee.loan_id, fee.payment_index));
+    uncollected
+}
+
+/// Check each collection against the revenue entry it produced: the shares must add up to the
+/// fee, and the entry must be this loan's protocol fee revenue for the treasury share
+pub fn reconcile_fee_collections(
+    collections: &[RepaymentFeeCollection],
+    revenue_entry: impl Fn(u64) -> Option<RevenueEntry>,
+) -> FeeCollectionReconciliation {
+    let mut report = FeeCollectionReconciliation {
+        collections: collections.len() as u64,
+        total_collected: 0,
+        total_insurance_share: 0,
+        total_treasury_share: 0,
+        total_recorded_revenue: 0,
+        discrepancies: Vec::new(),
+        reconciled: false,
+    };
+    let mut seen_entries = std::collections::HashSet::new();
+    for collection in collections {
+        let reference = format!("loan #{} payment {}", collection.loan_id, collection.payment_index);
+        report.total_collected = report.total_collected.saturating_add(collection.fee_amount);
+        report.total_insurance_share = report.total_insurance_share.saturating_add(collection.insurance_share);
+        report.total_treasury_share = report.total_treasury_share.saturating_add(collection.treasury_share);
+
+        if collection.insurance_share.saturating_add(collection.treasury_share) != collection.fee_amount {
+            report.discrepancies.push(format!(
+                "{}: shares {} + {} do not add up to fee {}",
+                reference, collection.insurance_share, collection.treasury_share, collection.fee_amount
+            ));
+        }
+        if collection.treasury_share == 0 {
+            continue;
+        }
+        if !seen_entries.insert(collection.revenue_entry_id) {
+            report.discrepancies.push(format!("{}: revenue entry {} is claimed by another collection", reference, collection.revenue_entry_id));
+            continue;
+        }
+        match revenue_entry(collection.revenue_entry_id) {
+            None => report.discrepancies.push(format!("{}: revenue entry {} not found", reference, collection.revenue_entry_id)),
+            Some(entry) => {
+                report.total_recorded_revenue = report.total_recorded_revenue.saturating_add(entry.amount);
+                if entry.amount != collection.treasury_share
+                    || entry.source_loan_id != collection.loan_id
+                    || entry.revenue_type != RevenueType::ProtocolFee
+                {
+                    report.discrepancies.push(format!(
+                        "{}: revenue entry {} records {} {:?} for loan #{}, expected {} ProtocolFee",
+                        reference, entry.id, entry.amount, entry.revenue_type, entry.source_loan_id, collection.treasury_share
+                    ));
+                }
+            }
+        }
+    }
+    report.reconciled = report.discrepancies.is_empty()
+        && report.total_recorded_revenue == report.total_treasury_share;
+    report
+}
+
+fn log_fee_collection_event(
+    loan: &Loan,
+    payment_index: u64,
+    fee_amount: u64,
+    actor: Principal,
+    outcome: Result<&RepaymentFeeCollection, &String>,
+) {
+    use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+
+    let transaction_id = loan.repayment_history.get(payment_index as usize)
+        .and_then(|payment| payment.transaction_id.clone())
+        .unwrap_or_default();
+    let mut metadata = vec![
+        ("loan_id".to_string(), loan.id.to_string()),
+        ("payment_index".to_string(), payment_index.to_string()),
+        ("fee_amount".to_string(), fee_amount.to_string()),
+        ("transaction_id".to_string(), transaction_id),
+    ];
+    if let Ok(collection) = outcome {
+        metadata.push(("insurance_share".to_string(), collection.insurance_share.to_string()));
+        metadata.push(("treasury_share".to_string(), collection.treasury_share.to_string()));
+        metadata.push(("revenue_entry_id".to_string(), collection.revenue_entry_id.to_string()));
+    }
+    let error = outcome.err().cloned();
+    log_audit_enhanced(
+        AuditCategory::Treasury,
+        if error.is_none() { "PROTOCOL_FEE_COLLECTED" } else { "PROTOCOL_FEE_COLLECTION_FAILED" }.to_string(),
+        if error.is_none() { AuditEventLevel::Success } else { AuditEventLevel::Error },
+        AuditDetails {
+            description: format!(
+                "Protocol fee of {} satoshi from loan #{} payment {}{}",
+                fee_amount, loan.id, payment_index,
+                error.as_ref().map(|e| format!(" could not be collected: {}", e)).unwrap_or_else(|| " collected".to_string())
+            ),
+            entity_type: Some("repayment".to_string()),
+            entity_id: Some(repayment_fee_key(loan.id, payment_index)),
+            affected_principals: vec![loan.borrower],
+            metadata,
+            ..Default::default()
+        },
+        AuditResult {
+            success: error.is_none(),
+            error_code: error.as_ref().map(|_| "PROTOCOL_FEE_COLLECTION_FAILED".to_string()),
+            error_message: error,
+            execution_time_ms: None,
+            gas_used: None,
+            cycles_consumed: None,
+            memory_used_bytes: None,
+            warning_flags: vec![],
+        },
+        None,
+    );
+}
+
+/// Route the protocol fee of one repayment to the insurance fund and treasury. Returns the
+/// existing record if the fee was already collected, and `None` if the payment owes no fee.
+pub fn collect_repayment_fee(
+    loan: &Loan,
+    payment_index: u64,
+    collected_by: Principal,
+) -> Result<Option<RepaymentFeeCollection>, String> {
+    if let Some(existing) = get_repayment_fee_collection(loan.id, payment_index) {
+        return Ok(Some(existing));
+    }
+    let fee_amount = match repayment_fee_due(loan, payment_index as usize) {
+        Some(fee) => fee,
+        None => return Ok(None),
+    };
+
+    let routed = crate::treasury_management::route_protocol_fee_with_entry(loan.id, fee_amount);
+    let collection = match routed {
+        Ok((insurance_share, treasury_share, revenue_entry_id)) => RepaymentFeeCollection {
+            loan_id: loan.id,
+            payment_index,
+            fee_amount,
+            insurance_share,
+            treasury_share,
+            revenue_entry_id,
+            transaction_id: loan.repayment_history[payment_index as usize].transaction_id.clone(),
+            collected_at: time(),
+            collected_by,
+        },
+        Err(e) => {
+            log_fee_collection_event(loan, payment_index, fee_amount, collected_by, Err(&e));
+            return Err(e);
+        }
+    };
+    store_repayment_fee_collection(collection.clone());
+    log_fee_collection_event(loan, payment_index, fee_amount, collected_by, Ok(&collection));
+    Ok(Some(collection))
+}
+
+/// Collect the protocol fee of the latest payment on `loan`, logging rather than failing the
+/// repayment if it cannot be routed; a later sweep picks it up
+pub fn collect_latest_repayment_fee(loan: &Loan, collected_by: Principal) {
+    if let Some(payment_index) = loan.repayment_history.len().checked_sub(1) {
+        let _ = collect_repayment_fee(loan, payment_index as u64, collected_by);
+    }
+}
+
+/// Collect the protocol fee of a single repayment (admin only); a no-op if already collected
+#[update]
+pub fn collect_protocol_fees_from_repayment(loan_id: u64, payment_index: u64) -> Result<String, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "collect_protocol_fees_from_repayment")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can collect repayment fees".to_string());
+    }
+    let loan = get_loan(loan_id).ok_or("Loan not found")?;
+    if payment_index as usize >= loan.repayment_history.len() {
+        return Err(format!("Loan #{} has no payment {}", loan_id, payment_index));
+    }
+    let already_collected = get_repayment_fee_collection(loan_id, payment_index).is_some();
+    match collect_repayment_fee(&loan, payment_index, caller)? {
+        Some(collection) if already_collected => Ok(format!(
+            "Fee of {} satoshi from loan #{} payment {} was already collected",
+            collection.fee_amount, loan_id, payment_index
+        )),
+        Some(collection) => Ok(format!(
+            "Collected {} satoshi from loan #{} payment {} ({} to insurance, {} to treasury)",
+            collection.fee_amount, loan_id, payment_index, collection.insurance_share, collection.treasury_share
+        )),
+        None => Ok("No fees to collect".to_string()),
+    }
+}
+
+/// ckBTC repayments whose protocol fee has not been collected (admin only)
+#[query]
+pub fn get_uncollected_fees() -> Result<Vec<UncollectedRepaymentFee>, String> {
+    if !is_admin(&caller()) {
+        return Err("Unauthorized: Only admins can view uncollected fees".to_string());
+    }
+    Ok(uncollected_repayment_fees(&get_all_loans_data(), |loan_id, index| {
+        get_repayment_fee_collection(loan_id, index).is_some()
+    }))
+}
+
+/// Collect up to `limit` uncollected repayment fees, oldest first (admin only)
+#[update]
+pub fn collect_missing_fees(limit: Option<u64>) -> Result<FeeSweepResult, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "collect_missing_fees")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can sweep uncollected fees".to_string());
+    }
+    let loans = get_all_loans_data();
+    let loans_by_id: HashMap<u64, &Loan> = loans.iter().map(|loan| (loan.id, loan)).collect();
+    let uncollected = uncollected_repayment_fees(&loans, |loan_id, index| {
+        get_repayment_fee_collection(loan_id, index).is_some()
+    });
+    let limit = limit.unwrap_or(DEFAULT_FEE_SWEEP_LIMIT).max(1) as usize;
+
+    let mut result = FeeSweepResult {
+        collected: 0,
+        total_collected_amount: 0,
+        failed: Vec::new(),
+        remaining: uncollected.len().saturating_sub(limit) as u64,
+    };
+    for fee in uncollected.iter().take(limit) {
+        match collect_repayment_fee(loans_by_id[&fee.loan_id], fee.payment_index, caller) {
+            Ok(Some(collection)) => {
+                result.collected += 1;
+                result.total_collected_amount = result.total_collected_amount.saturating_add(collection.fee_amount);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                result.failed.push(format!("loan #{} payment {}: {}", fee.loan_id, fee.payment_index, e));
+                result.remaining += 1;
+            }
+        }
+    }
+
+    log_audit_action(
+        caller,
+        "PROTOCOL_FEE_SWEEP".to_string(),
+        format!(
+            "Collected {} missing repayment fee(s) totalling {} satoshi; {} failed, {} remaining",
+            result.collected, result.total_collected_amount, result.failed.len(), result.remaining
+        ),
+        result.failed.is_empty(),
+    );
+    Ok(result)
+}
+
+/// Collected repayment fees checked against the treasury revenue log (admin only)
+#[query]
+pub fn reconcile_collected_fees() -> Result<FeeCollectionReconciliation, String> {
+    if !is_admin(&caller()) {
+        return Err("Unauthorized: Only admins can reconcile fee collections".to_string());
     }
+    Ok(reconcile_fee_collections(
+        &get_all_repayment_fee_collections(),
+        crate::treasury_management::get_revenue_entry,
+    ))
 }
 
 /// Validate repayment amount and check daily limits
@@ -510,10 +1206,124 @@ pub fn validate_repayment_amount(caller: Principal, amount: u64) -> Result<(), S
     Ok(())
 }
 
+// ========== PREPAYMENT PENALTY ==========
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Penalty for repaying in full at `repaid_at`. Applies only inside the first
+/// `free_window_days` after `loan_start`; a zero rate or zero-day window never charges.
+pub fn calculate_prepayment_penalty(
+    remaining_principal: u64,
+    penalty_bps: u64,
+    free_window_days: u64,
+    loan_start: u64,
+    repaid_at: u64,
+) -> u64 {
+    if penalty_bps == 0 || free_window_days == 0 {
+        return 0;
+    }
+
+    let window_end = loan_start.saturating_add(free_window_days.saturating_mul(NANOS_PER_DAY));
+    if repaid_at >= window_end {
+        return 0;
+    }
+
+    ((remaining_principal as u128 * penalty_bps as u128) / 10_000) as u64
+}
+
+/// Principal repaid so far. Payments carry their principal share in `allocation`; older
+/// payments without one count in full, as they did before allocations were recorded.
+pub fn principal_repaid(loan: &Loan) -> u64 {
+    let repaid = loan.repayment_history.iter().fold(0u64, |acc, payment| {
+        acc.saturating_add(payment.allocation.as_ref().map_or(payment.amount, |a| a.principal_amount))
+    });
+    repaid.min(loan.amount_approved)
+}
+
+/// Prepayment penalty owed if `loan` were repaid in full at `at`, using current governance settings.
+/// Charged on outstanding principal only; interest already paid does not reduce the base.
+/// The treasury is ckBTC-denominated, so ICP loans carry no prepayment penalty.
+pub fn prepayment_penalty_for(loan: &Loan, at: u64) -> Result<u64, String> {
+    if loan.asset() != Asset::CkBtc {
+        return Ok(0);
+    }
+    let params = get_protocol_parameters();
+    let remaining_principal = loan.amount_approved.saturating_sub(principal_repaid(loan));
+
+    Ok(calculate_prepayment_penalty(
+        remaining_principal,
+        params.prepayment_penalty_bps(),
+        params.prepayment_free_window_days(),
+        loan.created_at,
+        at,
+    ))
+}
+
+/// Route a collected prepayment penalty to the insurance fund and treasury. The borrower has
+/// already paid it, so a routing failure parks it for `retry_unrouted_prepayment_penalties`
+/// instead of failing the repayment.
+pub fn collect_prepayment_penalty(loan_id: u64, penalty: u64, collected_by: Principal) {
+    match crate::treasury_management::route_prepayment_penalty(loan_id, penalty) {
+        Ok(_) => {
+            log_audit_action(
+                collected_by,
+                "PREPAYMENT_PENALTY_COLLECTED".to_string(),
+                format!("Collected {} satoshi prepayment penalty from loan #{}", penalty, loan_id),
+                true,
+            );
+        }
+        Err(e) => {
+            store_unrouted_prepayment_penalty(loan_id, penalty);
+            log_audit_action(
+                collected_by,
+                "PREPAYMENT_PENALTY_COLLECTION_FAILED".to_string(),
+                format!("Failed to route {} satoshi prepayment penalty for loan #{}, queued for retry: {}", penalty, loan_id, e),
+                false,
+            );
+        }
+    }
+}
+
+/// Route prepayment penalties whose first routing failed (admin only). Returns how many were routed.
+#[update]
+pub fn retry_unrouted_prepayment_penalties() -> Result<u64, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "retry_unrouted_prepayment_penalties")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can retry prepayment penalty routing".to_string());
+    }
+
+    let mut routed = 0u64;
+    for (loan_id, penalty) in get_unrouted_prepayment_penalties() {
+        match crate::treasury_management::route_prepayment_penalty(loan_id, penalty) {
+            Ok(_) => {
+                remove_unrouted_prepayment_penalty(loan_id);
+                routed += 1;
+            }
+            Err(e) => {
+                log_audit_action(
+                    caller,
+                    "PREPAYMENT_PENALTY_RETRY_FAILED".to_string(),
+                    format!("Prepayment penalty of {} satoshi for loan #{} still unrouted: {}", penalty, loan_id, e),
+                    false,
+                );
+            }
+        }
+    }
+
+    log_audit_action(
+        caller,
+        "PREPAYMENT_PENALTY_RETRY".to_string(),
+        format!("Routed {} queued prepayment penalties", routed),
+        true,
+    );
+    Ok(routed)
+}
+
 /// Calculate early repayment benefits (if any)
 /// Implementasi untuk memberikan diskon early repayment
 #[query]
-pub fn calculate_early_repayment_benefits(loan_id: u64) -> Result<u64, String> {
+pub fn calculate_early_repayment_benefits(loan_id: u64) -> Result<EarlyRepaymentBenefits, String> {
     let loan = get_loan(loan_id).ok_or("Loan not found")?;
     
     // Verify caller authority
@@ -524,8 +1334,10 @@ pub fn calculate_early_repayment_benefits(loan_id: u64) -> Result<u64, String> {
     
     // For early repayment, we might offer a small discount on interest
     // Implementasi sesuai dengan EARLY_REPAYMENT_DISCOUNT_RATE dan THRESHOLD
+    let current_time = time();
+    let prepayment_penalty = prepayment_penalty_for(&loan, current_time)?;
+    let mut interest_discount = 0u64;
     if let Some(due_date) = loan.due_date {
-        let current_time = time();
         if current_time < due_date {
             let time_remaining = due_date - current_time;
             let total_loan_duration = due_date - loan.created_at;
@@ -539,15 +1351,18 @@ pub fn calculate_early_repayment_benefits(loan_id: u64) -> Result<u64, String> {
                     let remaining_interest = accrued_interest.saturating_sub(
                         loan.total_repaid.saturating_sub(loan.amount_approved.min(loan.total_repaid))
                     );
-                    let discount = (remaining_interest * EARLY_REPAYMENT_DISCOUNT_RATE) / 100;
-                    
-                    return Ok(discount);
+                    interest_discount = (remaining_interest * EARLY_REPAYMENT_DISCOUNT_RATE) / 100;
                 }
             }
         }
     }
     
-    Ok(0) // No early repayment benefits
+    // Net may be negative: a penalty larger than the discount is a real cost to the borrower
+    Ok(EarlyRepaymentBenefits {
+        interest_discount,
+        prepayment_penalty,
+        net_benefit: interest_discount as i64 - prepayment_penalty as i64,
+    })
 }
 
 /// Emergency repayment function (admin only) - for special circumstances
@@ -558,6 +1373,7 @@ pub async fn emergency_repayment(
     reason: String
 ) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "emergency_repayment")?;
     
     // Verify admin access
     verify_admin_access()?;
@@ -579,6 +1395,8 @@ pub async fn emergency_repayment(
         timestamp: time(),
         payment_type: PaymentType::Mixed,
         transaction_id: Some(format!("EMERGENCY_PAYMENT_{}", time())),
+        allocation: None,
+        source: None,
     };
     
     loan.repayment_history.push(payment);
@@ -636,6 +1454,7 @@ pub fn get_comprehensive_repayment_analytics() -> Result<ComprehensiveRepaymentA
     let mut overdue_loans = 0u64;
     let mut total_overdue_amount = 0u64;
     let mut early_repayments = 0u64;
+    let rounding = crate::helpers::rounding_mode();
     
     for loan in &all_loans {
         total_principal_paid += std::cmp::min(loan.total_repaid, loan.amount_approved);
@@ -648,7 +1467,7 @@ pub fn get_comprehensive_repayment_analytics() -> Result<ComprehensiveRepaymentA
         for payment in &loan.repayment_history {
             // Estimate fee as 10% of interest portion
             if payment.amount > 0 {
-                total_fees_collected += (payment.amount * PROTOCOL_FEE_PERCENTAGE) / 100;
+                total_fees_collected += crate::helpers::rounded_share_with(payment.amount, PROTOCOL_FEE_PERCENTAGE, 100, rounding);
             }
         }
         
@@ -745,6 +1564,7 @@ pub async fn process_batch_repayments(
     repayment_requests: Vec<BatchRepaymentRequest>
 ) -> Result<Vec<BatchRepaymentResult>, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "process_batch_repayments")?;
     
     // Only admins dapat melakukan batch processing
     if !is_admin(&caller) {
@@ -853,6 +1673,7 @@ pub async fn schedule_automatic_repayment(
     frequency_days: u64
 ) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "schedule_automatic_repayment")?;
     let loan = get_loan(loan_id).ok_or("Loan not found")?;
     
     // Verify caller is the borrower
@@ -899,12 +1720,10 @@ pub fn get_repayment_forecast(loan_id: u64, months_ahead: u64) -> Result<Vec<Rep
     for month in 1..=months_ahead {
         let forecast_time = current_time + (month * month_in_nanoseconds);
         
-        // Create a temporary loan state for the forecast
-        let mut forecast_loan = loan.clone();
-        forecast_loan.created_at = current_time;
-        
-        if let Ok((_, accrued_interest, _, total_debt)) = calculate_total_debt_with_interest(&forecast_loan) {
+        // Project the balance with the same accrual model as the live debt calculation
+        if let Ok((_, accrued_interest, _, total_debt)) = calculate_total_debt_at(&loan, forecast_time) {
             let remaining_balance = total_debt.saturating_sub(loan.total_repaid);
+            let projected_prepayment_penalty = prepayment_penalty_for(&loan, forecast_time)?;
             
             forecasts.push(RepaymentForecast {
                 month,
@@ -916,6 +1735,7 @@ pub fn get_repayment_forecast(loan_id: u64, months_ahead: u64) -> Result<Vec<Rep
                     remaining_balance / (months_ahead - month + 1),
                     MINIMUM_PAYMENT_AMOUNT
                 ),
+                projected_prepayment_penalty,
             });
         }
     }
@@ -923,6 +1743,166 @@ pub fn get_repayment_forecast(loan_id: u64, months_ahead: u64) -> Result<Vec<Rep
     Ok(forecasts)
 }
 
+// ========== AMORTIZATION SCHEDULE ==========
+
+const NANOS_PER_MONTH: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+const DEFAULT_LOAN_TERM_NANOS: u64 = 365 * 24 * 60 * 60 * 1_000_000_000;
+
+/// Loans spanning more than one month amortize monthly; shorter loans are bullet
+pub fn loan_repayment_structure(loan: &Loan) -> RepaymentStructure {
+    let term = loan.due_date.unwrap_or(loan.created_at + DEFAULT_LOAN_TERM_NANOS).saturating_sub(loan.created_at);
+    if term > NANOS_PER_MONTH {
+        RepaymentStructure::FixedInstallment
+    } else {
+        RepaymentStructure::Bullet
+    }
+}
+
+/// Split `total` into `parts` equal amounts, remainder on the last part
+fn split_evenly(total: u64, parts: usize) -> Vec<u64> {
+    if parts == 0 {
+        return Vec::new();
+    }
+    let base = total / parts as u64;
+    let mut amounts = vec![base; parts];
+    amounts[parts - 1] += total - base * parts as u64;
+    amounts
+}
+
+/// Build the amortization table for a loan using the accrual model of
+/// `calculate_total_debt_with_interest` over the full term. Rows covered by
+/// actual payments are marked paid; the unpaid remainder is re-spread evenly
+/// over the outstanding installments.
+pub fn build_amortization_schedule(
+    loan: &Loan,
+    structure: &RepaymentStructure,
+    payments: &[Payment],
+    compounding: &[(u64, CompoundingFrequency)],
+) -> Vec<AmortizationRow> {
+    let start = loan.created_at;
+    let end = loan.due_date.unwrap_or(start + DEFAULT_LOAN_TERM_NANOS).max(start + 1);
+    let term = end - start;
+    
+    let principal = loan.amount_approved;
+    let gross_interest = accrue_interest_scheduled(principal, start, end, &[(start, loan.apr)], compounding);
+    let total_fee = crate::helpers::rounded_share(gross_interest, PROTOCOL_FEE_PERCENTAGE, 100);
+    let total_interest = gross_interest - total_fee;
+    
+    let installments = match structure {
+        RepaymentStructure::Bullet => 1,
+        RepaymentStructure::FixedInstallment => ((term + NANOS_PER_MONTH - 1) / NANOS_PER_MONTH).max(1) as usize,
+    };
+    
+    let due_dates: Vec<u64> = (1..=installments as u64)
+        .map(|i| start + (term as u128 * i as u128 / installments as u128) as u64)
+        .collect();
+    let scheduled_principal = split_evenly(principal, installments);
+    let scheduled_interest = split_evenly(total_interest, installments);
+    let scheduled_fee = split_evenly(total_fee, installments);
+    
+    // Running totals of actual payments, oldest first
+    let mut sorted_payments: Vec<&Payment> = payments.iter().collect();
+    sorted_payments.sort_by_key(|p| p.timestamp);
+    let mut running_paid = 0u64;
+    let payment_totals: Vec<(u64, u64)> = sorted_payments.iter()
+        .map(|p| {
+            running_paid += p.amount;
+            (running_paid, p.timestamp)
+        })
+        .collect();
+    let total_paid = running_paid;
+    
+    // An installment is paid once cumulative payments reach its cumulative scheduled amount
+    let mut scheduled_cumulative = 0u64;
+    let mut paid_rows: Vec<Option<u64>> = Vec::new();
+    for i in 0..installments {
+        scheduled_cumulative += scheduled_principal[i] + scheduled_interest[i] + scheduled_fee[i];
+        if total_paid < scheduled_cumulative {
+            break;
+        }
+        paid_rows.push(
+            payment_totals.iter()
+                .find(|(cumulative, _)| *cumulative >= scheduled_cumulative)
+                .map(|(_, timestamp)| *timestamp)
+        );
+    }
+    let paid_count = paid_rows.len();
+    
+    // Components settled by fully paid rows
+    let paid_principal: u64 = scheduled_principal[..paid_count].iter().sum();
+    let paid_interest: u64 = scheduled_interest[..paid_count].iter().sum();
+    let paid_fee: u64 = scheduled_fee[..paid_count].iter().sum();
+    let paid_scheduled = paid_principal + paid_interest + paid_fee;
+    
+    // A partial payment toward the next row settles interest and fee before principal
+    let mut excess = total_paid.saturating_sub(paid_scheduled);
+    let mut remaining_fee = total_fee - paid_fee;
+    let mut remaining_interest = total_interest - paid_interest;
+    let mut remaining_principal = principal - paid_principal;
+    for remaining in [&mut remaining_fee, &mut remaining_interest, &mut remaining_principal] {
+        let applied = excess.min(*remaining);
+        *remaining -= applied;
+        excess -= applied;
+    }
+    
+    let unpaid = installments - paid_count;
+    let projected_principal = split_evenly(remaining_principal, unpaid);
+    let projected_interest = split_evenly(remaining_interest, unpaid);
+    let projected_fee = split_evenly(remaining_fee, unpaid);
+    
+    let mut outstanding = principal;
+    (0..installments)
+        .map(|i| {
+            let (principal_portion, interest_portion, fee_portion, is_paid, paid_at) = if i < paid_count {
+                (scheduled_principal[i], scheduled_interest[i], scheduled_fee[i], true, paid_rows[i])
+            } else {
+                let j = i - paid_count;
+                (projected_principal[j], projected_interest[j], projected_fee[j], false, None)
+            };
+            if i == paid_count {
+                // Partial principal already paid toward the next installment
+                outstanding = remaining_principal;
+            }
+            outstanding = outstanding.saturating_sub(principal_portion);
+            AmortizationRow {
+                installment_no: (i + 1) as u32,
+                due_date: due_dates[i],
+                payment_amount: principal_portion + interest_portion + fee_portion,
+                principal_portion,
+                interest_portion,
+                fee_portion,
+                remaining_balance: outstanding,
+                is_paid,
+                paid_at,
+            }
+        })
+        .collect()
+}
+
+/// Get the full amortization schedule for a loan (borrower or admin)
+#[query]
+pub fn get_amortization_schedule(loan_id: u64) -> Result<Vec<AmortizationRow>, String> {
+    let loan = get_loan(loan_id).ok_or("Loan not found")?;
+    let caller = caller();
+    
+    if loan.borrower != caller && !is_admin(&caller) {
+        return Err("Unauthorized: Only borrower or admin can view the amortization schedule".to_string());
+    }
+    
+    if loan.amount_approved == 0 {
+        return Err("Loan has no approved amount to amortize".to_string());
+    }
+    
+    Ok(amortization_schedule_for(&loan))
+}
+
+/// Current amortization table for a loan under the live repayment structure and compounding
+pub fn amortization_schedule_for(loan: &Loan) -> Vec<AmortizationRow> {
+    let structure = loan_repayment_structure(loan);
+    let compounding = get_protocol_parameters().compounding_schedule();
+    build_amortization_schedule(loan, &structure, &loan.repayment_history, &compounding)
+}
+
 // Struct untuk forecasting
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct RepaymentForecast {
@@ -957,6 +1937,9 @@ mod tests {
             total_repaid: 0,
             repayment_history: Vec::new(),
             last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
         }
     }
     
@@ -1075,4 +2058,251 @@ mod tests {
         assert_eq!(metrics.repayment_rate, 80);
         assert_eq!(metrics.total_payments_made, 12);
     }
+    
+    #[test]
+    fn test_amortization_schedule_fixed_installment() {
+        let loan = create_test_loan();
+        let schedule = build_amortization_schedule(&loan, &RepaymentStructure::FixedInstallment, &[], &[(0, CompoundingFrequency::Simple)]);
+        
+        // 365-day term in 30-day months
+        assert_eq!(schedule.len(), 13);
+        assert_eq!(schedule.iter().map(|r| r.principal_portion).sum::<u64>(), loan.amount_approved);
+        assert_eq!(schedule.last().unwrap().remaining_balance, 0);
+        assert_eq!(schedule.last().unwrap().due_date, loan.due_date.unwrap());
+        assert!(schedule.iter().all(|r| !r.is_paid));
+        assert!(schedule.iter().all(|r| r.payment_amount == r.principal_portion + r.interest_portion + r.fee_portion));
+        
+        // Protocol fee is its share of gross interest
+        let interest: u64 = schedule.iter().map(|r| r.interest_portion).sum();
+        let fee: u64 = schedule.iter().map(|r| r.fee_portion).sum();
+        assert_eq!(fee, ((interest + fee) * PROTOCOL_FEE_PERCENTAGE) / 100);
+    }
+    
+    #[test]
+    fn test_amortization_schedule_bullet() {
+        let loan = create_test_loan();
+        let schedule = build_amortization_schedule(&loan, &RepaymentStructure::Bullet, &[], &[(0, CompoundingFrequency::Simple)]);
+        
+        assert_eq!(schedule.len(), 1);
+        assert_eq!(schedule[0].principal_portion, loan.amount_approved);
+        assert_eq!(schedule[0].remaining_balance, 0);
+        assert_eq!(schedule[0].due_date, loan.due_date.unwrap());
+    }
+    
+    #[test]
+    fn test_amortization_schedule_reflects_payments() {
+        let loan = create_test_loan();
+        let original = build_amortization_schedule(&loan, &RepaymentStructure::FixedInstallment, &[], &[(0, CompoundingFrequency::Simple)]);
+        let total_due: u64 = original.iter().map(|r| r.payment_amount).sum();
+        
+        // Two full installments plus a partial third
+        let partial = 1_000;
+        let payments = vec![
+            Payment { amount: original[0].payment_amount, timestamp: loan.created_at + 10, payment_type: PaymentType::Mixed, transaction_id: None, allocation: None, source: None },
+            Payment { amount: original[1].payment_amount + partial, timestamp: loan.created_at + 20, payment_type: PaymentType::Mixed, transaction_id: None, allocation: None, source: None },
+        ];
+        let schedule = build_amortization_schedule(&loan, &RepaymentStructure::FixedInstallment, &payments, &[(0, CompoundingFrequency::Simple)]);
+        
+        assert!(schedule[0].is_paid && schedule[1].is_paid);
+        assert_eq!(schedule[0].paid_at, Some(loan.created_at + 10));
+        assert_eq!(schedule[1].paid_at, Some(loan.created_at + 20));
+        assert!(!schedule[2].is_paid);
+        
+        // Projected remainder is recomputed from what is still owed
+        let paid: u64 = payments.iter().map(|p| p.amount).sum();
+        let projected: u64 = schedule.iter().filter(|r| !r.is_paid).map(|r| r.payment_amount).sum();
+        assert_eq!(projected, total_due - paid);
+        assert_eq!(schedule.last().unwrap().remaining_balance, 0);
+    }
+    
+    #[test]
+    fn test_compounding_frequency_over_one_year() {
+        let start = 1_000_000_000_000_000_000u64;
+        let end = start + NANOS_PER_YEAR;
+        let periods = [(start, 10)];
+        
+        // 10% APR on 10M satoshi for 365.25 days
+        let simple = accrue_interest_compounded(10_000_000, start, end, &periods, &[(0, CompoundingFrequency::Simple)]);
+        let daily = accrue_interest_compounded(10_000_000, start, end, &periods, &CompoundingFrequency::Daily);
+        let monthly = accrue_interest_compounded(10_000_000, start, end, &periods, &CompoundingFrequency::Monthly);
+        
+        assert_eq!(simple, 1_000_000);
+        // 10M * ((1 + 0.1/365.25)^365.25 - 1) = 1_051_557.9
+        assert!((daily as i64 - 1_051_558).abs() <= 1);
+        // 10M * ((1 + 0.1/12)^12 - 1) = 1_047_130.7
+        assert!((monthly as i64 - 1_047_131).abs() <= 1);
+        assert!(simple < monthly && monthly < daily);
+        
+        // Within a single compounding period every model matches simple interest
+        let half_day = start + NANOS_PER_DAY / 2;
+        assert_eq!(
+            accrue_interest_compounded(10_000_000, start, half_day, &periods, &CompoundingFrequency::Daily),
+            accrue_interest_compounded(10_000_000, start, half_day, &periods, &CompoundingFrequency::Simple)
+        );
+    }
+    
+    #[test]
+    fn test_compounding_applies_from_activation() {
+        let start = 1_000_000_000_000_000_000u64;
+        let mid = start + NANOS_PER_YEAR / 2;
+        let end = start + NANOS_PER_YEAR;
+        let periods = [(start, 10)];
+        let switched = [(0, CompoundingFrequency::Simple), (mid, CompoundingFrequency::Daily)];
+        
+        // Interest up to the switch is untouched by it
+        assert_eq!(accrue_interest_scheduled(10_000_000, start, mid, &periods, &switched), 500_000);
+        // Afterwards the 10.5M balance compounds daily: 500_000 + 10.5M * ((1 + 0.1/365.25)^182.625 - 1)
+        let total = accrue_interest_scheduled(10_000_000, start, end, &periods, &switched);
+        assert!((total as i64 - 1_038_271).abs() <= 2, "{}", total);
+        
+        // A switch after the loan closes changes nothing; loans opened after it compound throughout
+        let later = [(0, CompoundingFrequency::Simple), (end, CompoundingFrequency::Daily)];
+        assert_eq!(accrue_interest_scheduled(10_000_000, start, end, &periods, &later), 1_000_000);
+        let earlier = [(0, CompoundingFrequency::Simple), (start, CompoundingFrequency::Daily)];
+        assert_eq!(
+            accrue_interest_scheduled(10_000_000, start, end, &periods, &earlier),
+            accrue_interest_compounded(10_000_000, start, end, &periods, &CompoundingFrequency::Daily)
+        );
+    }
+    
+    #[test]
+    fn test_variable_rate_accrues_piecewise() {
+        let year = 365.25 * 24.0 * 60.0 * 60.0 * 1_000_000_000.0;
+        let half_year = (year / 2.0) as u64;
+        let start = 1_000_000_000_000_000_000u64;
+        
+        // Half a year at 10% then half a year at 14% on 10M principal
+        let periods = vec![(start, 10), (start + half_year, 14)];
+        let interest = accrue_interest_piecewise(10_000_000, start, start + 2 * half_year, &periods);
+        assert!((interest as i64 - 1_200_000).abs() <= 1);
+        
+        // A single period matches the flat simple-interest formula
+        let flat = accrue_interest_piecewise(10_000_000, start, start + half_year, &[(start, 10)]);
+        assert!((flat as i64 - 500_000).abs() <= 1);
+        
+        // Rate movement is capped per reset in both directions
+        assert_eq!(variable_rate_target(10, 8_000, 10), 18);
+        assert_eq!(capped_rate_step(10, 18, 2), 12);
+        assert_eq!(capped_rate_step(12, 11, 2), 11);
+        assert_eq!(capped_rate_step(15, 10, 2), 13);
+        
+        let mut loan = create_test_loan();
+        let day = 24 * 60 * 60 * 1_000_000_000u64;
+        loan.rate_type = Some(RateType::Variable(VariableRateTerms {
+            initial_apr: 10,
+            reset_interval_seconds: 90 * 24 * 60 * 60,
+            next_reset_at: loan.created_at + 90 * day,
+            resets: vec![],
+        }));
+        let resets = upcoming_rate_resets(&loan, loan.created_at + 100 * day, 3);
+        assert_eq!(resets, vec![loan.created_at + 180 * day, loan.created_at + 270 * day, loan.created_at + 360 * day]);
+    }
+    
+    #[test]
+    fn test_prepayment_penalty_window() {
+        let day = 24 * 60 * 60 * 1_000_000_000u64;
+        let start = 1_000_000_000_000_000_000u64;
+        
+        // 2% of 10M outstanding principal inside a 90-day window
+        assert_eq!(calculate_prepayment_penalty(10_000_000, 200, 90, start, start + 30 * day), 200_000);
+        assert_eq!(calculate_prepayment_penalty(10_000_000, 200, 90, start, start + 90 * day - 1), 200_000);
+        
+        // No penalty once the window has passed
+        assert_eq!(calculate_prepayment_penalty(10_000_000, 200, 90, start, start + 90 * day), 0);
+        
+        // Zero rate or zero-day window preserves the penalty-free behavior
+        assert_eq!(calculate_prepayment_penalty(10_000_000, 0, 90, start, start + day), 0);
+        assert_eq!(calculate_prepayment_penalty(10_000_000, 200, 0, start, start + day), 0);
+        
+        // Large principals do not overflow
+        assert_eq!(calculate_prepayment_penalty(u64::MAX, 500, 90, start, start), u64::MAX / 20);
+    }
+    
+    #[test]
+    fn test_principal_repaid_excludes_interest() {
+        let mut loan = create_test_loan();
+        let paid_at = loan.created_at;
+        let payment = |amount: u64, principal: Option<u64>| Payment {
+            amount,
+            timestamp: paid_at,
+            payment_type: PaymentType::Mixed,
+            transaction_id: None,
+            allocation: principal.map(|principal_amount| PaymentBreakdown {
+                principal_amount,
+                interest_amount: amount - principal_amount,
+                protocol_fee_amount: 0,
+                penalty_amount: 0,
+                total_amount: amount,
+            }),
+            source: None,
+        };
+        
+        // Only the principal share of allocated payments counts; older payments count in full
+        loan.repayment_history = vec![payment(1_000, Some(400)), payment(500, None)];
+        assert_eq!(principal_repaid(&loan), 900);
+        
+        // Never more than the approved amount
+        loan.repayment_history.push(payment(u64::MAX, Some(u64::MAX)));
+        assert_eq!(principal_repaid(&loan), loan.amount_approved);
+    }
+    
+    #[test]
+    fn test_allocation_partially_covers_first_bucket() {
+        let default_order = RepaymentAllocationOrder::default();
+        assert_eq!(default_order.buckets, vec![RepaymentBucket::Fees, RepaymentBucket::Interest, RepaymentBucket::Principal]);
+        
+        // 300 against 500 of fees leaves interest and principal untouched
+        assert_eq!(allocate_payment(300, &default_order, 500, 1_000, 10_000), (300, 0, 0));
+        
+        // Interest-first sends the same payment to interest instead
+        let interest_first = RepaymentAllocationOrder::from_code(2).unwrap();
+        assert_eq!(allocate_payment(300, &interest_first, 500, 1_000, 10_000), (0, 300, 0));
+    }
+    
+    #[test]
+    fn test_allocation_overflows_all_buckets() {
+        let principal_first = RepaymentAllocationOrder::from_code(4).unwrap();
+        
+        // Each bucket is filled in order; the excess reduces principal
+        assert_eq!(allocate_payment(12_000, &principal_first, 500, 1_000, 10_000), (500, 1_000, 10_500));
+        assert_eq!(allocate_payment(10_200, &principal_first, 500, 1_000, 10_000), (200, 0, 10_000));
+        
+        // Unknown codes are rejected so governance cannot select an invalid order
+        assert!(RepaymentAllocationOrder::from_code(6).is_none());
+    }
+    
+    #[test]
+    fn test_offchain_repayment_validation() {
+        assert!(validate_offchain_repayment(5_000, 10_000, "BANK-TRX-001", "Paid at cooperative branch").is_ok());
+        // Settling the exact remaining balance is allowed
+        assert!(validate_offchain_repayment(10_000, 10_000, "BANK-TRX-001", "Final installment").is_ok());
+        
+        // Reason and external reference are mandatory, whitespace does not count
+        assert!(validate_offchain_repayment(5_000, 10_000, "BANK-TRX-001", "  ").is_err());
+        assert!(validate_offchain_repayment(5_000, 10_000, "", "Paid at cooperative branch").is_err());
+        
+        // Zero, overpayment and already-settled loans are rejected
+        assert!(validate_offchain_repayment(0, 10_000, "BANK-TRX-001", "reason").is_err());
+        assert!(validate_offchain_repayment(10_001, 10_000, "BANK-TRX-001", "reason").is_err());
+        assert!(validate_offchain_repayment(1, 0, "BANK-TRX-001", "reason").is_err());
+    }
+    
+    #[test]
+    fn test_offchain_reference_recorded_once() {
+        let mut loan = create_test_loan();
+        let payment = |transaction_id: &str, source: RepaymentSource| Payment {
+            amount: 5_000,
+            timestamp: 0,
+            payment_type: PaymentType::Mixed,
+            transaction_id: Some(transaction_id.to_string()),
+            allocation: None,
+            source: Some(source),
+        };
+        loan.repayment_history = vec![payment("BANK-TRX-001", RepaymentSource::OffChain), payment("42", RepaymentSource::CkBtc)];
+        
+        assert!(offchain_reference_recorded(&loan, "BANK-TRX-001"));
+        assert!(!offchain_reference_recorded(&loan, "BANK-TRX-002"));
+        // A ckBTC block index never blocks an off-chain reference
+        assert!(!offchain_reference_recorded(&loan, "42"));
+    }
 }
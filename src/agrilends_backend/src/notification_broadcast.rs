@@ -0,0 +1,219 @@
+// ========== NOTIFICATION BROADCAST MODULE ==========
+// Operator announcements (maintenance windows, rate changes, harvest-season guidance) sent to
+// a targeted audience. Recipients are resolved when the broadcast is created and each gets an
+// ordinary notification, so language templates, opt-outs and quiet hours apply per user. The
+// first batch goes out with the call; the heartbeat delivers the rest in bounded batches,
+// reading only the recipients of the batch it is sending.
+
+use candid::Principal;
+use ic_cdk::api::time;
+use ic_cdk_macros::{query, update};
+use std::collections::{HashMap, HashSet};
+
+use crate::types::*;
+use crate::storage::{
+    get_all_loans_data, next_broadcast_id, store_notification_broadcast, get_recent_notification_broadcasts,
+    next_in_progress_broadcast, store_broadcast_recipients, get_broadcast_recipients, remove_broadcast_recipients,
+};
+use crate::helpers::{is_admin, log_audit_action};
+use crate::user_management::{get_active_users, Role};
+use crate::notification_system::{create_notification, NotificationEvent};
+
+pub const BROADCAST_EVENT_TYPE: &str = "operator_broadcast";
+pub const BROADCAST_BATCH_SIZE: usize = 50;
+pub const MAX_BROADCAST_TITLE_LENGTH: usize = 100;
+pub const MAX_BROADCAST_BODY_LENGTH: usize = 1000;
+pub const MAX_BROADCAST_PRINCIPALS: usize = 5000;
+const MAX_BROADCAST_HISTORY: usize = 100;
+
+/// Operator tier and above, matching investor data access
+fn ensure_operator(caller: &Principal) -> Result<(), String> {
+    let role = crate::governance::get_admin_role(*caller);
+    match crate::liquidity_management::resolve_investor_data_tier(role.as_ref(), is_admin(caller)) {
+        Some(InvestorDataAccessTier::Operator) | Some(InvestorDataAccessTier::Full) => Ok(()),
+        _ => Err("Unauthorized: Operator access required to broadcast notifications".to_string()),
+    }
+}
+
+/// Trimmed title and body. Template placeholders are refused so the text renders verbatim.
+pub fn validate_broadcast_content(title: &str, body: &str) -> Result<(String, String), String> {
+    let title = title.trim();
+    let body = body.trim();
+    if title.is_empty() || body.is_empty() {
+        return Err("Broadcast title and body cannot be empty".to_string());
+    }
+    if title.len() > MAX_BROADCAST_TITLE_LENGTH {
+        return Err(format!("Broadcast title cannot exceed {} characters", MAX_BROADCAST_TITLE_LENGTH));
+    }
+    if body.len() > MAX_BROADCAST_BODY_LENGTH {
+        return Err(format!("Broadcast body cannot exceed {} characters", MAX_BROADCAST_BODY_LENGTH));
+    }
+    if title.contains("{{") || body.contains("{{") {
+        return Err("Broadcast text cannot contain template placeholders".to_string());
+    }
+    Ok((title.to_string(), body.to_string()))
+}
+
+/// Drop duplicates and the anonymous principal, keeping first-seen order
+pub fn dedup_recipients(principals: impl IntoIterator<Item = Principal>) -> Vec<Principal> {
+    let mut seen = HashSet::new();
+    principals.into_iter()
+        .filter(|principal| *principal != Principal::anonymous() && seen.insert(*principal))
+        .collect()
+}
+
+/// Borrowers with an active loan past its due date at `now`
+pub fn overdue_borrowers(loans: &[Loan], now: u64) -> Vec<Principal> {
+    dedup_recipients(
+        loans.iter()
+            .filter(|loan| loan.status == LoanStatus::Active && loan.due_date.map_or(false, |due_date| now > due_date))
+            .map(|loan| loan.borrower),
+    )
+}
+
+fn resolve_audience(audience: &BroadcastAudience) -> Vec<Principal> {
+    let active_users = |role: Option<Role>| {
+        get_active_users()
+            .into_iter()
+            .filter(|user| role.as_ref().map_or(true, |role| user.role == *role))
+            .map(|user| user.id)
+            .collect::<Vec<_>>()
+    };
+    match audience {
+        BroadcastAudience::AllFarmers => dedup_recipients(active_users(Some(Role::Farmer))),
+        BroadcastAudience::AllInvestors => dedup_recipients(active_users(Some(Role::Investor))),
+        BroadcastAudience::All => dedup_recipients(active_users(None)),
+        BroadcastAudience::Principals(principals) => dedup_recipients(principals.iter().copied()),
+        BroadcastAudience::OverdueBorrowers => overdue_borrowers(&get_all_loans_data(), time()),
+    }
+}
+
+/// Short audience description for audit entries
+pub fn audience_label(audience: &BroadcastAudience) -> String {
+    match audience {
+        BroadcastAudience::AllFarmers => "all farmers".to_string(),
+        BroadcastAudience::AllInvestors => "all investors".to_string(),
+        BroadcastAudience::Principals(principals) => format!("{} listed principal(s)", principals.len()),
+        BroadcastAudience::OverdueBorrowers => "overdue borrowers".to_string(),
+        BroadcastAudience::All => "all users".to_string(),
+    }
+}
+
+/// Range of recipient indexes the next batch covers
+pub fn next_broadcast_batch(broadcast: &NotificationBroadcast, limit: usize) -> std::ops::Range<usize> {
+    let count = broadcast.recipient_count as usize;
+    let start = (broadcast.next_index as usize).min(count);
+    start..(start + limit).min(count)
+}
+
+/// Send up to `limit` pending notifications of `broadcast`; returns how many were attempted
+fn deliver_broadcast_batch(broadcast: &mut NotificationBroadcast, limit: usize) -> usize {
+    let batch = next_broadcast_batch(broadcast, limit);
+    let recipients = get_broadcast_recipients(broadcast.id, batch.start as u64, batch.end as u64);
+    let attempted = batch.len();
+    for recipient in recipients {
+        let mut data = HashMap::new();
+        data.insert("title".to_string(), broadcast.title.clone());
+        data.insert("message".to_string(), broadcast.body.clone());
+        data.insert("broadcast_id".to_string(), broadcast.id.to_string());
+        let event = NotificationEvent::Custom { event_type: BROADCAST_EVENT_TYPE.to_string(), data };
+        // Ok(0) means the recipient's settings suppressed it
+        match create_notification(recipient, event, None, None) {
+            Ok(id) if id > 0 => broadcast.delivered += 1,
+            _ => broadcast.skipped += 1,
+        }
+    }
+    broadcast.next_index += attempted as u64;
+    if broadcast.next_index >= broadcast.recipient_count {
+        broadcast.status = BroadcastStatus::Completed;
+        broadcast.completed_at = Some(time());
+        remove_broadcast_recipients(broadcast.id);
+    }
+    attempted
+}
+
+/// Send an announcement to `audience` (operator tier and above). The first batch is
+/// delivered now; larger audiences finish over the following heartbeats.
+#[update]
+pub fn broadcast_notification(audience: BroadcastAudience, title: String, body: String) -> Result<NotificationBroadcast, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "broadcast_notification")?;
+    ensure_operator(&caller)?;
+    let (title, body) = validate_broadcast_content(&title, &body)?;
+    if let BroadcastAudience::Principals(principals) = &audience {
+        if principals.is_empty() || principals.len() > MAX_BROADCAST_PRINCIPALS {
+            return Err(format!("Provide between 1 and {} principals", MAX_BROADCAST_PRINCIPALS));
+        }
+    }
+
+    let recipients = resolve_audience(&audience);
+    if recipients.is_empty() {
+        return Err("The audience has no recipients".to_string());
+    }
+
+    let now = time();
+    let mut broadcast = NotificationBroadcast {
+        id: next_broadcast_id(),
+        audience,
+        title,
+        body,
+        created_by: caller,
+        created_at: now,
+        recipient_count: recipients.len() as u64,
+        next_index: 0,
+        delivered: 0,
+        skipped: 0,
+        status: BroadcastStatus::InProgress,
+        completed_at: None,
+    };
+    log_audit_action(
+        caller,
+        "NOTIFICATION_BROADCAST_CREATED".to_string(),
+        format!(
+            "Broadcast #{} \"{}\" to {} resolved to {} recipient(s)",
+            broadcast.id, broadcast.title, audience_label(&broadcast.audience), broadcast.recipient_count
+        ),
+        true,
+    );
+    store_broadcast_recipients(broadcast.id, &recipients);
+
+    deliver_broadcast_batch(&mut broadcast, BROADCAST_BATCH_SIZE);
+    store_notification_broadcast(broadcast.clone());
+    Ok(broadcast)
+}
+
+/// Most recent broadcasts first (operator tier and above)
+#[query]
+pub fn get_broadcast_history() -> Result<Vec<NotificationBroadcast>, String> {
+    ensure_operator(&ic_cdk::caller())?;
+    Ok(get_recent_notification_broadcasts(MAX_BROADCAST_HISTORY))
+}
+
+/// Heartbeat task: continue unfinished broadcasts, at most `BROADCAST_BATCH_SIZE` notifications per run
+pub async fn broadcast_delivery_task() -> Result<String, String> {
+    let mut remaining = BROADCAST_BATCH_SIZE;
+    let mut sent = 0usize;
+    let mut after = 0u64;
+    while remaining > 0 {
+        let Some(mut broadcast) = next_in_progress_broadcast(after) else {
+            break;
+        };
+        after = broadcast.id;
+        let attempted = deliver_broadcast_batch(&mut broadcast, remaining);
+        remaining -= attempted;
+        sent += attempted;
+        if broadcast.status == BroadcastStatus::Completed {
+            log_audit_action(
+                ic_cdk::id(),
+                "NOTIFICATION_BROADCAST_COMPLETED".to_string(),
+                format!(
+                    "Broadcast #{} finished: {} delivered, {} skipped",
+                    broadcast.id, broadcast.delivered, broadcast.skipped
+                ),
+                true,
+            );
+        }
+        store_notification_broadcast(broadcast);
+    }
+    Ok(format!("Broadcast delivery: {} notification(s) processed", sent))
+}
@@ -13,7 +13,7 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 
 use crate::types::*;
-use crate::storage::{get_memory_by_id, log_audit_action};
+use crate::storage::{get_memory_by_id, log_audit_action, bump_state_version};
 use crate::helpers::{is_admin, get_canister_config};
 use crate::audit_logging::log_audit_action as audit_log;
 
@@ -127,15 +127,22 @@ pub struct NotificationRecord {
     pub last_retry_at: Option<u64>,
 }
 
-// Notification template for consistent messaging
+// Notification template for consistent messaging, keyed by (event_type, language)
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct NotificationTemplate {
     pub event_type: String,
+    pub language: Option<String>, // e.g. "id", "en"; None (templates stored before localization) means English
     pub title_template: String,
     pub message_template: String,
     pub default_priority: NotificationPriority,
     pub default_channels: Vec<NotificationChannel>,
-    pub variables: Vec<String>, // Template variables like {loan_id}, {amount}
+    pub variables: Vec<String>, // Template variables like {{loan_id}}, {{amount}}
+}
+
+impl NotificationTemplate {
+    pub fn language(&self) -> String {
+        self.language.clone().unwrap_or_else(|| FALLBACK_TEMPLATE_LANGUAGE.to_string())
+    }
 }
 
 // User notification preferences
@@ -262,8 +269,9 @@ pub fn create_notification(
         current
     });
     
-    // Create template-based message
-    let (title, message) = generate_notification_content(&event, custom_message)?;
+    // Create template-based message in the recipient's language
+    let language = crate::user_management::get_user_preferred_language(&recipient);
+    let (title, message) = generate_notification_content(&event, custom_message, &language)?;
     
     // Determine priority
     let priority = custom_priority.unwrap_or_else(|| get_priority_from_event(&event));
@@ -313,6 +321,7 @@ pub fn create_notification(
     
     // Update statistics
     update_notification_stats(&notification, "created");
+    bump_state_version(StateDomain::Notifications);
     
     // Log audit trail
     log_audit_action(
@@ -427,6 +436,7 @@ pub fn get_unread_notifications_count() -> Result<u64, String> {
 #[update]
 pub fn mark_notification_as_read(notification_id: u64) -> Result<(), String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "mark_notification_as_read")?;
     
     NOTIFICATIONS.with(|notifications| {
         let mut map = notifications.borrow_mut();
@@ -444,6 +454,7 @@ pub fn mark_notification_as_read(notification_id: u64) -> Result<(), String> {
             notification.read_at = Some(time());
             
             map.insert(notification_id, notification.clone());
+            bump_state_version(StateDomain::Notifications);
             
             // Update statistics
             update_notification_stats(&notification, "read");
@@ -466,6 +477,7 @@ pub fn mark_notification_as_read(notification_id: u64) -> Result<(), String> {
 #[update]
 pub fn mark_notifications_as_read(notification_ids: Vec<u64>) -> Result<u64, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "mark_notifications_as_read")?;
     let mut marked_count = 0u64;
     
     for notification_id in notification_ids {
@@ -487,6 +499,7 @@ pub fn mark_notifications_as_read(notification_ids: Vec<u64>) -> Result<u64, Str
 #[update]
 pub fn mark_all_notifications_as_read() -> Result<u64, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "mark_all_notifications_as_read")?;
     
     USER_NOTIFICATIONS.with(|user_notifications| {
         let user_notifs = user_notifications.borrow().get(&caller).unwrap_or_default();
@@ -508,6 +521,10 @@ pub fn mark_all_notifications_as_read() -> Result<u64, String> {
                 }
             }
             
+            if marked_count > 0 {
+                bump_state_version(StateDomain::Notifications);
+            }
+            
             log_audit_action(
                 caller,
                 "all_notifications_read".to_string(),
@@ -523,6 +540,7 @@ pub fn mark_all_notifications_as_read() -> Result<u64, String> {
 #[update]
 pub fn acknowledge_notification(notification_id: u64) -> Result<(), String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "acknowledge_notification")?;
     
     NOTIFICATIONS.with(|notifications| {
         let mut map = notifications.borrow_mut();
@@ -541,6 +559,7 @@ pub fn acknowledge_notification(notification_id: u64) -> Result<(), String> {
             notification.acknowledged_at = Some(time());
             
             map.insert(notification_id, notification.clone());
+            bump_state_version(StateDomain::Notifications);
             
             // Update statistics
             update_notification_stats(&notification, "acknowledged");
@@ -563,6 +582,7 @@ pub fn acknowledge_notification(notification_id: u64) -> Result<(), String> {
 #[update]
 pub fn delete_notification(notification_id: u64) -> Result<(), String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "delete_notification")?;
     
     NOTIFICATIONS.with(|notifications| {
         let mut map = notifications.borrow_mut();
@@ -574,6 +594,7 @@ pub fn delete_notification(notification_id: u64) -> Result<(), String> {
             
             // Remove from notifications map
             map.remove(&notification_id);
+            bump_state_version(StateDomain::Notifications);
             
             // Remove from user's notification list
             USER_NOTIFICATIONS.with(|user_notifications| {
@@ -614,6 +635,7 @@ pub fn get_my_notification_settings() -> Result<NotificationSettings, String> {
 #[update]
 pub fn update_my_notification_settings(settings: NotificationSettings) -> Result<(), String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "update_my_notification_settings")?;
     
     // Validate settings
     if settings.user_id != caller {
@@ -709,6 +731,7 @@ pub fn get_all_notifications(filter: Option<NotificationFilter>) -> Notification
 #[update]
 pub fn cleanup_old_notifications() -> Result<u64, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "cleanup_old_notifications")?;
     
     if !is_admin(&caller) {
         return Err("Unauthorized: Admin access required".to_string());
@@ -746,6 +769,10 @@ pub fn cleanup_old_notifications() -> Result<u64, String> {
         });
     });
     
+    if cleaned_count > 0 {
+        bump_state_version(StateDomain::Notifications);
+    }
+    
     // Update cleanup timestamp
     NOTIFICATION_STATS.with(|stats| {
         let mut stats_mut = stats.borrow_mut();
@@ -765,6 +792,7 @@ pub fn cleanup_old_notifications() -> Result<u64, String> {
 #[update]
 pub fn send_test_notification(recipient: Principal, message: String) -> Result<u64, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "send_test_notification")?;
     
     if !is_admin(&caller) {
         return Err("Unauthorized: Admin access required".to_string());
@@ -905,12 +933,29 @@ fn get_priority_from_event(event: &NotificationEvent) -> NotificationPriority {
 
 fn generate_notification_content(
     event: &NotificationEvent,
-    custom_message: Option<String>
+    custom_message: Option<String>,
+    language: &str,
 ) -> Result<(String, String), String> {
     if let Some(message) = custom_message {
         return Ok(("Custom Notification".to_string(), message));
     }
     
+    // Preferred language template, then English, then the built-in default below
+    let event_type = get_event_type_string(event);
+    let variables = get_event_template_variables(event);
+    for candidate in [language, FALLBACK_TEMPLATE_LANGUAGE] {
+        let template = NOTIFICATION_TEMPLATES.with(|templates| {
+            templates.borrow().get(&template_key(&event_type, candidate))
+        });
+        if let Some(template) = template {
+            let title = render_template(&template.title_template, &variables);
+            let message = render_template(&template.message_template, &variables);
+            if !title.contains("{{") && !message.contains("{{") {
+                return Ok((title, message));
+            }
+        }
+    }
+    
     let (title, message) = match event {
         NotificationEvent::LoanApplicationSubmitted { loan_id } => (
             "Loan Application Submitted".to_string(),
@@ -1048,6 +1093,209 @@ fn generate_notification_content(
     Ok((title, message))
 }
 
+// ========== NOTIFICATION TEMPLATES ==========
+
+const FALLBACK_TEMPLATE_LANGUAGE: &str = "en";
+
+fn template_key(event_type: &str, language: &str) -> String {
+    format!("{}:{}", event_type, language.to_lowercase())
+}
+
+/// Substitute {{placeholder}} occurrences with the given values
+pub fn render_template(template: &str, variables: &HashMap<String, String>) -> String {
+    let mut rendered = template.to_string();
+    for (name, value) in variables {
+        rendered = rendered.replace(&format!("{{{{{}}}}}", name), value);
+    }
+    rendered
+}
+
+/// Placeholder names used in a template, in order of appearance
+pub fn extract_placeholders(template: &str) -> Vec<String> {
+    let mut placeholders = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                placeholders.push(after[..end].to_string());
+                rest = &after[end + 2..];
+            }
+            None => break,
+        }
+    }
+    placeholders
+}
+
+/// Variables each built-in event provides to templates; None for custom event types
+pub fn get_event_variable_names(event_type: &str) -> Option<Vec<&'static str>> {
+    let names = match event_type {
+        "loan_application_submitted" | "loan_approved" | "loan_fully_repaid" => vec!["loan_id"],
+        "loan_offer_ready" | "loan_disbursed" => vec!["loan_id", "amount"],
+        "loan_repayment_received" => vec!["loan_id", "amount", "remaining_balance"],
+        "loan_overdue" => vec!["loan_id", "days_overdue"],
+        "loan_liquidated" => vec!["loan_id", "collateral_seized"],
+        "collateral_minted" => vec!["nft_id", "commodity_type"],
+        "collateral_escrowed" | "collateral_released" => vec!["nft_id", "loan_id"],
+        "collateral_liquidated" => vec!["nft_id", "sale_price"],
+        "liquidity_deposited" | "liquidity_withdrawn" => vec!["amount"],
+        "investment_returns" => vec!["amount", "period"],
+        "price_alert" => vec!["commodity", "old_price", "new_price", "change_percentage"],
+        "oracle_failure" => vec!["commodity", "error"],
+        "proposal_created" => vec!["proposal_id", "title"],
+        "proposal_voted" => vec!["proposal_id", "vote"],
+        "proposal_executed" => vec!["proposal_id", "outcome"],
+        "maintenance_scheduled" => vec!["start_time", "duration_hours"],
+        "emergency_stop" => vec!["reason"],
+        "system_resumed" => vec![],
+        "security_alert" => vec!["event_type"],
+        "unusual_activity" => vec!["description"],
+        "operator_broadcast" => vec!["title", "message", "broadcast_id"],
+        _ => return None,
+    };
+    Some(names)
+}
+
+fn get_event_template_variables(event: &NotificationEvent) -> HashMap<String, String> {
+    let pairs: Vec<(&str, String)> = match event {
+        NotificationEvent::LoanApplicationSubmitted { loan_id } |
+        NotificationEvent::LoanApproved { loan_id } |
+        NotificationEvent::LoanFullyRepaid { loan_id } => vec![("loan_id", loan_id.to_string())],
+        NotificationEvent::LoanOfferReady { loan_id, amount } |
+        NotificationEvent::LoanDisbursed { loan_id, amount } =>
+            vec![("loan_id", loan_id.to_string()), ("amount", amount.to_string())],
+        NotificationEvent::LoanRepaymentReceived { loan_id, amount, remaining_balance } => vec![
+            ("loan_id", loan_id.to_string()),
+            ("amount", amount.to_string()),
+            ("remaining_balance", remaining_balance.to_string()),
+        ],
+        NotificationEvent::LoanOverdue { loan_id, days_overdue } =>
+            vec![("loan_id", loan_id.to_string()), ("days_overdue", days_overdue.to_string())],
+        NotificationEvent::LoanLiquidated { loan_id, collateral_seized } =>
+            vec![("loan_id", loan_id.to_string()), ("collateral_seized", format!("{:?}", collateral_seized))],
+        NotificationEvent::CollateralMinted { nft_id, commodity_type } =>
+            vec![("nft_id", nft_id.to_string()), ("commodity_type", commodity_type.clone())],
+        NotificationEvent::CollateralEscrowed { nft_id, loan_id } |
+        NotificationEvent::CollateralReleased { nft_id, loan_id } =>
+            vec![("nft_id", nft_id.to_string()), ("loan_id", loan_id.to_string())],
+        NotificationEvent::CollateralLiquidated { nft_id, sale_price } =>
+            vec![("nft_id", nft_id.to_string()), ("sale_price", sale_price.to_string())],
+        NotificationEvent::LiquidityDeposited { amount } |
+        NotificationEvent::LiquidityWithdrawn { amount } => vec![("amount", amount.to_string())],
+        NotificationEvent::InvestmentReturns { amount, period } =>
+            vec![("amount", amount.to_string()), ("period", period.clone())],
+        NotificationEvent::PriceAlert { commodity, old_price, new_price, change_percentage } => vec![
+            ("commodity", commodity.clone()),
+            ("old_price", old_price.to_string()),
+            ("new_price", new_price.to_string()),
+            ("change_percentage", format!("{:.2}", change_percentage)),
+        ],
+        NotificationEvent::OracleFailure { commodity, error } =>
+            vec![("commodity", commodity.clone()), ("error", error.clone())],
+        NotificationEvent::ProposalCreated { proposal_id, title } =>
+            vec![("proposal_id", proposal_id.to_string()), ("title", title.clone())],
+        NotificationEvent::ProposalVoted { proposal_id, vote } =>
+            vec![("proposal_id", proposal_id.to_string()), ("vote", vote.clone())],
+        NotificationEvent::ProposalExecuted { proposal_id, outcome } =>
+            vec![("proposal_id", proposal_id.to_string()), ("outcome", outcome.clone())],
+        NotificationEvent::MaintenanceScheduled { start_time, duration_hours } =>
+            vec![("start_time", start_time.to_string()), ("duration_hours", duration_hours.to_string())],
+        NotificationEvent::EmergencyStop { reason } => vec![("reason", reason.clone())],
+        NotificationEvent::SystemResumed => vec![],
+        NotificationEvent::SecurityAlert { event_type, .. } => vec![("event_type", event_type.clone())],
+        NotificationEvent::UnusualActivity { description } => vec![("description", description.clone())],
+        NotificationEvent::Custom { data, .. } => {
+            return data.clone();
+        }
+    };
+    
+    pairs.into_iter().map(|(name, value)| (name.to_string(), value)).collect()
+}
+
+/// Check a template only references placeholders its event can supply
+pub fn validate_notification_template(template: &NotificationTemplate) -> Result<(), String> {
+    if template.event_type.trim().is_empty() {
+        return Err("Template event_type is required".to_string());
+    }
+    if template.language.as_deref().map_or(true, |language| language.trim().is_empty()) {
+        return Err("Template language is required".to_string());
+    }
+    if template.title_template.trim().is_empty() || template.message_template.trim().is_empty() {
+        return Err("Template title and message are required".to_string());
+    }
+    if template.message_template.len() > MAX_NOTIFICATION_MESSAGE_LENGTH {
+        return Err(format!("Template message exceeds {} characters", MAX_NOTIFICATION_MESSAGE_LENGTH));
+    }
+    
+    // Custom events provide whatever variables the template declares
+    let available: Vec<String> = match get_event_variable_names(&template.event_type) {
+        Some(names) => names.into_iter().map(|name| name.to_string()).collect(),
+        None => template.variables.clone(),
+    };
+    
+    for placeholder in extract_placeholders(&template.title_template)
+        .into_iter()
+        .chain(extract_placeholders(&template.message_template))
+    {
+        if !available.contains(&placeholder) {
+            return Err(format!(
+                "Unknown placeholder {{{{{}}}}} for event {}. Available: {:?}",
+                placeholder, template.event_type, available
+            ));
+        }
+    }
+    
+    for variable in &template.variables {
+        if !available.contains(variable) {
+            return Err(format!("Declared variable {} is not provided by event {}", variable, template.event_type));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Create or replace a notification template (admin only)
+#[update]
+pub fn set_notification_template(template: NotificationTemplate) -> Result<(), String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "set_notification_template")?;
+    
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Admin access required".to_string());
+    }
+    
+    let mut template = template;
+    template.language = template.language.map(|language| language.to_lowercase());
+    validate_notification_template(&template)?;
+    
+    let key = template_key(&template.event_type, &template.language());
+    NOTIFICATION_TEMPLATES.with(|templates| {
+        templates.borrow_mut().insert(key.clone(), template);
+    });
+    
+    log_audit_action(
+        caller,
+        "notification_template_updated".to_string(),
+        format!("Notification template {} updated", key),
+    );
+    
+    Ok(())
+}
+
+/// List all notification templates (admin only)
+#[query]
+pub fn list_notification_templates() -> Result<Vec<NotificationTemplate>, String> {
+    let caller = caller();
+    
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Admin access required".to_string());
+    }
+    
+    Ok(NOTIFICATION_TEMPLATES.with(|templates| {
+        templates.borrow().iter().map(|(_, template)| template).collect()
+    }))
+}
+
 fn calculate_expiry_time(priority: &NotificationPriority) -> Option<u64> {
     let current_time = time();
     let expiry_duration = match priority {
@@ -1774,28 +2022,126 @@ pub fn notification_post_upgrade() {
 }
 
 fn initialize_default_templates() {
+    let defaults = vec![
+        ("loan_offer_ready", "en", "Loan Offer Ready",
+            "Your loan offer for #{{loan_id}} is ready! Amount: {{amount}} satoshi.",
+            vec!["loan_id", "amount"]),
+        ("loan_offer_ready", "id", "Penawaran Pinjaman Siap",
+            "Penawaran pinjaman #{{loan_id}} Anda sudah siap! Jumlah: {{amount}} satoshi.",
+            vec!["loan_id", "amount"]),
+        ("loan_repayment_received", "en", "Payment Received",
+            "Payment of {{amount}} satoshi received for loan #{{loan_id}}. Remaining: {{remaining_balance}} satoshi.",
+            vec!["loan_id", "amount", "remaining_balance"]),
+        ("loan_repayment_received", "id", "Pembayaran Diterima",
+            "Pembayaran {{amount}} satoshi untuk pinjaman #{{loan_id}} telah diterima. Sisa: {{remaining_balance}} satoshi.",
+            vec!["loan_id", "amount", "remaining_balance"]),
+        ("loan_overdue", "id", "Pembayaran Pinjaman Terlambat",
+            "Pembayaran pinjaman #{{loan_id}} Anda terlambat {{days_overdue}} hari. Segera lakukan pembayaran untuk menghindari likuidasi.",
+            vec!["loan_id", "days_overdue"]),
+        ("operator_broadcast", "en", "{{title}}", "{{message}}", vec!["title", "message"]),
+        ("operator_broadcast", "id", "Pengumuman: {{title}}", "{{message}}", vec!["title", "message"]),
+    ];
+    
     NOTIFICATION_TEMPLATES.with(|templates| {
         let mut map = templates.borrow_mut();
         
-        // Loan templates
-        map.insert("loan_offer_ready".to_string(), NotificationTemplate {
-            event_type: "loan_offer_ready".to_string(),
-            title_template: "Loan Offer Ready".to_string(),
-            message_template: "Your loan offer for #{loan_id} is ready! Amount: {amount} satoshi.".to_string(),
-            default_priority: NotificationPriority::Normal,
-            default_channels: vec![NotificationChannel::OnChain],
-            variables: vec!["loan_id".to_string(), "amount".to_string()],
-        });
+        // Templates stored before localization were keyed by event type alone; lookups
+        // always include the language now, so those entries are never read
+        let legacy_keys: Vec<String> = map.iter()
+            .map(|(key, _)| key)
+            .filter(|key| !key.contains(':'))
+            .collect();
+        for key in legacy_keys {
+            map.remove(&key);
+        }
         
-        map.insert("loan_repayment_received".to_string(), NotificationTemplate {
-            event_type: "loan_repayment_received".to_string(),
-            title_template: "Payment Received".to_string(),
-            message_template: "Payment of {amount} satoshi received for loan #{loan_id}. Remaining: {remaining_balance} satoshi.".to_string(),
+        for (event_type, language, title, message, variables) in defaults {
+            let key = template_key(event_type, language);
+            // Keep templates customised by admins across upgrades
+            if map.contains_key(&key) {
+                continue;
+            }
+            map.insert(key, NotificationTemplate {
+                event_type: event_type.to_string(),
+                language: Some(language.to_string()),
+                title_template: title.to_string(),
+                message_template: message.to_string(),
+                default_priority: NotificationPriority::Normal,
+                default_channels: vec![NotificationChannel::OnChain],
+                variables: variables.into_iter().map(|v| v.to_string()).collect(),
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(event_type: &str, message: &str) -> NotificationTemplate {
+        NotificationTemplate {
+            event_type: event_type.to_string(),
+            language: Some("id".to_string()),
+            title_template: "Pembayaran Diterima".to_string(),
+            message_template: message.to_string(),
             default_priority: NotificationPriority::Normal,
             default_channels: vec![NotificationChannel::OnChain],
-            variables: vec!["loan_id".to_string(), "amount".to_string(), "remaining_balance".to_string()],
+            variables: vec![],
+        }
+    }
+
+    #[test]
+    fn test_render_template_substitutes_placeholders() {
+        let event = NotificationEvent::LoanRepaymentReceived { loan_id: 7, amount: 1_500, remaining_balance: 500 };
+        let rendered = render_template(
+            "Pembayaran {{amount}} untuk pinjaman #{{loan_id}}, sisa {{remaining_balance}}",
+            &get_event_template_variables(&event),
+        );
+        assert_eq!(rendered, "Pembayaran 1500 untuk pinjaman #7, sisa 500");
+    }
+
+    #[test]
+    fn test_template_validation_rejects_unknown_placeholders() {
+        assert!(validate_notification_template(
+            &template("loan_repayment_received", "Sisa {{remaining_balance}} dari {{amount}}")
+        ).is_ok());
+
+        // loan_approved events carry no amount, so {{amount}} would render literally
+        assert!(validate_notification_template(
+            &template("loan_approved", "Pinjaman #{{loan_id}} sebesar {{amount}} disetujui")
+        ).is_err());
+
+        // Custom events may only use declared variables
+        let mut custom = template("harvest_reminder", "Panen {{crop}} minggu ini");
+        assert!(validate_notification_template(&custom).is_err());
+        custom.variables = vec!["crop".to_string()];
+        assert!(validate_notification_template(&custom).is_ok());
+    }
+
+    #[test]
+    fn test_default_templates_drop_unlocalized_keys() {
+        NOTIFICATION_TEMPLATES.with(|templates| {
+            templates.borrow_mut().insert(
+                "loan_offer_ready".to_string(),
+                template("loan_offer_ready", "Your loan offer for #{loan_id} is ready"),
+            );
         });
-        
-        // Add more templates as needed...
-    });
+
+        initialize_default_templates();
+
+        NOTIFICATION_TEMPLATES.with(|templates| {
+            let map = templates.borrow();
+            assert!(!map.contains_key(&"loan_offer_ready".to_string()));
+            assert!(map.contains_key(&template_key("loan_offer_ready", "en")));
+            assert!(map.iter().all(|(key, _)| key.contains(':')));
+        });
+    }
+
+    #[test]
+    fn test_extract_placeholders() {
+        assert_eq!(
+            extract_placeholders("{{a}} and {{b}} but not {{c"),
+            vec!["a".to_string(), "b".to_string()]
+        );
+    }
 }
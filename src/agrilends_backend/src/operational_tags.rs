@@ -0,0 +1,149 @@
+// ========== OPERATIONAL TAGS MODULE ==========
+// Free-form labels ("flagged for review", "vip", "disputed") that support staff attach to
+// loans and investor accounts. Tags are an overlay only: nothing in loan health, liquidation
+// eligibility or liquidity math reads them.
+
+use ic_cdk::{caller, api::time};
+use ic_cdk_macros::{query, update};
+use candid::Principal;
+
+use crate::types::*;
+use crate::storage::{get_entity_tags, store_entity_tags, get_entity_ids_with_tag, get_loan};
+use crate::helpers::{is_admin, log_audit_action};
+
+pub const MAX_TAGS_PER_ENTITY: usize = 10;
+pub const MAX_TAG_LENGTH: usize = 32;
+
+/// Tags are compared case-insensitively and stored lowercase
+pub fn normalize_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() || tag.len() > MAX_TAG_LENGTH {
+        return Err(format!("Tag must be between 1 and {} characters", MAX_TAG_LENGTH));
+    }
+    if !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '-' || c == '_') {
+        return Err("Tag may only contain letters, digits, spaces, '-' and '_'".to_string());
+    }
+    Ok(tag)
+}
+
+/// Canonical entity ID: decimal loan ID or principal text
+fn normalize_entity_id(entity_type: &TaggedEntityType, entity_id: &str) -> Result<String, String> {
+    match entity_type {
+        TaggedEntityType::Loan => {
+            let loan_id: u64 = entity_id.trim().parse()
+                .map_err(|_| format!("Invalid loan ID: {}", entity_id))?;
+            get_loan(loan_id).ok_or_else(|| format!("Loan #{} not found", loan_id))?;
+            Ok(loan_id.to_string())
+        },
+        TaggedEntityType::Investor => Principal::from_text(entity_id.trim())
+            .map(|principal| principal.to_text())
+            .map_err(|_| format!("Invalid investor principal: {}", entity_id)),
+    }
+}
+
+/// Operator tier and above, matching investor data access
+fn ensure_operator(caller: &Principal) -> Result<(), String> {
+    let role = crate::governance::get_admin_role(*caller);
+    match crate::liquidity_management::resolve_investor_data_tier(role.as_ref(), is_admin(caller)) {
+        Some(InvestorDataAccessTier::Operator) | Some(InvestorDataAccessTier::Full) => Ok(()),
+        _ => Err("Unauthorized: Operator access required to manage tags".to_string()),
+    }
+}
+
+/// Add `tag` unless already present, enforcing the per-entity cap
+pub fn apply_tag(entity_tags: &mut EntityTags, tag: String, added_by: Principal, now: u64) -> Result<bool, String> {
+    if entity_tags.tags.iter().any(|t| t.tag == tag) {
+        return Ok(false);
+    }
+    if entity_tags.tags.len() >= MAX_TAGS_PER_ENTITY {
+        return Err(format!("An entity can carry at most {} tags", MAX_TAGS_PER_ENTITY));
+    }
+    entity_tags.tags.push(EntityTag { tag, added_by, added_at: now });
+    Ok(true)
+}
+
+/// Tag a loan or investor account (operator and above)
+#[update]
+pub fn add_tag(entity_type: TaggedEntityType, entity_id: String, tag: String) -> Result<EntityTags, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "add_tag")?;
+    ensure_operator(&caller)?;
+
+    let entity_id = normalize_entity_id(&entity_type, &entity_id)?;
+    let tag = normalize_tag(&tag)?;
+    let mut entity_tags = get_entity_tags(&entity_type, &entity_id).unwrap_or(EntityTags {
+        entity_type: entity_type.clone(),
+        entity_id: entity_id.clone(),
+        tags: Vec::new(),
+    });
+
+    if apply_tag(&mut entity_tags, tag.clone(), caller, time())? {
+        store_entity_tags(entity_tags.clone());
+        log_audit_action(
+            caller,
+            "ENTITY_TAG_ADDED".to_string(),
+            format!("Tag '{}' added to {} {}", tag, entity_type.key_prefix(), entity_id),
+            true,
+        );
+    }
+
+    Ok(entity_tags)
+}
+
+/// Remove a tag from a loan or investor account (operator and above)
+#[update]
+pub fn remove_tag(entity_type: TaggedEntityType, entity_id: String, tag: String) -> Result<EntityTags, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "remove_tag")?;
+    ensure_operator(&caller)?;
+
+    let entity_id = normalize_entity_id(&entity_type, &entity_id)?;
+    let tag = normalize_tag(&tag)?;
+    let mut entity_tags = get_entity_tags(&entity_type, &entity_id)
+        .ok_or_else(|| format!("{} {} has no tags", entity_type.key_prefix(), entity_id))?;
+
+    let before = entity_tags.tags.len();
+    entity_tags.tags.retain(|t| t.tag != tag);
+    if entity_tags.tags.len() == before {
+        return Err(format!("Tag '{}' not found on {} {}", tag, entity_type.key_prefix(), entity_id));
+    }
+    store_entity_tags(entity_tags.clone());
+
+    log_audit_action(
+        caller,
+        "ENTITY_TAG_REMOVED".to_string(),
+        format!("Tag '{}' removed from {} {}", tag, entity_type.key_prefix(), entity_id),
+        true,
+    );
+
+    Ok(entity_tags)
+}
+
+/// Tags on a loan or investor account (operator and above)
+#[query]
+pub fn get_tags(entity_type: TaggedEntityType, entity_id: String) -> Result<Vec<EntityTag>, String> {
+    let caller = caller();
+    ensure_operator(&caller)?;
+
+    let entity_id = normalize_entity_id(&entity_type, &entity_id)?;
+    Ok(get_entity_tags(&entity_type, &entity_id).map(|t| t.tags).unwrap_or_default())
+}
+
+/// Loan IDs carrying `tag`, for admin query filters. Checks operator access.
+pub fn loan_ids_with_tag(caller: &Principal, tag: &str) -> Result<Vec<u64>, String> {
+    ensure_operator(caller)?;
+    let tag = normalize_tag(tag)?;
+    Ok(get_entity_ids_with_tag(&TaggedEntityType::Loan, &tag)
+        .iter()
+        .filter_map(|id| id.parse().ok())
+        .collect())
+}
+
+/// Investor principals carrying `tag`, for admin query filters
+pub fn investors_with_tag(tag: &str) -> Result<Vec<Principal>, String> {
+    let tag = normalize_tag(tag)?;
+    Ok(get_entity_ids_with_tag(&TaggedEntityType::Investor, &tag)
+        .iter()
+        .filter_map(|id| Principal::from_text(id).ok())
+        .collect())
+}
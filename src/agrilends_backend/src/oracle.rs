@@ -13,12 +13,17 @@ use serde_json;
 use std::collections::HashMap;
 use crate::storage::{
     log_audit_action, store_commodity_price, get_stored_commodity_price, 
-    get_all_stored_commodity_prices, update_last_price_fetch, get_last_price_fetch
+    get_all_stored_commodity_prices, update_last_price_fetch, get_last_price_fetch, get_price_samples,
+    get_protocol_parameters, next_price_alert_id, store_price_alert_subscription,
+    get_price_alert_subscription, remove_price_alert_subscription,
+    get_price_alert_subscriptions_by_subscriber, get_price_alert_subscriptions_page,
+    get_price_fetch_statistics, record_price_write_rejection, record_forced_price_write, clear_held_price
 };
-use crate::helpers::{is_admin, get_canister_config};
+use crate::helpers::{is_admin, get_canister_config, record_dependency_call, elapsed_ms_since, ORACLE_DEPENDENCY};
 use crate::types::{
     CommodityPrice, CommodityPriceData, PriceFetchRecord, OracleConfig, 
-    OracleStatistics, PriceAlert, PriceThresholdType
+    OracleStatistics, PriceAlert, PriceAlertSubscription, PriceThresholdType, PriceSample,
+    PricingMode, TwapPrice, BtcIdrRate, Asset
 };
 
 // Production Oracle Configuration Constants
@@ -29,6 +34,13 @@ const PRICE_STALE_THRESHOLD: u64 = 86400_000_000_000; // 24 hours in nanoseconds
 const MAX_RETRIES: u32 = 3;
 const CONFIDENCE_THRESHOLD: u64 = 70; // Minimum confidence score for price data
 const HEARTBEAT_INTERVAL: u64 = 3600_000_000_000; // 1 hour heartbeat interval
+const TWAP_FALLBACK_CONFIDENCE: u64 = 50; // Confidence reported when TWAP falls back to spot
+const MAX_TWAP_WINDOW_SECONDS: u64 = 7 * 24 * 60 * 60;
+const MAX_PRICE_ALERTS_PER_USER: usize = 20;
+const MAX_PRICE_ALERT_EVALUATIONS_PER_RUN: usize = 200; // Bounds instructions spent per heartbeat
+const DEFAULT_MAX_PRICE_DEVIATION_BPS: u64 = 2_000;
+const DEFAULT_DEVIATION_WINDOW_SECONDS: u64 = 3_600;
+const ADMIN_PRICE_SOURCE: &str = "admin";
 
 // Thread-local storage for Oracle state management
 use std::cell::RefCell;
@@ -48,6 +60,8 @@ thread_local! {
     static PRICE_ALERTS: RefCell<Vec<PriceAlert>> = RefCell::new(vec![]);
     static FETCH_RECORDS: RefCell<HashMap<String, PriceFetchRecord>> = RefCell::new(HashMap::new());
     static LAST_HEARTBEAT: RefCell<u64> = RefCell::new(0);
+    // Last subscription ID evaluated; the next heartbeat resumes after it
+    static PRICE_ALERT_CURSOR: RefCell<u64> = RefCell::new(0);
 }
 
 // Data structures for API responses
@@ -165,6 +179,7 @@ fn transform_commodity_response(response: TransformArgs) -> HttpResponse {
 pub async fn fetch_commodity_price(commodity_id: String) -> Result<CommodityPrice, String> {
     // Security check - only admins or automated heartbeat can trigger fetches
     let caller_principal = caller();
+    crate::validation::guard_caller(&caller_principal, "fetch_commodity_price")?;
     if !is_admin(&caller_principal) && caller_principal != ic_cdk::id() {
         return Err("Unauthorized: Only admins can fetch commodity prices".to_string());
     }
@@ -192,7 +207,13 @@ pub async fn fetch_commodity_price(commodity_id: String) -> Result<CommodityPric
         match fetch_from_api(&commodity_id, &api_url, &api_name).await {
             Ok(commodity_price) => {
                 // Validate price data quality
-                if validate_price_data(&commodity_price) {
+                if validate_price_data(&commodity_id, &commodity_price) {
+                    // Out-of-band prices are held until another source confirms them
+                    if let Err(e) = guard_price_write(&commodity_id, commodity_price.price_per_unit, &api_name, caller_principal) {
+                        last_error = format!("{} from {}", e, api_name);
+                        continue;
+                    }
+
                     // Store successful price data
                     store_commodity_price(commodity_id.clone(), commodity_price.clone())?;
                     update_last_price_fetch(&commodity_id, start_time);
@@ -222,8 +243,12 @@ pub async fn fetch_commodity_price(commodity_id: String) -> Result<CommodityPric
         }
     }
 
-    // All APIs failed - try emergency fallback
-    if let Some(fallback_price) = get_emergency_fallback_price(&commodity_id) {
+    // All APIs failed - try emergency fallback, which is held to the same band. Exchange rates
+    // never fall back, since a fixed rate stored as fresh would price loans indefinitely
+    if let Some(fallback_price) = get_emergency_fallback_price(&commodity_id)
+        .filter(|_| !is_rate_feed(&commodity_id))
+        .filter(|price| guard_price_write(&commodity_id, *price, "emergency_fallback", caller_principal).is_ok())
+    {
         log_audit_action(
             caller_principal,
             "EMERGENCY_PRICE_FALLBACK".to_string(),
@@ -269,12 +294,232 @@ pub fn get_commodity_price(commodity_id: String) -> Result<CommodityPrice, Strin
     }
 }
 
-/// Administrative function to manually set commodity price (for testing/emergency)
+/// Time-weighted average of price samples over [window_start, now].
+/// Each sample's price holds until the next sample. Returns the average and
+/// whether the samples reach back to the window start.
+pub fn calculate_twap(samples: &[PriceSample], window_start: u64, now: u64) -> Option<(u64, bool)> {
+    if now <= window_start {
+        return None;
+    }
+
+    let mut weighted_sum: u128 = 0;
+    let mut covered: u128 = 0;
+
+    for (i, sample) in samples.iter().enumerate() {
+        let segment_end = samples.get(i + 1).map(|next| next.timestamp).unwrap_or(now).min(now);
+        let segment_start = sample.timestamp.max(window_start);
+        if segment_end <= segment_start {
+            continue;
+        }
+        let duration = (segment_end - segment_start) as u128;
+        weighted_sum += sample.price_per_unit as u128 * duration;
+        covered += duration;
+    }
+
+    if covered == 0 {
+        return None;
+    }
+
+    let covers_window = samples.first().map(|s| s.timestamp <= window_start).unwrap_or(false);
+    Some(((weighted_sum / covered) as u64, covers_window))
+}
+
+/// Get the time-weighted average price of a commodity over the given window
+#[query]
+pub fn get_commodity_twap(commodity_id: String, window_seconds: u64) -> Result<TwapPrice, String> {
+    if window_seconds == 0 || window_seconds > MAX_TWAP_WINDOW_SECONDS {
+        return Err(format!("TWAP window must be between 1 and {} seconds", MAX_TWAP_WINDOW_SECONDS));
+    }
+
+    let spot = get_stored_commodity_price(&commodity_id)
+        .ok_or_else(|| format!("Price not available for commodity: {}", commodity_id))?;
+
+    let now = time();
+    let window_start = now.saturating_sub(window_seconds * 1_000_000_000);
+    let samples = get_price_samples(&commodity_id);
+    let (twap_price, covers_window) = calculate_twap(&samples, window_start, now)
+        .unwrap_or((spot.price_per_unit, false));
+
+    Ok(TwapPrice {
+        commodity_id,
+        twap_price,
+        spot_price: spot.price_per_unit,
+        window_seconds,
+        sample_count: samples.iter().filter(|s| s.timestamp >= window_start).count() as u64,
+        covers_window,
+        calculated_at: now,
+    })
+}
+
+/// Price used for origination and liquidation math, following the governance pricing mode.
+/// In Twap mode without enough history to cover the window, falls back to spot with reduced confidence.
+pub fn get_effective_commodity_price(commodity_id: &str) -> Option<CommodityPriceData> {
+    let mut price_data = get_stored_commodity_price(commodity_id)?;
+    let params = get_protocol_parameters();
+
+    if params.pricing_mode() == PricingMode::Twap {
+        match get_commodity_twap(commodity_id.to_string(), params.twap_window_seconds()) {
+            Ok(twap) if twap.covers_window => {
+                price_data.price_per_unit = twap.twap_price;
+                price_data.source = "twap".to_string();
+            },
+            _ => {
+                price_data.source = "spot_fallback".to_string();
+                price_data.confidence_score = price_data.confidence_score.min(TWAP_FALLBACK_CONFIDENCE);
+            }
+        }
+    }
+
+    Some(price_data)
+}
+
+/// Price feed holding the rupiah price of one BTC, written like any commodity price
+pub const BTC_IDR_PRICE_ID: &str = "btc_idr";
+pub const ICP_IDR_PRICE_ID: &str = "icp_idr";
+
+/// Price feed quoting one whole token of `asset` in rupiah
+pub fn asset_price_feed_id(asset: &Asset) -> &'static str {
+    match asset {
+        Asset::CkBtc => BTC_IDR_PRICE_ID,
+        Asset::Icp => ICP_IDR_PRICE_ID,
+    }
+}
+
+/// Exchange-rate feeds the heartbeat refreshes alongside the enabled commodities
+pub const RATE_FEED_IDS: [&str; 2] = [BTC_IDR_PRICE_ID, ICP_IDR_PRICE_ID];
+
+/// Whether `feed_id` is an exchange rate rather than a per-unit commodity price
+fn is_rate_feed(feed_id: &str) -> bool {
+    RATE_FEED_IDS.contains(&feed_id)
+}
+
+/// Current BTC/IDR rate for converting rupiah valuations to satoshi.
+pub fn get_btc_idr_rate() -> Result<BtcIdrRate, String> {
+    get_asset_idr_rate(&Asset::CkBtc)
+}
+
+/// Current rupiah rate for `asset` from its own price feed. ICP has 10^8 e8s per token like
+/// satoshi per BTC, so the same rate type converts rupiah valuations into either unit.
+/// A rate that has not been fetched yet or has gone stale is an error; there is no fixed
+/// fallback rate.
+pub fn get_asset_idr_rate(asset: &Asset) -> Result<BtcIdrRate, String> {
+    let feed_id = asset_price_feed_id(asset);
+    let price = get_effective_commodity_price(feed_id)
+        .ok_or_else(|| format!("{}/IDR rate not available. Please wait for the '{}' price feed to be fetched.", asset.symbol(), feed_id))?;
+    if is_price_stale(feed_id.to_string()) {
+        return Err(format!("{}/IDR rate is stale. Please wait for price update.", asset.symbol()));
+    }
+    Ok(BtcIdrRate {
+        idr_per_btc: price.price_per_unit,
+        timestamp: price.timestamp,
+    })
+}
+
+// =============================================================================
+// PRICE SANITY BAND
+// =============================================================================
+
+/// Gap between two prices in basis points of the lower one, so a move and its reversal score
+/// the same and a price can always return to where it came from
+pub fn price_deviation_bps(reference: u64, price: u64) -> u64 {
+    if reference == price {
+        return 0;
+    }
+    let lower = reference.min(price);
+    if lower == 0 {
+        return u64::MAX;
+    }
+    (reference.abs_diff(price) as u128 * 10_000 / lower as u128).min(u64::MAX as u128) as u64
+}
+
+/// Accept a write within `max_deviation_bps` of the last accepted price, or any write once that
+/// price is older than the window. An out-of-band write is still accepted when it agrees with a
+/// price held from a different source, so moves that persist across sources get through.
+/// Returns the deviation from the last accepted price on rejection.
+pub fn check_price_band(
+    last_accepted: Option<(u64, u64)>,
+    held_from_other_source: Option<u64>,
+    price: u64,
+    now: u64,
+    max_deviation_bps: u64,
+    window_nanos: u64,
+) -> Result<(), u64> {
+    let (last_price, last_timestamp) = match last_accepted {
+        Some(last) => last,
+        None => return Ok(()),
+    };
+    if now.saturating_sub(last_timestamp) >= window_nanos {
+        return Ok(());
+    }
+
+    let deviation = price_deviation_bps(last_price, price);
+    if deviation <= max_deviation_bps {
+        return Ok(());
+    }
+    match held_from_other_source {
+        Some(held) if price_deviation_bps(held, price) <= max_deviation_bps => Ok(()),
+        _ => Err(deviation),
+    }
+}
+
+fn price_band_limits() -> (u64, u64) {
+    let max_deviation_bps = crate::governance::get_protocol_parameter("oracle_max_price_deviation_bps".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_MAX_PRICE_DEVIATION_BPS);
+    let window_seconds = crate::governance::get_protocol_parameter("oracle_deviation_window_seconds".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_DEVIATION_WINDOW_SECONDS);
+    (max_deviation_bps, window_seconds.saturating_mul(1_000_000_000))
+}
+
+/// Band check for a price write from `source`; None when the price is within the band
+fn price_band_violation(commodity_id: &str, price: u64, source: &str) -> Option<u64> {
+    let (max_deviation_bps, window_nanos) = price_band_limits();
+    let last_accepted = get_stored_commodity_price(commodity_id)
+        .map(|last| (last.price_per_unit, last.timestamp));
+    let held_from_other_source = get_price_fetch_statistics(commodity_id).and_then(|record| {
+        match (record.held_price, record.held_price_source) {
+            (Some(held), Some(held_source)) if held_source != source => Some(held),
+            _ => None,
+        }
+    });
+
+    check_price_band(last_accepted, held_from_other_source, price, time(), max_deviation_bps, window_nanos).err()
+}
+
+/// Reject and hold an out-of-band write; clears any held price once a write is accepted
+fn guard_price_write(commodity_id: &str, price: u64, source: &str, actor: candid::Principal) -> Result<(), String> {
+    match price_band_violation(commodity_id, price, source) {
+        None => {
+            clear_held_price(commodity_id);
+            Ok(())
+        },
+        Some(deviation) => {
+            record_price_write_rejection(commodity_id, price, source);
+            log_audit_action(
+                actor,
+                "COMMODITY_PRICE_WRITE_REJECTED".to_string(),
+                format!(
+                    "{} price {} IDR from {} deviates {} bps from the last accepted price; held for confirmation",
+                    commodity_id, price, source, deviation
+                ),
+                false,
+            );
+            Err(format!("Price deviates {} bps from the last accepted price", deviation))
+        }
+    }
+}
+
+/// Administrative function to manually set commodity price (for testing/emergency).
+/// Prices outside the sanity band are rejected unless `force` is set with a reason.
 #[update]
 pub fn admin_set_commodity_price(
     commodity_id: String,
     price_idr: u64,
+    force: bool,
+    reason: Option<String>,
 ) -> Result<(), String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "admin_set_commodity_price")?;
     if !is_admin(&caller()) {
         return Err("Only admins can manually set commodity prices".to_string());
     }
@@ -287,6 +532,30 @@ pub fn admin_set_commodity_price(
         return Err(format!("Unsupported commodity type: {}", commodity_id));
     }
 
+    let reason = reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+    if force && reason.is_none() {
+        return Err("A reason is required to force a price write".to_string());
+    }
+
+    if force {
+        if let Some(deviation) = price_band_violation(&commodity_id, price_idr, ADMIN_PRICE_SOURCE) {
+            record_forced_price_write(&commodity_id);
+            log_audit_action(
+                caller(),
+                "ADMIN_PRICE_OVERRIDE_FORCED".to_string(),
+                format!(
+                    "Admin forced {} price to {} IDR, {} bps from the last accepted price: {}",
+                    commodity_id, price_idr, deviation, reason.clone().unwrap_or_default()
+                ),
+                true,
+            );
+        } else {
+            clear_held_price(&commodity_id);
+        }
+    } else {
+        guard_price_write(&commodity_id, price_idr, ADMIN_PRICE_SOURCE, caller())?;
+    }
+
     let commodity_price = CommodityPrice {
         price_per_unit: price_idr,
         currency: "IDR".to_string(),
@@ -298,7 +567,11 @@ pub fn admin_set_commodity_price(
     log_audit_action(
         caller(),
         "ADMIN_PRICE_OVERRIDE".to_string(),
-        format!("Admin manually set {} price to {} IDR", commodity_id, price_idr),
+        format!(
+            "Admin manually set {} price to {} IDR{}",
+            commodity_id, price_idr,
+            reason.map(|r| format!(": {}", r)).unwrap_or_default()
+        ),
         true,
     );
 
@@ -329,6 +602,7 @@ pub fn get_oracle_config() -> OracleConfig {
 /// Update Oracle configuration (admin only)
 #[update]
 pub fn update_oracle_config(new_config: OracleConfig) -> Result<(), String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "update_oracle_config")?;
     if !is_admin(&caller()) {
         return Err("Only admins can update Oracle configuration".to_string());
     }
@@ -373,6 +647,7 @@ pub fn create_price_alert(
     commodity_id: String,
     threshold_type: PriceThresholdType,
 ) -> Result<(), String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "create_price_alert")?;
     if !is_admin(&caller()) {
         return Err("Only admins can create price alerts".to_string());
     }
@@ -419,6 +694,214 @@ pub fn get_price_alerts() -> Vec<PriceAlert> {
     })
 }
 
+// =============================================================================
+// USER PRICE ALERT SUBSCRIPTIONS
+// =============================================================================
+
+/// Subscribe the caller to an alert on a commodity price. Change thresholds are in basis
+/// points relative to the price at subscription (and, after each trigger, the triggering price).
+#[update]
+pub fn subscribe_price_alert(
+    commodity_id: String,
+    threshold_type: PriceThresholdType,
+    recurring: bool,
+) -> Result<PriceAlertSubscription, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "subscribe_price_alert")?;
+
+    if caller == candid::Principal::anonymous() {
+        return Err("Anonymous callers cannot subscribe to price alerts".to_string());
+    }
+
+    if crate::user_management::get_user_by_principal(&caller).is_none() {
+        return Err("User not registered".to_string());
+    }
+
+    if !is_supported_commodity(&commodity_id) {
+        return Err(format!("Unsupported commodity type: {}", commodity_id));
+    }
+
+    match &threshold_type {
+        PriceThresholdType::Above(value) | PriceThresholdType::Below(value) if *value == 0 => {
+            return Err("Threshold price must be greater than zero".to_string());
+        },
+        PriceThresholdType::Change(bps) if *bps == 0 || *bps > 10_000 => {
+            return Err("Change threshold must be between 1 and 10000 basis points".to_string());
+        },
+        _ => {}
+    }
+
+    let active_count = get_price_alert_subscriptions_by_subscriber(caller)
+        .iter()
+        .filter(|alert| alert.is_active)
+        .count();
+    if active_count >= MAX_PRICE_ALERTS_PER_USER {
+        return Err(format!("Maximum of {} active price alerts reached", MAX_PRICE_ALERTS_PER_USER));
+    }
+
+    let alert = PriceAlertSubscription {
+        id: next_price_alert_id(),
+        subscriber: caller,
+        commodity_id: commodity_id.clone(),
+        threshold_type,
+        recurring,
+        is_active: true,
+        is_crossed: false,
+        reference_price: get_stored_commodity_price(&commodity_id).map(|price| price.price_per_unit),
+        last_observed_price: None,
+        created_at: time(),
+        last_triggered_at: None,
+        trigger_count: 0,
+    };
+    store_price_alert_subscription(alert.clone());
+
+    log_audit_action(
+        caller,
+        "PRICE_ALERT_SUBSCRIBED".to_string(),
+        format!("Price alert #{} on {}: {:?} ({})", alert.id, commodity_id, alert.threshold_type,
+            if recurring { "recurring" } else { "one-shot" }),
+        true,
+    );
+
+    Ok(alert)
+}
+
+/// The caller's price alerts, including ones that have already fired
+#[query]
+pub fn get_my_price_alerts() -> Vec<PriceAlertSubscription> {
+    get_price_alert_subscriptions_by_subscriber(caller())
+}
+
+/// Cancel one of the caller's price alerts
+#[update]
+pub fn cancel_price_alert(alert_id: u64) -> Result<(), String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "cancel_price_alert")?;
+
+    let alert = get_price_alert_subscription(alert_id)
+        .ok_or_else(|| format!("Price alert #{} not found", alert_id))?;
+    if alert.subscriber != caller {
+        return Err("Unauthorized: You can only cancel your own price alerts".to_string());
+    }
+
+    remove_price_alert_subscription(alert_id);
+
+    log_audit_action(
+        caller,
+        "PRICE_ALERT_CANCELLED".to_string(),
+        format!("Price alert #{} on {} cancelled", alert_id, alert.commodity_id),
+        true,
+    );
+
+    Ok(())
+}
+
+/// Apply an observed price to a subscription. Returns the price the move is measured
+/// from when the alert fires, or None when it stays quiet.
+pub fn evaluate_price_alert_subscription(
+    alert: &mut PriceAlertSubscription,
+    price: u64,
+    now: u64,
+) -> Option<u64> {
+    if !alert.is_active {
+        return None;
+    }
+
+    let previous_price = alert.last_observed_price.or(alert.reference_price).unwrap_or(price);
+    alert.last_observed_price = Some(price);
+
+    let crossed = match &alert.threshold_type {
+        PriceThresholdType::Above(threshold) => price > *threshold,
+        PriceThresholdType::Below(threshold) => price < *threshold,
+        PriceThresholdType::Change(bps) => match alert.reference_price {
+            Some(reference) if reference > 0 => {
+                (price.abs_diff(reference) as u128) * 10_000 >= (reference as u128) * (*bps as u128)
+            },
+            _ => {
+                // No baseline yet; the first observation becomes the reference
+                alert.reference_price = Some(price);
+                false
+            }
+        },
+    };
+
+    if !crossed {
+        alert.is_crossed = false;
+        return None;
+    }
+    if alert.is_crossed {
+        return None;
+    }
+
+    alert.last_triggered_at = Some(now);
+    alert.trigger_count += 1;
+    let measured_from = match alert.threshold_type {
+        PriceThresholdType::Change(_) => {
+            // Re-arm against the new price so the next alert needs a fresh move
+            let reference = alert.reference_price.unwrap_or(previous_price);
+            alert.reference_price = Some(price);
+            reference
+        },
+        _ => {
+            alert.is_crossed = true;
+            previous_price
+        }
+    };
+    if !alert.recurring {
+        alert.is_active = false;
+    }
+
+    Some(measured_from)
+}
+
+/// Evaluate the next batch of subscriptions against stored prices, resuming where the
+/// previous run stopped. Returns the number of alerts that fired.
+pub fn evaluate_price_alert_subscriptions(limit: usize) -> u64 {
+    let cursor = PRICE_ALERT_CURSOR.with(|cursor| *cursor.borrow());
+    let page = get_price_alert_subscriptions_page(cursor, limit);
+
+    // Wrap around once the end of the map is reached
+    let next_cursor = if page.len() < limit { 0 } else { page.last().map(|alert| alert.id).unwrap_or(0) };
+    PRICE_ALERT_CURSOR.with(|cursor| *cursor.borrow_mut() = next_cursor);
+
+    let now = time();
+    let mut prices: HashMap<String, Option<u64>> = HashMap::new();
+    let mut fired = 0;
+
+    for mut alert in page.into_iter().filter(|alert| alert.is_active) {
+        let price = *prices.entry(alert.commodity_id.clone()).or_insert_with(|| {
+            get_stored_commodity_price(&alert.commodity_id).map(|price| price.price_per_unit)
+        });
+        let price = match price {
+            Some(price) => price,
+            None => continue,
+        };
+
+        if let Some(measured_from) = evaluate_price_alert_subscription(&mut alert, price, now) {
+            let change_percentage = if measured_from > 0 {
+                (price as f64 - measured_from as f64) / measured_from as f64 * 100.0
+            } else {
+                0.0
+            };
+            if let Err(e) = crate::notification_system::notify_price_alert(
+                alert.subscriber, &alert.commodity_id, measured_from, price, change_percentage
+            ) {
+                log_audit_action(
+                    ic_cdk::id(),
+                    "PRICE_ALERT_NOTIFICATION_FAILED".to_string(),
+                    format!("Price alert #{} fired but notification failed: {}", alert.id, e),
+                    false,
+                );
+            }
+            fired += 1;
+        }
+
+        store_price_alert_subscription(alert);
+    }
+
+    fired
+}
+
 // =============================================================================
 // HEARTBEAT FUNCTION - Automated price updates
 // =============================================================================
@@ -442,10 +925,11 @@ pub async fn heartbeat_price_update() {
         return;
     }
 
-    // Get list of commodities to update
-    let commodities = ORACLE_CONFIG.with(|config| {
+    // Get list of commodities to update, plus the exchange rates loans are converted at
+    let mut commodities = ORACLE_CONFIG.with(|config| {
         config.borrow().enabled_commodities.clone()
     });
+    commodities.extend(RATE_FEED_IDS.iter().map(|feed_id| feed_id.to_string()));
     
     for commodity in commodities {
         // Only update stale prices to avoid unnecessary API calls
@@ -477,6 +961,8 @@ pub async fn heartbeat_price_update() {
         }
     }
 
+    evaluate_price_alert_subscriptions(MAX_PRICE_ALERT_EVALUATIONS_PER_RUN);
+
     // Update uptime percentage
     ORACLE_STATS.with(|stats| {
         let mut current_stats = stats.borrow_mut();
@@ -527,7 +1013,14 @@ async fn fetch_from_api(
     let start_time = time();
     
     // Make HTTP request with retry logic
-    match http_request(request, CYCLES_PER_REQUEST).await {
+    let http_result = http_request(request, CYCLES_PER_REQUEST).await;
+    record_dependency_call(
+        ORACLE_DEPENDENCY,
+        elapsed_ms_since(start_time),
+        matches!(&http_result, Ok((response,)) if response.status == 200u16),
+    );
+    
+    match http_result {
         Ok((response,)) => {
             let response_time = time() - start_time;
             
@@ -616,6 +1109,15 @@ fn extract_price_from_json(json: &serde_json::Value) -> Result<u64, String> {
         }
     }
 
+    // Strategy 5: Exchange rate quoted per asset, e.g. {"bitcoin": {"idr": 1500000000}} or {"internet-computer": {"idr": 150000}}
+    if let Some(quotes) = json.as_object() {
+        for quote in quotes.values() {
+            if let Some(price) = quote.get("idr").and_then(|p| p.as_f64()) {
+                return Ok(price as u64);
+            }
+        }
+    }
+
     Err("No valid price field found in JSON response".to_string())
 }
 
@@ -682,22 +1184,23 @@ fn get_api_sources_for_commodity(commodity_id: &str) -> Vec<(String, String)> {
     })
 }
 
-/// Check if commodity type is supported
+/// Check if commodity type is supported. The BTC/IDR rate is always accepted.
 fn is_supported_commodity(commodity_id: &str) -> bool {
-    ORACLE_CONFIG.with(|config| {
+    commodity_id == BTC_IDR_PRICE_ID || commodity_id == ICP_IDR_PRICE_ID || ORACLE_CONFIG.with(|config| {
         config.borrow().enabled_commodities.contains(&commodity_id.to_string())
     })
 }
 
 /// Validate price data quality
-fn validate_price_data(price: &CommodityPrice) -> bool {
+fn validate_price_data(commodity_id: &str, price: &CommodityPrice) -> bool {
     // Basic validation rules
     if price.price_per_unit == 0 {
         return false;
     }
 
-    // Price should be reasonable (between 1000 and 1_000_000 IDR per unit)
-    if price.price_per_unit < 1000 || price.price_per_unit > 1_000_000 {
+    // Commodity prices should be reasonable (between 1000 and 1_000_000 IDR per unit);
+    // exchange rates quote a whole token and sit far above that range
+    if !is_rate_feed(commodity_id) && (price.price_per_unit < 1000 || price.price_per_unit > 1_000_000) {
         return false;
     }
 
@@ -753,6 +1256,10 @@ fn update_fetch_attempt(commodity_id: &str) {
             last_error: None,
             average_response_time: 0,
             rate_limit_reset: current_time,
+            rejected_writes: None,
+            forced_writes: None,
+            held_price: None,
+            held_price_source: None,
         });
         
         record.fetch_count += 1;
@@ -913,6 +1420,7 @@ pub fn oracle_diagnostics() -> String {
 /// Enable emergency mode (admin only)
 #[update]
 pub fn enable_emergency_mode() -> Result<(), String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "enable_emergency_mode")?;
     if !is_admin(&caller()) {
         return Err("Only admins can enable emergency mode".to_string());
     }
@@ -935,6 +1443,7 @@ pub fn enable_emergency_mode() -> Result<(), String> {
 /// Disable emergency mode (admin only)
 #[update]
 pub fn disable_emergency_mode() -> Result<(), String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "disable_emergency_mode")?;
     if !is_admin(&caller()) {
         return Err("Only admins can disable emergency mode".to_string());
     }
@@ -1000,6 +1509,7 @@ pub fn oracle_health_check() -> Result<String, String> {
 /// Reset Oracle statistics (admin only)
 #[update]
 pub fn reset_oracle_statistics() -> Result<(), String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "reset_oracle_statistics")?;
     if !is_admin(&caller()) {
         return Err("Only admins can reset Oracle statistics".to_string());
     }
@@ -1039,6 +1549,7 @@ pub fn reset_oracle_statistics() -> Result<(), String> {
 /// Test Oracle connectivity with a simple request (admin only)
 #[update]
 pub async fn test_oracle_connectivity(test_url: Option<String>) -> Result<String, String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "test_oracle_connectivity")?;
     if !is_admin(&caller()) {
         return Err("Only admins can test Oracle connectivity".to_string());
     }
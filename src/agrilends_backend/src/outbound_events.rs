@@ -0,0 +1,277 @@
+// ========== OUTBOUND EVENTS MODULE ==========
+// Webhook-style HTTPS outcalls that push protocol events to off-chain systems
+// Payloads are signed with HMAC-SHA256 over "<timestamp>.<body>" using a shared secret
+
+use ic_cdk::{caller, api::time};
+use ic_cdk::api::management_canister::http_request::{
+    CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse, TransformContext,
+    TransformArgs, http_request
+};
+use ic_cdk_macros::{query, update};
+use candid::Principal;
+use sha2::{Digest, Sha256};
+
+use crate::types::*;
+use crate::storage::{
+    get_outbound_webhook_config, set_outbound_webhook_config, next_outbound_delivery_id,
+    store_outbound_delivery, get_outbound_deliveries
+};
+use crate::helpers::{is_admin, log_audit_action};
+
+const WEBHOOK_CYCLES_PER_REQUEST: u64 = 50_000_000;
+const WEBHOOK_MAX_RESPONSE_BYTES: u64 = 2_048;
+const DEFAULT_WEBHOOK_MAX_ATTEMPTS: u64 = 3;
+const MAX_WEBHOOK_ATTEMPTS: u64 = 10;
+const HMAC_BLOCK_SIZE: usize = 64;
+
+pub const LIQUIDATION_EVENT_TYPE: &str = "loan.liquidated";
+
+/// HMAC-SHA256 (RFC 2104) as lowercase hex
+pub fn hmac_sha256_hex(secret: &[u8], message: &[u8]) -> String {
+    let mut key = if secret.len() > HMAC_BLOCK_SIZE {
+        Sha256::digest(secret).to_vec()
+    } else {
+        secret.to_vec()
+    };
+    key.resize(HMAC_BLOCK_SIZE, 0);
+
+    let inner_pad: Vec<u8> = key.iter().map(|b| b ^ 0x36).collect();
+    let outer_pad: Vec<u8> = key.iter().map(|b| b ^ 0x5c).collect();
+
+    let mut inner = Sha256::new();
+    inner.update(&inner_pad);
+    inner.update(message);
+    let inner_hash = inner.finalize();
+
+    let mut outer = Sha256::new();
+    outer.update(&outer_pad);
+    outer.update(inner_hash);
+    hex::encode(outer.finalize())
+}
+
+/// Liquidation event body; keys are emitted in a fixed order so signatures are reproducible
+pub fn build_liquidation_payload(
+    loan_id: u64,
+    borrower: &Principal,
+    collateral_value: u64,
+    debt: u64,
+    timestamp: u64,
+) -> String {
+    serde_json::json!({
+        "event": LIQUIDATION_EVENT_TYPE,
+        "loan_id": loan_id,
+        "borrower": borrower.to_text(),
+        "collateral_value": collateral_value,
+        "debt": debt,
+        "timestamp": timestamp,
+    }).to_string()
+}
+
+/// Consensus transform: keep only the status code and a bounded body, drop all headers
+#[query]
+fn transform_outbound_event_response(response: TransformArgs) -> HttpResponse {
+    let mut body = response.response.body.clone();
+    body.truncate(WEBHOOK_MAX_RESPONSE_BYTES as usize);
+
+    HttpResponse {
+        status: response.response.status.clone(),
+        headers: vec![],
+        body,
+    }
+}
+
+/// Configure the liquidation webhook (super admin only). An empty secret keeps the existing one.
+#[update]
+pub fn configure_outbound_webhook(
+    enabled: bool,
+    endpoint_url: Option<String>,
+    hmac_secret: Option<String>,
+    max_attempts: Option<u64>,
+) -> Result<OutboundWebhookStatus, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "configure_outbound_webhook")?;
+
+    if !crate::governance::is_super_admin(&caller) {
+        return Err("Unauthorized: Only super admins can configure outbound webhooks".to_string());
+    }
+
+    let endpoint_url = endpoint_url
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty());
+    if let Some(url) = &endpoint_url {
+        if !url.starts_with("https://") {
+            return Err("Webhook endpoint must use https://".to_string());
+        }
+    }
+
+    let existing = get_outbound_webhook_config();
+    let hmac_secret = match hmac_secret.filter(|secret| !secret.is_empty()) {
+        Some(secret) => secret,
+        None => existing.as_ref().map(|config| config.hmac_secret.clone()).unwrap_or_default(),
+    };
+    if enabled && endpoint_url.is_some() && hmac_secret.len() < 32 {
+        return Err("An HMAC secret of at least 32 characters is required to enable the webhook".to_string());
+    }
+
+    let max_attempts = max_attempts
+        .or(existing.as_ref().map(|config| config.max_attempts))
+        .unwrap_or(DEFAULT_WEBHOOK_MAX_ATTEMPTS);
+    if max_attempts == 0 || max_attempts > MAX_WEBHOOK_ATTEMPTS {
+        return Err(format!("max_attempts must be between 1 and {}", MAX_WEBHOOK_ATTEMPTS));
+    }
+
+    let config = OutboundWebhookConfig {
+        enabled,
+        endpoint_url,
+        hmac_secret,
+        max_attempts,
+        updated_by: caller,
+        updated_at: time(),
+    };
+    set_outbound_webhook_config(config.clone());
+
+    log_audit_action(
+        caller,
+        "OUTBOUND_WEBHOOK_CONFIGURED".to_string(),
+        format!(
+            "Outbound webhook {} for endpoint {:?} with {} max attempts",
+            if config.enabled { "enabled" } else { "disabled" },
+            config.endpoint_url,
+            config.max_attempts
+        ),
+        true,
+    );
+
+    Ok(webhook_status(&config))
+}
+
+/// Redacted webhook configuration (admin only)
+#[query]
+pub fn get_outbound_webhook_status() -> Result<Option<OutboundWebhookStatus>, String> {
+    let caller = caller();
+
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can view webhook configuration".to_string());
+    }
+
+    Ok(get_outbound_webhook_config().map(|config| webhook_status(&config)))
+}
+
+/// Recent outbound event deliveries, newest first (admin only)
+#[query]
+pub fn get_outbound_event_deliveries() -> Result<Vec<OutboundEventDelivery>, String> {
+    let caller = caller();
+
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can view outbound event deliveries".to_string());
+    }
+
+    Ok(get_outbound_deliveries())
+}
+
+fn webhook_status(config: &OutboundWebhookConfig) -> OutboundWebhookStatus {
+    OutboundWebhookStatus {
+        enabled: config.enabled,
+        endpoint_url: config.endpoint_url.clone(),
+        has_secret: !config.hmac_secret.is_empty(),
+        max_attempts: config.max_attempts,
+        updated_by: config.updated_by,
+        updated_at: config.updated_at,
+    }
+}
+
+/// Post a liquidation event to the configured webhook. No-op when disabled or unconfigured;
+/// delivery failures are recorded and never propagate to the liquidation itself.
+pub async fn publish_liquidation_event(loan_id: u64, borrower: Principal, collateral_value: u64, debt: u64) {
+    let config = match get_outbound_webhook_config() {
+        Some(config) if config.enabled => config,
+        _ => return,
+    };
+    let endpoint_url = match &config.endpoint_url {
+        Some(url) => url.clone(),
+        None => return,
+    };
+
+    let timestamp = time();
+    let payload = build_liquidation_payload(loan_id, &borrower, collateral_value, debt, timestamp);
+    let mut delivery = OutboundEventDelivery {
+        id: next_outbound_delivery_id(),
+        event_type: LIQUIDATION_EVENT_TYPE.to_string(),
+        loan_id,
+        endpoint_url: endpoint_url.clone(),
+        payload: payload.clone(),
+        status: DeliveryStatus::Pending,
+        attempts: 0,
+        last_status_code: None,
+        failure_reason: None,
+        created_at: timestamp,
+        last_attempt_at: None,
+    };
+    store_outbound_delivery(delivery.clone());
+
+    let signature = hmac_sha256_hex(
+        config.hmac_secret.as_bytes(),
+        format!("{}.{}", timestamp, payload).as_bytes(),
+    );
+    let request = CanisterHttpRequestArgument {
+        url: endpoint_url,
+        method: HttpMethod::POST,
+        body: Some(payload.into_bytes()),
+        max_response_bytes: Some(WEBHOOK_MAX_RESPONSE_BYTES),
+        transform: Some(TransformContext::from_name(
+            "transform_outbound_event_response".to_string(),
+            vec![]
+        )),
+        headers: vec![
+            HttpHeader { name: "Content-Type".to_string(), value: "application/json".to_string() },
+            HttpHeader { name: "User-Agent".to_string(), value: "Agrilends-Webhook/1.0".to_string() },
+            HttpHeader { name: "Idempotency-Key".to_string(), value: format!("agrilends-{}", delivery.id) },
+            HttpHeader { name: "X-Agrilends-Timestamp".to_string(), value: timestamp.to_string() },
+            HttpHeader { name: "X-Agrilends-Signature".to_string(), value: format!("sha256={}", signature) },
+        ],
+    };
+
+    while delivery.attempts < config.max_attempts {
+        delivery.attempts += 1;
+        delivery.last_attempt_at = Some(time());
+
+        match http_request(request.clone(), WEBHOOK_CYCLES_PER_REQUEST).await {
+            Ok((response,)) => {
+                let status_code: u64 = response.status.0.try_into().unwrap_or(0);
+                delivery.last_status_code = Some(status_code);
+                if (200..300).contains(&status_code) {
+                    delivery.status = DeliveryStatus::Delivered;
+                    delivery.failure_reason = None;
+                    break;
+                }
+                delivery.failure_reason = Some(format!("HTTP {}", status_code));
+                // Client errors will not succeed on retry
+                if (400..500).contains(&status_code) && status_code != 408 && status_code != 429 {
+                    break;
+                }
+            },
+            Err((rejection_code, message)) => {
+                delivery.failure_reason = Some(format!("Outcall rejected ({:?}): {}", rejection_code, message));
+            }
+        }
+    }
+
+    if delivery.status != DeliveryStatus::Delivered {
+        delivery.status = DeliveryStatus::Failed;
+    }
+    store_outbound_delivery(delivery.clone());
+
+    log_audit_action(
+        ic_cdk::id(),
+        "OUTBOUND_EVENT_DELIVERY".to_string(),
+        format!(
+            "{} event for loan #{}: {:?} after {} attempt(s){}",
+            delivery.event_type,
+            loan_id,
+            delivery.status,
+            delivery.attempts,
+            delivery.failure_reason.as_ref().map(|reason| format!(" - {}", reason)).unwrap_or_default()
+        ),
+        delivery.status == DeliveryStatus::Delivered,
+    );
+}
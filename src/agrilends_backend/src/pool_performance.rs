@@ -0,0 +1,161 @@
+// ========== POOL PERFORMANCE MODULE ==========
+// Daily snapshots of pool totals and period flows for investor and admin charts.
+// Each snapshot stores cumulative deposits, withdrawals, interest and losses; the period
+// figures are the difference from the previous snapshot, so nothing is recomputed from
+// the full transaction history at query time.
+
+use ic_cdk::api::time;
+use ic_cdk_macros::query;
+
+use crate::types::*;
+use crate::storage::{
+    get_liquidity_pool, get_all_investor_balances, get_all_loans_data, store_performance_snapshot,
+    get_latest_performance_snapshot, get_performance_snapshots_in_range, prune_performance_snapshots
+};
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+const NANOS_PER_YEAR: u64 = 365 * NANOS_PER_DAY;
+
+/// Snapshots are taken at most once per day
+pub const SNAPSHOT_INTERVAL_NANOS: u64 = NANOS_PER_DAY;
+
+/// Rolling retention window (2 years)
+pub const SNAPSHOT_RETENTION_NANOS: u64 = 730 * NANOS_PER_DAY;
+
+const MAX_SNAPSHOT_PRUNES_PER_RUN: usize = 100;
+
+/// Lifetime pool flows at the moment a snapshot is taken
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CumulativePoolTotals {
+    pub deposits: u64,
+    pub withdrawals: u64,
+    pub interest: u64,
+    pub losses: u64,
+}
+
+/// Interest minus losses over the period, relative to pool liquidity, annualized (percent)
+pub fn realized_apy(period_interest: u64, period_losses: u64, total_liquidity: u64, period_nanos: u64) -> f64 {
+    if total_liquidity == 0 || period_nanos == 0 {
+        return 0.0;
+    }
+    let net_return = period_interest as f64 - period_losses as f64;
+    net_return / total_liquidity as f64 * (NANOS_PER_YEAR as f64 / period_nanos as f64) * 100.0
+}
+
+/// Quoted APY (basis points) at monthly points going back from `now`, newest first: each
+/// point takes the latest snapshot at or before it that recorded an APY. Points older than
+/// every such snapshot are left out rather than invented.
+pub fn monthly_apy_history(snapshots: &[PerformanceSnapshot], now: u64, months: u64) -> Vec<(u64, u64)> {
+    (0..months)
+        .map(|i| now.saturating_sub(i.saturating_mul(30 * NANOS_PER_DAY)))
+        .filter_map(|point| {
+            snapshots.iter()
+                .filter(|snapshot| snapshot.captured_at <= point)
+                .filter_map(|snapshot| snapshot.pool_apy_bps.map(|apy| (snapshot.captured_at, apy)))
+                .max_by_key(|(captured_at, _)| *captured_at)
+                .map(|(_, apy)| (point, apy))
+        })
+        .collect()
+}
+
+/// Build a snapshot by diffing `totals` against the previous snapshot's cumulative figures,
+/// recording `pool_apy_bps` as the APY quoted at capture.
+/// Cumulative totals never decrease; a lower reading (e.g. a pruned record) yields a zero delta.
+pub fn build_performance_snapshot(
+    previous: Option<&PerformanceSnapshot>,
+    totals: &CumulativePoolTotals,
+    pool: &LiquidityPool,
+    pool_apy_bps: u64,
+    now: u64,
+) -> PerformanceSnapshot {
+    let (period_start, base) = match previous {
+        Some(previous) => (previous.captured_at, CumulativePoolTotals {
+            deposits: previous.cumulative_deposits,
+            withdrawals: previous.cumulative_withdrawals,
+            interest: previous.cumulative_interest,
+            losses: previous.cumulative_losses,
+        }),
+        None => (pool.created_at.min(now), CumulativePoolTotals::default()),
+    };
+
+    let period_deposits = totals.deposits.saturating_sub(base.deposits);
+    let period_withdrawals = totals.withdrawals.saturating_sub(base.withdrawals);
+    let period_interest = totals.interest.saturating_sub(base.interest);
+    let period_losses = totals.losses.saturating_sub(base.losses);
+
+    PerformanceSnapshot {
+        captured_at: now,
+        period_start,
+        total_liquidity: pool.total_liquidity,
+        available_liquidity: pool.available_liquidity,
+        total_borrowed: pool.total_borrowed,
+        total_investors: pool.total_investors,
+        utilization_bps: if pool.total_liquidity > 0 {
+            ((pool.total_liquidity.saturating_sub(pool.available_liquidity) as u128 * 10_000)
+                / pool.total_liquidity as u128) as u64
+        } else {
+            0
+        },
+        cumulative_deposits: base.deposits.max(totals.deposits),
+        cumulative_withdrawals: base.withdrawals.max(totals.withdrawals),
+        cumulative_interest: base.interest.max(totals.interest),
+        cumulative_losses: base.losses.max(totals.losses),
+        period_deposits,
+        period_withdrawals,
+        period_interest,
+        period_losses,
+        net_inflow: period_deposits as i64 - period_withdrawals as i64,
+        realized_apy: realized_apy(period_interest, period_losses, pool.total_liquidity, now.saturating_sub(period_start)),
+        pool_apy_bps: Some(pool_apy_bps),
+    }
+}
+
+/// Whether a new snapshot is due given the latest one
+pub fn snapshot_due(latest: Option<&PerformanceSnapshot>, now: u64) -> bool {
+    latest.map_or(true, |latest| now.saturating_sub(latest.captured_at) >= SNAPSHOT_INTERVAL_NANOS)
+}
+
+/// Current lifetime deposits, withdrawals, interest collected and liquidation losses
+fn current_pool_totals() -> CumulativePoolTotals {
+    let mut totals = CumulativePoolTotals::default();
+    for balance in get_all_investor_balances() {
+        totals.deposits = totals.deposits.saturating_add(balance.total_deposited);
+        totals.withdrawals = totals.withdrawals.saturating_add(balance.total_withdrawn);
+    }
+    for loan in get_all_loans_data() {
+        for payment in &loan.repayment_history {
+            if let Some(allocation) = &payment.allocation {
+                totals.interest = totals.interest.saturating_add(allocation.interest_amount);
+            }
+        }
+    }
+    totals.losses = crate::liquidation::total_liquidation_losses();
+    totals
+}
+
+/// Heartbeat hook: take today's snapshot if due and prune those past retention.
+/// Returns whether a snapshot was captured and how many were pruned.
+pub fn capture_performance_snapshot(now: u64) -> (bool, u64) {
+    let latest = get_latest_performance_snapshot();
+    let captured = if snapshot_due(latest.as_ref(), now) {
+        let pool = get_liquidity_pool();
+        let pool_apy_bps = crate::liquidity_management::calculate_pool_apy(&pool);
+        let snapshot = build_performance_snapshot(latest.as_ref(), &current_pool_totals(), &pool, pool_apy_bps, now);
+        store_performance_snapshot(snapshot);
+        true
+    } else {
+        false
+    };
+
+    let pruned = prune_performance_snapshots(now.saturating_sub(SNAPSHOT_RETENTION_NANOS), MAX_SNAPSHOT_PRUNES_PER_RUN);
+    (captured, pruned)
+}
+
+/// Daily performance snapshots captured between `from` and `to` (nanoseconds, inclusive), oldest first
+#[query]
+pub fn get_performance_snapshots(from: u64, to: u64) -> Result<Vec<PerformanceSnapshot>, String> {
+    if from > to {
+        return Err("'from' must not be after 'to'".to_string());
+    }
+    Ok(get_performance_snapshots_in_range(from, to))
+}
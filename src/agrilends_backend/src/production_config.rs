@@ -1,13 +1,16 @@
 use ic_cdk::api::time;
 use ic_cdk_macros::{update, query};
-use crate::types::CanisterConfig;
+use crate::types::{CanisterConfig, RoundingMode, FeeBearer};
 use crate::storage::{get_config, update_config, log_action};
-use crate::helpers::is_admin;
+use crate::helpers::{is_admin, mainnet_ckbtc_ledger, mainnet_ckbtc_minter, mainnet_icp_ledger};
+use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+use candid::Principal;
 
 /// Production-ready configuration update with validation
 #[update]
 pub fn update_canister_config(new_config: CanisterConfig) -> Result<(), String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "update_canister_config")?;
     
     // Security check
     if !is_admin(&caller) {
@@ -27,6 +30,15 @@ pub fn update_canister_config(new_config: CanisterConfig) -> Result<(), String>
         return Err("Invalid config: min_collateral_value must be less than max_collateral_value".to_string());
     }
     
+    // Sandbox mode and ckBTC principals change only through their dedicated endpoints
+    let current = get_config();
+    if new_config.sandbox_mode != current.sandbox_mode
+        || new_config.ckbtc_ledger_principal != current.ckbtc_ledger_principal
+        || new_config.ckbtc_minter_principal != current.ckbtc_minter_principal
+    {
+        return Err("Invalid config: use set_sandbox_mode and set_ckbtc_principals to change ckBTC settings".to_string());
+    }
+    
     // Update configuration
     update_config(new_config.clone())?;
     
@@ -38,6 +50,7 @@ pub fn update_canister_config(new_config: CanisterConfig) -> Result<(), String>
 #[update]
 pub fn emergency_stop() -> Result<(), String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "emergency_stop")?;
     
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can trigger emergency stop".to_string());
@@ -55,6 +68,7 @@ pub fn emergency_stop() -> Result<(), String> {
 #[update]
 pub fn resume_operations() -> Result<(), String> {
     let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "resume_operations")?;
     
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can resume operations".to_string());
@@ -92,3 +106,271 @@ pub struct SystemHealth {
     pub total_users: u64,
     pub system_uptime: u64,
 }
+
+// ========== SANDBOX MODE AND CKBTC PRINCIPALS ==========
+
+/// True unless the canister has been explicitly switched to sandbox/testnet mode
+pub fn is_production_mode() -> bool {
+    !get_config().sandbox_mode.unwrap_or(false)
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug, PartialEq)]
+pub struct CkbtcPrincipals {
+    pub ledger: Principal,
+    pub minter: Principal,
+    pub is_mainnet: bool,
+    pub production_mode: bool,
+}
+
+fn log_ckbtc_config_change(action: &str, description: String, before: String, after: String) {
+    log_audit_enhanced(
+        AuditCategory::Configuration,
+        action.to_string(),
+        AuditEventLevel::Warning,
+        AuditDetails {
+            description,
+            entity_type: Some("canister_config".to_string()),
+            entity_id: Some("ckbtc".to_string()),
+            before_state: Some(before),
+            after_state: Some(after),
+            ..Default::default()
+        },
+        AuditResult {
+            success: true,
+            error_code: None,
+            error_message: None,
+            execution_time_ms: None,
+            gas_used: None,
+            cycles_consumed: None,
+            memory_used_bytes: None,
+            warning_flags: vec!["CKBTC_CONFIGURATION".to_string()],
+        },
+        None,
+    );
+}
+
+/// Switch between production and sandbox/testnet mode (super admin only).
+/// Returning to production resets the ckBTC and ICP ledger principals to mainnet.
+#[update]
+pub fn set_sandbox_mode(enabled: bool) -> Result<(), String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "set_sandbox_mode")?;
+    
+    if !crate::governance::is_super_admin(&caller) {
+        return Err("Unauthorized: Only super admins can change sandbox mode".to_string());
+    }
+    
+    let mut config = get_config();
+    let was_production = !config.sandbox_mode.unwrap_or(false);
+    config.sandbox_mode = Some(enabled);
+    if !enabled {
+        config.ckbtc_ledger_principal = None;
+        config.ckbtc_minter_principal = None;
+        config.icp_ledger_principal = None;
+    }
+    config.updated_at = time();
+    update_config(config)?;
+    
+    log_ckbtc_config_change(
+        "SANDBOX_MODE_CHANGED",
+        format!(
+            "{} switched the canister to {} mode",
+            caller.to_text(),
+            if enabled { "sandbox" } else { "production" }
+        ),
+        format!("production_mode={}", was_production),
+        format!("production_mode={}", !enabled),
+    );
+    Ok(())
+}
+
+/// Point ckBTC calls at different ledger/minter canisters, e.g. testnet (admin only, sandbox mode only)
+#[update]
+pub fn set_ckbtc_principals(ledger: Principal, minter: Principal) -> Result<CkbtcPrincipals, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "set_ckbtc_principals")?;
+    
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can change ckBTC principals".to_string());
+    }
+    
+    let is_mainnet = ledger == mainnet_ckbtc_ledger() && minter == mainnet_ckbtc_minter();
+    if is_production_mode() && !is_mainnet {
+        return Err("Non-default ckBTC principals are not allowed in production mode".to_string());
+    }
+    if ledger == Principal::anonymous() || minter == Principal::anonymous() || ledger == minter {
+        return Err("Ledger and minter must be distinct canister principals".to_string());
+    }
+    
+    let mut config = get_config();
+    let before = format!(
+        "ledger={:?}, minter={:?}",
+        config.ckbtc_ledger_principal.map(|p| p.to_text()),
+        config.ckbtc_minter_principal.map(|p| p.to_text())
+    );
+    // Mainnet is stored as None so it tracks the built-in defaults
+    config.ckbtc_ledger_principal = if is_mainnet { None } else { Some(ledger) };
+    config.ckbtc_minter_principal = if is_mainnet { None } else { Some(minter) };
+    config.updated_at = time();
+    update_config(config)?;
+    
+    log_ckbtc_config_change(
+        "CKBTC_PRINCIPALS_CHANGED",
+        format!("{} set ckBTC ledger {} and minter {}", caller.to_text(), ledger.to_text(), minter.to_text()),
+        before,
+        format!("ledger={}, minter={}", ledger.to_text(), minter.to_text()),
+    );
+    
+    Ok(get_ckbtc_principals())
+}
+
+/// Point ICP pool and loan transfers at a different ledger canister (admin only, sandbox mode only)
+#[update]
+pub fn set_icp_ledger_principal(ledger: Principal) -> Result<Principal, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "set_icp_ledger_principal")?;
+    
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can change the ICP ledger principal".to_string());
+    }
+    
+    let is_mainnet = ledger == mainnet_icp_ledger();
+    if is_production_mode() && !is_mainnet {
+        return Err("Non-default ICP ledger principals are not allowed in production mode".to_string());
+    }
+    if ledger == Principal::anonymous() {
+        return Err("Ledger must be a canister principal".to_string());
+    }
+    
+    let mut config = get_config();
+    let before = format!("icp_ledger={:?}", config.icp_ledger_principal.map(|p| p.to_text()));
+    // Mainnet is stored as None so it tracks the built-in default
+    config.icp_ledger_principal = if is_mainnet { None } else { Some(ledger) };
+    config.updated_at = time();
+    update_config(config)?;
+    
+    log_ckbtc_config_change(
+        "ICP_LEDGER_PRINCIPAL_CHANGED",
+        format!("{} set ICP ledger {}", caller.to_text(), ledger.to_text()),
+        before,
+        format!("icp_ledger={}", ledger.to_text()),
+    );
+    
+    Ok(crate::helpers::ledger_principal_for(&crate::types::Asset::Icp))
+}
+
+/// ckBTC canisters currently in use
+#[query]
+pub fn get_ckbtc_principals() -> CkbtcPrincipals {
+    let ledger = crate::helpers::ckbtc_ledger_principal();
+    let minter = crate::helpers::ckbtc_minter_principal();
+    CkbtcPrincipals {
+        ledger,
+        minter,
+        is_mainnet: ledger == mainnet_ckbtc_ledger() && minter == mainnet_ckbtc_minter(),
+        production_mode: is_production_mode(),
+    }
+}
+
+/// Choose how fee and yield shares are rounded (admin only). Applies to shares computed
+/// from now on; amounts already recorded are not recomputed.
+#[update]
+pub fn set_rounding_mode(mode: RoundingMode) -> Result<RoundingMode, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "set_rounding_mode")?;
+    
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can change the rounding mode".to_string());
+    }
+    
+    let mut config = get_config();
+    let before = format!("{:?}", config.rounding_mode.unwrap_or_default());
+    config.rounding_mode = Some(mode);
+    config.updated_at = time();
+    let result = update_config(config);
+    
+    // Every fee and yield share in the protocol follows this setting, so keep a configuration
+    // record with the old and new mode whether or not the change was stored
+    crate::audit_logging::log_configuration_change("rounding_mode", Some(before), format!("{:?}", mode), result.is_ok());
+    result?;
+    Ok(mode)
+}
+
+/// Rounding applied to fee and yield shares
+#[query]
+pub fn get_rounding_mode() -> RoundingMode {
+    crate::helpers::rounding_mode()
+}
+
+/// Choose who pays the Bitcoin network fee on ckBTC loan disbursements (admin only)
+#[update]
+pub fn set_disbursement_fee_bearer(bearer: FeeBearer) -> Result<FeeBearer, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "set_disbursement_fee_bearer")?;
+    
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can change the disbursement fee bearer".to_string());
+    }
+    
+    let mut config = get_config();
+    let before = format!("{:?}", config.disbursement_fee_bearer.unwrap_or_default());
+    config.disbursement_fee_bearer = Some(bearer);
+    config.updated_at = time();
+    update_config(config)?;
+    
+    crate::helpers::log_audit_action(
+        caller,
+        "DISBURSEMENT_FEE_BEARER_CHANGED".to_string(),
+        format!("Disbursement fee bearer changed from {} to {:?}", before, bearer),
+        true,
+    );
+    Ok(bearer)
+}
+
+/// Who pays the Bitcoin network fee on ckBTC loan disbursements
+#[query]
+pub fn get_disbursement_fee_bearer() -> FeeBearer {
+    get_config().disbursement_fee_bearer.unwrap_or_default()
+}
+
+/// Canister subaccounts that hold ckBTC and must be counted by balance reconciliation (admin only).
+/// Replaces the whole list; each entry is a 32-byte ICRC-1 subaccount.
+#[update]
+pub fn set_reconciliation_subaccounts(subaccounts: Vec<Vec<u8>>) -> Result<u64, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "set_reconciliation_subaccounts")?;
+    
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can change reconciliation subaccounts".to_string());
+    }
+    if let Some(invalid) = subaccounts.iter().find(|subaccount| subaccount.len() != 32) {
+        return Err(format!("Subaccounts must be 32 bytes, got {}", invalid.len()));
+    }
+    
+    let mut subaccounts = subaccounts;
+    subaccounts.sort();
+    subaccounts.dedup();
+    // The default account is always reconciled
+    subaccounts.retain(|subaccount| subaccount.iter().any(|byte| *byte != 0));
+    
+    let mut config = get_config();
+    let before = config.reconciliation_subaccounts().len();
+    let count = subaccounts.len() as u64;
+    config.reconciliation_subaccounts = if subaccounts.is_empty() { None } else { Some(subaccounts) };
+    config.updated_at = time();
+    update_config(config)?;
+    
+    crate::helpers::log_audit_action(
+        caller,
+        "RECONCILIATION_SUBACCOUNTS_CHANGED".to_string(),
+        format!("Reconciliation subaccounts changed from {} to {}", before, count),
+        true,
+    );
+    Ok(count)
+}
+
+/// Canister subaccounts counted by ckBTC balance reconciliation
+#[query]
+pub fn get_reconciliation_subaccounts() -> Vec<Vec<u8>> {
+    get_config().reconciliation_subaccounts()
+}
@@ -1,24 +1,50 @@
 use candid::Principal;
 use ic_cdk::api::time;
+use ic_cdk_macros::{query, update};
 use std::cell::RefCell;
-use crate::storage::log_action;
+use crate::storage::{
+    log_action, store_blacklist_entry, remove_blacklist_entry, get_blacklist_entry, get_all_blacklist_entries,
+    store_btc_deny_rule, remove_btc_deny_rule, get_all_btc_deny_rules
+};
+use crate::types::{BlacklistEntry, BtcAddressDenyRule, BtcDenyRuleKind};
+use crate::audit_logging::{log_security_event, AuditEventLevel};
 
 // Enhanced security measures for production
 thread_local! {
-    static BLACKLISTED_PRINCIPALS: RefCell<std::collections::HashSet<Principal>> = RefCell::new(std::collections::HashSet::new());
     static ADMIN_ACTIONS_LOG: RefCell<Vec<(u64, Principal, String)>> = RefCell::new(Vec::new());
     static FAILED_AUTH_ATTEMPTS: RefCell<std::collections::HashMap<Principal, u64>> = RefCell::new(std::collections::HashMap::new());
 }
 
 /// Security middleware - check if principal is blacklisted
 pub fn security_check(principal: &Principal) -> Result<(), String> {
-    BLACKLISTED_PRINCIPALS.with(|blacklist| {
-        if blacklist.borrow().contains(principal) {
-            log_action("security_violation", &format!("Blacklisted principal attempted access: {}", principal.to_text()), false);
-            return Err("Access denied: Principal is blacklisted".to_string());
-        }
-        Ok(())
-    })
+    if get_blacklist_entry(principal).is_some() {
+        log_action("security_violation", &format!("Blacklisted principal attempted access: {}", principal.to_text()), false);
+        return Err("Access denied: Principal is blacklisted".to_string());
+    }
+    Ok(())
+}
+
+/// Call-boundary guard for sensitive updates. Must be the first check in the function.
+#[inline]
+pub fn ensure_not_blacklisted(caller: &Principal, operation: &str) -> Result<(), String> {
+    let entry = match get_blacklist_entry(caller) {
+        Some(entry) => entry,
+        None => return Ok(()),
+    };
+
+    log_security_event(
+        "BLACKLISTED_CALLER_BLOCKED",
+        AuditEventLevel::Critical,
+        format!("Blacklisted principal {} blocked from {}", caller.to_text(), operation),
+        Some(*caller),
+        vec![
+            "BLACKLISTED_PRINCIPAL".to_string(),
+            format!("OPERATION:{}", operation),
+            format!("BLACKLIST_REASON:{}", entry.reason),
+        ],
+    );
+
+    Err("Unauthorized: Principal is blacklisted".to_string())
 }
 
 /// Track failed authentication attempts
@@ -27,11 +53,14 @@ pub fn track_failed_auth(principal: &Principal) {
         let mut map = attempts.borrow_mut();
         let count = map.get(principal).unwrap_or(&0) + 1;
         map.insert(*principal, count);
-        
+
         // Auto-blacklist after 10 failed attempts
-        if count >= 10 {
-            BLACKLISTED_PRINCIPALS.with(|blacklist| {
-                blacklist.borrow_mut().insert(*principal);
+        if count >= 10 && get_blacklist_entry(principal).is_none() {
+            store_blacklist_entry(BlacklistEntry {
+                principal: *principal,
+                reason: format!("Auto-blacklisted after {} failed authentication attempts", count),
+                blacklisted_by: ic_cdk::id(),
+                blacklisted_at: time(),
             });
             log_action("auto_blacklist", &format!("Principal auto-blacklisted after {} failed attempts: {}", count, principal.to_text()), true);
         }
@@ -39,22 +68,220 @@ pub fn track_failed_auth(principal: &Principal) {
 }
 
 /// Admin function to blacklist a principal
-pub fn admin_blacklist_principal(principal: Principal) -> Result<(), String> {
+#[update]
+pub fn blacklist_principal(principal: Principal, reason: String) -> Result<(), String> {
     let caller = ic_cdk::caller();
-    
+    crate::validation::guard_caller(&caller, "blacklist_principal")?;
+
     // Only allow admin to blacklist
     if !crate::helpers::is_admin(&caller) {
         return Err("Unauthorized: Only admins can blacklist principals".to_string());
     }
-    
-    BLACKLISTED_PRINCIPALS.with(|blacklist| {
-        blacklist.borrow_mut().insert(principal);
+
+    if crate::helpers::is_admin(&principal) {
+        return Err("Admins cannot be blacklisted; remove the admin role first".to_string());
+    }
+
+    if reason.trim().is_empty() {
+        return Err("A reason is required to blacklist a principal".to_string());
+    }
+
+    store_blacklist_entry(BlacklistEntry {
+        principal,
+        reason: reason.clone(),
+        blacklisted_by: caller,
+        blacklisted_at: time(),
     });
-    
+
     ADMIN_ACTIONS_LOG.with(|log| {
         log.borrow_mut().push((time(), caller, format!("Blacklisted principal: {}", principal.to_text())));
     });
-    
-    log_action("admin_blacklist", &format!("Admin {} blacklisted principal: {}", caller.to_text(), principal.to_text()), true);
+
+    log_security_event(
+        "BLACKLIST_PRINCIPAL",
+        AuditEventLevel::Warning,
+        format!("Admin {} blacklisted principal {}: {}", caller.to_text(), principal.to_text(), reason),
+        Some(principal),
+        vec!["MANUAL_BLACKLIST".to_string()],
+    );
+    Ok(())
+}
+
+/// Legacy entry point kept for existing callers
+pub fn admin_blacklist_principal(principal: Principal) -> Result<(), String> {
+    blacklist_principal(principal, "Blacklisted by admin".to_string())
+}
+
+/// Admin function to remove a principal from the blacklist
+#[update]
+pub fn unblacklist_principal(principal: Principal) -> Result<(), String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "unblacklist_principal")?;
+
+    if !crate::helpers::is_admin(&caller) {
+        return Err("Unauthorized: Only admins can unblacklist principals".to_string());
+    }
+
+    remove_blacklist_entry(&principal)
+        .ok_or_else(|| format!("Principal {} is not blacklisted", principal.to_text()))?;
+
+    FAILED_AUTH_ATTEMPTS.with(|attempts| {
+        attempts.borrow_mut().remove(&principal);
+    });
+
+    ADMIN_ACTIONS_LOG.with(|log| {
+        log.borrow_mut().push((time(), caller, format!("Unblacklisted principal: {}", principal.to_text())));
+    });
+
+    log_security_event(
+        "UNBLACKLIST_PRINCIPAL",
+        AuditEventLevel::Warning,
+        format!("Admin {} removed principal {} from the blacklist", caller.to_text(), principal.to_text()),
+        Some(principal),
+        vec![],
+    );
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Check whether a principal is blacklisted
+#[query]
+pub fn is_blacklisted(principal: Principal) -> bool {
+    get_blacklist_entry(&principal).is_some()
+}
+
+/// List all blacklisted principals (admin only)
+#[query]
+pub fn get_blacklist() -> Result<Vec<BlacklistEntry>, String> {
+    let caller = ic_cdk::caller();
+
+    if !crate::helpers::is_admin(&caller) {
+        return Err("Unauthorized: Only admins can view the blacklist".to_string());
+    }
+
+    Ok(get_all_blacklist_entries())
+}
+
+// ========== BTC ADDRESS DENYLIST ==========
+
+/// Bech32 addresses are case-insensitive, so compare them lowercased; base58 addresses are case-sensitive
+pub fn normalize_btc_address(address: &str) -> String {
+    let trimmed = address.trim();
+    let lower = trimmed.to_lowercase();
+    if lower.starts_with("bc1") || lower.starts_with("tb1") || lower.starts_with("bcrt1") {
+        lower
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// First rule that denies the address, if any
+pub fn find_btc_deny_rule(address: &str, rules: &[BtcAddressDenyRule]) -> Option<BtcAddressDenyRule> {
+    let normalized = normalize_btc_address(address);
+    rules.iter().find(|rule| rule.matches(&normalized)).cloned()
+}
+
+/// Reject a denied destination, raising a critical security event
+pub fn ensure_btc_address_allowed(address: &str, operation: &str, subject: Option<Principal>) -> Result<(), String> {
+    let rule = match find_btc_deny_rule(address, &get_all_btc_deny_rules()) {
+        Some(rule) => rule,
+        None => return Ok(()),
+    };
+
+    log_security_event(
+        "DENIED_BTC_ADDRESS_BLOCKED",
+        AuditEventLevel::Critical,
+        format!("{} to denied Bitcoin address {} blocked ({:?} rule {})", operation, address, rule.kind, rule.pattern),
+        subject,
+        vec![
+            "DENIED_BTC_ADDRESS".to_string(),
+            format!("OPERATION:{}", operation),
+            format!("DENY_REASON:{}", rule.reason),
+        ],
+    );
+
+    Err("Destination Bitcoin address is not allowed".to_string())
+}
+
+/// Add an address, or an address prefix, to the disbursement denylist (admin only)
+#[update]
+pub fn add_denied_btc_address(pattern: String, is_prefix: bool, reason: String) -> Result<(), String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "add_denied_btc_address")?;
+
+    if !crate::helpers::is_admin(&caller) {
+        return Err("Unauthorized: Only admins can manage the Bitcoin address denylist".to_string());
+    }
+
+    let pattern = normalize_btc_address(&pattern);
+    if pattern.is_empty() {
+        return Err("Address or prefix cannot be empty".to_string());
+    }
+    // Short prefixes such as "bc1" would block every address of a type
+    if is_prefix && pattern.len() < 6 {
+        return Err("Prefix rules must be at least 6 characters".to_string());
+    }
+    if reason.trim().is_empty() {
+        return Err("A reason is required to deny an address".to_string());
+    }
+
+    let kind = if is_prefix { BtcDenyRuleKind::Prefix } else { BtcDenyRuleKind::Exact };
+    store_btc_deny_rule(BtcAddressDenyRule {
+        pattern: pattern.clone(),
+        kind,
+        reason: reason.clone(),
+        added_by: caller,
+        added_at: time(),
+    });
+
+    log_security_event(
+        "BTC_ADDRESS_DENIED",
+        AuditEventLevel::Warning,
+        format!("Admin {} added {:?} denylist rule {}: {}", caller.to_text(), kind, pattern, reason),
+        None,
+        vec!["BTC_ADDRESS_DENYLIST".to_string()],
+    );
+    Ok(())
+}
+
+/// Remove an address or prefix rule from the denylist (admin only)
+#[update]
+pub fn remove_denied_btc_address(pattern: String, is_prefix: bool) -> Result<(), String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "remove_denied_btc_address")?;
+
+    if !crate::helpers::is_admin(&caller) {
+        return Err("Unauthorized: Only admins can manage the Bitcoin address denylist".to_string());
+    }
+
+    let pattern = normalize_btc_address(&pattern);
+    let kind = if is_prefix { BtcDenyRuleKind::Prefix } else { BtcDenyRuleKind::Exact };
+    remove_btc_deny_rule(kind, &pattern)
+        .ok_or_else(|| format!("No {:?} denylist rule for {}", kind, pattern))?;
+
+    log_security_event(
+        "BTC_ADDRESS_UNDENIED",
+        AuditEventLevel::Warning,
+        format!("Admin {} removed {:?} denylist rule {}", caller.to_text(), kind, pattern),
+        None,
+        vec!["BTC_ADDRESS_DENYLIST".to_string()],
+    );
+    Ok(())
+}
+
+/// Whether funds may be sent to this address. Deny reasons are only visible to admins.
+#[query]
+pub fn check_btc_address_allowed(address: String) -> bool {
+    find_btc_deny_rule(&address, &get_all_btc_deny_rules()).is_none()
+}
+
+/// List all denylist rules (admin only)
+#[query]
+pub fn get_btc_address_denylist() -> Result<Vec<BtcAddressDenyRule>, String> {
+    let caller = ic_cdk::caller();
+
+    if !crate::helpers::is_admin(&caller) {
+        return Err("Unauthorized: Only admins can view the Bitcoin address denylist".to_string());
+    }
+
+    Ok(get_all_btc_deny_rules())
+}
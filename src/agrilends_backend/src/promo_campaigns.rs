@@ -0,0 +1,261 @@
+// ========== PROMO CAMPAIGNS MODULE ==========
+// Zero- or reduced-interest campaigns that marketing runs for selected farmers or commodities.
+// A campaign only changes the APR a new loan is priced at; LTV, collateral floor and exposure
+// checks in origination run unchanged. Promo loans carry RateType::Promotional and are never
+// picked up by variable-rate resets.
+
+use ic_cdk::{caller, api::time};
+use ic_cdk_macros::{query, update};
+use candid::Principal;
+
+use crate::types::*;
+use crate::storage::{
+    next_promo_campaign_id, store_promo_campaign, get_promo_campaign, get_all_promo_campaigns,
+    get_protocol_parameters
+};
+use crate::helpers::{is_admin, log_audit_action};
+
+const MAX_ELIGIBLE_PRINCIPALS: usize = 500;
+
+/// Campaign APR in whole percent, matching `Loan::apr`
+pub fn promo_apr(campaign: &PromoCampaign) -> u64 {
+    campaign.rate_override_bps / 100
+}
+
+/// Check a new campaign's terms. A promo must undercut the base APR and target someone.
+pub fn validate_promo_campaign(campaign: &PromoCampaign, base_apr: u64, now: u64) -> Result<(), String> {
+    if campaign.name.trim().is_empty() {
+        return Err("Campaign name cannot be empty".to_string());
+    }
+    if campaign.rate_override_bps % 100 != 0 {
+        return Err("Promo rate must be a whole percentage (a multiple of 100 bps)".to_string());
+    }
+    if promo_apr(campaign) >= base_apr {
+        return Err(format!(
+            "Promo rate {} bps must be below the base APR of {}%",
+            campaign.rate_override_bps, base_apr
+        ));
+    }
+    if campaign.eligible_principals.is_empty() && campaign.eligible_commodity.is_none() {
+        return Err("A campaign must target eligible principals, a commodity, or both".to_string());
+    }
+    if campaign.eligible_principals.len() > MAX_ELIGIBLE_PRINCIPALS {
+        return Err(format!("A campaign can list at most {} eligible principals", MAX_ELIGIBLE_PRINCIPALS));
+    }
+    if campaign.ends_at <= campaign.starts_at || campaign.ends_at <= now {
+        return Err("Campaign must end after it starts and in the future".to_string());
+    }
+    if campaign.max_loans == 0 || campaign.budget == 0 {
+        return Err("Campaign loan cap and budget must be greater than zero".to_string());
+    }
+    Ok(())
+}
+
+/// Active, inside its window, and with room left under both caps for `amount`
+pub fn promo_has_capacity(campaign: &PromoCampaign, amount: u64, now: u64) -> bool {
+    campaign.is_active
+        && now >= campaign.starts_at
+        && now < campaign.ends_at
+        && campaign.loans_used < campaign.max_loans
+        && campaign.budget_used.saturating_add(amount) <= campaign.budget
+}
+
+/// Whether `borrower` borrowing against `commodity` is targeted by the campaign
+pub fn promo_targets(campaign: &PromoCampaign, borrower: &Principal, commodity: &str) -> bool {
+    let principal_ok = campaign.eligible_principals.is_empty() || campaign.eligible_principals.contains(borrower);
+    let commodity_ok = campaign.eligible_commodity.as_ref()
+        .map_or(true, |eligible| eligible.eq_ignore_ascii_case(commodity));
+    principal_ok && commodity_ok
+}
+
+/// Best campaign for a new loan: lowest rate, then oldest campaign
+pub fn select_promo_campaign<'a>(
+    campaigns: &'a [PromoCampaign],
+    borrower: &Principal,
+    commodity: &str,
+    amount: u64,
+    now: u64,
+) -> Option<&'a PromoCampaign> {
+    campaigns.iter()
+        .filter(|campaign| promo_targets(campaign, borrower, commodity) && promo_has_capacity(campaign, amount, now))
+        .min_by_key(|campaign| (campaign.rate_override_bps, campaign.id))
+}
+
+/// Reserve campaign capacity for a loan being originated. Returns the campaign whose rate applies.
+pub fn claim_promo_rate(borrower: Principal, commodity: &str, loan_id: u64, amount: u64, now: u64) -> Option<PromoCampaign> {
+    let campaigns = get_all_promo_campaigns();
+    let mut campaign = select_promo_campaign(&campaigns, &borrower, commodity, amount, now)?.clone();
+
+    campaign.loans_used += 1;
+    campaign.budget_used += amount;
+    campaign.loan_ids.push(loan_id);
+    store_promo_campaign(campaign.clone());
+
+    log_audit_action(
+        borrower,
+        "PROMO_RATE_APPLIED".to_string(),
+        format!(
+            "Loan #{} priced at {} bps under promo campaign #{} ({}/{} loans, {}/{} budget)",
+            loan_id, campaign.rate_override_bps, campaign.id,
+            campaign.loans_used, campaign.max_loans, campaign.budget_used, campaign.budget
+        ),
+        true,
+    );
+
+    Some(campaign)
+}
+
+/// Return a promo loan's share of its campaign caps when the application does not go ahead
+pub fn release_promo_usage(loan: &Loan) {
+    let campaign_id = match &loan.rate_type {
+        Some(RateType::Promotional(terms)) => terms.campaign_id,
+        _ => return,
+    };
+    let mut campaign = match get_promo_campaign(campaign_id) {
+        Some(campaign) => campaign,
+        None => return,
+    };
+    let before = campaign.loan_ids.len();
+    campaign.loan_ids.retain(|id| *id != loan.id);
+    if campaign.loan_ids.len() == before {
+        return;
+    }
+    campaign.loans_used = campaign.loans_used.saturating_sub(1);
+    campaign.budget_used = campaign.budget_used.saturating_sub(loan.amount_approved);
+    store_promo_campaign(campaign);
+}
+
+fn promo_usage(campaign: &PromoCampaign, now: u64) -> PromoUsage {
+    PromoUsage {
+        campaign_id: campaign.id,
+        loans_used: campaign.loans_used,
+        max_loans: campaign.max_loans,
+        budget_used: campaign.budget_used,
+        budget: campaign.budget,
+        remaining_budget: campaign.budget.saturating_sub(campaign.budget_used),
+        loan_ids: campaign.loan_ids.clone(),
+        is_running: promo_has_capacity(campaign, 0, now),
+    }
+}
+
+/// Create a promotional rate campaign (admin only). Times are in nanoseconds.
+#[update]
+pub fn create_promo_campaign(
+    name: String,
+    rate_override_bps: u64,
+    eligible_principals: Vec<Principal>,
+    eligible_commodity: Option<String>,
+    starts_at: u64,
+    ends_at: u64,
+    max_loans: u64,
+    budget: u64,
+) -> Result<PromoCampaign, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "create_promo_campaign")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can manage promo campaigns".to_string());
+    }
+    crate::feature_flags::ensure_feature_enabled(crate::feature_flags::FEATURE_PROMO_CAMPAIGNS)?;
+
+    let now = time();
+    let mut eligible_principals = eligible_principals;
+    eligible_principals.sort();
+    eligible_principals.dedup();
+    let mut campaign = PromoCampaign {
+        id: 0,
+        name: name.trim().to_string(),
+        rate_override_bps,
+        eligible_principals,
+        eligible_commodity: eligible_commodity
+            .map(|commodity| commodity.trim().to_string())
+            .filter(|commodity| !commodity.is_empty()),
+        starts_at,
+        ends_at,
+        max_loans,
+        budget,
+        loans_used: 0,
+        budget_used: 0,
+        loan_ids: Vec::new(),
+        is_active: true,
+        created_by: caller,
+        created_at: now,
+    };
+    validate_promo_campaign(&campaign, get_protocol_parameters().base_apr, now)?;
+
+    campaign.id = next_promo_campaign_id();
+    store_promo_campaign(campaign.clone());
+
+    log_audit_action(
+        caller,
+        "PROMO_CAMPAIGN_CREATED".to_string(),
+        format!(
+            "Promo campaign #{} '{}' at {} bps for {} principal(s), commodity {:?}; cap {} loans / {} satoshi",
+            campaign.id, campaign.name, campaign.rate_override_bps, campaign.eligible_principals.len(),
+            campaign.eligible_commodity, campaign.max_loans, campaign.budget
+        ),
+        true,
+    );
+
+    Ok(campaign)
+}
+
+/// End a campaign early (admin only). Loans already priced under it keep their rate.
+#[update]
+pub fn end_promo_campaign(campaign_id: u64) -> Result<PromoCampaign, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "end_promo_campaign")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can manage promo campaigns".to_string());
+    }
+
+    let mut campaign = get_promo_campaign(campaign_id)
+        .ok_or_else(|| format!("Promo campaign #{} not found", campaign_id))?;
+    if !campaign.is_active {
+        return Err(format!("Promo campaign #{} has already ended", campaign_id));
+    }
+    campaign.is_active = false;
+    store_promo_campaign(campaign.clone());
+
+    log_audit_action(
+        caller,
+        "PROMO_CAMPAIGN_ENDED".to_string(),
+        format!("Promo campaign #{} ended with {} loans and {} satoshi used", campaign_id, campaign.loans_used, campaign.budget_used),
+        true,
+    );
+
+    Ok(campaign)
+}
+
+/// Campaigns currently open. Admins see all of them; other callers see those open to them.
+#[query]
+pub fn get_active_promos() -> Vec<PromoCampaign> {
+    let caller = caller();
+    let now = time();
+    let admin = is_admin(&caller);
+
+    get_all_promo_campaigns()
+        .into_iter()
+        .filter(|campaign| promo_has_capacity(campaign, 0, now))
+        .filter(|campaign| admin || campaign.eligible_principals.is_empty() || campaign.eligible_principals.contains(&caller))
+        .map(|mut campaign| {
+            if !admin {
+                campaign.eligible_principals.clear();
+                campaign.loan_ids.clear();
+            }
+            campaign
+        })
+        .collect()
+}
+
+/// Usage of a campaign against its loan and budget caps (admin only)
+#[query]
+pub fn get_promo_usage(campaign_id: u64) -> Result<PromoUsage, String> {
+    let caller = caller();
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can view promo usage".to_string());
+    }
+
+    let campaign = get_promo_campaign(campaign_id)
+        .ok_or_else(|| format!("Promo campaign #{} not found", campaign_id))?;
+    Ok(promo_usage(&campaign, time()))
+}
@@ -10,6 +10,12 @@ use crate::helpers::*;
 
 // ICRC-7 Standard Implementation
 
+const COLLECTION_NAME: &str = "Agrilends RWA Collateral";
+const COLLECTION_SYMBOL: &str = "AGRI-RWA";
+const COLLECTION_DESCRIPTION: &str = "Tokenized agricultural warehouse receipts pledged as loan collateral on Agrilends";
+const DEFAULT_TOKENS_PAGE_SIZE: u64 = 100;
+const MAX_TOKENS_PAGE_SIZE: u64 = 1_000;
+
 /// Emergency stop check
 fn check_emergency_stop() -> Result<(), String> {
     let config = get_config();
@@ -32,6 +38,9 @@ pub fn mint_nft(owner: Principal, metadata: Vec<(String, MetadataValue)>) -> RWA
     }
     
     let caller = ic_cdk::caller();
+    if let Err(e) = crate::validation::guard_caller(&caller, "mint_nft") {
+        return RWANFTResult::Err(e);
+    }
     
     // Rate limiting
     if let Err(e) = check_rate_limit(&caller, 10) { // Max 10 mints per minute
@@ -63,9 +72,14 @@ pub fn mint_nft(owner: Principal, metadata: Vec<(String, MetadataValue)>) -> RWA
     
     // Validate valuation limits
     let (_, valuation_idr, _) = extract_metadata_values(&metadata);
-    if valuation_idr < config.min_collateral_value || valuation_idr > config.max_collateral_value {
+    if let Err(e) = crate::helpers::ensure_min_collateral_value(valuation_idr) {
+        let error = e.to_string();
+        log_action("mint_nft", &error, false);
+        return RWANFTResult::Err(error);
+    }
+    if valuation_idr < Idr(config.min_collateral_value) || valuation_idr > Idr(config.max_collateral_value) {
         let error = format!("Valuation {} is outside allowed range: {} - {}", 
-                          valuation_idr, config.min_collateral_value, config.max_collateral_value);
+                          valuation_idr.0, config.min_collateral_value, config.max_collateral_value);
         log_action("mint_nft", &error, false);
         return RWANFTResult::Err(error);
     }
@@ -164,19 +178,130 @@ pub fn get_nft_stats() -> NFTStats {
     })
 }
 
+// ========== ICRC-7 / ICRC-10 QUERIES ==========
+
+#[query]
+pub fn icrc7_name() -> String {
+    COLLECTION_NAME.to_string()
+}
+
+#[query]
+pub fn icrc7_symbol() -> String {
+    COLLECTION_SYMBOL.to_string()
+}
+
+/// Number of tokens in the collection
+#[query]
+pub fn icrc7_total_supply() -> u64 {
+    RWA_NFTS.with(|nfts| nfts.borrow().len())
+}
+
+#[query]
+pub fn icrc7_collection_metadata() -> Vec<(String, MetadataValue)> {
+    vec![
+        ("icrc7:name".to_string(), MetadataValue::Text(icrc7_name())),
+        ("icrc7:symbol".to_string(), MetadataValue::Text(icrc7_symbol())),
+        ("icrc7:description".to_string(), MetadataValue::Text(COLLECTION_DESCRIPTION.to_string())),
+        ("icrc7:total_supply".to_string(), MetadataValue::Nat(icrc7_total_supply())),
+        ("icrc7:max_take_value".to_string(), MetadataValue::Nat(MAX_TOKENS_PAGE_SIZE)),
+    ]
+}
+
+/// Standard ICRC-7 fields first, then the Agrilends collateral fields as `rwa:` extension metadata.
+/// Caller-supplied `icrc7:` keys are dropped so the standard fields always come from the canister.
+pub fn build_token_metadata(nft: &RWANFTData) -> Vec<(String, MetadataValue)> {
+    let (_, _, asset_description) = extract_metadata_values(&nft.metadata);
+    let mut metadata = vec![
+        ("icrc7:name".to_string(), MetadataValue::Text(format!("{} #{}", COLLECTION_NAME, nft.token_id))),
+        ("icrc7:description".to_string(), MetadataValue::Text(asset_description)),
+    ];
+    metadata.extend(
+        nft.metadata.iter()
+            .filter(|(key, _)| !key.starts_with("icrc7:"))
+            .cloned()
+    );
+    metadata.push(("rwa:is_locked".to_string(), MetadataValue::Bool(nft.is_locked)));
+    if let Some(loan_id) = nft.loan_id {
+        metadata.push(("rwa:loan_id".to_string(), MetadataValue::Nat(loan_id)));
+    }
+    metadata
+}
+
+#[query]
+pub fn icrc7_token_metadata(token_ids: Vec<u64>) -> Vec<Option<Vec<(String, MetadataValue)>>> {
+    token_ids.into_iter()
+        .map(|token_id| get_nft_by_token_id(token_id).map(|nft| build_token_metadata(&nft)))
+        .collect()
+}
+
+/// Token IDs in ascending order, starting after `prev`
+#[query]
+pub fn icrc7_tokens(prev: Option<u64>, take: Option<u64>) -> Vec<u64> {
+    let take = take.unwrap_or(DEFAULT_TOKENS_PAGE_SIZE).min(MAX_TOKENS_PAGE_SIZE) as usize;
+    let start = match prev {
+        Some(prev) => match prev.checked_add(1) {
+            Some(start) => start,
+            None => return Vec::new(),
+        },
+        None => 0,
+    };
+
+    RWA_NFTS.with(|nfts| {
+        nfts.borrow()
+            .range(start..)
+            .take(take)
+            .map(|(token_id, _)| token_id)
+            .collect()
+    })
+}
+
+#[query]
+pub fn icrc10_supported_standards() -> Vec<SupportedStandard> {
+    vec![
+        SupportedStandard {
+            name: "ICRC-7".to_string(),
+            url: "https://github.com/dfinity/ICRC/ICRCs/ICRC-7".to_string(),
+        },
+        SupportedStandard {
+            name: "ICRC-10".to_string(),
+            url: "https://github.com/dfinity/ICRC/ICRCs/ICRC-10".to_string(),
+        },
+    ]
+}
+
+/// Check whether the caller may transfer this NFT. Collateralized tokens are
+/// non-transferable until the loan is closed and the lock is cleared.
+pub fn validate_transfer(nft: &RWANFTData, caller: &Principal) -> TransferResult {
+    if nft.owner != *caller {
+        return TransferResult::Err("Unauthorized: You don't own this NFT".to_string());
+    }
+    
+    if nft.is_locked || nft.loan_id.is_some() {
+        return TransferResult::CollateralLocked {
+            token_id: nft.token_id,
+            loan_id: nft.loan_id,
+        };
+    }
+    
+    TransferResult::Ok
+}
+
 /// Transfer NFT (ICRC-7 compliance)
 #[update]
 pub fn transfer(request: TransferRequest) -> TransferResult {
     let caller = caller();
+    if let Err(e) = crate::validation::guard_caller(&caller, "transfer") {
+        return TransferResult::Err(e);
+    }
     
-    // Verify ownership
+    // Verify ownership and collateral lock
     if let Some(nft) = get_nft_by_token_id(request.token_id) {
-        if nft.owner != caller {
-            return TransferResult::Err("Unauthorized: You don't own this NFT".to_string());
-        }
-        
-        if nft.is_locked {
-            return TransferResult::Err("NFT is locked and cannot be transferred".to_string());
+        match validate_transfer(&nft, &caller) {
+            TransferResult::Ok => {},
+            rejected => {
+                log_action("transfer", &format!("Transfer of NFT #{} rejected: {:?}", request.token_id, rejected), false);
+                return rejected;
+            }
         }
         
         // Update ownership
@@ -195,3 +320,389 @@ pub fn transfer(request: TransferRequest) -> TransferResult {
         TransferResult::Err("NFT not found".to_string())
     }
 }
+
+/// The NFT `settle_collateral_transfer` would move, if it is still the loan's collateral
+pub fn ensure_collateral_settleable(token_id: u64, loan_id: u64) -> Result<RWANFTData, String> {
+    let nft = get_nft_by_token_id(token_id).ok_or_else(|| "NFT not found".to_string())?;
+    if nft.loan_id != Some(loan_id) {
+        return Err(format!("NFT #{} is not collateral for loan #{}", token_id, loan_id));
+    }
+    Ok(nft)
+}
+
+/// Settlement transfer of collateral to a liquidation buyer. This is the only
+/// path that moves a locked NFT; it clears the lock as part of settlement.
+pub fn settle_collateral_transfer(token_id: u64, new_owner: Principal, loan_id: u64) -> Result<(), String> {
+    let nft = ensure_collateral_settleable(token_id, loan_id)?;
+    
+    let previous_owner = nft.owner;
+    RWA_NFTS.with(|nfts| {
+        let mut nfts_map = nfts.borrow_mut();
+        if let Some(mut nft_data) = nfts_map.get(&token_id) {
+            nft_data.owner = new_owner;
+            nft_data.is_locked = false;
+            nft_data.loan_id = None;
+            nft_data.updated_at = time();
+            nfts_map.insert(token_id, nft_data);
+        }
+    });
+    
+    log_audit_action(
+        caller(),
+        "COLLATERAL_SETTLEMENT_TRANSFER".to_string(),
+        format!(
+            "NFT #{} for loan #{} transferred from {} to {} at settlement",
+            token_id, loan_id, previous_owner.to_text(), new_owner.to_text()
+        ),
+        true,
+    );
+    
+    Ok(())
+}
+
+/// Admin-forced collateral transfer for off-chain liquidation settlement
+#[update]
+pub fn admin_force_transfer_collateral(token_id: u64, new_owner: Principal, loan_id: u64) -> Result<(), String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "admin_force_transfer_collateral")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can force collateral transfers".to_string());
+    }
+    
+    let loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    if loan.status != LoanStatus::Defaulted {
+        return Err("Collateral can only be force-transferred for liquidated loans".to_string());
+    }
+    
+    settle_collateral_transfer(token_id, new_owner, loan_id)
+}
+
+// ========== COLLATERAL RE-ATTESTATION ==========
+
+/// Authorize a principal to re-attest warehouse receipts (admin only)
+#[update]
+pub fn add_collateral_attestor(attestor: Principal) -> Result<String, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "add_collateral_attestor")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admin can manage attestors".to_string());
+    }
+
+    COLLATERAL_ATTESTORS.with(|attestors| {
+        attestors.borrow_mut().insert(attestor, time());
+    });
+    log_action("add_collateral_attestor", &format!("Attestor {} authorized", attestor.to_text()), true);
+
+    Ok(format!("Attestor {} authorized", attestor.to_text()))
+}
+
+/// Revoke a collateral attestor (admin only)
+#[update]
+pub fn remove_collateral_attestor(attestor: Principal) -> Result<String, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "remove_collateral_attestor")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admin can manage attestors".to_string());
+    }
+
+    let removed = COLLATERAL_ATTESTORS.with(|attestors| attestors.borrow_mut().remove(&attestor));
+    if removed.is_none() {
+        return Err("Principal is not an attestor".to_string());
+    }
+    log_action("remove_collateral_attestor", &format!("Attestor {} revoked", attestor.to_text()), true);
+
+    Ok(format!("Attestor {} revoked", attestor.to_text()))
+}
+
+/// Replace the warehouse receipt hash of an RWA-NFT after the receipt is renewed. Pass the
+/// renewed receipt's expiry; it is required when the current receipt has already expired.
+#[update]
+pub fn reattest_collateral(
+    token_id: u64,
+    new_sha256_hash: String,
+    attestation_doc_ref: String,
+    new_receipt_expiry: Option<u64>,
+) -> Result<NFTAttestationHistory, String> {
+    check_emergency_stop()?;
+
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "reattest_collateral")?;
+    if !is_collateral_attestor(&caller) && !is_admin(&caller) {
+        log_action("reattest_collateral", "Unauthorized re-attestation attempt", false);
+        return Err("Unauthorized: Only authorized attestors can re-attest collateral".to_string());
+    }
+
+    if !validate_sha256_hash(&new_sha256_hash) {
+        return Err("Invalid SHA-256 hash format".to_string());
+    }
+    if attestation_doc_ref.trim().is_empty() {
+        return Err("Attestation document reference is required".to_string());
+    }
+
+    let mut nft = get_nft_data(token_id).ok_or_else(|| "NFT not found".to_string())?;
+
+    if crate::liquidation::is_collateral_in_active_liquidation(token_id) {
+        return Err("Cannot re-attest collateral during an active liquidation".to_string());
+    }
+
+    let (previous_hash, _, _) = extract_metadata_values(&nft.metadata);
+    if previous_hash == new_sha256_hash {
+        return Err("New hash matches the current attestation".to_string());
+    }
+
+    let now = time();
+    match new_receipt_expiry {
+        Some(expiry) if expiry <= now => {
+            return Err("New receipt expiry must be in the future".to_string());
+        }
+        None if receipt_expiry_status(get_receipt_expiry_from_metadata(&nft.metadata), now, 0) == ReceiptExpiryStatus::Expired => {
+            return Err("The current receipt has expired; provide the renewed receipt's expiry".to_string());
+        }
+        _ => {}
+    }
+
+    // Collateral backing a live loan must stay within the commodity LTV
+    let backing_loan = nft.loan_id
+        .and_then(get_loan)
+        .filter(|loan| matches!(loan.status, LoanStatus::Approved | LoanStatus::Active | LoanStatus::Defaulted));
+    if let Some(loan) = &backing_loan {
+        if loan.status == LoanStatus::Defaulted {
+            return Err("Cannot re-attest collateral during an active liquidation".to_string());
+        }
+        let ltv_ratio = get_loan_commodity(loan)
+            .map(|commodity| get_commodity_ltv_ratio(&commodity))
+            .unwrap_or_else(|| get_protocol_parameters().loan_to_value_ratio);
+        check_reattestation_ltv(loan, ltv_ratio, now)?;
+    }
+
+    // Update the receipt hash and expiry on the NFT and its collateral record
+    for (key, value) in nft.metadata.iter_mut() {
+        if key == "rwa:legal_doc_hash" {
+            *value = MetadataValue::Text(new_sha256_hash.clone());
+        }
+    }
+    if let Some(expiry) = new_receipt_expiry {
+        nft.metadata.retain(|(key, _)| key != "rwa:receipt_expiry");
+        nft.metadata.push(("rwa:receipt_expiry".to_string(), MetadataValue::Nat(expiry)));
+    }
+    nft.updated_at = now;
+    RWA_NFTS.with(|nfts| {
+        nfts.borrow_mut().insert(token_id, nft.clone());
+    });
+
+    if let Some(mut record) = get_collateral_by_nft_token_id(token_id) {
+        record.legal_doc_hash = new_sha256_hash.clone();
+        record.updated_at = now;
+        COLLATERAL_RECORDS.with(|records| {
+            records.borrow_mut().insert(record.collateral_id, record);
+        });
+    }
+
+    let history = record_attestation(token_id, AttestationRecord {
+        version: 0,
+        previous_hash: previous_hash.clone(),
+        new_hash: new_sha256_hash.clone(),
+        attestation_doc_ref: attestation_doc_ref.clone(),
+        attestor: caller,
+        timestamp: now,
+        receipt_expiry: new_receipt_expiry,
+    });
+
+    let description = format!(
+        "NFT #{} re-attested to version {} (ref: {}), previous hash {}",
+        token_id, history.attestation_version, attestation_doc_ref, previous_hash
+    );
+    log_action("reattest_collateral", &description, true);
+
+    if let Some(loan) = backing_loan {
+        use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+        log_audit_enhanced(
+            AuditCategory::NFTOperations,
+            "COLLATERAL_REATTESTED_WITH_ACTIVE_LOAN".to_string(),
+            AuditEventLevel::Critical,
+            AuditDetails {
+                description: format!("{} while backing loan #{}", description, loan.id),
+                entity_type: Some("nft".to_string()),
+                entity_id: Some(token_id.to_string()),
+                affected_principals: vec![loan.borrower],
+                ..Default::default()
+            },
+            AuditResult {
+                success: true,
+                error_code: None,
+                error_message: None,
+                execution_time_ms: None,
+                gas_used: None,
+                cycles_consumed: None,
+                memory_used_bytes: None,
+                warning_flags: vec!["ACTIVE_LOAN_COLLATERAL".to_string()],
+            },
+            None,
+        );
+    }
+
+    Ok(history)
+}
+
+/// Collateral backing a live loan may only be re-attested while the remaining debt, accrued
+/// interest and late penalty included, stays within `ltv_ratio` percent of the collateral value
+pub fn check_reattestation_ltv(loan: &Loan, ltv_ratio: u64, now: u64) -> Result<(), String> {
+    let max_debt = (loan.collateral_value_btc * ltv_ratio) / 100;
+    let outstanding = crate::liquidation::remaining_debt_at(loan, now);
+    if outstanding > max_debt {
+        return Err(format!(
+            "Loan #{} is outside LTV ({} outstanding, {} allowed); re-attestation blocked",
+            loan.id, outstanding, max_debt
+        ));
+    }
+    Ok(())
+}
+
+/// Append a re-attestation to the NFT's history as the next version and return the history
+pub fn record_attestation(token_id: u64, mut record: AttestationRecord) -> NFTAttestationHistory {
+    let mut history = load_attestation_history(token_id);
+    history.attestation_version += 1;
+    record.version = history.attestation_version;
+    history.records.push(record);
+    store_attestation_history(token_id, history.clone());
+    history
+}
+
+/// Get warehouse receipt attestation history for an NFT
+#[query]
+pub fn get_attestation_history(token_id: u64) -> Result<NFTAttestationHistory, String> {
+    get_nft_data(token_id).ok_or_else(|| "NFT not found".to_string())?;
+    Ok(load_attestation_history(token_id))
+}
+
+// ========== WAREHOUSE RECEIPT EXPIRY ==========
+
+const RECEIPT_EXPIRY_CHECK_INTERVAL: u64 = 24 * 60 * 60 * 1_000_000_000; // Daily
+
+thread_local! {
+    static LAST_RECEIPT_EXPIRY_CHECK: std::cell::RefCell<u64> = std::cell::RefCell::new(0);
+    // loan_id -> (receipt expiry, status) last alerted, so each stage is announced once per receipt
+    static RECEIPT_EXPIRY_ALERTS: std::cell::RefCell<std::collections::HashMap<u64, (u64, ReceiptExpiryStatus)>> =
+        std::cell::RefCell::new(std::collections::HashMap::new());
+}
+
+/// Approved and active loans whose collateral receipt expires within `window` of `now`,
+/// including ones already expired, soonest first
+fn collateral_with_receipt_expiring(now: u64, window: u64) -> Vec<ExpiringCollateral> {
+    let mut expiring: Vec<ExpiringCollateral> = get_all_loans_data()
+        .into_iter()
+        .filter(|loan| matches!(loan.status, LoanStatus::Approved | LoanStatus::Active))
+        .filter_map(|loan| {
+            let expiry = get_nft_data(loan.nft_id)
+                .and_then(|nft| get_receipt_expiry_from_metadata(&nft.metadata))?;
+            let status = receipt_expiry_status(Some(expiry), now, window);
+            if !matches!(status, ReceiptExpiryStatus::ExpiringSoon | ReceiptExpiryStatus::Expired) {
+                return None;
+            }
+            Some(ExpiringCollateral {
+                loan_id: loan.id,
+                nft_id: loan.nft_id,
+                borrower: loan.borrower,
+                loan_status: loan.status,
+                receipt_expiry: expiry,
+                status,
+            })
+        })
+        .collect();
+    expiring.sort_by_key(|entry| entry.receipt_expiry);
+    expiring
+}
+
+/// Number of approved and active loans backed by an expired warehouse receipt
+pub fn count_loans_with_expired_receipts(now: u64) -> u64 {
+    collateral_with_receipt_expiring(now, 0).len() as u64
+}
+
+/// Loan collateral whose warehouse receipt expires within `within_days`, or has expired (admin only)
+#[query]
+pub fn get_expiring_collateral(within_days: u64) -> Result<Vec<ExpiringCollateral>, String> {
+    if !is_admin(&caller()) {
+        return Err("Unauthorized: Only admins can view expiring collateral".to_string());
+    }
+    let window = within_days.saturating_mul(24 * 60 * 60 * 1_000_000_000);
+    Ok(collateral_with_receipt_expiring(time(), window))
+}
+
+/// Heartbeat task: warn borrowers once when their collateral receipt enters the warning
+/// window and again when it expires. Borrowers cure by having the receipt re-attested.
+pub async fn receipt_expiry_check_task() -> Result<String, String> {
+    let now = time();
+    let last_run = LAST_RECEIPT_EXPIRY_CHECK.with(|last| *last.borrow());
+    if now.saturating_sub(last_run) < RECEIPT_EXPIRY_CHECK_INTERVAL {
+        return Ok("Receipt expiry check not due yet".to_string());
+    }
+    LAST_RECEIPT_EXPIRY_CHECK.with(|last| *last.borrow_mut() = now);
+
+    let mut alerted = 0u64;
+    for entry in collateral_with_receipt_expiring(now, receipt_expiry_warning_window()) {
+        let already_alerted = RECEIPT_EXPIRY_ALERTS.with(|alerts| {
+            alerts.borrow().get(&entry.loan_id) == Some(&(entry.receipt_expiry, entry.status.clone()))
+        });
+        if already_alerted {
+            continue;
+        }
+        alert_receipt_expiry(&entry, now);
+        RECEIPT_EXPIRY_ALERTS.with(|alerts| {
+            alerts.borrow_mut().insert(entry.loan_id, (entry.receipt_expiry, entry.status.clone()));
+        });
+        alerted += 1;
+    }
+
+    Ok(format!("Receipt expiry check: {} alerts sent", alerted))
+}
+
+fn alert_receipt_expiry(entry: &ExpiringCollateral, now: u64) {
+    use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+    use crate::notification_system::{create_notification, NotificationEvent, NotificationPriority};
+
+    let expired = entry.status == ReceiptExpiryStatus::Expired;
+    let message = if expired {
+        format!(
+            "The warehouse receipt backing loan #{} (NFT #{}) has expired. Have it re-attested; you cannot borrow against it until then.",
+            entry.loan_id, entry.nft_id
+        )
+    } else {
+        format!(
+            "The warehouse receipt backing loan #{} (NFT #{}) expires in {} days. Have it re-attested before then.",
+            entry.loan_id, entry.nft_id, (entry.receipt_expiry - now) / (24 * 60 * 60 * 1_000_000_000)
+        )
+    };
+
+    let mut data = std::collections::HashMap::new();
+    data.insert("loan_id".to_string(), entry.loan_id.to_string());
+    data.insert("nft_id".to_string(), entry.nft_id.to_string());
+    data.insert("receipt_expiry".to_string(), entry.receipt_expiry.to_string());
+    data.insert("message".to_string(), message.clone());
+    let event_type = if expired { "collateral_receipt_expired" } else { "collateral_receipt_expiring" };
+    let event = NotificationEvent::Custom { event_type: event_type.to_string(), data };
+    let _ = create_notification(entry.borrower, event, None, Some(NotificationPriority::High));
+
+    log_audit_enhanced(
+        AuditCategory::NFTOperations,
+        if expired { "COLLATERAL_RECEIPT_EXPIRED" } else { "COLLATERAL_RECEIPT_EXPIRING" }.to_string(),
+        AuditEventLevel::Warning,
+        AuditDetails {
+            description: message,
+            entity_type: Some("nft".to_string()),
+            entity_id: Some(entry.nft_id.to_string()),
+            affected_principals: vec![entry.borrower],
+            ..Default::default()
+        },
+        AuditResult {
+            success: true,
+            error_code: None,
+            error_message: None,
+            execution_time_ms: None,
+            gas_used: None,
+            cycles_consumed: None,
+            memory_used_bytes: None,
+            warning_flags: vec!["COLLATERAL_RECEIPT_EXPIRY".to_string()],
+        },
+        None,
+    );
+}
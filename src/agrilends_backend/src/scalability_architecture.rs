@@ -160,6 +160,7 @@ thread_local! {
 #[update]
 pub async fn create_new_data_shard(region: Option<String>) -> Result<ShardInfo, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "create_new_data_shard")?;
     if !is_admin(&caller) {
         return Err("Only admin can create new data shards".to_string());
     }
@@ -221,6 +222,7 @@ pub async fn create_new_data_shard(region: Option<String>) -> Result<ShardInfo,
     SHARDS.with(|shards| {
         shards.borrow_mut().insert(shard_id, shard_info.clone());
     });
+    crate::advanced_query_routing::refresh_shard_ownership();
     
     // Log audit action
     log_audit_action(
@@ -262,7 +264,7 @@ pub fn get_shard_for_loan(user_id: Principal, loan_data: Option<LoanApplication>
         return Err("No active shards available".to_string());
     }
     
-    let target_shard_index = (user_hash % shard_count as u64) as u32 + 1;
+    let target_shard_index = owning_shard_id(user_hash, shard_count);
     
     SHARDS.with(|shards| {
         let shards_ref = shards.borrow();
@@ -431,6 +433,7 @@ async fn update_shard_health(shard: &ShardInfo) -> Result<(), String> {
 #[update]
 pub fn mark_shard_read_only(shard_id: u32) -> Result<(), String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "mark_shard_read_only")?;
     if !is_admin(&caller) {
         return Err("Only admin can mark shards as read-only".to_string());
     }
@@ -444,7 +447,9 @@ pub fn mark_shard_read_only(shard_id: u32) -> Result<(), String> {
         } else {
             Err("Shard not found".to_string())
         }
-    })
+    })?;
+    crate::advanced_query_routing::refresh_shard_ownership();
+    Ok(())
 }
 
 // ========== DATA MIGRATION & REBALANCING ==========
@@ -457,6 +462,7 @@ pub async fn migrate_shard_data(
     migration_percentage: f64,
 ) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "migrate_shard_data")?;
     if !is_admin(&caller) {
         return Err("Only admin can migrate shard data".to_string());
     }
@@ -487,6 +493,9 @@ pub async fn migrate_shard_data(
         Some(migration_id.clone()),
     );
     
+    // Routed reads must not keep targeting the old layout
+    crate::advanced_query_routing::refresh_shard_ownership();
+    
     // TODO: Implement actual data migration logic
     // This would involve:
     // 1. Reading data from source shard
@@ -502,6 +511,7 @@ pub async fn migrate_shard_data(
 #[update]
 pub async fn rebalance_shards() -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "rebalance_shards")?;
     if !is_admin(&caller) {
         return Err("Only admin can rebalance shards".to_string());
     }
@@ -639,6 +649,7 @@ pub fn get_scalability_metrics() -> ScalabilityMetrics {
 #[update]
 pub fn update_scalability_config(new_config: ScalabilityConfig) -> Result<(), String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "update_scalability_config")?;
     if !is_admin(&caller) {
         return Err("Only admin can update scalability configuration".to_string());
     }
@@ -668,8 +679,14 @@ pub fn update_scalability_config(new_config: ScalabilityConfig) -> Result<(), St
 
 // ========== HELPER FUNCTIONS ==========
 
+/// Shard a borrower's loans are placed on, by principal hash over the active shard count.
+/// Shard IDs start at 1.
+pub fn owning_shard_id(user_hash: u64, shard_count: usize) -> u32 {
+    (user_hash % shard_count.max(1) as u64) as u32 + 1
+}
+
 /// Hash a principal for consistent sharding
-fn hash_principal(principal: &Principal) -> u64 {
+pub(crate) fn hash_principal(principal: &Principal) -> u64 {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
     
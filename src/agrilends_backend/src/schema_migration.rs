@@ -0,0 +1,314 @@
+// ========== SCHEMA MIGRATION MODULE ==========
+// Versioned upgrades of the data kept in stable memory. The version the data was last
+// migrated to is stored alongside it; `post_upgrade` runs every migration from that version
+// up to `SCHEMA_VERSION` in order before anything else reads state, then records the new
+// version. A binary older than the stored data refuses to start, so a downgrade cannot
+// misread records written in a newer layout.
+//
+// To change a stored struct: add the field as an `Option` so existing records still decode,
+// bump `SCHEMA_VERSION`, and append a migration that backfills it. Migrations must be safe to
+// run again on data they have already migrated.
+
+use std::collections::HashSet;
+
+use candid::Principal;
+use ic_cdk_macros::query;
+
+use crate::types::*;
+use crate::storage::{
+    get_stored_schema_version, set_stored_schema_version, get_all_asset_investor_balances,
+    store_asset_investor_balance, log_action, get_all_loans_data, get_all_repayment_fee_collections,
+    get_repayment_fee_collection, store_repayment_fee_collection,
+};
+use crate::loan_repayment::repayment_fee_due;
+
+pub const SCHEMA_VERSION: u32 = 4;
+// Canisters installed before versioning have no stored version; their data is v1
+pub const UNVERSIONED_SCHEMA_VERSION: u32 = 1;
+
+pub struct SchemaMigration {
+    pub from_version: u32,
+    pub description: &'static str,
+    // Returns the number of records changed
+    pub run: fn() -> Result<u64, String>,
+}
+
+pub const SCHEMA_MIGRATIONS: &[SchemaMigration] = &[
+    SchemaMigration {
+        from_version: 1,
+        description: "Backfill investor balance fee totals",
+        run: migrate_v1_to_v2,
+    },
+    SchemaMigration {
+        from_version: 2,
+        description: "Record fee collections for repayments already booked as protocol fee revenue",
+        run: migrate_v2_to_v3,
+    },
+    SchemaMigration {
+        from_version: 3,
+        description: "Record dust sweeps made before sweeps were kept as withdrawals",
+        run: migrate_v3_to_v4,
+    },
+];
+
+/// Migrations taking data from `stored` to `target`, in order. Errors when the data is newer
+/// than the binary or a step is missing.
+pub fn migration_plan(
+    migrations: &'static [SchemaMigration],
+    stored: u32,
+    target: u32,
+) -> Result<Vec<&'static SchemaMigration>, String> {
+    if stored > target {
+        return Err(format!(
+            "Stored data is at schema v{} but this build only understands up to v{}; refusing to downgrade",
+            stored, target
+        ));
+    }
+    (stored..target)
+        .map(|version| {
+            migrations.iter()
+                .find(|migration| migration.from_version == version)
+                .ok_or_else(|| format!("No migration from schema v{} to v{}", version, version + 1))
+        })
+        .collect()
+}
+
+/// v2 field: total fees paid, taken from the fee records. Balances that already have it are
+/// returned unchanged.
+pub fn backfill_total_fees_paid(mut balance: InvestorBalance) -> Option<InvestorBalance> {
+    if balance.total_fees_paid.is_some() {
+        return None;
+    }
+    balance.total_fees_paid = Some(balance.recorded_fees());
+    Some(balance)
+}
+
+fn migrate_v1_to_v2() -> Result<u64, String> {
+    let mut migrated = 0;
+    for asset in [Asset::CkBtc, Asset::Icp] {
+        for balance in get_all_asset_investor_balances(&asset) {
+            if let Some(balance) = backfill_total_fees_paid(balance) {
+                store_asset_investor_balance(&asset, balance)?;
+                migrated += 1;
+            }
+        }
+    }
+    Ok(migrated)
+}
+
+/// A protocol fee revenue entry as the treasury booked it. Before v3 the fee was routed without
+/// a loan (`source_loan_id` 0) and only the treasury share was booked here.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BookedFeeRevenue {
+    pub entry_id: u64,
+    pub source_loan_id: u64,
+    pub amount: u64,
+    pub timestamp: u64,
+}
+
+/// An insurance fund fee contribution: the insurance share of a routed fee, recorded with its loan
+#[derive(Clone, Debug, PartialEq)]
+pub struct BookedInsuranceShare {
+    pub entry_id: u64,
+    pub loan_id: u64,
+    pub amount: u64,
+    pub timestamp: u64,
+}
+
+/// v3 records: collections for the fee-bearing payments of `loan` that were routed before
+/// collections were recorded. A fee was routed in one call, so its treasury share (a revenue
+/// entry for this loan or for no loan) and its insurance share (a contribution for this loan)
+/// carry the same timestamp, no earlier than the payment, and add up to the fee. Either share may
+/// be missing when it was zero. Each payment takes the earliest such pair; entries in the claimed
+/// sets are never reused.
+pub fn fee_collections_from_revenue(
+    loan: &Loan,
+    revenue: &[BookedFeeRevenue],
+    insurance: &[BookedInsuranceShare],
+    claimed_revenue: &mut HashSet<u64>,
+    claimed_insurance: &mut HashSet<u64>,
+    is_collected: impl Fn(u64) -> bool,
+    collected_by: Principal,
+) -> Vec<RepaymentFeeCollection> {
+    let mut revenue: Vec<&BookedFeeRevenue> = revenue.iter()
+        .filter(|entry| entry.source_loan_id == loan.id || entry.source_loan_id == 0)
+        .collect();
+    revenue.sort_by_key(|entry| (entry.timestamp, entry.entry_id));
+    let mut insurance: Vec<&BookedInsuranceShare> = insurance.iter().filter(|entry| entry.loan_id == loan.id).collect();
+    insurance.sort_by_key(|entry| (entry.timestamp, entry.entry_id));
+    
+    let mut collections = Vec::new();
+    for (index, payment) in loan.repayment_history.iter().enumerate() {
+        let payment_index = index as u64;
+        let fee_amount = match repayment_fee_due(loan, index) {
+            Some(fee) if !is_collected(payment_index) => fee,
+            _ => continue,
+        };
+        
+        // (revenue entry, insurance entry, timestamp) of the earliest routing of this fee
+        let mut matched: Option<(Option<&BookedFeeRevenue>, Option<&BookedInsuranceShare>, u64)> = None;
+        for entry in revenue.iter().filter(|entry| entry.timestamp >= payment.timestamp && !claimed_revenue.contains(&entry.entry_id)) {
+            if entry.amount > fee_amount {
+                continue;
+            }
+            let insurance_share = fee_amount - entry.amount;
+            let partner = insurance.iter().find(|share| {
+                share.timestamp == entry.timestamp && share.amount == insurance_share && !claimed_insurance.contains(&share.entry_id)
+            });
+            if insurance_share == 0 || partner.is_some() {
+                matched = Some((Some(*entry), partner.copied(), entry.timestamp));
+                break;
+            }
+        }
+        // The whole fee went to the insurance fund, so no revenue entry was booked
+        let insurance_only = insurance.iter().find(|share| {
+            share.timestamp >= payment.timestamp && share.amount == fee_amount && !claimed_insurance.contains(&share.entry_id)
+        });
+        if let Some(share) = insurance_only {
+            if matched.map_or(true, |(_, _, timestamp)| share.timestamp < timestamp) {
+                matched = Some((None, Some(*share), share.timestamp));
+            }
+        }
+        
+        let Some((entry, share, timestamp)) = matched else {
+            continue;
+        };
+        if let Some(entry) = entry {
+            claimed_revenue.insert(entry.entry_id);
+        }
+        if let Some(share) = share {
+            claimed_insurance.insert(share.entry_id);
+        }
+        let treasury_share = entry.map_or(0, |entry| entry.amount);
+        collections.push(RepaymentFeeCollection {
+            loan_id: loan.id,
+            payment_index,
+            fee_amount,
+            insurance_share: fee_amount - treasury_share,
+            treasury_share,
+            revenue_entry_id: entry.map_or(0, |entry| entry.entry_id),
+            transaction_id: payment.transaction_id.clone(),
+            collected_at: timestamp,
+            collected_by,
+        });
+    }
+    collections
+}
+
+fn migrate_v2_to_v3() -> Result<u64, String> {
+    let existing = get_all_repayment_fee_collections();
+    let protocol_fee = crate::treasury_management::RevenueType::ProtocolFee;
+    let revenue: Vec<BookedFeeRevenue> = crate::treasury_management::get_revenue_entries_by_type(&protocol_fee)
+        .into_iter()
+        .map(|entry| BookedFeeRevenue {
+            entry_id: entry.id,
+            source_loan_id: entry.source_loan_id,
+            amount: entry.amount,
+            timestamp: entry.timestamp,
+        })
+        .collect();
+    let insurance: Vec<BookedInsuranceShare> = crate::treasury_management::get_insurance_fee_contributions()
+        .into_iter()
+        .map(|entry| BookedInsuranceShare {
+            entry_id: entry.id,
+            loan_id: entry.loan_id,
+            amount: entry.amount,
+            timestamp: entry.timestamp,
+        })
+        .collect();
+    
+    // Shares already behind a recorded collection were routed in the same call it was stored in
+    let mut claimed_revenue: HashSet<u64> = existing.iter()
+        .map(|collection| collection.revenue_entry_id)
+        .filter(|entry_id| *entry_id != 0)
+        .collect();
+    let mut claimed_insurance: HashSet<u64> = insurance.iter()
+        .filter(|share| existing.iter().any(|collection| {
+            collection.loan_id == share.loan_id
+                && collection.collected_at == share.timestamp
+                && collection.insurance_share == share.amount
+        }))
+        .map(|share| share.entry_id)
+        .collect();
+    
+    let collected_by = ic_cdk::api::canister_self();
+    let mut migrated = 0;
+    for loan in get_all_loans_data() {
+        let is_collected = |payment_index| get_repayment_fee_collection(loan.id, payment_index).is_some();
+        let collections = fee_collections_from_revenue(
+            &loan, &revenue, &insurance, &mut claimed_revenue, &mut claimed_insurance, is_collected, collected_by,
+        );
+        for collection in collections {
+            store_repayment_fee_collection(collection);
+            migrated += 1;
+        }
+    }
+    Ok(migrated)
+}
+
+/// v4 record: the dust swept from `balance` before sweeps were recorded as withdrawals. A sweep
+/// was the only debit that raised `total_withdrawn` without a withdrawal record, so the gap is
+/// the swept amount; it is recorded at the balance's last activity. Balances without a gap
+/// are returned unchanged.
+pub fn backfill_dust_sweep_withdrawal(mut balance: InvestorBalance) -> Option<InvestorBalance> {
+    let recorded = balance.withdrawals.iter().fold(0u64, |total, w| total.saturating_add(w.amount));
+    let swept = balance.total_withdrawn.checked_sub(recorded).filter(|gap| *gap > 0)?;
+    balance.withdrawals.push(WithdrawalRecord {
+        investor: balance.investor,
+        amount: swept,
+        ckbtc_block_index: 0,
+        timestamp: balance.last_activity_at,
+        fee: None,
+        lots: None,
+    });
+    Some(balance)
+}
+
+fn migrate_v3_to_v4() -> Result<u64, String> {
+    // Dust has only ever been swept from ckBTC balances
+    let mut migrated = 0;
+    for balance in get_all_asset_investor_balances(&Asset::CkBtc) {
+        if let Some(balance) = backfill_dust_sweep_withdrawal(balance) {
+            store_asset_investor_balance(&Asset::CkBtc, balance)?;
+            migrated += 1;
+        }
+    }
+    Ok(migrated)
+}
+
+/// Version of the data in stable memory
+pub fn stored_schema_version() -> u32 {
+    get_stored_schema_version().unwrap_or(UNVERSIONED_SCHEMA_VERSION)
+}
+
+/// Record a fresh install as already current; there is nothing to migrate
+pub fn initialize_schema_version() {
+    if get_stored_schema_version().is_none() {
+        set_stored_schema_version(SCHEMA_VERSION);
+    }
+}
+
+/// Bring stable data up to `SCHEMA_VERSION`, returning a summary of each step applied
+pub fn run_schema_migrations() -> Result<Vec<String>, String> {
+    let stored = stored_schema_version();
+    let mut applied = Vec::new();
+    for migration in migration_plan(SCHEMA_MIGRATIONS, stored, SCHEMA_VERSION)? {
+        let changed = (migration.run)().map_err(|e| {
+            format!("Schema migration v{} failed ({}): {}", migration.from_version, migration.description, e)
+        })?;
+        set_stored_schema_version(migration.from_version + 1);
+        let summary = format!(
+            "v{} -> v{}: {} ({} record(s) updated)",
+            migration.from_version, migration.from_version + 1, migration.description, changed
+        );
+        log_action("SCHEMA_MIGRATION", &summary, true);
+        applied.push(summary);
+    }
+    Ok(applied)
+}
+
+/// Schema version of the data in stable memory
+#[query]
+pub fn get_schema_version() -> u32 {
+    stored_schema_version()
+}
@@ -0,0 +1,380 @@
+// ========== STATE SNAPSHOT MODULE ==========
+// Portable backups of critical state for off-chain storage, and the matching import for
+// disaster recovery into a fresh canister. A domain is exported as candid-encoded
+// `StateSnapshotChunk`s of at most `SNAPSHOT_RECORDS_PER_CHUNK` records each, carrying a
+// format version and a SHA-256 content hash that the import verifies before writing anything.
+// Imports run only in maintenance mode and in chunk order. On a production canister each
+// domain must still be empty when its import starts, so restore the parameter domains first,
+// then the pool, investor balances and loans. Chunk 0 starts an export and names its
+// `export_id`; later chunks must ask for that export and fail once the domain has changed
+// under it, so the chunks of an export always form one consistent cut.
+
+use candid::{CandidType, Deserialize, Principal};
+use ic_cdk::api::time;
+use ic_cdk_macros::update;
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::types::*;
+use crate::storage::{
+    get_loans_page, get_loan_count, restore_loan, get_investor_balances_page, get_investor_count,
+    store_investor_balance, get_liquidity_pool, store_liquidity_pool, get_protocol_parameters,
+    set_protocol_parameters, reconcile_id_counter, get_state_version_counter,
+};
+use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+use crate::validation::CallerRole;
+
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 2;
+pub const SNAPSHOT_RECORDS_PER_CHUNK: usize = 200;
+
+/// An export in progress: the cut its chunks are taken from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SnapshotExportSession {
+    pub export_id: u64,
+    pub total_records: u64,
+    pub state_version: u64,
+}
+
+thread_local! {
+    // Export each domain's later chunks belong to
+    static EXPORT_SESSIONS: RefCell<HashMap<SnapshotDomain, SnapshotExportSession>> = RefCell::new(HashMap::new());
+    // Export and next chunk expected for each domain with an import in progress
+    static IMPORT_PROGRESS: RefCell<HashMap<SnapshotDomain, (u64, u64)>> = RefCell::new(HashMap::new());
+}
+
+/// Chunks needed for `record_count` records. An empty domain still exports one empty chunk.
+pub fn snapshot_chunk_count(record_count: usize) -> u64 {
+    record_count.div_ceil(SNAPSHOT_RECORDS_PER_CHUNK).max(1) as u64
+}
+
+/// SHA-256 over the header fields and payload, so neither can change without detection
+pub fn snapshot_content_hash(chunk: &StateSnapshotChunk) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!(
+        "{}:{}:{}:{}:{}:{}:{}:{}:",
+        chunk.format_version,
+        chunk.domain.label(),
+        chunk.chunk_index,
+        chunk.total_chunks,
+        chunk.record_count,
+        chunk.total_records,
+        chunk.exported_at,
+        chunk.export_id,
+    ).as_bytes());
+    hasher.update(&chunk.payload);
+    hex::encode(hasher.finalize())
+}
+
+/// Offset of the first record in chunk `chunk_index` of a `total_records` snapshot
+pub fn snapshot_chunk_offset(domain: SnapshotDomain, total_records: u64, chunk_index: u64) -> Result<usize, String> {
+    let total_chunks = snapshot_chunk_count(total_records as usize);
+    if chunk_index >= total_chunks {
+        return Err(format!(
+            "Chunk {} is out of range; the {} snapshot has {} chunk(s)",
+            chunk_index, domain.label(), total_chunks
+        ));
+    }
+    Ok(chunk_index as usize * SNAPSHOT_RECORDS_PER_CHUNK)
+}
+
+/// Chunk `chunk_index` of export `export_id`, carrying `page`: that chunk's records out of
+/// `total_records`
+pub fn build_snapshot_chunk<T: CandidType>(
+    domain: SnapshotDomain,
+    page: &[T],
+    chunk_index: u64,
+    total_records: u64,
+    export_id: u64,
+    now: u64,
+) -> Result<StateSnapshotChunk, String> {
+    let offset = snapshot_chunk_offset(domain, total_records, chunk_index)?;
+    let expected = (total_records as usize - offset).min(SNAPSHOT_RECORDS_PER_CHUNK);
+    if page.len() != expected {
+        return Err(format!(
+            "Chunk {} of the {} snapshot should hold {} record(s), got {}",
+            chunk_index, domain.label(), expected, page.len()
+        ));
+    }
+    let payload = candid::encode_one(page)
+        .map_err(|e| format!("Failed to encode {} snapshot: {}", domain.label(), e))?;
+
+    let mut chunk = StateSnapshotChunk {
+        format_version: SNAPSHOT_FORMAT_VERSION,
+        domain,
+        chunk_index,
+        total_chunks: snapshot_chunk_count(total_records as usize),
+        record_count: page.len() as u64,
+        total_records,
+        exported_at: now,
+        export_id,
+        content_hash: String::new(),
+        payload,
+    };
+    chunk.content_hash = snapshot_content_hash(&chunk);
+    Ok(chunk)
+}
+
+/// Decode an exported chunk and check its version, domain and content hash
+pub fn decode_snapshot_chunk(bytes: &[u8], domain: SnapshotDomain) -> Result<StateSnapshotChunk, String> {
+    let chunk: StateSnapshotChunk = candid::decode_one(bytes)
+        .map_err(|e| format!("Snapshot chunk could not be decoded: {}", e))?;
+    if chunk.format_version != SNAPSHOT_FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported snapshot format version {} (expected {})",
+            chunk.format_version, SNAPSHOT_FORMAT_VERSION
+        ));
+    }
+    if chunk.domain != domain {
+        return Err(format!(
+            "Chunk belongs to the {} snapshot, not {}",
+            chunk.domain.label(), domain.label()
+        ));
+    }
+    if chunk.chunk_index >= chunk.total_chunks {
+        return Err("Chunk index is outside the snapshot".to_string());
+    }
+    if snapshot_content_hash(&chunk) != chunk.content_hash {
+        return Err("Snapshot chunk failed its integrity check".to_string());
+    }
+    Ok(chunk)
+}
+
+/// Records carried by a verified chunk
+pub fn decode_snapshot_records<T: CandidType + for<'de> Deserialize<'de>>(chunk: &StateSnapshotChunk) -> Result<Vec<T>, String> {
+    let records: Vec<T> = candid::decode_one(&chunk.payload)
+        .map_err(|e| format!("Failed to decode {} records: {}", chunk.domain.label(), e))?;
+    if records.len() as u64 != chunk.record_count {
+        return Err(format!(
+            "Chunk declares {} records but carries {}",
+            chunk.record_count, records.len()
+        ));
+    }
+    Ok(records)
+}
+
+/// Whether `domain` holds nothing a production import could overwrite
+fn domain_is_empty(domain: SnapshotDomain) -> bool {
+    let no_loans_or_balances = || get_loan_count() == 0 && get_investor_count() == 0;
+    match domain {
+        SnapshotDomain::Loans => get_loan_count() == 0,
+        SnapshotDomain::InvestorBalances => get_investor_count() == 0,
+        SnapshotDomain::LiquidityPool => {
+            let pool = get_liquidity_pool();
+            pool.total_liquidity == 0 && pool.total_borrowed == 0
+        }
+        // Parameters always have defaults, so a canister counts as fresh until it has business
+        SnapshotDomain::ProtocolParameters | SnapshotDomain::GovernanceParameters => no_loans_or_balances(),
+    }
+}
+
+fn audit_snapshot(caller: Principal, action: &str, chunk: &StateSnapshotChunk) {
+    log_audit_enhanced(
+        AuditCategory::Maintenance,
+        action.to_string(),
+        AuditEventLevel::Critical,
+        AuditDetails {
+            description: format!(
+                "{} snapshot chunk {}/{} with {} record(s) by {}",
+                chunk.domain.label(), chunk.chunk_index + 1, chunk.total_chunks, chunk.record_count, caller.to_text()
+            ),
+            entity_type: Some("state_snapshot".to_string()),
+            entity_id: Some(chunk.domain.label().to_string()),
+            metadata: vec![
+                ("domain".to_string(), chunk.domain.label().to_string()),
+                ("record_count".to_string(), chunk.record_count.to_string()),
+                ("total_records".to_string(), chunk.total_records.to_string()),
+                ("content_hash".to_string(), chunk.content_hash.clone()),
+            ],
+            ..Default::default()
+        },
+        AuditResult {
+            success: true,
+            error_code: None,
+            error_message: None,
+            execution_time_ms: None,
+            gas_used: None,
+            cycles_consumed: None,
+            memory_used_bytes: None,
+            warning_flags: vec![],
+        },
+        None,
+    );
+}
+
+/// Records in `domain` and the state version bumped by every write to it. Parameter domains
+/// have no version counter, so only their record count guards the cut.
+fn domain_cut(domain: SnapshotDomain) -> (u64, u64) {
+    match domain {
+        SnapshotDomain::Loans => (get_loan_count(), get_state_version_counter(StateDomain::Loans)),
+        SnapshotDomain::InvestorBalances => (get_investor_count(), get_state_version_counter(StateDomain::Pool)),
+        SnapshotDomain::LiquidityPool => (1, get_state_version_counter(StateDomain::Pool)),
+        SnapshotDomain::ProtocolParameters => (1, 0),
+        SnapshotDomain::GovernanceParameters => (crate::governance::get_all_protocol_parameters().len() as u64, 0),
+    }
+}
+
+/// The export chunk `chunk_index` belongs to. Chunk 0 without an ID starts a new export;
+/// any other chunk must name the current export, taken from a cut that still holds.
+pub fn resolve_export_session(
+    domain: SnapshotDomain,
+    current: Option<SnapshotExportSession>,
+    cut: (u64, u64),
+    chunk_index: u64,
+    export_id: Option<u64>,
+    now: u64,
+) -> Result<SnapshotExportSession, String> {
+    let (total_records, state_version) = cut;
+    let session = match (export_id, current) {
+        (None, _) if chunk_index == 0 => {
+            return Ok(SnapshotExportSession { export_id: now, total_records, state_version });
+        }
+        (None, _) => return Err("Chunks after the first must name their export_id".to_string()),
+        (Some(id), Some(session)) if session.export_id == id => session,
+        (Some(id), _) => {
+            return Err(format!("Export {} of the {} snapshot is not in progress; restart from chunk 0", id, domain.label()));
+        }
+    };
+    if session.total_records != total_records || session.state_version != state_version {
+        return Err(format!(
+            "The {} state changed since export {} started; restart from chunk 0",
+            domain.label(), session.export_id
+        ));
+    }
+    Ok(session)
+}
+
+/// Export one chunk of a domain snapshot as candid bytes (super admin only). Request chunk 0
+/// without an `export_id` to start an export, then pass the chunk's `export_id` for the rest;
+/// every chunk reports `total_chunks`.
+#[update]
+pub fn export_state_snapshot(domain: SnapshotDomain, chunk_index: u64, export_id: Option<u64>) -> Result<Vec<u8>, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "export_state_snapshot")?;
+    crate::validation::require_role(&caller, CallerRole::SuperAdmin)?;
+
+    let now = time();
+    let current = EXPORT_SESSIONS.with(|sessions| sessions.borrow().get(&domain).copied());
+    let session = resolve_export_session(domain, current, domain_cut(domain), chunk_index, export_id, now)?;
+    let offset = snapshot_chunk_offset(domain, session.total_records, chunk_index)?;
+    let (total, id) = (session.total_records, session.export_id);
+    // Stable maps iterate in key order and the cut is unchanged, so pages line up between calls
+    let chunk = match domain {
+        SnapshotDomain::Loans => {
+            build_snapshot_chunk(domain, &get_loans_page(offset, SNAPSHOT_RECORDS_PER_CHUNK), chunk_index, total, id, now)
+        }
+        SnapshotDomain::InvestorBalances => {
+            build_snapshot_chunk(domain, &get_investor_balances_page(offset, SNAPSHOT_RECORDS_PER_CHUNK), chunk_index, total, id, now)
+        }
+        SnapshotDomain::LiquidityPool => build_snapshot_chunk(domain, &[get_liquidity_pool()], chunk_index, total, id, now),
+        SnapshotDomain::ProtocolParameters => build_snapshot_chunk(domain, &[get_protocol_parameters()], chunk_index, total, id, now),
+        SnapshotDomain::GovernanceParameters => {
+            let params = crate::governance::get_all_protocol_parameters();
+            let end = (offset + SNAPSHOT_RECORDS_PER_CHUNK).min(params.len());
+            build_snapshot_chunk(domain, &params[offset..end], chunk_index, total, id, now)
+        }
+    }?;
+    EXPORT_SESSIONS.with(|sessions| {
+        sessions.borrow_mut().insert(domain, session);
+    });
+
+    audit_snapshot(caller, "STATE_SNAPSHOT_EXPORTED", &chunk);
+    candid::encode_one(&chunk).map_err(|e| format!("Failed to encode snapshot chunk: {}", e))
+}
+
+/// Restore one exported chunk (super admin only, maintenance mode). Chunks must arrive in
+/// order; sending chunk 0 again restarts the domain's import.
+#[update]
+pub fn import_state_snapshot(domain: SnapshotDomain, chunk: Vec<u8>) -> Result<SnapshotImportProgress, String> {
+    let caller = ic_cdk::caller();
+    crate::validation::guard_caller(&caller, "import_state_snapshot")?;
+    crate::validation::require_role(&caller, CallerRole::SuperAdmin)?;
+    if !crate::helpers::is_in_maintenance_mode() {
+        return Err("Snapshots can only be imported while the canister is in maintenance mode".to_string());
+    }
+
+    let chunk = decode_snapshot_chunk(&chunk, domain)?;
+    if chunk.chunk_index != 0 {
+        let (export_id, expected) = IMPORT_PROGRESS.with(|progress| progress.borrow().get(&domain).copied())
+            .ok_or_else(|| format!("No {} import in progress; start with chunk 0", domain.label()))?;
+        if chunk.export_id != export_id {
+            return Err(format!(
+                "Chunk belongs to export {}, but the {} import in progress is export {}",
+                chunk.export_id, domain.label(), export_id
+            ));
+        }
+        if chunk.chunk_index != expected {
+            return Err(format!(
+                "Expected chunk {} of the {} snapshot, got chunk {}",
+                expected, domain.label(), chunk.chunk_index
+            ));
+        }
+    }
+    if chunk.chunk_index == 0 && crate::production_config::is_production_mode() && !domain_is_empty(domain) {
+        return Err(format!(
+            "Production canisters only accept a {} snapshot while that state is still empty",
+            domain.label()
+        ));
+    }
+
+    let records_imported = match domain {
+        SnapshotDomain::Loans => {
+            let loans: Vec<Loan> = decode_snapshot_records(&chunk)?;
+            let max_id = loans.iter().map(|loan| loan.id).max();
+            for loan in loans {
+                restore_loan(loan);
+            }
+            // New loans must not reuse a restored ID
+            reconcile_id_counter(IdCounter::Loan, max_id);
+            chunk.record_count
+        }
+        SnapshotDomain::InvestorBalances => {
+            let balances: Vec<InvestorBalance> = decode_snapshot_records(&chunk)?;
+            for balance in balances {
+                store_investor_balance(balance)?;
+            }
+            chunk.record_count
+        }
+        SnapshotDomain::LiquidityPool => {
+            let pools: Vec<LiquidityPool> = decode_snapshot_records(&chunk)?;
+            for pool in pools {
+                store_liquidity_pool(pool)?;
+            }
+            chunk.record_count
+        }
+        SnapshotDomain::ProtocolParameters => {
+            let params: Vec<ProtocolParameters> = decode_snapshot_records(&chunk)?;
+            for params in params {
+                set_protocol_parameters(params)?;
+            }
+            chunk.record_count
+        }
+        SnapshotDomain::GovernanceParameters => {
+            let params: Vec<ProtocolParameter> = decode_snapshot_records(&chunk)?;
+            for param in params {
+                crate::governance::restore_protocol_parameter(param);
+            }
+            chunk.record_count
+        }
+    };
+
+    let complete = chunk.chunk_index + 1 == chunk.total_chunks;
+    IMPORT_PROGRESS.with(|progress| {
+        let mut progress = progress.borrow_mut();
+        if complete {
+            progress.remove(&domain);
+        } else {
+            progress.insert(domain, (chunk.export_id, chunk.chunk_index + 1));
+        }
+    });
+
+    audit_snapshot(caller, "STATE_SNAPSHOT_IMPORTED", &chunk);
+    Ok(SnapshotImportProgress {
+        domain,
+        export_id: chunk.export_id,
+        chunk_index: chunk.chunk_index,
+        total_chunks: chunk.total_chunks,
+        records_imported,
+        complete,
+    })
+}
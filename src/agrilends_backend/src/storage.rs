@@ -128,6 +128,15 @@ thread_local! {
     );
 }
 
+// ICP investor balances; ckBTC balances stay in INVESTOR_BALANCES
+thread_local! {
+    pub static ICP_INVESTOR_BALANCES: RefCell<InvestorBalanceStorage> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(57)))
+        )
+    );
+}
+
 thread_local! {
     pub static INVESTOR_BALANCES: RefCell<InvestorBalanceStorage> = RefCell::new(
         StableBTreeMap::init(
@@ -175,239 +184,1187 @@ thread_local! {
     );
 }
 
-// Token ID counters
+// Storage for loan restructure requests
+thread_local! {
+    pub static LOAN_RESTRUCTURE_REQUESTS: RefCell<StableBTreeMap<u64, LoanRestructureRequest, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(16)))
+        )
+    );
+}
+
+// Storage for allowance repayment idempotency receipts, keyed by "<payer>:<request_id>"
 thread_local! {
-    static NFT_TOKEN_COUNTER: RefCell<u64> = RefCell::new(0);
-    static COLLATERAL_COUNTER: RefCell<u64> = RefCell::new(0);
-    pub static AUDIT_LOG_COUNTER: RefCell<u64> = RefCell::new(0);
-    static LOAN_COUNTER: RefCell<u64> = RefCell::new(0);
-    static DISBURSEMENT_COUNTER: RefCell<u64> = RefCell::new(0);
+    pub static ALLOWANCE_REPAYMENT_RECEIPTS: RefCell<StableBTreeMap<String, AllowanceRepaymentReceipt, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(17)))
+        )
+    );
 }
 
-// Helper functions for token ID generation
-pub fn next_nft_token_id() -> u64 {
-    NFT_TOKEN_COUNTER.with(|counter| {
-        let current = *counter.borrow();
-        *counter.borrow_mut() = current + 1;
-        current + 1
+// Ring buffer of recent oracle price samples per commodity
+pub const MAX_PRICE_SAMPLES: usize = 288;
+
+thread_local! {
+    pub static PRICE_HISTORY: RefCell<StableBTreeMap<String, PriceSampleHistory, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(25)))
+        )
+    );
+}
+
+// Principal blacklist enforced at the call boundary
+thread_local! {
+    pub static BLACKLIST: RefCell<StableBTreeMap<Principal, BlacklistEntry, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(26)))
+        )
+    );
+}
+
+// Monotonic per-domain state version counters (StateDomain::storage_key -> version)
+thread_local! {
+    pub static STATE_VERSIONS: RefCell<StableBTreeMap<u8, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(27)))
+        )
+    );
+}
+
+// Disbursement address denylist (BtcAddressDenyRule::storage_key -> rule) and per-loan overrides
+thread_local! {
+    pub static BTC_ADDRESS_DENYLIST: RefCell<StableBTreeMap<String, BtcAddressDenyRule, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(34)))
+        )
+    );
+
+    pub static DISBURSEMENT_ADDRESS_OVERRIDES: RefCell<StableBTreeMap<u64, DisbursementAddressOverride, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(38)))
+        )
+    );
+
+    // User price alert subscriptions keyed by alert ID
+    pub static PRICE_ALERT_SUBSCRIPTIONS: RefCell<StableBTreeMap<u64, PriceAlertSubscription, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(39)))
+        )
+    );
+}
+
+// Outbound webhook configuration and delivery log
+thread_local! {
+    pub static OUTBOUND_WEBHOOK_CONFIG: RefCell<StableBTreeMap<u8, OutboundWebhookConfig, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(28)))
+        )
+    );
+
+    pub static OUTBOUND_EVENT_DELIVERIES: RefCell<StableBTreeMap<u64, OutboundEventDelivery, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(29)))
+        )
+    );
+}
+
+// Liquidity reserved for approved loans, keyed by loan ID
+thread_local! {
+    pub static LIQUIDITY_RESERVATIONS: RefCell<StableBTreeMap<u64, LiquidityReservation, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(44)))
+        )
+    );
+}
+
+// Append-only loan lifecycle event log keyed by sequence number
+thread_local! {
+    pub static LOAN_EVENT_LOG: RefCell<StableBTreeMap<u64, LoanEvent, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(45)))
+        )
+    );
+}
+
+// Operational tags keyed by "<entity type>:<entity id>"
+thread_local! {
+    pub static ENTITY_TAGS: RefCell<StableBTreeMap<String, EntityTags, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(46)))
+        )
+    );
+}
+
+// Secondary market offers for investor positions, keyed by offer ID
+thread_local! {
+    pub static POSITION_OFFERS: RefCell<StableBTreeMap<u64, PositionOffer, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(47)))
+        )
+    );
+}
+
+// Promotional rate campaigns, keyed by campaign ID
+thread_local! {
+    pub static PROMO_CAMPAIGNS: RefCell<StableBTreeMap<u64, PromoCampaign, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(48)))
+        )
+    );
+}
+
+// Loan manager rotation awaiting acceptance
+thread_local! {
+    pub static PENDING_LOAN_MANAGER: RefCell<StableBTreeMap<u8, PendingLoanManager, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(49)))
+        )
+    );
+}
+
+// Daily pool performance snapshots keyed by capture time
+thread_local! {
+    pub static PERFORMANCE_SNAPSHOTS: RefCell<StableBTreeMap<u64, PerformanceSnapshot, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(56)))
+        )
+    );
+}
+
+// Runtime feature flags keyed by name
+thread_local! {
+    pub static FEATURE_FLAGS: RefCell<StableBTreeMap<String, FeatureFlag, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(58)))
+        )
+    );
+}
+
+// Auto-pay mandates keyed by loan ID
+thread_local! {
+    pub static AUTOPAY_MANDATES: RefCell<StableBTreeMap<u64, AutopayMandate, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(59)))
+        )
+    );
+}
+
+// Borrower repayment reputations keyed by principal
+thread_local! {
+    pub static BORROWER_REPUTATIONS: RefCell<StableBTreeMap<Principal, BorrowerReputation, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(60)))
+        )
+    );
+}
+
+// Per-admin nonces for replay protection of sensitive admin calls
+thread_local! {
+    pub static ADMIN_NONCES: RefCell<StableBTreeMap<Principal, AdminNonceState, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(61)))
+        )
+    );
+}
+
+// Operator notification broadcasts keyed by ID
+thread_local! {
+    pub static NOTIFICATION_BROADCASTS: RefCell<StableBTreeMap<u64, NotificationBroadcast, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(62)))
+        )
+    );
+}
+
+// Broadcast recipients keyed by (broadcast ID, position), so delivery reads one batch at a time
+thread_local! {
+    pub static BROADCAST_RECIPIENTS: RefCell<StableBTreeMap<(u64, u64), Principal, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(82)))
+        )
+    );
+}
+
+// Per-borrower exceptions to the active loan limit
+thread_local! {
+    pub static LOAN_LIMIT_OVERRIDES: RefCell<StableBTreeMap<Principal, BorrowerLoanLimitOverride, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(63)))
+        )
+    );
+}
+
+// Stable data schema version, under key 0
+thread_local! {
+    static SCHEMA_VERSION_STORAGE: RefCell<StableBTreeMap<u8, u32, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(64)))
+        )
+    );
+}
+
+// Rolling 24h deposit and withdrawal usage per investor, for transaction limits
+thread_local! {
+    pub static TRANSACTION_USAGE: RefCell<StableBTreeMap<Principal, TransactionUsage, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(65)))
+        )
+    );
+}
+
+// Protocol fees collected from repayments, keyed by "loan_id:payment_index"
+thread_local! {
+    pub static COLLECTED_REPAYMENT_FEES: RefCell<StableBTreeMap<String, RepaymentFeeCollection, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(66)))
+        )
+    );
+}
+
+// Satoshi credited so far by each promotional credit campaign, keyed by campaign ID
+thread_local! {
+    pub static PROMO_CREDIT_TOTALS: RefCell<StableBTreeMap<String, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(74)))
+        )
+    );
+}
+
+// Prepayment penalties collected from borrowers but not yet routed to the insurance fund and treasury, keyed by loan ID
+thread_local! {
+    pub static UNROUTED_PREPAYMENT_PENALTIES: RefCell<StableBTreeMap<u64, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(77)))
+        )
+    );
+}
+
+// Scheduled maintenance windows
+thread_local! {
+    pub static MAINTENANCE_WINDOWS: RefCell<StableBTreeMap<u64, MaintenanceWindow, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(32)))
+        )
+    );
+}
+
+// Storage for loan application underwriting reviews
+thread_local! {
+    pub static LOAN_APPLICATION_REVIEWS: RefCell<StableBTreeMap<u64, LoanApplicationReview, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(24)))
+        )
+    );
+}
+
+// Storage for NFT warehouse receipt attestation history
+thread_local! {
+    pub static NFT_ATTESTATION_HISTORY: RefCell<StableBTreeMap<u64, NFTAttestationHistory, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(18)))
+        )
+    );
+}
+
+// Storage for authorized collateral attestors (principal -> added_at)
+thread_local! {
+    pub static COLLATERAL_ATTESTORS: RefCell<StableBTreeMap<Principal, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(19)))
+        )
+    );
+}
+
+// Monotonic ID counters (IdCounter::storage_key -> next ID to issue).
+// Kept in stable memory so IDs are never reused after an upgrade.
+thread_local! {
+    static ID_COUNTERS: RefCell<StableBTreeMap<u8, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(33)))
+        )
+    );
+}
+
+/// Issue the next ID for a counter. IDs start at 1.
+pub fn allocate_id(counter: IdCounter) -> u64 {
+    ID_COUNTERS.with(|counters| {
+        let mut counters = counters.borrow_mut();
+        let id = counters.get(&counter.storage_key()).unwrap_or(1);
+        counters.insert(counter.storage_key(), id + 1);
+        id
     })
 }
 
-pub fn next_collateral_id() -> u64 {
-    COLLATERAL_COUNTER.with(|counter| {
-        let current = *counter.borrow();
-        *counter.borrow_mut() = current + 1;
-        current + 1
+/// The ID the counter will issue next
+pub fn peek_next_id(counter: IdCounter) -> u64 {
+    ID_COUNTERS.with(|counters| counters.borrow().get(&counter.storage_key()).unwrap_or(1))
+}
+
+pub(crate) fn set_next_id(counter: IdCounter, next_id: u64) {
+    ID_COUNTERS.with(|counters| {
+        counters.borrow_mut().insert(counter.storage_key(), next_id);
+    });
+}
+
+/// Move a counter past the largest key already in use, if it is not already
+pub fn reconcile_id_counter(counter: IdCounter, max_existing_id: Option<u64>) -> Option<IdCounterRepair> {
+    let max_existing_id = max_existing_id?;
+    let previous_next_id = peek_next_id(counter);
+    if previous_next_id > max_existing_id {
+        return None;
+    }
+
+    let repaired_next_id = max_existing_id + 1;
+    set_next_id(counter, repaired_next_id);
+    Some(IdCounterRepair {
+        counter,
+        previous_next_id,
+        repaired_next_id,
+        max_existing_id,
     })
 }
 
+/// Largest key in use for each counter. Disbursements are keyed by loan ID, so that
+/// counter has no map of its own to check against.
+fn max_existing_id(counter: IdCounter) -> Option<u64> {
+    match counter {
+        IdCounter::NftToken => RWA_NFTS.with(|nfts| nfts.borrow().last_key_value().map(|(id, _)| id)),
+        IdCounter::Collateral => COLLATERAL_RECORDS.with(|records| records.borrow().last_key_value().map(|(id, _)| id)),
+        IdCounter::AuditLog => {
+            // Basic and enhanced audit logs share one counter
+            let basic = AUDIT_LOGS.with(|logs| logs.borrow().last_key_value().map(|(id, _)| id));
+            basic.max(crate::audit_logging::max_enhanced_audit_log_id())
+        },
+        IdCounter::Loan => LOANS.with(|loans| loans.borrow().last_key_value().map(|(id, _)| id)),
+        IdCounter::Disbursement => None,
+        IdCounter::PriceAlert => PRICE_ALERT_SUBSCRIPTIONS.with(|alerts| alerts.borrow().last_key_value().map(|(id, _)| id)),
+        IdCounter::LoanEvent => LOAN_EVENT_LOG.with(|events| events.borrow().last_key_value().map(|(seq, _)| seq)),
+        IdCounter::PositionOffer => POSITION_OFFERS.with(|offers| offers.borrow().last_key_value().map(|(id, _)| id)),
+        IdCounter::PromoCampaign => PROMO_CAMPAIGNS.with(|campaigns| campaigns.borrow().last_key_value().map(|(id, _)| id)),
+    }
+}
+
+/// Check every counter against its map after an upgrade, repairing any that fell behind
+pub fn repair_id_counters() -> Vec<IdCounterRepair> {
+    IdCounter::ALL.iter()
+        .filter_map(|counter| reconcile_id_counter(*counter, max_existing_id(*counter)))
+        .collect()
+}
+
+// Helper functions for ID generation
+pub fn next_nft_token_id() -> u64 {
+    allocate_id(IdCounter::NftToken)
+}
+
+pub fn next_collateral_id() -> u64 {
+    allocate_id(IdCounter::Collateral)
+}
+
 pub fn next_loan_id() -> u64 {
-    LOAN_COUNTER.with(|counter| {
-        let current = *counter.borrow();
-        *counter.borrow_mut() = current + 1;
-        current + 1
-    })
+    allocate_id(IdCounter::Loan)
 }
 
 pub fn next_disbursement_id() -> u64 {
-    DISBURSEMENT_COUNTER.with(|counter| {
-        let current = *counter.borrow();
-        *counter.borrow_mut() = current + 1;
-        current + 1
-    })
+    allocate_id(IdCounter::Disbursement)
+}
+
+pub fn next_audit_log_id() -> u64 {
+    allocate_id(IdCounter::AuditLog)
+}
+
+// Helper function to get NFT by token ID
+pub fn get_nft_by_token_id(token_id: u64) -> Option<RWANFTData> {
+    RWA_NFTS.with(|nfts| nfts.borrow().get(&token_id))
+}
+
+// Helper function to get collateral by ID
+pub fn get_collateral_by_id(collateral_id: u64) -> Option<CollateralRecord> {
+    COLLATERAL_RECORDS.with(|records| records.borrow().get(&collateral_id))
+}
+
+// Helper function to get loan by ID
+pub fn get_loan_by_id(loan_id: u64) -> Option<Loan> {
+    LOANS.with(|loans| loans.borrow().get(&loan_id))
+}
+
+// Update collateral status
+pub fn update_collateral_status(token_id: u64, status: CollateralStatus, loan_id: Option<u64>) {
+    COLLATERAL_RECORDS.with(|records| {
+        let mut records_map = records.borrow_mut();
+        for (collateral_id, record) in records_map.iter() {
+            if record.nft_token_id == token_id {
+                let mut updated_record = record.clone();
+                updated_record.status = status;
+                updated_record.loan_id = loan_id;
+                updated_record.updated_at = time();
+                records_map.insert(collateral_id, updated_record);
+                break;
+            }
+        }
+    });
+}
+
+// Count NFTs owned by a user
+pub fn count_user_nfts(owner: &Principal) -> u64 {
+    RWA_NFTS.with(|nfts| {
+        nfts.borrow()
+            .iter()
+            .filter(|(_, nft_data)| nft_data.owner == *owner)
+            .count() as u64
+    })
+}
+
+// Configuration management
+pub fn get_config() -> CanisterConfig {
+    CONFIG_STORAGE.with(|config| {
+        config.borrow()
+            .get(&0) // Use key 0 for main config
+            .unwrap_or_default()
+    })
+}
+
+pub fn update_config(new_config: CanisterConfig) -> Result<(), String> {
+    CONFIG_STORAGE.with(|config| {
+        config.borrow_mut().insert(0, new_config);
+        Ok(())
+    })
+}
+
+/// Schema version the stable data was last migrated to; None before versioning was introduced
+pub fn get_stored_schema_version() -> Option<u32> {
+    SCHEMA_VERSION_STORAGE.with(|version| version.borrow().get(&0))
+}
+
+pub fn set_stored_schema_version(version: u32) {
+    SCHEMA_VERSION_STORAGE.with(|stored| {
+        stored.borrow_mut().insert(0, version);
+    });
+}
+
+// Audit logging functions
+pub fn log_action(action: &str, details: &str, success: bool) {
+    let log_entry = AuditLog {
+        timestamp: time(),
+        caller: ic_cdk::api::caller(),
+        action: action.to_string(),
+        details: details.to_string(),
+        success,
+    };
+    
+    AUDIT_LOGS.with(|logs| {
+        let next_id = next_audit_log_id();
+        
+        logs.borrow_mut().insert(next_id, log_entry);
+    });
+}
+
+pub fn log_nft_activity(activity: &str, token_id: u64, caller: Principal) {
+    let details = format!("Token ID: {}, Caller: {}", token_id, caller.to_text());
+    log_action(activity, &details, true);
+}
+
+// Additional helper functions for better storage management
+
+/// Get all NFTs for a specific owner
+pub fn get_nfts_by_owner(owner: &Principal) -> Vec<RWANFTData> {
+    RWA_NFTS.with(|nfts| {
+        nfts.borrow()
+            .iter()
+            .filter(|(_, nft_data)| nft_data.owner == *owner)
+            .map(|(_, nft_data)| nft_data.clone())
+            .collect()
+    })
+}
+
+/// Get collateral record by NFT token ID
+pub fn get_collateral_by_nft_token_id(token_id: u64) -> Option<CollateralRecord> {
+    COLLATERAL_RECORDS.with(|records| {
+        records.borrow()
+            .iter()
+            .find(|(_, record)| record.nft_token_id == token_id)
+            .map(|(_, record)| record.clone())
+    })
+}
+
+/// Get all audit logs for debugging (admin only in production)
+pub fn get_audit_logs(limit: Option<u64>) -> Vec<AuditLog> {
+    AUDIT_LOGS.with(|logs| {
+        let logs_map = logs.borrow();
+        let mut result: Vec<AuditLog> = logs_map.iter()
+            .map(|(_, log)| log.clone())
+            .collect();
+        
+        // Sort by timestamp (newest first)
+        result.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        
+        // Apply limit if specified
+        if let Some(limit) = limit {
+            result.truncate(limit as usize);
+        }
+        
+        result
+    })
+}
+
+/// Clear old audit logs (keep only recent ones)
+pub fn cleanup_audit_logs(keep_recent: u64) {
+    AUDIT_LOGS.with(|logs| {
+        let mut logs_map = logs.borrow_mut();
+        
+        // Get all logs sorted by timestamp
+        let mut all_logs: Vec<(u64, AuditLog)> = logs_map.iter().collect();
+        all_logs.sort_by(|a, b| b.1.timestamp.cmp(&a.1.timestamp));
+        
+        // Remove old logs (keep only recent ones)
+        if all_logs.len() > keep_recent as usize {
+            for (log_id, _) in all_logs.iter().skip(keep_recent as usize) {
+                logs_map.remove(log_id);
+            }
+        }
+    });
+}
+
+// Loan management functions
+pub fn get_next_loan_id() -> u64 {
+    next_loan_id()
+}
+
+pub fn store_loan(loan: Loan) -> Result<(), String> {
+    let previous_status = LOANS.with(|loans| {
+        loans.borrow_mut().insert(loan.id, loan.clone()).map(|previous| previous.status)
+    });
+    record_loan_event(&loan, previous_status.clone());
+    if previous_status.as_ref() != Some(&loan.status) {
+        crate::borrower_reputation::record_loan_outcome(&loan);
+    }
+    bump_state_version(StateDomain::Loans);
+    Ok(())
+}
+
+pub fn update_loan(loan: Loan) -> Result<(), String> {
+    store_loan(loan)
+}
+
+/// Write a loan restored from a snapshot exactly as exported. Its history already happened on
+/// the source canister, so no lifecycle event or reputation outcome is recorded for it.
+pub fn restore_loan(loan: Loan) {
+    LOANS.with(|loans| {
+        loans.borrow_mut().insert(loan.id, loan);
+    });
+    bump_state_version(StateDomain::Loans);
+}
+
+// Loan event log retention: the oldest events are dropped past this count
+pub const MAX_LOAN_EVENTS: u64 = 50_000;
+
+/// Append an event when a loan is created or changes status; other updates are not events
+fn record_loan_event(loan: &Loan, previous_status: Option<LoanStatus>) {
+    if previous_status.as_ref() == Some(&loan.status) {
+        return;
+    }
+
+    let event_type = if previous_status == Some(LoanStatus::Liquidating) && loan.status == LoanStatus::Active {
+        LoanEventType::LiquidationAborted
+    } else {
+        LoanEventType::from_status(&loan.status, previous_status.is_none())
+    };
+    let amount = match event_type {
+        LoanEventType::Created | LoanEventType::SubmittedForApproval => loan.amount_requested,
+        LoanEventType::Repaid => loan.total_repaid,
+        _ => loan.amount_approved,
+    };
+    let event = LoanEvent {
+        sequence: allocate_id(IdCounter::LoanEvent),
+        loan_id: loan.id,
+        event_type,
+        old_status: previous_status,
+        new_status: loan.status.clone(),
+        amount,
+        timestamp: time(),
+    };
+
+    LOAN_EVENT_LOG.with(|events| {
+        let mut events = events.borrow_mut();
+        events.insert(event.sequence, event);
+        while events.len() > MAX_LOAN_EVENTS {
+            match events.first_key_value() {
+                Some((oldest, _)) => { events.remove(&oldest); },
+                None => break,
+            }
+        }
+    });
+}
+
+/// Events with a sequence number above `after_sequence`, oldest first, plus the latest sequence
+/// number issued (0 before the first event)
+pub fn get_loan_events_page(after_sequence: u64, limit: usize) -> (Vec<LoanEvent>, u64) {
+    let latest_sequence = peek_next_id(IdCounter::LoanEvent).saturating_sub(1);
+    let events = LOAN_EVENT_LOG.with(|events| {
+        events.borrow()
+            .range((after_sequence.saturating_add(1))..)
+            .take(limit)
+            .map(|(_, event)| event)
+            .collect()
+    });
+    (events, latest_sequence)
+}
+
+fn entity_tag_key(entity_type: &TaggedEntityType, entity_id: &str) -> String {
+    format!("{}:{}", entity_type.key_prefix(), entity_id)
+}
+
+pub fn get_entity_tags(entity_type: &TaggedEntityType, entity_id: &str) -> Option<EntityTags> {
+    ENTITY_TAGS.with(|tags| tags.borrow().get(&entity_tag_key(entity_type, entity_id)))
+}
+
+pub fn store_entity_tags(entity_tags: EntityTags) {
+    let key = entity_tag_key(&entity_tags.entity_type, &entity_tags.entity_id);
+    ENTITY_TAGS.with(|tags| {
+        let mut tags = tags.borrow_mut();
+        if entity_tags.tags.is_empty() {
+            tags.remove(&key);
+        } else {
+            tags.insert(key, entity_tags);
+        }
+    });
+}
+
+/// IDs of all entities of `entity_type` carrying `tag`
+pub fn get_entity_ids_with_tag(entity_type: &TaggedEntityType, tag: &str) -> Vec<String> {
+    ENTITY_TAGS.with(|tags| {
+        tags.borrow().iter()
+            .map(|(_, entity_tags)| entity_tags)
+            .filter(|entity_tags| entity_tags.entity_type == *entity_type)
+            .filter(|entity_tags| entity_tags.tags.iter().any(|t| t.tag == tag))
+            .map(|entity_tags| entity_tags.entity_id)
+            .collect()
+    })
+}
+
+/// Retained events for one loan, oldest first
+pub fn get_loan_events_for_loan(loan_id: u64) -> Vec<LoanEvent> {
+    LOAN_EVENT_LOG.with(|events| {
+        events.borrow().iter()
+            .map(|(_, event)| event)
+            .filter(|event| event.loan_id == loan_id)
+            .collect()
+    })
+}
+
+/// Lowest sequence number still retained, if any
+pub fn oldest_loan_event_sequence() -> Option<u64> {
+    LOAN_EVENT_LOG.with(|events| events.borrow().first_key_value().map(|(seq, _)| seq))
+}
+
+pub fn get_loan(loan_id: u64) -> Option<Loan> {
+    LOANS.with(|loans| loans.borrow().get(&loan_id))
+}
+
+pub fn get_loans_by_borrower(borrower: Principal) -> Vec<Loan> {
+    LOANS.with(|loans| {
+        loans.borrow()
+            .iter()
+            .filter(|(_, loan)| loan.borrower == borrower)
+            .map(|(_, loan)| loan.clone())
+            .collect()
+    })
+}
+
+// Loan restructure request storage functions
+pub fn get_next_restructure_request_id() -> u64 {
+    LOAN_RESTRUCTURE_REQUESTS.with(|requests| {
+        requests.borrow().last_key_value().map(|(id, _)| id + 1).unwrap_or(1)
+    })
+}
+
+pub fn store_restructure_request(request: LoanRestructureRequest) {
+    LOAN_RESTRUCTURE_REQUESTS.with(|requests| {
+        requests.borrow_mut().insert(request.request_id, request);
+    });
+}
+
+pub fn get_restructure_request(request_id: u64) -> Option<LoanRestructureRequest> {
+    LOAN_RESTRUCTURE_REQUESTS.with(|requests| requests.borrow().get(&request_id))
+}
+
+pub fn get_all_restructure_requests() -> Vec<LoanRestructureRequest> {
+    LOAN_RESTRUCTURE_REQUESTS.with(|requests| {
+        requests.borrow().iter().map(|(_, request)| request).collect()
+    })
+}
+
+// Blacklist storage functions
+pub fn store_blacklist_entry(entry: BlacklistEntry) {
+    BLACKLIST.with(|blacklist| {
+        blacklist.borrow_mut().insert(entry.principal, entry);
+    });
+}
+
+pub fn remove_blacklist_entry(principal: &Principal) -> Option<BlacklistEntry> {
+    BLACKLIST.with(|blacklist| blacklist.borrow_mut().remove(principal))
+}
+
+pub fn get_blacklist_entry(principal: &Principal) -> Option<BlacklistEntry> {
+    BLACKLIST.with(|blacklist| blacklist.borrow().get(principal))
+}
+
+pub fn get_all_blacklist_entries() -> Vec<BlacklistEntry> {
+    BLACKLIST.with(|blacklist| blacklist.borrow().iter().map(|(_, entry)| entry).collect())
+}
+
+pub fn get_blacklist_count() -> u64 {
+    BLACKLIST.with(|blacklist| blacklist.borrow().len())
+}
+
+// BTC address denylist storage functions
+pub fn store_btc_deny_rule(rule: BtcAddressDenyRule) {
+    BTC_ADDRESS_DENYLIST.with(|denylist| {
+        denylist.borrow_mut().insert(BtcAddressDenyRule::storage_key(rule.kind, &rule.pattern), rule);
+    });
+}
+
+pub fn remove_btc_deny_rule(kind: BtcDenyRuleKind, pattern: &str) -> Option<BtcAddressDenyRule> {
+    BTC_ADDRESS_DENYLIST.with(|denylist| {
+        denylist.borrow_mut().remove(&BtcAddressDenyRule::storage_key(kind, pattern))
+    })
+}
+
+pub fn get_all_btc_deny_rules() -> Vec<BtcAddressDenyRule> {
+    BTC_ADDRESS_DENYLIST.with(|denylist| denylist.borrow().iter().map(|(_, rule)| rule).collect())
+}
+
+pub fn store_disbursement_address_override(entry: DisbursementAddressOverride) {
+    DISBURSEMENT_ADDRESS_OVERRIDES.with(|overrides| {
+        overrides.borrow_mut().insert(entry.loan_id, entry);
+    });
+}
+
+pub fn get_disbursement_address_override(loan_id: u64) -> Option<DisbursementAddressOverride> {
+    DISBURSEMENT_ADDRESS_OVERRIDES.with(|overrides| overrides.borrow().get(&loan_id))
+}
+
+// Position offer storage functions
+pub fn next_position_offer_id() -> u64 {
+    allocate_id(IdCounter::PositionOffer)
+}
+
+pub fn store_position_offer(offer: PositionOffer) {
+    POSITION_OFFERS.with(|offers| {
+        offers.borrow_mut().insert(offer.id, offer);
+    });
+}
+
+pub fn get_position_offer(offer_id: u64) -> Option<PositionOffer> {
+    POSITION_OFFERS.with(|offers| offers.borrow().get(&offer_id))
+}
+
+pub fn get_all_position_offers() -> Vec<PositionOffer> {
+    POSITION_OFFERS.with(|offers| offers.borrow().iter().map(|(_, offer)| offer).collect())
+}
+
+// Promo campaign storage functions
+pub fn next_promo_campaign_id() -> u64 {
+    allocate_id(IdCounter::PromoCampaign)
+}
+
+pub fn store_promo_campaign(campaign: PromoCampaign) {
+    PROMO_CAMPAIGNS.with(|campaigns| {
+        campaigns.borrow_mut().insert(campaign.id, campaign);
+    });
+}
+
+pub fn get_promo_campaign(campaign_id: u64) -> Option<PromoCampaign> {
+    PROMO_CAMPAIGNS.with(|campaigns| campaigns.borrow().get(&campaign_id))
+}
+
+pub fn get_all_promo_campaigns() -> Vec<PromoCampaign> {
+    PROMO_CAMPAIGNS.with(|campaigns| campaigns.borrow().iter().map(|(_, campaign)| campaign).collect())
+}
+
+// Price alert subscription storage functions
+pub fn next_price_alert_id() -> u64 {
+    allocate_id(IdCounter::PriceAlert)
+}
+
+pub fn store_price_alert_subscription(alert: PriceAlertSubscription) {
+    PRICE_ALERT_SUBSCRIPTIONS.with(|alerts| {
+        alerts.borrow_mut().insert(alert.id, alert);
+    });
+}
+
+pub fn get_price_alert_subscription(alert_id: u64) -> Option<PriceAlertSubscription> {
+    PRICE_ALERT_SUBSCRIPTIONS.with(|alerts| alerts.borrow().get(&alert_id))
+}
+
+pub fn remove_price_alert_subscription(alert_id: u64) -> Option<PriceAlertSubscription> {
+    PRICE_ALERT_SUBSCRIPTIONS.with(|alerts| alerts.borrow_mut().remove(&alert_id))
+}
+
+pub fn get_price_alert_subscriptions_by_subscriber(subscriber: Principal) -> Vec<PriceAlertSubscription> {
+    PRICE_ALERT_SUBSCRIPTIONS.with(|alerts| {
+        alerts.borrow().iter()
+            .map(|(_, alert)| alert)
+            .filter(|alert| alert.subscriber == subscriber)
+            .collect()
+    })
+}
+
+/// Up to `limit` subscriptions with an ID greater than `after_id`, in ID order
+pub fn get_price_alert_subscriptions_page(after_id: u64, limit: usize) -> Vec<PriceAlertSubscription> {
+    PRICE_ALERT_SUBSCRIPTIONS.with(|alerts| {
+        alerts.borrow().range((after_id + 1)..)
+            .take(limit)
+            .map(|(_, alert)| alert)
+            .collect()
+    })
+}
+
+// State version functions
+pub fn bump_state_version(domain: StateDomain) -> u64 {
+    STATE_VERSIONS.with(|versions| {
+        let mut versions = versions.borrow_mut();
+        let next = versions.get(&domain.storage_key()).unwrap_or(0).saturating_add(1);
+        versions.insert(domain.storage_key(), next);
+        next
+    })
+}
+
+pub fn get_state_version_counter(domain: StateDomain) -> u64 {
+    STATE_VERSIONS.with(|versions| versions.borrow().get(&domain.storage_key()).unwrap_or(0))
+}
+
+// Outbound webhook storage functions
+pub const MAX_OUTBOUND_DELIVERY_RECORDS: u64 = 500;
+
+pub fn get_outbound_webhook_config() -> Option<OutboundWebhookConfig> {
+    OUTBOUND_WEBHOOK_CONFIG.with(|config| config.borrow().get(&0))
+}
+
+pub fn set_outbound_webhook_config(config: OutboundWebhookConfig) {
+    OUTBOUND_WEBHOOK_CONFIG.with(|storage| {
+        storage.borrow_mut().insert(0, config);
+    });
+}
+
+pub fn get_pending_loan_manager() -> Option<PendingLoanManager> {
+    PENDING_LOAN_MANAGER.with(|pending| pending.borrow().get(&0))
+}
+
+pub fn set_pending_loan_manager(pending: PendingLoanManager) {
+    PENDING_LOAN_MANAGER.with(|storage| {
+        storage.borrow_mut().insert(0, pending);
+    });
+}
+
+pub fn clear_pending_loan_manager() {
+    PENDING_LOAN_MANAGER.with(|storage| {
+        storage.borrow_mut().remove(&0);
+    });
+}
+
+pub fn store_feature_flag(flag: FeatureFlag) {
+    FEATURE_FLAGS.with(|flags| {
+        flags.borrow_mut().insert(flag.name.clone(), flag);
+    });
+}
+
+pub fn get_feature_flag(name: &str) -> Option<FeatureFlag> {
+    FEATURE_FLAGS.with(|flags| flags.borrow().get(&name.to_string()))
+}
+
+pub fn get_all_stored_feature_flags() -> Vec<FeatureFlag> {
+    FEATURE_FLAGS.with(|flags| flags.borrow().iter().map(|(_, flag)| flag).collect())
+}
+
+pub fn store_autopay_mandate(mandate: AutopayMandate) {
+    AUTOPAY_MANDATES.with(|mandates| {
+        mandates.borrow_mut().insert(mandate.loan_id, mandate);
+    });
+}
+
+pub fn get_autopay_mandate(loan_id: u64) -> Option<AutopayMandate> {
+    AUTOPAY_MANDATES.with(|mandates| mandates.borrow().get(&loan_id))
+}
+
+pub fn get_enabled_autopay_mandates() -> Vec<AutopayMandate> {
+    AUTOPAY_MANDATES.with(|mandates| {
+        mandates.borrow().iter()
+            .map(|(_, mandate)| mandate)
+            .filter(|mandate| mandate.enabled)
+            .collect()
+    })
+}
+
+pub fn store_borrower_reputation(reputation: BorrowerReputation) {
+    BORROWER_REPUTATIONS.with(|reputations| {
+        reputations.borrow_mut().insert(reputation.borrower, reputation);
+    });
+}
+
+pub fn get_borrower_reputation(borrower: Principal) -> Option<BorrowerReputation> {
+    BORROWER_REPUTATIONS.with(|reputations| reputations.borrow().get(&borrower))
+}
+
+pub fn store_admin_nonce_state(state: AdminNonceState) {
+    ADMIN_NONCES.with(|nonces| {
+        nonces.borrow_mut().insert(state.admin, state);
+    });
+}
+
+pub fn get_admin_nonce_state(admin: Principal) -> Option<AdminNonceState> {
+    ADMIN_NONCES.with(|nonces| nonces.borrow().get(&admin))
+}
+
+pub fn next_broadcast_id() -> u64 {
+    NOTIFICATION_BROADCASTS.with(|broadcasts| {
+        broadcasts.borrow().last_key_value().map(|(id, _)| id + 1).unwrap_or(1)
+    })
+}
+
+pub fn store_notification_broadcast(broadcast: NotificationBroadcast) {
+    NOTIFICATION_BROADCASTS.with(|broadcasts| {
+        broadcasts.borrow_mut().insert(broadcast.id, broadcast);
+    });
+}
+
+/// The `limit` most recent broadcasts, newest first
+pub fn get_recent_notification_broadcasts(limit: usize) -> Vec<NotificationBroadcast> {
+    NOTIFICATION_BROADCASTS.with(|broadcasts| {
+        broadcasts.borrow().iter().rev().take(limit).map(|(_, broadcast)| broadcast).collect()
+    })
+}
+
+pub fn store_broadcast_recipients(broadcast_id: u64, recipients: &[Principal]) {
+    BROADCAST_RECIPIENTS.with(|stored| {
+        let mut stored = stored.borrow_mut();
+        for (index, recipient) in recipients.iter().enumerate() {
+            stored.insert((broadcast_id, index as u64), *recipient);
+        }
+    });
+}
+
+/// Recipients of `broadcast_id` at positions `start..end`
+pub fn get_broadcast_recipients(broadcast_id: u64, start: u64, end: u64) -> Vec<Principal> {
+    BROADCAST_RECIPIENTS.with(|stored| {
+        stored.borrow().range((broadcast_id, start)..(broadcast_id, end)).map(|(_, recipient)| recipient).collect()
+    })
+}
+
+pub fn remove_broadcast_recipients(broadcast_id: u64) {
+    BROADCAST_RECIPIENTS.with(|stored| {
+        let mut stored = stored.borrow_mut();
+        let keys: Vec<(u64, u64)> = stored.range((broadcast_id, 0)..(broadcast_id + 1, 0)).map(|(key, _)| key).collect();
+        for key in keys {
+            stored.remove(&key);
+        }
+    });
+}
+
+pub fn store_loan_limit_override(limit_override: BorrowerLoanLimitOverride) {
+    LOAN_LIMIT_OVERRIDES.with(|overrides| {
+        overrides.borrow_mut().insert(limit_override.borrower, limit_override);
+    });
+}
+
+pub fn remove_loan_limit_override(borrower: Principal) -> Option<BorrowerLoanLimitOverride> {
+    LOAN_LIMIT_OVERRIDES.with(|overrides| overrides.borrow_mut().remove(&borrower))
+}
+
+pub fn get_loan_limit_override(borrower: Principal) -> Option<BorrowerLoanLimitOverride> {
+    LOAN_LIMIT_OVERRIDES.with(|overrides| overrides.borrow().get(&borrower))
+}
+
+pub fn get_transaction_usage(investor: Principal) -> Option<TransactionUsage> {
+    TRANSACTION_USAGE.with(|usage| usage.borrow().get(&investor))
+}
+
+pub fn store_transaction_usage(usage: TransactionUsage) {
+    TRANSACTION_USAGE.with(|stored| {
+        stored.borrow_mut().insert(usage.investor, usage);
+    });
+}
+
+pub fn repayment_fee_key(loan_id: u64, payment_index: u64) -> String {
+    format!("{}:{}", loan_id, payment_index)
+}
+
+pub fn get_repayment_fee_collection(loan_id: u64, payment_index: u64) -> Option<RepaymentFeeCollection> {
+    COLLECTED_REPAYMENT_FEES.with(|fees| fees.borrow().get(&repayment_fee_key(loan_id, payment_index)))
+}
+
+pub fn store_repayment_fee_collection(collection: RepaymentFeeCollection) {
+    COLLECTED_REPAYMENT_FEES.with(|fees| {
+        fees.borrow_mut().insert(repayment_fee_key(collection.loan_id, collection.payment_index), collection);
+    });
 }
 
-// Helper function to get NFT by token ID
-pub fn get_nft_by_token_id(token_id: u64) -> Option<RWANFTData> {
-    RWA_NFTS.with(|nfts| nfts.borrow().get(&token_id))
+pub fn get_all_repayment_fee_collections() -> Vec<RepaymentFeeCollection> {
+    COLLECTED_REPAYMENT_FEES.with(|fees| fees.borrow().iter().map(|(_, collection)| collection).collect())
 }
 
-// Helper function to get collateral by ID
-pub fn get_collateral_by_id(collateral_id: u64) -> Option<CollateralRecord> {
-    COLLATERAL_RECORDS.with(|records| records.borrow().get(&collateral_id))
+pub fn get_promo_credit_total(campaign_id: &str) -> u64 {
+    PROMO_CREDIT_TOTALS.with(|totals| totals.borrow().get(&campaign_id.to_string()).unwrap_or(0))
 }
 
-// Helper function to get loan by ID
-pub fn get_loan_by_id(loan_id: u64) -> Option<Loan> {
-    LOANS.with(|loans| loans.borrow().get(&loan_id))
+pub fn set_promo_credit_total(campaign_id: &str, total: u64) {
+    PROMO_CREDIT_TOTALS.with(|totals| {
+        totals.borrow_mut().insert(campaign_id.to_string(), total);
+    });
 }
 
-// Update collateral status
-pub fn update_collateral_status(token_id: u64, status: CollateralStatus, loan_id: Option<u64>) {
-    COLLATERAL_RECORDS.with(|records| {
-        let mut records_map = records.borrow_mut();
-        for (collateral_id, record) in records_map.iter() {
-            if record.nft_token_id == token_id {
-                let mut updated_record = record.clone();
-                updated_record.status = status;
-                updated_record.loan_id = loan_id;
-                updated_record.updated_at = time();
-                records_map.insert(collateral_id, updated_record);
-                break;
-            }
-        }
+pub fn store_unrouted_prepayment_penalty(loan_id: u64, penalty: u64) {
+    UNROUTED_PREPAYMENT_PENALTIES.with(|penalties| {
+        let mut penalties = penalties.borrow_mut();
+        let pending = penalties.get(&loan_id).unwrap_or(0);
+        penalties.insert(loan_id, pending.saturating_add(penalty));
     });
 }
 
-// Count NFTs owned by a user
-pub fn count_user_nfts(owner: &Principal) -> u64 {
-    RWA_NFTS.with(|nfts| {
-        nfts.borrow()
-            .iter()
-            .filter(|(_, nft_data)| nft_data.owner == *owner)
-            .count() as u64
-    })
+pub fn get_unrouted_prepayment_penalties() -> Vec<(u64, u64)> {
+    UNROUTED_PREPAYMENT_PENALTIES.with(|penalties| penalties.borrow().iter().collect())
 }
 
-// Configuration management
-pub fn get_config() -> CanisterConfig {
-    CONFIG_STORAGE.with(|config| {
-        config.borrow()
-            .get(&0) // Use key 0 for main config
-            .unwrap_or_default()
-    })
+pub fn remove_unrouted_prepayment_penalty(loan_id: u64) {
+    UNROUTED_PREPAYMENT_PENALTIES.with(|penalties| {
+        penalties.borrow_mut().remove(&loan_id);
+    });
 }
 
-pub fn update_config(new_config: CanisterConfig) -> Result<(), String> {
-    CONFIG_STORAGE.with(|config| {
-        config.borrow_mut().insert(0, new_config);
-        Ok(())
+/// Oldest unfinished broadcast with an ID above `after`
+pub fn next_in_progress_broadcast(after: u64) -> Option<NotificationBroadcast> {
+    NOTIFICATION_BROADCASTS.with(|broadcasts| {
+        broadcasts.borrow().range((after + 1)..)
+            .map(|(_, broadcast)| broadcast)
+            .find(|broadcast| broadcast.status == BroadcastStatus::InProgress)
     })
 }
 
-// Audit logging functions
-pub fn log_action(action: &str, details: &str, success: bool) {
-    let log_entry = AuditLog {
-        timestamp: time(),
-        caller: ic_cdk::api::caller(),
-        action: action.to_string(),
-        details: details.to_string(),
-        success,
-    };
-    
-    AUDIT_LOGS.with(|logs| {
-        let next_id = AUDIT_LOG_COUNTER.with(|counter| {
-            let current = *counter.borrow();
-            *counter.borrow_mut() = current + 1;
-            current
-        });
-        
-        logs.borrow_mut().insert(next_id, log_entry);
+pub fn store_performance_snapshot(snapshot: PerformanceSnapshot) {
+    PERFORMANCE_SNAPSHOTS.with(|snapshots| {
+        snapshots.borrow_mut().insert(snapshot.captured_at, snapshot);
     });
 }
 
-pub fn log_nft_activity(activity: &str, token_id: u64, caller: Principal) {
-    let details = format!("Token ID: {}, Caller: {}", token_id, caller.to_text());
-    log_action(activity, &details, true);
+pub fn get_latest_performance_snapshot() -> Option<PerformanceSnapshot> {
+    PERFORMANCE_SNAPSHOTS.with(|snapshots| snapshots.borrow().last_key_value().map(|(_, snapshot)| snapshot))
 }
 
-// Additional helper functions for better storage management
-
-/// Get all NFTs for a specific owner
-pub fn get_nfts_by_owner(owner: &Principal) -> Vec<RWANFTData> {
-    RWA_NFTS.with(|nfts| {
-        nfts.borrow()
-            .iter()
-            .filter(|(_, nft_data)| nft_data.owner == *owner)
-            .map(|(_, nft_data)| nft_data.clone())
-            .collect()
+/// Snapshots captured in `[from, to]`, oldest first
+pub fn get_performance_snapshots_in_range(from: u64, to: u64) -> Vec<PerformanceSnapshot> {
+    PERFORMANCE_SNAPSHOTS.with(|snapshots| {
+        snapshots.borrow().range(from..=to).map(|(_, snapshot)| snapshot).collect()
     })
 }
 
-/// Get collateral record by NFT token ID
-pub fn get_collateral_by_nft_token_id(token_id: u64) -> Option<CollateralRecord> {
-    COLLATERAL_RECORDS.with(|records| {
-        records.borrow()
-            .iter()
-            .find(|(_, record)| record.nft_token_id == token_id)
-            .map(|(_, record)| record.clone())
+/// Remove up to `max` snapshots captured before `cutoff`, oldest first
+pub fn prune_performance_snapshots(cutoff: u64, max: usize) -> u64 {
+    PERFORMANCE_SNAPSHOTS.with(|snapshots| {
+        let mut snapshots = snapshots.borrow_mut();
+        let expired: Vec<u64> = snapshots.range(..cutoff).take(max).map(|(key, _)| key).collect();
+        for key in &expired {
+            snapshots.remove(key);
+        }
+        expired.len() as u64
     })
 }
 
-/// Get all audit logs for debugging (admin only in production)
-pub fn get_audit_logs(limit: Option<u64>) -> Vec<AuditLog> {
-    AUDIT_LOGS.with(|logs| {
-        let logs_map = logs.borrow();
-        let mut result: Vec<AuditLog> = logs_map.iter()
-            .map(|(_, log)| log.clone())
-            .collect();
-        
-        // Sort by timestamp (newest first)
-        result.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-        
-        // Apply limit if specified
-        if let Some(limit) = limit {
-            result.truncate(limit as usize);
-        }
-        
-        result
+pub fn next_outbound_delivery_id() -> u64 {
+    OUTBOUND_EVENT_DELIVERIES.with(|deliveries| {
+        deliveries.borrow().last_key_value().map(|(id, _)| id + 1).unwrap_or(1)
     })
 }
 
-/// Clear old audit logs (keep only recent ones)
-pub fn cleanup_audit_logs(keep_recent: u64) {
-    AUDIT_LOGS.with(|logs| {
-        let mut logs_map = logs.borrow_mut();
-        
-        // Get all logs sorted by timestamp
-        let mut all_logs: Vec<(u64, AuditLog)> = logs_map.iter().collect();
-        all_logs.sort_by(|a, b| b.1.timestamp.cmp(&a.1.timestamp));
-        
-        // Remove old logs (keep only recent ones)
-        if all_logs.len() > keep_recent as usize {
-            for (log_id, _) in all_logs.iter().skip(keep_recent as usize) {
-                logs_map.remove(log_id);
+/// Store a delivery record, pruning the oldest beyond MAX_OUTBOUND_DELIVERY_RECORDS
+pub fn store_outbound_delivery(delivery: OutboundEventDelivery) {
+    OUTBOUND_EVENT_DELIVERIES.with(|deliveries| {
+        let mut deliveries = deliveries.borrow_mut();
+        deliveries.insert(delivery.id, delivery);
+        while deliveries.len() > MAX_OUTBOUND_DELIVERY_RECORDS {
+            match deliveries.first_key_value() {
+                Some((oldest_id, _)) => { deliveries.remove(&oldest_id); },
+                None => break,
             }
         }
     });
 }
 
-// Loan management functions
-pub fn get_next_loan_id() -> u64 {
-    LOAN_COUNTER.with(|counter| {
-        let current = *counter.borrow();
-        *counter.borrow_mut() = current + 1;
-        current + 1
+pub fn get_outbound_deliveries() -> Vec<OutboundEventDelivery> {
+    OUTBOUND_EVENT_DELIVERIES.with(|deliveries| {
+        deliveries.borrow().iter().rev().map(|(_, delivery)| delivery).collect()
     })
 }
 
-pub fn store_loan(loan: Loan) -> Result<(), String> {
-    LOANS.with(|loans| {
-        loans.borrow_mut().insert(loan.id, loan);
-        Ok(())
+// Maintenance window storage functions
+pub fn next_maintenance_window_id() -> u64 {
+    MAINTENANCE_WINDOWS.with(|windows| {
+        windows.borrow().last_key_value().map(|(id, _)| id + 1).unwrap_or(1)
     })
 }
 
-pub fn update_loan(loan: Loan) -> Result<(), String> {
-    LOANS.with(|loans| {
-        loans.borrow_mut().insert(loan.id, loan);
-        Ok(())
-    })
+pub fn store_maintenance_window(window: MaintenanceWindow) {
+    MAINTENANCE_WINDOWS.with(|windows| {
+        windows.borrow_mut().insert(window.id, window);
+    });
 }
 
-pub fn get_loan(loan_id: u64) -> Option<Loan> {
-    LOANS.with(|loans| loans.borrow().get(&loan_id))
+pub fn get_maintenance_window(id: u64) -> Option<MaintenanceWindow> {
+    MAINTENANCE_WINDOWS.with(|windows| windows.borrow().get(&id))
 }
 
-pub fn get_loans_by_borrower(borrower: Principal) -> Vec<Loan> {
+/// Windows that are scheduled or currently active, ordered by start time
+pub fn get_pending_maintenance_windows() -> Vec<MaintenanceWindow> {
+    let mut pending: Vec<MaintenanceWindow> = MAINTENANCE_WINDOWS.with(|windows| {
+        windows.borrow().iter()
+            .map(|(_, window)| window)
+            .filter(|window| matches!(window.status, MaintenanceWindowStatus::Scheduled | MaintenanceWindowStatus::Active))
+            .collect()
+    });
+    pending.sort_by_key(|window| window.start);
+    pending
+}
+
+// Loan application review storage functions
+pub fn store_application_review(review: LoanApplicationReview) {
+    LOAN_APPLICATION_REVIEWS.with(|reviews| {
+        reviews.borrow_mut().insert(review.loan_id, review);
+    });
+}
+
+pub fn get_application_review(loan_id: u64) -> Option<LoanApplicationReview> {
+    LOAN_APPLICATION_REVIEWS.with(|reviews| reviews.borrow().get(&loan_id))
+}
+
+pub fn get_all_application_reviews() -> Vec<LoanApplicationReview> {
+    LOAN_APPLICATION_REVIEWS.with(|reviews| {
+        reviews.borrow().iter().map(|(_, review)| review).collect()
+    })
+}
+
+pub fn get_all_loans_data() -> Vec<Loan> {
     LOANS.with(|loans| {
         loans.borrow()
             .iter()
-            .filter(|(_, loan)| loan.borrower == borrower)
             .map(|(_, loan)| loan.clone())
             .collect()
     })
 }
 
-pub fn get_all_loans_data() -> Vec<Loan> {
+/// Loans `offset..offset + limit` in ID order, without copying the rest of the map
+pub fn get_loans_page(offset: usize, limit: usize) -> Vec<Loan> {
     LOANS.with(|loans| {
         loans.borrow()
             .iter()
-            .map(|(_, loan)| loan.clone())
+            .skip(offset)
+            .take(limit)
+            .map(|(_, loan)| loan)
             .collect()
     })
 }
 
+pub fn get_loan_count() -> u64 {
+    LOANS.with(|loans| loans.borrow().len())
+}
+
 pub fn get_protocol_parameters() -> ProtocolParameters {
     PROTOCOL_PARAMS.with(|params| {
         params.borrow()
@@ -471,6 +1428,24 @@ pub fn unlock_nft(token_id: u64) -> Result<(), String> {
     })
 }
 
+/// Move an NFT lock from one loan to another without ever unlocking it
+pub fn transfer_nft_lock(token_id: u64, from_loan_id: u64, to_loan_id: u64) -> Result<(), String> {
+    RWA_NFTS.with(|nfts| {
+        let mut nfts_map = nfts.borrow_mut();
+        let mut nft_data = nfts_map.get(&token_id).ok_or_else(|| "NFT not found".to_string())?;
+        if !nft_data.is_locked || nft_data.loan_id != Some(from_loan_id) {
+            return Err(format!("NFT is not locked for loan #{}", from_loan_id));
+        }
+
+        nft_data.loan_id = Some(to_loan_id);
+        nft_data.updated_at = time();
+        nfts_map.insert(token_id, nft_data);
+
+        update_collateral_status(token_id, CollateralStatus::Locked, Some(to_loan_id));
+        Ok(())
+    })
+}
+
 pub fn liquidate_collateral(token_id: u64, loan_id: u64) -> Result<(), String> {
     RWA_NFTS.with(|nfts| {
         let mut nfts_map = nfts.borrow_mut();
@@ -520,6 +1495,41 @@ pub fn store_repayment_record(record: RepaymentRecord) -> Result<(), String> {
     })
 }
 
+fn allowance_receipt_key(payer: &Principal, request_id: u64) -> String {
+    format!("{}:{}", payer.to_text(), request_id)
+}
+
+pub fn get_allowance_repayment_receipt(payer: &Principal, request_id: u64) -> Option<AllowanceRepaymentReceipt> {
+    ALLOWANCE_REPAYMENT_RECEIPTS.with(|receipts| receipts.borrow().get(&allowance_receipt_key(payer, request_id)))
+}
+
+pub fn store_allowance_repayment_receipt(receipt: AllowanceRepaymentReceipt) {
+    ALLOWANCE_REPAYMENT_RECEIPTS.with(|receipts| {
+        receipts.borrow_mut().insert(allowance_receipt_key(&receipt.payer, receipt.request_id), receipt);
+    });
+}
+
+pub fn remove_allowance_repayment_receipt(payer: &Principal, request_id: u64) {
+    ALLOWANCE_REPAYMENT_RECEIPTS.with(|receipts| {
+        receipts.borrow_mut().remove(&allowance_receipt_key(payer, request_id));
+    });
+}
+
+// Collateral attestation storage functions
+pub fn load_attestation_history(token_id: u64) -> NFTAttestationHistory {
+    NFT_ATTESTATION_HISTORY.with(|history| history.borrow().get(&token_id).unwrap_or_default())
+}
+
+pub fn store_attestation_history(token_id: u64, history: NFTAttestationHistory) {
+    NFT_ATTESTATION_HISTORY.with(|storage| {
+        storage.borrow_mut().insert(token_id, history);
+    });
+}
+
+pub fn is_collateral_attestor(principal: &Principal) -> bool {
+    COLLATERAL_ATTESTORS.with(|attestors| attestors.borrow().contains_key(principal))
+}
+
 pub fn get_repayment_record(loan_id: u64) -> Option<RepaymentRecord> {
     REPAYMENTS.with(|repayments| repayments.borrow().get(&loan_id))
 }
@@ -539,30 +1549,19 @@ pub fn get_repayment_records_by_loan(loan_id: u64) -> Vec<RepaymentRecord> {
         .collect()
 }
 
+// Both go through store_loan so status changes get their event, reputation outcome and version bump
 pub fn update_loan_status(loan_id: u64, status: LoanStatus) -> Result<(), String> {
-    LOANS.with(|loans| {
-        let mut loans_map = loans.borrow_mut();
-        if let Some(mut loan) = loans_map.get(&loan_id) {
-            loan.status = status;
-            loans_map.insert(loan_id, loan);
-            Ok(())
-        } else {
-            Err("Loan not found".to_string())
-        }
-    })
+    let mut loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    loan.status = status;
+    store_loan(loan)
 }
 
+/// Record a payment of `amount` made now, so a closure that follows is scored by its real date
 pub fn update_loan_repaid_amount(loan_id: u64, amount: u64) -> Result<(), String> {
-    LOANS.with(|loans| {
-        let mut loans_map = loans.borrow_mut();
-        if let Some(mut loan) = loans_map.get(&loan_id) {
-            loan.total_repaid += amount;
-            loans_map.insert(loan_id, loan);
-            Ok(())
-        } else {
-            Err("Loan not found".to_string())
-        }
-    })
+    let mut loan = get_loan(loan_id).ok_or_else(|| "Loan not found".to_string())?;
+    loan.total_repaid = loan.total_repaid.saturating_add(amount);
+    loan.last_payment_date = Some(time());
+    store_loan(loan)
 }
 
 pub fn calculate_remaining_balance(loan_id: u64) -> Result<u64, String> {
@@ -739,7 +1738,7 @@ pub fn get_storage_statistics() -> StorageStats {
     let pool = get_liquidity_pool();
     let total_collateral = COLLATERAL_RECORDS.with(|records| {
         records.borrow().iter()
-            .map(|(_, record)| record.valuation_idr)
+            .map(|(_, record)| record.valuation_idr.0)
             .sum::<u64>()
     });
     
@@ -769,11 +1768,32 @@ pub fn store_commodity_price(commodity_id: String, price: CommodityPriceData) ->
             currency: price.currency.clone(),
             timestamp: price.timestamp,
         };
-        prices.borrow_mut().insert(commodity_id, legacy_price);
+        prices.borrow_mut().insert(commodity_id.clone(), legacy_price);
     });
+    record_price_sample(&commodity_id, price.price_per_unit, price.timestamp);
+    bump_state_version(StateDomain::Oracle);
     Ok(())
 }
 
+pub fn record_price_sample(commodity_id: &str, price_per_unit: u64, timestamp: u64) {
+    PRICE_HISTORY.with(|history| {
+        let mut history_map = history.borrow_mut();
+        let mut entry = history_map.get(&commodity_id.to_string()).unwrap_or_default();
+        entry.samples.push(PriceSample { price_per_unit, timestamp });
+        if entry.samples.len() > MAX_PRICE_SAMPLES {
+            let excess = entry.samples.len() - MAX_PRICE_SAMPLES;
+            entry.samples.drain(..excess);
+        }
+        history_map.insert(commodity_id.to_string(), entry);
+    });
+}
+
+pub fn get_price_samples(commodity_id: &str) -> Vec<PriceSample> {
+    PRICE_HISTORY.with(|history| {
+        history.borrow().get(&commodity_id.to_string()).map(|h| h.samples).unwrap_or_default()
+    })
+}
+
 pub fn get_stored_commodity_price(commodity_id: &str) -> Option<CommodityPriceData> {
     ORACLE_PRICES.with(|prices| {
         prices.borrow().get(&commodity_id.to_string()).map(|legacy_price| {
@@ -826,6 +1846,10 @@ pub fn update_last_price_fetch(commodity_id: &str, timestamp: u64) {
                 last_error: None,
                 average_response_time: 0,
                 rate_limit_reset: 0,
+                rejected_writes: None,
+                forced_writes: None,
+                held_price: None,
+                held_price_source: None,
             });
         
         fetch_record.last_fetch_timestamp = timestamp;
@@ -855,6 +1879,10 @@ pub fn update_price_fetch_failure(commodity_id: &str, error: String) {
                 last_error: None,
                 average_response_time: 0,
                 rate_limit_reset: 0,
+                rejected_writes: None,
+                forced_writes: None,
+                held_price: None,
+                held_price_source: None,
             });
         
         fetch_record.fetch_count += 1;
@@ -866,6 +1894,56 @@ pub fn update_price_fetch_failure(commodity_id: &str, error: String) {
     });
 }
 
+/// Count a write refused by the price sanity band and hold the price for confirmation
+pub fn record_price_write_rejection(commodity_id: &str, price: u64, source: &str) {
+    update_price_guard(commodity_id, |record| {
+        record.rejected_writes = Some(record.rejected_writes.unwrap_or(0) + 1);
+        record.held_price = Some(price);
+        record.held_price_source = Some(source.to_string());
+    });
+}
+
+/// Count an admin write that overrode the price sanity band
+pub fn record_forced_price_write(commodity_id: &str) {
+    update_price_guard(commodity_id, |record| {
+        record.forced_writes = Some(record.forced_writes.unwrap_or(0) + 1);
+        record.held_price = None;
+        record.held_price_source = None;
+    });
+}
+
+/// Drop any held price once a write has been accepted
+pub fn clear_held_price(commodity_id: &str) {
+    update_price_guard(commodity_id, |record| {
+        record.held_price = None;
+        record.held_price_source = None;
+    });
+}
+
+fn update_price_guard(commodity_id: &str, update: impl FnOnce(&mut PriceFetchRecord)) {
+    PRICE_FETCH_TRACKER.with(|tracker| {
+        let mut fetch_record = tracker.borrow().get(&commodity_id.to_string())
+            .unwrap_or(PriceFetchRecord {
+                commodity_id: commodity_id.to_string(),
+                last_fetch_timestamp: 0,
+                fetch_count: 0,
+                success_count: 0,
+                failure_count: 0,
+                last_error: None,
+                average_response_time: 0,
+                rate_limit_reset: 0,
+                rejected_writes: None,
+                forced_writes: None,
+                held_price: None,
+                held_price_source: None,
+            });
+        
+        update(&mut fetch_record);
+        
+        tracker.borrow_mut().insert(commodity_id.to_string(), fetch_record);
+    });
+}
+
 pub fn get_price_fetch_statistics(commodity_id: &str) -> Option<PriceFetchRecord> {
     PRICE_FETCH_TRACKER.with(|tracker| {
         tracker.borrow().get(&commodity_id.to_string())
@@ -874,9 +1952,15 @@ pub fn get_price_fetch_statistics(commodity_id: &str) -> Option<PriceFetchRecord
 
 // Liquidity Management Storage Functions
 
+/// The ckBTC pool
 pub fn get_liquidity_pool() -> LiquidityPool {
+    get_asset_pool(&Asset::CkBtc)
+}
+
+/// Pool for `asset`; the ckBTC pool is the one stored under key 0 before assets existed
+pub fn get_asset_pool(asset: &Asset) -> LiquidityPool {
     LIQUIDITY_POOL.with(|pool| {
-        pool.borrow().get(&0).unwrap_or(LiquidityPool {
+        pool.borrow().get(&asset.pool_key()).unwrap_or(LiquidityPool {
             total_liquidity: 0,
             available_liquidity: 0,
             total_borrowed: 0,
@@ -886,17 +1970,73 @@ pub fn get_liquidity_pool() -> LiquidityPool {
             apy: 0,
             created_at: time(),
             updated_at: time(),
+            reserved_liquidity: None,
         })
     })
 }
 
+pub fn store_liquidity_reservation(reservation: LiquidityReservation) {
+    LIQUIDITY_RESERVATIONS.with(|reservations| {
+        reservations.borrow_mut().insert(reservation.loan_id, reservation);
+    });
+}
+
+pub fn get_liquidity_reservation(loan_id: u64) -> Option<LiquidityReservation> {
+    LIQUIDITY_RESERVATIONS.with(|reservations| reservations.borrow().get(&loan_id))
+}
+
+pub fn remove_liquidity_reservation(loan_id: u64) -> Option<LiquidityReservation> {
+    LIQUIDITY_RESERVATIONS.with(|reservations| reservations.borrow_mut().remove(&loan_id))
+}
+
+pub fn get_all_liquidity_reservations() -> Vec<LiquidityReservation> {
+    LIQUIDITY_RESERVATIONS.with(|reservations| {
+        reservations.borrow().iter().map(|(_, reservation)| reservation).collect()
+    })
+}
+
 pub fn store_liquidity_pool(pool: LiquidityPool) -> Result<(), String> {
+    store_asset_pool(&Asset::CkBtc, pool)
+}
+
+pub fn store_asset_pool(asset: &Asset, pool: LiquidityPool) -> Result<(), String> {
     LIQUIDITY_POOL.with(|p| {
-        p.borrow_mut().insert(0, pool);
+        p.borrow_mut().insert(asset.pool_key(), pool);
     });
+    bump_state_version(StateDomain::Pool);
     Ok(())
 }
 
+/// Investor balance in `asset`
+pub fn get_asset_investor_balance(asset: &Asset, investor: Principal) -> Option<InvestorBalance> {
+    match asset {
+        Asset::CkBtc => get_investor_balance_by_principal(investor),
+        Asset::Icp => ICP_INVESTOR_BALANCES.with(|balances| balances.borrow().get(&investor)),
+    }
+}
+
+pub fn get_all_asset_investor_balances(asset: &Asset) -> Vec<InvestorBalance> {
+    match asset {
+        Asset::CkBtc => get_all_investor_balances(),
+        Asset::Icp => ICP_INVESTOR_BALANCES.with(|balances| {
+            balances.borrow().iter().map(|(_, balance)| balance).collect()
+        }),
+    }
+}
+
+pub fn store_asset_investor_balance(asset: &Asset, balance: InvestorBalance) -> Result<(), String> {
+    match asset {
+        Asset::CkBtc => store_investor_balance(balance),
+        Asset::Icp => {
+            ICP_INVESTOR_BALANCES.with(|balances| {
+                balances.borrow_mut().insert(balance.investor, balance);
+            });
+            bump_state_version(StateDomain::Pool);
+            Ok(())
+        }
+    }
+}
+
 pub fn get_investor_balance_by_principal(investor: Principal) -> Option<InvestorBalance> {
     INVESTOR_BALANCES.with(|balances| {
         balances.borrow().get(&investor)
@@ -907,6 +2047,7 @@ pub fn store_investor_balance(balance: InvestorBalance) -> Result<(), String> {
     INVESTOR_BALANCES.with(|balances| {
         balances.borrow_mut().insert(balance.investor, balance);
     });
+    bump_state_version(StateDomain::Pool);
     Ok(())
 }
 
@@ -916,6 +2057,13 @@ pub fn get_all_investor_balances() -> Vec<InvestorBalance> {
     })
 }
 
+/// Investor balances `offset..offset + limit` in principal order
+pub fn get_investor_balances_page(offset: usize, limit: usize) -> Vec<InvestorBalance> {
+    INVESTOR_BALANCES.with(|balances| {
+        balances.borrow().iter().skip(offset).take(limit).map(|(_, balance)| balance).collect()
+    })
+}
+
 pub fn is_transaction_processed(tx_id: u64) -> bool {
     PROCESSED_TRANSACTIONS.with(|transactions| {
         transactions.borrow().contains_key(&tx_id)
@@ -953,6 +2101,22 @@ pub fn is_emergency_paused() -> bool {
     })
 }
 
+// Origination-only pause shares the emergency pause map under its own key
+const ORIGINATION_PAUSE_KEY: u8 = 1;
+
+pub fn set_origination_pause(paused: bool) -> Result<(), String> {
+    EMERGENCY_PAUSE.with(|pause| {
+        pause.borrow_mut().insert(ORIGINATION_PAUSE_KEY, paused);
+    });
+    Ok(())
+}
+
+pub fn is_origination_paused() -> bool {
+    EMERGENCY_PAUSE.with(|pause| {
+        pause.borrow().get(&ORIGINATION_PAUSE_KEY).unwrap_or(false)
+    })
+}
+
 pub fn get_processed_transaction(tx_id: u64) -> Option<ProcessedTransaction> {
     PROCESSED_TRANSACTIONS.with(|transactions| {
         transactions.borrow().get(&tx_id)
@@ -974,3 +2138,35 @@ pub fn get_all_users() -> Vec<User> {
     })
 }
 
+// Memory usage reporting
+const WASM_PAGE_SIZE_BYTES: u64 = 65_536;
+const ENHANCED_AUDIT_LOGS_MEMORY_ID: u8 = 100;
+
+/// Bytes of stable memory allocated to a single virtual memory region
+pub fn get_region_size_bytes(id: u8) -> u64 {
+    let memory = get_memory_by_id(MemoryId::new(id));
+    ic_stable_structures::Memory::size(&memory) * WASM_PAGE_SIZE_BYTES
+}
+
+/// Actual stable memory consumed by the major storage maps
+pub fn get_memory_breakdown() -> MemoryBreakdown {
+    let nfts_bytes = get_region_size_bytes(1);
+    let audit_logs_bytes = get_region_size_bytes(3);
+    let loans_bytes = get_region_size_bytes(5);
+    let investor_balances_bytes = get_region_size_bytes(11);
+    let enhanced_audit_logs_bytes = get_region_size_bytes(ENHANCED_AUDIT_LOGS_MEMORY_ID);
+
+    // MemoryId 255 is reserved by the memory manager for its own bookkeeping
+    let total_bytes: u64 = (0..u8::MAX).map(get_region_size_bytes).sum();
+    let tracked = nfts_bytes + audit_logs_bytes + loans_bytes + investor_balances_bytes + enhanced_audit_logs_bytes;
+
+    MemoryBreakdown {
+        audit_logs_bytes,
+        enhanced_audit_logs_bytes,
+        loans_bytes,
+        investor_balances_bytes,
+        nfts_bytes,
+        other_bytes: total_bytes.saturating_sub(tracked),
+        total_bytes,
+    }
+}
@@ -351,4 +351,237 @@ mod audit_logging_tests {
             None,
         );
     }
+
+    // Helper to build a chained log entry without touching canister APIs
+    fn chained_log(id: u64, previous: Option<&EnhancedAuditLog>) -> EnhancedAuditLog {
+        let mut log = EnhancedAuditLog {
+            id,
+            timestamp: 1_700_000_000_000_000_000 + id,
+            block_height: Some(previous.and_then(|p| p.block_height).unwrap_or(0) + 1),
+            caller: test_principal(),
+            category: AuditCategory::LoanLifecycle,
+            action: format!("CHAIN_TEST_{}", id),
+            level: AuditEventLevel::Info,
+            details: AuditDetails {
+                description: format!("Chained entry {}", id),
+                ..Default::default()
+            },
+            result: AuditResult {
+                success: true,
+                error_code: None,
+                error_message: None,
+                execution_time_ms: None,
+                gas_used: None,
+                cycles_consumed: None,
+                memory_used_bytes: None,
+                warning_flags: vec![],
+            },
+            correlation_id: None,
+            session_id: None,
+            ip_hash: None,
+            canister_id: None,
+            version: "test".to_string(),
+            prev_log_hash: previous.and_then(|p| p.log_hash.clone()),
+            log_hash: None,
+        };
+        log.log_hash = Some(compute_log_hash(&log));
+        log
+    }
+
+    #[test]
+    fn test_hash_chain_detects_corrupted_entry() {
+        let first = chained_log(1, None);
+        let second = chained_log(2, Some(&first));
+        let mut third = chained_log(3, Some(&second));
+
+        // An untouched chain verifies end to end
+        assert!(verify_chain_link(None, &first).is_valid);
+        assert!(verify_chain_link(Some(&first), &second).is_valid);
+        assert!(verify_chain_link(Some(&second), &third).is_valid);
+
+        // Tampering with a stored entry breaks its own hash check
+        let mut corrupted = second.clone();
+        corrupted.details.description = "Rewritten after the fact".to_string();
+        let report = verify_chain_link(Some(&first), &corrupted);
+        assert!(!report.is_valid);
+        assert!(!report.hash_matches);
+
+        // ...and the next entry can no longer vouch for its predecessor
+        let report = verify_chain_link(Some(&corrupted), &third);
+        assert!(!report.is_valid);
+        assert!(!report.chain_link_valid);
+
+        // Re-hashing the corrupted entry does not help: the link from the next entry breaks
+        corrupted.log_hash = Some(compute_log_hash(&corrupted));
+        let report = verify_chain_link(Some(&corrupted), &third);
+        assert!(report.hash_matches);
+        assert!(!report.chain_link_valid);
+
+        // Splicing in a re-linked entry is caught by its own hash
+        third.prev_log_hash = corrupted.log_hash.clone();
+        assert!(!verify_chain_link(Some(&corrupted), &third).hash_matches);
+    }
+
+    #[test]
+    fn test_jsonl_export_round_trips_through_serde() {
+        let mut log = chained_log(7, None);
+        log.details.description = "Path C:\\farm\\\"north\"\n\ttab\u{1}".to_string();
+        log.details.metadata = vec![("region".to_string(), "west java".to_string())];
+        log.details.risk_score = Some(42);
+        log.ip_hash = Some("ip-hash".to_string());
+
+        // Full export keeps every field, including metadata
+        let line = audit_log_to_jsonl_line(log.clone(), true).unwrap();
+        assert!(!line.contains('\n'));
+        let parsed: EnhancedAuditLog = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed.details.description, log.details.description);
+        assert_eq!(parsed.details.metadata, log.details.metadata);
+        assert_eq!(parsed.details.risk_score, Some(42));
+        assert_eq!(parsed.log_hash, log.log_hash);
+
+        // Metadata-free export uses the projection and drops metadata fields
+        let line = audit_log_to_jsonl_line(log.clone(), false).unwrap();
+        assert!(!line.contains("west java"));
+        assert!(!line.contains("ip-hash"));
+        let parsed: AuditLogExportRecord = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed.id, 7);
+        assert_eq!(parsed.description, log.details.description);
+        assert_eq!(parsed.risk_score, Some(42));
+    }
+
+    #[test]
+    fn test_csv_chunks_reassemble_single_shot_export() {
+        let mut logs: Vec<EnhancedAuditLog> = Vec::new();
+        for id in 8_001..=8_007 {
+            let mut log = chained_log(id, logs.last());
+            log.details.description = format!("Entry {}, with comma\nand newline", id);
+            log.details.entity_type = Some("csv_export_test".to_string());
+            log.details.entity_id = Some(format!("loan,{}", id));
+            logs.push(log);
+        }
+        ENHANCED_AUDIT_LOGS.with(|stored| {
+            // Stored out of order: both exports follow the log key, not insertion order
+            for log in logs.iter().rev() {
+                stored.borrow_mut().insert(log.id, log.clone());
+            }
+        });
+        let filter = AuditLogFilter {
+            entity_type: Some("csv_export_test".to_string()),
+            ..Default::default()
+        };
+
+        let single_shot = audit_csv_single_shot(&filter);
+        assert_eq!(single_shot, build_audit_csv(&logs));
+        assert_eq!(single_shot.lines().count(), 8);
+        assert!(!single_shot.contains("loan,"));
+
+        let chunk_size = 3u64;
+        let mut reassembled = String::new();
+        let mut cursor: Option<u64> = None;
+        let mut chunks = 0;
+        loop {
+            let chunk = audit_csv_chunk(&filter, cursor, chunk_size).unwrap();
+            assert_eq!(chunk.header_included, chunks == 0);
+            chunks += 1;
+
+            reassembled.push_str(&chunk.rows);
+            if !chunk.has_more {
+                assert!(chunk.next_cursor.is_none());
+                break;
+            }
+            cursor = chunk.next_cursor;
+        }
+
+        assert_eq!(chunks, 3);
+        assert_eq!(reassembled, single_shot);
+        assert!(audit_csv_chunk(&filter, None, 0).is_err());
+        assert!(audit_csv_chunk(&filter, None, MAX_CSV_CHUNK_SIZE + 1).is_err());
+    }
+
+    #[test]
+    fn test_retention_pass_scrubs_stored_logs_and_keeps_chain_valid() {
+        let mut logs: Vec<EnhancedAuditLog> = Vec::new();
+        for id in 9_001..=9_003 {
+            let mut log = chained_log(id, logs.last());
+            log.ip_hash = Some(format!("ip-{}", id));
+            log.details.location_hash = Some("loc".to_string());
+            log.details.user_agent_hash = Some("ua".to_string());
+            if id == 9_002 {
+                log.category = AuditCategory::Security;
+            }
+            log.log_hash = Some(compute_log_hash(&log));
+            logs.push(log);
+        }
+        ENHANCED_AUDIT_LOGS.with(|stored| {
+            for log in &logs {
+                stored.borrow_mut().insert(log.id, log.clone());
+            }
+        });
+
+        let config = AuditConfiguration {
+            anonymization_enabled: true,
+            anonymization_after_days: Some(90),
+            anonymize_caller_principal: Some(true),
+            ..AuditConfiguration::default()
+        };
+        let now = logs[0].timestamp + 100 * 24 * 60 * 60 * 1_000_000_000;
+        assert_eq!(build_retention_compliance_status(&config, now).pending_anonymization, 3);
+
+        let (anonymized, deleted) = enforce_audit_retention(&config, now, 500);
+        assert_eq!((anonymized, deleted), (3, 0));
+
+        let stored: Vec<EnhancedAuditLog> = ENHANCED_AUDIT_LOGS.with(|stored| {
+            (9_001..=9_003).map(|id| stored.borrow().get(&id).unwrap()).collect()
+        });
+        for log in &stored {
+            assert!(log.ip_hash.is_none());
+            assert!(log.details.location_hash.is_none());
+            assert!(log.details.user_agent_hash.is_none());
+        }
+        assert_eq!(stored[0].caller, Principal::anonymous());
+        assert_eq!(stored[1].caller, test_principal()); // Security logs keep their caller
+        assert_eq!(build_retention_compliance_status(&config, now).pending_anonymization, 0);
+
+        // Scrubbed entries still verify, and later edits are still caught
+        assert!(verify_chain_link(Some(&stored[0]), &stored[1]).is_valid);
+        assert!(verify_chain_link(Some(&stored[1]), &stored[2]).is_valid);
+        let mut tampered = stored[1].clone();
+        tampered.details.description = "Rewritten after anonymization".to_string();
+        assert!(!verify_chain_link(Some(&stored[0]), &tampered).hash_matches);
+    }
+
+    #[test]
+    fn test_cleanup_of_20k_logs_completes_across_bounded_calls() {
+        let day = 24 * 60 * 60 * 1_000_000_000u64;
+        // 15k expired logs followed by 5k recent ones, keyed by time-ordered log ID
+        let mut logs: std::collections::BTreeMap<u64, u64> = (1..=20_000u64)
+            .map(|id| (id, if id <= 15_000 { id } else { 100 * day + id }))
+            .collect();
+        let cutoff = 50 * day;
+
+        let mut cursor = 0u64;
+        let mut calls = 0;
+        loop {
+            let (to_remove, next_cursor) =
+                plan_audit_cleanup_step(logs.range(cursor..).map(|(id, ts)| (*id, *ts)), cutoff, 2_000);
+            assert!(to_remove.len() <= 2_000);
+            for id in &to_remove {
+                logs.remove(id);
+            }
+            calls += 1;
+            match next_cursor {
+                Some(next) => cursor = next,
+                None => break,
+            }
+        }
+
+        assert_eq!(calls, 8);
+        assert_eq!(logs.len(), 5_000);
+        assert!(logs.values().all(|ts| *ts >= cutoff));
+
+        // Caught up: nothing left to plan
+        let (to_remove, next_cursor) = plan_audit_cleanup_step(logs.iter().map(|(id, ts)| (*id, *ts)), cutoff, 2_000);
+        assert!(to_remove.is_empty());
+        assert_eq!(next_cursor, None);
+    }
 }
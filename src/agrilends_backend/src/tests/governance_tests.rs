@@ -466,6 +466,49 @@ mod governance_tests {
         assert!(param.is_ok());
         assert_eq!(param.unwrap().current_value, 8500);
     }
+    
+    #[test]
+    fn test_parameter_snapshot_diff() {
+        let current = vec![
+            ("base_interest_rate".to_string(), 1500),
+            ("loan_to_value_ratio".to_string(), 6000),
+            ("custom_flag".to_string(), 1),
+        ];
+        let snapshot = vec![
+            ("base_interest_rate".to_string(), 1000),
+            ("loan_to_value_ratio".to_string(), 6000),
+            ("grace_period_days".to_string(), 30),
+        ];
+        
+        let diff = diff_parameter_values(&current, &snapshot);
+        
+        assert_eq!(diff, vec![
+            ParameterDiff { key: "base_interest_rate".to_string(), current_value: Some(1500), snapshot_value: Some(1000) },
+            ParameterDiff { key: "custom_flag".to_string(), current_value: Some(1), snapshot_value: None },
+            ParameterDiff { key: "grace_period_days".to_string(), current_value: None, snapshot_value: Some(30) },
+        ]);
+        assert!(diff_parameter_values(&snapshot, &snapshot).is_empty());
+    }
+    
+    #[test]
+    fn test_restore_plan_writes_only_snapshot_parameters() {
+        let current = vec![
+            ("base_interest_rate".to_string(), 1500),
+            ("custom_flag".to_string(), 1),
+            ("emergency_stop".to_string(), 1),
+            ("maintenance_mode".to_string(), 0),
+        ];
+        let snapshot = vec![
+            ("base_interest_rate".to_string(), 1000),
+            ("emergency_stop".to_string(), 0),
+            ("maintenance_mode".to_string(), 1),
+        ];
+        
+        // The newer custom_flag stays, and the live pause flags are not rolled back
+        assert_eq!(parameter_restore_plan(&current, &snapshot), vec![
+            ParameterDiff { key: "base_interest_rate".to_string(), current_value: Some(1500), snapshot_value: Some(1000) },
+        ]);
+    }
 }
 
 // Integration tests for governance system
@@ -523,4 +566,17 @@ mod governance_integration_tests {
         
         println!("✅ Complete governance workflow test passed successfully!");
     }
+    
+    #[test]
+    fn test_loan_manager_candidate_validation() {
+        let current = get_test_admin();
+        let candidate = get_test_user();
+        
+        assert!(validate_loan_manager_candidate(&candidate, Some(current)).is_ok());
+        assert!(validate_loan_manager_candidate(&candidate, None).is_ok());
+        // Re-proposing the active manager or an unsignable principal is refused
+        assert!(validate_loan_manager_candidate(&current, Some(current)).is_err());
+        assert!(validate_loan_manager_candidate(&Principal::anonymous(), Some(current)).is_err());
+        assert!(validate_loan_manager_candidate(&Principal::management_canister(), None).is_err());
+    }
 }
@@ -322,6 +322,20 @@ mod liquidation_tests {
         assert!(emergency_loan.due_date.unwrap() > time()); // Future due date
     }
 
+    /// Test 11: Auto-liquidation vs manual review bands
+    #[test]
+    fn test_liquidation_band_thresholds() {
+        let auto_max = 100_000_000;
+
+        assert_eq!(liquidation_band_for(25_000_000, auto_max), LiquidationBand::Automatic);
+        assert_eq!(liquidation_band_for(auto_max - 1, auto_max), LiquidationBand::Automatic);
+        // The threshold itself and anything above needs a human
+        assert_eq!(liquidation_band_for(auto_max, auto_max), LiquidationBand::ManualReview);
+        assert_eq!(liquidation_band_for(500_000_000, auto_max), LiquidationBand::ManualReview);
+        // A zero threshold sends every loan to review
+        assert_eq!(liquidation_band_for(1, 0), LiquidationBand::ManualReview);
+    }
+
     /// Run all liquidation tests
     pub fn run_all_liquidation_tests() {
         println!("🔥 Starting Comprehensive Liquidation Tests");
@@ -28,6 +28,7 @@ mod liquidity_withdrawal_tests {
             withdrawals: vec![],
             first_deposit_at: time(),
             last_activity_at: time(),
+            total_fees_paid: None,
         };
         store_investor_balance(investor_balance).unwrap();
         
@@ -42,6 +43,7 @@ mod liquidity_withdrawal_tests {
             apy: 8,
             created_at: time(),
             updated_at: time(),
+            reserved_liquidity: None,
         };
         store_liquidity_pool(pool).unwrap();
         
@@ -79,6 +81,7 @@ mod liquidity_withdrawal_tests {
             withdrawals: vec![],
             first_deposit_at: time(),
             last_activity_at: time(),
+            total_fees_paid: None,
         };
         store_investor_balance(investor_balance).unwrap();
         
@@ -93,6 +96,7 @@ mod liquidity_withdrawal_tests {
             apy: 8,
             created_at: time(),
             updated_at: time(),
+            reserved_liquidity: None,
         };
         store_liquidity_pool(pool).unwrap();
         
@@ -119,6 +123,7 @@ mod liquidity_withdrawal_tests {
             withdrawals: vec![],
             first_deposit_at: time(),
             last_activity_at: time(),
+            total_fees_paid: None,
         };
         store_investor_balance(investor_balance).unwrap();
         
@@ -133,6 +138,7 @@ mod liquidity_withdrawal_tests {
             apy: 8,
             created_at: time(),
             updated_at: time(),
+            reserved_liquidity: None,
         };
         store_liquidity_pool(pool).unwrap();
         
@@ -142,6 +148,97 @@ mod liquidity_withdrawal_tests {
         assert!(validation_result.unwrap_err().contains("pool liquidity"));
     }
     
+    /// Test withdrawal against liquidity reserved for approved loans
+    #[test]
+    fn test_withdrawal_excludes_reserved_liquidity() {
+        let investor = Principal::from_text("rdmx6-jaaaa-aaaah-qcaiq-cai").unwrap();
+        let balance = 1_000_000u64; // 0.01 BTC
+        let withdrawal_amount = 500_000u64; // 0.005 BTC
+        
+        let investor_balance = InvestorBalance {
+            investor,
+            balance,
+            total_deposited: balance,
+            total_withdrawn: 0,
+            deposits: vec![],
+            withdrawals: vec![],
+            first_deposit_at: time(),
+            last_activity_at: time(),
+            total_fees_paid: None,
+        };
+        store_investor_balance(investor_balance).unwrap();
+        
+        // Enough available liquidity, but most of it is reserved for approved loans
+        let pool = LiquidityPool {
+            total_liquidity: 10_000_000u64,
+            available_liquidity: 1_000_000u64,
+            total_borrowed: 9_000_000u64,
+            total_repaid: 0,
+            utilization_rate: 90,
+            total_investors: 1,
+            apy: 8,
+            created_at: time(),
+            updated_at: time(),
+            reserved_liquidity: Some(800_000u64),
+        };
+        store_liquidity_pool(pool).unwrap();
+        
+        // Unreserved: 1_000_000 - 800_000 = 200_000 < 500_000
+        let validation_result = validate_withdrawal_request(withdrawal_amount);
+        assert!(validation_result.is_err());
+        assert!(validation_result.unwrap_err().contains("Available: 200000"));
+    }
+    
+    /// Test that a withdrawal holds its liquidity while the ledger call is in flight and
+    /// returns it when the transfer fails
+    #[tokio::test]
+    async fn test_withdrawal_reserves_liquidity_across_the_ledger_call() {
+        let investor = Principal::from_slice(&[61u8; 29]);
+        store_asset_investor_balance(&Asset::CkBtc, InvestorBalance {
+            investor,
+            balance: 1_000_000,
+            total_deposited: 1_000_000,
+            total_withdrawn: 0,
+            deposits: vec![],
+            withdrawals: vec![],
+            first_deposit_at: 0,
+            last_activity_at: 0,
+            total_fees_paid: None,
+        }).unwrap();
+        store_liquidity_pool(LiquidityPool {
+            total_liquidity: 10_000_000,
+            available_liquidity: 8_000_000,
+            total_borrowed: 2_000_000,
+            total_repaid: 0,
+            utilization_rate: 20,
+            total_investors: 1,
+            apy: 8,
+            created_at: time(),
+            updated_at: time(),
+            reserved_liquidity: None,
+        }).unwrap();
+        
+        let failed = withdraw_within_limits(investor, Satoshi(500_000), Asset::CkBtc, LotMethod::Aggregate, |asset, _| async move {
+            // A withdrawal checked now no longer sees the amount in flight
+            assert_eq!(get_asset_pool(&asset).available_liquidity, 7_500_000);
+            assert_eq!(get_asset_investor_balance(&asset, investor).unwrap().balance, 500_000);
+            Ok((Err(TransferError::TemporarilyUnavailable),))
+        }).await;
+        assert!(failed.is_err());
+        assert_eq!(get_asset_pool(&Asset::CkBtc).available_liquidity, 8_000_000);
+        assert_eq!(get_asset_pool(&Asset::CkBtc).total_liquidity, 10_000_000);
+        assert_eq!(get_asset_investor_balance(&Asset::CkBtc, investor).unwrap().balance, 1_000_000);
+        
+        let settled = withdraw_within_limits(investor, Satoshi(500_000), Asset::CkBtc, LotMethod::Aggregate, |_, _| async {
+            Ok((Ok(candid::Nat::from(77u64)),))
+        }).await;
+        assert!(settled.unwrap().contains("Transaction Block: 77"));
+        assert_eq!(get_asset_pool(&Asset::CkBtc).available_liquidity, 7_500_000);
+        let balance = get_asset_investor_balance(&Asset::CkBtc, investor).unwrap();
+        assert_eq!((balance.balance, balance.total_withdrawn), (500_000, 500_000));
+        assert_eq!(balance.withdrawals.len(), 1);
+    }
+    
     /// Test withdrawal that would violate emergency reserve
     #[test]
     fn test_withdrawal_emergency_reserve_violation() {
@@ -159,6 +256,7 @@ mod liquidity_withdrawal_tests {
             withdrawals: vec![],
             first_deposit_at: time(),
             last_activity_at: time(),
+            total_fees_paid: None,
         };
         store_investor_balance(investor_balance).unwrap();
         
@@ -173,6 +271,7 @@ mod liquidity_withdrawal_tests {
             apy: 8,
             created_at: time(),
             updated_at: time(),
+            reserved_liquidity: None,
         };
         store_liquidity_pool(pool).unwrap();
         
@@ -237,12 +336,14 @@ mod liquidity_withdrawal_tests {
                     amount: 600_000u64,
                     ckbtc_block_index: 1,
                     timestamp: current_time - (30 * 24 * 60 * 60 * 1_000_000_000), // 30 days ago
+                    fee: None,
                 },
                 DepositRecord {
                     investor,
                     amount: 400_000u64,
                     ckbtc_block_index: 2,
                     timestamp: current_time - (15 * 24 * 60 * 60 * 1_000_000_000), // 15 days ago
+                    fee: None,
                 },
             ],
             withdrawals: vec![
@@ -251,10 +352,13 @@ mod liquidity_withdrawal_tests {
                     amount: 200_000u64,
                     ckbtc_block_index: 3,
                     timestamp: current_time - (5 * 24 * 60 * 60 * 1_000_000_000), // 5 days ago
+                    fee: None,
+                    lots: None,
                 },
             ],
             first_deposit_at: current_time - (30 * 24 * 60 * 60 * 1_000_000_000),
             last_activity_at: current_time - (5 * 24 * 60 * 60 * 1_000_000_000),
+            total_fees_paid: None,
         };
         store_investor_balance(investor_balance).unwrap();
         
@@ -269,6 +373,7 @@ mod liquidity_withdrawal_tests {
             apy: 8,
             created_at: current_time - (60 * 24 * 60 * 60 * 1_000_000_000),
             updated_at: current_time,
+            reserved_liquidity: None,
         };
         store_liquidity_pool(pool).unwrap();
         
@@ -305,6 +410,7 @@ mod liquidity_withdrawal_tests {
             withdrawals: vec![],
             first_deposit_at: time(),
             last_activity_at: time(),
+            total_fees_paid: None,
         };
         store_investor_balance(investor_balance.clone()).unwrap();
         
@@ -3,6 +3,7 @@ use crate::types::*;
 use crate::user_management::*;
 // use crate::rwa_nft::*; // Commented out unused import
 use crate::storage::*;
+use crate::helpers::{get_commodity_ltv_ratio, risk_adjust_health_ratio, commodity_share_after_bps, exposure_cap_applies, get_region_from_metadata, build_regional_exposure};
 use candid::Principal;
 
 #[cfg(test)]
@@ -25,6 +26,10 @@ mod tests {
             email: Some("farmer@test.com".to_string()),
             phone: Some("+1234567890".to_string()),
             profile_completed: true,
+            preferred_language: None,
+            kyc_tier: None,
+            kyc_reference: None,
+            kyc_updated_at: None,
         };
         
         // Store user in the system (this would normally be done through register_user function)
@@ -74,6 +79,17 @@ mod tests {
             base_apr: 10,            // 10% annual rate
             max_loan_duration_days: 365, // 1 year
             grace_period_days: 30,   // 30 days grace period
+            commodity_ltv_overrides: None,
+            max_commodity_exposure_bps: None,
+            pricing_mode: None,
+            twap_window_seconds: None,
+            region_exposure_caps: None,
+            prepayment_penalty_bps: None,
+            prepayment_free_window_days: None,
+            compounding_frequency: None,
+            compounding_changes: None,
+            paused_commodities: None,
+            pool_apy: None,
         };
         
         PROTOCOL_PARAMS.with(|storage| {
@@ -81,6 +97,25 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_commodity_ltv_override_falls_back_to_global() {
+        setup_protocol_parameters();
+        assert_eq!(get_commodity_ltv_ratio("rice"), 60);
+
+        let mut params = get_protocol_parameters();
+        params.commodity_ltv_overrides.get_or_insert_with(Default::default).insert("rice".to_string(), 30);
+        set_protocol_parameters(params).unwrap();
+
+        assert_eq!(get_commodity_ltv_ratio("rice"), 30);
+        assert_eq!(get_commodity_ltv_ratio("Rice"), 30);
+        assert_eq!(get_commodity_ltv_ratio("corn"), 60);
+
+        // Half the global LTV means the same raw ratio reads as half as healthy
+        assert_eq!(risk_adjust_health_ratio(2.0, Some("rice")), 1.0);
+        assert_eq!(risk_adjust_health_ratio(2.0, Some("corn")), 2.0);
+        assert_eq!(risk_adjust_health_ratio(2.0, None), 2.0);
+    }
+
     #[test]
     fn test_extract_valuation_from_metadata() {
         let metadata = vec![
@@ -91,7 +126,7 @@ mod tests {
 
         let result = extract_valuation_from_metadata(&metadata);
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), 1_000_000_000);
+        assert_eq!(result.unwrap(), Idr(1_000_000_000));
     }
 
     #[test]
@@ -139,13 +174,20 @@ mod tests {
     #[test]
     fn test_calculate_collateral_value_btc() {
         let mock_time = 1234567890_u64;
-        let commodity_price = CommodityPrice {
+        let commodity_price = CommodityPriceData {
+            commodity_type: "rice".to_string(),
             price_per_unit: 15000,
             currency: "IDR".to_string(),
             timestamp: mock_time,
+            source: "test".to_string(),
+            confidence_score: 100,
+            is_stale: false,
+            fetch_attempt_count: 1,
+            last_successful_fetch: mock_time,
         };
+        let btc_rate = BtcIdrRate { idr_per_btc: 600_000_000, timestamp: mock_time };
 
-        let result = calculate_collateral_value_btc(1_000_000_000, 10000, &commodity_price);
+        let result = calculate_collateral_value_btc(Idr(1_000_000_000), 10000, &commodity_price, &btc_rate);
         assert!(result.is_ok());
         
         let collateral_value = result.unwrap();
@@ -153,7 +195,30 @@ mod tests {
         // Market value = 10000 * 15000 = 150,000,000 IDR
         // Conservative = min(1,000,000,000, 150,000,000) = 150,000,000 IDR
         // In satoshi = (150,000,000 * 100,000,000) / 600,000,000 = 25,000,000 satoshi
-        assert_eq!(collateral_value, 25_000_000);
+        assert_eq!(collateral_value, Satoshi(25_000_000));
+
+        // The oracle rate drives the conversion: a doubled BTC price halves the collateral
+        let doubled = BtcIdrRate { idr_per_btc: 1_200_000_000, timestamp: mock_time };
+        assert_eq!(calculate_collateral_value_btc(Idr(1_000_000_000), 10000, &commodity_price, &doubled), Ok(Satoshi(12_500_000)));
+        // Market value overflow is an error, not a wrapped valuation
+        assert!(calculate_collateral_value_btc(Idr(1_000_000_000), u64::MAX, &commodity_price, &btc_rate).is_err());
+    }
+
+    #[test]
+    fn test_btc_idr_conversion_rounding_and_guards() {
+        let rate = BtcIdrRate { idr_per_btc: 1_000_000_000, timestamp: 0 };
+        assert_eq!(rate.idr_to_satoshi(Idr(10_000_000)), Ok(Satoshi(1_000_000)));
+        assert_eq!(rate.satoshi_to_idr(Satoshi(1_000_000)), Ok(Idr(10_000_000)));
+        // Rounds down to whole satoshi
+        assert_eq!(rate.idr_to_satoshi(Idr(9)), Ok(Satoshi(0)));
+        assert!(BtcIdrRate { idr_per_btc: 0, timestamp: 0 }.idr_to_satoshi(Idr(1)).is_err());
+        assert!(BtcIdrRate { idr_per_btc: 1, timestamp: 0 }.idr_to_satoshi(Idr(u64::MAX)).is_err());
+
+        assert_eq!(Satoshi(5).checked_add(Satoshi(7)), Ok(Satoshi(12)));
+        assert!(Satoshi(u64::MAX).checked_add(Satoshi(1)).is_err());
+        assert!(Satoshi(5).checked_sub(Satoshi(6)).is_err());
+        assert!(Idr(u64::MAX).checked_add(Idr(1)).is_err());
+        assert_eq!(u64::from(Satoshi::from(42)), 42);
     }
 
     #[test]
@@ -183,6 +248,80 @@ mod tests {
         assert_eq!(loan.status, LoanStatus::Active);
     }
 
+    #[test]
+    fn test_resolve_restructured_due_date() {
+        let day = 24 * 60 * 60 * 1_000_000_000_u64;
+        let created_at = 32_000_000_000_000_000_u64;
+        let due_date = created_at + 365 * day;
+        let now = created_at + 300 * day;
+        let loan = Loan {
+            id: 1,
+            borrower: Principal::from_slice(&[2u8; 29]),
+            nft_id: 1,
+            collateral_value_btc: 25_000_000,
+            amount_requested: 15_000_000,
+            amount_approved: 15_000_000,
+            apr: 10,
+            status: LoanStatus::Active,
+            created_at,
+            due_date: Some(due_date),
+            total_repaid: 0,
+            repayment_history: vec![],
+            last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
+        };
+        let proposal = |new_due_date, new_term_days, new_apr| RestructureProposal {
+            new_due_date,
+            new_term_days,
+            new_apr,
+            reason: "Harvest delayed by flooding".to_string(),
+        };
+
+        // Term is measured from loan creation
+        assert_eq!(
+            resolve_restructured_due_date(&loan, &proposal(None, Some(425), None), now),
+            Ok(Some(created_at + 425 * day))
+        );
+
+        // Rate-only relief keeps the schedule
+        assert_eq!(resolve_restructured_due_date(&loan, &proposal(None, None, Some(8)), now), Ok(None));
+
+        // Empty proposals, shortened schedules, oversized extensions and rate increases are rejected
+        assert!(resolve_restructured_due_date(&loan, &proposal(None, None, None), now).is_err());
+        assert!(resolve_restructured_due_date(&loan, &proposal(Some(due_date - day), None, None), now).is_err());
+        assert!(resolve_restructured_due_date(&loan, &proposal(Some(due_date + 181 * day), None, None), now).is_err());
+        assert!(resolve_restructured_due_date(&loan, &proposal(None, None, Some(12)), now).is_err());
+    }
+
+    #[test]
+    fn test_compute_max_borrowable_reports_limiting_factor() {
+        // Plenty of liquidity: collateral LTV binds
+        assert_eq!(
+            compute_max_borrowable(6_000_000, 0, 80_000_000, 100_000_000),
+            (6_000_000, BorrowLimitFactor::LtvBound)
+        );
+
+        // Existing exposure on the NFT eats into the LTV room
+        assert_eq!(
+            compute_max_borrowable(6_000_000, 2_000_000, 80_000_000, 100_000_000),
+            (4_000_000, BorrowLimitFactor::LtvBound)
+        );
+
+        // Small pool: the single-loan cap binds
+        assert_eq!(
+            compute_max_borrowable(6_000_000, 0, 4_000_000, 5_000_000),
+            (4_000_000, BorrowLimitFactor::PoolSingleLoanCap)
+        );
+
+        // Most liquidity lent out: available liquidity binds
+        assert_eq!(
+            compute_max_borrowable(6_000_000, 0, 8_000_000, 1_000_000),
+            (1_000_000, BorrowLimitFactor::PoolAvailableLiquidity)
+        );
+    }
+
     // Integration test to verify the complete loan lifecycle
     #[test]
     fn test_loan_data_structures() {
@@ -215,6 +354,281 @@ mod tests {
         
         println!("Loan lifecycle data structures test completed ✓");
     }
+
+    #[test]
+    fn test_underwriting_transitions() {
+        use UnderwritingState::*;
+
+        assert!(validate_underwriting_transition(&Submitted, &UnderReview).is_ok());
+        assert!(validate_underwriting_transition(&UnderReview, &NeedsInfo).is_ok());
+        assert!(validate_underwriting_transition(&NeedsInfo, &UnderReview).is_ok());
+        assert!(validate_underwriting_transition(&UnderReview, &Approved).is_ok());
+        assert!(validate_underwriting_transition(&UnderReview, &Rejected).is_ok());
+
+        // NeedsInfo must be resubmitted before a decision
+        assert!(validate_underwriting_transition(&NeedsInfo, &Approved).is_err());
+        assert!(validate_underwriting_transition(&Submitted, &Approved).is_err());
+        // Decisions are final
+        assert!(validate_underwriting_transition(&Rejected, &UnderReview).is_err());
+        assert!(validate_underwriting_transition(&Approved, &NeedsInfo).is_err());
+    }
+
+    #[test]
+    fn test_refinance_validation() {
+        assert!(validate_refinance(&LoanStatus::Active, 5_000_000, 6_000_000, 180, 365).is_ok());
+        // Payoff must still fit the collateral's LTV limit at current prices
+        assert!(validate_refinance(&LoanStatus::Active, 6_000_001, 6_000_000, 180, 365).is_err());
+        assert!(validate_refinance(&LoanStatus::Repaid, 5_000_000, 6_000_000, 180, 365).is_err());
+        assert!(validate_refinance(&LoanStatus::Active, 0, 6_000_000, 180, 365).is_err());
+        assert!(validate_refinance(&LoanStatus::Active, 5_000_000, 6_000_000, 7, 365).is_err());
+        assert!(validate_refinance(&LoanStatus::Active, 5_000_000, 6_000_000, 400, 365).is_err());
+    }
+
+    #[test]
+    fn test_application_expiry_only_applies_to_undecided_applications() {
+        let mut review = LoanApplicationReview {
+            loan_id: 1,
+            borrower: Principal::anonymous(),
+            state: UnderwritingState::UnderReview,
+            underwriter: None,
+            thread: Vec::new(),
+            submitted_at: 0,
+            updated_at: 0,
+            expires_at: Some(1_000),
+        };
+
+        assert!(!is_application_expirable(&LoanStatus::PendingApproval, &review, 999));
+        assert!(is_application_expirable(&LoanStatus::PendingApproval, &review, 1_000));
+        // Loans already past the application stage never expire
+        assert!(!is_application_expirable(&LoanStatus::Approved, &review, 2_000));
+        assert!(!is_application_expirable(&LoanStatus::Active, &review, 2_000));
+
+        review.state = UnderwritingState::Approved;
+        assert!(!is_application_expirable(&LoanStatus::PendingApproval, &review, 2_000));
+        assert!(validate_underwriting_transition(&UnderwritingState::Approved, &UnderwritingState::Expired).is_err());
+        assert!(validate_underwriting_transition(&UnderwritingState::Approved, &UnderwritingState::Withdrawn).is_ok());
+
+        // Applications from before expiry existed stay open
+        review.state = UnderwritingState::Submitted;
+        review.expires_at = None;
+        assert!(!is_application_expirable(&LoanStatus::PendingApproval, &review, u64::MAX));
+    }
+
+    #[test]
+    fn test_commodity_share_after_bps() {
+        let exposures = vec![
+            ("coffee".to_string(), 60_000_000),
+            ("rice".to_string(), 40_000_000),
+        ];
+
+        // 60M + 100M of 200M total
+        assert_eq!(commodity_share_after_bps(&exposures, "Coffee", 100_000_000), 8_000);
+        // 40M + 0 of 100M total
+        assert_eq!(commodity_share_after_bps(&exposures, "rice", 0), 4_000);
+        // A new commodity only holds its own collateral
+        assert_eq!(commodity_share_after_bps(&exposures, "corn", 100_000_000), 5_000);
+        // First loan in an empty book is the whole book
+        assert_eq!(commodity_share_after_bps(&[], "corn", 1), 10_000);
+        assert_eq!(commodity_share_after_bps(&[], "corn", 0), 0);
+    }
+
+    #[test]
+    fn test_exposure_cap_skips_empty_book() {
+        // The first loan would be 100% of the book, so no cap can bind yet
+        assert!(!exposure_cap_applies(&[]));
+        assert!(!exposure_cap_applies(&[("coffee".to_string(), 0)]));
+        assert!(exposure_cap_applies(&[("coffee".to_string(), 1)]));
+    }
+
+    #[test]
+    fn test_state_version_bumps_on_loan_mutation() {
+        let loans_before = get_state_version_counter(StateDomain::Loans);
+        let oracle_before = get_state_version_counter(StateDomain::Oracle);
+
+        let loan = Loan {
+            id: 9_001,
+            borrower: Principal::from_slice(&[2u8; 29]),
+            nft_id: 9_001,
+            collateral_value_btc: 25_000_000,
+            amount_requested: 15_000_000,
+            amount_approved: 15_000_000,
+            apr: 10,
+            status: LoanStatus::PendingApproval,
+            created_at: 0,
+            due_date: None,
+            total_repaid: 0,
+            repayment_history: vec![],
+            last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
+        };
+        store_loan(loan).unwrap();
+        update_loan_status(9_001, LoanStatus::Approved).unwrap();
+        assert!(update_loan_status(9_999_999, LoanStatus::Approved).is_err());
+
+        // Only successful writes bump, and only the touched domain moves
+        assert_eq!(get_state_version_counter(StateDomain::Loans), loans_before + 2);
+        assert_eq!(get_state_version_counter(StateDomain::Oracle), oracle_before);
+
+        // Status updates land in the loan event log like any other store_loan write
+        let events = get_loan_events_for_loan(9_001);
+        assert_eq!(events.last().map(|e| e.event_type.clone()), Some(LoanEventType::Approved));
+    }
+
+    #[test]
+    fn test_regional_exposure_report() {
+        let loan = |id: u64, region: Option<&str>, status: LoanStatus, amount: u64, repaid: u64| Loan {
+            id,
+            borrower: Principal::from_slice(&[2u8; 29]),
+            nft_id: 10_000 + id,
+            collateral_value_btc: amount * 2,
+            amount_requested: amount,
+            amount_approved: amount,
+            apr: 10,
+            status,
+            created_at: 0,
+            due_date: None,
+            total_repaid: repaid,
+            repayment_history: vec![],
+            last_payment_date: None,
+            region: region.map(|r| r.to_string()),
+            rate_type: None,
+            asset: None,
+        };
+        let loans = vec![
+            loan(1, Some("west_java"), LoanStatus::Active, 60_000_000, 20_000_000),
+            loan(2, Some("west_java"), LoanStatus::Defaulted, 10_000_000, 0),
+            loan(3, Some("lampung"), LoanStatus::Approved, 40_000_000, 0),
+            loan(4, None, LoanStatus::Repaid, 5_000_000, 5_000_000),
+        ];
+
+        let report = build_regional_exposure(&loans);
+        let find = |region: &str| report.iter().find(|r| r.region == region).unwrap().clone();
+
+        let west_java = find("west_java");
+        assert_eq!(west_java.outstanding_amount, 40_000_000);
+        assert_eq!(west_java.loan_count, 1);
+        assert_eq!(west_java.default_rate, 50.0);
+        assert_eq!(west_java.average_health_ratio, 2.0);
+        assert_eq!(west_java.share_bps, 5_000);
+
+        let lampung = find("lampung");
+        assert_eq!(lampung.outstanding_amount, 40_000_000);
+        assert_eq!(lampung.default_rate, 0.0);
+
+        // Fully repaid loans without a region still show up as funded history
+        let unknown = find("unknown");
+        assert_eq!(unknown.outstanding_amount, 0);
+        assert_eq!(unknown.share_bps, 0);
+
+        let metadata = vec![("rwa:region".to_string(), MetadataValue::Text(" West_Java ".to_string()))];
+        assert_eq!(get_region_from_metadata(&metadata), Some("west_java".to_string()));
+        assert_eq!(get_region_from_metadata(&vec![]), None);
+    }
+
+    #[test]
+    fn test_memory_breakdown_grows_with_records() {
+        let before = get_memory_breakdown();
+
+        for id in 0..2_000u64 {
+            let loan = Loan {
+                id: 1_000_000 + id,
+                borrower: Principal::from_slice(&[3u8; 29]),
+                nft_id: id,
+                collateral_value_btc: 25_000_000,
+                amount_requested: 15_000_000,
+                amount_approved: 15_000_000,
+                apr: 10,
+                status: LoanStatus::Active,
+                created_at: 32_000_000_000_000_000_u64,
+                due_date: None,
+                total_repaid: 0,
+                repayment_history: vec![],
+                last_payment_date: None,
+                region: None,
+                rate_type: None,
+                asset: None,
+            };
+            store_loan(loan).unwrap();
+        }
+
+        let after = get_memory_breakdown();
+        assert!(after.loans_bytes > before.loans_bytes);
+        assert!(after.total_bytes > before.total_bytes);
+        assert!(after.total_bytes >= after.loans_bytes + after.nfts_bytes + after.audit_logs_bytes);
+    }
+
+    #[test]
+    fn test_batch_loan_lookup_order_and_authorization() {
+        use crate::dashboard_support::select_viewable_loans;
+
+        let owner = Principal::from_slice(&[2u8; 29]);
+        let other = Principal::from_slice(&[3u8; 29]);
+        let loan = |id: u64, borrower: Principal| Loan {
+            id,
+            borrower,
+            nft_id: id,
+            collateral_value_btc: 25_000_000,
+            amount_requested: 15_000_000,
+            amount_approved: 15_000_000,
+            apr: 10,
+            status: LoanStatus::Active,
+            created_at: 0,
+            due_date: None,
+            total_repaid: 0,
+            repayment_history: vec![],
+            last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
+        };
+        let book = vec![loan(1, owner), loan(2, other), loan(3, owner)];
+        let lookup = |id: u64| book.iter().find(|l| l.id == id).cloned();
+
+        // Input order is preserved, unknown IDs and other borrowers' loans are None
+        let ids = vec![3, 2, 42, 1];
+        let visible: Vec<Option<u64>> = select_viewable_loans(&ids, lookup, owner, false)
+            .iter()
+            .map(|l| l.as_ref().map(|l| l.id))
+            .collect();
+        assert_eq!(visible, vec![Some(3), None, None, Some(1)]);
+
+        // Admins see every existing loan
+        let visible: Vec<Option<u64>> = select_viewable_loans(&ids, lookup, other, true)
+            .iter()
+            .map(|l| l.as_ref().map(|l| l.id))
+            .collect();
+        assert_eq!(visible, vec![Some(3), Some(2), None, Some(1)]);
+    }
+
+    #[test]
+    fn test_collateral_nft_summary_reads_metadata() {
+        let nft = RWANFTData {
+            token_id: 7,
+            owner: Principal::from_slice(&[1u8; 29]),
+            metadata: vec![
+                ("rwa:legal_doc_hash".to_string(), MetadataValue::Text("abc123".to_string())),
+                ("rwa:valuation_idr".to_string(), MetadataValue::Nat(250_000_000)),
+                ("rwa:asset_description".to_string(), MetadataValue::Text("Corn warehouse receipt".to_string())),
+            ],
+            created_at: 0,
+            updated_at: 0,
+            is_locked: true,
+            loan_id: Some(3),
+        };
+
+        let summary = collateral_nft_summary(&nft);
+        assert_eq!(summary.token_id, 7);
+        assert_eq!(summary.valuation_idr, Idr(250_000_000));
+        assert_eq!(summary.asset_description, "Corn warehouse receipt");
+        assert_eq!(summary.legal_doc_hash, "abc123");
+        assert_eq!(summary.locked_for_loan, Some(3));
+
+        // Missing metadata falls back to empty values rather than failing the detail view
+        let bare = RWANFTData { metadata: vec![], ..nft };
+        assert_eq!(collateral_nft_summary(&bare).valuation_idr, Idr(0));
+    }
 }
 
 // Integration test functions (for manual testing)
@@ -22,6 +22,9 @@ mod tests {
             total_repaid: 0,
             repayment_history: Vec::new(),
             last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
         }
     }
 
@@ -55,6 +58,8 @@ mod tests {
             timestamp: 1_000_000_000_000_000_000u64,
             payment_type: PaymentType::Mixed,
             transaction_id: Some("test_tx_123".to_string()),
+            allocation: None,
+            source: None,
         };
 
         assert_eq!(payment.amount, 1_000_000);
@@ -75,6 +80,9 @@ mod tests {
             next_payment_due: Some(1_000_000_000_000_000_000u64 + (30 * 24 * 60 * 60 * 1_000_000_000)),
             is_overdue: false,
             days_overdue: 0,
+            current_apr: 10,
+            is_variable_rate: false,
+            upcoming_rate_resets: vec![],
         };
 
         assert_eq!(summary.loan_id, 1);
@@ -93,6 +101,7 @@ mod tests {
             protocol_fee: 150_000, // 10% of interest
             due_date: 1_000_000_000_000_000_000u64 + (365 * 24 * 60 * 60 * 1_000_000_000),
             minimum_payment: 1000,
+            network_fee_charged: None,
         };
 
         assert_eq!(plan.loan_id, 1);
@@ -170,6 +179,8 @@ pub fn create_test_payment(amount: u64, payment_type: PaymentType) -> Payment {
         timestamp: 1_000_000_000_000_000_000u64,
         payment_type,
         transaction_id: Some(format!("test_tx_{}", amount)),
+        allocation: None,
+        source: None,
     }
 }
 
@@ -186,5 +197,6 @@ pub fn create_test_repayment_record(loan_id: u64, amount: u64) -> RepaymentRecor
             protocol_fee_amount: amount * 2 / 100,
             total_amount: amount,
         },
+        source: None,
     }
 }
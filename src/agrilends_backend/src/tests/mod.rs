@@ -42,6 +42,10 @@ mod basic_tests {
             email: None,
             phone: None,
             profile_completed: false,
+            preferred_language: None,
+            kyc_tier: None,
+            kyc_reference: None,
+            kyc_updated_at: None,
         };
         
         assert_eq!(user.id, principal);
@@ -79,6 +83,10 @@ mod basic_tests {
             email: None,
             phone: None,
             profile_completed: false,
+            preferred_language: None,
+            kyc_tier: None,
+            kyc_reference: None,
+            kyc_updated_at: None,
         };
         
         let success_result = UserResult::Ok(user);
@@ -439,6 +447,699 @@ mod user_management_integration_tests {
 }
 */
 
+#[cfg(test)]
+mod dependency_health_tests {
+    use crate::helpers::{calculate_p95_latency, classify_dependency_status, DependencyCallStats};
+    use crate::types::DependencyStatus;
+    
+    #[test]
+    fn test_p95_latency() {
+        assert_eq!(calculate_p95_latency(&[]), 0);
+        assert_eq!(calculate_p95_latency(&[120]), 120);
+        
+        let latencies: Vec<u64> = (1..=100).collect();
+        assert_eq!(calculate_p95_latency(&latencies), 95);
+    }
+    
+    #[test]
+    fn test_dependency_status_classification() {
+        let mut stats = DependencyCallStats {
+            last_success: Some(1),
+            recent_latencies_ms: vec![200; 20],
+            consecutive_failures: 0,
+        };
+        assert_eq!(classify_dependency_status(&stats, 1_000), DependencyStatus::Up);
+        
+        // Slow but successful calls are degraded, not down
+        stats.recent_latencies_ms = vec![2_000; 20];
+        assert_eq!(classify_dependency_status(&stats, 1_000), DependencyStatus::Degraded);
+        
+        stats.recent_latencies_ms = vec![200; 20];
+        stats.consecutive_failures = 1;
+        assert_eq!(classify_dependency_status(&stats, 1_000), DependencyStatus::Degraded);
+        
+        stats.consecutive_failures = 3;
+        assert_eq!(classify_dependency_status(&stats, 1_000), DependencyStatus::Down);
+    }
+}
+
+#[cfg(test)]
+mod oracle_twap_tests {
+    use crate::oracle::calculate_twap;
+    use crate::types::PriceSample;
+    
+    const SECOND: u64 = 1_000_000_000;
+    
+    fn sample(price: u64, at_seconds: u64) -> PriceSample {
+        PriceSample { price_per_unit: price, timestamp: at_seconds * SECOND }
+    }
+    
+    #[test]
+    fn test_twap_weights_by_duration() {
+        // 100 for 30s, 200 for 10s over a 40s window
+        let samples = vec![sample(100, 0), sample(200, 30)];
+        assert_eq!(calculate_twap(&samples, 0, 40 * SECOND), Some((125, true)));
+    }
+    
+    #[test]
+    fn test_twap_resists_single_spike() {
+        // A one-second manipulated print barely moves the hour average
+        let samples = vec![sample(10_000, 0), sample(1_000_000, 3_599)];
+        let (twap, covers) = calculate_twap(&samples, 0, 3_600 * SECOND).unwrap();
+        assert!(covers);
+        assert!(twap < 11_000);
+    }
+    
+    #[test]
+    fn test_twap_clips_to_window_and_flags_short_history() {
+        // Sample before the window start still holds into the window
+        let samples = vec![sample(100, 0), sample(300, 50)];
+        assert_eq!(calculate_twap(&samples, 40 * SECOND, 60 * SECOND), Some((200, true)));
+        
+        // History starting inside the window does not cover it
+        let samples = vec![sample(100, 50)];
+        assert_eq!(calculate_twap(&samples, 40 * SECOND, 60 * SECOND), Some((100, false)));
+        
+        assert_eq!(calculate_twap(&[], 0, 60 * SECOND), None);
+    }
+}
+
+#[cfg(test)]
+mod oracle_price_band_tests {
+    use crate::oracle::{check_price_band, price_deviation_bps};
+    
+    const HOUR: u64 = 3_600 * 1_000_000_000;
+    
+    #[test]
+    fn test_deviation_is_symmetric_in_direction() {
+        assert_eq!(price_deviation_bps(10_000, 12_000), 2_000);
+        assert_eq!(price_deviation_bps(12_000, 10_000), 2_000);
+        assert_eq!(price_deviation_bps(10_000, 8_000), 2_500);
+        assert_eq!(price_deviation_bps(8_000, 10_000), 2_500);
+        assert_eq!(price_deviation_bps(10_000, 10_000), 0);
+    }
+    
+    #[test]
+    fn test_band_lets_a_price_return() {
+        // Any accepted move can be reversed by the next write
+        for (from, to) in [(10_000, 12_000), (10_000, 8_334), (10_000, 11_000)] {
+            assert_eq!(check_price_band(Some((from, 0)), None, to, 1, 2_000, HOUR), Ok(()));
+            assert_eq!(check_price_band(Some((to, 1)), None, from, 2, 2_000, HOUR), Ok(()));
+        }
+        assert!(check_price_band(Some((10_000, 0)), None, 8_000, 1, 2_000, HOUR).is_err());
+    }
+    
+    #[test]
+    fn test_band_rejects_large_move_inside_window() {
+        let last = Some((10_000, 0));
+        assert_eq!(check_price_band(last, None, 12_000, HOUR / 2, 2_000, HOUR), Ok(()));
+        assert_eq!(check_price_band(last, None, 12_001, HOUR / 2, 2_000, HOUR), Err(2_001));
+        // Once the last price is older than the window any move is accepted
+        assert_eq!(check_price_band(last, None, 50_000, HOUR, 2_000, HOUR), Ok(()));
+        // Nothing to compare against on the first write
+        assert_eq!(check_price_band(None, None, 50_000, 0, 2_000, HOUR), Ok(()));
+    }
+    
+    #[test]
+    fn test_move_confirmed_by_another_source_is_accepted() {
+        let last = Some((10_000, 0));
+        assert_eq!(check_price_band(last, Some(15_000), 15_500, HOUR / 2, 2_000, HOUR), Ok(()));
+        // A held price far from the new one does not confirm it
+        assert_eq!(check_price_band(last, Some(30_000), 15_500, HOUR / 2, 2_000, HOUR), Err(5_500));
+    }
+}
+
+#[cfg(test)]
+mod liquidity_reservation_tests {
+    use crate::liquidity_management::{apply_liquidity_reservation, release_reserved_liquidity};
+    use crate::types::LiquidityPool;
+    
+    fn pool(available_liquidity: u64) -> LiquidityPool {
+        LiquidityPool {
+            total_liquidity: available_liquidity,
+            available_liquidity,
+            total_borrowed: 0,
+            total_repaid: 0,
+            utilization_rate: 0,
+            total_investors: 1,
+            apy: 0,
+            created_at: 0,
+            updated_at: 0,
+            reserved_liquidity: None,
+        }
+    }
+    
+    #[test]
+    fn test_second_approval_cannot_claim_reserved_funds() {
+        let mut pool = pool(10_000_000);
+        apply_liquidity_reservation(&mut pool, 7_000_000).unwrap();
+        assert_eq!(pool.unreserved_liquidity(), 3_000_000);
+        assert!(apply_liquidity_reservation(&mut pool, 5_000_000).is_err());
+        assert_eq!(pool.reserved(), 7_000_000);
+        
+        release_reserved_liquidity(&mut pool, 7_000_000);
+        assert_eq!(pool.reserved(), 0);
+        assert!(apply_liquidity_reservation(&mut pool, 5_000_000).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod origination_pause_tests {
+    use crate::liquidity_management::origination_block_reason;
+    
+    #[test]
+    fn test_full_pause_supersedes_origination_pause() {
+        assert!(origination_block_reason(false, false).is_none());
+        assert_eq!(
+            origination_block_reason(false, true).as_deref(),
+            Some("New loan originations are currently paused")
+        );
+        assert_eq!(
+            origination_block_reason(true, true).as_deref(),
+            Some("Pool operations are currently paused")
+        );
+        assert_eq!(
+            origination_block_reason(true, false).as_deref(),
+            Some("Pool operations are currently paused")
+        );
+    }
+}
+
+#[cfg(test)]
+mod reserve_requirement_tests {
+    use crate::liquidity_management::compute_required_reserve;
+    use crate::types::CanisterConfig;
+    
+    const BTC: u64 = 100_000_000;
+    
+    #[test]
+    fn test_flat_ratio_without_tiers() {
+        assert_eq!(compute_required_reserve(10 * BTC, 500, 0, None, None), BTC / 2);
+        assert_eq!(compute_required_reserve(0, 500, 0, None, None), 0);
+    }
+    
+    #[test]
+    fn test_default_reserve_is_five_percent() {
+        let config = CanisterConfig::default();
+        assert_eq!(config.pool_reserve_ratio_bps(), 500);
+        assert_eq!(compute_required_reserve(10 * BTC, config.pool_reserve_ratio_bps(), 0, None, None), BTC / 2);
+    }
+    
+    #[test]
+    fn test_small_pools_keep_absolute_floor_up_to_half_the_pool() {
+        // 5% of 0.5 BTC is 0.025 BTC, below the 0.1 BTC floor
+        assert_eq!(compute_required_reserve(BTC / 2, 500, BTC / 10, None, None), BTC / 10);
+        // A pool smaller than twice the floor reserves half of itself
+        assert_eq!(compute_required_reserve(BTC / 10, 500, BTC / 10, None, None), BTC / 20);
+    }
+    
+    #[test]
+    fn test_large_pools_taper_above_threshold() {
+        // 5% on the first 10 BTC, 2% on the remaining 40 BTC
+        let tapered = compute_required_reserve(50 * BTC, 500, BTC / 10, Some(10 * BTC), Some(200));
+        assert_eq!(tapered, 130_000_000);
+        assert!(tapered < compute_required_reserve(50 * BTC, 500, BTC / 10, None, None));
+        
+        // Below the threshold the base ratio applies unchanged
+        assert_eq!(compute_required_reserve(5 * BTC, 500, 0, Some(10 * BTC), Some(200)), 25_000_000);
+    }
+}
+
+#[cfg(test)]
+mod min_collateral_value_tests {
+    use crate::helpers::check_min_collateral_value;
+    use crate::types::CollateralValueError;
+    
+    #[test]
+    fn test_floor_boundary() {
+        assert!(check_min_collateral_value(1_000_000, 1_000_000).is_ok());
+        assert!(check_min_collateral_value(1_000_001, 1_000_000).is_ok());
+        assert_eq!(
+            check_min_collateral_value(999_999, 1_000_000),
+            Err(CollateralValueError::BelowMinimum { valuation_idr: 999_999, min_collateral_value_idr: 1_000_000 })
+        );
+        // A zero floor disables the check
+        assert!(check_min_collateral_value(0, 0).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod position_offer_tests {
+    use crate::liquidity_management::{
+        position_settlement_stale, validate_position_offer, MAX_OPEN_POSITION_OFFERS, POSITION_SETTLEMENT_TIMEOUT_NANOS,
+    };
+    use crate::types::{PositionOffer, PositionOfferStatus};
+    use candid::Principal;
+    
+    #[test]
+    fn test_offer_limited_to_uncommitted_balance() {
+        assert!(validate_position_offer(1_000_000, 0, 0, 1_000_000, 990_000).is_ok());
+        // 600k of the balance is already on offer
+        assert!(validate_position_offer(1_000_000, 600_000, 1, 400_000, 400_000).is_ok());
+        assert!(validate_position_offer(1_000_000, 600_000, 1, 400_001, 400_000).is_err());
+    }
+    
+    #[test]
+    fn test_offer_cap_and_inputs() {
+        assert!(validate_position_offer(1_000_000, 0, MAX_OPEN_POSITION_OFFERS, 10_000, 10_000).is_err());
+        assert!(validate_position_offer(1_000_000, 0, 0, 10_000, 0).is_err());
+        // Offers below the minimum withdrawal amount would leave unsellable dust
+        assert!(validate_position_offer(1_000_000, 0, 0, 999, 999).is_err());
+    }
+    
+    #[test]
+    fn test_settling_offer_times_out() {
+        let mut offer = PositionOffer {
+            id: 1,
+            seller: Principal::from_slice(&[71u8; 29]),
+            buyer: None,
+            amount: 100_000,
+            price: 100_000,
+            status: PositionOfferStatus::Settling,
+            created_at: 0,
+            accepted_by: Some(Principal::from_slice(&[72u8; 29])),
+            settlement_block_index: None,
+            settled_at: None,
+            payment_created_at: Some(1_000),
+        };
+        assert!(!position_settlement_stale(&offer, 1_000 + POSITION_SETTLEMENT_TIMEOUT_NANOS - 1));
+        assert!(position_settlement_stale(&offer, 1_000 + POSITION_SETTLEMENT_TIMEOUT_NANOS));
+        
+        // Only settling offers are swept
+        offer.status = PositionOfferStatus::Open;
+        assert!(!position_settlement_stale(&offer, u64::MAX));
+    }
+}
+
+#[cfg(test)]
+mod operational_tag_tests {
+    use crate::operational_tags::{apply_tag, normalize_tag, MAX_TAGS_PER_ENTITY};
+    use crate::types::{EntityTags, TaggedEntityType};
+    use candid::Principal;
+    
+    #[test]
+    fn test_tags_are_normalized_deduplicated_and_capped() {
+        assert_eq!(normalize_tag("  Flagged for Review ").unwrap(), "flagged for review");
+        assert!(normalize_tag("").is_err());
+        assert!(normalize_tag("vip;drop").is_err());
+        
+        let operator = Principal::anonymous();
+        let mut entity_tags = EntityTags {
+            entity_type: TaggedEntityType::Loan,
+            entity_id: "7".to_string(),
+            tags: Vec::new(),
+        };
+        assert!(apply_tag(&mut entity_tags, "vip".to_string(), operator, 1).unwrap());
+        assert!(!apply_tag(&mut entity_tags, "vip".to_string(), operator, 2).unwrap());
+        assert_eq!(entity_tags.tags.len(), 1);
+        
+        for i in 1..MAX_TAGS_PER_ENTITY {
+            apply_tag(&mut entity_tags, format!("tag-{}", i), operator, 3).unwrap();
+        }
+        assert!(apply_tag(&mut entity_tags, "disputed".to_string(), operator, 4).is_err());
+    }
+}
+
+#[cfg(test)]
+mod loan_event_tests {
+    use crate::types::{LoanEventType, LoanStatus};
+    
+    #[test]
+    fn test_loan_event_type_follows_new_status() {
+        assert_eq!(LoanEventType::from_status(&LoanStatus::PendingApproval, true), LoanEventType::Created);
+        assert_eq!(LoanEventType::from_status(&LoanStatus::Approved, false), LoanEventType::Approved);
+        assert_eq!(LoanEventType::from_status(&LoanStatus::Active, false), LoanEventType::Disbursed);
+        assert_eq!(LoanEventType::from_status(&LoanStatus::Defaulted, false), LoanEventType::Defaulted);
+    }
+}
+
+#[cfg(test)]
+mod proposal_action_tests {
+    use crate::governance::validate_proposal_action;
+    use crate::types::{ProposalAction, ProposalType};
+    use candid::Principal;
+    
+    #[test]
+    fn test_actions_map_to_proposal_types_and_distinct_audit_actions() {
+        let actions = vec![
+            ProposalAction::SetParameter { key: "base_interest_rate".to_string(), value: 1000 },
+            ProposalAction::TreasurySpend { amount: 1, destination: Principal::management_canister() },
+            ProposalAction::GrantRole { principal: Principal::management_canister(), role: crate::types::AdminRoleType::OracleAdmin },
+            ProposalAction::Signal,
+        ];
+        
+        let mut audit_actions: Vec<&str> = actions.iter().map(|action| action.audit_action()).collect();
+        audit_actions.sort();
+        audit_actions.dedup();
+        assert_eq!(audit_actions.len(), actions.len());
+        
+        assert!(matches!(actions[1].proposal_type(), ProposalType::TreasuryManagement));
+        assert!(matches!(actions[3].proposal_type(), ProposalType::Signaling));
+    }
+    
+    #[test]
+    fn test_action_preconditions() {
+        assert!(validate_proposal_action(&ProposalAction::GrantRole {
+            principal: Principal::anonymous(),
+            role: crate::types::AdminRoleType::OracleAdmin,
+        }).is_err());
+        assert!(validate_proposal_action(&ProposalAction::Signal).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod price_alert_subscription_tests {
+    use crate::oracle::evaluate_price_alert_subscription;
+    use crate::types::{PriceAlertSubscription, PriceThresholdType};
+    use candid::Principal;
+    
+    fn subscription(threshold_type: PriceThresholdType, recurring: bool) -> PriceAlertSubscription {
+        PriceAlertSubscription {
+            id: 1,
+            subscriber: Principal::anonymous(),
+            commodity_id: "rice".to_string(),
+            threshold_type,
+            recurring,
+            is_active: true,
+            is_crossed: false,
+            reference_price: Some(10_000),
+            last_observed_price: None,
+            created_at: 0,
+            last_triggered_at: None,
+            trigger_count: 0,
+        }
+    }
+    
+    #[test]
+    fn test_alert_does_not_refire_while_crossed() {
+        let mut alert = subscription(PriceThresholdType::Above(12_000), true);
+        assert_eq!(evaluate_price_alert_subscription(&mut alert, 11_000, 1), None);
+        assert_eq!(evaluate_price_alert_subscription(&mut alert, 12_500, 2), Some(11_000));
+        assert_eq!(evaluate_price_alert_subscription(&mut alert, 13_000, 3), None);
+        assert_eq!(alert.trigger_count, 1);
+        
+        // Recurring alerts re-arm after the price falls back
+        assert_eq!(evaluate_price_alert_subscription(&mut alert, 11_500, 4), None);
+        assert_eq!(evaluate_price_alert_subscription(&mut alert, 12_100, 5), Some(11_500));
+        assert_eq!(alert.trigger_count, 2);
+        assert!(alert.is_active);
+    }
+    
+    #[test]
+    fn test_one_shot_alert_deactivates() {
+        let mut alert = subscription(PriceThresholdType::Below(9_000), false);
+        assert_eq!(evaluate_price_alert_subscription(&mut alert, 8_500, 1), Some(10_000));
+        assert!(!alert.is_active);
+        assert_eq!(evaluate_price_alert_subscription(&mut alert, 9_500, 2), None);
+        assert_eq!(evaluate_price_alert_subscription(&mut alert, 8_000, 3), None);
+        assert_eq!(alert.trigger_count, 1);
+    }
+    
+    #[test]
+    fn test_change_alert_measures_from_reference() {
+        // 5% move from the 10_000 reference
+        let mut alert = subscription(PriceThresholdType::Change(500), true);
+        assert_eq!(evaluate_price_alert_subscription(&mut alert, 10_400, 1), None);
+        assert_eq!(evaluate_price_alert_subscription(&mut alert, 9_400, 2), Some(10_000));
+        assert_eq!(alert.reference_price, Some(9_400));
+        
+        // Holding at the new level does not fire again
+        assert_eq!(evaluate_price_alert_subscription(&mut alert, 9_400, 3), None);
+        assert_eq!(alert.trigger_count, 1);
+    }
+}
+
+#[cfg(test)]
+mod outbound_event_tests {
+    use crate::outbound_events::{hmac_sha256_hex, build_liquidation_payload};
+    use candid::Principal;
+    
+    #[test]
+    fn test_hmac_sha256_matches_rfc4231() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hmac_sha256_hex(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // Keys longer than the block size are hashed first (test case 6)
+        assert_eq!(
+            hmac_sha256_hex(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First"),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+    
+    #[test]
+    fn test_liquidation_payload_fields() {
+        let borrower = Principal::from_slice(&[7u8; 29]);
+        let payload = build_liquidation_payload(42, &borrower, 50_000_000, 31_000_000, 1_700_000_000_000_000_000);
+        let parsed: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        
+        assert_eq!(parsed["event"], "loan.liquidated");
+        assert_eq!(parsed["loan_id"], 42);
+        assert_eq!(parsed["borrower"], borrower.to_text());
+        assert_eq!(parsed["collateral_value"], 50_000_000);
+        assert_eq!(parsed["debt"], 31_000_000);
+        assert_eq!(parsed["timestamp"], 1_700_000_000_000_000_000u64);
+    }
+}
+
+#[cfg(test)]
+mod maintenance_window_tests {
+    use crate::helpers::validate_maintenance_operations;
+    use crate::types::{MaintenanceWindow, MaintenanceWindowStatus};
+    use candid::Principal;
+    
+    fn window(affected_operations: Vec<&str>, status: MaintenanceWindowStatus) -> MaintenanceWindow {
+        MaintenanceWindow {
+            id: 1,
+            start: 1_000,
+            end: 2_000,
+            affected_operations: affected_operations.into_iter().map(String::from).collect(),
+            message: "Ledger upgrade".to_string(),
+            status,
+            scheduled_by: Principal::anonymous(),
+            scheduled_at: 0,
+        }
+    }
+    
+    #[test]
+    fn test_scoped_window_blocks_only_listed_operations() {
+        let scoped = window(vec!["deposit_liquidity"], MaintenanceWindowStatus::Scheduled);
+        
+        assert!(scoped.blocks_operation("deposit_liquidity", 1_500));
+        assert!(!scoped.blocks_operation("repay_loan", 1_500));
+        // Outside the window, including the exclusive end
+        assert!(!scoped.blocks_operation("deposit_liquidity", 999));
+        assert!(!scoped.blocks_operation("deposit_liquidity", 2_000));
+    }
+    
+    #[test]
+    fn test_full_scope_and_cancelled_windows() {
+        let full = window(vec![], MaintenanceWindowStatus::Active);
+        assert!(full.is_full_scope());
+        assert!(full.blocks_operation("deposit_liquidity", 1_000));
+        
+        let cancelled = window(vec![], MaintenanceWindowStatus::Cancelled);
+        assert!(!cancelled.blocks_operation("deposit_liquidity", 1_500));
+    }
+    
+    #[test]
+    fn test_window_operations_must_be_known_and_pausable() {
+        let names = |ops: &[&str]| ops.iter().map(|op| op.to_string()).collect::<Vec<_>>();
+        assert!(validate_maintenance_operations(&names(&["deposit_liquidity", "withdraw_liquidity"])).is_ok());
+        assert!(validate_maintenance_operations(&[]).is_ok());
+        // Typos would otherwise schedule a window that pauses nothing
+        assert!(validate_maintenance_operations(&names(&["deposit_liquidty"])).unwrap_err().contains("Unknown operation"));
+        // Repayments are never paused
+        assert!(validate_maintenance_operations(&names(&["repay_loan"])).is_err());
+        assert!(validate_maintenance_operations(&names(&["withdraw_liquidity", "repay_loan_via_allowance"])).is_err());
+    }
+}
+
+#[cfg(test)]
+mod id_counter_tests {
+    use crate::storage::*;
+    use crate::types::{AuditLog, IdCounter};
+    use candid::Principal;
+    
+    fn insert_audit_log(id: u64) {
+        AUDIT_LOGS.with(|logs| {
+            logs.borrow_mut().insert(id, AuditLog {
+                timestamp: 0,
+                caller: Principal::anonymous(),
+                action: "TEST".to_string(),
+                details: String::new(),
+                success: true,
+            });
+        });
+    }
+    
+    #[test]
+    fn test_ids_are_not_reused_across_upgrade() {
+        let issued: Vec<u64> = (0..3).map(|_| next_audit_log_id()).collect();
+        issued.iter().for_each(|id| insert_audit_log(*id));
+        let max_issued = *issued.iter().max().unwrap();
+        
+        // Counters live in stable memory, so a clean upgrade needs no repair
+        assert!(repair_id_counters().iter().all(|r| r.counter != IdCounter::AuditLog));
+        assert!(next_audit_log_id() > max_issued);
+        
+        // Simulate an upgrade that lost the counter, as the old heap counters did
+        set_next_id(IdCounter::AuditLog, 1);
+        let repairs = repair_id_counters();
+        let repair = repairs.iter().find(|r| r.counter == IdCounter::AuditLog).unwrap();
+        assert_eq!(repair.previous_next_id, 1);
+        assert!(repair.repaired_next_id > repair.max_existing_id);
+        
+        let after_upgrade = next_audit_log_id();
+        assert!(after_upgrade > max_issued);
+        assert!(AUDIT_LOGS.with(|logs| logs.borrow().get(&after_upgrade).is_none()));
+    }
+    
+    #[test]
+    fn test_reconcile_leaves_healthy_counter_alone() {
+        set_next_id(IdCounter::Disbursement, 10);
+        assert!(reconcile_id_counter(IdCounter::Disbursement, Some(9)).is_none());
+        assert!(reconcile_id_counter(IdCounter::Disbursement, None).is_none());
+        
+        let repair = reconcile_id_counter(IdCounter::Disbursement, Some(10)).unwrap();
+        assert_eq!(repair.repaired_next_id, 11);
+        assert_eq!(next_disbursement_id(), 11);
+    }
+}
+
+#[cfg(test)]
+mod btc_denylist_tests {
+    use crate::production_security::{find_btc_deny_rule, normalize_btc_address};
+    use crate::liquidity_management::disbursement_destination_allowed;
+    use crate::types::{BtcAddressDenyRule, BtcDenyRuleKind};
+    use candid::Principal;
+    
+    fn rule(pattern: &str, kind: BtcDenyRuleKind) -> BtcAddressDenyRule {
+        BtcAddressDenyRule {
+            pattern: normalize_btc_address(pattern),
+            kind,
+            reason: "Sanctions list".to_string(),
+            added_by: Principal::anonymous(),
+            added_at: 0,
+        }
+    }
+    
+    #[test]
+    fn test_exact_and_prefix_rules() {
+        let rules = vec![
+            rule("1BoatSLRHtKNngkdXEeobR76b53LETtpyT", BtcDenyRuleKind::Exact),
+            rule("bc1qdenied", BtcDenyRuleKind::Prefix),
+        ];
+        
+        assert!(find_btc_deny_rule("1BoatSLRHtKNngkdXEeobR76b53LETtpyT", &rules).is_some());
+        // Base58 is case-sensitive, bech32 is not
+        assert!(find_btc_deny_rule("1boatslrhtknngkdxeeobr76b53lettpyt", &rules).is_none());
+        assert!(find_btc_deny_rule("BC1QDENIED0000000000000000000000000", &rules).is_some());
+        assert!(find_btc_deny_rule("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", &rules).is_none());
+    }
+    
+    #[test]
+    fn test_disbursement_must_match_registered_address() {
+        let registered = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+        let other = "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy";
+        
+        assert!(disbursement_destination_allowed(registered, Some(registered), None));
+        assert!(disbursement_destination_allowed(&registered.to_uppercase(), Some(registered), None));
+        assert!(!disbursement_destination_allowed(other, Some(registered), None));
+        assert!(!disbursement_destination_allowed(other, None, None));
+        // An admin override allows exactly the approved address
+        assert!(disbursement_destination_allowed(other, Some(registered), Some(other)));
+    }
+}
+
+#[cfg(test)]
+mod ckbtc_principal_tests {
+    use crate::helpers::{ckbtc_ledger_principal, ckbtc_minter_principal, MAINNET_CKBTC_LEDGER_PRINCIPAL, MAINNET_CKBTC_MINTER_PRINCIPAL};
+    use crate::production_config::is_production_mode;
+    use crate::storage::update_config;
+    use crate::types::CanisterConfig;
+    use candid::Principal;
+    
+    #[test]
+    fn test_principals_default_to_mainnet_and_follow_sandbox_config() {
+        update_config(CanisterConfig::default()).unwrap();
+        assert!(is_production_mode());
+        assert_eq!(ckbtc_ledger_principal().to_text(), MAINNET_CKBTC_LEDGER_PRINCIPAL);
+        assert_eq!(ckbtc_minter_principal().to_text(), MAINNET_CKBTC_MINTER_PRINCIPAL);
+        
+        let testnet_ledger = Principal::from_text("mc6ru-gyaaa-aaaar-qaaaq-cai").unwrap();
+        let testnet_minter = Principal::from_text("ml52i-qqaaa-aaaar-qaaba-cai").unwrap();
+        update_config(CanisterConfig {
+            sandbox_mode: Some(true),
+            ckbtc_ledger_principal: Some(testnet_ledger),
+            ckbtc_minter_principal: Some(testnet_minter),
+            ..CanisterConfig::default()
+        }).unwrap();
+        assert!(!is_production_mode());
+        assert_eq!(ckbtc_ledger_principal(), testnet_ledger);
+        assert_eq!(ckbtc_minter_principal(), testnet_minter);
+        
+        update_config(CanisterConfig::default()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod investor_cohort_tests {
+    use crate::advanced_analytics::{build_investor_cohorts, cohort_period, CohortGranularity};
+    use crate::types::InvestorBalance;
+    use candid::Principal;
+    
+    const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+    
+    fn investor(first_deposit_day: u64, last_activity_day: u64, deposited: u64, withdrawn: u64, balance: u64) -> InvestorBalance {
+        InvestorBalance {
+            investor: Principal::anonymous(),
+            balance,
+            total_deposited: deposited,
+            total_withdrawn: withdrawn,
+            deposits: vec![],
+            withdrawals: vec![],
+            first_deposit_at: first_deposit_day * DAY,
+            last_activity_at: last_activity_day * DAY,
+            total_fees_paid: None,
+        }
+    }
+    
+    #[test]
+    fn test_cohort_periods() {
+        assert_eq!(cohort_period(0, CohortGranularity::Month), ("1970-01".to_string(), 0));
+        assert_eq!(cohort_period(20_157 * DAY, CohortGranularity::Month), ("2025-03".to_string(), 20_148 * DAY));
+        assert_eq!(cohort_period(20_157 * DAY, CohortGranularity::Quarter), ("2025-Q1".to_string(), 20_089 * DAY));
+        assert_eq!(cohort_period(20_180 * DAY, CohortGranularity::Quarter), ("2025-Q2".to_string(), 20_179 * DAY));
+    }
+    
+    #[test]
+    fn test_cohorts_ordered_oldest_first_with_churn_and_returns() {
+        let now = 20_269 * DAY;
+        let balances = vec![
+            investor(20_180, 20_260, 1_000_000, 0, 1_050_000),      // Q2, active, +5%
+            investor(20_103, 20_110, 1_000_000, 1_020_000, 0),      // Q1, churned, +2%
+            investor(20_157, 20_200, 2_000_000, 0, 2_000_000),      // Q1, active, flat
+        ];
+        
+        let cohorts = build_investor_cohorts(&balances, CohortGranularity::Quarter, None, None, now);
+        assert_eq!(cohorts.len(), 2);
+        assert_eq!(cohorts[0].cohort_id, "2025-Q1");
+        assert_eq!(cohorts[0].investor_count, 2);
+        assert_eq!(cohorts[0].churned_count, 1);
+        assert_eq!(cohorts[0].total_invested_balance, 2_000_000);
+        assert_eq!(cohorts[0].average_net_return, 10_000);
+        assert_eq!(cohorts[0].average_net_return_bps, 100);
+        assert_eq!(cohorts[1].cohort_id, "2025-Q2");
+        assert_eq!(cohorts[1].average_net_return_bps, 500);
+        
+        // Date range filters on first deposit
+        let march_on = build_investor_cohorts(&balances, CohortGranularity::Month, Some(20_148 * DAY), None, now);
+        assert_eq!(march_on.iter().map(|c| c.cohort_id.as_str()).collect::<Vec<_>>(), vec!["2025-03", "2025-04"]);
+    }
+}
+
 #[cfg(test)]
 mod validation_tests {
     use crate::user_management::{validate_btc_address, validate_email, validate_phone};
@@ -486,4 +1187,2756 @@ mod validation_tests {
         assert!(!validate_phone("abc123def")); // Contains letters
         assert!(!validate_phone("")); // Empty
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod promo_campaign_tests {
+    use crate::promo_campaigns::{select_promo_campaign, validate_promo_campaign, promo_apr};
+    use crate::types::PromoCampaign;
+    use candid::Principal;
+    
+    fn campaign(id: u64, rate_override_bps: u64, eligible_principals: Vec<Principal>, eligible_commodity: Option<&str>) -> PromoCampaign {
+        PromoCampaign {
+            id,
+            name: format!("Campaign {}", id),
+            rate_override_bps,
+            eligible_principals,
+            eligible_commodity: eligible_commodity.map(|c| c.to_string()),
+            starts_at: 100,
+            ends_at: 1_000,
+            max_loans: 2,
+            budget: 1_000_000,
+            loans_used: 0,
+            budget_used: 0,
+            loan_ids: Vec::new(),
+            is_active: true,
+            created_by: Principal::anonymous(),
+            created_at: 0,
+        }
+    }
+    
+    #[test]
+    fn test_promo_terms_validation() {
+        let farmer = Principal::from_slice(&[1; 29]);
+        assert!(validate_promo_campaign(&campaign(1, 0, vec![farmer], None), 10, 50).is_ok());
+        assert_eq!(promo_apr(&campaign(1, 500, vec![], Some("rice"))), 5);
+        // Not a discount, not targeted, or not a whole percentage
+        assert!(validate_promo_campaign(&campaign(1, 1_000, vec![farmer], None), 10, 50).is_err());
+        assert!(validate_promo_campaign(&campaign(1, 0, vec![], None), 10, 50).is_err());
+        assert!(validate_promo_campaign(&campaign(1, 250, vec![], Some("rice")), 10, 50).is_err());
+        // Already over
+        assert!(validate_promo_campaign(&campaign(1, 0, vec![farmer], None), 10, 1_000).is_err());
+    }
+    
+    #[test]
+    fn test_promo_selection_respects_targeting_window_and_caps() {
+        let farmer = Principal::from_slice(&[1; 29]);
+        let other = Principal::from_slice(&[2; 29]);
+        let campaigns = vec![
+            campaign(1, 500, vec![], Some("Rice")),
+            campaign(2, 0, vec![farmer], None),
+        ];
+        
+        // The cheapest matching campaign wins
+        assert_eq!(select_promo_campaign(&campaigns, &farmer, "rice", 100_000, 500).map(|c| c.id), Some(2));
+        assert_eq!(select_promo_campaign(&campaigns, &other, "rice", 100_000, 500).map(|c| c.id), Some(1));
+        assert!(select_promo_campaign(&campaigns, &other, "corn", 100_000, 500).is_none());
+        // Outside the window
+        assert!(select_promo_campaign(&campaigns, &farmer, "rice", 100_000, 1_000).is_none());
+        
+        // Exhausted caps fall through to the next campaign
+        let mut exhausted = campaigns.clone();
+        exhausted[1].loans_used = 2;
+        assert_eq!(select_promo_campaign(&exhausted, &farmer, "rice", 100_000, 500).map(|c| c.id), Some(1));
+        exhausted[0].budget_used = 950_000;
+        assert!(select_promo_campaign(&exhausted, &farmer, "rice", 100_000, 500).is_none());
+    }
+}
+
+#[cfg(test)]
+mod liquidation_projection_tests {
+    use crate::liquidation::{build_liquidation_projection, health_at_price, solve_liquidation_price};
+    use crate::types::{BtcIdrRate, CollateralPriceComponent, CommodityPriceData, Idr, Loan, LoanStatus};
+    use candid::Principal;
+    
+    fn loan(amount_approved: u64) -> Loan {
+        Loan {
+            id: 7,
+            borrower: Principal::anonymous(),
+            nft_id: 1,
+            collateral_value_btc: 0,
+            amount_requested: amount_approved,
+            amount_approved,
+            apr: 10,
+            status: LoanStatus::Active,
+            created_at: 0,
+            due_date: None,
+            total_repaid: 0,
+            repayment_history: Vec::new(),
+            last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
+        }
+    }
+    
+    fn collateral(quantity: u64, price_per_unit: u64, valuation_idr: u64) -> CollateralPriceComponent {
+        CollateralPriceComponent {
+            nft_id: 1,
+            commodity_type: "rice".to_string(),
+            quantity,
+            valuation_idr: Idr(valuation_idr),
+            price: CommodityPriceData {
+                commodity_type: "rice".to_string(),
+                price_per_unit,
+                currency: "IDR".to_string(),
+                timestamp: 0,
+                source: "test".to_string(),
+                confidence_score: 100,
+                is_stale: false,
+                fetch_attempt_count: 0,
+                last_successful_fetch: 0,
+            },
+        }
+    }
+    
+    #[test]
+    fn test_liquidation_price_and_cushion() {
+        // 10,000 units at 15,000 IDR = 150M IDR = 25M satoshi at 600M IDR/BTC; 15M satoshi approved
+        let rate = BtcIdrRate { idr_per_btc: 600_000_000, timestamp: 0 };
+        let rice = collateral(10_000, 15_000, 1_000_000_000);
+        let projection = build_liquidation_projection(&loan(15_000_000), &rice, &rate, 0.85, 0).unwrap();
+        
+        assert!((projection.current_health_ratio - 25.0 / 15.0).abs() < 1e-9);
+        // Health 0.85 needs 12.75M satoshi of collateral: 7,650 IDR per unit, a 49% drop
+        assert_eq!(projection.current_price, 15_000);
+        assert_eq!(projection.liquidation_price, Some(7_650));
+        assert!((projection.cushion_percent.unwrap() - 49.0).abs() < 1e-6);
+        assert!(health_at_price(&loan(15_000_000), &rice, &rate, 7_649, 0).unwrap() < 0.85);
+    }
+    
+    #[test]
+    fn test_unreachable_or_empty_loans_have_no_trigger() {
+        let rate = BtcIdrRate { idr_per_btc: 600_000_000, timestamp: 0 };
+        // Appraisal caps collateral at 6M satoshi, below 0.85 x 10M at any price
+        let capped = collateral(10_000, 15_000, 36_000_000);
+        assert_eq!(solve_liquidation_price(&loan(10_000_000), &capped, &rate, 0.85, 0), None);
+        assert_eq!(solve_liquidation_price(&loan(0), &capped, &rate, 0.85, 0), None);
+        
+        // Already below the threshold: the trigger price is above today's price
+        let rice = collateral(10_000, 15_000, u64::MAX);
+        let projection = build_liquidation_projection(&loan(50_000_000), &rice, &rate, 0.85, 0).unwrap();
+        assert!(projection.cushion_percent.unwrap() < 0.0);
+        assert_eq!(projection.liquidation_price, Some(25_500));
+    }
+    
+    #[test]
+    fn test_health_is_scored_on_remaining_debt() {
+        let rate = BtcIdrRate { idr_per_btc: 600_000_000, timestamp: 0 };
+        let rice = collateral(10_000, 15_000, 1_000_000_000);
+        // 5M of the 15M satoshi repaid: 25M collateral over 10M remaining
+        let mut repaid = loan(15_000_000);
+        repaid.total_repaid = 5_000_000;
+        assert!((health_at_price(&repaid, &rice, &rate, 15_000, 0).unwrap() - 2.5).abs() < 1e-9);
+        assert!(solve_liquidation_price(&repaid, &rice, &rate, 0.85, 0).unwrap() < 7_650);
+        
+        // Accrued interest adds to the debt, so health falls over time
+        let year = 365 * 24 * 60 * 60 * 1_000_000_000;
+        let fresh = health_at_price(&loan(15_000_000), &rice, &rate, 15_000, 0).unwrap();
+        assert!(health_at_price(&loan(15_000_000), &rice, &rate, 15_000, year).unwrap() < fresh);
+        
+        repaid.total_repaid = 15_000_000;
+        assert_eq!(solve_liquidation_price(&repaid, &rice, &rate, 0.85, 0), None);
+    }
+}
+
+#[cfg(test)]
+mod pool_performance_tests {
+    use crate::pool_performance::{
+        build_performance_snapshot, monthly_apy_history, realized_apy, snapshot_due, CumulativePoolTotals, SNAPSHOT_INTERVAL_NANOS,
+    };
+    use crate::types::LiquidityPool;
+    
+    const DAY: u64 = SNAPSHOT_INTERVAL_NANOS;
+    
+    fn pool(total_liquidity: u64, available_liquidity: u64) -> LiquidityPool {
+        LiquidityPool {
+            total_liquidity,
+            available_liquidity,
+            total_borrowed: total_liquidity - available_liquidity,
+            total_repaid: 0,
+            utilization_rate: 0,
+            total_investors: 3,
+            apy: 0,
+            created_at: 0,
+            updated_at: 0,
+            reserved_liquidity: None,
+        }
+    }
+    
+    #[test]
+    fn test_period_flows_are_diffs_of_cumulative_totals() {
+        let first = build_performance_snapshot(
+            None,
+            &CumulativePoolTotals { deposits: 1_000_000, withdrawals: 0, interest: 0, losses: 0 },
+            &pool(1_000_000, 400_000),
+            600,
+            DAY,
+        );
+        assert_eq!(first.period_start, 0);
+        assert_eq!(first.pool_apy_bps, Some(600));
+        assert_eq!(first.period_deposits, 1_000_000);
+        assert_eq!(first.utilization_bps, 6_000);
+        
+        let second = build_performance_snapshot(
+            Some(&first),
+            &CumulativePoolTotals { deposits: 1_500_000, withdrawals: 200_000, interest: 1_000, losses: 0 },
+            &pool(1_300_000, 600_000),
+            550,
+            2 * DAY,
+        );
+        assert_eq!(second.period_start, DAY);
+        assert_eq!(second.period_deposits, 500_000);
+        assert_eq!(second.period_withdrawals, 200_000);
+        assert_eq!(second.period_interest, 1_000);
+        assert_eq!(second.net_inflow, 300_000);
+        assert_eq!(second.cumulative_deposits, 1_500_000);
+        
+        // A lower cumulative reading never produces a negative period
+        let third = build_performance_snapshot(
+            Some(&second),
+            &CumulativePoolTotals { deposits: 1_400_000, withdrawals: 200_000, interest: 1_000, losses: 0 },
+            &pool(1_300_000, 600_000),
+            550,
+            3 * DAY,
+        );
+        assert_eq!(third.period_deposits, 0);
+        assert_eq!(third.cumulative_deposits, 1_500_000);
+    }
+    
+    #[test]
+    fn test_realized_apy_is_annualized_net_of_losses() {
+        // 1,000 satoshi on 365,000 over one day is 100% a year
+        assert!((realized_apy(1_000, 0, 365_000, DAY) - 100.0).abs() < 1e-9);
+        assert!((realized_apy(1_000, 500, 365_000, DAY) - 50.0).abs() < 1e-9);
+        assert!(realized_apy(0, 1_000, 365_000, DAY) < 0.0);
+        assert_eq!(realized_apy(1_000, 0, 0, DAY), 0.0);
+    }
+    
+    #[test]
+    fn test_snapshots_are_taken_once_a_day() {
+        let snapshot = build_performance_snapshot(None, &CumulativePoolTotals::default(), &pool(0, 0), 300, 10 * DAY);
+        assert!(snapshot_due(None, 0));
+        assert!(!snapshot_due(Some(&snapshot), 11 * DAY - 1));
+        assert!(snapshot_due(Some(&snapshot), 11 * DAY));
+    }
+    
+    #[test]
+    fn test_apy_history_comes_from_snapshots() {
+        let snapshot = |captured_at: u64, apy_bps: Option<u64>| {
+            let mut snapshot = build_performance_snapshot(None, &CumulativePoolTotals::default(), &pool(0, 0), 0, captured_at);
+            snapshot.pool_apy_bps = apy_bps;
+            snapshot
+        };
+        // Snapshots from before APY was recorded carry None and are skipped
+        let snapshots = vec![snapshot(5 * DAY, None), snapshot(20 * DAY, Some(450)), snapshot(50 * DAY, Some(600))];
+        
+        let history = monthly_apy_history(&snapshots, 60 * DAY, 12);
+        assert_eq!(history, vec![(60 * DAY, 600), (30 * DAY, 450)]);
+    }
+}
+
+#[cfg(test)]
+mod query_cache_invalidation_tests {
+    use crate::advanced_query_routing::{
+        get_query_statistics, invalidate_cache_for, lookup_cache_entry, principal_cache_keys, store_cache_entry,
+        CacheEntry, CachedData
+    };
+    use candid::Principal;
+    
+    fn cache_dashboard(key: String, payload: &str, now: u64) {
+        store_cache_entry(CacheEntry {
+            key,
+            data: CachedData::QueryResults(payload.to_string()),
+            created_at: now,
+            expires_at: now + 300_000_000_000,
+            access_count: 0,
+            last_accessed: now,
+            size_bytes: 0,
+        });
+    }
+    
+    fn cached_payload(key: &str, now: u64) -> Option<String> {
+        match lookup_cache_entry(key, now)?.data {
+            CachedData::QueryResults(payload) => Some(payload),
+            _ => None,
+        }
+    }
+    
+    #[test]
+    fn test_mutation_invalidates_only_that_principals_entries() {
+        let farmer = Principal::from_slice(&[1u8; 29]);
+        let other = Principal::from_slice(&[2u8; 29]);
+        let farmer_key = principal_cache_keys(&farmer)[0].clone();
+        let other_key = principal_cache_keys(&other)[0].clone();
+        
+        cache_dashboard(farmer_key.clone(), "{\"total_repaid\": 0}", 1_000);
+        cache_dashboard(other_key.clone(), "{\"total_repaid\": 0}", 1_000);
+        assert_eq!(cached_payload(&farmer_key, 2_000).as_deref(), Some("{\"total_repaid\": 0}"));
+        
+        // A repayment invalidates the farmer's entries before the TTL runs out
+        let invalidations_before = get_query_statistics().cache_invalidations;
+        assert_eq!(invalidate_cache_for(farmer), 1);
+        assert_eq!(get_query_statistics().cache_invalidations, invalidations_before + 1);
+        assert!(cached_payload(&farmer_key, 2_000).is_none());
+        assert!(cached_payload(&other_key, 2_000).is_some());
+        
+        // The next read repopulates the cache with fresh data
+        cache_dashboard(farmer_key.clone(), "{\"total_repaid\": 500}", 3_000);
+        assert_eq!(cached_payload(&farmer_key, 3_000).as_deref(), Some("{\"total_repaid\": 500}"));
+        
+        // Nothing cached, nothing counted
+        assert_eq!(invalidate_cache_for(Principal::from_slice(&[3u8; 29])), 0);
+    }
+}
+
+#[cfg(test)]
+mod asset_pool_tests {
+    use crate::types::*;
+    use crate::storage::{get_asset_pool, store_asset_pool, get_liquidity_pool};
+    
+    #[test]
+    fn test_ckbtc_keeps_the_legacy_pool_key() {
+        assert_eq!(Asset::default(), Asset::CkBtc);
+        assert_eq!(Asset::CkBtc.pool_key(), 0);
+        assert_ne!(Asset::Icp.pool_key(), Asset::CkBtc.pool_key());
+        assert_eq!(Asset::CkBtc.min_amount(), 100_000);
+        assert_eq!(Asset::Icp.min_amount(), 10_000_000);
+    }
+    
+    #[test]
+    fn test_asset_pools_are_tracked_separately() {
+        let ckbtc_before = get_liquidity_pool();
+        
+        let mut icp_pool = get_asset_pool(&Asset::Icp);
+        icp_pool.total_liquidity += 50_000_000;
+        icp_pool.available_liquidity += 50_000_000;
+        store_asset_pool(&Asset::Icp, icp_pool.clone()).unwrap();
+        
+        assert_eq!(get_asset_pool(&Asset::Icp).total_liquidity, icp_pool.total_liquidity);
+        assert_eq!(get_liquidity_pool().total_liquidity, ckbtc_before.total_liquidity);
+        assert_eq!(get_asset_pool(&Asset::CkBtc).total_liquidity, ckbtc_before.total_liquidity);
+    }
+    
+    #[test]
+    fn test_rupiah_rate_converts_e8s_like_satoshi() {
+        // 1 ICP = Rp 150,000
+        let icp_rate = BtcIdrRate { idr_per_btc: 150_000, timestamp: 0 };
+        assert_eq!(icp_rate.idr_to_satoshi(Idr(75_000)).unwrap(), Satoshi(50_000_000));
+        assert_eq!(icp_rate.satoshi_to_idr(Satoshi(200_000_000)).unwrap(), Idr(300_000));
+    }
+    
+    #[test]
+    fn test_loans_without_asset_are_ckbtc() {
+        let mut loan = Loan {
+            id: 1,
+            borrower: candid::Principal::anonymous(),
+            nft_id: 1,
+            collateral_value_btc: 1_000_000,
+            amount_requested: 500_000,
+            amount_approved: 500_000,
+            apr: 10,
+            status: LoanStatus::Active,
+            created_at: 0,
+            due_date: None,
+            total_repaid: 0,
+            repayment_history: Vec::new(),
+            last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
+        };
+        assert_eq!(loan.asset(), Asset::CkBtc);
+        loan.asset = Some(Asset::Icp);
+        assert_eq!(loan.asset(), Asset::Icp);
+    }
+    
+    #[test]
+    fn test_refinancing_books_against_the_loan_asset_pool() {
+        let ckbtc_before = get_liquidity_pool();
+        let icp_before = get_asset_pool(&Asset::Icp);
+        
+        crate::liquidity_management::apply_refinancing_to_pool(&Asset::Icp, 40_000_000).unwrap();
+        crate::liquidity_management::retain_fee_in_asset_pool(&Asset::Icp, 1_000_000).unwrap();
+        
+        let icp_after = get_asset_pool(&Asset::Icp);
+        assert_eq!(icp_after.total_borrowed, icp_before.total_borrowed + 40_000_000);
+        assert_eq!(icp_after.total_repaid, icp_before.total_repaid + 40_000_000);
+        assert_eq!(icp_after.available_liquidity, icp_before.available_liquidity + 1_000_000);
+        assert_eq!(get_liquidity_pool().total_borrowed, ckbtc_before.total_borrowed);
+        assert_eq!(get_liquidity_pool().available_liquidity, ckbtc_before.available_liquidity);
+    }
+    
+    #[test]
+    fn test_ledger_memo_fits_the_icrc1_limit() {
+        use crate::helpers::{ledger_memo, MAX_LEDGER_MEMO_BYTES};
+        
+        assert_eq!(ledger_memo("Loan disbursement #42"), b"Loan disbursement #42".to_vec());
+        let long = format!("Emergency withdrawal: {}", "x".repeat(100));
+        assert_eq!(ledger_memo(&long).len(), MAX_LEDGER_MEMO_BYTES);
+        // Never splits a multi-byte character
+        let memo = ledger_memo(&"é".repeat(40));
+        assert!(memo.len() <= MAX_LEDGER_MEMO_BYTES);
+        assert!(String::from_utf8(memo).is_ok());
+    }
+    
+    #[test]
+    fn test_icp_rate_feed_is_seeded() {
+        let config = OracleConfig::default();
+        assert!(config.api_endpoints.iter().any(|(id, _)| id == crate::oracle::ICP_IDR_PRICE_ID));
+        // Rates are only ever priced from a fetched feed
+        assert!(!config.backup_prices.iter().any(|(id, _)| crate::oracle::RATE_FEED_IDS.contains(&id.as_str())));
+        assert!(crate::oracle::RATE_FEED_IDS.contains(&crate::oracle::ICP_IDR_PRICE_ID));
+    }
+}
+
+#[cfg(test)]
+mod reentrancy_guard_tests {
+    use crate::helpers::{is_resource_in_flight, ResourceGuard};
+    use crate::liquidity_management::{credit_deposit_to_pool, debit_withdrawal_from_pool, investor_resource};
+    use crate::storage::get_asset_pool;
+    use crate::types::Asset;
+    use candid::Principal;
+    
+    #[test]
+    fn test_guard_rejects_contention_and_releases_on_drop() {
+        let investor = Principal::from_slice(&[41u8; 29]);
+        let resource = investor_resource(investor);
+        
+        let guard = ResourceGuard::acquire(resource.clone()).unwrap();
+        assert!(is_resource_in_flight(&resource));
+        let err = ResourceGuard::acquire(resource.clone()).err().unwrap();
+        assert!(err.contains("Operation in progress"));
+        
+        // Other investors are not blocked
+        let other = ResourceGuard::acquire(investor_resource(Principal::from_slice(&[42u8; 29])));
+        assert!(other.is_ok());
+        
+        drop(guard);
+        assert!(!is_resource_in_flight(&resource));
+        assert!(ResourceGuard::acquire(resource).is_ok());
+    }
+    
+    #[test]
+    fn test_guard_released_when_call_fails_after_acquiring() {
+        let resource = "loan:9041".to_string();
+        let failing_call = || -> Result<(), String> {
+            let _guard = ResourceGuard::acquire(resource.clone())?;
+            Err("ledger rejected the transfer".to_string())
+        };
+        assert!(failing_call().is_err());
+        assert!(!is_resource_in_flight(&resource));
+    }
+    
+    #[test]
+    fn test_interleaved_deposits_keep_pool_totals_consistent() {
+        let before = get_asset_pool(&Asset::Icp);
+        let first = Principal::from_slice(&[43u8; 29]);
+        let second = Principal::from_slice(&[44u8; 29]);
+        
+        // Both deposits are awaiting their ledger calls at the same time
+        let first_guard = ResourceGuard::acquire(investor_resource(first)).unwrap();
+        let second_guard = ResourceGuard::acquire(investor_resource(second)).unwrap();
+        
+        // They settle in reverse order; each credit reads the totals left by the other
+        credit_deposit_to_pool(&Asset::Icp, 30_000_000, true).unwrap();
+        drop(second_guard);
+        credit_deposit_to_pool(&Asset::Icp, 20_000_000, true).unwrap();
+        drop(first_guard);
+        
+        let after = get_asset_pool(&Asset::Icp);
+        assert_eq!(after.total_liquidity, before.total_liquidity + 50_000_000);
+        assert_eq!(after.available_liquidity, before.available_liquidity + 50_000_000);
+        assert_eq!(after.total_investors, before.total_investors + 2);
+        
+        debit_withdrawal_from_pool(&Asset::Icp, 20_000_000).unwrap();
+        let after_withdrawal = get_asset_pool(&Asset::Icp);
+        assert_eq!(after_withdrawal.total_liquidity, before.total_liquidity + 30_000_000);
+        assert_eq!(after_withdrawal.total_withdrawals, before.total_withdrawals + 1);
+    }
+}
+
+#[cfg(test)]
+mod investor_statement_tests {
+    use crate::investor_statements::{build_annual_statement, build_statement_csv, statement_year_bounds};
+    use crate::types::*;
+    use candid::Principal;
+    
+    const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+    
+    fn snapshot(period_start: u64, captured_at: u64, total_liquidity: u64, interest: u64, losses: u64) -> PerformanceSnapshot {
+        PerformanceSnapshot {
+            captured_at,
+            period_start,
+            total_liquidity,
+            available_liquidity: total_liquidity,
+            total_borrowed: 0,
+            total_investors: 1,
+            utilization_bps: 0,
+            cumulative_deposits: 0,
+            cumulative_withdrawals: 0,
+            cumulative_interest: 0,
+            cumulative_losses: 0,
+            period_deposits: 0,
+            period_withdrawals: 0,
+            period_interest: interest,
+            period_losses: losses,
+            net_inflow: 0,
+            realized_apy: 0.0,
+            pool_apy_bps: None,
+        }
+    }
+    
+    #[test]
+    fn test_year_bounds() {
+        let (start, end) = statement_year_bounds(2024).unwrap();
+        assert_eq!(start, 19_723 * DAY); // 2024-01-01
+        assert_eq!(end - start, 366 * DAY); // leap year
+        assert!(statement_year_bounds(1969).is_err());
+    }
+    
+    #[test]
+    fn test_statement_totals_within_year() {
+        let investor = Principal::from_slice(&[51u8; 29]);
+        let (start, end) = statement_year_bounds(2024).unwrap();
+        let balance = InvestorBalance {
+            investor,
+            balance: 1_300_000,
+            total_deposited: 1_500_000,
+            total_withdrawn: 200_000,
+            deposits: vec![
+                DepositRecord { investor, amount: 1_000_000, ckbtc_block_index: 1, timestamp: start - DAY, fee: None },
+                DepositRecord { investor, amount: 500_000, ckbtc_block_index: 2, timestamp: start + 10 * DAY, fee: Some(5_000) },
+            ],
+            withdrawals: vec![
+                WithdrawalRecord { investor, amount: 200_000, ckbtc_block_index: 3, timestamp: start + 20 * DAY, fee: Some(2_000), lots: None },
+            ],
+            first_deposit_at: start - DAY,
+            last_activity_at: start + 20 * DAY,
+            total_fees_paid: None,
+        };
+        let snapshots = vec![
+            // Investor held 1,000,000 of 4,000,000: a quarter of interest and losses
+            snapshot(start, start + DAY, 4_000_000, 40_000, 8_000),
+            // Outside the year
+            snapshot(end, end + DAY, 4_000_000, 40_000, 0),
+        ];
+        
+        let statement = build_annual_statement(investor, Some(&balance), None, &snapshots, 0, 2024, end).unwrap();
+        assert_eq!(statement.opening_balance, 1_000_000);
+        assert_eq!(statement.closing_balance, 1_300_000);
+        assert_eq!(statement.total_deposits, 500_000);
+        assert_eq!(statement.total_withdrawals, 200_000);
+        assert_eq!((statement.deposit_count, statement.withdrawal_count), (1, 1));
+        assert_eq!(statement.fees_paid, 7_000);
+        assert_eq!(statement.yield_earned, 10_000);
+        assert_eq!(statement.losses_allocated, 2_000);
+        assert_eq!(statement.net_realized_return, 1_000);
+        
+        assert_eq!(statement.icp_activity, None);
+        
+        let csv = build_statement_csv(&statement, Some(&balance), None);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "Timestamp,Type,Amount,Fee,Reference");
+        assert!(lines[1].ends_with("OPENING_BALANCE,1000000,0,"));
+        assert!(lines[2].contains(",DEPOSIT,500000,5000,2"));
+        assert!(lines[3].contains(",WITHDRAWAL,200000,2000,3"));
+        assert!(lines[6].ends_with("CLOSING_BALANCE,1300000,0,"));
+        assert_eq!(lines.len(), 7);
+    }
+    
+    #[test]
+    fn test_statement_includes_icp_balances() {
+        let investor = Principal::from_slice(&[53u8; 29]);
+        let (start, end) = statement_year_bounds(2024).unwrap();
+        let icp_balance = InvestorBalance {
+            investor,
+            balance: 250_000_000,
+            total_deposited: 300_000_000,
+            total_withdrawn: 50_000_000,
+            deposits: vec![
+                DepositRecord { investor, amount: 100_000_000, ckbtc_block_index: 7, timestamp: start - DAY, fee: None },
+                DepositRecord { investor, amount: 200_000_000, ckbtc_block_index: 8, timestamp: start + DAY, fee: Some(10_000) },
+            ],
+            withdrawals: vec![
+                WithdrawalRecord { investor, amount: 50_000_000, ckbtc_block_index: 9, timestamp: start + 2 * DAY, fee: None, lots: None },
+            ],
+            first_deposit_at: start - DAY,
+            last_activity_at: start + 2 * DAY,
+            total_fees_paid: None,
+        };
+        
+        // An ICP-only investor still gets the ICP section
+        let statement = build_annual_statement(investor, None, Some(&icp_balance), &[], 0, 2024, end).unwrap();
+        assert_eq!(statement.closing_balance, 0);
+        let icp = statement.icp_activity.clone().unwrap();
+        assert_eq!(icp.asset, Asset::Icp);
+        assert_eq!((icp.opening_balance, icp.closing_balance), (100_000_000, 250_000_000));
+        assert_eq!((icp.total_deposits, icp.total_withdrawals), (200_000_000, 50_000_000));
+        assert_eq!((icp.deposit_count, icp.withdrawal_count, icp.fees_paid), (1, 1, 10_000));
+        
+        let csv = build_statement_csv(&statement, None, Some(&icp_balance));
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[5].ends_with("ICP_OPENING_BALANCE,100000000,0,"));
+        assert!(lines[6].contains(",ICP_DEPOSIT,200000000,10000,8"));
+        assert!(lines[7].contains(",ICP_WITHDRAWAL,50000000,0,9"));
+        assert!(lines[8].ends_with("ICP_CLOSING_BALANCE,250000000,0,"));
+    }
+    
+    #[test]
+    fn test_no_activity_returns_zeroed_statement() {
+        let investor = Principal::from_slice(&[52u8; 29]);
+        let statement = build_annual_statement(investor, None, None, &[], 0, 2023, 0).unwrap();
+        assert_eq!(statement.year, 2023);
+        assert_eq!(statement.opening_balance, 0);
+        assert_eq!(statement.closing_balance, 0);
+        assert_eq!(statement.fees_paid, 0);
+        assert_eq!(statement.net_realized_return, 0);
+    }
+}
+
+#[cfg(test)]
+mod feature_flag_tests {
+    use crate::feature_flags::{
+        ensure_feature_enabled, is_feature_enabled, resolve_feature_flag, validate_feature_flag_name,
+        get_feature_flags, FEATURE_LIQUIDATION_AUCTIONS, FEATURE_POSITION_MARKETPLACE,
+    };
+    use crate::storage::store_feature_flag;
+    use crate::types::FeatureFlag;
+    
+    fn flag(name: &str, enabled: bool) -> FeatureFlag {
+        FeatureFlag { name: name.to_string(), enabled, updated_at: 1, updated_by: None }
+    }
+    
+    #[test]
+    fn test_defaults_and_unknown_flags() {
+        assert!(resolve_feature_flag(None, FEATURE_LIQUIDATION_AUCTIONS));
+        assert!(!resolve_feature_flag(None, "auto_reinvest"));
+        assert!(resolve_feature_flag(Some(&flag("auto_reinvest", true)), "auto_reinvest"));
+        assert!(!resolve_feature_flag(Some(&flag(FEATURE_LIQUIDATION_AUCTIONS, false)), FEATURE_LIQUIDATION_AUCTIONS));
+    }
+    
+    #[test]
+    fn test_disabled_flag_blocks_gated_entry() {
+        assert!(ensure_feature_enabled(FEATURE_POSITION_MARKETPLACE).is_ok());
+        store_feature_flag(flag(FEATURE_POSITION_MARKETPLACE, false));
+        assert!(!is_feature_enabled(FEATURE_POSITION_MARKETPLACE));
+        let err = ensure_feature_enabled(FEATURE_POSITION_MARKETPLACE).unwrap_err();
+        assert!(err.contains("disabled"));
+        
+        let listed = get_feature_flags();
+        let marketplace = listed.iter().find(|f| f.name == FEATURE_POSITION_MARKETPLACE).unwrap();
+        assert!(!marketplace.enabled);
+        assert!(listed.iter().any(|f| f.name == FEATURE_LIQUIDATION_AUCTIONS && f.enabled));
+        
+        store_feature_flag(flag(FEATURE_POSITION_MARKETPLACE, true));
+        assert!(ensure_feature_enabled(FEATURE_POSITION_MARKETPLACE).is_ok());
+    }
+    
+    #[test]
+    fn test_flag_name_validation() {
+        assert!(validate_feature_flag_name("withdrawal_queue").is_ok());
+        assert!(validate_feature_flag_name("").is_err());
+        assert!(validate_feature_flag_name("Withdrawal-Queue").is_err());
+        assert!(validate_feature_flag_name(&"a".repeat(65)).is_err());
+    }
+}
+
+#[cfg(test)]
+mod liquidation_cooldown_tests {
+    use candid::Principal;
+    use crate::liquidation::liquidation_status_allows;
+    use crate::types::{LiquidationAttempt, LoanEventType, LoanStatus};
+    
+    fn attempt(expires_at: u64) -> LiquidationAttempt {
+        LiquidationAttempt {
+            loan_id: 1,
+            started_at: 100,
+            started_by: Principal::anonymous(),
+            expires_at,
+        }
+    }
+    
+    #[test]
+    fn test_active_loan_can_start_liquidation() {
+        assert!(liquidation_status_allows(&LoanStatus::Active, None, 500));
+    }
+    
+    #[test]
+    fn test_locked_loan_rejected_until_timeout() {
+        let lock = attempt(1_000);
+        assert!(!liquidation_status_allows(&LoanStatus::Liquidating, Some(&lock), 999));
+        assert!(liquidation_status_allows(&LoanStatus::Liquidating, Some(&lock), 1_000));
+    }
+    
+    #[test]
+    fn test_liquidating_loan_without_lock_is_retryable() {
+        assert!(liquidation_status_allows(&LoanStatus::Liquidating, None, 0));
+    }
+    
+    #[test]
+    fn test_closed_loans_never_liquidate() {
+        for status in [LoanStatus::Repaid, LoanStatus::Defaulted, LoanStatus::Approved] {
+            assert!(!liquidation_status_allows(&status, None, u64::MAX));
+        }
+    }
+    
+    #[test]
+    fn test_liquidating_status_maps_to_started_event() {
+        assert_eq!(
+            LoanEventType::from_status(&LoanStatus::Liquidating, false),
+            LoanEventType::LiquidationStarted
+        );
+    }
+}
+
+#[cfg(test)]
+mod holding_period_tests {
+    use candid::Principal;
+    use crate::liquidity_management::{holding_period_status, unseasoned_deposit_lots};
+    use crate::types::{DepositRecord, InvestorBalance, WithdrawalRecord};
+    
+    const PERIOD: u64 = 3_600 * 1_000_000_000;
+    
+    fn balance(deposits: &[(u64, u64)], withdrawals: &[(u64, u64)]) -> InvestorBalance {
+        let investor = Principal::from_slice(&[61u8; 29]);
+        let total_deposited: u64 = deposits.iter().map(|(_, amount)| amount).sum();
+        let total_withdrawn: u64 = withdrawals.iter().map(|(_, amount)| amount).sum();
+        InvestorBalance {
+            investor,
+            balance: total_deposited - total_withdrawn,
+            total_deposited,
+            total_withdrawn,
+            deposits: deposits.iter()
+                .map(|(timestamp, amount)| DepositRecord { investor, amount: *amount, ckbtc_block_index: 0, timestamp: *timestamp, fee: None })
+                .collect(),
+            withdrawals: withdrawals.iter()
+                .map(|(timestamp, amount)| WithdrawalRecord { investor, amount: *amount, ckbtc_block_index: 0, timestamp: *timestamp, fee: None, lots: None })
+                .collect(),
+            first_deposit_at: 0,
+            last_activity_at: 0,
+            total_fees_paid: None,
+        }
+    }
+    
+    #[test]
+    fn test_zero_period_makes_everything_eligible() {
+        let b = balance(&[(1_000, 500_000)], &[]);
+        assert!(unseasoned_deposit_lots(&b, 1_001, 0).is_empty());
+        assert_eq!(holding_period_status(&b, b.balance, 1_001, 0), (500_000, 0, None));
+    }
+    
+    #[test]
+    fn test_deposit_qualifies_exactly_at_period_end() {
+        let b = balance(&[(1_000, 500_000)], &[]);
+        assert_eq!(
+            holding_period_status(&b, b.balance, 1_000 + PERIOD - 1, PERIOD),
+            (0, 500_000, Some(1_000 + PERIOD))
+        );
+        assert_eq!(holding_period_status(&b, b.balance, 1_000 + PERIOD, PERIOD), (500_000, 0, None));
+    }
+    
+    #[test]
+    fn test_withdrawal_draws_newest_lot_first() {
+        // A seasoned lot, then a fresh one partly withdrawn straight away
+        let b = balance(&[(0, 400_000), (PERIOD, 300_000)], &[(PERIOD + 1, 100_000)]);
+        let at = PERIOD + 2;
+        assert_eq!(unseasoned_deposit_lots(&b, at, PERIOD), vec![(2 * PERIOD, 200_000)]);
+        assert_eq!(holding_period_status(&b, b.balance, at, PERIOD), (400_000, 200_000, Some(2 * PERIOD)));
+    }
+    
+    #[test]
+    fn test_balance_from_position_purchase_is_eligible() {
+        // 250,000 bought on the marketplace has no deposit record
+        let b = balance(&[(1_000, 100_000)], &[]);
+        assert_eq!(holding_period_status(&b, 350_000, 2_000, PERIOD), (250_000, 100_000, Some(1_000 + PERIOD)));
+    }
+}
+
+#[cfg(test)]
+mod validation_tests {
+    use candid::Principal;
+    use crate::validation::{
+        guard_caller, reject_anonymous, require_positive, require_registered_active_user, require_role,
+        CallerRole,
+    };
+    
+    const UNREGISTERED: Principal = Principal::from_slice(&[71u8; 29]);
+    
+    #[test]
+    fn test_anonymous_rejected_before_any_other_check() {
+        let anonymous = Principal::anonymous();
+        assert!(reject_anonymous(&anonymous).is_err());
+        // Every caller-facing endpoint gets the same error for an anonymous call
+        for operation in ["deposit_liquidity", "withdraw_liquidity", "repay_loan", "vote_on_proposal"] {
+            assert_eq!(guard_caller(&anonymous, operation), Err("Anonymous callers are not allowed".to_string()));
+        }
+    }
+    
+    #[test]
+    fn test_zero_amount_rejected() {
+        assert_eq!(require_positive(0, "Amount"), Err("Amount must be greater than zero".to_string()));
+        assert_eq!(require_positive(0, "Payment amount"), Err("Payment amount must be greater than zero".to_string()));
+        assert!(require_positive(1, "Amount").is_ok());
+    }
+    
+    #[test]
+    fn test_unregistered_caller_rejected_for_user_roles() {
+        let expected = Err("User not registered. Please register first".to_string());
+        assert_eq!(require_registered_active_user(&UNREGISTERED).map(|_| ()), expected);
+        assert_eq!(require_role(&UNREGISTERED, CallerRole::Farmer), expected);
+        assert_eq!(require_role(&UNREGISTERED, CallerRole::Investor), expected);
+    }
+    
+    #[test]
+    fn test_update_endpoints_reject_anonymous_callers() {
+        let expected = Err("Anonymous callers are not allowed".to_string());
+        ic_cdk::api::set_caller(Principal::anonymous());
+        
+        assert_eq!(crate::governance::set_protocol_parameter("max_ltv_ratio".to_string(), 6000), expected);
+        assert_eq!(crate::governance::set_multiple_protocol_parameters(vec![("max_ltv_ratio".to_string(), 6000)]), vec![expected.clone()]);
+        assert_eq!(crate::governance::emergency_stop(), expected);
+        assert_eq!(crate::production_config::set_sandbox_mode(true), expected.clone().map(|_: String| ()));
+        assert_eq!(crate::oracle::enable_emergency_mode(), expected.clone().map(|_: String| ()));
+        assert_eq!(crate::automated_maintenance::reset_circuit_breakers(), expected);
+        assert_eq!(crate::liquidation::add_liquidation_bidder(UNREGISTERED), expected);
+        assert_eq!(crate::notification_system::mark_all_notifications_as_read(), expected.clone().map(|_: String| 0));
+        assert_eq!(crate::feature_flags::set_feature_flag("liquidation_auctions".to_string(), true).map(|_| ()), expected.clone().map(|_: String| ()));
+        assert!(matches!(
+            crate::user_management::set_preferred_language("en".to_string()),
+            crate::user_management::UserResult::Err(e) if e == "Anonymous callers are not allowed"
+        ));
+        assert!(matches!(
+            crate::governance::create_proposal(crate::types::ProposalType::ProtocolParameterUpdate, "t".to_string(), "d".to_string(), None),
+            Err(crate::types::GovernanceError::Unauthorized)
+        ));
+    }
+}
+
+#[cfg(test)]
+mod autopay_tests {
+    use candid::Principal;
+    use crate::autopay::{
+        autopay_request_id, is_autopay_due, is_autopay_request_id, next_autopay_attempt_after,
+        next_unpaid_installment,
+    };
+    use crate::types::{AmortizationRow, AutopayMandate};
+    
+    const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+    
+    fn mandate() -> AutopayMandate {
+        AutopayMandate {
+            loan_id: 7,
+            borrower: Principal::from_slice(&[81u8; 29]),
+            enabled: true,
+            enabled_at: 0,
+            disabled_at: None,
+            last_attempt_at: None,
+            last_success_at: None,
+            last_failure: None,
+            consecutive_failures: 0,
+            payments_collected: 0,
+            total_collected: 0,
+        }
+    }
+    
+    fn row(installment_no: u32, due_date: u64, is_paid: bool) -> AmortizationRow {
+        AmortizationRow {
+            installment_no,
+            due_date,
+            payment_amount: 100_000,
+            principal_portion: 90_000,
+            interest_portion: 9_000,
+            fee_portion: 1_000,
+            remaining_balance: 0,
+            is_paid,
+            paid_at: None,
+        }
+    }
+    
+    #[test]
+    fn test_next_unpaid_installment_skips_paid_rows() {
+        let schedule = vec![row(1, 30 * DAY, true), row(2, 60 * DAY, false), row(3, 90 * DAY, false)];
+        assert_eq!(next_unpaid_installment(&schedule).map(|r| r.installment_no), Some(2));
+        assert!(next_unpaid_installment(&[row(1, DAY, true)]).is_none());
+    }
+    
+    #[test]
+    fn test_due_only_on_or_after_due_date() {
+        let installment = row(1, 30 * DAY, false);
+        assert!(!is_autopay_due(&mandate(), Some(&installment), 30 * DAY - 1));
+        assert!(is_autopay_due(&mandate(), Some(&installment), 30 * DAY));
+        assert!(!is_autopay_due(&mandate(), None, 30 * DAY));
+    }
+    
+    #[test]
+    fn test_disabled_mandate_never_due() {
+        let mut disabled = mandate();
+        disabled.enabled = false;
+        assert!(!is_autopay_due(&disabled, Some(&row(1, 0, false)), DAY));
+    }
+    
+    #[test]
+    fn test_failure_not_retried_same_day() {
+        let mut failed = mandate();
+        failed.last_attempt_at = Some(30 * DAY + 5);
+        failed.last_failure = Some("InsufficientFunds".to_string());
+        let installment = row(1, 30 * DAY, false);
+        assert_eq!(next_autopay_attempt_after(&failed), Some(31 * DAY));
+        assert!(!is_autopay_due(&failed, Some(&installment), 31 * DAY - 1));
+        assert!(is_autopay_due(&failed, Some(&installment), 31 * DAY));
+    }
+    
+    #[test]
+    fn test_request_ids_are_per_installment_and_flagged() {
+        let first = autopay_request_id(7, 1);
+        assert_ne!(first, autopay_request_id(7, 2));
+        assert_ne!(first, autopay_request_id(8, 1));
+        assert!(first >= 1 << 63);
+        assert!(is_autopay_request_id(first));
+        assert!(!is_autopay_request_id(u64::MAX >> 1));
+    }
+}
+
+#[cfg(test)]
+mod balance_reconciliation_tests {
+    use crate::treasury_management::classify_reconciliation;
+    use crate::types::ReconciliationStatus;
+    
+    #[test]
+    fn test_within_tolerance_is_balanced() {
+        assert_eq!(classify_reconciliation(0, 10_000, 0), ReconciliationStatus::Balanced);
+        assert_eq!(classify_reconciliation(10_000, 10_000, 0), ReconciliationStatus::Balanced);
+        assert_eq!(classify_reconciliation(-10_000, 10_000, 3), ReconciliationStatus::Balanced);
+    }
+    
+    #[test]
+    fn test_gap_outside_tolerance_is_surplus_or_deficit() {
+        assert_eq!(classify_reconciliation(10_001, 10_000, 0), ReconciliationStatus::Surplus);
+        assert_eq!(classify_reconciliation(-10_001, 10_000, 0), ReconciliationStatus::Deficit);
+        assert_eq!(classify_reconciliation(i64::MIN, 0, 0), ReconciliationStatus::Deficit);
+    }
+    
+    #[test]
+    fn test_gap_with_pending_transfers_is_deferred() {
+        assert_eq!(classify_reconciliation(-500_000, 10_000, 1), ReconciliationStatus::TransfersInFlight);
+    }
+    
+    #[test]
+    fn test_subaccounts_reconciled_only_when_configured() {
+        let mut config = crate::types::CanisterConfig::default();
+        assert!(config.reconciliation_subaccounts().is_empty());
+        config.reconciliation_subaccounts = Some(vec![vec![1u8; 32]]);
+        assert_eq!(config.reconciliation_subaccounts(), vec![vec![1u8; 32]]);
+    }
+}
+
+#[cfg(test)]
+mod contact_normalization_tests {
+    use crate::user_management::{validate_and_normalize_email, validate_and_normalize_phone, ContactValidationError};
+
+    #[test]
+    fn test_email_domain_is_lowercased() {
+        assert_eq!(
+            validate_and_normalize_email("  Farmer.Budi@AgriLends.CO.ID ", false),
+            Ok("Farmer.Budi@agrilends.co.id".to_string())
+        );
+    }
+
+    #[test]
+    fn test_email_rejects_consecutive_dots_in_name() {
+        assert_eq!(
+            validate_and_normalize_email("test..test@domain.com", false),
+            Err(ContactValidationError::InvalidLocalPart)
+        );
+    }
+
+    #[test]
+    fn test_strict_email_requires_recognized_tld() {
+        assert!(validate_and_normalize_email("user@example.invalidtld", false).is_ok());
+        assert_eq!(
+            validate_and_normalize_email("user@example.invalidtld", true),
+            Err(ContactValidationError::UnrecognizedTld("invalidtld".to_string()))
+        );
+        assert!(validate_and_normalize_email("user@example.COM", true).is_ok());
+    }
+
+    #[test]
+    fn test_spaced_phone_becomes_e164() {
+        assert_eq!(validate_and_normalize_phone("+62 812 3456 7890", false), Ok("+6281234567890".to_string()));
+        assert_eq!(validate_and_normalize_phone("0062 (812) 3456-7890", false), Ok("+6281234567890".to_string()));
+    }
+
+    #[test]
+    fn test_trunk_prefixed_national_number_gets_default_country_code() {
+        assert_eq!(validate_and_normalize_phone("0812 3456 789", false), Ok("+628123456789".to_string()));
+    }
+
+    #[test]
+    fn test_strict_phone_requires_country_code() {
+        assert_eq!(validate_and_normalize_phone("555 123 4567", false), Ok("5551234567".to_string()));
+        assert_eq!(
+            validate_and_normalize_phone("555 123 4567", true),
+            Err(ContactValidationError::MissingCountryCode)
+        );
+    }
+
+    #[test]
+    fn test_phone_longer_than_e164_is_rejected() {
+        assert_eq!(
+            validate_and_normalize_phone("+1234567890123456", false),
+            Err(ContactValidationError::PhoneLength { digits: 16 })
+        );
+        // 15 digits with a trunk '0' would be 16 once +62 replaces it
+        assert_eq!(
+            validate_and_normalize_phone("081234567890123", false),
+            Err(ContactValidationError::PhoneLength { digits: 16 })
+        );
+        assert_eq!(validate_and_normalize_phone("08123456789012", false), Ok("+628123456789012".to_string()));
+    }
+}
+
+#[cfg(test)]
+mod deposit_lot_tests {
+    use candid::Principal;
+    use crate::liquidity_management::{allocate_withdrawal_lots, open_deposit_lots, realized_lot_gains};
+    use crate::types::*;
+    
+    const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+    
+    fn investor() -> Principal {
+        Principal::from_slice(&[62u8; 29])
+    }
+    
+    fn balance(deposits: &[(u64, u64)], withdrawals: Vec<WithdrawalRecord>) -> InvestorBalance {
+        let investor = investor();
+        InvestorBalance {
+            investor,
+            balance: 0,
+            total_deposited: deposits.iter().map(|(_, amount)| amount).sum(),
+            total_withdrawn: withdrawals.iter().map(|withdrawal| withdrawal.amount).sum(),
+            deposits: deposits.iter()
+                .map(|(timestamp, amount)| DepositRecord { investor, amount: *amount, ckbtc_block_index: 0, timestamp: *timestamp, fee: None })
+                .collect(),
+            withdrawals,
+            first_deposit_at: 0,
+            last_activity_at: 0,
+            total_fees_paid: None,
+        }
+    }
+    
+    fn withdrawal(timestamp: u64, amount: u64, lots: Option<Vec<LotDraw>>) -> WithdrawalRecord {
+        WithdrawalRecord { investor: investor(), amount, ckbtc_block_index: 0, timestamp, fee: None, lots }
+    }
+    
+    fn draw(deposit_index: u64, deposited_at: u64, amount: u64) -> LotDraw {
+        LotDraw { deposit_index, deposited_at, amount }
+    }
+    
+    #[test]
+    fn test_fifo_withdrawal_spans_oldest_lots() {
+        let b = balance(&[(DAY, 300_000), (2 * DAY, 200_000), (3 * DAY, 100_000)], vec![]);
+        let lots = allocate_withdrawal_lots(&open_deposit_lots(&b, u64::MAX), 400_000, LotMethod::Fifo);
+        assert_eq!(lots, vec![draw(0, DAY, 300_000), draw(1, 2 * DAY, 100_000)]);
+    }
+    
+    #[test]
+    fn test_lifo_withdrawal_spans_newest_lots() {
+        let b = balance(&[(DAY, 300_000), (2 * DAY, 200_000), (3 * DAY, 100_000)], vec![]);
+        let lots = allocate_withdrawal_lots(&open_deposit_lots(&b, u64::MAX), 250_000, LotMethod::Lifo);
+        assert_eq!(lots, vec![draw(2, 3 * DAY, 100_000), draw(1, 2 * DAY, 150_000)]);
+    }
+    
+    #[test]
+    fn test_aggregate_withdrawal_assigns_no_lots() {
+        let b = balance(&[(DAY, 300_000)], vec![]);
+        assert!(allocate_withdrawal_lots(&open_deposit_lots(&b, u64::MAX), 100_000, LotMethod::Aggregate).is_empty());
+        assert_eq!(LotMethod::default(), LotMethod::Aggregate);
+    }
+    
+    #[test]
+    fn test_open_lots_replay_named_and_aggregate_withdrawals() {
+        let b = balance(
+            &[(DAY, 300_000), (2 * DAY, 200_000)],
+            vec![
+                // FIFO draw from the oldest lot, then an aggregate withdrawal taken newest-first
+                withdrawal(3 * DAY, 100_000, Some(vec![draw(0, DAY, 100_000)])),
+                withdrawal(4 * DAY, 50_000, None),
+            ],
+        );
+        assert_eq!(open_deposit_lots(&b, u64::MAX), vec![draw(0, DAY, 200_000), draw(1, 2 * DAY, 150_000)]);
+    }
+    
+    #[test]
+    fn test_shortfall_beyond_deposits_is_left_unassigned() {
+        let b = balance(&[(DAY, 100_000)], vec![]);
+        let lots = allocate_withdrawal_lots(&open_deposit_lots(&b, u64::MAX), 150_000, LotMethod::Fifo);
+        assert_eq!(lots, vec![draw(0, DAY, 100_000)]);
+    }
+    
+    #[test]
+    fn test_realized_gain_counts_periods_each_lot_was_held() {
+        let snapshot = |period_start: u64| PerformanceSnapshot {
+            captured_at: period_start + DAY,
+            period_start,
+            total_liquidity: 1_000_000,
+            available_liquidity: 1_000_000,
+            total_borrowed: 0,
+            total_investors: 1,
+            utilization_bps: 0,
+            cumulative_deposits: 0,
+            cumulative_withdrawals: 0,
+            cumulative_interest: 0,
+            cumulative_losses: 0,
+            period_deposits: 0,
+            period_withdrawals: 0,
+            period_interest: 10_000,
+            period_losses: 0,
+            net_inflow: 0,
+            realized_apy: 0.0,
+            pool_apy_bps: None,
+        };
+        let snapshots: Vec<PerformanceSnapshot> = (1..=4).map(|day| snapshot(day * DAY + 1)).collect();
+        let b = balance(
+            &[(DAY, 300_000), (3 * DAY, 200_000)],
+            vec![withdrawal(5 * DAY, 400_000, Some(vec![draw(0, DAY, 300_000), draw(1, 3 * DAY, 100_000)]))],
+        );
+        
+        let gains = realized_lot_gains(&b, &snapshots, 0, 0, u64::MAX);
+        assert_eq!(gains.len(), 2);
+        // Held through four periods at 1% each
+        assert_eq!((gains[0].deposit_index, gains[0].yield_earned, gains[0].net_gain), (0, 12_000, 12_000));
+        // Deposited later, so only the last two periods
+        assert_eq!((gains[1].deposit_index, gains[1].yield_earned), (1, 2_000));
+        
+        // A two-day holding period drops the periods inside it
+        let gains = realized_lot_gains(&b, &snapshots, 2 * DAY, 0, u64::MAX);
+        assert_eq!((gains[0].yield_earned, gains[1].yield_earned), (6_000, 0));
+    }
+}
+
+#[cfg(test)]
+mod commodity_pause_tests {
+    use crate::governance::update_paused_commodities;
+    use crate::helpers::ensure_commodity_not_paused;
+    use crate::storage::{get_protocol_parameters, set_protocol_parameters};
+    
+    #[test]
+    fn test_pause_adds_commodity_in_sorted_order() {
+        let paused = update_paused_commodities(Some(vec!["rice".to_string()]), "corn", true);
+        assert_eq!(paused, vec!["corn".to_string(), "rice".to_string()]);
+    }
+    
+    #[test]
+    fn test_pausing_twice_keeps_one_entry() {
+        let paused = update_paused_commodities(None, "rice", true);
+        let paused = update_paused_commodities(Some(paused), "rice", true);
+        assert_eq!(paused, vec!["rice".to_string()]);
+    }
+    
+    #[test]
+    fn test_unpause_leaves_other_commodities_paused() {
+        let paused = update_paused_commodities(Some(vec!["corn".to_string(), "rice".to_string()]), "rice", false);
+        assert_eq!(paused, vec!["corn".to_string()]);
+        assert!(update_paused_commodities(None, "rice", false).is_empty());
+    }
+    
+    #[test]
+    fn test_paused_commodity_blocks_new_loans() {
+        let mut params = get_protocol_parameters();
+        params.paused_commodities = Some(vec!["rice".to_string()]);
+        set_protocol_parameters(params).unwrap();
+        
+        // Checked at application and again when the offer is accepted
+        assert!(ensure_commodity_not_paused(" Rice").unwrap_err().contains("paused by governance"));
+        assert!(ensure_commodity_not_paused("corn").is_ok());
+    }
+}
+
+#[cfg(test)]
+mod auction_tie_break_tests {
+    use candid::Principal;
+    use crate::liquidation::{seeded_tie_break_index, top_bid_ties};
+    use crate::types::LiquidationBid;
+    
+    fn bid(id: u8, amount: u64, placed_at: u64) -> LiquidationBid {
+        LiquidationBid { bidder: Principal::from_slice(&[id; 29]), amount, placed_at }
+    }
+    
+    #[test]
+    fn test_ties_do_not_depend_on_bid_order() {
+        let bids = vec![bid(3, 900, 1), bid(1, 1_000, 2), bid(2, 1_000, 3)];
+        let reversed: Vec<LiquidationBid> = bids.iter().rev().cloned().collect();
+        
+        let (top, tied) = top_bid_ties(&bids, |_| true);
+        assert_eq!(top, Some(1_000));
+        assert_eq!(tied, vec![Principal::from_slice(&[1; 29]), Principal::from_slice(&[2; 29])]);
+        assert_eq!(top_bid_ties(&reversed, |_| true), (top, tied));
+    }
+    
+    #[test]
+    fn test_removed_bidders_are_not_eligible() {
+        let bids = vec![bid(1, 1_000, 1), bid(2, 800, 2)];
+        let removed = Principal::from_slice(&[1; 29]);
+        let (top, tied) = top_bid_ties(&bids, |bidder| *bidder != removed);
+        assert_eq!((top, tied), (Some(800), vec![Principal::from_slice(&[2; 29])]));
+        assert_eq!(top_bid_ties(&bids, |_| false), (None, vec![]));
+    }
+    
+    #[test]
+    fn test_seed_selection_is_reproducible() {
+        let seed = [5u8, 0, 0, 0, 0, 0, 0, 0, 9, 9];
+        assert_eq!(seeded_tie_break_index(&seed, 3), 2);
+        assert_eq!(seeded_tie_break_index(&seed, 3), seeded_tie_break_index(&seed, 3));
+        assert_eq!(seeded_tie_break_index(&seed, 1), 0);
+        assert_eq!(seeded_tie_break_index(&[], 4), 0);
+    }
+}
+
+#[cfg(test)]
+mod parameter_diff_tests {
+    use crate::governance::{build_parameter_diffs, flatten_protocol_parameters, pair_parameter_entries};
+    use crate::types::ProtocolParameters;
+    
+    fn diff_keys(current: &ProtocolParameters, candidate: &ProtocolParameters) -> Vec<String> {
+        build_parameter_diffs(pair_parameter_entries(
+            flatten_protocol_parameters(current),
+            flatten_protocol_parameters(candidate),
+        ))
+        .into_iter()
+        .map(|diff| diff.key)
+        .collect()
+    }
+    
+    #[test]
+    fn test_identical_sets_have_no_diff() {
+        let params = ProtocolParameters::default();
+        assert!(diff_keys(&params, &params.clone()).is_empty());
+    }
+    
+    #[test]
+    fn test_only_changed_keys_are_listed() {
+        let current = ProtocolParameters::default();
+        let mut candidate = current.clone();
+        candidate.base_apr = 12;
+        candidate.commodity_ltv_overrides.get_or_insert_with(Default::default).insert("rice".to_string(), 50);
+        candidate.paused_commodities = Some(vec!["corn".to_string()]);
+        
+        let diffs = build_parameter_diffs(pair_parameter_entries(
+            flatten_protocol_parameters(&current),
+            flatten_protocol_parameters(&candidate),
+        ));
+        assert_eq!(diffs.len(), 3);
+        let rice = diffs.iter().find(|diff| diff.key == "commodity_ltv:rice").unwrap();
+        assert_eq!((rice.current_value, rice.proposed_value), (None, Some(50)));
+        let apr = diffs.iter().find(|diff| diff.key == "base_apr").unwrap();
+        assert_eq!((apr.current_value, apr.proposed_value), (Some(10), Some(12)));
+        assert!(diffs.iter().any(|diff| diff.key == "commodity_paused:corn"));
+    }
+    
+    #[test]
+    fn test_diffs_sorted_by_descending_risk() {
+        let diffs = build_parameter_diffs(vec![
+            ("base_apr".to_string(), Some(10), Some(12)),
+            ("emergency_stop".to_string(), Some(0), Some(1)),
+            ("max_utilization_rate".to_string(), Some(80), Some(90)),
+        ]);
+        let keys: Vec<&str> = diffs.iter().map(|diff| diff.key.as_str()).collect();
+        assert_eq!(keys, vec!["emergency_stop", "max_utilization_rate", "base_apr"]);
+        assert!(diffs.windows(2).all(|pair| pair[0].risk_score >= pair[1].risk_score));
+    }
+}
+
+#[cfg(test)]
+mod receipt_expiry_tests {
+    use crate::helpers::{ensure_receipt_not_expired, get_receipt_expiry_from_metadata, receipt_expiry_status, validate_nft_metadata};
+    use crate::types::{MetadataValue, ReceiptExpiryStatus};
+    
+    const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+    
+    fn metadata(expiry: Option<MetadataValue>) -> Vec<(String, MetadataValue)> {
+        let mut metadata = vec![
+            ("rwa:legal_doc_hash".to_string(), MetadataValue::Text("a".repeat(64))),
+            ("rwa:valuation_idr".to_string(), MetadataValue::Nat(50_000_000)),
+            ("rwa:asset_description".to_string(), MetadataValue::Text("Rice, 10 tons".to_string())),
+        ];
+        if let Some(expiry) = expiry {
+            metadata.push(("rwa:receipt_expiry".to_string(), expiry));
+        }
+        metadata
+    }
+    
+    #[test]
+    fn test_expiry_status_boundaries() {
+        let now = 100 * DAY;
+        assert_eq!(receipt_expiry_status(None, now, 14 * DAY), ReceiptExpiryStatus::Untracked);
+        assert_eq!(receipt_expiry_status(Some(now + 15 * DAY), now, 14 * DAY), ReceiptExpiryStatus::Current);
+        assert_eq!(receipt_expiry_status(Some(now + 14 * DAY), now, 14 * DAY), ReceiptExpiryStatus::ExpiringSoon);
+        assert_eq!(receipt_expiry_status(Some(now), now, 14 * DAY), ReceiptExpiryStatus::Expired);
+    }
+    
+    #[test]
+    fn test_expiry_is_optional_metadata() {
+        assert!(validate_nft_metadata(&metadata(None)).is_ok());
+        assert!(validate_nft_metadata(&metadata(Some(MetadataValue::Nat(200 * DAY)))).is_ok());
+        assert!(validate_nft_metadata(&metadata(Some(MetadataValue::Text("2025-01-01".to_string())))).is_err());
+        assert_eq!(get_receipt_expiry_from_metadata(&metadata(Some(MetadataValue::Nat(200 * DAY)))), Some(200 * DAY));
+    }
+    
+    #[test]
+    fn test_expired_receipt_blocks_borrowing() {
+        let now = 100 * DAY;
+        assert!(ensure_receipt_not_expired(&metadata(Some(MetadataValue::Nat(now - 1))), now).is_err());
+        assert!(ensure_receipt_not_expired(&metadata(Some(MetadataValue::Nat(now + DAY))), now).is_ok());
+        assert!(ensure_receipt_not_expired(&metadata(None), now).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod state_snapshot_tests {
+    use crate::state_snapshot::{
+        build_snapshot_chunk, decode_snapshot_chunk, decode_snapshot_records, resolve_export_session,
+        snapshot_chunk_count, snapshot_chunk_offset, SnapshotExportSession, SNAPSHOT_RECORDS_PER_CHUNK,
+    };
+    use crate::types::SnapshotDomain;
+    
+    fn records(count: u64) -> Vec<(u64, String)> {
+        (0..count).map(|i| (i, format!("record-{}", i))).collect()
+    }
+    
+    fn chunk_of(domain: SnapshotDomain, all: &[(u64, String)], index: u64) -> Result<crate::types::StateSnapshotChunk, String> {
+        let offset = snapshot_chunk_offset(domain, all.len() as u64, index)?;
+        let end = (offset + SNAPSHOT_RECORDS_PER_CHUNK).min(all.len());
+        build_snapshot_chunk(domain, &all[offset..end], index, all.len() as u64, 7, 1_000)
+    }
+    
+    #[test]
+    fn test_chunk_count_has_at_least_one_chunk() {
+        assert_eq!(snapshot_chunk_count(0), 1);
+        assert_eq!(snapshot_chunk_count(SNAPSHOT_RECORDS_PER_CHUNK), 1);
+        assert_eq!(snapshot_chunk_count(SNAPSHOT_RECORDS_PER_CHUNK + 1), 2);
+    }
+    
+    #[test]
+    fn test_chunks_round_trip_in_order() {
+        let all = records(SNAPSHOT_RECORDS_PER_CHUNK as u64 + 5);
+        let mut restored: Vec<(u64, String)> = Vec::new();
+        for index in 0..snapshot_chunk_count(all.len()) {
+            let chunk = chunk_of(SnapshotDomain::Loans, &all, index).unwrap();
+            let bytes = candid::encode_one(&chunk).unwrap();
+            let verified = decode_snapshot_chunk(&bytes, SnapshotDomain::Loans).unwrap();
+            assert_eq!(verified.total_records, all.len() as u64);
+            restored.extend(decode_snapshot_records::<(u64, String)>(&verified).unwrap());
+        }
+        assert_eq!(restored, all);
+        assert!(chunk_of(SnapshotDomain::Loans, &all, 2).is_err());
+        // A page that does not match the declared cut is refused
+        assert!(build_snapshot_chunk(SnapshotDomain::Loans, &all[..3], 0, all.len() as u64, 7, 1_000).is_err());
+    }
+    
+    #[test]
+    fn test_tampered_or_mismatched_chunks_are_rejected() {
+        let chunk = chunk_of(SnapshotDomain::InvestorBalances, &records(3), 0).unwrap();
+        let bytes = candid::encode_one(&chunk).unwrap();
+        assert!(decode_snapshot_chunk(&bytes, SnapshotDomain::Loans).is_err());
+        
+        let mut tampered = chunk.clone();
+        tampered.payload = candid::encode_one(records(2)).unwrap();
+        assert!(decode_snapshot_chunk(&candid::encode_one(&tampered).unwrap(), SnapshotDomain::InvestorBalances).is_err());
+        
+        let mut relabeled = chunk.clone();
+        relabeled.record_count = 2;
+        assert!(decode_snapshot_chunk(&candid::encode_one(&relabeled).unwrap(), SnapshotDomain::InvestorBalances).is_err());
+        
+        let mut future = chunk.clone();
+        future.format_version += 1;
+        assert!(decode_snapshot_chunk(&candid::encode_one(&future).unwrap(), SnapshotDomain::InvestorBalances).is_err());
+        
+        let mut other_export = chunk;
+        other_export.export_id += 1;
+        assert!(decode_snapshot_chunk(&candid::encode_one(&other_export).unwrap(), SnapshotDomain::InvestorBalances).is_err());
+    }
+    
+    #[test]
+    fn test_export_chunks_are_bound_to_one_export_and_cut() {
+        let domain = SnapshotDomain::Loans;
+        let started = resolve_export_session(domain, None, (450, 9), 0, None, 1_000).unwrap();
+        assert_eq!(started, SnapshotExportSession { export_id: 1_000, total_records: 450, state_version: 9 });
+        
+        // Later chunks name the export and see the same cut
+        assert_eq!(resolve_export_session(domain, Some(started), (450, 9), 1, Some(1_000), 2_000), Ok(started));
+        assert!(resolve_export_session(domain, Some(started), (450, 9), 1, None, 2_000).is_err());
+        assert!(resolve_export_session(domain, Some(started), (450, 9), 1, Some(999), 2_000).is_err());
+        // A write since chunk 0 invalidates the export
+        assert!(resolve_export_session(domain, Some(started), (450, 10), 2, Some(1_000), 3_000).is_err());
+        assert!(resolve_export_session(domain, Some(started), (451, 9), 2, Some(1_000), 3_000).is_err());
+        // Chunk 0 without an ID always starts over
+        let restarted = resolve_export_session(domain, Some(started), (451, 10), 0, None, 4_000).unwrap();
+        assert_eq!(restarted.export_id, 4_000);
+    }
+}
+
+#[cfg(test)]
+mod borrower_reputation_tests {
+    use crate::borrower_reputation::{
+        adjusted_apr, adjusted_ltv, apply_loan_outcome, classify_loan_outcome, earns_on_time_credit,
+        neutral_reputation, reputation_term_adjustments, LoanOutcome, NEUTRAL_REPUTATION_SCORE,
+    };
+    use crate::types::*;
+    use candid::Principal;
+    
+    fn weights() -> ReputationWeights {
+        ReputationWeights {
+            on_time_points: 50,
+            late_penalty: 25,
+            default_penalty: 200,
+            volume_points: 5,
+            max_rate_discount: 2,
+            max_rate_premium: 2,
+            max_ltv_bonus: 5,
+            max_ltv_reduction: 10,
+        }
+    }
+    
+    fn closed_loan(status: LoanStatus, last_payment_date: Option<u64>, total_repaid: u64) -> Loan {
+        Loan {
+            id: 7,
+            borrower: Principal::anonymous(),
+            nft_id: 1,
+            collateral_value_btc: 2_000_000,
+            amount_requested: 1_000_000,
+            amount_approved: 1_000_000,
+            apr: 10,
+            status,
+            created_at: 0,
+            due_date: Some(1_000),
+            total_repaid,
+            repayment_history: Vec::new(),
+            last_payment_date,
+            region: None,
+            rate_type: None,
+            asset: None,
+        }
+    }
+    
+    fn metrics(repayment_rate: u64) -> LoanPerformanceMetrics {
+        LoanPerformanceMetrics {
+            loan_id: 7,
+            is_performing: repayment_rate >= 100,
+            repayment_rate,
+            payment_frequency: 0,
+            total_payments_made: 1,
+            days_since_last_payment: 0,
+        }
+    }
+    
+    fn close(loan: &Loan, repayment_rate: u64) -> BorrowerReputation {
+        let outcome = classify_loan_outcome(loan).unwrap();
+        apply_loan_outcome(neutral_reputation(loan.borrower, 0), loan, outcome, &metrics(repayment_rate), &weights(), 0)
+    }
+    
+    #[test]
+    fn test_on_time_repayment_raises_score_and_improves_terms() {
+        let loan = closed_loan(LoanStatus::Repaid, Some(900), 1_000_000);
+        assert_eq!(classify_loan_outcome(&loan), Some(LoanOutcome::OnTime));
+        let reputation = close(&loan, 100);
+        assert_eq!(reputation.on_time_repayments, 1);
+        assert_eq!(reputation.repaid_volume, 1_000_000);
+        assert_eq!(reputation.score, NEUTRAL_REPUTATION_SCORE + 50 + 5);
+        
+        let (apr_adjustment, ltv_adjustment) = reputation_term_adjustments(1000, &weights());
+        assert_eq!((apr_adjustment, ltv_adjustment), (-2, 5));
+        assert_eq!(adjusted_apr(10, apr_adjustment), 8);
+        assert_eq!(adjusted_ltv(60, ltv_adjustment), 65);
+    }
+    
+    #[test]
+    fn test_dust_loans_do_not_earn_on_time_points() {
+        let mut dust = closed_loan(LoanStatus::Repaid, Some(900), 10_000);
+        dust.amount_requested = 10_000;
+        dust.amount_approved = 10_000;
+        assert!(!earns_on_time_credit(&dust));
+        let mut reputation = neutral_reputation(dust.borrower, 0);
+        for _ in 0..20 {
+            reputation = apply_loan_outcome(reputation, &dust, LoanOutcome::OnTime, &metrics(100), &weights(), 0);
+        }
+        assert_eq!(reputation.loans_closed, 20);
+        assert_eq!(reputation.on_time_repayments, 0);
+        assert_eq!(reputation.score, NEUTRAL_REPUTATION_SCORE);
+        
+        // The threshold scales with the asset's own minimum
+        let mut icp = closed_loan(LoanStatus::Repaid, Some(900), 100_000_000);
+        icp.asset = Some(Asset::Icp);
+        icp.amount_approved = 100_000_000;
+        assert!(earns_on_time_credit(&icp));
+        icp.amount_approved = 99_999_999;
+        assert!(!earns_on_time_credit(&icp));
+    }
+    
+    #[test]
+    fn test_default_lowers_score_in_proportion_to_unpaid_share() {
+        let loan = closed_loan(LoanStatus::Defaulted, None, 0);
+        let full = close(&loan, 0);
+        assert_eq!(full.defaults, 1);
+        assert_eq!(full.repaid_volume, 0);
+        assert_eq!(full.score, NEUTRAL_REPUTATION_SCORE - 200);
+        assert_eq!(close(&loan, 75).score, NEUTRAL_REPUTATION_SCORE - 50);
+        
+        // Adjustments round toward the neutral terms
+        let (apr_adjustment, ltv_adjustment) = reputation_term_adjustments(full.score, &weights());
+        assert_eq!((apr_adjustment, ltv_adjustment), (0, -4));
+        assert_eq!(adjusted_ltv(60, ltv_adjustment), 56);
+        assert_eq!(reputation_term_adjustments(0, &weights()), (2, -10));
+    }
+    
+    #[test]
+    fn test_late_repayment_and_neutral_baseline() {
+        let late = closed_loan(LoanStatus::Repaid, Some(2_000), 1_000_000);
+        assert_eq!(classify_loan_outcome(&late), Some(LoanOutcome::Late));
+        assert_eq!(close(&late, 100).score, NEUTRAL_REPUTATION_SCORE - 25 + 5);
+        assert_eq!(classify_loan_outcome(&closed_loan(LoanStatus::Active, None, 0)), None);
+        assert_eq!(reputation_term_adjustments(NEUTRAL_REPUTATION_SCORE, &weights()), (0, 0));
+        assert_eq!(adjusted_ltv(88, 5), 90);
+    }
+    
+    #[test]
+    fn test_refinance_payoff_is_not_scored() {
+        let mut refinanced = closed_loan(LoanStatus::Repaid, Some(900), 1_000_000);
+        refinanced.repayment_history.push(Payment {
+            amount: 1_000_000,
+            timestamp: 900,
+            payment_type: PaymentType::Mixed,
+            transaction_id: Some(format!("{}{}", crate::loan_lifecycle::REFINANCE_TRANSACTION_PREFIX, 8)),
+            allocation: None,
+            source: None,
+        });
+        assert_eq!(classify_loan_outcome(&refinanced), None);
+        
+        // Only the closing payment decides
+        refinanced.repayment_history.push(Payment {
+            amount: 1,
+            timestamp: 950,
+            payment_type: PaymentType::Principal,
+            transaction_id: Some("block:42".to_string()),
+            allocation: None,
+            source: None,
+        });
+        assert_eq!(classify_loan_outcome(&refinanced), Some(LoanOutcome::OnTime));
+    }
+}
+
+#[cfg(test)]
+mod at_risk_report_tests {
+    use crate::liquidation::{sort_at_risk_loans, suggest_at_risk_action};
+    use crate::types::*;
+    use candid::Principal;
+    
+    fn entry(id: u64, action: AtRiskAction, health_ratio: f64, days_overdue: u64) -> AtRiskLoan {
+        AtRiskLoan {
+            loan: Loan {
+                id,
+                borrower: Principal::anonymous(),
+                nft_id: id,
+                collateral_value_btc: 1_000_000,
+                amount_requested: 500_000,
+                amount_approved: 500_000,
+                apr: 10,
+                status: LoanStatus::Active,
+                created_at: 0,
+                due_date: None,
+                total_repaid: 0,
+                repayment_history: Vec::new(),
+                last_payment_date: None,
+                region: None,
+                rate_type: None,
+                asset: None,
+            },
+            health_ratio,
+            liquidation_price: None,
+            cushion_percent: None,
+            days_overdue,
+            borrower_has_email: true,
+            borrower_has_phone: false,
+            suggested_action: action,
+        }
+    }
+    
+    #[test]
+    fn test_suggested_action_follows_thresholds() {
+        assert_eq!(suggest_at_risk_action(0.80, 0.85, 1.2, false, false), Some(AtRiskAction::LiquidateNow));
+        assert_eq!(suggest_at_risk_action(1.50, 0.85, 1.2, true, true), Some(AtRiskAction::LiquidateNow));
+        assert_eq!(suggest_at_risk_action(1.50, 0.85, 1.2, true, false), Some(AtRiskAction::GracePeriod));
+        assert_eq!(suggest_at_risk_action(1.10, 0.85, 1.2, false, false), Some(AtRiskAction::MarginCall));
+        assert_eq!(suggest_at_risk_action(1.20, 0.85, 1.2, false, false), None);
+    }
+    
+    #[test]
+    fn test_report_sorted_by_urgency() {
+        let mut entries = vec![
+            entry(1, AtRiskAction::MarginCall, 0.95, 0),
+            entry(2, AtRiskAction::GracePeriod, 1.40, 3),
+            entry(3, AtRiskAction::LiquidateNow, 0.80, 0),
+            entry(4, AtRiskAction::GracePeriod, 1.40, 9),
+            entry(5, AtRiskAction::LiquidateNow, 0.60, 0),
+        ];
+        sort_at_risk_loans(&mut entries);
+        let ids: Vec<u64> = entries.iter().map(|entry| entry.loan.id).collect();
+        assert_eq!(ids, vec![5, 3, 4, 2, 1]);
+    }
+}
+
+#[cfg(test)]
+mod admin_nonce_tests {
+    use crate::types::AdminNonceState;
+    use crate::validation::check_admin_nonce;
+    use candid::Principal;
+    
+    fn state(required: bool, last_nonce: u64) -> AdminNonceState {
+        AdminNonceState { admin: Principal::anonymous(), required, last_nonce, updated_at: 0 }
+    }
+    
+    #[test]
+    fn test_nonce_must_strictly_increase() {
+        let enrolled = state(true, 5);
+        assert_eq!(check_admin_nonce(Some(&enrolled), true, Some(6)), Ok(Some(6)));
+        assert_eq!(check_admin_nonce(Some(&enrolled), true, Some(100)), Ok(Some(100)));
+        assert!(check_admin_nonce(Some(&enrolled), true, Some(5)).is_err());
+        assert!(check_admin_nonce(Some(&enrolled), true, Some(4)).is_err());
+        assert!(check_admin_nonce(Some(&enrolled), true, None).is_err());
+    }
+    
+    #[test]
+    fn test_nonce_ignored_unless_flag_on_and_admin_enrolled() {
+        assert_eq!(check_admin_nonce(None, true, None), Ok(None));
+        assert_eq!(check_admin_nonce(Some(&state(false, 5)), true, Some(1)), Ok(None));
+        assert_eq!(check_admin_nonce(Some(&state(true, 5)), false, None), Ok(None));
+    }
+}
+
+#[cfg(test)]
+mod loan_state_replay_tests {
+    use crate::loan_lifecycle::reconstruct_loan_state;
+    use crate::types::*;
+    use candid::Principal;
+    
+    const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+    
+    fn event(sequence: u64, event_type: LoanEventType, old_status: Option<LoanStatus>, new_status: LoanStatus, timestamp: u64) -> LoanEvent {
+        LoanEvent { sequence, loan_id: 9, event_type, old_status, new_status, amount: 1_000_000, timestamp }
+    }
+    
+    fn history() -> Vec<LoanEvent> {
+        vec![
+            event(1, LoanEventType::Created, None, LoanStatus::PendingApproval, 100),
+            event(2, LoanEventType::Approved, Some(LoanStatus::PendingApproval), LoanStatus::Approved, 200),
+            event(3, LoanEventType::Disbursed, Some(LoanStatus::Approved), LoanStatus::Active, 300),
+        ]
+    }
+    
+    fn loan() -> Loan {
+        Loan {
+            id: 9,
+            borrower: Principal::anonymous(),
+            nft_id: 1,
+            collateral_value_btc: 2_000_000,
+            amount_requested: 1_000_000,
+            amount_approved: 1_000_000,
+            apr: 10,
+            status: LoanStatus::Active,
+            created_at: 100,
+            due_date: Some(300 + 30 * DAY),
+            total_repaid: 200_000,
+            repayment_history: vec![Payment {
+                amount: 200_000,
+                timestamp: 400,
+                payment_type: PaymentType::Mixed,
+                transaction_id: None,
+                allocation: None,
+                source: None,
+            }],
+            last_payment_date: Some(400),
+            region: None,
+            rate_type: None,
+            asset: None,
+        }
+    }
+    
+    #[test]
+    fn test_replay_at_each_stage() {
+        let (loan, events) = (loan(), history());
+        
+        let submitted = reconstruct_loan_state(&loan, &events, None, 150).unwrap();
+        assert_eq!(submitted.status, LoanStatus::PendingApproval);
+        assert_eq!((submitted.events_applied, submitted.outstanding_balance), (1, 0));
+        assert_eq!(submitted.due_date, None);
+        
+        let approved = reconstruct_loan_state(&loan, &events, None, 250).unwrap();
+        assert_eq!(approved.status, LoanStatus::Approved);
+        assert_eq!(approved.disbursed_at, None);
+        
+        let disbursed = reconstruct_loan_state(&loan, &events, None, 350).unwrap();
+        assert_eq!(disbursed.status, LoanStatus::Active);
+        assert_eq!(disbursed.disbursed_at, Some(300));
+        assert_eq!((disbursed.total_repaid, disbursed.outstanding_balance), (0, 1_000_000));
+        assert_eq!(disbursed.health_ratio, Some(2.0));
+        
+        let partly_repaid = reconstruct_loan_state(&loan, &events, None, 500).unwrap();
+        assert_eq!(partly_repaid.last_event_sequence, Some(3));
+        assert_eq!((partly_repaid.total_repaid, partly_repaid.outstanding_balance), (200_000, 800_000));
+        assert_eq!(partly_repaid.days_overdue, 0);
+    }
+    
+    #[test]
+    fn test_replay_rejects_times_before_creation_and_pruned_history() {
+        assert!(reconstruct_loan_state(&loan(), &history(), None, 50).is_err());
+        assert!(reconstruct_loan_state(&loan(), &history()[1..], None, 350).is_err());
+        assert!(reconstruct_loan_state(&loan(), &[], None, 350).is_err());
+    }
+    
+    #[test]
+    fn test_unlogged_disbursement_falls_back_to_the_disbursement_record() {
+        let (loan, events) = (loan(), history()[..2].to_vec());
+        
+        let before = reconstruct_loan_state(&loan, &events, Some(300), 250).unwrap();
+        assert_eq!((before.status, before.disbursed_at), (LoanStatus::Approved, None));
+        
+        let after = reconstruct_loan_state(&loan, &events, Some(300), 350).unwrap();
+        assert_eq!(after.status, LoanStatus::Active);
+        assert_eq!(after.disbursed_at, Some(300));
+        assert_eq!(after.due_date, loan.due_date);
+        assert_eq!(after.outstanding_balance, 1_000_000);
+        
+        // A logged Active event wins over the record
+        assert_eq!(reconstruct_loan_state(&loan, &history(), Some(320), 350).unwrap().disbursed_at, Some(300));
+    }
+}
+
+#[cfg(test)]
+mod notification_broadcast_tests {
+    use crate::notification_broadcast::*;
+    use crate::types::*;
+    use candid::Principal;
+    
+    fn principal(byte: u8) -> Principal {
+        Principal::from_slice(&[byte; 29])
+    }
+    
+    fn broadcast(recipients: usize, next_index: u64) -> NotificationBroadcast {
+        NotificationBroadcast {
+            id: 1,
+            audience: BroadcastAudience::All,
+            title: "Maintenance".to_string(),
+            body: "Scheduled downtime tonight".to_string(),
+            created_by: principal(9),
+            created_at: 0,
+            recipient_count: recipients as u64,
+            next_index,
+            delivered: 0,
+            skipped: 0,
+            status: BroadcastStatus::InProgress,
+            completed_at: None,
+        }
+    }
+    
+    #[test]
+    fn test_content_validation() {
+        assert_eq!(
+            validate_broadcast_content("  Rates  ", " New APRs apply from Monday "),
+            Ok(("Rates".to_string(), "New APRs apply from Monday".to_string()))
+        );
+        assert!(validate_broadcast_content("", "body").is_err());
+        assert!(validate_broadcast_content("title", "   ").is_err());
+        assert!(validate_broadcast_content(&"t".repeat(MAX_BROADCAST_TITLE_LENGTH + 1), "body").is_err());
+        assert!(validate_broadcast_content("title", &"b".repeat(MAX_BROADCAST_BODY_LENGTH + 1)).is_err());
+        assert!(validate_broadcast_content("Hello {{title}}", "body").is_err());
+    }
+    
+    #[test]
+    fn test_recipients_are_deduplicated_in_order() {
+        let recipients = dedup_recipients(vec![principal(2), principal(1), principal(2), Principal::anonymous(), principal(3)]);
+        assert_eq!(recipients, vec![principal(2), principal(1), principal(3)]);
+    }
+    
+    #[test]
+    fn test_batches_cover_the_remaining_recipients() {
+        assert_eq!(next_broadcast_batch(&broadcast(120, 0), BROADCAST_BATCH_SIZE), 0..50);
+        assert_eq!(next_broadcast_batch(&broadcast(120, 100), BROADCAST_BATCH_SIZE), 100..120);
+        assert_eq!(next_broadcast_batch(&broadcast(120, 120), BROADCAST_BATCH_SIZE), 120..120);
+        assert_eq!(next_broadcast_batch(&broadcast(10, 4), 3), 4..7);
+    }
+    
+    #[test]
+    fn test_recipients_are_read_one_batch_at_a_time() {
+        use crate::storage::{get_broadcast_recipients, remove_broadcast_recipients, store_broadcast_recipients};
+        let recipients: Vec<Principal> = (0..120).map(principal).collect();
+        store_broadcast_recipients(7, &recipients);
+        store_broadcast_recipients(8, &recipients[..3]);
+        assert_eq!(get_broadcast_recipients(7, 100, 120), recipients[100..120].to_vec());
+        assert_eq!(get_broadcast_recipients(7, 0, 50).len(), 50);
+        remove_broadcast_recipients(7);
+        assert!(get_broadcast_recipients(7, 0, 120).is_empty());
+        assert_eq!(get_broadcast_recipients(8, 0, 50), recipients[..3].to_vec());
+    }
+    
+    #[test]
+    fn test_audience_labels_hide_principal_lists() {
+        let audience = BroadcastAudience::Principals(vec![principal(1), principal(2)]);
+        assert_eq!(audience_label(&audience), "2 listed principal(s)");
+        assert_eq!(audience_label(&BroadcastAudience::OverdueBorrowers), "overdue borrowers");
+    }
+}
+
+#[cfg(test)]
+mod amount_rounding_tests {
+    use crate::helpers::{format_amount, round_satoshi, rounded_share, rounded_share_with, ROUNDING_DUST_BOUND_SATOSHI};
+    use crate::treasury_management::split_protocol_fee;
+    use crate::types::*;
+    
+    const MODES: [RoundingMode; 4] = [RoundingMode::Down, RoundingMode::Up, RoundingMode::HalfUp, RoundingMode::HalfEven];
+    
+    #[test]
+    fn test_rounding_directions() {
+        // 25 / 10 = 2.5, 35 / 10 = 3.5, 27 / 10 = 2.7
+        assert_eq!(MODES.map(|mode| round_satoshi(25, 10, mode)), [2, 3, 3, 2]);
+        assert_eq!(MODES.map(|mode| round_satoshi(35, 10, mode)), [3, 4, 4, 4]);
+        assert_eq!(MODES.map(|mode| round_satoshi(27, 10, mode)), [2, 3, 3, 3]);
+        assert_eq!(MODES.map(|mode| round_satoshi(30, 10, mode)), [3, 3, 3, 3]);
+        assert_eq!(round_satoshi(10, 0, RoundingMode::Up), 0);
+        assert_eq!(round_satoshi(u128::MAX, 1, RoundingMode::Down), u64::MAX);
+    }
+    
+    #[test]
+    fn test_rounded_shares_stay_within_dust_bound() {
+        for mode in MODES {
+            for amount in [1u64, 7, 99, 12_345, 1_000_001, 987_654_321] {
+                for bps in [1u64, 33, 250, 1_000, 9_999] {
+                    let exact = amount as u128 * bps as u128;
+                    let rounded = round_satoshi(exact, 10_000, mode) as u128 * 10_000;
+                    assert!(rounded.abs_diff(exact) < ROUNDING_DUST_BOUND_SATOSHI as u128 * 10_000);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn test_shares_follow_the_given_mode() {
+        // 12_345 at 250 bps = 308.625
+        assert_eq!(MODES.map(|mode| rounded_share_with(12_345, 250, 10_000, mode)), [308, 309, 309, 309]);
+        assert_eq!(rounded_share(12_345, 250, 10_000), rounded_share_with(12_345, 250, 10_000, RoundingMode::default()));
+    }
+    
+    #[test]
+    fn test_fee_split_conserves_every_satoshi() {
+        for fee in [0u64, 1, 3, 999, 1_234_567] {
+            for bps in [0u64, 1, 3_333, 5_000, 10_000] {
+                let (insurance, treasury) = split_protocol_fee(fee, bps, 0, u64::MAX);
+                assert_eq!(insurance + treasury, fee);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_amounts_display_with_eight_decimals() {
+        assert_eq!(format_amount(150_000, Asset::CkBtc), "0.00150000 ckBTC");
+        assert_eq!(format_amount(250_000_001, Asset::Icp), "2.50000001 ICP");
+        assert_eq!(format_amount(0, Asset::CkBtc), "0.00000000 ckBTC");
+    }
+}
+
+#[cfg(test)]
+mod sharded_loan_read_tests {
+    use crate::advanced_query_routing::*;
+    use crate::scalability_architecture::owning_shard_id;
+    use crate::types::*;
+    use candid::Principal;
+    
+    fn canister(byte: u8) -> Principal {
+        Principal::from_slice(&[byte; 10])
+    }
+    
+    fn shards() -> Vec<(u32, Principal)> {
+        vec![(1, canister(1)), (2, canister(2)), (3, canister(3))]
+    }
+    
+    fn loan(id: u64, apr: u64) -> Loan {
+        Loan {
+            id,
+            borrower: Principal::anonymous(),
+            nft_id: id,
+            collateral_value_btc: 2_000_000,
+            amount_requested: 1_000_000,
+            amount_approved: 1_000_000,
+            apr,
+            status: LoanStatus::Active,
+            created_at: 0,
+            due_date: None,
+            total_repaid: 0,
+            repayment_history: vec![],
+            last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
+        }
+    }
+    
+    #[test]
+    fn test_owner_shard_matches_loan_placement() {
+        assert_eq!(owning_shard_id(0, 3), 1);
+        assert_eq!(owning_shard_id(7, 3), 2);
+        assert_eq!(owning_shard_id(u64::MAX, 1), 1);
+        
+        // Hash 7 over three shards lands on shard 2, then the other shards, then local storage
+        assert_eq!(plan_borrower_read(7, &shards(), true), vec![
+            LoanReadRoute::Shard { shard_id: 2, canister_id: canister(2) },
+            LoanReadRoute::Shard { shard_id: 1, canister_id: canister(1) },
+            LoanReadRoute::Shard { shard_id: 3, canister_id: canister(3) },
+            LoanReadRoute::Local,
+        ]);
+    }
+    
+    #[test]
+    fn test_local_only_when_flag_off_or_no_shards() {
+        assert_eq!(plan_borrower_read(7, &shards(), false), vec![LoanReadRoute::Local]);
+        assert_eq!(plan_borrower_read(7, &[], true), vec![LoanReadRoute::Local]);
+        assert_eq!(plan_loan_read(&shards(), false), vec![LoanReadRoute::Local]);
+        
+        // Owner ID missing from the active set (e.g. shard 2 deactivated) still reads the
+        // remaining shards before local storage
+        let gapped = vec![(1, canister(1)), (3, canister(3))];
+        assert_eq!(plan_borrower_read(1, &gapped, true), vec![
+            LoanReadRoute::Shard { shard_id: 1, canister_id: canister(1) },
+            LoanReadRoute::Shard { shard_id: 3, canister_id: canister(3) },
+            LoanReadRoute::Local,
+        ]);
+    }
+    
+    #[test]
+    fn test_loan_reads_try_each_shard_then_local() {
+        let routes = plan_loan_read(&shards(), true);
+        assert_eq!(routes.len(), 4);
+        assert_eq!(routes[0], LoanReadRoute::Shard { shard_id: 1, canister_id: canister(1) });
+        assert_eq!(routes[2], LoanReadRoute::Shard { shard_id: 3, canister_id: canister(3) });
+        assert_eq!(routes[3], LoanReadRoute::Local);
+    }
+    
+    #[test]
+    fn test_shard_copies_replace_local_duplicates() {
+        let merged = merge_routed_loans(vec![loan(2, 12)], vec![loan(1, 10), loan(2, 10)]);
+        assert_eq!(merged.iter().map(|l| (l.id, l.apr)).collect::<Vec<_>>(), vec![(1, 10), (2, 12)]);
+    }
+}
+
+#[cfg(test)]
+mod active_loan_limit_tests {
+    use crate::loan_lifecycle::{build_loan_capacity, counts_toward_loan_limit, ensure_loan_capacity};
+    use crate::types::*;
+    use candid::Principal;
+    
+    fn loan(id: u64, status: LoanStatus) -> Loan {
+        Loan {
+            id,
+            borrower: Principal::anonymous(),
+            nft_id: id,
+            collateral_value_btc: 2_000_000,
+            amount_requested: 1_000_000,
+            amount_approved: 1_000_000,
+            apr: 10,
+            status,
+            created_at: 0,
+            due_date: None,
+            total_repaid: 0,
+            repayment_history: vec![],
+            last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
+        }
+    }
+    
+    fn loans(statuses: &[LoanStatus]) -> Vec<Loan> {
+        statuses.iter().enumerate().map(|(i, status)| loan(i as u64, status.clone())).collect()
+    }
+    
+    #[test]
+    fn test_closed_loans_do_not_hold_slots() {
+        for status in [LoanStatus::Repaid, LoanStatus::Defaulted, LoanStatus::Expired, LoanStatus::Withdrawn] {
+            assert!(!counts_toward_loan_limit(&status));
+        }
+        for status in [
+            LoanStatus::PendingApplication, LoanStatus::PendingApproval, LoanStatus::Approved,
+            LoanStatus::Active, LoanStatus::Liquidating,
+        ] {
+            assert!(counts_toward_loan_limit(&status));
+        }
+    }
+    
+    #[test]
+    fn test_limit_boundary() {
+        let borrower = Principal::anonymous();
+        let one_below = loans(&[LoanStatus::Active, LoanStatus::PendingApproval, LoanStatus::Repaid]);
+        let capacity = build_loan_capacity(borrower, &one_below, 3, None);
+        assert_eq!((capacity.used, capacity.remaining), (2, 1));
+        assert!(ensure_loan_capacity(&capacity).is_ok());
+        
+        let at_limit = loans(&[LoanStatus::Active, LoanStatus::Approved, LoanStatus::Liquidating, LoanStatus::Defaulted]);
+        let capacity = build_loan_capacity(borrower, &at_limit, 3, None);
+        assert_eq!((capacity.used, capacity.remaining), (3, 0));
+        assert!(ensure_loan_capacity(&capacity).is_err());
+    }
+    
+    #[test]
+    fn test_override_replaces_governance_limit() {
+        let borrower = Principal::anonymous();
+        let limit_override = BorrowerLoanLimitOverride {
+            borrower,
+            max_active_loans: 4,
+            reason: "Cooperative aggregator".to_string(),
+            set_by: Principal::anonymous(),
+            set_at: 0,
+        };
+        let held = loans(&[LoanStatus::Active, LoanStatus::Active, LoanStatus::Active]);
+        
+        let capacity = build_loan_capacity(borrower, &held, 3, Some(&limit_override));
+        assert_eq!((capacity.limit, capacity.remaining), (4, 1));
+        assert_eq!(capacity.override_reason.as_deref(), Some("Cooperative aggregator"));
+        
+        // A lowered override below current usage leaves no room rather than underflowing
+        let lowered = BorrowerLoanLimitOverride { max_active_loans: 1, ..limit_override };
+        assert_eq!(build_loan_capacity(borrower, &held, 3, Some(&lowered)).remaining, 0);
+    }
+}
+
+#[cfg(test)]
+mod disbursement_fee_tests {
+    use crate::liquidity_management::{borrower_network_fee_charge, disbursement_amounts};
+    use crate::types::{DisbursementRecord, FeeBearer};
+    use candid::Principal;
+    
+    #[test]
+    fn test_borrower_bears_fee() {
+        // Principal goes out unchanged; the minter's fee comes out of what the borrower receives
+        assert_eq!(disbursement_amounts(1_000_000, 12_000, FeeBearer::Borrower), Ok((1_000_000, 988_000)));
+        assert_eq!(disbursement_amounts(1_000_000, 0, FeeBearer::Borrower), Ok((1_000_000, 1_000_000)));
+    }
+    
+    #[test]
+    fn test_protocol_bears_fee() {
+        // The treasury tops up the withdrawal so the borrower nets the full principal
+        assert_eq!(disbursement_amounts(1_000_000, 12_000, FeeBearer::Protocol), Ok((1_012_000, 1_000_000)));
+        assert!(disbursement_amounts(u64::MAX, 1, FeeBearer::Protocol).is_err());
+    }
+    
+    #[test]
+    fn test_fee_consuming_disbursement_rejected() {
+        assert!(disbursement_amounts(10_000, 10_000, FeeBearer::Borrower).is_err());
+        assert!(disbursement_amounts(5_000, 10_000, FeeBearer::Borrower).is_err());
+        // The protocol can still cover a fee larger than a small principal
+        assert_eq!(disbursement_amounts(5_000, 10_000, FeeBearer::Protocol), Ok((15_000, 5_000)));
+    }
+    
+    #[test]
+    fn test_borrower_paid_fee_is_a_charge_line() {
+        let record = |network_fee: Option<u64>, fee_bearer: Option<FeeBearer>| DisbursementRecord {
+            loan_id: 1,
+            borrower_btc_address: String::new(),
+            amount: 1_000_000,
+            ckbtc_block_index: 1,
+            disbursed_at: 0,
+            disbursed_by: Principal::anonymous(),
+            network_fee,
+            fee_bearer,
+        };
+        assert_eq!(borrower_network_fee_charge(&[record(Some(12_000), Some(FeeBearer::Borrower))]), Some(12_000));
+        // Older records without a bearer were borrower-paid
+        assert_eq!(borrower_network_fee_charge(&[record(Some(12_000), None)]), Some(12_000));
+        assert_eq!(borrower_network_fee_charge(&[record(Some(12_000), Some(FeeBearer::Protocol))]), None);
+        // ICP disbursements carry no network fee
+        assert_eq!(borrower_network_fee_charge(&[record(None, None)]), None);
+    }
+}
+
+#[cfg(test)]
+mod schema_migration_tests {
+    use crate::schema_migration::{
+        backfill_dust_sweep_withdrawal, backfill_total_fees_paid, fee_collections_from_revenue, migration_plan,
+        BookedFeeRevenue, BookedInsuranceShare, SchemaMigration, SCHEMA_MIGRATIONS, SCHEMA_VERSION, UNVERSIONED_SCHEMA_VERSION,
+    };
+    use crate::types::*;
+    use candid::{CandidType, Deserialize, Principal};
+    use std::collections::HashSet;
+    
+    // InvestorBalance as stored before schema v2
+    #[derive(CandidType, Deserialize)]
+    struct InvestorBalanceV1 {
+        investor: Principal,
+        balance: u64,
+        total_deposited: u64,
+        total_withdrawn: u64,
+        deposits: Vec<DepositRecord>,
+        withdrawals: Vec<WithdrawalRecord>,
+        first_deposit_at: u64,
+        last_activity_at: u64,
+    }
+    
+    fn v1_balance() -> InvestorBalanceV1 {
+        let investor = Principal::from_slice(&[9u8; 29]);
+        InvestorBalanceV1 {
+            investor,
+            balance: 880_000,
+            total_deposited: 1_000_000,
+            total_withdrawn: 120_000,
+            deposits: vec![
+                DepositRecord { investor, amount: 600_000, ckbtc_block_index: 1, timestamp: 0, fee: Some(3_000) },
+                DepositRecord { investor, amount: 400_000, ckbtc_block_index: 2, timestamp: 0, fee: None },
+            ],
+            withdrawals: vec![
+                WithdrawalRecord { investor, amount: 120_000, ckbtc_block_index: 3, timestamp: 0, fee: Some(1_500), lots: None },
+            ],
+            first_deposit_at: 0,
+            last_activity_at: 0,
+        }
+    }
+    
+    #[test]
+    fn test_v1_to_v2_backfills_fee_total() {
+        // A v1 record decodes under the v2 layout with the new field missing
+        let bytes = candid::encode_one(v1_balance()).unwrap();
+        let decoded: InvestorBalance = candid::decode_one(&bytes).unwrap();
+        assert_eq!(decoded.total_fees_paid, None);
+        
+        let migrated = backfill_total_fees_paid(decoded).expect("v1 balance needs a backfill");
+        assert_eq!(migrated.total_fees_paid, Some(4_500));
+        assert_eq!(migrated.balance, 880_000);
+        
+        // Running the migration again leaves migrated data alone
+        assert!(backfill_total_fees_paid(migrated).is_none());
+    }
+    
+    fn fee_payment(timestamp: u64, protocol_fee_amount: u64) -> Payment {
+        Payment {
+            amount: 100_000,
+            timestamp,
+            payment_type: PaymentType::Mixed,
+            transaction_id: Some(format!("block-{}", timestamp)),
+            allocation: Some(PaymentBreakdown {
+                principal_amount: 80_000,
+                interest_amount: 20_000 - protocol_fee_amount,
+                protocol_fee_amount,
+                penalty_amount: 0,
+                total_amount: 100_000,
+            }),
+            source: None,
+        }
+    }
+    
+    fn repaid_loan(repayment_history: Vec<Payment>) -> Loan {
+        Loan {
+            id: 4,
+            borrower: Principal::anonymous(),
+            nft_id: 4,
+            collateral_value_btc: 2_000_000,
+            amount_requested: 1_000_000,
+            amount_approved: 1_000_000,
+            apr: 10,
+            status: LoanStatus::Active,
+            created_at: 0,
+            due_date: None,
+            total_repaid: 0,
+            repayment_history,
+            last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
+        }
+    }
+    
+    fn booked(entry_id: u64, amount: u64, timestamp: u64) -> BookedFeeRevenue {
+        // Pre-v3 routing booked only the treasury share, against no loan
+        BookedFeeRevenue { entry_id, source_loan_id: 0, amount, timestamp }
+    }
+    
+    fn insured(entry_id: u64, loan_id: u64, amount: u64, timestamp: u64) -> BookedInsuranceShare {
+        BookedInsuranceShare { entry_id, loan_id, amount, timestamp }
+    }
+    
+    #[test]
+    fn test_v2_to_v3_marks_booked_fees_collected() {
+        let canister = Principal::from_slice(&[3u8; 10]);
+        let loan = repaid_loan(vec![fee_payment(100, 2_000), fee_payment(200, 2_000), fee_payment(300, 1_500)]);
+        // Payments 0 and 1 were routed 30% to insurance (600 + 1_400) by the old code. Revenue
+        // entry 8 is another loan's fee routed at the same time as payment 0's; payment 2's fee
+        // never reached the treasury.
+        let revenue = vec![booked(9, 1_400, 200), booked(7, 1_400, 100), booked(8, 1_400, 150)];
+        let insurance = vec![
+            insured(21, 4, 600, 100),
+            insured(22, 5, 600, 150),
+            insured(23, 4, 600, 200),
+        ];
+        let (mut claimed_revenue, mut claimed_insurance) = (HashSet::new(), HashSet::new());
+        
+        let collections = fee_collections_from_revenue(
+            &loan, &revenue, &insurance, &mut claimed_revenue, &mut claimed_insurance, |_| false, canister,
+        );
+        let matched: Vec<(u64, u64, u64, u64)> = collections.iter()
+            .map(|c| (c.payment_index, c.revenue_entry_id, c.insurance_share, c.treasury_share))
+            .collect();
+        assert_eq!(matched, vec![(0, 7, 600, 1_400), (1, 9, 600, 1_400)]);
+        assert_eq!(collections[1].transaction_id.as_deref(), Some("block-200"));
+        assert_eq!(collections[0].collected_at, 100);
+        
+        // A second run finds every routed share claimed, and collected payments are skipped
+        let again = fee_collections_from_revenue(
+            &loan, &revenue, &insurance, &mut claimed_revenue, &mut claimed_insurance, |_| false, canister,
+        );
+        assert!(again.is_empty());
+        let skipped = fee_collections_from_revenue(
+            &loan, &revenue, &insurance, &mut HashSet::new(), &mut HashSet::new(), |index| index < 2, canister,
+        );
+        assert!(skipped.is_empty());
+    }
+    
+    #[test]
+    fn test_v2_to_v3_matches_fees_routed_wholly_to_one_side() {
+        let canister = Principal::from_slice(&[3u8; 10]);
+        let loan = repaid_loan(vec![fee_payment(100, 2_000), fee_payment(200, 1_000)]);
+        // Payment 0 was routed with no insurance share, payment 1 entirely to the insurance fund
+        let revenue = vec![booked(1, 2_000, 100)];
+        let insurance = vec![insured(2, 4, 1_000, 200)];
+        let collections = fee_collections_from_revenue(
+            &loan, &revenue, &insurance, &mut HashSet::new(), &mut HashSet::new(), |_| false, canister,
+        );
+        let matched: Vec<(u64, u64, u64, u64)> = collections.iter()
+            .map(|c| (c.payment_index, c.revenue_entry_id, c.insurance_share, c.treasury_share))
+            .collect();
+        assert_eq!(matched, vec![(0, 1, 0, 2_000), (1, 0, 1_000, 0)]);
+    }
+    
+    #[test]
+    fn test_v3_to_v4_records_earlier_dust_sweeps() {
+        let bytes = candid::encode_one(v1_balance()).unwrap();
+        let mut swept: InvestorBalance = candid::decode_one(&bytes).unwrap();
+        // 400 of dust swept at the last activity, with no withdrawal recorded for it
+        swept.balance = 0;
+        swept.total_withdrawn = 120_400;
+        swept.last_activity_at = 77;
+        
+        let migrated = backfill_dust_sweep_withdrawal(swept).expect("unrecorded sweep needs a record");
+        let sweep = migrated.withdrawals.last().unwrap();
+        assert_eq!((sweep.amount, sweep.ckbtc_block_index, sweep.timestamp), (400, 0, 77));
+        assert_eq!(migrated.withdrawals.iter().map(|w| w.amount).sum::<u64>(), migrated.total_withdrawn);
+        
+        assert!(backfill_dust_sweep_withdrawal(migrated).is_none());
+    }
+    
+    #[test]
+    fn test_migration_plan_runs_in_order() {
+        let plan = migration_plan(SCHEMA_MIGRATIONS, UNVERSIONED_SCHEMA_VERSION, SCHEMA_VERSION).unwrap();
+        let versions: Vec<u32> = plan.iter().map(|migration| migration.from_version).collect();
+        assert_eq!(versions, (UNVERSIONED_SCHEMA_VERSION..SCHEMA_VERSION).collect::<Vec<_>>());
+        assert!(migration_plan(SCHEMA_MIGRATIONS, SCHEMA_VERSION, SCHEMA_VERSION).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_migration_plan_refuses_downgrade_and_gaps() {
+        assert!(migration_plan(SCHEMA_MIGRATIONS, SCHEMA_VERSION + 1, SCHEMA_VERSION).is_err());
+        
+        static SPARSE: &[SchemaMigration] = &[
+            SchemaMigration { from_version: 1, description: "first", run: || Ok(0) },
+            SchemaMigration { from_version: 3, description: "third", run: || Ok(0) },
+        ];
+        assert!(migration_plan(SPARSE, 1, 4).is_err());
+        assert_eq!(migration_plan(SPARSE, 3, 4).unwrap().len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod farmer_alert_tests {
+    use crate::dashboard_support::{
+        build_loan_alerts, sort_farmer_alerts, AlertSeverity, FarmerAlertKind, LoanAlertSignals,
+    };
+    use crate::types::*;
+    use candid::Principal;
+    
+    const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+    const NOW: u64 = 1_000 * DAY;
+    
+    fn loan(id: u64, due_in_days: i64, apr: u64) -> Loan {
+        Loan {
+            id,
+            borrower: Principal::anonymous(),
+            nft_id: id,
+            collateral_value_btc: 2_000_000,
+            amount_requested: 1_000_000,
+            amount_approved: 1_000_000,
+            apr,
+            status: LoanStatus::Active,
+            created_at: NOW - 90 * DAY,
+            due_date: Some((NOW as i64 + due_in_days * DAY as i64) as u64),
+            total_repaid: 0,
+            repayment_history: Vec::new(),
+            last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
+        }
+    }
+    
+    fn healthy() -> LoanAlertSignals {
+        LoanAlertSignals {
+            health_ratio: Some(1.8),
+            liquidation_price: Some(7_650),
+            liquidation_threshold: 1.0,
+            safe_band: 1.2,
+            grace_period: 30 * DAY,
+            due_soon_window: 7 * DAY,
+            receipt_status: ReceiptExpiryStatus::Current,
+            receipt_expiry: Some(NOW + 200 * DAY),
+            refinance_apr: Some(10),
+            refinance_payoff: 1_000_000,
+            refinance_ltv_limit: Some(1_200_000),
+        }
+    }
+    
+    #[test]
+    fn test_healthy_portfolio_has_no_alerts() {
+        assert!(build_loan_alerts(&loan(1, 60, 10), &healthy(), NOW).is_empty());
+        
+        let mut repaid = loan(2, -60, 15);
+        repaid.status = LoanStatus::Repaid;
+        assert!(build_loan_alerts(&repaid, &LoanAlertSignals { health_ratio: Some(0.5), ..healthy() }, NOW).is_empty());
+    }
+    
+    #[test]
+    fn test_health_bands() {
+        let warning = build_loan_alerts(&loan(1, 60, 10), &LoanAlertSignals { health_ratio: Some(1.1), ..healthy() }, NOW);
+        assert_eq!(warning.len(), 1);
+        assert_eq!((warning[0].kind, warning[0].severity), (FarmerAlertKind::LowHealth, AlertSeverity::Warning));
+        assert!(warning[0].message.contains("7650"));
+        
+        let critical = build_loan_alerts(&loan(1, 60, 10), &LoanAlertSignals { health_ratio: Some(0.9), ..healthy() }, NOW);
+        assert_eq!(critical[0].severity, AlertSeverity::Critical);
+    }
+    
+    #[test]
+    fn test_due_date_and_grace_period() {
+        let due_soon = build_loan_alerts(&loan(1, 3, 10), &healthy(), NOW);
+        assert_eq!((due_soon[0].kind, due_soon[0].severity), (FarmerAlertKind::PaymentDueSoon, AlertSeverity::Info));
+        
+        let in_grace = build_loan_alerts(&loan(1, -5, 10), &healthy(), NOW);
+        assert_eq!((in_grace[0].kind, in_grace[0].severity), (FarmerAlertKind::PaymentOverdue, AlertSeverity::Warning));
+        assert_eq!(in_grace[0].deadline, Some(NOW - 5 * DAY + 30 * DAY));
+        
+        let grace_over = build_loan_alerts(&loan(1, -40, 10), &healthy(), NOW);
+        assert_eq!(grace_over[0].severity, AlertSeverity::Critical);
+    }
+    
+    #[test]
+    fn test_receipt_and_refinance_alerts() {
+        let expiring = LoanAlertSignals { receipt_status: ReceiptExpiryStatus::ExpiringSoon, ..healthy() };
+        assert_eq!(build_loan_alerts(&loan(1, 60, 10), &expiring, NOW)[0].kind, FarmerAlertKind::ReceiptExpiring);
+        
+        let cheaper = build_loan_alerts(&loan(1, 60, 14), &healthy(), NOW);
+        assert_eq!(cheaper[0].kind, FarmerAlertKind::RefinanceAvailable);
+        
+        // No refinance suggestion when refinancing is closed, the loan is overdue or the receipt expired
+        assert!(build_loan_alerts(&loan(1, 60, 14), &LoanAlertSignals { refinance_apr: None, ..healthy() }, NOW).is_empty());
+        let overdue = build_loan_alerts(&loan(1, -5, 14), &healthy(), NOW);
+        assert!(overdue.iter().all(|alert| alert.kind != FarmerAlertKind::RefinanceAvailable));
+        let expired = LoanAlertSignals { receipt_status: ReceiptExpiryStatus::Expired, ..healthy() };
+        let expired_alerts = build_loan_alerts(&loan(1, 60, 14), &expired, NOW);
+        assert_eq!(expired_alerts.len(), 1);
+        assert_eq!(expired_alerts[0].kind, FarmerAlertKind::ReceiptExpired);
+        
+        // Nor when the payoff is above what the collateral supports, which refinance_loan rejects
+        let underwater = LoanAlertSignals { refinance_ltv_limit: Some(900_000), ..healthy() };
+        assert!(build_loan_alerts(&loan(1, 60, 14), &underwater, NOW).is_empty());
+        let unpriced = LoanAlertSignals { refinance_ltv_limit: None, ..healthy() };
+        assert!(build_loan_alerts(&loan(1, 60, 14), &unpriced, NOW).is_empty());
+    }
+    
+    #[test]
+    fn test_alerts_ordered_by_severity() {
+        let mut alerts = build_loan_alerts(&loan(1, 60, 14), &healthy(), NOW);
+        alerts.extend(build_loan_alerts(&loan(2, -40, 10), &LoanAlertSignals { health_ratio: Some(1.1), ..healthy() }, NOW));
+        alerts.extend(build_loan_alerts(&loan(3, 3, 10), &healthy(), NOW));
+        sort_farmer_alerts(&mut alerts);
+        
+        let order: Vec<(AlertSeverity, u64)> = alerts.iter().map(|alert| (alert.severity, alert.loan_id)).collect();
+        assert_eq!(order, vec![
+            (AlertSeverity::Critical, 2),
+            (AlertSeverity::Warning, 2),
+            (AlertSeverity::Info, 1),
+            (AlertSeverity::Info, 3),
+        ]);
+    }
+}
+
+#[cfg(test)]
+mod transaction_limit_tests {
+    use crate::liquidity_management::{
+        check_transaction_limits, daily_capacity_resets_at, entries_in_window, release_transaction_usage,
+        reserve_transaction_usage, scaled_transaction_limit, transaction_usage_entries, TransactionDirection,
+        TransactionLimitKind, TRANSACTION_LIMIT_WINDOW_NANOS, UNLIMITED,
+    };
+    use crate::types::Asset;
+    use crate::user_management::KycTier;
+    use candid::Principal;
+    
+    const HOUR: u64 = 60 * 60 * 1_000_000_000;
+    
+    #[test]
+    fn test_unlimited_defaults_never_block() {
+        let window = vec![(0, u64::MAX / 2), (HOUR, u64::MAX / 2)];
+        assert!(check_transaction_limits(TransactionDirection::Deposit, u64::MAX, UNLIMITED, UNLIMITED, &window).is_none());
+        assert_eq!(scaled_transaction_limit(UNLIMITED, &KycTier::Verified, 50_000), UNLIMITED);
+    }
+    
+    #[test]
+    fn test_single_transaction_limit() {
+        let breach = check_transaction_limits(TransactionDirection::Withdrawal, 600_000, 500_000, UNLIMITED, &[]).unwrap();
+        assert_eq!(breach.kind, TransactionLimitKind::SingleWithdrawal);
+        assert_eq!(breach.resets_at, None);
+        assert!(check_transaction_limits(TransactionDirection::Withdrawal, 500_000, 500_000, UNLIMITED, &[]).is_none());
+    }
+    
+    #[test]
+    fn test_daily_limit_reports_reset_time() {
+        // 1_000_000 per day; 400_000 at hour 1 and 500_000 at hour 5 are still counted at hour 10
+        let window = vec![(HOUR, 400_000), (5 * HOUR, 500_000)];
+        assert!(check_transaction_limits(TransactionDirection::Deposit, 100_000, UNLIMITED, 1_000_000, &window).is_none());
+        
+        let breach = check_transaction_limits(TransactionDirection::Deposit, 300_000, UNLIMITED, 1_000_000, &window).unwrap();
+        assert_eq!(breach.kind, TransactionLimitKind::DailyDeposit);
+        assert_eq!((breach.used_in_window, breach.remaining), (900_000, 100_000));
+        // Room opens once the hour-1 deposit leaves the window
+        assert_eq!(breach.resets_at, Some(HOUR + TRANSACTION_LIMIT_WINDOW_NANOS));
+        
+        // A larger attempt has to wait for both entries to age out
+        assert_eq!(daily_capacity_resets_at(&window, 1_000_000, 700_000), Some(5 * HOUR + TRANSACTION_LIMIT_WINDOW_NANOS));
+        assert_eq!(daily_capacity_resets_at(&window, 1_000_000, 1_100_000), None);
+    }
+    
+    #[test]
+    fn test_window_ages_out_old_transactions() {
+        let entries = vec![(0, 700_000), (10 * HOUR, 200_000)];
+        assert_eq!(entries_in_window(&entries, 23 * HOUR).len(), 2);
+        // Exactly 24 hours later the first entry no longer counts
+        let later = entries_in_window(&entries, TRANSACTION_LIMIT_WINDOW_NANOS);
+        assert_eq!(later, vec![(10 * HOUR, 200_000)]);
+        assert!(check_transaction_limits(TransactionDirection::Deposit, 800_000, UNLIMITED, 1_000_000, &later).is_none());
+    }
+    
+    #[test]
+    fn test_verified_tier_scaling() {
+        assert_eq!(scaled_transaction_limit(1_000_000, &KycTier::Verified, 25_000), 2_500_000);
+        assert_eq!(scaled_transaction_limit(1_000_000, &KycTier::Basic, 25_000), 1_000_000);
+        assert_eq!(scaled_transaction_limit(u64::MAX - 1, &KycTier::Verified, 100_000), UNLIMITED);
+    }
+    
+    #[test]
+    fn test_reserved_usage_counts_until_released() {
+        let investor = Principal::from_slice(&[37; 29]);
+        let first = reserve_transaction_usage(investor, TransactionDirection::Deposit, Asset::CkBtc, 400_000, HOUR);
+        // A second deposit in flight at the same time sees the first one's reservation
+        let second = reserve_transaction_usage(investor, TransactionDirection::Deposit, Asset::CkBtc, 400_000, HOUR);
+        assert_eq!(first, second);
+        assert_eq!(transaction_usage_entries(investor, TransactionDirection::Deposit).len(), 2);
+        
+        // A failed deposit gives back only its own entry, and never touches withdrawals
+        release_transaction_usage(investor, TransactionDirection::Deposit, Asset::CkBtc, 400_000, second);
+        let remaining = transaction_usage_entries(investor, TransactionDirection::Deposit);
+        assert_eq!(remaining.len(), 1);
+        assert_eq!((remaining[0].amount, remaining[0].timestamp), (400_000, HOUR));
+        release_transaction_usage(investor, TransactionDirection::Withdrawal, Asset::CkBtc, 400_000, first);
+        assert_eq!(transaction_usage_entries(investor, TransactionDirection::Deposit).len(), 1);
+        assert!(transaction_usage_entries(investor, TransactionDirection::Withdrawal).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod fee_collection_tests {
+    use candid::Principal;
+    use crate::types::*;
+    use crate::loan_repayment::{reconcile_fee_collections, repayment_fee_due, uncollected_repayment_fees};
+    
+    fn payment(timestamp: u64, fee: Option<u64>, source: Option<RepaymentSource>) -> Payment {
+        Payment {
+            amount: 100_000,
+            timestamp,
+            payment_type: PaymentType::Mixed,
+            transaction_id: Some(format!("block-{}", timestamp)),
+            allocation: fee.map(|protocol_fee_amount| PaymentBreakdown {
+                principal_amount: 80_000,
+                interest_amount: 20_000 - protocol_fee_amount,
+                protocol_fee_amount,
+                penalty_amount: 0,
+                total_amount: 100_000,
+            }),
+            source,
+        }
+    }
+    
+    fn loan(id: u64, asset: Option<Asset>, repayment_history: Vec<Payment>) -> Loan {
+        Loan {
+            id,
+            borrower: Principal::anonymous(),
+            nft_id: id,
+            collateral_value_btc: 2_000_000,
+            amount_requested: 1_000_000,
+            amount_approved: 1_000_000,
+            apr: 10,
+            status: LoanStatus::Active,
+            created_at: 0,
+            due_date: None,
+            total_repaid: 0,
+            repayment_history,
+            last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset,
+        }
+    }
+    
+    fn collection(loan_id: u64, payment_index: u64, insurance_share: u64, treasury_share: u64, revenue_entry_id: u64) -> RepaymentFeeCollection {
+        RepaymentFeeCollection {
+            loan_id,
+            payment_index,
+            fee_amount: insurance_share + treasury_share,
+            insurance_share,
+            treasury_share,
+            revenue_entry_id,
+            transaction_id: None,
+            collected_at: 0,
+            collected_by: Principal::anonymous(),
+        }
+    }
+    
+    fn revenue(id: u64, source_loan_id: u64, amount: u64) -> RevenueEntry {
+        RevenueEntry {
+            id,
+            source_loan_id,
+            amount,
+            revenue_type: RevenueType::ProtocolFee,
+            source_canister: Principal::anonymous(),
+            timestamp: 0,
+            transaction_hash: None,
+            status: TransactionStatus::Completed,
+        }
+    }
+    
+    #[test]
+    fn test_only_ckbtc_fee_bearing_payments_owe_fees() {
+        let ckbtc = loan(1, None, vec![
+            payment(10, Some(2_000), Some(RepaymentSource::CkBtc)),
+            payment(20, Some(0), Some(RepaymentSource::CkBtc)),
+            payment(30, Some(2_000), Some(RepaymentSource::OffChain)),
+            payment(40, None, None),
+        ]);
+        assert_eq!(repayment_fee_due(&ckbtc, 0), Some(2_000));
+        assert_eq!(repayment_fee_due(&ckbtc, 1), None);
+        assert_eq!(repayment_fee_due(&ckbtc, 2), None);
+        assert_eq!(repayment_fee_due(&ckbtc, 3), None);
+        assert_eq!(repayment_fee_due(&ckbtc, 4), None);
+        
+        let icp = loan(2, Some(Asset::Icp), vec![payment(10, Some(2_000), Some(RepaymentSource::CkBtc))]);
+        assert_eq!(repayment_fee_due(&icp, 0), None);
+    }
+    
+    #[test]
+    fn test_collected_payments_are_skipped() {
+        let loans = vec![
+            loan(1, None, vec![payment(50, Some(1_000), None), payment(10, Some(2_000), Some(RepaymentSource::CkBtc))]),
+            loan(2, None, vec![payment(30, Some(3_000), Some(RepaymentSource::CkBtc))]),
+        ];
+        let uncollected = uncollected_repayment_fees(&loans, |_, _| false);
+        let references: Vec<(u64, u64)> = uncollected.iter().map(|fee| (fee.loan_id, fee.payment_index)).collect();
+        // Oldest first
+        assert_eq!(references, vec![(1, 1), (2, 0), (1, 0)]);
+        assert_eq!(uncollected[0].fee_amount, 2_000);
+        assert_eq!(uncollected[0].transaction_id, Some("block-10".to_string()));
+        
+        let remaining = uncollected_repayment_fees(&loans, |loan_id, index| (loan_id, index) == (2, 0));
+        assert_eq!(remaining.len(), 2);
+        assert!(remaining.iter().all(|fee| fee.loan_id != 2));
+    }
+    
+    #[test]
+    fn test_reconciliation_matches_revenue_entries() {
+        let collections = vec![collection(1, 0, 500, 1_500, 7), collection(2, 0, 800, 0, 0)];
+        let report = reconcile_fee_collections(&collections, |id| (id == 7).then(|| revenue(7, 1, 1_500)));
+        assert!(report.reconciled);
+        assert_eq!(report.total_collected, 2_800);
+        assert_eq!((report.total_treasury_share, report.total_recorded_revenue), (1_500, 1_500));
+    }
+    
+    #[test]
+    fn test_reconciliation_flags_mismatches() {
+        let collections = vec![
+            collection(1, 0, 500, 1_500, 7),
+            collection(1, 1, 500, 1_500, 7),
+            collection(3, 0, 500, 1_500, 9),
+        ];
+        // Entry 7 is claimed twice and entry 9 is missing
+        let report = reconcile_fee_collections(&collections, |id| (id == 7).then(|| revenue(7, 1, 1_500)));
+        assert!(!report.reconciled);
+        assert_eq!(report.discrepancies.len(), 2);
+        
+        // Wrong loan and amount on the entry
+        let report = reconcile_fee_collections(&collections[..1], |_| Some(revenue(7, 4, 1_200)));
+        assert!(!report.reconciled);
+        assert_eq!(report.discrepancies.len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod pool_apy_tests {
+    use crate::liquidity_management::pool_apy_bps;
+    use crate::types::{LiquidityPool, PoolApyParameters};
+    
+    fn pool(total_liquidity: u64, available_liquidity: u64, total_borrowed: u64, total_repaid: u64) -> LiquidityPool {
+        LiquidityPool {
+            total_liquidity,
+            available_liquidity,
+            total_borrowed,
+            total_repaid,
+            utilization_rate: 0,
+            total_investors: 1,
+            apy: 0,
+            created_at: 0,
+            updated_at: 0,
+            reserved_liquidity: None,
+        }
+    }
+    
+    #[test]
+    fn test_default_model_matches_previous_rates() {
+        let params = PoolApyParameters::default();
+        assert_eq!(pool_apy_bps(&pool(0, 0, 0, 0), &params), 300);
+        // 70% utilization, 95% repaid: 3% + 3.5% + 2%
+        assert_eq!(pool_apy_bps(&pool(1_000, 300, 700, 665), &params), 850);
+        // 80% repaid earns the low tier
+        assert_eq!(pool_apy_bps(&pool(1_000, 300, 700, 560), &params), 750);
+    }
+    
+    #[test]
+    fn test_apy_stays_inside_configured_band() {
+        let bands = [
+            PoolApyParameters::default(),
+            PoolApyParameters { base_apy_bps: 500, max_apy_bps: 600, utilization_bonus_per_pct: 100, ..Default::default() },
+            PoolApyParameters { base_apy_bps: 0, max_apy_bps: 0, ..Default::default() },
+            PoolApyParameters {
+                base_apy_bps: 200,
+                max_apy_bps: 10_000,
+                utilization_bonus_per_pct: u64::MAX,
+                repayment_bonus_high_bps: u64::MAX,
+                repayment_bonus_low_bps: u64::MAX,
+                ..Default::default()
+            },
+        ];
+        let pools = [
+            pool(0, 0, 0, 0),
+            pool(u64::MAX, 0, u64::MAX, u64::MAX),
+            pool(u64::MAX, u64::MAX, 1, 0),
+            pool(1_000, 2_000, 0, 0), // More available than total
+            pool(1, 0, 1, u64::MAX),
+            pool(1_000_000, 500_000, 500_000, 400_000),
+        ];
+        for params in &bands {
+            for pool in &pools {
+                let apy = pool_apy_bps(pool, params);
+                assert!(apy >= params.base_apy_bps && apy <= params.max_apy_bps, "{} outside {:?}", apy, params);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_parameter_changes_keep_floor_below_cap() {
+        let params = PoolApyParameters::default();
+        assert!(params.with_parameter("base_apy_bps", 1_500).unwrap().validate().is_ok());
+        assert!(params.with_parameter("base_apy_bps", 1_600).unwrap().validate().is_err());
+        assert!(params.with_parameter("max_apy_bps", 200).unwrap().validate().is_err());
+        assert!(params.with_parameter("repayment_bonus_low_threshold", 95).unwrap().validate().is_err());
+        assert!(params.with_parameter("loan_to_value_ratio", 50).is_none());
+    }
+}
+
+#[cfg(test)]
+mod collateral_attestation_tests {
+    use crate::rwa_nft::{check_reattestation_ltv, record_attestation};
+    use crate::storage::load_attestation_history;
+    use crate::types::{AttestationRecord, Loan, LoanStatus};
+    use candid::Principal;
+    
+    const ONE_YEAR: u64 = 31_557_600 * 1_000_000_000;
+    
+    fn loan(amount_approved: u64, collateral_value_btc: u64, total_repaid: u64) -> Loan {
+        Loan {
+            id: 11,
+            borrower: Principal::anonymous(),
+            nft_id: 5,
+            collateral_value_btc,
+            amount_requested: amount_approved,
+            amount_approved,
+            apr: 10,
+            status: LoanStatus::Active,
+            created_at: 0,
+            due_date: None,
+            total_repaid,
+            repayment_history: Vec::new(),
+            last_payment_date: None,
+            region: None,
+            rate_type: None,
+            asset: None,
+        }
+    }
+    
+    fn record(new_hash: &str) -> AttestationRecord {
+        AttestationRecord {
+            version: 0,
+            previous_hash: "old".to_string(),
+            new_hash: new_hash.to_string(),
+            attestation_doc_ref: "WR-2026-001".to_string(),
+            attestor: Principal::anonymous(),
+            timestamp: 1,
+            receipt_expiry: None,
+        }
+    }
+    
+    #[test]
+    fn test_reattestation_ltv_counts_accrued_interest() {
+        // Principal alone sits exactly at the 60% limit
+        let at_limit = loan(600_000, 1_000_000, 0);
+        assert!(check_reattestation_ltv(&at_limit, 60, 0).is_ok());
+        // A year of 10% interest pushes the debt past it
+        let err = check_reattestation_ltv(&at_limit, 60, ONE_YEAR).unwrap_err();
+        assert!(err.contains("outside LTV"), "{}", err);
+        // Repayments bring it back inside
+        assert!(check_reattestation_ltv(&loan(600_000, 1_000_000, 100_000), 60, ONE_YEAR).is_ok());
+    }
+    
+    #[test]
+    fn test_reattestations_append_versioned_history() {
+        let token_id = 9_001;
+        assert_eq!(load_attestation_history(token_id).attestation_version, 0);
+        
+        let first = record_attestation(token_id, record("hash-1"));
+        assert_eq!(first.attestation_version, 1);
+        let second = record_attestation(token_id, record("hash-2"));
+        assert_eq!(second.attestation_version, 2);
+        
+        let stored = load_attestation_history(token_id);
+        assert_eq!(stored.records.iter().map(|r| r.version).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(stored.records[1].new_hash, "hash-2");
+        assert!(load_attestation_history(token_id + 1).records.is_empty());
+    }
+}
@@ -3,6 +3,7 @@ mod rwa_nft_tests {
     use candid::Principal;
     use crate::types::*;
     use crate::helpers::*;
+    use crate::rwa_nft::validate_transfer;
 
     // Helper function to create test principal
     fn create_test_principal(id: u8) -> Principal {
@@ -119,7 +120,7 @@ mod rwa_nft_tests {
         let (legal_doc_hash, valuation_idr, asset_description) = extract_metadata_values(&metadata);
         
         assert_eq!(legal_doc_hash, "a".repeat(64));
-        assert_eq!(valuation_idr, 300_000_000);
+        assert_eq!(valuation_idr, Idr(300_000_000));
         assert_eq!(asset_description, "Gabah, 20 Ton, Kualitas A");
     }
 
@@ -133,7 +134,7 @@ mod rwa_nft_tests {
         let (legal_doc_hash, valuation_idr, asset_description) = extract_metadata_values(&metadata);
         
         assert_eq!(legal_doc_hash, "");
-        assert_eq!(valuation_idr, 500_000_000);
+        assert_eq!(valuation_idr, Idr(500_000_000));
         assert_eq!(asset_description, "");
     }
 
@@ -217,6 +218,73 @@ mod rwa_nft_tests {
         assert_eq!(transfer_request.created_at_time, Some(mock_time));
     }
 
+    fn create_nft(owner: Principal, is_locked: bool, loan_id: Option<u64>) -> RWANFTData {
+        RWANFTData {
+            token_id: 7,
+            owner,
+            metadata: create_valid_metadata(),
+            created_at: mock_time(),
+            updated_at: mock_time(),
+            is_locked,
+            loan_id,
+        }
+    }
+
+    #[test]
+    fn test_transfer_rejected_while_collateralized() {
+        let owner = create_test_principal(1);
+        
+        match validate_transfer(&create_nft(owner, true, Some(42)), &owner) {
+            TransferResult::CollateralLocked { token_id, loan_id } => {
+                assert_eq!(token_id, 7);
+                assert_eq!(loan_id, Some(42));
+            },
+            other => panic!("Expected CollateralLocked, got {:?}", other),
+        }
+        
+        // A dangling loan reference blocks transfer even without the lock flag
+        assert!(matches!(
+            validate_transfer(&create_nft(owner, false, Some(42)), &owner),
+            TransferResult::CollateralLocked { .. }
+        ));
+    }
+
+    #[test]
+    fn test_transfer_allowed_after_repayment() {
+        let owner = create_test_principal(1);
+        
+        // Repayment unlocks the NFT and clears the loan reference
+        assert!(matches!(validate_transfer(&create_nft(owner, false, None), &owner), TransferResult::Ok));
+        
+        // Ownership is still enforced
+        assert!(matches!(
+            validate_transfer(&create_nft(owner, false, None), &create_test_principal(2)),
+            TransferResult::Err(_)
+        ));
+    }
+
+    #[test]
+    fn test_repayment_unlock_makes_nft_transferable() {
+        let owner = create_test_principal(1);
+        let mut nft = create_nft(owner, true, Some(42));
+        nft.token_id = 4_201;
+        crate::storage::RWA_NFTS.with(|nfts| {
+            nfts.borrow_mut().insert(nft.token_id, nft.clone());
+        });
+        assert!(matches!(validate_transfer(&nft, &owner), TransferResult::CollateralLocked { .. }));
+        assert!(crate::rwa_nft::ensure_collateral_settleable(4_201, 42).is_ok());
+
+        // Full repayment releases the collateral through unlock_nft
+        crate::storage::unlock_nft(4_201).unwrap();
+
+        let released = crate::storage::get_nft_by_token_id(4_201).unwrap();
+        assert!(!released.is_locked);
+        assert_eq!(released.loan_id, None);
+        assert!(matches!(validate_transfer(&released, &owner), TransferResult::Ok));
+        // A released NFT can no longer be settled to a liquidation buyer
+        assert!(crate::rwa_nft::ensure_collateral_settleable(4_201, 42).is_err());
+    }
+
     #[test]
     fn test_collateral_status_transitions() {
         // Test valid status transitions
@@ -324,7 +392,7 @@ mod rwa_nft_tests {
             nft_token_id: 1,
             owner,
             loan_id: Some(123),
-            valuation_idr: 300_000_000,
+            valuation_idr: Idr(300_000_000),
             asset_description: "Gabah, 20 Ton, Kualitas A".to_string(),
             legal_doc_hash: "a".repeat(64),
             status: CollateralStatus::Locked,
@@ -336,7 +404,7 @@ mod rwa_nft_tests {
         assert_eq!(collateral_record.nft_token_id, 1);
         assert_eq!(collateral_record.owner, owner);
         assert_eq!(collateral_record.loan_id, Some(123));
-        assert_eq!(collateral_record.valuation_idr, 300_000_000);
+        assert_eq!(collateral_record.valuation_idr, Idr(300_000_000));
         assert_eq!(collateral_record.asset_description, "Gabah, 20 Ton, Kualitas A");
         assert_eq!(collateral_record.legal_doc_hash, "a".repeat(64));
         assert_eq!(collateral_record.status, CollateralStatus::Locked);
@@ -389,7 +457,7 @@ mod rwa_nft_tests {
         assert!(result.is_ok());
         
         let (_, valuation_idr, _) = extract_metadata_values(&metadata);
-        assert_eq!(valuation_idr, u64::MAX);
+        assert_eq!(valuation_idr, Idr(u64::MAX));
     }
 
     #[test]
@@ -419,7 +487,7 @@ mod rwa_nft_tests {
         ];
         
         let (_, valuation_idr, _) = extract_metadata_values(&metadata);
-        assert_eq!(valuation_idr, 500_000_000); // Should use the last value
+        assert_eq!(valuation_idr, Idr(500_000_000)); // Should use the last value
     }
 }
 
@@ -456,8 +524,44 @@ mod performance_tests {
         for _ in 0..1000 {
             let (legal_doc_hash, valuation_idr, asset_description) = extract_metadata_values(&metadata);
             assert!(!legal_doc_hash.is_empty());
-            assert!(valuation_idr > 0);
+            assert!(valuation_idr > Idr(0));
             assert!(!asset_description.is_empty());
         }
     }
+    
+    #[test]
+    fn test_icrc7_token_metadata_and_enumeration() {
+        use crate::rwa_nft::{build_token_metadata, icrc7_tokens, icrc7_total_supply, icrc10_supported_standards};
+        use crate::storage::RWA_NFTS;
+        
+        let mut metadata = create_valid_metadata();
+        metadata.push(("icrc7:name".to_string(), MetadataValue::Text("Spoofed".to_string())));
+        for token_id in [10_001u64, 10_002, 10_003] {
+            RWA_NFTS.with(|nfts| nfts.borrow_mut().insert(token_id, RWANFTData {
+                token_id,
+                owner: create_test_principal(1),
+                metadata: metadata.clone(),
+                created_at: mock_time(),
+                updated_at: mock_time(),
+                is_locked: token_id == 10_002,
+                loan_id: if token_id == 10_002 { Some(7) } else { None },
+            }));
+        }
+        
+        // Base fields come first and caller-supplied icrc7 keys are ignored
+        let nft = RWA_NFTS.with(|nfts| nfts.borrow().get(&10_002)).unwrap();
+        let token_metadata = build_token_metadata(&nft);
+        assert_eq!(token_metadata[0].0, "icrc7:name");
+        assert_eq!(token_metadata.iter().filter(|(key, _)| key == "icrc7:name").count(), 1);
+        assert!(token_metadata.iter().any(|(key, _)| key == "rwa:legal_doc_hash"));
+        assert!(token_metadata.iter().any(|(key, value)| key == "rwa:loan_id" && matches!(value, MetadataValue::Nat(7))));
+        
+        // Pagination resumes strictly after prev
+        assert!(icrc7_total_supply() >= 3);
+        assert_eq!(icrc7_tokens(Some(10_000), Some(2)), vec![10_001, 10_002]);
+        assert_eq!(icrc7_tokens(Some(10_002), Some(2)), vec![10_003]);
+        assert!(icrc7_tokens(Some(u64::MAX), None).is_empty());
+        
+        assert!(icrc10_supported_standards().iter().any(|standard| standard.name == "ICRC-7"));
+    }
 }
\ No newline at end of file
@@ -60,6 +60,10 @@ mod basic_tests {
             email: None,
             phone: None,
             profile_completed: false,
+            preferred_language: None,
+            kyc_tier: None,
+            kyc_reference: None,
+            kyc_updated_at: None,
         };
         
         assert_eq!(user.id, principal);
@@ -97,6 +101,10 @@ mod basic_tests {
             email: None,
             phone: None,
             profile_completed: false,
+            preferred_language: None,
+            kyc_tier: None,
+            kyc_reference: None,
+            kyc_updated_at: None,
         };
         
         let success_result = UserResult::Ok(user);
@@ -413,7 +421,7 @@ mod user_profile_tests {
             UserResult::Ok(user) => {
                 assert_eq!(user.btc_address, Some("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".to_string()));
                 assert_eq!(user.email, Some("farmer@agrilends.com".to_string()));
-                assert_eq!(user.phone, Some("+62-812-3456-7890".to_string()));
+                assert_eq!(user.phone, Some("+6281234567890".to_string()));
                 assert!(user.profile_completed);
             }
             UserResult::Err(msg) => panic!("Expected success, got error: {}", msg),
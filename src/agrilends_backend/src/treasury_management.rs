@@ -84,9 +84,20 @@ pub enum RevenueType {
     CollateralProcessingFee,
     OracleServiceFee,
     GovernanceFee,
+    DepositFee,
+    WithdrawalFee,
+    PromotionalCreditExpense, // Outflow: treasury-funded investor bonuses
+    DisbursementFeeExpense,   // Outflow: Bitcoin network fees topped up on loan disbursements
     OtherRevenue(String),
 }
 
+impl RevenueType {
+    /// Outflows logged alongside revenue, left out of revenue totals
+    pub fn is_expense(&self) -> bool {
+        matches!(self, RevenueType::PromotionalCreditExpense | RevenueType::DisbursementFeeExpense)
+    }
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub enum TransactionStatus {
     Pending,
@@ -133,6 +144,169 @@ pub enum CanisterType {
     Testing,        // Testing and development canisters
 }
 
+// Periodic cycle balance samples used for burn-rate forecasting
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CycleBalanceSample {
+    pub timestamp: u64,
+    pub cycles: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CycleSampleHistory {
+    pub canister_name: String,
+    pub canister_id: Principal,
+    pub samples: Vec<CycleBalanceSample>,
+    // Last low-runway alert; cleared once the runway recovers
+    pub last_runway_alert_at: Option<u64>,
+}
+
+impl ic_stable_structures::Storable for CycleSampleHistory {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        std::borrow::Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CycleForecast {
+    pub canister_name: String,
+    pub canister_id: Principal,
+    pub current_cycles: u64,
+    pub samples_used: u32,
+    pub daily_burn_rate: u64,
+    pub days_until_depletion: Option<f64>, // None when the balance is not decreasing
+    pub target_runway_days: u64,
+    pub recommended_top_up: u64,
+    pub below_alert_threshold: bool,
+    pub generated_at: u64,
+}
+
+// Multi-signature emergency withdrawal requests
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum EmergencyWithdrawalStatus {
+    Pending,
+    Executing,
+    Executed,
+    Failed,
+    Expired,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct EmergencyWithdrawalRequest {
+    pub id: u64,
+    pub amount: u64,
+    pub destination: Principal,
+    pub reason: String,
+    pub proposer: Principal,
+    pub approvals: Vec<Principal>, // Distinct SuperAdmins, proposer included
+    pub created_at: u64,
+    pub expires_at: u64,
+    pub status: EmergencyWithdrawalStatus,
+    pub transaction_id: Option<String>,
+    pub failure_reason: Option<String>,
+}
+
+impl ic_stable_structures::Storable for EmergencyWithdrawalRequest {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        std::borrow::Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+}
+
+// Insurance fund: loss-absorbing buffer funded by a share of protocol fees
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct InsuranceFundState {
+    pub balance: u64,
+    pub total_contributions: u64,
+    pub total_payouts: u64,
+    pub updated_at: u64,
+}
+
+impl ic_stable_structures::Storable for InsuranceFundState {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        std::borrow::Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum InsuranceFundEntryKind {
+    FeeContribution,
+    LossCoverage,
+    DustDonation, // Below-minimum investor residuals swept into the fund
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct InsuranceFundEntry {
+    pub id: u64,
+    pub kind: InsuranceFundEntryKind,
+    pub loan_id: u64,
+    pub amount: u64,
+    pub balance_after: u64,
+    pub timestamp: u64,
+}
+
+impl ic_stable_structures::Storable for InsuranceFundEntry {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        std::borrow::Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+}
+
+// Treasury outflow, kept apart from the revenue log so revenue totals and listings only see inflows
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct TreasuryExpense {
+    pub id: u64,
+    pub expense_type: RevenueType, // One of the `is_expense` variants
+    pub amount: u64,
+    pub source_loan_id: u64,       // 0 when not tied to a loan
+    pub reference: String,
+    pub timestamp: u64,
+}
+
+impl ic_stable_structures::Storable for TreasuryExpense {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        std::borrow::Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ProtocolCycleForecast {
+    pub canisters: Vec<CycleForecast>,
+    pub total_current_cycles: u64,
+    pub total_daily_burn_rate: u64,
+    pub min_days_until_depletion: Option<f64>,
+    pub total_recommended_top_up: u64,
+    pub canisters_below_threshold: u32,
+    pub generated_at: u64,
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct CycleTransaction {
     pub id: u64,
@@ -268,6 +442,16 @@ const AUTO_TOP_UP_PERCENTAGE: u64 = 150; // Top up to 150% of threshold
 const CYCLE_MONITORING_INTERVAL_SECONDS: u64 = 3600; // Check every hour
 const MIN_TREASURY_BALANCE_FOR_OPERATIONS: u64 = 100_000; // 0.001 BTC minimum
 const CKBTC_TO_CYCLES_EXCHANGE_BUFFER: f64 = 1.1; // 10% buffer for exchange rate fluctuation
+const MAX_CYCLE_SAMPLES_PER_CANISTER: usize = 168; // 7 days of hourly samples
+const DEFAULT_CYCLE_TARGET_RUNWAY_DAYS: u64 = 30;
+const DEFAULT_CYCLE_RUNWAY_ALERT_DAYS: u64 = 7;
+const CYCLE_RUNWAY_ALERT_COOLDOWN_NANOS: u64 = 24 * 60 * 60 * 1_000_000_000; // Repeat a low-runway alert daily, not hourly
+const NANOS_PER_DAY: f64 = 24.0 * 60.0 * 60.0 * 1_000_000_000.0;
+const DEFAULT_EMERGENCY_WITHDRAW_THRESHOLD: u64 = 2;
+const DEFAULT_EMERGENCY_WITHDRAW_WINDOW_HOURS: u64 = 24;
+const NANOS_PER_HOUR: u64 = 60 * 60 * 1_000_000_000;
+const DEFAULT_INSURANCE_FUND_FEE_BPS: u64 = 2000; // 20% of protocol fees
+const DEFAULT_INSURANCE_FUND_TARGET: u64 = 1_000_000_000; // 10 BTC in satoshi
 
 // Treasury storage
 thread_local! {
@@ -287,6 +471,31 @@ thread_local! {
         StableBTreeMap::init(get_treasury_memory(23))
     );
     
+    static CYCLE_SAMPLES: RefCell<StableBTreeMap<String, CycleSampleHistory, Memory>> = RefCell::new(
+        StableBTreeMap::init(crate::storage::get_memory_by_id(MemoryId::new(70)))
+    );
+    
+    static EMERGENCY_WITHDRAWALS: RefCell<StableBTreeMap<u64, EmergencyWithdrawalRequest, Memory>> = RefCell::new(
+        StableBTreeMap::init(crate::storage::get_memory_by_id(MemoryId::new(72)))
+    );
+    
+    static INSURANCE_FUND: RefCell<StableBTreeMap<u8, InsuranceFundState, Memory>> = RefCell::new(
+        StableBTreeMap::init(crate::storage::get_memory_by_id(MemoryId::new(75)))
+    );
+    
+    static INSURANCE_FUND_HISTORY: RefCell<StableBTreeMap<u64, InsuranceFundEntry, Memory>> = RefCell::new(
+        StableBTreeMap::init(crate::storage::get_memory_by_id(MemoryId::new(76)))
+    );
+    
+    // Latest ckBTC balance reconciliation under key 0
+    static LAST_RECONCILIATION: RefCell<StableBTreeMap<u8, BalanceReconciliation, Memory>> = RefCell::new(
+        StableBTreeMap::init(crate::storage::get_memory_by_id(MemoryId::new(80)))
+    );
+    
+    static TREASURY_EXPENSES: RefCell<StableBTreeMap<u64, TreasuryExpense, Memory>> = RefCell::new(
+        StableBTreeMap::init(crate::storage::get_memory_by_id(MemoryId::new(73)))
+    );
+    
     static REVENUE_COUNTER: RefCell<u64> = RefCell::new(0);
     static CYCLE_TX_COUNTER: RefCell<u64> = RefCell::new(0);
 }
@@ -399,9 +608,10 @@ pub async fn collect_fees(
     revenue_type: RevenueType
 ) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "collect_fees")?;
     
     // Security: Only loan management canister can collect fees
-    if !is_loan_manager(&caller) {
+    if crate::validation::require_role(&caller, crate::validation::CallerRole::LoanManager).is_err() {
         log_action(
             "TREASURY_UNAUTHORIZED_ACCESS",
             &format!("Unauthorized attempt to collect fees by {}", caller.to_text()),
@@ -473,10 +683,162 @@ pub async fn collect_fees(
     Ok(format!("Successfully collected {} satoshi in treasury", amount))
 }
 
+/// Record a liquidity pool fee (deposit/withdrawal) as treasury revenue.
+/// Internal entry point for liquidity management; pool fees are not tied to a loan.
+pub fn record_pool_fee_revenue(amount: u64, revenue_type: RevenueType) -> Result<u64, String> {
+    record_fee_revenue(amount, 0, revenue_type)
+}
+
+/// Record fee revenue attributed to `source_loan_id` (0 for pool fees); returns the revenue
+/// entry ID, or 0 when there is nothing to record
+fn record_fee_revenue(amount: u64, source_loan_id: u64, revenue_type: RevenueType) -> Result<u64, String> {
+    if amount == 0 {
+        return Ok(0);
+    }
+    
+    let revenue_id = REVENUE_COUNTER.with(|counter| {
+        let mut counter = counter.borrow_mut();
+        *counter += 1;
+        *counter
+    });
+    
+    let revenue_entry = RevenueEntry {
+        id: revenue_id,
+        source_loan_id,
+        amount,
+        revenue_type: revenue_type.clone(),
+        source_canister: ic_cdk::api::canister_self(),
+        timestamp: time(),
+        transaction_hash: None,
+        status: TransactionStatus::Completed,
+        processing_fee: 0,
+        net_amount: amount,
+    };
+    
+    REVENUE_LOG.with(|log| {
+        log.borrow_mut().insert(revenue_id, revenue_entry);
+    });
+    
+    let mut treasury_state = get_treasury_state();
+    treasury_state.balance_ckbtc += amount;
+    treasury_state.total_fees_collected += amount;
+    treasury_state.emergency_reserve = (treasury_state.balance_ckbtc * EMERGENCY_RESERVE_PERCENTAGE) / 100;
+    treasury_state.updated_at = time();
+    update_treasury_state(treasury_state)?;
+    
+    log_action(
+        "TREASURY_POOL_FEE_COLLECTED",
+        &format!("Collected {} satoshi pool fee as {:?}", amount, revenue_type),
+        true,
+    );
+    
+    Ok(revenue_id)
+}
+
+/// Treasury ckBTC that may be spent without touching the emergency reserve
+pub fn treasury_spendable_balance() -> u64 {
+    let treasury_state = get_treasury_state();
+    treasury_state.balance_ckbtc.saturating_sub(treasury_state.emergency_reserve)
+}
+
+/// Take `amount` out of the treasury and record it in the expense log
+fn record_treasury_expense(amount: u64, source_loan_id: u64, expense_type: RevenueType, reference: &str) -> Result<u64, String> {
+    let mut treasury_state = get_treasury_state();
+    
+    let expense_id = TREASURY_EXPENSES.with(|expenses| {
+        let mut expenses = expenses.borrow_mut();
+        let expense_id = expenses.last_key_value().map(|(id, _)| id + 1).unwrap_or(1);
+        expenses.insert(expense_id, TreasuryExpense {
+            id: expense_id,
+            expense_type,
+            amount,
+            source_loan_id,
+            reference: reference.to_string(),
+            timestamp: time(),
+        });
+        expense_id
+    });
+    
+    treasury_state.balance_ckbtc -= amount;
+    treasury_state.emergency_reserve = (treasury_state.balance_ckbtc * EMERGENCY_RESERVE_PERCENTAGE) / 100;
+    treasury_state.updated_at = time();
+    update_treasury_state(treasury_state)?;
+    
+    Ok(expense_id)
+}
+
+/// Move treasury funds into the liquidity pool to back promotional investor credits.
+/// Recorded in the expense log; the emergency reserve cannot be used.
+pub fn fund_promotional_credit(amount: u64, reason: &str) -> Result<u64, String> {
+    if amount == 0 {
+        return Err("Promotional credit amount must be greater than zero".to_string());
+    }
+    
+    let available = treasury_spendable_balance();
+    if amount > available {
+        return Err(format!("Insufficient treasury funds for promotional credit. Available: {} satoshi", available));
+    }
+    
+    let expense_id = record_treasury_expense(amount, 0, RevenueType::PromotionalCreditExpense, reason)?;
+    
+    log_action(
+        "TREASURY_PROMOTIONAL_CREDIT_FUNDED",
+        &format!("Moved {} satoshi to liquidity pool for promotional credits. Reason: {}", amount, reason),
+        true,
+    );
+    
+    Ok(expense_id)
+}
+
+/// Charge the treasury for the Bitcoin network fee added on top of a loan disbursement,
+/// so the borrower nets the full principal. The emergency reserve cannot be used.
+pub fn fund_disbursement_network_fee(loan_id: u64, fee: u64) -> Result<u64, String> {
+    let available = treasury_spendable_balance();
+    if fee > available {
+        return Err(format!("Insufficient treasury funds for the disbursement network fee. Available: {} satoshi", available));
+    }
+    
+    let expense_id = record_treasury_expense(fee, loan_id, RevenueType::DisbursementFeeExpense, "Bitcoin network fee")?;
+    
+    log_action(
+        "TREASURY_DISBURSEMENT_FEE_FUNDED",
+        &format!("Paid {} satoshi Bitcoin network fee for the disbursement of loan #{}", fee, loan_id),
+        true,
+    );
+    
+    Ok(expense_id)
+}
+
+/// Return a network fee reserved by `fund_disbursement_network_fee` when the withdrawal it
+/// was reserved for did not go through
+pub fn release_disbursement_network_fee(expense_id: u64) -> Result<u64, String> {
+    let expense = TREASURY_EXPENSES.with(|expenses| expenses.borrow_mut().remove(&expense_id))
+        .ok_or_else(|| format!("Treasury expense #{} not found", expense_id))?;
+    if !matches!(expense.expense_type, RevenueType::DisbursementFeeExpense) {
+        TREASURY_EXPENSES.with(|expenses| expenses.borrow_mut().insert(expense_id, expense));
+        return Err(format!("Treasury expense #{} is not a disbursement network fee", expense_id));
+    }
+    
+    let mut treasury_state = get_treasury_state();
+    treasury_state.balance_ckbtc += expense.amount;
+    treasury_state.emergency_reserve = (treasury_state.balance_ckbtc * EMERGENCY_RESERVE_PERCENTAGE) / 100;
+    treasury_state.updated_at = time();
+    update_treasury_state(treasury_state)?;
+    
+    log_action(
+        "TREASURY_DISBURSEMENT_FEE_RELEASED",
+        &format!("Released {} satoshi network fee reserved for the failed disbursement of loan #{}", expense.amount, expense.source_loan_id),
+        true,
+    );
+    
+    Ok(expense.amount)
+}
+
 /// Top up cycles for a specific canister (admin or governance only)
 #[update]
 pub async fn top_up_canister_cycles(canister_name: String) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "top_up_canister_cycles")?;
     
     // Security check
     if !is_admin(&caller) {
@@ -559,6 +921,7 @@ pub fn get_treasury_stats() -> TreasuryStats {
     let recent_revenue: u64 = REVENUE_LOG.with(|log| {
         log.borrow().iter()
             .filter(|(_, entry)| entry.timestamp >= thirty_days_ago)
+            .filter(|(_, entry)| !entry.revenue_type.is_expense())
             .map(|(_, entry)| entry.amount)
             .sum()
     });
@@ -593,6 +956,7 @@ pub fn register_canister(
     priority: u8
 ) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "register_canister")?;
     
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can register canisters".to_string());
@@ -643,6 +1007,7 @@ pub fn update_canister_config(
     auto_top_up_enabled: Option<bool>
 ) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "update_canister_config")?;
     
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can update canister configuration".to_string());
@@ -781,17 +1146,48 @@ pub fn get_revenue_log(
     entries
 }
 
+/// Treasury outflows (promotional credits, disbursement network fees), most recent first
+#[query]
+pub fn get_treasury_expenses(limit: Option<u32>) -> Vec<TreasuryExpense> {
+    let limit = limit.unwrap_or(100).min(1000) as usize;
+    TREASURY_EXPENSES.with(|expenses| {
+        expenses.borrow()
+            .iter()
+            .rev()
+            .take(limit)
+            .map(|(_, expense)| expense)
+            .collect()
+    })
+}
+
 /// Emergency withdraw function (super admin only)
+/// Opens a multi-signature request; funds move once enough SuperAdmins approve
 #[update]
 pub async fn emergency_withdraw(
     amount: u64,
     destination: Principal,
-    reason: String
+    reason: String,
+    nonce: Option<u64>,
 ) -> Result<String, String> {
+    crate::validation::guard_caller(&ic_cdk::caller(), "emergency_withdraw")?;
+    let request_id = propose_emergency_withdraw(amount, destination, reason, nonce)?;
+    Ok(format!("Emergency withdrawal request {} created. Awaiting {} SuperAdmin approvals.",
+        request_id, get_emergency_withdraw_threshold()))
+}
+
+/// Propose an emergency withdrawal; the proposer counts as the first approval
+#[update]
+pub fn propose_emergency_withdraw(
+    amount: u64,
+    destination: Principal,
+    reason: String,
+    nonce: Option<u64>,
+) -> Result<u64, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "propose_emergency_withdraw")?;
     
-    // Only super admin can perform emergency withdrawals
-    if !is_admin(&caller) {
+    // Only super admin can propose emergency withdrawals
+    if crate::validation::require_role(&caller, crate::validation::CallerRole::SuperAdmin).is_err() {
         log_action(
             "TREASURY_UNAUTHORIZED_EMERGENCY_WITHDRAWAL",
             &format!("Unauthorized emergency withdrawal attempt by {}", caller.to_text()),
@@ -799,7 +1195,146 @@ pub async fn emergency_withdraw(
         );
         return Err("Unauthorized: Only super admins can perform emergency withdrawals".to_string());
     }
+    crate::validation::require_admin_nonce(&caller, nonce, "propose_emergency_withdraw")?;
+    
+    crate::validation::require_positive(amount, "Withdrawal amount")?;
+    if destination == Principal::anonymous() {
+        return Err("Invalid destination principal".to_string());
+    }
+    if reason.trim().is_empty() {
+        return Err("A reason is required for emergency withdrawals".to_string());
+    }
+    validate_emergency_withdrawal_amount(amount, &reason)?;
+    
+    let now = time();
+    let request_id = EMERGENCY_WITHDRAWALS.with(|requests| {
+        let requests = requests.borrow();
+        requests.last_key_value().map(|(id, _)| id + 1).unwrap_or(1)
+    });
+    
+    let request = EmergencyWithdrawalRequest {
+        id: request_id,
+        amount,
+        destination,
+        reason,
+        proposer: caller,
+        approvals: vec![caller],
+        created_at: now,
+        expires_at: now + get_emergency_withdraw_window_hours() * NANOS_PER_HOUR,
+        status: EmergencyWithdrawalStatus::Pending,
+        transaction_id: None,
+        failure_reason: None,
+    };
+    
+    store_emergency_withdrawal(request.clone());
+    log_emergency_withdrawal_event("EMERGENCY_WITHDRAWAL_PROPOSED", &request, caller, true, None);
+    
+    Ok(request_id)
+}
+
+/// Approve a pending emergency withdrawal; executes the transfer once the threshold is met
+#[update]
+pub async fn approve_emergency_withdraw(request_id: u64, nonce: Option<u64>) -> Result<String, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "approve_emergency_withdraw")?;
+    
+    if crate::validation::require_role(&caller, crate::validation::CallerRole::SuperAdmin).is_err() {
+        return Err("Unauthorized: Only super admins can approve emergency withdrawals".to_string());
+    }
+    crate::validation::require_admin_nonce(&caller, nonce, "approve_emergency_withdraw")?;
+    
+    let mut request = get_emergency_withdrawal(request_id)
+        .ok_or_else(|| format!("Emergency withdrawal request {} not found", request_id))?;
     
+    if request.status != EmergencyWithdrawalStatus::Pending {
+        return Err(format!("Emergency withdrawal request {} is not pending", request_id));
+    }
+    
+    if time() > request.expires_at {
+        request.status = EmergencyWithdrawalStatus::Expired;
+        store_emergency_withdrawal(request.clone());
+        log_emergency_withdrawal_event("EMERGENCY_WITHDRAWAL_EXPIRED", &request, caller, false, None);
+        return Err(format!("Emergency withdrawal request {} has expired", request_id));
+    }
+    
+    if request.approvals.contains(&caller) {
+        return Err("You have already approved this request".to_string());
+    }
+    
+    request.approvals.push(caller);
+    log_emergency_withdrawal_event("EMERGENCY_WITHDRAWAL_APPROVED", &request, caller, true, None);
+    
+    let threshold = get_emergency_withdraw_threshold();
+    if !has_emergency_withdrawal_quorum(&request, threshold) {
+        store_emergency_withdrawal(request.clone());
+        return Ok(format!("Approval recorded ({}/{})", request.approvals.len(), threshold));
+    }
+    
+    // Mark as executing before the transfer so concurrent approvals cannot execute twice
+    request.status = EmergencyWithdrawalStatus::Executing;
+    store_emergency_withdrawal(request.clone());
+    
+    match execute_emergency_withdrawal(&request).await {
+        Ok(tx_id) => {
+            request.status = EmergencyWithdrawalStatus::Executed;
+            request.transaction_id = Some(tx_id.clone());
+            store_emergency_withdrawal(request.clone());
+            log_emergency_withdrawal_event("EMERGENCY_WITHDRAWAL_EXECUTED", &request, caller, true, None);
+            Ok(format!("Emergency withdrawal completed. TX ID: {}", tx_id))
+        },
+        Err(e) => {
+            request.status = EmergencyWithdrawalStatus::Failed;
+            request.failure_reason = Some(e.clone());
+            store_emergency_withdrawal(request.clone());
+            log_emergency_withdrawal_event("EMERGENCY_WITHDRAWAL_FAILED", &request, caller, false, Some(e.clone()));
+            Err(format!("Emergency withdrawal failed: {}", e))
+        }
+    }
+}
+
+/// Get emergency withdrawal requests still awaiting approval (admin only)
+#[query]
+pub fn get_pending_emergency_withdrawals() -> Result<Vec<EmergencyWithdrawalRequest>, String> {
+    if !is_admin(&caller()) {
+        return Err("Unauthorized: Only admins can view emergency withdrawals".to_string());
+    }
+    
+    let now = time();
+    Ok(EMERGENCY_WITHDRAWALS.with(|requests| {
+        requests.borrow().iter()
+            .map(|(_, request)| request)
+            .filter(|request| request.status == EmergencyWithdrawalStatus::Pending && request.expires_at >= now)
+            .collect()
+    }))
+}
+
+/// Check whether a request has enough distinct approvals to execute
+pub fn has_emergency_withdrawal_quorum(request: &EmergencyWithdrawalRequest, threshold: u64) -> bool {
+    let mut approvers = request.approvals.clone();
+    approvers.sort();
+    approvers.dedup();
+    approvers.len() as u64 >= threshold.max(1)
+}
+
+fn get_emergency_withdraw_threshold() -> u64 {
+    get_runway_parameter("emergency_withdraw_threshold", DEFAULT_EMERGENCY_WITHDRAW_THRESHOLD)
+}
+
+fn get_emergency_withdraw_window_hours() -> u64 {
+    get_runway_parameter("emergency_withdraw_window_hours", DEFAULT_EMERGENCY_WITHDRAW_WINDOW_HOURS)
+}
+
+fn get_emergency_withdrawal(request_id: u64) -> Option<EmergencyWithdrawalRequest> {
+    EMERGENCY_WITHDRAWALS.with(|requests| requests.borrow().get(&request_id))
+}
+
+fn store_emergency_withdrawal(request: EmergencyWithdrawalRequest) {
+    EMERGENCY_WITHDRAWALS.with(|requests| {
+        requests.borrow_mut().insert(request.id, request);
+    });
+}
+
+fn validate_emergency_withdrawal_amount(amount: u64, reason: &str) -> Result<(), String> {
     let treasury_state = get_treasury_state();
     
     if amount > treasury_state.balance_ckbtc {
@@ -812,43 +1347,127 @@ pub async fn emergency_withdraw(
         return Err(format!("Cannot withdraw emergency reserve. Available: {} satoshi", available_for_withdrawal));
     }
     
-    // Perform ckBTC transfer
-    let transfer_result = transfer_ckbtc_to_account(
+    Ok(())
+}
+
+/// Preconditions for a governance-approved treasury spend. The emergency reserve is never spendable this way.
+pub fn validate_governance_treasury_spend(amount: u64, destination: Principal) -> Result<(), String> {
+    if amount == 0 {
+        return Err("Spend amount must be greater than zero".to_string());
+    }
+    if destination == Principal::anonymous() {
+        return Err("Invalid destination principal".to_string());
+    }
+
+    let treasury_state = get_treasury_state();
+    let spendable = treasury_state.balance_ckbtc.saturating_sub(treasury_state.emergency_reserve);
+    if amount > spendable {
+        return Err(format!("Insufficient spendable treasury balance. Available: {} satoshi", spendable));
+    }
+
+    Ok(())
+}
+
+/// Transfer treasury funds approved by a governance proposal
+pub async fn execute_governance_treasury_spend(
+    proposal_id: u64,
+    amount: u64,
+    destination: Principal,
+) -> Result<String, String> {
+    // Balance may have changed during the voting period
+    validate_governance_treasury_spend(amount, destination)?;
+
+    let tx_id = transfer_ckbtc_to_account(
         Account {
             owner: destination,
             subaccount: None,
         },
         amount
-    ).await;
+    ).await?;
+
+    let mut new_state = get_treasury_state();
+    new_state.balance_ckbtc = new_state.balance_ckbtc.saturating_sub(amount);
+    new_state.updated_at = time();
+    new_state.emergency_reserve = (new_state.balance_ckbtc * EMERGENCY_RESERVE_PERCENTAGE) / 100;
+    update_treasury_state(new_state)?;
+
+    log_action(
+        "TREASURY_GOVERNANCE_SPEND",
+        &format!("Proposal {} transferred {} satoshi to {}. TX ID: {}", proposal_id, amount, destination.to_text(), tx_id),
+        true,
+    );
+
+    Ok(tx_id)
+}
+
+async fn execute_emergency_withdrawal(request: &EmergencyWithdrawalRequest) -> Result<String, String> {
+    // Balance may have changed while approvals were collected
+    validate_emergency_withdrawal_amount(request.amount, &request.reason)?;
     
-    match transfer_result {
-        Ok(tx_id) => {
-            // Update treasury balance
-            let mut new_state = treasury_state;
-            new_state.balance_ckbtc -= amount;
-            new_state.updated_at = time();
-            // Recalculate emergency reserve
-            new_state.emergency_reserve = (new_state.balance_ckbtc * EMERGENCY_RESERVE_PERCENTAGE) / 100;
-            update_treasury_state(new_state)?;
-            
-            log_action(
-                "TREASURY_EMERGENCY_WITHDRAWAL",
-                &format!("Emergency withdrawal of {} satoshi to {} (TX: {}). Reason: {}", 
-                    amount, destination.to_text(), tx_id, reason),
-                true,
-            );
-            
-            Ok(format!("Emergency withdrawal completed. TX ID: {}", tx_id))
+    // Perform ckBTC transfer
+    let tx_id = transfer_ckbtc_to_account(
+        Account {
+            owner: request.destination,
+            subaccount: None,
         },
-        Err(e) => {
-            log_action(
-                "TREASURY_EMERGENCY_WITHDRAWAL_FAILED",
-                &format!("Failed emergency withdrawal attempt: {}", e),
-                false,
-            );
-            Err(format!("Emergency withdrawal failed: {}", e))
-        }
-    }
+        request.amount
+    ).await?;
+    
+    // Update treasury balance
+    let mut new_state = get_treasury_state();
+    new_state.balance_ckbtc = new_state.balance_ckbtc.saturating_sub(request.amount);
+    new_state.updated_at = time();
+    // Recalculate emergency reserve
+    new_state.emergency_reserve = (new_state.balance_ckbtc * EMERGENCY_RESERVE_PERCENTAGE) / 100;
+    update_treasury_state(new_state)?;
+    
+    Ok(tx_id)
+}
+
+fn log_emergency_withdrawal_event(
+    action: &str,
+    request: &EmergencyWithdrawalRequest,
+    actor: Principal,
+    success: bool,
+    error: Option<String>,
+) {
+    use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+    
+    log_audit_enhanced(
+        AuditCategory::Treasury,
+        action.to_string(),
+        AuditEventLevel::Critical,
+        AuditDetails {
+            description: format!(
+                "Emergency withdrawal {} of {} satoshi to {} by {} ({} approvals). Reason: {}",
+                request.id,
+                request.amount,
+                request.destination.to_text(),
+                actor.to_text(),
+                request.approvals.len(),
+                request.reason
+            ),
+            entity_type: Some("emergency_withdrawal".to_string()),
+            entity_id: Some(request.id.to_string()),
+            metadata: vec![
+                ("amount".to_string(), request.amount.to_string()),
+                ("approvals".to_string(), request.approvals.len().to_string()),
+                ("transaction_id".to_string(), request.transaction_id.clone().unwrap_or_default()),
+            ],
+            ..Default::default()
+        },
+        AuditResult {
+            success,
+            error_code: error.as_ref().map(|_| "EMERGENCY_WITHDRAWAL_FAILED".to_string()),
+            error_message: error,
+            execution_time_ms: None,
+            gas_used: None,
+            cycles_consumed: None,
+            memory_used_bytes: None,
+            warning_flags: vec![],
+        },
+        None,
+    );
 }
 
 /// Get detailed cycle transactions log with filtering
@@ -907,6 +1526,7 @@ pub fn get_cycle_transactions(
 #[update]
 pub async fn trigger_cycle_distribution() -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "trigger_cycle_distribution")?;
     
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can trigger cycle distribution".to_string());
@@ -938,6 +1558,7 @@ pub fn get_treasury_health_report() -> TreasuryStats {
     let recent_revenue: u64 = REVENUE_LOG.with(|log| {
         log.borrow().iter()
             .filter(|(_, entry)| entry.timestamp >= thirty_days_ago)
+            .filter(|(_, entry)| !entry.revenue_type.is_expense())
             .map(|(_, entry)| entry.amount)
             .sum()
     });
@@ -1242,8 +1863,7 @@ async fn transfer_ckbtc_to_account(to_account: Account, amount: u64) -> Result<S
         GenericError { error_code: Nat, message: String },
     }
     
-    let ckbtc_ledger = Principal::from_text("mxzaz-hqaaa-aaaar-qaada-cai")
-        .map_err(|_| "Invalid ckBTC ledger principal")?;
+    let ckbtc_ledger = crate::helpers::ckbtc_ledger_principal();
     
     let transfer_args = TransferArgs {
         from_subaccount: None,
@@ -1289,48 +1909,6 @@ fn calculate_daily_cycle_cost() -> u64 {
     })
 }
 
-/// Process fee collection from loan operations with detailed tracking
-pub async fn process_loan_fee_collection(
-    loan_id: u64,
-    total_amount: u64,
-    admin_fee_amount: u64,
-    interest_share_amount: u64
-) -> Result<String, String> {
-    let caller = caller();
-    
-    // Security: Only loan management canister can collect fees
-    if !is_loan_manager(&caller) {
-        return Err("Unauthorized: Only loan management canister can collect fees".to_string());
-    }
-    
-    let mut results = Vec::new();
-    
-    // Collect admin fee
-    if admin_fee_amount > 0 {
-        match collect_fees(loan_id, admin_fee_amount, RevenueType::AdminFee).await {
-            Ok(msg) => results.push(format!("Admin fee: {}", msg)),
-            Err(e) => return Err(format!("Failed to collect admin fee: {}", e)),
-        }
-    }
-    
-    // Collect interest share
-    if interest_share_amount > 0 {
-        match collect_fees(loan_id, interest_share_amount, RevenueType::InterestShare).await {
-            Ok(msg) => results.push(format!("Interest share: {}", msg)),
-            Err(e) => return Err(format!("Failed to collect interest share: {}", e)),
-        }
-    }
-    
-    log_action(
-        "TREASURY_LOAN_FEE_COLLECTION",
-        &format!("Collected {} satoshi total fees from loan #{} (Admin: {}, Interest: {})", 
-            total_amount, loan_id, admin_fee_amount, interest_share_amount),
-        true,
-    );
-    
-    Ok(format!("Successfully collected fees: {}", results.join(", ")))
-}
-
 /// Process liquidation penalty collection
 pub async fn process_liquidation_penalty(
     loan_id: u64,
@@ -1369,6 +1947,7 @@ pub fn set_treasury_configuration(
     cycle_monitoring_interval: Option<u64>
 ) -> Result<String, String> {
     let caller = caller();
+    crate::validation::guard_caller(&caller, "set_treasury_configuration")?;
     
     if !is_admin(&caller) {
         return Err("Unauthorized: Only admins can update treasury configuration".to_string());
@@ -1402,6 +1981,486 @@ pub fn set_treasury_configuration(
     Ok("Treasury configuration updated successfully".to_string())
 }
 
+// ========== CYCLE BURN FORECASTING ==========
+
+/// Average daily burn rate from a least-squares fit of cycles over time.
+/// Returns 0 when there are too few samples or the balance is flat/growing.
+pub fn calculate_daily_burn_rate(samples: &[CycleBalanceSample]) -> u64 {
+    if samples.len() < 2 {
+        return 0;
+    }
+    
+    // Work relative to the first sample to keep the sums well-conditioned
+    let t0 = samples[0].timestamp;
+    let n = samples.len() as f64;
+    let xs: Vec<f64> = samples.iter().map(|s| (s.timestamp - t0) as f64 / NANOS_PER_DAY).collect();
+    let ys: Vec<f64> = samples.iter().map(|s| s.cycles as f64).collect();
+    
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let covariance: f64 = xs.iter().zip(&ys).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    
+    if variance == 0.0 {
+        return 0;
+    }
+    
+    // Slope is cycles per day; a negative slope is a burn
+    let slope = covariance / variance;
+    if slope >= 0.0 { 0 } else { (-slope) as u64 }
+}
+
+fn get_runway_parameter(key: &str, default: u64) -> u64 {
+    crate::governance::get_protocol_parameter(key.to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(default)
+}
+
+fn build_cycle_forecast(history: &CycleSampleHistory) -> CycleForecast {
+    let target_runway_days = get_runway_parameter("cycle_target_runway_days", DEFAULT_CYCLE_TARGET_RUNWAY_DAYS);
+    let alert_days = get_runway_parameter("cycle_runway_alert_days", DEFAULT_CYCLE_RUNWAY_ALERT_DAYS);
+    
+    let current_cycles = history.samples.last().map(|s| s.cycles).unwrap_or(0);
+    let daily_burn_rate = calculate_daily_burn_rate(&history.samples);
+    
+    let days_until_depletion = if daily_burn_rate > 0 {
+        Some(current_cycles as f64 / daily_burn_rate as f64)
+    } else {
+        None
+    };
+    
+    let recommended_top_up = daily_burn_rate
+        .saturating_mul(target_runway_days)
+        .saturating_sub(current_cycles);
+    
+    CycleForecast {
+        canister_name: history.canister_name.clone(),
+        canister_id: history.canister_id,
+        current_cycles,
+        samples_used: history.samples.len() as u32,
+        daily_burn_rate,
+        days_until_depletion,
+        target_runway_days,
+        recommended_top_up,
+        below_alert_threshold: days_until_depletion.map_or(false, |days| days < alert_days as f64),
+        generated_at: time(),
+    }
+}
+
+/// Record the current cycle balance of every active registered canister
+async fn sample_canister_cycle_balances() {
+    let canisters: Vec<CanisterInfo> = CANISTER_REGISTRY.with(|registry| {
+        registry.borrow().iter()
+            .filter(|(_, canister)| canister.is_active)
+            .map(|(_, canister)| canister.clone())
+            .collect()
+    });
+    
+    for canister_info in canisters {
+        let cycles = match get_canister_cycles(canister_info.principal).await {
+            Ok(cycles) => cycles,
+            Err(e) => {
+                log_action(
+                    "TREASURY_CYCLE_SAMPLE_FAILED",
+                    &format!("Failed to sample cycles for canister {}: {}", canister_info.name, e),
+                    false,
+                );
+                continue;
+            }
+        };
+        
+        CYCLE_SAMPLES.with(|samples| {
+            let mut samples = samples.borrow_mut();
+            let mut history = samples.get(&canister_info.name).unwrap_or(CycleSampleHistory {
+                canister_name: canister_info.name.clone(),
+                canister_id: canister_info.principal,
+                samples: Vec::new(),
+                last_runway_alert_at: None,
+            });
+            
+            history.canister_id = canister_info.principal;
+            history.samples.push(CycleBalanceSample { timestamp: time(), cycles });
+            if history.samples.len() > MAX_CYCLE_SAMPLES_PER_CANISTER {
+                let excess = history.samples.len() - MAX_CYCLE_SAMPLES_PER_CANISTER;
+                history.samples.drain(..excess);
+            }
+            
+            samples.insert(canister_info.name.clone(), history);
+        });
+    }
+}
+
+/// Whether a canister below the runway threshold is due another alert: on first crossing,
+/// then once per cooldown while it stays low
+pub fn runway_alert_due(below_alert_threshold: bool, last_alert_at: Option<u64>, now: u64) -> bool {
+    below_alert_threshold
+        && last_alert_at.map_or(true, |alerted_at| now.saturating_sub(alerted_at) >= CYCLE_RUNWAY_ALERT_COOLDOWN_NANOS)
+}
+
+/// Raise a Critical audit alert for every canister whose runway is below the governance threshold
+fn check_cycle_runway_alerts() {
+    use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+    
+    let now = time();
+    let histories: Vec<(String, CycleSampleHistory)> = CYCLE_SAMPLES.with(|samples| samples.borrow().iter().collect());
+    for (name, mut history) in histories {
+        let forecast = build_cycle_forecast(&history);
+        if !runway_alert_due(forecast.below_alert_threshold, history.last_runway_alert_at, now) {
+            // A recovered canister alerts again as soon as it next drops below the threshold
+            if !forecast.below_alert_threshold && history.last_runway_alert_at.is_some() {
+                history.last_runway_alert_at = None;
+                CYCLE_SAMPLES.with(|samples| samples.borrow_mut().insert(name, history));
+            }
+            continue;
+        }
+        history.last_runway_alert_at = Some(now);
+        CYCLE_SAMPLES.with(|samples| samples.borrow_mut().insert(name, history));
+        
+        log_audit_enhanced(
+            AuditCategory::Treasury,
+            "CYCLE_RUNWAY_CRITICAL".to_string(),
+            AuditEventLevel::Critical,
+            AuditDetails {
+                description: format!(
+                    "Canister {} projected to run out of cycles in {:.1} days (burn {} cycles/day, recommended top-up {})",
+                    forecast.canister_name,
+                    forecast.days_until_depletion.unwrap_or(0.0),
+                    forecast.daily_burn_rate,
+                    forecast.recommended_top_up
+                ),
+                entity_type: Some("canister".to_string()),
+                entity_id: Some(forecast.canister_id.to_text()),
+                metadata: vec![
+                    ("current_cycles".to_string(), forecast.current_cycles.to_string()),
+                    ("daily_burn_rate".to_string(), forecast.daily_burn_rate.to_string()),
+                ],
+                ..Default::default()
+            },
+            AuditResult {
+                success: false,
+                error_code: Some("CYCLE_RUNWAY_LOW".to_string()),
+                error_message: None,
+                execution_time_ms: None,
+                gas_used: None,
+                cycles_consumed: None,
+                memory_used_bytes: None,
+                warning_flags: vec!["LOW_CYCLE_RUNWAY".to_string()],
+            },
+            None,
+        );
+    }
+}
+
+fn get_all_cycle_forecasts() -> Vec<CycleForecast> {
+    CYCLE_SAMPLES.with(|samples| {
+        samples.borrow().iter()
+            .map(|(_, history)| build_cycle_forecast(&history))
+            .collect()
+    })
+}
+
+/// Get the cycle burn forecast for a registered canister
+#[query]
+pub fn get_cycle_burn_forecast(canister_id: Principal) -> Result<CycleForecast, String> {
+    let history = CYCLE_SAMPLES.with(|samples| {
+        samples.borrow().iter()
+            .find(|(_, history)| history.canister_id == canister_id)
+            .map(|(_, history)| history)
+    }).ok_or_else(|| "No cycle samples recorded for canister".to_string())?;
+    
+    Ok(build_cycle_forecast(&history))
+}
+
+/// Get the protocol-wide cycle forecast across all registered canisters
+#[query]
+pub fn get_protocol_cycle_forecast() -> ProtocolCycleForecast {
+    let canisters = get_all_cycle_forecasts();
+    
+    let min_days_until_depletion = canisters.iter()
+        .filter_map(|f| f.days_until_depletion)
+        .fold(None, |min: Option<f64>, days| Some(min.map_or(days, |m| m.min(days))));
+    
+    ProtocolCycleForecast {
+        total_current_cycles: canisters.iter().map(|f| f.current_cycles).sum(),
+        total_daily_burn_rate: canisters.iter().map(|f| f.daily_burn_rate).sum(),
+        min_days_until_depletion,
+        total_recommended_top_up: canisters.iter().map(|f| f.recommended_top_up).sum(),
+        canisters_below_threshold: canisters.iter().filter(|f| f.below_alert_threshold).count() as u32,
+        generated_at: time(),
+        canisters,
+    }
+}
+
+// ========== INSURANCE FUND ==========
+
+/// Split a protocol fee into (insurance share, treasury share). The insurance
+/// share stops once the fund reaches its target; the excess goes to treasury.
+pub fn split_protocol_fee(fee_amount: u64, insurance_bps: u64, fund_balance: u64, fund_target: u64) -> (u64, u64) {
+    let share = crate::helpers::rounded_share(fee_amount, insurance_bps.min(10_000), 10_000);
+    let insurance = share.min(fund_target.saturating_sub(fund_balance));
+    (insurance, fee_amount - insurance)
+}
+
+/// Split a principal loss into (covered by insurance fund, socialized to investors)
+pub fn allocate_liquidation_loss(principal_loss: u64, fund_balance: u64) -> (u64, u64) {
+    let covered = principal_loss.min(fund_balance);
+    (covered, principal_loss - covered)
+}
+
+fn get_insurance_fund_state() -> InsuranceFundState {
+    INSURANCE_FUND.with(|fund| fund.borrow().get(&0).unwrap_or_default())
+}
+
+fn record_insurance_fund_change(kind: InsuranceFundEntryKind, loan_id: u64, amount: u64) -> InsuranceFundState {
+    let now = time();
+    let mut state = get_insurance_fund_state();
+    match kind {
+        InsuranceFundEntryKind::FeeContribution | InsuranceFundEntryKind::DustDonation => {
+            state.balance += amount;
+            state.total_contributions += amount;
+        },
+        InsuranceFundEntryKind::LossCoverage => {
+            state.balance -= amount;
+            state.total_payouts += amount;
+        },
+    }
+    state.updated_at = now;
+    INSURANCE_FUND.with(|fund| fund.borrow_mut().insert(0, state.clone()));
+    
+    INSURANCE_FUND_HISTORY.with(|history| {
+        let mut history = history.borrow_mut();
+        let id = history.len() + 1;
+        history.insert(id, InsuranceFundEntry {
+            id,
+            kind,
+            loan_id,
+            amount,
+            balance_after: state.balance,
+            timestamp: now,
+        });
+    });
+    
+    state
+}
+
+/// Route a collected protocol fee between the insurance fund and treasury. Returns (insurance
+/// share, treasury share, treasury revenue entry ID); the entry ID is 0 when the whole fee went
+/// to the insurance fund. Repayment fees must come through
+/// `loan_repayment::collect_repayment_fee`, which records each one so it is never routed twice.
+pub fn route_protocol_fee_with_entry(loan_id: u64, fee_amount: u64) -> Result<(u64, u64, u64), String> {
+    route_fee_revenue(loan_id, fee_amount, RevenueType::ProtocolFee)
+}
+
+/// Route a prepayment penalty the same way as protocol fees, recorded as early repayment revenue
+pub fn route_prepayment_penalty(loan_id: u64, penalty_amount: u64) -> Result<(u64, u64), String> {
+    route_fee_revenue(loan_id, penalty_amount, RevenueType::EarlyRepaymentFee).map(|(insurance, treasury, _)| (insurance, treasury))
+}
+
+fn route_fee_revenue(loan_id: u64, fee_amount: u64, revenue_type: RevenueType) -> Result<(u64, u64, u64), String> {
+    let insurance_bps = crate::governance::get_protocol_parameter("insurance_fund_fee_bps".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_INSURANCE_FUND_FEE_BPS);
+    let fund_target = crate::governance::get_protocol_parameter("insurance_fund_target".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_INSURANCE_FUND_TARGET);
+    
+    let (insurance_share, treasury_share) =
+        split_protocol_fee(fee_amount, insurance_bps, get_insurance_fund_state().balance, fund_target);
+    
+    if insurance_share > 0 {
+        record_insurance_fund_change(InsuranceFundEntryKind::FeeContribution, loan_id, insurance_share);
+    }
+    let revenue_id = record_fee_revenue(treasury_share, loan_id, revenue_type.clone())?;
+    
+    log_action(
+        "PROTOCOL_FEE_ROUTED",
+        &format!(
+            "{:?} of {} satoshi from loan #{}: {} to insurance fund, {} to treasury",
+            revenue_type, fee_amount, loan_id, insurance_share, treasury_share
+        ),
+        true,
+    );
+    
+    Ok((insurance_share, treasury_share, revenue_id))
+}
+
+/// A single revenue log entry
+pub fn get_revenue_entry(revenue_id: u64) -> Option<RevenueEntry> {
+    REVENUE_LOG.with(|log| log.borrow().get(&revenue_id))
+}
+
+/// Every revenue log entry of `revenue_type`, oldest first
+pub fn get_revenue_entries_by_type(revenue_type: &RevenueType) -> Vec<RevenueEntry> {
+    REVENUE_LOG.with(|log| {
+        log.borrow()
+            .iter()
+            .map(|(_, entry)| entry)
+            .filter(|entry| std::mem::discriminant(&entry.revenue_type) == std::mem::discriminant(revenue_type))
+            .collect()
+    })
+}
+
+/// Draw from the insurance fund to cover a liquidation principal loss.
+/// Returns the amount covered; the remainder is socialized to investors.
+pub fn cover_loss_from_insurance_fund(loan_id: u64, principal_loss: u64) -> u64 {
+    let (covered, socialized) = allocate_liquidation_loss(principal_loss, get_insurance_fund_state().balance);
+    
+    if covered > 0 {
+        record_insurance_fund_change(InsuranceFundEntryKind::LossCoverage, loan_id, covered);
+    }
+    
+    log_action(
+        "INSURANCE_FUND_LOSS_COVERAGE",
+        &format!(
+            "Loan #{} principal loss {} satoshi: {} covered by insurance fund, {} socialized to investors",
+            loan_id, principal_loss, covered, socialized
+        ),
+        true,
+    );
+    
+    covered
+}
+
+/// Credit swept investor dust to the insurance fund (no associated loan)
+pub fn record_dust_donation(amount: u64) -> u64 {
+    if amount == 0 {
+        return get_insurance_fund_state().balance;
+    }
+    record_insurance_fund_change(InsuranceFundEntryKind::DustDonation, 0, amount).balance
+}
+
+/// Insurance fund shares of routed fees, oldest first
+pub fn get_insurance_fee_contributions() -> Vec<InsuranceFundEntry> {
+    INSURANCE_FUND_HISTORY.with(|history| {
+        history.borrow()
+            .iter()
+            .map(|(_, entry)| entry)
+            .filter(|entry| matches!(entry.kind, InsuranceFundEntryKind::FeeContribution))
+            .collect()
+    })
+}
+
+/// Get the current insurance fund balance
+#[query]
+pub fn get_insurance_fund_balance() -> u64 {
+    get_insurance_fund_state().balance
+}
+
+/// Get insurance fund contributions and payouts, newest first
+#[query]
+pub fn get_insurance_fund_history(limit: Option<u32>) -> Vec<InsuranceFundEntry> {
+    let limit = limit.unwrap_or(100).min(1000) as usize;
+    INSURANCE_FUND_HISTORY.with(|history| {
+        history.borrow().iter()
+            .rev()
+            .take(limit)
+            .map(|(_, entry)| entry)
+            .collect()
+    })
+}
+
+// ========== BALANCE RECONCILIATION ==========
+
+const RECONCILIATION_INTERVAL_NANOS: u64 = 60 * 60 * 1_000_000_000; // Hourly
+const DEFAULT_RECONCILIATION_TOLERANCE: u64 = 10_000;
+
+/// Within tolerance is balanced. Outside it, pending ledger calls may explain the gap, so
+/// only a gap with nothing in flight counts as a surplus or deficit.
+pub fn classify_reconciliation(delta: i64, tolerance: u64, in_flight_operations: u64) -> ReconciliationStatus {
+    if delta.unsigned_abs() <= tolerance {
+        ReconciliationStatus::Balanced
+    } else if in_flight_operations > 0 {
+        ReconciliationStatus::TransfersInFlight
+    } else if delta > 0 {
+        ReconciliationStatus::Surplus
+    } else {
+        ReconciliationStatus::Deficit
+    }
+}
+
+pub fn get_last_reconciliation() -> Option<BalanceReconciliation> {
+    LAST_RECONCILIATION.with(|last| last.borrow().get(&0))
+}
+
+/// Compare the canister's ckBTC ledger balance with pool liquidity, treasury, insurance fund
+/// and borrower surplus owed. The ledger side sums the default account and every configured
+/// reconciliation subaccount.
+async fn run_ckbtc_reconciliation() -> Result<BalanceReconciliation, String> {
+    let mut ledger_balance = crate::ckbtc_integration::get_protocol_ckbtc_balance().await?;
+    let mut subaccount_balances = Vec::new();
+    for subaccount in get_config().reconciliation_subaccounts() {
+        let balance = crate::ckbtc_integration::check_ckbtc_balance(crate::ckbtc_integration::Account {
+            owner: ic_cdk::api::canister_self(),
+            subaccount: Some(subaccount.clone()),
+        }).await?;
+        ledger_balance = ledger_balance.saturating_add(balance);
+        subaccount_balances.push((subaccount, balance));
+    }
+
+    let pool_available_liquidity = crate::storage::get_liquidity_pool().available_liquidity;
+    let treasury_balance = get_treasury_state().balance_ckbtc;
+    let insurance_fund_balance = get_insurance_fund_balance();
+    let borrower_surplus_owed = crate::liquidation::total_borrower_surplus_owed();
+    let internal_obligations = pool_available_liquidity
+        .saturating_add(treasury_balance)
+        .saturating_add(insurance_fund_balance)
+        .saturating_add(borrower_surplus_owed);
+
+    let delta = ledger_balance as i128 - internal_obligations as i128;
+    let delta = delta.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+    let tolerance = crate::governance::get_protocol_parameter("reconciliation_tolerance_satoshi".to_string())
+        .map(|param| param.current_value)
+        .unwrap_or(DEFAULT_RECONCILIATION_TOLERANCE);
+    let in_flight_operations = crate::helpers::in_flight_resource_count();
+
+    let reconciliation = BalanceReconciliation {
+        checked_at: time(),
+        ledger_balance,
+        subaccount_balances: Some(subaccount_balances),
+        pool_available_liquidity,
+        treasury_balance,
+        insurance_fund_balance,
+        borrower_surplus_owed,
+        internal_obligations,
+        delta,
+        tolerance,
+        in_flight_operations,
+        status: classify_reconciliation(delta, tolerance, in_flight_operations),
+    };
+    LAST_RECONCILIATION.with(|last| {
+        last.borrow_mut().insert(0, reconciliation.clone());
+    });
+
+    if matches!(reconciliation.status, ReconciliationStatus::Surplus | ReconciliationStatus::Deficit) {
+        crate::audit_logging::log_security_event(
+            "CKBTC_BALANCE_MISMATCH",
+            crate::audit_logging::AuditEventLevel::Critical,
+            format!(
+                "ckBTC ledger balance {} differs from internal obligations {} by {} (tolerance {})",
+                ledger_balance, internal_obligations, delta, tolerance
+            ),
+            None,
+            vec![
+                format!("STATUS:{:?}", reconciliation.status),
+                format!("DELTA:{}", delta),
+            ],
+        );
+    }
+
+    Ok(reconciliation)
+}
+
+/// Reconcile the canister's ckBTC ledger balance against internal accounting now (admin only)
+#[update]
+pub async fn reconcile_ckbtc_balance() -> Result<BalanceReconciliation, String> {
+    let caller = caller();
+    crate::validation::guard_caller(&caller, "reconcile_ckbtc_balance")?;
+    if !is_admin(&caller) {
+        return Err("Unauthorized: Only admins can reconcile balances".to_string());
+    }
+    run_ckbtc_reconciliation().await
+}
+
 // ========== HEARTBEAT AND MONITORING ==========
 
 /// Heartbeat function to check canister cycles periodically
@@ -1412,8 +2471,17 @@ pub async fn treasury_heartbeat() {
     
     // Check every hour
     if now - last_check >= CYCLE_MONITORING_INTERVAL_SECONDS * 1_000_000_000 {
+        sample_canister_cycle_balances().await;
+        check_cycle_runway_alerts();
         let _ = check_and_auto_top_up_canisters().await;
     }
+    
+    let last_reconciled = get_last_reconciliation().map_or(0, |last| last.checked_at);
+    if now.saturating_sub(last_reconciled) >= RECONCILIATION_INTERVAL_NANOS {
+        if let Err(e) = run_ckbtc_reconciliation().await {
+            log_action("CKBTC_RECONCILIATION_FAILED", &e, false);
+        }
+    }
 }
 
 // ========== INITIALIZATION AND UPGRADE HOOKS ==========
@@ -1422,6 +2490,7 @@ pub async fn treasury_heartbeat() {
 #[init]
 fn init() {
     init_treasury();
+    crate::schema_migration::initialize_schema_version();
 }
 
 #[pre_upgrade]
@@ -1446,8 +2515,12 @@ pub use update_canister_config;
 pub use get_canister_cycle_status;
 pub use get_revenue_log;
 pub use emergency_withdraw;
+pub use propose_emergency_withdraw;
+pub use approve_emergency_withdraw;
+pub use get_pending_emergency_withdrawals;
 pub use get_cycle_transactions;
 pub use trigger_cycle_distribution;
-pub use process_loan_fee_collection;
 pub use process_liquidation_penalty;
+pub use get_cycle_burn_forecast;
+pub use get_protocol_cycle_forecast;
 pub use set_treasury_configuration;
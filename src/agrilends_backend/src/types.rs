@@ -12,11 +12,198 @@ pub const DEFAULT_LOAN_DURATION_DAYS: u64 = 365;
 pub const DEFAULT_GRACE_PERIOD_DAYS: u64 = 30;
 pub const MIN_COLLATERAL_VALUE_SATOSHI: u64 = 100_000; // 0.001 BTC
 pub const MAX_COLLATERAL_VALUE_SATOSHI: u64 = 100_000_000; // 1 BTC
+pub const MIN_COMMODITY_LTV_RATIO: u64 = 10; // 10% floor for per-commodity LTV overrides
+pub const MAX_COMMODITY_LTV_RATIO: u64 = 90; // 90% cap for per-commodity LTV overrides
+pub const MAX_POOL_FEE_BPS: u64 = 200; // 2% cap for deposit/withdrawal fees
 
 // Standardized Result Types
 pub type AgrilendsResult<T> = Result<T, AgrilendsError>;
 pub type AsyncResult<T> = std::result::Result<T, String>;
 
+// ========== AMOUNT UNITS ==========
+// ckBTC amounts are satoshi and collateral valuations are whole rupiah. The newtypes keep the two
+// from being mixed; both encode on the wire exactly like the nat64 they wrap.
+pub const SATOSHI_PER_BTC: u64 = 100_000_000;
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Satoshi(pub u64);
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Idr(pub u64);
+
+impl Satoshi {
+    pub fn checked_add(self, other: Satoshi) -> Result<Satoshi, String> {
+        self.0.checked_add(other.0).map(Satoshi)
+            .ok_or_else(|| format!("Satoshi overflow adding {} to {}", other.0, self.0))
+    }
+
+    pub fn checked_sub(self, other: Satoshi) -> Result<Satoshi, String> {
+        self.0.checked_sub(other.0).map(Satoshi)
+            .ok_or_else(|| format!("Satoshi underflow subtracting {} from {}", other.0, self.0))
+    }
+}
+
+impl Idr {
+    pub fn checked_add(self, other: Idr) -> Result<Idr, String> {
+        self.0.checked_add(other.0).map(Idr)
+            .ok_or_else(|| format!("IDR overflow adding {} to {}", other.0, self.0))
+    }
+
+    pub fn checked_sub(self, other: Idr) -> Result<Idr, String> {
+        self.0.checked_sub(other.0).map(Idr)
+            .ok_or_else(|| format!("IDR underflow subtracting {} from {}", other.0, self.0))
+    }
+
+    /// Value of `quantity` units at `price_per_unit`
+    pub fn checked_mul_quantity(self, quantity: u64) -> Result<Idr, String> {
+        self.0.checked_mul(quantity).map(Idr)
+            .ok_or_else(|| format!("IDR overflow valuing {} units at {}", quantity, self.0))
+    }
+}
+
+impl From<u64> for Satoshi {
+    fn from(amount: u64) -> Self {
+        Satoshi(amount)
+    }
+}
+
+impl From<Satoshi> for u64 {
+    fn from(amount: Satoshi) -> Self {
+        amount.0
+    }
+}
+
+impl From<u64> for Idr {
+    fn from(amount: u64) -> Self {
+        Idr(amount)
+    }
+}
+
+impl From<Idr> for u64 {
+    fn from(amount: Idr) -> Self {
+        amount.0
+    }
+}
+
+impl std::fmt::Display for Satoshi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} satoshi", self.0)
+    }
+}
+
+impl std::fmt::Display for Idr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} IDR", self.0)
+    }
+}
+
+// Price of one BTC in rupiah, read from the oracle. The only route between the two units.
+// ICP loans reuse it with the ICP/IDR price, since e8s per ICP matches satoshi per BTC.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct BtcIdrRate {
+    pub idr_per_btc: u64,
+    pub timestamp: u64,
+}
+
+impl BtcIdrRate {
+    /// Rupiah value in satoshi, rounded down
+    pub fn idr_to_satoshi(&self, amount: Idr) -> Result<Satoshi, String> {
+        if self.idr_per_btc == 0 {
+            return Err("BTC/IDR rate must be greater than zero".to_string());
+        }
+        let satoshi = amount.0 as u128 * SATOSHI_PER_BTC as u128 / self.idr_per_btc as u128;
+        u64::try_from(satoshi).map(Satoshi)
+            .map_err(|_| format!("{} does not fit in a satoshi amount", amount))
+    }
+
+    /// Satoshi value in rupiah, rounded down
+    pub fn satoshi_to_idr(&self, amount: Satoshi) -> Result<Idr, String> {
+        let idr = amount.0 as u128 * self.idr_per_btc as u128 / SATOSHI_PER_BTC as u128;
+        u64::try_from(idr).map(Idr)
+            .map_err(|_| format!("{} does not fit in a rupiah amount", amount))
+    }
+}
+
+// Currency a pool, deposit or loan is denominated in. Both assets use 10^8 base units per
+// token (satoshi for ckBTC, e8s for ICP), so pool and loan amounts carry either unchanged.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Asset {
+    CkBtc,
+    Icp,
+}
+
+impl Default for Asset {
+    fn default() -> Self {
+        Asset::CkBtc
+    }
+}
+
+impl Asset {
+    pub const ALL: [Asset; 2] = [Asset::CkBtc, Asset::Icp];
+
+    /// Key in the liquidity pool map. ckBTC keeps key 0, so the single pool stored before
+    /// assets existed is read as the ckBTC pool without moving any state on upgrade.
+    pub fn pool_key(&self) -> u8 {
+        match self {
+            Asset::CkBtc => 0,
+            Asset::Icp => 1,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Asset::CkBtc => "ckBTC",
+            Asset::Icp => "ICP",
+        }
+    }
+
+    /// Base unit amounts are quoted in
+    pub fn base_unit(&self) -> &'static str {
+        match self {
+            Asset::CkBtc => "ckBTC satoshi",
+            Asset::Icp => "ICP e8s",
+        }
+    }
+
+    /// Smallest deposit or disbursement in base units (0.001 BTC, 0.1 ICP)
+    pub fn min_amount(&self) -> u64 {
+        match self {
+            Asset::CkBtc => 100_000,
+            Asset::Icp => 10_000_000,
+        }
+    }
+
+    /// Smallest withdrawal in base units; smaller balances are dust. Both are 100x the ledger
+    /// fee (0.00001 BTC, 0.01 ICP).
+    pub fn min_withdrawal_amount(&self) -> u64 {
+        match self {
+            Asset::CkBtc => crate::liquidity_management::MIN_WITHDRAWAL_AMOUNT,
+            Asset::Icp => 1_000_000,
+        }
+    }
+}
+
+// Who pays the ckBTC minter's fee when a loan is withdrawn to Bitcoin. With Borrower the
+// principal is sent as is and the borrower nets it less the fee; with Protocol the treasury
+// adds the fee on top so the borrower nets the full principal.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FeeBearer {
+    #[default]
+    Borrower,
+    Protocol,
+}
+
+// Direction a fee or yield share is rounded when it does not divide evenly. Down truncates,
+// which is how shares were always computed and remains the default.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RoundingMode {
+    #[default]
+    Down,
+    Up,
+    HalfUp,
+    HalfEven,
+}
+
 // Scalability and Load Balancing Support Types
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct ShardMetrics {
@@ -157,6 +344,27 @@ impl std::fmt::Display for AgrilendsError {
     }
 }
 
+// Collateral rejected by the minimum-value floor at mint or origination
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum CollateralValueError {
+    BelowMinimum {
+        valuation_idr: u64,
+        min_collateral_value_idr: u64,
+    },
+}
+
+impl std::fmt::Display for CollateralValueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CollateralValueError::BelowMinimum { valuation_idr, min_collateral_value_idr } => write!(
+                f,
+                "Collateral valuation {} IDR is below the minimum collateral value of {} IDR",
+                valuation_idr, min_collateral_value_idr
+            ),
+        }
+    }
+}
+
 // Account type for ICRC-7 compliance
 #[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
 pub struct Account {
@@ -190,6 +398,7 @@ pub struct TransferRequest {
 pub enum TransferResult {
     Ok,
     Err(String),
+    CollateralLocked { token_id: u64, loan_id: Option<u64> }, // Token backs a loan and cannot move
 }
 
 // RWA NFT Data structure
@@ -204,6 +413,13 @@ pub struct RWANFTData {
     pub loan_id: Option<u64>,
 }
 
+// ICRC-10 supported standard entry
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct SupportedStandard {
+    pub name: String,
+    pub url: String,
+}
+
 // RWA NFT Result type
 #[derive(CandidType, Deserialize)]
 pub enum RWANFTResult {
@@ -227,7 +443,7 @@ pub struct CollateralRecord {
     pub nft_token_id: u64,
     pub owner: Principal,
     pub loan_id: Option<u64>,
-    pub valuation_idr: u64,
+    pub valuation_idr: Idr,
     pub asset_description: String,
     pub legal_doc_hash: String,
     pub status: CollateralStatus,
@@ -298,6 +514,31 @@ pub struct CanisterConfig {
     pub emergency_reserve_percentage: u64,
     pub auto_top_up_percentage: u64,
     pub cycle_monitoring_interval: u64,
+    // Liquidity pool fees (basis points, routed to treasury); None means no fee
+    pub deposit_fee_bps: Option<u64>,
+    pub withdrawal_fee_bps: Option<u64>,
+    // ckBTC ledger/minter retry policy; None means 3 attempts with a 1-round base delay
+    pub ckbtc_retry_max_attempts: Option<u64>,
+    pub ckbtc_retry_base_delay_rounds: Option<u64>,
+    // Sandbox/testnet mode; None means production
+    pub sandbox_mode: Option<bool>,
+    // ckBTC canisters; None means the mainnet defaults
+    pub ckbtc_ledger_principal: Option<Principal>,
+    pub ckbtc_minter_principal: Option<Principal>,
+    // ICP ledger; None means the mainnet default
+    pub icp_ledger_principal: Option<Principal>,
+    // Pool reserve ratio in basis points; None keeps the 5% the pool has always held back
+    pub pool_reserve_ratio_bps: Option<u64>,
+    // Pool reserve tiers on top of pool_reserve_ratio_bps; None keeps a flat ratio
+    pub reserve_min_absolute: Option<u64>,     // Satoshi floor so small pools keep a usable buffer
+    pub reserve_taper_threshold: Option<u64>,  // Pool size above which the tapered ratio applies
+    pub reserve_taper_ratio_bps: Option<u64>,  // Ratio on liquidity above the threshold
+    // Rounding of fee and yield shares; None means RoundingMode::Down
+    pub rounding_mode: Option<RoundingMode>,
+    // Payer of the Bitcoin network fee on ckBTC disbursements; None means FeeBearer::Borrower
+    pub disbursement_fee_bearer: Option<FeeBearer>,
+    // Canister subaccounts holding ckBTC besides the default account; None means none
+    pub reconciliation_subaccounts: Option<Vec<Vec<u8>>>,
 }
 
 impl Default for CanisterConfig {
@@ -320,10 +561,52 @@ impl Default for CanisterConfig {
             emergency_reserve_percentage: 20, // 20%
             auto_top_up_percentage: 150, // 150%
             cycle_monitoring_interval: 3600, // 1 hour
+            // No pool fees unless set by governance
+            deposit_fee_bps: None,
+            withdrawal_fee_bps: None,
+            ckbtc_retry_max_attempts: None,
+            ckbtc_retry_base_delay_rounds: None,
+            sandbox_mode: None,
+            ckbtc_ledger_principal: None,
+            ckbtc_minter_principal: None,
+            icp_ledger_principal: None,
+            pool_reserve_ratio_bps: None,
+            reserve_min_absolute: None,
+            reserve_taper_threshold: None,
+            reserve_taper_ratio_bps: None,
+            rounding_mode: None,
+            disbursement_fee_bearer: None,
+            reconciliation_subaccounts: None,
         }
     }
 }
 
+impl CanisterConfig {
+    pub fn deposit_fee_bps(&self) -> u64 {
+        self.deposit_fee_bps.unwrap_or(0)
+    }
+
+    pub fn withdrawal_fee_bps(&self) -> u64 {
+        self.withdrawal_fee_bps.unwrap_or(0)
+    }
+
+    pub fn ckbtc_retry_max_attempts(&self) -> u64 {
+        self.ckbtc_retry_max_attempts.unwrap_or(3)
+    }
+
+    pub fn ckbtc_retry_base_delay_rounds(&self) -> u64 {
+        self.ckbtc_retry_base_delay_rounds.unwrap_or(1)
+    }
+
+    pub fn pool_reserve_ratio_bps(&self) -> u64 {
+        self.pool_reserve_ratio_bps.unwrap_or(500)
+    }
+
+    pub fn reconciliation_subaccounts(&self) -> Vec<Vec<u8>> {
+        self.reconciliation_subaccounts.clone().unwrap_or_default()
+    }
+}
+
 // Loan Lifecycle Types
 #[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
 pub enum LoanStatus {
@@ -333,6 +616,9 @@ pub enum LoanStatus {
     Active,             // Dana sudah cair, pinjaman aktif
     Repaid,             // Lunas
     Defaulted,          // Gagal bayar
+    Expired,            // Aplikasi kedaluwarsa sebelum diputuskan
+    Withdrawn,          // Aplikasi dibatalkan oleh peminjam
+    Liquidating,        // Likuidasi sedang berjalan, percobaan lain ditolak
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -350,168 +636,139 @@ pub struct Loan {
     pub total_repaid: u64,          // Total yang sudah dibayar
     pub repayment_history: Vec<Payment>, // Riwayat pembayaran
     pub last_payment_date: Option<u64>,  // Tanggal pembayaran terakhir
+    pub region: Option<String>,          // Wilayah agunan, disalin dari metadata NFT saat origination
+    pub rate_type: Option<RateType>,     // Jenis suku bunga; None berarti tetap (pinjaman lama)
+    pub asset: Option<Asset>,            // Mata uang pinjaman; None berarti ckBTC (pinjaman lama)
 }
 
-#[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct LoanApplication {
-    pub nft_id: u64,
-    pub amount_requested: u64,
-    pub commodity_type: String,
-    pub quantity: u64,
-    pub grade: String,
+// Interest rate regime for a loan. `Loan::apr` always holds the current effective rate.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum RateType {
+    Fixed,
+    Variable(VariableRateTerms),
+    Promotional(PromoRateTerms), // Fixed at a campaign rate; never repriced
 }
 
-#[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct CommodityPrice {
-    pub price_per_unit: u64, // Harga per unit dalam satoshi
-    pub currency: String,
-    pub timestamp: u64,
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct PromoRateTerms {
+    pub campaign_id: u64,
+    pub standard_apr: u64, // Base APR the loan would have carried without the campaign
 }
 
-impl Storable for CommodityPrice {
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())  // Ubah dari Encode!
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).unwrap()            // Ubah dari Decode!
-    }
-    
-    const BOUND: Bound = Bound::Unbounded;
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct VariableRateTerms {
+    pub initial_apr: u64,            // Rate in force from created_at until the first reset
+    pub reset_interval_seconds: u64,
+    pub next_reset_at: u64,          // Nanoseconds
+    pub resets: Vec<RateResetRecord>,
 }
 
-// Struktur untuk repayment summary dan detail
-#[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct LoanRepaymentSummary {
-    pub loan_id: u64,
-    pub borrower: Principal,
-    pub total_debt: u64,           // Total utang (pokok + bunga akumulasi)
-    pub principal_outstanding: u64,  // Sisa pokok
-    pub interest_outstanding: u64,   // Sisa bunga
-    pub total_repaid: u64,          // Total yang sudah dibayar
-    pub remaining_balance: u64,     // Sisa yang harus dibayar
-    pub next_payment_due: Option<u64>, // Tanggal pembayaran berikutnya
-    pub is_overdue: bool,           // Apakah terlambat
-    pub days_overdue: u64,          // Jumlah hari terlambat
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct RateResetRecord {
+    pub effective_from: u64,   // Scheduled reset time the new rate accrues from
+    pub previous_apr: u64,
+    pub new_apr: u64,
+    pub target_apr: u64,       // Model rate before the per-reset movement cap
+    pub utilization_bps: u64,  // Pool utilization observed at the reset
+    pub processed_at: u64,
 }
 
+// Outstanding exposure and credit quality for one region
 #[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct RepaymentPlan {
-    pub loan_id: u64,
-    pub total_amount_due: u64,
-    pub principal_amount: u64,
-    pub interest_amount: u64,
-    pub protocol_fee: u64,
-    pub due_date: u64,
-    pub minimum_payment: u64,
+pub struct RegionalExposure {
+    pub region: String,
+    pub outstanding_amount: u64,    // Principal outstanding on approved and active loans (satoshi)
+    pub loan_count: u64,            // Approved and active loans
+    pub default_rate: f64,          // Defaulted loans as a percentage of funded loans
+    pub average_health_ratio: f64,  // Across active loans
+    pub share_bps: u64,             // Share of total outstanding principal
 }
 
-// Response structure untuk repayment
-#[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct RepaymentResponse {
-    pub success: bool,
-    pub message: String,
-    pub transaction_id: Option<String>,
-    pub new_loan_status: LoanStatus,
-    pub remaining_balance: u64,
-    pub collateral_released: bool,
+// Borrowing capacity preview for a collateral NFT
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum BorrowLimitFactor {
+    LtvBound,               // Limited by collateral value x commodity LTV
+    PoolSingleLoanCap,      // Limited by the per-loan share of total pool liquidity
+    PoolAvailableLiquidity, // Limited by currently available pool liquidity
 }
 
-// Additional comprehensive types untuk production loan repayment features
 #[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct ComprehensiveRepaymentAnalytics {
-    pub total_loans_count: u64,
-    pub active_loans_count: u64,
-    pub repaid_loans_count: u64,
-    pub defaulted_loans_count: u64,
-    pub total_principal_paid: u64,
-    pub total_interest_paid: u64,
-    pub total_fees_collected: u64,
-    pub overdue_loans_count: u64,
-    pub total_overdue_amount: u64,
-    pub early_repayments_count: u64,
-    pub average_repayment_time: u64, // in days
-    pub current_timestamp: u64,
+pub struct MaxBorrowable {
+    pub nft_id: u64,
+    pub commodity_type: String,
+    pub collateral_value_btc: u64,
+    pub ltv_ratio: u64,
+    pub ltv_limit: u64,
+    pub existing_exposure: u64,
+    pub pool_single_loan_cap: u64,
+    pub pool_available_liquidity: u64,
+    pub max_borrowable: u64,
+    pub limiting_factor: BorrowLimitFactor,
 }
 
+// Loan restructuring types
 #[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct LoanPerformanceMetrics {
-    pub loan_id: u64,
-    pub is_performing: bool,
-    pub repayment_rate: u64, // percentage (0-100)
-    pub payment_frequency: u64, // payments per month
-    pub total_payments_made: u64,
-    pub days_since_last_payment: u64,
+pub struct RestructureProposal {
+    pub new_due_date: Option<u64>,   // Tanggal jatuh tempo baru (nanoseconds)
+    pub new_term_days: Option<u64>,  // Tenor baru dihitung dari created_at
+    pub new_apr: Option<u64>,        // Penyesuaian suku bunga (tidak boleh naik)
+    pub reason: String,
 }
 
+// Principals blocked from sensitive update calls
 #[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct BatchRepaymentRequest {
-    pub loan_id: u64,
-    pub amount: u64,
+pub struct BlacklistEntry {
+    pub principal: Principal,
+    pub reason: String,
+    pub blacklisted_by: Principal,
+    pub blacklisted_at: u64,
 }
 
-#[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct BatchRepaymentResult {
-    pub loan_id: u64,
-    pub success: bool,
-    pub message: String,
-    pub transaction_id: Option<String>,
-}
+impl Storable for BlacklistEntry {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
 
-#[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct RepaymentStatistics {
-    pub total_loans: u64,
-    pub active_loans: u64,
-    pub repaid_loans: u64,
-    pub defaulted_loans: u64,
-    pub total_repaid_amount: u64,
-    pub total_outstanding_amount: u64,
-    pub average_repayment_time: u64, // in days
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
 }
 
-// Repayment forecasting untuk financial planning
-#[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct RepaymentForecast {
-    pub month: u64,
-    pub forecast_date: u64,
-    pub projected_interest: u64,
-    pub projected_total_debt: u64,
-    pub projected_remaining_balance: u64,
-    pub recommended_payment: u64,
+// Disbursement destinations rejected for compliance reasons
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum BtcDenyRuleKind {
+    Exact,  // The full address
+    Prefix, // Any address starting with the pattern
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct NFTMetadata {
-    pub valuation_idr: u64,
-    pub commodity_type: String,
-    pub quantity: u64,
-    pub grade: String,
-    pub warehouse_receipt_hash: String,
+pub struct BtcAddressDenyRule {
+    pub pattern: String, // Normalized: bech32 patterns are lowercased
+    pub kind: BtcDenyRuleKind,
+    pub reason: String,
+    pub added_by: Principal,
+    pub added_at: u64,
 }
 
-#[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct ProtocolParameters {
-    pub loan_to_value_ratio: u64, // Default 60%
-    pub base_apr: u64,            // Default 10%
-    pub max_loan_duration_days: u64, // Default 365 days
-    pub grace_period_days: u64,   // Default 30 days
-}
+impl BtcAddressDenyRule {
+    pub fn storage_key(kind: BtcDenyRuleKind, pattern: &str) -> String {
+        match kind {
+            BtcDenyRuleKind::Exact => format!("exact:{}", pattern),
+            BtcDenyRuleKind::Prefix => format!("prefix:{}", pattern),
+        }
+    }
 
-impl Default for ProtocolParameters {
-    fn default() -> Self {
-        Self {
-            loan_to_value_ratio: 60,
-            base_apr: 10,
-            max_loan_duration_days: 365,
-            grace_period_days: 30,
+    pub fn matches(&self, normalized_address: &str) -> bool {
+        match self.kind {
+            BtcDenyRuleKind::Exact => normalized_address == self.pattern,
+            BtcDenyRuleKind::Prefix => normalized_address.starts_with(&self.pattern),
         }
     }
 }
 
-// Implement Storable for RWANFTData
-impl Storable for RWANFTData {
-    const BOUND: Bound = Bound::Unbounded;
-    
+impl Storable for BtcAddressDenyRule {
     fn to_bytes(&self) -> Cow<[u8]> {
         Cow::Owned(candid::encode_one(self).unwrap())
     }
@@ -519,14 +776,704 @@ impl Storable for RWANFTData {
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
         candid::decode_one(&bytes).unwrap()
     }
+    
+    const BOUND: Bound = Bound::Unbounded;
 }
 
-// Implement Storable for CollateralRecord
-impl Storable for CollateralRecord {
-    const BOUND: Bound = Bound::Unbounded;
-    
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())
+// Admin approval to disburse a loan to an address other than the borrower's registered one
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DisbursementAddressOverride {
+    pub loan_id: u64,
+    pub btc_address: String,
+    pub reason: String,
+    pub approved_by: Principal,
+    pub approved_at: u64,
+}
+
+impl Storable for DisbursementAddressOverride {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Outbound webhook for liquidation events. The shared secret never leaves the canister.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct OutboundWebhookConfig {
+    pub enabled: bool,
+    pub endpoint_url: Option<String>,
+    pub hmac_secret: String,
+    pub max_attempts: u64,
+    pub updated_by: Principal,
+    pub updated_at: u64,
+}
+
+impl Storable for OutboundWebhookConfig {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Redacted view of the webhook configuration for admins
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct OutboundWebhookStatus {
+    pub enabled: bool,
+    pub endpoint_url: Option<String>,
+    pub has_secret: bool,
+    pub max_attempts: u64,
+    pub updated_by: Principal,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct OutboundEventDelivery {
+    pub id: u64,
+    pub event_type: String,
+    pub loan_id: u64,
+    pub endpoint_url: String,
+    pub payload: String,
+    pub status: DeliveryStatus,
+    pub attempts: u64,
+    pub last_status_code: Option<u64>,
+    pub failure_reason: Option<String>,
+    pub created_at: u64,
+    pub last_attempt_at: Option<u64>,
+}
+
+impl Storable for OutboundEventDelivery {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum MaintenanceWindowStatus {
+    Scheduled,
+    Active,
+    Completed,
+    Cancelled,
+}
+
+// Scheduled maintenance; an empty affected_operations list means every operation is paused
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct MaintenanceWindow {
+    pub id: u64,
+    pub start: u64,
+    pub end: u64,
+    pub affected_operations: Vec<String>,
+    pub message: String,
+    pub status: MaintenanceWindowStatus,
+    pub scheduled_by: Principal,
+    pub scheduled_at: u64,
+}
+
+impl MaintenanceWindow {
+    pub fn is_full_scope(&self) -> bool {
+        self.affected_operations.is_empty()
+    }
+
+    /// Whether this window blocks `operation` at `now`, independent of heartbeat transitions
+    pub fn blocks_operation(&self, operation: &str, now: u64) -> bool {
+        if self.status == MaintenanceWindowStatus::Cancelled || now < self.start || now >= self.end {
+            return false;
+        }
+        self.is_full_scope() || self.affected_operations.iter().any(|op| op == operation)
+    }
+}
+
+impl Storable for MaintenanceWindow {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Data domains whose mutations bump a state version counter
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum StateDomain {
+    Pool,
+    Loans,
+    Notifications,
+    Oracle,
+}
+
+impl StateDomain {
+    pub fn storage_key(&self) -> u8 {
+        match self {
+            StateDomain::Pool => 0,
+            StateDomain::Loans => 1,
+            StateDomain::Notifications => 2,
+            StateDomain::Oracle => 3,
+        }
+    }
+}
+
+// Monotonic ID counters persisted in stable memory
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum IdCounter {
+    NftToken,
+    Collateral,
+    AuditLog,
+    Loan,
+    Disbursement,
+    PriceAlert,
+    LoanEvent,
+    PositionOffer,
+    PromoCampaign,
+}
+
+impl IdCounter {
+    pub const ALL: [IdCounter; 9] = [
+        IdCounter::NftToken,
+        IdCounter::Collateral,
+        IdCounter::AuditLog,
+        IdCounter::Loan,
+        IdCounter::Disbursement,
+        IdCounter::PriceAlert,
+        IdCounter::LoanEvent,
+        IdCounter::PositionOffer,
+        IdCounter::PromoCampaign,
+    ];
+
+    pub fn storage_key(&self) -> u8 {
+        match self {
+            IdCounter::NftToken => 0,
+            IdCounter::Collateral => 1,
+            IdCounter::AuditLog => 2,
+            IdCounter::Loan => 3,
+            IdCounter::Disbursement => 4,
+            IdCounter::PriceAlert => 5,
+            IdCounter::LoanEvent => 6,
+            IdCounter::PositionOffer => 7,
+            IdCounter::PromoCampaign => 8,
+        }
+    }
+}
+
+// A counter found at or below an existing key and moved past it
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct IdCounterRepair {
+    pub counter: IdCounter,
+    pub previous_next_id: u64,
+    pub repaired_next_id: u64,
+    pub max_existing_id: u64,
+}
+
+// Per-domain change counters. Counters are stable and never decrease, including across upgrades,
+// so clients can refetch a domain whenever its counter differs from the last value they saw.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct StateVersion {
+    pub pool: u64,
+    pub loans: u64,
+    pub notifications: u64,
+    pub oracle: u64,
+}
+
+// Underwriting workflow for loan applications
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum UnderwritingState {
+    Submitted,
+    UnderReview,
+    NeedsInfo,
+    Approved,
+    Rejected,
+    Expired,
+    Withdrawn,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ReviewNote {
+    pub actor: Principal,
+    pub note: String,
+    pub from_state: UnderwritingState,
+    pub to_state: UnderwritingState,
+    pub timestamp: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LoanApplicationReview {
+    pub loan_id: u64,
+    pub borrower: Principal,
+    pub state: UnderwritingState,
+    pub underwriter: Option<Principal>,
+    pub thread: Vec<ReviewNote>,
+    pub submitted_at: u64,
+    pub updated_at: u64,
+    pub expires_at: Option<u64>, // Undecided applications expire at this time; None for applications submitted before expiry existed
+}
+
+impl Storable for LoanApplicationReview {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum RestructureStatus {
+    Pending,
+    Approved,
+    Rejected(String),
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LoanRestructureRequest {
+    pub request_id: u64,
+    pub loan_id: u64,
+    pub borrower: Principal,
+    pub proposal: RestructureProposal,
+    pub status: RestructureStatus,
+    pub requested_at: u64,
+    pub reviewed_by: Option<Principal>,
+    pub reviewed_at: Option<u64>,
+    pub previous_due_date: Option<u64>,
+    pub previous_apr: u64,
+    pub debt_before: Option<u64>,
+    pub debt_after: Option<u64>,
+}
+
+impl Storable for LoanRestructureRequest {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LoanApplication {
+    pub nft_id: u64,
+    pub amount_requested: u64,
+    pub commodity_type: String,
+    pub quantity: u64,
+    pub grade: String,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CommodityPrice {
+    pub price_per_unit: u64, // Harga per unit dalam satoshi
+    pub currency: String,
+    pub timestamp: u64,
+}
+
+impl Storable for CommodityPrice {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())  // Ubah dari Encode!
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()            // Ubah dari Decode!
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Struktur untuk repayment summary dan detail
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LoanRepaymentSummary {
+    pub loan_id: u64,
+    pub borrower: Principal,
+    pub total_debt: u64,           // Total utang (pokok + bunga akumulasi)
+    pub principal_outstanding: u64,  // Sisa pokok
+    pub interest_outstanding: u64,   // Sisa bunga
+    pub total_repaid: u64,          // Total yang sudah dibayar
+    pub remaining_balance: u64,     // Sisa yang harus dibayar
+    pub next_payment_due: Option<u64>, // Tanggal pembayaran berikutnya
+    pub is_overdue: bool,           // Apakah terlambat
+    pub days_overdue: u64,          // Jumlah hari terlambat
+    pub current_apr: u64,           // Suku bunga efektif saat ini
+    pub is_variable_rate: bool,
+    pub upcoming_rate_resets: Vec<u64>, // Jadwal reset suku bunga berikutnya (pinjaman variabel)
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct RepaymentPlan {
+    pub loan_id: u64,
+    pub total_amount_due: u64,
+    pub principal_amount: u64,
+    pub interest_amount: u64,
+    pub protocol_fee: u64,
+    pub due_date: u64,
+    pub minimum_payment: u64,
+    pub network_fee_charged: Option<u64>, // Borrower-paid network fee on disbursement, included in principal_amount
+}
+
+// Amortization schedule
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum RepaymentStructure {
+    FixedInstallment, // Equal monthly installments over the term
+    Bullet,           // Single payment at due date
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct AmortizationRow {
+    pub installment_no: u32,
+    pub due_date: u64,
+    pub payment_amount: u64,     // principal + interest + fee
+    pub principal_portion: u64,
+    pub interest_portion: u64,   // Interest to investors, net of protocol fee
+    pub fee_portion: u64,        // Protocol share of interest
+    pub remaining_balance: u64,  // Principal outstanding after this installment
+    pub is_paid: bool,
+    pub paid_at: Option<u64>,
+}
+
+// Collateral NFT fields shown on a loan detail page
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CollateralNftSummary {
+    pub token_id: u64,
+    pub owner: Principal,
+    pub is_locked: bool,
+    pub locked_for_loan: Option<u64>,
+    pub valuation_idr: Idr,
+    pub asset_description: String,
+    pub legal_doc_hash: String,
+}
+
+// Everything a loan detail page needs in one response
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LoanFullDetails {
+    pub loan: Loan,
+    pub collateral: Option<CollateralNftSummary>, // None if the NFT record is missing
+    pub disbursements: Vec<DisbursementRecord>,
+    pub repayments: Vec<RepaymentRecord>,
+    pub health_ratio: Option<f64>,                // None before an amount is approved
+    pub repayment_summary: Option<LoanRepaymentSummary>,
+    pub amortization_schedule: Vec<AmortizationRow>,
+    pub liquidation: Option<LiquidationRecord>,
+    pub generated_at: u64,
+}
+
+// Response structure untuk repayment
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct RepaymentResponse {
+    pub success: bool,
+    pub message: String,
+    pub transaction_id: Option<String>,
+    pub new_loan_status: LoanStatus,
+    pub remaining_balance: u64,
+    pub collateral_released: bool,
+}
+
+// Additional comprehensive types untuk production loan repayment features
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ComprehensiveRepaymentAnalytics {
+    pub total_loans_count: u64,
+    pub active_loans_count: u64,
+    pub repaid_loans_count: u64,
+    pub defaulted_loans_count: u64,
+    pub total_principal_paid: u64,
+    pub total_interest_paid: u64,
+    pub total_fees_collected: u64,
+    pub overdue_loans_count: u64,
+    pub total_overdue_amount: u64,
+    pub early_repayments_count: u64,
+    pub average_repayment_time: u64, // in days
+    pub current_timestamp: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LoanPerformanceMetrics {
+    pub loan_id: u64,
+    pub is_performing: bool,
+    pub repayment_rate: u64, // percentage (0-100)
+    pub payment_frequency: u64, // payments per month
+    pub total_payments_made: u64,
+    pub days_since_last_payment: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct BatchRepaymentRequest {
+    pub loan_id: u64,
+    pub amount: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct BatchRepaymentResult {
+    pub loan_id: u64,
+    pub success: bool,
+    pub message: String,
+    pub transaction_id: Option<String>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct RepaymentStatistics {
+    pub total_loans: u64,
+    pub active_loans: u64,
+    pub repaid_loans: u64,
+    pub defaulted_loans: u64,
+    pub total_repaid_amount: u64,
+    pub total_outstanding_amount: u64,
+    pub average_repayment_time: u64, // in days
+}
+
+// Early repayment outcome: interest discount against the prepayment penalty. A negative
+// net_benefit is the net cost of repaying now.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct EarlyRepaymentBenefits {
+    pub interest_discount: u64,
+    pub prepayment_penalty: u64,
+    pub net_benefit: i64,
+}
+
+// Repayment forecasting untuk financial planning
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct RepaymentForecast {
+    pub month: u64,
+    pub forecast_date: u64,
+    pub projected_interest: u64,
+    pub projected_total_debt: u64,
+    pub projected_remaining_balance: u64,
+    pub recommended_payment: u64,
+    pub projected_prepayment_penalty: u64, // Penalty if paid off in full at forecast_date
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct NFTMetadata {
+    pub valuation_idr: Idr,
+    pub commodity_type: String,
+    pub quantity: u64,
+    pub grade: String,
+    pub warehouse_receipt_hash: String,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ProtocolParameters {
+    pub loan_to_value_ratio: u64, // Default 60%
+    pub base_apr: u64,            // Default 10%
+    pub max_loan_duration_days: u64, // Default 365 days
+    pub grace_period_days: u64,   // Default 30 days
+    pub commodity_ltv_overrides: Option<std::collections::HashMap<String, u64>>, // commodity -> LTV %, falls back to loan_to_value_ratio; None means none
+    pub max_commodity_exposure_bps: Option<u64>, // Max share of outstanding collateral backed by one commodity; None means no limit
+    pub pricing_mode: Option<PricingMode>, // Price feed used for origination and liquidation math; None means Spot
+    pub twap_window_seconds: Option<u64>,  // Averaging window when pricing_mode is Twap; None means 6 hours
+    pub region_exposure_caps: Option<std::collections::HashMap<String, u64>>, // region -> max share (bps) of outstanding principal; keys are the region allowlist; None means none
+    pub prepayment_penalty_bps: Option<u64>,      // Charged on outstanding principal for full repayment inside the penalty window; None means 0
+    pub prepayment_free_window_days: Option<u64>, // Days after origination during which full prepayment is penalized; None means 0
+    pub compounding_frequency: Option<CompoundingFrequency>, // Interest accrual model; None means simple interest
+    pub compounding_changes: Option<Vec<(u64, CompoundingFrequency)>>, // (activated_at, model), oldest first; None means compounding_frequency since origination
+    pub paused_commodities: Option<Vec<String>>, // Commodities closed to new loans, lowercase and sorted; None means none
+    pub pool_apy: Option<PoolApyParameters>, // Liquidity pool APY model; None means the defaults
+}
+
+impl ProtocolParameters {
+    pub fn commodity_ltv_overrides(&self) -> std::collections::HashMap<String, u64> {
+        self.commodity_ltv_overrides.clone().unwrap_or_default()
+    }
+
+    pub fn max_commodity_exposure_bps(&self) -> u64 {
+        self.max_commodity_exposure_bps.unwrap_or(10_000)
+    }
+
+    pub fn pricing_mode(&self) -> PricingMode {
+        self.pricing_mode.clone().unwrap_or(PricingMode::Spot)
+    }
+
+    pub fn twap_window_seconds(&self) -> u64 {
+        self.twap_window_seconds.unwrap_or(6 * 60 * 60) // 6 hours
+    }
+
+    pub fn region_exposure_caps(&self) -> std::collections::HashMap<String, u64> {
+        self.region_exposure_caps.clone().unwrap_or_default()
+    }
+
+    pub fn prepayment_penalty_bps(&self) -> u64 {
+        self.prepayment_penalty_bps.unwrap_or(0)
+    }
+
+    pub fn prepayment_free_window_days(&self) -> u64 {
+        self.prepayment_free_window_days.unwrap_or(0)
+    }
+
+    pub fn compounding(&self) -> CompoundingFrequency {
+        self.compounding_frequency.clone().unwrap_or_default()
+    }
+
+    /// Accrual models with the time each took effect, oldest first, starting at 0
+    pub fn compounding_schedule(&self) -> Vec<(u64, CompoundingFrequency)> {
+        self.compounding_changes.clone().unwrap_or_else(|| vec![(0, self.compounding())])
+    }
+
+    /// Switch the accrual model from `now` on; interest before `now` keeps the earlier model
+    pub fn activate_compounding(&mut self, frequency: CompoundingFrequency, now: u64) {
+        let mut schedule = self.compounding_schedule();
+        schedule.push((now, frequency.clone()));
+        self.compounding_changes = Some(schedule);
+        self.compounding_frequency = Some(frequency);
+    }
+
+    pub fn pool_apy(&self) -> PoolApyParameters {
+        self.pool_apy.clone().unwrap_or_default()
+    }
+}
+
+// Quoted liquidity pool APY: a base rate plus bonuses for utilization and the pool's repayment
+// rate, held between the base rate (floor) and max_apy_bps (cap)
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct PoolApyParameters {
+    pub base_apy_bps: u64,
+    pub max_apy_bps: u64,
+    pub utilization_bonus_per_pct: u64, // APY bps added per percentage point of utilization
+    pub repayment_bonus_high_threshold: u64, // Repayment rate (%) above which the high bonus applies
+    pub repayment_bonus_high_bps: u64,
+    pub repayment_bonus_low_threshold: u64, // Repayment rate (%) above which the low bonus applies
+    pub repayment_bonus_low_bps: u64,
+}
+
+impl Default for PoolApyParameters {
+    fn default() -> Self {
+        Self {
+            base_apy_bps: 300,
+            max_apy_bps: 1_500,
+            utilization_bonus_per_pct: 5,
+            repayment_bonus_high_threshold: 90,
+            repayment_bonus_high_bps: 200,
+            repayment_bonus_low_threshold: 75,
+            repayment_bonus_low_bps: 100,
+        }
+    }
+}
+
+impl PoolApyParameters {
+    /// `apy_bps` held inside the floor and cap; the cap wins if the two ever cross
+    pub fn clamp_apy(&self, apy_bps: u64) -> u64 {
+        apy_bps.max(self.base_apy_bps).min(self.max_apy_bps)
+    }
+
+    /// Copy with the governance parameter `key` set to `value`, or None if `key` is not part
+    /// of the APY model
+    pub fn with_parameter(&self, key: &str, value: u64) -> Option<Self> {
+        let mut updated = self.clone();
+        match key {
+            "base_apy_bps" => updated.base_apy_bps = value,
+            "max_apy_bps" => updated.max_apy_bps = value,
+            "utilization_bonus_per_pct" => updated.utilization_bonus_per_pct = value,
+            "repayment_bonus_high_threshold" => updated.repayment_bonus_high_threshold = value,
+            "repayment_bonus_high_bps" => updated.repayment_bonus_high_bps = value,
+            "repayment_bonus_low_threshold" => updated.repayment_bonus_low_threshold = value,
+            "repayment_bonus_low_bps" => updated.repayment_bonus_low_bps = value,
+            _ => return None,
+        }
+        Some(updated)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.base_apy_bps > self.max_apy_bps {
+            return Err(format!(
+                "Base APY {} bps cannot exceed the APY cap of {} bps",
+                self.base_apy_bps, self.max_apy_bps
+            ));
+        }
+        if self.repayment_bonus_low_threshold > self.repayment_bonus_high_threshold {
+            return Err(format!(
+                "Low repayment bonus threshold {}% cannot exceed the high threshold of {}%",
+                self.repayment_bonus_low_threshold, self.repayment_bonus_high_threshold
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum PricingMode {
+    Spot,
+    Twap,
+}
+
+// How accrued interest is added back to the balance. Compounding periods are counted from origination,
+// or from the model's activation for loans originated before it.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum CompoundingFrequency {
+    Simple,
+    Daily,
+    Monthly,
+}
+
+impl Default for CompoundingFrequency {
+    fn default() -> Self {
+        CompoundingFrequency::Simple
+    }
+}
+
+impl Default for ProtocolParameters {
+    fn default() -> Self {
+        Self {
+            loan_to_value_ratio: 60,
+            base_apr: 10,
+            max_loan_duration_days: 365,
+            grace_period_days: 30,
+            commodity_ltv_overrides: None,
+            max_commodity_exposure_bps: None, // No limit until configured by governance
+            pricing_mode: None,
+            twap_window_seconds: None,
+            region_exposure_caps: None,
+            prepayment_penalty_bps: None,
+            prepayment_free_window_days: None,
+            compounding_frequency: None,
+            compounding_changes: None,
+            paused_commodities: None,
+            pool_apy: None,
+        }
+    }
+}
+
+// Implement Storable for RWANFTData
+impl Storable for RWANFTData {
+    const BOUND: Bound = Bound::Unbounded;
+    
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+}
+
+// Implement Storable for CollateralRecord
+impl Storable for CollateralRecord {
+    const BOUND: Bound = Bound::Unbounded;
+    
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
@@ -595,6 +1542,8 @@ pub struct DisbursementRecord {
     pub ckbtc_block_index: u64,
     pub disbursed_at: u64,
     pub disbursed_by: Principal,
+    pub network_fee: Option<u64>,        // Bitcoin network and minter fee on the withdrawal; None for ICP or older records
+    pub fee_bearer: Option<FeeBearer>,   // Who paid `network_fee`
 }
 
 impl Storable for DisbursementRecord {
@@ -616,6 +1565,15 @@ pub struct Payment {
     pub timestamp: u64,
     pub payment_type: PaymentType,
     pub transaction_id: Option<String>,
+    pub allocation: Option<PaymentBreakdown>, // How the payment was split; None for older payments
+    pub source: Option<RepaymentSource>, // Where the funds came from; None for older payments
+}
+
+// Asal dana pembayaran: transfer ckBTC on-chain atau pembayaran di luar ledger yang dicatat operator
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum RepaymentSource {
+    CkBtc,
+    OffChain,
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -647,6 +1605,47 @@ pub struct PaymentBreakdown {
     pub total_amount: u64,
 }
 
+// Debt components a repayment can be applied to
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum RepaymentBucket {
+    Fees,      // Late payment penalties
+    Interest,
+    Principal,
+}
+
+// Order in which a repayment is applied; overflow cascades to the next bucket
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct RepaymentAllocationOrder {
+    pub code: u64,
+    pub buckets: Vec<RepaymentBucket>,
+}
+
+impl RepaymentAllocationOrder {
+    /// Every permutation of the three buckets, indexed by the governance parameter value.
+    /// Code 0 (fees, interest, principal) is the original allocation order.
+    const ORDERS: [[RepaymentBucket; 3]; 6] = [
+        [RepaymentBucket::Fees, RepaymentBucket::Interest, RepaymentBucket::Principal],
+        [RepaymentBucket::Fees, RepaymentBucket::Principal, RepaymentBucket::Interest],
+        [RepaymentBucket::Interest, RepaymentBucket::Fees, RepaymentBucket::Principal],
+        [RepaymentBucket::Interest, RepaymentBucket::Principal, RepaymentBucket::Fees],
+        [RepaymentBucket::Principal, RepaymentBucket::Fees, RepaymentBucket::Interest],
+        [RepaymentBucket::Principal, RepaymentBucket::Interest, RepaymentBucket::Fees],
+    ];
+
+    pub fn from_code(code: u64) -> Option<Self> {
+        Self::ORDERS.get(code as usize).map(|buckets| Self {
+            code,
+            buckets: buckets.to_vec(),
+        })
+    }
+}
+
+impl Default for RepaymentAllocationOrder {
+    fn default() -> Self {
+        Self::from_code(0).unwrap()
+    }
+}
+
 impl Default for PaymentBreakdown {
     fn default() -> Self {
         Self {
@@ -664,9 +1663,94 @@ pub struct RepaymentRecord {
     pub loan_id: u64,
     pub payer: Principal,
     pub amount: u64,
-    pub ckbtc_block_index: u64,
+    pub ckbtc_block_index: u64, // 0 for off-chain repayments
     pub timestamp: u64,
     pub payment_breakdown: PaymentBreakdown,
+    pub source: Option<RepaymentSource>, // None for records written before sources were tracked
+}
+
+// Warehouse receipt re-attestation provenance for an RWA-NFT
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct AttestationRecord {
+    pub version: u64,
+    pub previous_hash: String,
+    pub new_hash: String,
+    pub attestation_doc_ref: String,
+    pub attestor: Principal,
+    pub timestamp: u64,
+    pub receipt_expiry: Option<u64>, // Expiry of the renewed receipt, when it has one
+}
+
+// Where a warehouse receipt stands against its validity period
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum ReceiptExpiryStatus {
+    Untracked,    // No rwa:receipt_expiry on the NFT
+    Current,
+    ExpiringSoon, // Inside the governance warning window
+    Expired,
+}
+
+// Loan collateral whose warehouse receipt is expiring or has expired
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ExpiringCollateral {
+    pub loan_id: u64,
+    pub nft_id: u64,
+    pub borrower: Principal,
+    pub loan_status: LoanStatus,
+    pub receipt_expiry: u64,
+    pub status: ReceiptExpiryStatus,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct NFTAttestationHistory {
+    pub attestation_version: u64, // 0 = original minted receipt
+    pub records: Vec<AttestationRecord>,
+}
+
+impl Storable for NFTAttestationHistory {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Idempotency receipt for allowance-based (ICRC-2) repayments
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct AllowanceRepaymentReceipt {
+    pub request_id: u64,
+    pub loan_id: u64,
+    pub payer: Principal,
+    pub amount: u64,
+    pub ckbtc_block_index: Option<u64>, // None while the ledger call is in flight or its outcome is unknown
+    pub created_at: u64,
+    pub transfer_amount: Option<u64>,   // Amount pulled, including any prepayment penalty
+}
+
+impl Storable for AllowanceRepaymentReceipt {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum AllowanceRepaymentError {
+    InsufficientAllowance { allowance: u64, required: u64 },
+    InsufficientFunds { balance: u64, required: u64 },
+    InvalidRequest(String),
+    RequestInProgress,
+    LedgerError(String),
+    CallFailed(String),
 }
 
 impl Storable for RepaymentRecord {
@@ -692,6 +1776,7 @@ pub struct LiquidityPool {
     pub apy: u64,
     pub created_at: u64,
     pub updated_at: u64,
+    pub reserved_liquidity: Option<u64>, // Held for approved loans awaiting disbursement
 }
 
 impl Storable for LiquidityPool {
@@ -707,17 +1792,203 @@ impl Storable for LiquidityPool {
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct InvestorBalance {
-    pub investor: Principal,
-    pub balance: u64,
+pub struct InvestorBalance {
+    pub investor: Principal,
+    pub balance: u64,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    pub deposits: Vec<DepositRecord>,
+    pub withdrawals: Vec<WithdrawalRecord>,
+    pub first_deposit_at: u64,
+    pub last_activity_at: u64,
+    // Deposit and withdrawal fees paid to date. None only on balances written before schema
+    // v2; the v2 migration backfills it from the fee records.
+    pub total_fees_paid: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum PositionOfferStatus {
+    Open,
+    Settling,  // Buyer's ckBTC payment is in flight
+    Accepted,
+    Cancelled,
+    Failed,    // Payment went through but the position could not be moved; needs manual review
+}
+
+// An investor's offer to sell part of their pool position to another investor.
+// The offered amount stays in the seller's balance but cannot be withdrawn while the offer is open.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PositionOffer {
+    pub id: u64,
+    pub seller: Principal,
+    pub buyer: Option<Principal>, // None means any eligible investor may accept
+    pub amount: u64,              // Pool balance being sold, in satoshi
+    pub price: u64,               // ckBTC the buyer pays the seller, in satoshi
+    pub status: PositionOfferStatus,
+    pub created_at: u64,
+    pub accepted_by: Option<Principal>,
+    pub settlement_block_index: Option<u64>,
+    pub settled_at: Option<u64>,
+    pub payment_created_at: Option<u64>, // Ledger created_at of the buyer's payment, reused when settlement resumes
+}
+
+impl Storable for PositionOffer {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Reduced-rate lending campaign for selected farmers and/or a commodity.
+// Usage counters cover loans priced under the campaign that have not been withdrawn, rejected or expired.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PromoCampaign {
+    pub id: u64,
+    pub name: String,
+    pub rate_override_bps: u64,              // 0 = interest free; whole percentages only
+    pub eligible_principals: Vec<Principal>, // Empty means any borrower
+    pub eligible_commodity: Option<String>,  // None means any commodity
+    pub starts_at: u64,
+    pub ends_at: u64,
+    pub max_loans: u64,
+    pub budget: u64,                         // Principal that may be lent at the promo rate, in satoshi
+    pub loans_used: u64,
+    pub budget_used: u64,
+    pub loan_ids: Vec<u64>,
+    pub is_active: bool,                     // Cleared when an admin ends the campaign early
+    pub created_by: Principal,
+    pub created_at: u64,
+}
+
+impl Storable for PromoCampaign {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PromoUsage {
+    pub campaign_id: u64,
+    pub loans_used: u64,
+    pub max_loans: u64,
+    pub budget_used: u64,
+    pub budget: u64,
+    pub remaining_budget: u64,
+    pub loan_ids: Vec<u64>,
+    pub is_running: bool, // Active, within its window and not exhausted
+}
+
+/// Entities that operations staff can annotate with tags
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum TaggedEntityType {
+    Loan,
+    Investor,
+}
+
+impl TaggedEntityType {
+    pub fn key_prefix(&self) -> &'static str {
+        match self {
+            TaggedEntityType::Loan => "loan",
+            TaggedEntityType::Investor => "investor",
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct EntityTag {
+    pub tag: String,
+    pub added_by: Principal,
+    pub added_at: u64,
+}
+
+/// Operational labels for one entity. Never read by loan health, liquidation or pool math.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct EntityTags {
+    pub entity_type: TaggedEntityType,
+    pub entity_id: String,
+    pub tags: Vec<EntityTag>,
+}
+
+impl Storable for EntityTags {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Investor data visibility, resolved from the caller's admin role
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum InvestorDataAccessTier {
+    Observer, // Aggregate statistics only
+    Operator, // Balances without deposit/withdrawal history
+    Full,     // Complete investor records
+}
+
+/// Investor balance with transaction history stripped (operator tier)
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct RedactedInvestorBalance {
+    pub investor: Principal,
+    pub balance: u64,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    pub first_deposit_at: u64,
+    pub last_activity_at: u64,
+}
+
+/// Anonymized investor statistics (observer tier)
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct InvestorAggregateStats {
+    pub investor_count: u64,
+    pub active_investor_count: u64,
+    pub total_balance: u64,
     pub total_deposited: u64,
     pub total_withdrawn: u64,
-    pub deposits: Vec<DepositRecord>,
-    pub withdrawals: Vec<WithdrawalRecord>,
-    pub first_deposit_at: u64,
+    pub average_balance: u64,
+    pub largest_balance_share_bps: u64,
+}
+
+/// An investor balance too small to withdraw
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DustBalanceEntry {
+    pub investor: Principal,
+    pub balance: u64,
     pub last_activity_at: u64,
 }
 
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DustBalanceReport {
+    pub threshold: u64,
+    pub investor_count: u64,
+    pub total_dust: u64,
+    pub entries: Vec<DustBalanceEntry>,
+    pub generated_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DustSweepSummary {
+    pub threshold: u64,
+    pub swept_count: u64,
+    pub total_swept: u64,
+    pub skipped_count: u64, // Recently active or not coverable by available liquidity
+    pub insurance_fund_balance: u64,
+}
+
 impl Storable for InvestorBalance {
     fn to_bytes(&self) -> Cow<[u8]> {
         Cow::Owned(candid::encode_one(self).unwrap()) // Ubah dari Encode!(self).unwrap()
@@ -733,9 +2004,10 @@ impl Storable for InvestorBalance {
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct DepositRecord {
     pub investor: Principal,
-    pub amount: u64,
+    pub amount: u64,                // Credited amount, after the deposit fee
     pub ckbtc_block_index: u64,
     pub timestamp: u64,
+    pub fee: Option<u64>,           // Deposit fee charged; None for records made before fees were recorded
 }
 
 impl Storable for DepositRecord {
@@ -753,9 +2025,40 @@ impl Storable for DepositRecord {
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct WithdrawalRecord {
     pub investor: Principal,
-    pub amount: u64,
+    pub amount: u64,                // Debited amount, including the withdrawal fee
     pub ckbtc_block_index: u64,
     pub timestamp: u64,
+    pub fee: Option<u64>,           // Withdrawal fee charged; None for records made before fees were recorded
+    pub lots: Option<Vec<LotDraw>>, // Deposit lots drawn by a FIFO/LIFO withdrawal; None for aggregate withdrawals
+}
+
+/// Order in which a withdrawal consumes the investor's deposit lots
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum LotMethod {
+    #[default]
+    Aggregate, // Debit the balance without assigning lots; treated as newest-first
+    Fifo,
+    Lifo,
+}
+
+/// Part of one deposit lot consumed by a withdrawal
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct LotDraw {
+    pub deposit_index: u64,         // Position of the lot in `InvestorBalance.deposits`
+    pub deposited_at: u64,
+    pub amount: u64,
+}
+
+/// Yield and losses realized on the part of a deposit lot taken out by a withdrawal
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct RealizedLotGain {
+    pub withdrawn_at: u64,
+    pub deposit_index: u64,
+    pub deposited_at: u64,
+    pub amount: u64,
+    pub yield_earned: u64,
+    pub losses_allocated: u64,
+    pub net_gain: i64,
 }
 
 impl Storable for WithdrawalRecord {
@@ -789,17 +2092,88 @@ impl Storable for ProcessedTransaction {
     const BOUND: Bound = Bound::Unbounded;
 }
 
+// External dependency health
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum DependencyStatus {
+    Up,
+    Degraded, // Calls succeed but are slow, or recent calls failed
+    Down,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DependencyHealth {
+    pub name: String,
+    pub status: DependencyStatus,
+    pub last_success: Option<u64>,
+    pub p95_latency_ms: u64,
+    pub consecutive_failures: u32,
+}
+
+// Stable memory consumed per storage region, in bytes
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct MemoryBreakdown {
+    pub audit_logs_bytes: u64,
+    pub enhanced_audit_logs_bytes: u64,
+    pub loans_bytes: u64,
+    pub investor_balances_bytes: u64,
+    pub nfts_bytes: u64,
+    pub other_bytes: u64,
+    pub total_bytes: u64,
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct ProductionHealthStatus {
     pub is_healthy: bool,
     pub emergency_stop: bool,
     pub maintenance_mode: bool,
+    pub origination_paused: bool,
     pub oracle_status: bool,
     pub ckbtc_integration: bool,
     pub memory_usage: u64,
     pub total_loans: u64,
     pub active_loans: u64,
     pub last_heartbeat: u64,
+    pub upcoming_maintenance: Option<MaintenanceWindow>,
+    pub is_solvent: bool,
+    pub last_reconciliation: Option<BalanceReconciliation>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum ReconciliationStatus {
+    Balanced,           // Within tolerance
+    Surplus,            // Ledger holds more than the protocol accounts for
+    Deficit,            // Ledger holds less than the protocol owes
+    TransfersInFlight,  // Outside tolerance while ledger calls are pending; re-checked next run
+}
+
+// Canister ckBTC held on the ledger compared with what internal accounting says it holds.
+// delta = ledger_balance - internal_obligations.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct BalanceReconciliation {
+    pub checked_at: u64,
+    pub ledger_balance: u64, // Default account plus every reconciled subaccount
+    pub subaccount_balances: Option<Vec<(Vec<u8>, u64)>>,
+    pub pool_available_liquidity: u64,
+    pub treasury_balance: u64,
+    pub insurance_fund_balance: u64,
+    pub borrower_surplus_owed: u64,
+    pub internal_obligations: u64,
+    pub delta: i64,
+    pub tolerance: u64,
+    pub in_flight_operations: u64,
+    pub status: ReconciliationStatus,
+}
+
+impl Storable for BalanceReconciliation {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
 }
 
 // Oracle-related Types
@@ -828,6 +2202,41 @@ impl Storable for CommodityPriceData {
     const BOUND: Bound = Bound::Unbounded;
 }
 
+// Recent price samples per commodity for TWAP calculation
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PriceSample {
+    pub price_per_unit: u64,
+    pub timestamp: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct PriceSampleHistory {
+    pub samples: Vec<PriceSample>, // Oldest first, capped at MAX_PRICE_SAMPLES
+}
+
+impl Storable for PriceSampleHistory {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct TwapPrice {
+    pub commodity_id: String,
+    pub twap_price: u64,
+    pub spot_price: u64,
+    pub window_seconds: u64,
+    pub sample_count: u64,
+    pub covers_window: bool, // false when samples do not reach back to the window start
+    pub calculated_at: u64,
+}
+
 // Price Fetch Record untuk tracking
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct PriceFetchRecord {
@@ -839,6 +2248,10 @@ pub struct PriceFetchRecord {
     pub last_error: Option<String>,
     pub average_response_time: u64, // in milliseconds
     pub rate_limit_reset: u64,
+    pub rejected_writes: Option<u64>,  // Writes refused by the price sanity band
+    pub forced_writes: Option<u64>,    // Admin writes that overrode the band
+    pub held_price: Option<u64>,       // Last out-of-band price awaiting confirmation from another source
+    pub held_price_source: Option<String>,
 }
 
 impl Storable for PriceFetchRecord {
@@ -881,6 +2294,8 @@ impl Default for OracleConfig {
                 ("rice".to_string(), "https://api.hargapangan.id/tabel/pasar/provinsi/komoditas/33/1".to_string()),
                 ("corn".to_string(), "https://api.hargapangan.id/tabel/pasar/provinsi/komoditas/33/2".to_string()),
                 ("wheat".to_string(), "https://api.hargapangan.id/tabel/pasar/provinsi/komoditas/33/3".to_string()),
+                ("btc_idr".to_string(), "https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies=idr".to_string()),
+                ("icp_idr".to_string(), "https://api.coingecko.com/api/v3/simple/price?ids=internet-computer&vs_currencies=idr".to_string()),
             ],
             fetch_interval_seconds: 3600, // 1 hour
             stale_threshold_seconds: 86400, // 24 hours
@@ -892,6 +2307,7 @@ impl Default for OracleConfig {
                 ("rice".to_string(), 15000), // IDR per kg - fallback price
                 ("corn".to_string(), 8000),
                 ("wheat".to_string(), 12000),
+                // Exchange-rate feeds have no fallback: loans are never priced off a fixed rate
             ],
         }
     }
@@ -966,6 +2382,37 @@ impl Storable for PriceAlert {
     const BOUND: Bound = Bound::Unbounded;
 }
 
+// A user's own price alert. Alerts are edge-triggered: they fire when the price crosses
+// the threshold and stay quiet while it remains crossed. Recurring alerts re-arm once the
+// price moves back; one-shot alerts deactivate after firing.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PriceAlertSubscription {
+    pub id: u64,
+    pub subscriber: Principal,
+    pub commodity_id: String,
+    pub threshold_type: PriceThresholdType,
+    pub recurring: bool,
+    pub is_active: bool,
+    pub is_crossed: bool,
+    pub reference_price: Option<u64>, // Baseline for Change thresholds
+    pub last_observed_price: Option<u64>,
+    pub created_at: u64,
+    pub last_triggered_at: Option<u64>,
+    pub trigger_count: u64,
+}
+
+impl Storable for PriceAlertSubscription {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
 impl Storable for ProductionHealthStatus {
     fn to_bytes(&self) -> Cow<[u8]> {
         Cow::Owned(candid::encode_one(self).unwrap())
@@ -1063,12 +2510,28 @@ pub struct PoolConfiguration {
     pub emergency_reserve_ratio: u64, // Basis points
     pub base_apy: u64, // Basis points
     pub performance_fee: u64, // Basis points
+    pub deposit_fee: u64, // Basis points
     pub withdrawal_fee: u64, // Basis points
     pub is_paused: bool,
     pub created_at: u64,
     pub updated_at: u64,
 }
 
+/// Current pool reserve requirement and the liquidity free above it
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ReserveInfo {
+    pub total_liquidity: u64,
+    pub available_liquidity: u64,
+    pub required_reserve: u64,
+    pub effective_ratio_bps: u64,
+    pub headroom: u64, // Largest withdrawal that keeps the reserve intact
+    pub base_ratio_bps: u64,
+    pub min_absolute: u64,
+    pub taper_threshold: Option<u64>,
+    pub taper_ratio_bps: Option<u64>,
+    pub is_below_requirement: bool,
+}
+
 impl Storable for PoolConfiguration {
     fn to_bytes(&self) -> Cow<[u8]> {
         Cow::Owned(candid::encode_one(self).unwrap())
@@ -1103,6 +2566,11 @@ impl Storable for PriceFetchRecord {
 }
 
 impl Loan {
+    /// Currency the loan was disbursed in; amounts and collateral value are in its base units
+    pub fn asset(&self) -> Asset {
+        self.asset.unwrap_or_default()
+    }
+
     pub fn is_active(&self) -> bool {
         self.status == LoanStatus::Active
     }
@@ -1128,6 +2596,19 @@ impl InvestorBalance {
     pub fn is_active(&self) -> bool {
         self.balance > 0
     }
+    
+    /// Sum of the fees on the deposit and withdrawal records
+    pub fn recorded_fees(&self) -> u64 {
+        let deposit_fees = self.deposits.iter().map(|deposit| deposit.fee.unwrap_or(0));
+        let withdrawal_fees = self.withdrawals.iter().map(|withdrawal| withdrawal.fee.unwrap_or(0));
+        deposit_fees.chain(withdrawal_fees).fold(0u64, |total, fee| total.saturating_add(fee))
+    }
+    
+    /// Add a fee to the running total; call before pushing the record that carries it
+    pub fn add_fee_paid(&mut self, fee: u64) {
+        let paid = self.total_fees_paid.unwrap_or_else(|| self.recorded_fees());
+        self.total_fees_paid = Some(paid.saturating_add(fee));
+    }
 }
 
 impl LiquidityPool {
@@ -1138,21 +2619,153 @@ impl LiquidityPool {
             (self.total_borrowed * 10000) / self.total_liquidity // Basis points
         }
     }
+
+    pub fn reserved(&self) -> u64 {
+        self.reserved_liquidity.unwrap_or(0)
+    }
+
+    /// Available liquidity not already promised to an approved loan
+    pub fn unreserved_liquidity(&self) -> u64 {
+        self.available_liquidity.saturating_sub(self.reserved())
+    }
+}
+
+// Liquidity held for an approved loan until it is disbursed, released, or expires
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct LiquidityReservation {
+    pub loan_id: u64,
+    pub amount: u64,
+    pub reserved_at: u64,
+    pub expires_at: u64,
+}
+
+impl Storable for LiquidityReservation {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Loan lifecycle event for external ledger sync. Sequence numbers are gap-free and
+// monotonically increasing; old_status is None when the loan is first created.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum LoanEventType {
+    Created,
+    SubmittedForApproval,
+    Approved,
+    Disbursed,
+    Repaid,
+    Defaulted,
+    Expired,
+    Withdrawn,
+    LiquidationStarted,
+    LiquidationAborted,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LoanEvent {
+    pub sequence: u64,
+    pub loan_id: u64,
+    pub event_type: LoanEventType,
+    pub old_status: Option<LoanStatus>,
+    pub new_status: LoanStatus,
+    pub amount: u64,
+    pub timestamp: u64,
+}
+
+// A loan as it stood at `as_of`, replayed from the loan event log and repayment history.
+// Balances use the loan's current terms; restructures are not replayed.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LoanStateSnapshot {
+    pub loan_id: u64,
+    pub as_of: u64,
+    pub status: LoanStatus,
+    pub events_applied: u64,
+    pub last_event_sequence: Option<u64>,
+    pub amount_approved: u64,
+    pub disbursed_at: Option<u64>,
+    pub due_date: Option<u64>, // None until disbursement
+    pub total_repaid: u64,
+    pub accrued_interest: u64,
+    pub late_penalty: u64,
+    pub outstanding_balance: u64,
+    pub collateral_value_btc: u64,
+    pub health_ratio: Option<f64>, // Collateral at origination valuation over outstanding balance
+    pub days_overdue: u64,
+}
+
+impl LoanEventType {
+    pub fn from_status(status: &LoanStatus, is_new: bool) -> Self {
+        if is_new {
+            return LoanEventType::Created;
+        }
+        match status {
+            LoanStatus::PendingApplication => LoanEventType::Created,
+            LoanStatus::PendingApproval => LoanEventType::SubmittedForApproval,
+            LoanStatus::Approved => LoanEventType::Approved,
+            LoanStatus::Active => LoanEventType::Disbursed,
+            LoanStatus::Repaid => LoanEventType::Repaid,
+            LoanStatus::Defaulted => LoanEventType::Defaulted,
+            LoanStatus::Expired => LoanEventType::Expired,
+            LoanStatus::Withdrawn => LoanEventType::Withdrawn,
+            LoanStatus::Liquidating => LoanEventType::LiquidationStarted,
+        }
+    }
+}
+
+impl Storable for LoanEvent {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct PoolStats {
     pub total_liquidity: u64,
     pub available_liquidity: u64,
+    pub reserved_liquidity: u64, // Part of available_liquidity held for approved loans
     pub total_borrowed: u64,
     pub total_repaid: u64,
     pub utilization_rate: u64, // Basis points
     pub total_investors: u64,
-    pub apy: u64, // Basis points
+    pub apy: u64,               // Whole percent, rounded down; kept for existing clients
+    pub apy_bps: Option<u64>,   // Basis points; None only on stats recorded before it was added
     pub created_at: u64,
     pub updated_at: u64,
 }
 
+/// Protocol-wide assets vs liabilities. Ratios are in basis points (u64::MAX when there are no liabilities).
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct SolvencyReport {
+    pub pool_liquidity: u64,
+    pub performing_loan_value: u64, // Outstanding debt of active loans, capped at collateral value
+    pub performing_loan_count: u64,
+    pub insurance_fund_balance: u64,
+    pub total_assets: u64,
+    pub investor_balances: u64,
+    pub accrued_investor_yield: u64, // Investor share of unpaid interest on active loans
+    pub total_liabilities: u64,
+    pub collateralization_ratio_bps: u64,
+    pub stress_haircut_bps: u64,
+    pub stressed_total_assets: u64,
+    pub stressed_collateralization_ratio_bps: u64,
+    pub solvency_floor_bps: u64,
+    pub is_solvent: bool,
+    pub is_above_floor: bool,
+    pub generated_at: u64,
+}
+
 impl Storable for PoolStats {
     fn to_bytes(&self) -> Cow<[u8]> {
         Cow::Owned(candid::encode_one(self).unwrap())
@@ -1166,17 +2779,6 @@ impl Storable for PoolStats {
 }
 
 impl PoolStats {
-    pub fn calculate_apy(&self) -> u64 {
-        // Implementasi kalkulasi APY berdasarkan utilization rate
-        if self.utilization_rate > 8000 { // 80%
-            1200 // 12% APY
-        } else if self.utilization_rate > 5000 { // 50%
-            1000 // 10% APY
-        } else {
-            800 // 8% APY
-        }
-    }
-    
     pub fn is_healthy(&self) -> bool {
         self.utilization_rate < 9000 && self.available_liquidity > 0
     }
@@ -1197,6 +2799,24 @@ pub struct LiquidationRecord {
     pub liquidation_wallet: Principal,
     pub processing_fee: u64,
     pub recovery_expected: u64,
+    pub settlement: Option<LiquidationSettlement>, // None until the seized collateral is sold
+}
+
+// How the proceeds from selling seized collateral were applied
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct LiquidationSettlement {
+    pub loan_id: u64,
+    pub auction_id: Option<u64>,     // None for collateral sold off-chain
+    pub proceeds: u64,
+    pub outstanding_debt: u64,
+    pub debt_repaid: u64,            // Returned to the pool
+    pub penalty_bps: u64,
+    pub penalty_paid: u64,           // Routed to treasury; never more than proceeds left after the debt
+    pub residual_returned: u64,      // Credited to the borrower's claimable surplus balance
+    pub shortfall: u64,              // Debt the proceeds did not cover
+    pub principal_loss: u64,         // Part of the shortfall written off against the pool
+    pub settled_at: u64,
+    pub proceeds_block_index: Option<u64>, // Ledger block the proceeds were received in; None when there were none
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -1208,6 +2828,75 @@ pub enum LiquidationReason {
     AutomatedLiquidation,        // Triggered by automated system
 }
 
+// Liquidation auction types
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum AuctionStatus {
+    Active,           // Accepting bids
+    Settled,          // Sold to the highest bidder
+    SettledAtReserve, // No longer produced; kept so earlier auctions still decode
+    Cancelled,
+    Unsold,           // No eligible bids; collateral stays with the liquidation wallet
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LiquidationBid {
+    pub bidder: Principal,
+    pub amount: u64,
+    pub placed_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct AuctionSettlement {
+    pub buyer: Principal,
+    pub sale_price: u64,
+    pub debt_repaid: u64,
+    pub penalty_paid: u64,
+    pub borrower_surplus: u64,
+    pub settled_at: u64,
+    pub tie_break_seed: Option<Vec<u8>>,      // raw_rand output that broke a tie at the top bid
+    pub tied_bidders: Option<Vec<Principal>>, // Bidders tied at the winning amount, in the order the seed indexes
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LiquidationAuction {
+    pub auction_id: u64,
+    pub loan_id: u64,
+    pub nft_id: u64,
+    pub borrower: Principal,
+    pub reserve_price: u64, // Outstanding debt at liquidation
+    pub started_at: u64,
+    pub ends_at: u64,
+    pub highest_bid: Option<LiquidationBid>,
+    pub bids: Vec<LiquidationBid>,
+    pub status: AuctionStatus,
+    pub settlement: Option<AuctionSettlement>,
+    pub bid_collection: Option<BidCollection>,
+}
+
+// Pull of the winning bid from the bidder's ICRC-2 allowance. `created_at` is reused when
+// finalization is retried so the ledger deduplicates a transfer whose outcome was unknown.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct BidCollection {
+    pub bidder: Principal,
+    pub amount: u64,
+    pub created_at: u64,
+    pub ckbtc_block_index: Option<u64>,       // Set once the ledger confirms the transfer
+    pub tie_break_seed: Option<Vec<u8>>,
+    pub tied_bidders: Option<Vec<Principal>>,
+}
+
+impl Storable for LiquidationAuction {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        std::borrow::Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct LiquidationEligibilityCheck {
     pub loan_id: u64,
@@ -1218,6 +2907,105 @@ pub struct LiquidationEligibilityCheck {
     pub grace_period_expired: bool,
 }
 
+// The collateral NFT's price inputs for a liquidation price projection
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CollateralPriceComponent {
+    pub nft_id: u64,
+    pub commodity_type: String,
+    pub quantity: u64,
+    pub valuation_idr: Idr,             // Appraisal cap; collateral is valued at min(appraisal, market)
+    pub price: CommodityPriceData,      // Current effective price, IDR per unit
+}
+
+// Commodity price at which a loan's health ratio, as calculate_loan_health_ratio reports it,
+// reaches the liquidation threshold
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LiquidationProjection {
+    pub loan_id: u64,
+    pub nft_id: u64,
+    pub commodity_type: String,
+    pub current_health_ratio: f64,
+    pub liquidation_threshold: f64,
+    pub current_price: u64,
+    pub liquidation_price: Option<u64>, // None when no price drop can trigger liquidation
+    pub cushion_percent: Option<f64>,   // Price drop to the trigger; negative when already past it
+    pub generated_at: u64,
+}
+
+// Operator action suggested for an at-risk loan, most urgent first
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AtRiskAction {
+    LiquidateNow, // Health below the liquidation threshold, or the grace period has run out
+    GracePeriod,  // Past due, still inside the grace period
+    MarginCall,   // Current, but health is below the safe band
+}
+
+// One entry in the admin at-risk triage report
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct AtRiskLoan {
+    pub loan: Loan,
+    pub health_ratio: f64,
+    pub liquidation_price: Option<u64>, // From the liquidation price projection, when prices are available
+    pub cushion_percent: Option<f64>,
+    pub days_overdue: u64,
+    pub borrower_has_email: bool,
+    pub borrower_has_phone: bool,
+    pub suggested_action: AtRiskAction,
+}
+
+/// Whether an eligible loan is liquidated by the heartbeat or waits for operator review
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum LiquidationBand {
+    Automatic,
+    ManualReview,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PendingLiquidationReview {
+    pub loan_id: u64,
+    pub borrower: Principal,
+    pub outstanding_debt: u64,
+    pub collateral_value: u64,
+    pub health_ratio: f64,
+    pub days_overdue: u64,
+    pub reason: String,
+    pub flagged_at: u64,
+}
+
+impl Storable for PendingLiquidationReview {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Per-loan liquidation lock held while the loan is Liquidating. Once `expires_at` passes,
+/// a stuck attempt may be retried.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LiquidationAttempt {
+    pub loan_id: u64,
+    pub started_at: u64,
+    pub started_by: Principal,
+    pub expires_at: u64,
+}
+
+impl Storable for LiquidationAttempt {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct LiquidationResult {
     pub loan_id: u64,
@@ -1265,6 +3053,8 @@ pub struct LiquidationRiskAssessment {
     pub assessment_timestamp: u64,
     pub collateral_value: u64,
     pub outstanding_debt: u64,
+    pub liquidation_band: LiquidationBand,
+    pub pending_review: bool,
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -1291,8 +3081,275 @@ pub enum ProposalType {
     EmergencyAction,
     SystemConfiguration,
     TreasuryManagement,
+    Signaling,
+}
+
+// Typed effect of a proposal, performed by execute_proposal once the vote passes.
+// Signal proposals have no effect; executing one only records the outcome.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum ProposalAction {
+    SetParameter { key: String, value: u64 },
+    TreasurySpend { amount: u64, destination: Principal },
+    GrantRole { principal: Principal, role: AdminRoleType },
+    Signal,
+}
+
+impl ProposalAction {
+    pub fn proposal_type(&self) -> ProposalType {
+        match self {
+            ProposalAction::SetParameter { .. } => ProposalType::ProtocolParameterUpdate,
+            ProposalAction::TreasurySpend { .. } => ProposalType::TreasuryManagement,
+            ProposalAction::GrantRole { .. } => ProposalType::AdminRoleUpdate,
+            ProposalAction::Signal => ProposalType::Signaling,
+        }
+    }
+
+    pub fn audit_action(&self) -> &'static str {
+        match self {
+            ProposalAction::SetParameter { .. } => "PROPOSAL_ACTION_SET_PARAMETER",
+            ProposalAction::TreasurySpend { .. } => "PROPOSAL_ACTION_TREASURY_SPEND",
+            ProposalAction::GrantRole { .. } => "PROPOSAL_ACTION_GRANT_ROLE",
+            ProposalAction::Signal => "PROPOSAL_ACTION_SIGNAL",
+        }
+    }
+}
+
+// One parameter a proposal or candidate parameter set would change. Values are None where the
+// key is not set, e.g. a commodity LTV override being added or removed.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct ProposedParameterDiff {
+    pub key: String,
+    pub current_value: Option<u64>,
+    pub proposed_value: Option<u64>,
+    pub risk_score: u32,
+}
+
+// Loan manager proposed by a super admin, waiting for the candidate to accept the role
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct PendingLoanManager {
+    pub principal: Principal,
+    pub proposed_by: Principal,
+    pub proposed_at: u64,
+    pub accepts_after: u64, // Governance execution delay; acceptance is refused before this
+}
+
+// Borrower's opt-in to have due installments collected by the heartbeat through an ICRC-2
+// allowance. Kept after it is disabled so its collection history stays visible.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct AutopayMandate {
+    pub loan_id: u64,
+    pub borrower: Principal,
+    pub enabled: bool,
+    pub enabled_at: u64,
+    pub disabled_at: Option<u64>,
+    pub last_attempt_at: Option<u64>,
+    pub last_success_at: Option<u64>,
+    pub last_failure: Option<String>,  // Cleared by the next successful collection
+    pub consecutive_failures: u32,
+    pub payments_collected: u64,
+    pub total_collected: u64,
+}
+
+impl Storable for AutopayMandate {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Replay protection state for one admin. Sensitive admin calls from an admin with `required`
+// set must carry a nonce above `last_nonce` while the admin_nonces feature flag is on.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct AdminNonceState {
+    pub admin: Principal,
+    pub required: bool,
+    pub last_nonce: u64,
+    pub updated_at: u64,
+}
+
+impl Storable for AdminNonceState {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Repayment track record of a borrower across closed loans. Borrowers without one are scored
+// at the neutral baseline.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct BorrowerReputation {
+    pub borrower: Principal,
+    pub score: u64, // 0-1000 under the weights in force at `updated_at`; 500 is neutral
+    pub loans_closed: u64,
+    pub on_time_repayments: u64,
+    pub late_repayments: u64,
+    pub defaults: u64,
+    pub default_severity_pct: u64, // Sum over defaults of the unrepaid share of the loan, in %
+    pub repaid_volume: u64,        // Principal of fully repaid ckBTC loans, in satoshi
+    pub last_loan_id: Option<u64>,
+    pub updated_at: u64,
+}
+
+impl Storable for BorrowerReputation {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Governance-set scoring weights and the bounds on reputation-based term adjustments.
+// Rate and LTV bounds are in whole percentage points.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReputationWeights {
+    pub on_time_points: u64,
+    pub late_penalty: u64,
+    pub default_penalty: u64,     // For a default with nothing repaid
+    pub volume_points: u64,       // Per 0.01 BTC repaid, capped
+    pub max_rate_discount: u64,
+    pub max_rate_premium: u64,
+    pub max_ltv_bonus: u64,
+    pub max_ltv_reduction: u64,
+}
+
+// A borrower's reputation with the terms it currently earns on new loans
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ReputationView {
+    pub reputation: BorrowerReputation,
+    pub apr_adjustment: i64, // Percentage points added to the standard APR
+    pub ltv_adjustment: i64, // Percentage points added to the commodity LTV
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct AutopayStatus {
+    pub loan_id: u64,
+    pub mandate: Option<AutopayMandate>,
+    pub next_installment: Option<AmortizationRow>,  // First unpaid installment
+    pub next_attempt_after: Option<u64>,            // Set while failures are held off until the next day
+}
+
+// Runtime on/off switch for a feature, set by a super admin. Flags that were never set
+// fall back to the built-in default set; names outside it are disabled.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct FeatureFlag {
+    pub name: String,
+    pub enabled: bool,
+    pub updated_at: u64,                // 0 for a built-in default that was never changed
+    pub updated_by: Option<Principal>,
+}
+
+// Daily pool performance record. Cumulative totals are carried so each period can be
+// derived by diffing against the previous snapshot instead of replaying history.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct PerformanceSnapshot {
+    pub captured_at: u64,
+    pub period_start: u64,          // Previous snapshot's captured_at, or pool creation for the first one
+    pub total_liquidity: u64,
+    pub available_liquidity: u64,
+    pub total_borrowed: u64,
+    pub total_investors: u64,
+    pub utilization_bps: u64,
+    pub cumulative_deposits: u64,
+    pub cumulative_withdrawals: u64,
+    pub cumulative_interest: u64,
+    pub cumulative_losses: u64,
+    pub period_deposits: u64,
+    pub period_withdrawals: u64,
+    pub period_interest: u64,       // Interest collected from borrowers and distributed to the pool
+    pub period_losses: u64,         // Principal written off by liquidations
+    pub net_inflow: i64,            // period_deposits - period_withdrawals
+    pub realized_apy: f64,          // Net of losses, annualized over the period, in percent
+    pub pool_apy_bps: Option<u64>,  // Quoted pool APY at capture; None for older snapshots
+}
+
+// One asset's pool balances and ledger activity over a statement year, in its base units
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct AssetStatementActivity {
+    pub asset: Asset,
+    pub opening_balance: u64,
+    pub closing_balance: u64,
+    pub total_deposits: u64,
+    pub total_withdrawals: u64,
+    pub deposit_count: u64,
+    pub withdrawal_count: u64,
+    pub fees_paid: u64,
+}
+
+// Calendar-year statement of an investor's pool activity, for tax and accounting. The top-level
+// amounts are ckBTC; yield and losses are the investor's pro-rata share of each daily ckBTC
+// performance snapshot.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct AnnualStatement {
+    pub investor: Principal,
+    pub year: u32,
+    pub period_start: u64,          // 1 January 00:00 UTC, nanoseconds
+    pub period_end: u64,            // 1 January of the following year, exclusive
+    pub opening_balance: u64,
+    pub closing_balance: u64,
+    pub total_deposits: u64,        // Credited amounts, after fees
+    pub total_withdrawals: u64,     // Debited amounts, including fees
+    pub deposit_count: u64,
+    pub withdrawal_count: u64,
+    pub fees_paid: u64,
+    pub yield_earned: u64,
+    pub losses_allocated: u64,
+    pub net_realized_return: i64,   // yield_earned - losses_allocated - fees_paid
+    pub realized_lot_gains: Vec<RealizedLotGain>, // Per-lot gains of FIFO/LIFO withdrawals in the year
+    pub generated_at: u64,
+    pub icp_activity: Option<AssetStatementActivity>, // ICP pool balances and activity; None without ICP records
+}
+
+impl Storable for PerformanceSnapshot {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for FeatureFlag {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for PendingLoanManager {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
 }
 
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub enum ProposalStatus {
     Pending,
@@ -1301,6 +3358,7 @@ pub enum ProposalStatus {
     Rejected,
     Executed,
     Expired,
+    Failed, // Execution was attempted and failed; never retried
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -1330,6 +3388,7 @@ pub struct Proposal {
     pub approval_threshold: u64, // Percentage in basis points
     pub executed_at: Option<u64>,
     pub executed_by: Option<Principal>,
+    pub action: Option<ProposalAction>, // None for proposals created before typed actions
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -1388,7 +3447,7 @@ pub struct AdminRole {
     pub is_active: bool,
 }
 
-#[derive(CandidType, Deserialize, Clone, Debug)]
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
 pub enum AdminRoleType {
     SuperAdmin,
     ProtocolAdmin,
@@ -1440,6 +3499,29 @@ pub struct GovernanceStats {
     pub last_proposal_id: u64,
 }
 
+// Full protocol configuration captured for rollback
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ParameterSnapshot {
+    pub id: u64,
+    pub label: String,
+    pub parameters: Vec<ProtocolParameter>,
+    pub protocol_parameters: ProtocolParameters,
+    pub governance_config: GovernanceConfig,
+    pub canister_config: CanisterConfig,
+    pub created_by: Principal,
+    pub created_at: u64,
+    pub restore_requested_by: Option<Principal>,
+    pub restore_requested_at: Option<u64>,
+    pub last_restored_at: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct ParameterDiff {
+    pub key: String,
+    pub current_value: Option<u64>,
+    pub snapshot_value: Option<u64>,
+}
+
 // Storable implementations for governance types
 impl Storable for Proposal {
     fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
@@ -1489,6 +3571,18 @@ impl Storable for AdminRole {
     const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
 }
 
+impl Storable for ParameterSnapshot {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        std::borrow::Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+}
+
 impl CommodityPriceData {
     pub fn is_stale(&self, max_age_seconds: u64) -> bool {
         time() > self.timestamp + (max_age_seconds * 1_000_000_000)
@@ -2011,6 +4105,10 @@ pub struct InvestorStatistics {
     pub days_since_last_activity: u64,
     pub is_active_investor: bool,
     pub risk_level: String, // "LOW", "MEDIUM", "HIGH"
+    pub yield_eligible_balance: u64, // Held at least the minimum holding period
+    pub yield_pending_balance: u64,  // Still inside the holding period
+    pub next_yield_eligible_at: Option<u64>,
+    pub min_holding_period_seconds: u64,
 }
 
 impl Storable for InvestorStatistics {
@@ -2107,4 +4205,216 @@ impl Storable for LiquidityPoolAnalytics {
     }
     
     const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
-}
\ No newline at end of file
+}
+// State covered by off-chain snapshot exports
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SnapshotDomain {
+    Loans,
+    InvestorBalances,
+    LiquidityPool,
+    ProtocolParameters,   // The lending parameter record
+    GovernanceParameters, // Governance key/value parameters
+}
+
+impl SnapshotDomain {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SnapshotDomain::Loans => "loans",
+            SnapshotDomain::InvestorBalances => "investor_balances",
+            SnapshotDomain::LiquidityPool => "liquidity_pool",
+            SnapshotDomain::ProtocolParameters => "protocol_parameters",
+            SnapshotDomain::GovernanceParameters => "governance_parameters",
+        }
+    }
+}
+
+// One chunk of a domain snapshot. `payload` is the candid-encoded Vec of the chunk's records.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct StateSnapshotChunk {
+    pub format_version: u32,
+    pub domain: SnapshotDomain,
+    pub chunk_index: u64,
+    pub total_chunks: u64,
+    pub record_count: u64,  // Records in this chunk
+    pub total_records: u64, // Records in the whole snapshot
+    pub exported_at: u64,
+    pub export_id: u64,       // Shared by every chunk of one export; imports refuse to mix exports
+    pub content_hash: String, // Lowercase hex SHA-256 of the header fields and payload
+    pub payload: Vec<u8>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct SnapshotImportProgress {
+    pub domain: SnapshotDomain,
+    pub export_id: u64,
+    pub chunk_index: u64,
+    pub total_chunks: u64,
+    pub records_imported: u64,
+    pub complete: bool,
+}
+
+// Who an operator broadcast goes to. Role audiences cover active registered users only.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum BroadcastAudience {
+    AllFarmers,
+    AllInvestors,
+    Principals(Vec<Principal>),
+    OverdueBorrowers, // Borrowers with an active loan past its due date
+    All,
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum BroadcastStatus {
+    InProgress,
+    Completed,
+}
+
+// An operator announcement fanned out as one notification per recipient. Recipients are
+// resolved once when the broadcast is created and stored apart from the record (see
+// storage::BROADCAST_RECIPIENTS); delivery resumes from `next_index`.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct NotificationBroadcast {
+    pub id: u64,
+    pub audience: BroadcastAudience,
+    pub title: String,
+    pub body: String,
+    pub created_by: Principal,
+    pub created_at: u64,
+    pub recipient_count: u64,
+    pub next_index: u64,
+    pub delivered: u64,
+    pub skipped: u64, // Opted out, notifications disabled, in quiet hours or rate limited
+    pub status: BroadcastStatus,
+    pub completed_at: Option<u64>,
+}
+
+impl Storable for NotificationBroadcast {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Admin exception to max_active_loans_per_borrower for one borrower
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct BorrowerLoanLimitOverride {
+    pub borrower: Principal,
+    pub max_active_loans: u64,
+    pub reason: String,
+    pub set_by: Principal,
+    pub set_at: u64,
+}
+
+impl Storable for BorrowerLoanLimitOverride {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct BorrowerLoanCapacity {
+    pub borrower: Principal,
+    pub limit: u64,
+    pub used: u64,      // Loans not yet repaid, defaulted, expired or withdrawn
+    pub remaining: u64,
+    pub override_reason: Option<String>, // Set when an admin exception replaces the governance limit
+}
+
+// One deposit or withdrawal counted toward an investor's rolling 24h limits, in the asset's
+// base unit
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct TransactionUsageEntry {
+    pub asset: Asset,
+    pub amount: u64,
+    pub timestamp: u64,
+}
+
+// An investor's deposits and withdrawals inside the rolling limit window; older entries are
+// dropped whenever a new one is recorded
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct TransactionUsage {
+    pub investor: Principal,
+    pub deposits: Vec<TransactionUsageEntry>,
+    pub withdrawals: Vec<TransactionUsageEntry>,
+}
+
+impl Storable for TransactionUsage {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Protocol fee taken from one repayment, identified by the loan and the payment's index in
+// its repayment history. Its presence marks the fee as collected.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct RepaymentFeeCollection {
+    pub loan_id: u64,
+    pub payment_index: u64,
+    pub fee_amount: u64,
+    pub insurance_share: u64,
+    pub treasury_share: u64,
+    pub revenue_entry_id: u64, // 0 when the whole fee went to the insurance fund
+    pub transaction_id: Option<String>, // Ledger block or reference of the repayment
+    pub collected_at: u64,
+    pub collected_by: Principal,
+}
+
+impl Storable for RepaymentFeeCollection {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+    
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// A ckBTC repayment whose protocol fee has not reached the treasury
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct UncollectedRepaymentFee {
+    pub loan_id: u64,
+    pub payment_index: u64,
+    pub borrower: Principal,
+    pub fee_amount: u64,
+    pub paid_at: u64,
+    pub transaction_id: Option<String>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct FeeSweepResult {
+    pub collected: u64,
+    pub total_collected_amount: u64,
+    pub failed: Vec<String>,
+    pub remaining: u64, // Uncollected fees left for a later sweep
+}
+
+// Collected repayment fees checked against the treasury revenue log
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct FeeCollectionReconciliation {
+    pub collections: u64,
+    pub total_collected: u64,
+    pub total_insurance_share: u64,
+    pub total_treasury_share: u64,
+    pub total_recorded_revenue: u64,
+    pub discrepancies: Vec<String>,
+    pub reconciled: bool,
+}
@@ -30,8 +30,30 @@ pub struct User {
     pub email: Option<String>,
     pub phone: Option<String>,
     pub profile_completed: bool,
+    pub preferred_language: Option<String>, // None means DEFAULT_LANGUAGE
+    pub kyc_tier: Option<KycTier>,           // None means KycTier::None
+    pub kyc_reference: Option<String>,       // Verification provider reference for the current tier
+    pub kyc_updated_at: Option<u64>,
 }
 
+impl User {
+    pub fn effective_kyc_tier(&self) -> KycTier {
+        self.kyc_tier.clone().unwrap_or(KycTier::None)
+    }
+}
+
+// KYC verification levels, ordered from least to most verified
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KycTier {
+    None,
+    Basic,
+    Verified,
+}
+
+// Languages available for user-facing text
+pub const DEFAULT_LANGUAGE: &str = "id";
+pub const SUPPORTED_LANGUAGES: [&str; 2] = ["id", "en"];
+
 // Enhanced result type for API responses
 #[derive(CandidType, Deserialize)]
 pub enum UserResult {
@@ -66,6 +88,41 @@ pub struct UserUpdateRequest {
     pub phone: Option<String>,
 }
 
+// Account status change kind
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum UserStatusAction {
+    Deactivated,
+    Reactivated,
+}
+
+// Who changed an account's status, when, and why
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DeactivationRecord {
+    pub action: UserStatusAction,
+    pub actor: Principal,
+    pub reason: Option<String>,
+    pub timestamp: u64,
+    pub admin_override: bool, // Deactivated despite active loans
+}
+
+// Per-user status history
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct UserStatusHistory {
+    pub records: Vec<DeactivationRecord>,
+}
+
+impl Storable for UserStatusHistory {
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+    
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+}
+
 // Implement Storable trait for User
 impl Storable for User {
     const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
@@ -93,6 +150,12 @@ thread_local! {
             MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(0)))
         )
     );
+
+    static USER_STATUS_HISTORY: RefCell<StableBTreeMap<Principal, UserStatusHistory, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            crate::storage::get_memory_by_id(MemoryId::new(71))
+        )
+    );
 }
 
 // Helper function to create a new user
@@ -108,6 +171,10 @@ pub fn create_user(principal: Principal, role: Role) -> User {
         email: None,
         phone: None,
         profile_completed: false,
+        preferred_language: None,
+        kyc_tier: None,
+        kyc_reference: None,
+        kyc_updated_at: None,
     }
 }
 
@@ -127,6 +194,9 @@ pub fn get_user_by_principal(principal: &Principal) -> Option<User> {
 #[update]
 pub fn register_as_farmer() -> UserResult {
     let principal = ic_cdk::caller();
+    if let Err(e) = crate::validation::reject_anonymous(&principal) {
+        return UserResult::Err(e);
+    }
     
     // Check if user is already registered
     if user_exists(&principal) {
@@ -148,6 +218,9 @@ pub fn register_as_farmer() -> UserResult {
 #[update]
 pub fn register_as_investor() -> UserResult {
     let principal = ic_cdk::caller();
+    if let Err(e) = crate::validation::reject_anonymous(&principal) {
+        return UserResult::Err(e);
+    }
     
     // Check if user is already registered
     if user_exists(&principal) {
@@ -180,6 +253,9 @@ pub fn get_user() -> UserResult {
 #[update]
 pub fn update_btc_address(btc_address: String) -> UserResult {
     let principal = ic_cdk::caller();
+    if let Err(e) = crate::validation::guard_caller(&principal, "update_btc_address") {
+        return UserResult::Err(e);
+    }
     
     match get_user_by_principal(&principal) {
         Some(mut user) => {
@@ -306,6 +382,9 @@ pub fn get_all_users() -> Vec<User> {
 #[update]
 pub fn update_user_profile(update_request: UserUpdateRequest) -> UserResult {
     let principal = ic_cdk::caller();
+    if let Err(e) = crate::validation::guard_caller(&principal, "update_user_profile") {
+        return UserResult::Err(e);
+    }
     
     match get_user_by_principal(&principal) {
         Some(mut user) => {
@@ -322,24 +401,26 @@ pub fn update_user_profile(update_request: UserUpdateRequest) -> UserResult {
                 }
             }
             
-            // Update email if provided
+            let strict = strict_contact_validation();
+            
+            // Update email if provided, stored in canonical form
             if let Some(email) = update_request.email {
                 if !email.is_empty() {
-                    if !validate_email(&email) {
-                        return UserResult::Err("Invalid email format".to_string());
+                    match validate_and_normalize_email(&email, strict) {
+                        Ok(normalized) => user.email = Some(normalized),
+                        Err(error) => return UserResult::Err(error.to_string()),
                     }
-                    user.email = Some(email);
                     updated = true;
                 }
             }
             
-            // Update phone if provided
+            // Update phone if provided, stored in canonical form
             if let Some(phone) = update_request.phone {
                 if !phone.is_empty() {
-                    if !validate_phone(&phone) {
-                        return UserResult::Err("Invalid phone number format".to_string());
+                    match validate_and_normalize_phone(&phone, strict) {
+                        Ok(normalized) => user.phone = Some(normalized),
+                        Err(error) => return UserResult::Err(error.to_string()),
                     }
-                    user.phone = Some(phone);
                     updated = true;
                 }
             }
@@ -347,10 +428,10 @@ pub fn update_user_profile(update_request: UserUpdateRequest) -> UserResult {
             if updated {
                 user.updated_at = time();
                 
-                // Check if profile is completed
+                // Check if profile is completed, from the stored (normalized) values
                 user.profile_completed = user.btc_address.is_some() 
-                    || user.email.is_some() 
-                    || user.phone.is_some();
+                    || user.email.as_deref().map_or(false, |email| !email.is_empty())
+                    || user.phone.as_deref().map_or(false, |phone| !phone.is_empty());
                 
                 // Update user in storage
                 USERS.with(|users| {
@@ -366,19 +447,54 @@ pub fn update_user_profile(update_request: UserUpdateRequest) -> UserResult {
     }
 }
 
-/// Deactivate user account
-#[update]
-pub fn deactivate_user() -> UserResult {
-    let principal = ic_cdk::caller();
-    
-    match get_user_by_principal(&principal) {
+// Helper to append a status change to a user's history
+fn record_status_change(user_id: Principal, record: DeactivationRecord) {
+    USER_STATUS_HISTORY.with(|history| {
+        let mut history = history.borrow_mut();
+        let mut user_history = history.get(&user_id).unwrap_or_default();
+        user_history.records.push(record);
+        history.insert(user_id, user_history);
+    });
+}
+
+// Helper to check whether a farmer still has loans backed by collateral
+fn has_active_loans(user_id: Principal) -> bool {
+    crate::storage::get_loans_by_borrower(user_id)
+        .iter()
+        .any(|loan| matches!(
+            loan.status,
+            crate::types::LoanStatus::Approved | crate::types::LoanStatus::Active
+        ))
+}
+
+// Shared status update used by self-service and admin flows
+fn set_user_active_status(
+    user_id: Principal,
+    is_active: bool,
+    actor: Principal,
+    reason: Option<String>,
+    admin_override: bool,
+) -> UserResult {
+    match get_user_by_principal(&user_id) {
         Some(mut user) => {
-            user.is_active = false;
+            if !is_active && user.role == Role::Farmer && has_active_loans(user_id) && !admin_override {
+                return UserResult::Err("Cannot deactivate a farmer with active loans".to_string());
+            }
+
+            user.is_active = is_active;
             user.updated_at = time();
             
             // Update user in storage
             USERS.with(|users| {
-                users.borrow_mut().insert(principal, user.clone());
+                users.borrow_mut().insert(user_id, user.clone());
+            });
+            
+            record_status_change(user_id, DeactivationRecord {
+                action: if is_active { UserStatusAction::Reactivated } else { UserStatusAction::Deactivated },
+                actor,
+                reason,
+                timestamp: time(),
+                admin_override,
             });
             
             UserResult::Ok(user)
@@ -387,17 +503,105 @@ pub fn deactivate_user() -> UserResult {
     }
 }
 
+/// Deactivate user account
+#[update]
+pub fn deactivate_user() -> UserResult {
+    let principal = ic_cdk::caller();
+    if let Err(e) = crate::validation::guard_caller(&principal, "deactivate_user") {
+        return UserResult::Err(e);
+    }
+    set_user_active_status(principal, false, principal, None, false)
+}
+
 /// Reactivate user account
 #[update]
 pub fn reactivate_user() -> UserResult {
     let principal = ic_cdk::caller();
+    if let Err(e) = crate::validation::guard_caller(&principal, "reactivate_user") {
+        return UserResult::Err(e);
+    }
+    set_user_active_status(principal, true, principal, None, false)
+}
+
+/// Deactivate another user's account (admin only, reason required)
+#[update]
+pub fn admin_deactivate_user(user_id: Principal, reason: String, override_active_loans: bool) -> UserResult {
+    let caller = ic_cdk::caller();
+    if let Err(e) = crate::validation::guard_caller(&caller, "admin_deactivate_user") {
+        return UserResult::Err(e);
+    }
+    if !crate::helpers::is_admin(&caller) {
+        return UserResult::Err("Unauthorized: Admin access required".to_string());
+    }
+    if reason.trim().is_empty() {
+        return UserResult::Err("A reason is required when deactivating another user".to_string());
+    }
+    
+    let result = set_user_active_status(user_id, false, caller, Some(reason.clone()), override_active_loans);
+    if let UserResult::Ok(_) = result {
+        crate::helpers::log_audit_action(
+            caller,
+            "USER_DEACTIVATED_BY_ADMIN".to_string(),
+            format!("User {} deactivated (override: {}): {}", user_id.to_text(), override_active_loans, reason),
+            true,
+        );
+    }
+    result
+}
+
+/// Reactivate another user's account (admin only)
+#[update]
+pub fn admin_reactivate_user(user_id: Principal, reason: Option<String>) -> UserResult {
+    let caller = ic_cdk::caller();
+    if let Err(e) = crate::validation::guard_caller(&caller, "admin_reactivate_user") {
+        return UserResult::Err(e);
+    }
+    if !crate::helpers::is_admin(&caller) {
+        return UserResult::Err("Unauthorized: Admin access required".to_string());
+    }
+    
+    let result = set_user_active_status(user_id, true, caller, reason.clone(), false);
+    if let UserResult::Ok(_) = result {
+        crate::helpers::log_audit_action(
+            caller,
+            "USER_REACTIVATED_BY_ADMIN".to_string(),
+            format!("User {} reactivated: {}", user_id.to_text(), reason.unwrap_or_default()),
+            true,
+        );
+    }
+    result
+}
+
+/// Get account status change history for a user (admin only)
+#[query]
+pub fn get_user_status_history(user_id: Principal) -> Result<Vec<DeactivationRecord>, String> {
+    if !crate::helpers::is_admin(&ic_cdk::caller()) {
+        return Err("Unauthorized: Admin access required".to_string());
+    }
+    
+    Ok(USER_STATUS_HISTORY.with(|history| {
+        history.borrow().get(&user_id).map(|h| h.records).unwrap_or_default()
+    }))
+}
+
+/// Set the caller's preferred language for notifications
+#[update]
+pub fn set_preferred_language(language: String) -> UserResult {
+    let principal = ic_cdk::caller();
+    if let Err(e) = crate::validation::guard_caller(&principal, "set_preferred_language") {
+        return UserResult::Err(e);
+    }
+    let language = language.to_lowercase();
+    
+    if !SUPPORTED_LANGUAGES.contains(&language.as_str()) {
+        return UserResult::Err(format!("Unsupported language. Supported: {:?}", SUPPORTED_LANGUAGES));
+    }
     
     match get_user_by_principal(&principal) {
         Some(mut user) => {
-            user.is_active = true;
+            user.preferred_language = Some(language);
             user.updated_at = time();
             
-            // Update user in storage
             USERS.with(|users| {
                 users.borrow_mut().insert(principal, user.clone());
             });
@@ -408,6 +612,77 @@ pub fn reactivate_user() -> UserResult {
     }
 }
 
+/// Set a user's KYC tier (admin only). `reference` identifies the verification case.
+#[update]
+pub fn set_kyc_tier(user_id: Principal, tier: KycTier, reference: String) -> UserResult {
+    use crate::audit_logging::{log_audit_enhanced, AuditCategory, AuditEventLevel, AuditDetails, AuditResult};
+    
+    let caller = ic_cdk::caller();
+    if let Err(e) = crate::validation::guard_caller(&caller, "set_kyc_tier") {
+        return UserResult::Err(e);
+    }
+    if !crate::helpers::is_admin(&caller) {
+        return UserResult::Err("Unauthorized: Admin access required".to_string());
+    }
+    let reference = reference.trim().to_string();
+    if reference.is_empty() || reference.len() > 200 {
+        return UserResult::Err("A KYC reference of 1-200 characters is required".to_string());
+    }
+    
+    let mut user = match get_user_by_principal(&user_id) {
+        Some(user) => user,
+        None => return UserResult::Err("User not found".to_string()),
+    };
+    let previous_tier = user.effective_kyc_tier();
+    
+    let now = time();
+    user.kyc_tier = Some(tier.clone());
+    user.kyc_reference = Some(reference.clone());
+    user.kyc_updated_at = Some(now);
+    user.updated_at = now;
+    USERS.with(|users| {
+        users.borrow_mut().insert(user_id, user.clone());
+    });
+    
+    log_audit_enhanced(
+        AuditCategory::Compliance,
+        "KYC_TIER_UPDATED".to_string(),
+        AuditEventLevel::Info,
+        AuditDetails {
+            description: format!(
+                "KYC tier for {} changed from {:?} to {:?} by {} (reference: {})",
+                user_id.to_text(), previous_tier, tier, caller.to_text(), reference
+            ),
+            entity_type: Some("user".to_string()),
+            entity_id: Some(user_id.to_text()),
+            before_state: Some(format!("{:?}", previous_tier)),
+            after_state: Some(format!("{:?}", tier)),
+            affected_principals: vec![user_id],
+            ..Default::default()
+        },
+        AuditResult {
+            success: true,
+            error_code: None,
+            error_message: None,
+            execution_time_ms: None,
+            gas_used: None,
+            cycles_consumed: None,
+            memory_used_bytes: None,
+            warning_flags: vec![],
+        },
+        None,
+    );
+    
+    UserResult::Ok(user)
+}
+
+/// Get a user's preferred language, defaulting to Indonesian
+pub fn get_user_preferred_language(user_id: &Principal) -> String {
+    get_user_by_principal(user_id)
+        .and_then(|user| user.preferred_language)
+        .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string())
+}
+
 /// Check if user has completed profile
 #[query]
 pub fn has_completed_profile(user_id: Principal) -> bool {
@@ -449,70 +724,145 @@ pub fn get_active_users() -> Vec<User> {
     })
 }
 
-/// Validate email format
-pub fn validate_email(email: &str) -> bool {
+/// Why an email address or phone number was rejected
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContactValidationError {
+    Empty,
+    MalformedEmail,
+    InvalidLocalPart,
+    InvalidDomain,
+    UnrecognizedTld(String),
+    InvalidPhoneCharacters,
+    PhoneLength { digits: usize },
+    MissingCountryCode,
+}
+
+impl std::fmt::Display for ContactValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContactValidationError::Empty => write!(f, "Value is empty"),
+            ContactValidationError::MalformedEmail => write!(f, "Invalid email format"),
+            ContactValidationError::InvalidLocalPart => write!(f, "Invalid email format: bad name before '@'"),
+            ContactValidationError::InvalidDomain => write!(f, "Invalid email format: bad domain"),
+            ContactValidationError::UnrecognizedTld(tld) => write!(f, "Invalid email format: unrecognized domain ending '.{}'", tld),
+            ContactValidationError::InvalidPhoneCharacters => write!(f, "Invalid phone number format"),
+            ContactValidationError::PhoneLength { digits } => write!(
+                f, "Invalid phone number format: {} digits, expected {} to {}", digits, MIN_PHONE_DIGITS, MAX_PHONE_DIGITS
+            ),
+            ContactValidationError::MissingCountryCode => write!(f, "Invalid phone number format: include the country code, e.g. +62"),
+        }
+    }
+}
+
+const MIN_PHONE_DIGITS: usize = 10;
+const MAX_PHONE_DIGITS: usize = 15; // E.164 limit
+
+/// Country code assumed for national numbers written with a leading trunk '0' (e.g. 0812...)
+const DEFAULT_PHONE_COUNTRY_CODE: &str = "62";
+
+/// Domain endings accepted when strict contact validation is on
+const RECOGNIZED_TLDS: [&str; 24] = [
+    "com", "org", "net", "edu", "gov", "mil", "int", "info", "biz", "io", "co", "id",
+    "my", "sg", "ph", "th", "vn", "au", "uk", "us", "de", "nl", "jp", "in",
+];
+
+/// Whether the `strict_contact_validation` governance parameter is on
+pub fn strict_contact_validation() -> bool {
+    crate::governance::get_protocol_parameter("strict_contact_validation".to_string())
+        .map(|p| p.current_value != 0)
+        .unwrap_or(false)
+}
+
+/// Canonical form of an email address: trimmed, with the domain lowercased. The local part
+/// keeps its case since mail servers may treat it as case-sensitive. In strict mode the
+/// domain must end in a recognized TLD.
+pub fn validate_and_normalize_email(email: &str, strict: bool) -> Result<String, ContactValidationError> {
+    let email = email.trim();
     if email.is_empty() {
-        return false;
+        return Err(ContactValidationError::Empty);
     }
-    
-    // Basic email validation
+
     let parts: Vec<&str> = email.split('@').collect();
-    if parts.len() != 2 {
-        return false;
+    if parts.len() != 2 || email.chars().any(|c| c.is_whitespace()) {
+        return Err(ContactValidationError::MalformedEmail);
     }
-    
     let local_part = parts[0];
-    let domain_part = parts[1];
-    
-    // Check if local part is not empty
-    if local_part.is_empty() {
-        return false;
-    }
-    
-    // Check if domain part is not empty and contains at least one dot
-    if domain_part.is_empty() || !domain_part.contains('.') {
-        return false;
+    let domain_part = parts[1].to_ascii_lowercase();
+
+    // No empty name, and no leading, trailing or consecutive dots in it
+    if local_part.is_empty() || local_part.split('.').any(|segment| segment.is_empty()) {
+        return Err(ContactValidationError::InvalidLocalPart);
     }
-    
-    // Check if domain has at least one character before and after the last dot
-    let domain_parts: Vec<&str> = domain_part.split('.').collect();
-    if domain_parts.len() < 2 {
-        return false;
+
+    // Domain needs at least two labels, none of them empty
+    let labels: Vec<&str> = domain_part.split('.').collect();
+    if labels.len() < 2 || labels.iter().any(|label| label.is_empty()) {
+        return Err(ContactValidationError::InvalidDomain);
     }
-    
-    // Check if all domain parts are not empty
-    for part in domain_parts {
-        if part.is_empty() {
-            return false;
+
+    if strict {
+        let tld = labels[labels.len() - 1];
+        if !RECOGNIZED_TLDS.contains(&tld) {
+            return Err(ContactValidationError::UnrecognizedTld(tld.to_string()));
         }
     }
-    
-    true
+
+    Ok(format!("{}@{}", local_part, domain_part))
 }
 
-/// Validate phone number format
-pub fn validate_phone(phone: &str) -> bool {
+/// Canonical form of a phone number. Separators are stripped; numbers with a '+' or '00'
+/// international prefix and national numbers with a trunk '0' become E.164 (`+<digits>`).
+/// Other numbers are kept as bare digits, which strict mode rejects.
+pub fn validate_and_normalize_phone(phone: &str, strict: bool) -> Result<String, ContactValidationError> {
+    let phone = phone.trim();
     if phone.is_empty() {
-        return false;
+        return Err(ContactValidationError::Empty);
     }
-    
-    // Remove common phone number separators
-    let cleaned = phone.replace(&['-', ' ', '(', ')'][..], "");
-    
-    // Check if it starts with + (optional)
-    let number_part = if cleaned.starts_with('+') {
-        &cleaned[1..]
+
+    let cleaned: String = phone.chars()
+        .filter(|c| !matches!(c, '-' | ' ' | '(' | ')' | '.'))
+        .collect();
+
+    let (international, digits) = if let Some(rest) = cleaned.strip_prefix('+') {
+        (true, rest.to_string())
+    } else if let Some(rest) = cleaned.strip_prefix("00") {
+        (true, rest.to_string())
     } else {
-        &cleaned
+        (false, cleaned.clone())
     };
-    
-    // Check if all remaining characters are digits
-    if !number_part.chars().all(|c| c.is_ascii_digit()) {
-        return false;
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(ContactValidationError::InvalidPhoneCharacters);
     }
-    
-    // Check minimum length (should be at least 10 digits)
-    number_part.len() >= 10
+    if digits.len() < MIN_PHONE_DIGITS || digits.len() > MAX_PHONE_DIGITS {
+        return Err(ContactValidationError::PhoneLength { digits: digits.len() });
+    }
+
+    if international {
+        return Ok(format!("+{}", digits));
+    }
+    if let Some(national) = digits.strip_prefix('0') {
+        // The country code replaces the trunk '0', so the E.164 form can outgrow the limit
+        let e164_digits = DEFAULT_PHONE_COUNTRY_CODE.len() + national.len();
+        if e164_digits > MAX_PHONE_DIGITS {
+            return Err(ContactValidationError::PhoneLength { digits: e164_digits });
+        }
+        return Ok(format!("+{}{}", DEFAULT_PHONE_COUNTRY_CODE, national));
+    }
+    if strict {
+        return Err(ContactValidationError::MissingCountryCode);
+    }
+    Ok(digits)
+}
+
+/// Validate email format
+pub fn validate_email(email: &str) -> bool {
+    validate_and_normalize_email(email, false).is_ok()
+}
+
+/// Validate phone number format
+pub fn validate_phone(phone: &str) -> bool {
+    validate_and_normalize_phone(phone, false).is_ok()
 }
 
 /// Validate BTC address format
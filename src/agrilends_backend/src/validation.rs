@@ -0,0 +1,120 @@
+// ========== INPUT VALIDATION MODULE ==========
+// Shared guards for the top of public update methods, so the same bad call gets the same
+// error from every endpoint. Guards run in this order:
+//   1. reject_anonymous                 - identity
+//   2. require_not_blacklisted          - security
+//   3. ensure_operation_available       - maintenance / pause (helpers)
+//   4. require_registered_active_user / require_role - authorization
+//   4b. require_admin_nonce             - replay protection for sensitive admin calls
+//   5. require_positive and other argument checks
+// `guard_caller` runs steps 1 and 2 together.
+
+use candid::Principal;
+
+use crate::types::AdminNonceState;
+use crate::user_management::{get_user_by_principal, Role, User};
+
+/// Roles an update method can be restricted to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CallerRole {
+    Farmer,
+    Investor,
+    Admin,
+    SuperAdmin,
+    LoanManager,
+}
+
+impl CallerRole {
+    fn label(&self) -> &'static str {
+        match self {
+            CallerRole::Farmer => "farmer",
+            CallerRole::Investor => "investor",
+            CallerRole::Admin => "admin",
+            CallerRole::SuperAdmin => "super admin",
+            CallerRole::LoanManager => "loan manager",
+        }
+    }
+}
+
+pub fn reject_anonymous(caller: &Principal) -> Result<(), String> {
+    if *caller == Principal::anonymous() {
+        return Err("Anonymous callers are not allowed".to_string());
+    }
+    Ok(())
+}
+
+/// `field` names the argument in the error, e.g. "Amount" or "Payment amount"
+pub fn require_positive(amount: u64, field: &str) -> Result<(), String> {
+    if amount == 0 {
+        return Err(format!("{} must be greater than zero", field));
+    }
+    Ok(())
+}
+
+pub fn require_not_blacklisted(caller: &Principal, operation: &str) -> Result<(), String> {
+    crate::production_security::ensure_not_blacklisted(caller, operation)
+}
+
+/// Steps 1 and 2 of the guard order, for every caller-facing update
+pub fn guard_caller(caller: &Principal, operation: &str) -> Result<(), String> {
+    reject_anonymous(caller)?;
+    require_not_blacklisted(caller, operation)
+}
+
+pub fn require_registered_active_user(caller: &Principal) -> Result<User, String> {
+    let user = get_user_by_principal(caller).ok_or("User not registered. Please register first")?;
+    if !user.is_active {
+        return Err("Account is not active".to_string());
+    }
+    Ok(user)
+}
+
+/// Farmer and investor roles also require a registered, active account
+pub fn require_role(caller: &Principal, role: CallerRole) -> Result<(), String> {
+    let allowed = match role {
+        CallerRole::Farmer => require_registered_active_user(caller)?.role == Role::Farmer,
+        CallerRole::Investor => require_registered_active_user(caller)?.role == Role::Investor,
+        CallerRole::Admin => crate::helpers::is_admin(caller),
+        CallerRole::SuperAdmin => crate::governance::is_super_admin(caller),
+        CallerRole::LoanManager => crate::helpers::is_loan_manager(caller),
+    };
+    if !allowed {
+        return Err(format!("Unauthorized: Only a registered {} can perform this action", role.label()));
+    }
+    Ok(())
+}
+
+/// Nonce to record for this call, if any. Checks apply only while the flag is on and the admin
+/// has nonces required; a nonce sent otherwise is ignored.
+pub fn check_admin_nonce(
+    state: Option<&AdminNonceState>,
+    nonces_enabled: bool,
+    nonce: Option<u64>,
+) -> Result<Option<u64>, String> {
+    let state = match state {
+        Some(state) if nonces_enabled && state.required => state,
+        _ => return Ok(None),
+    };
+    let nonce = nonce.ok_or("A nonce is required for this action")?;
+    if nonce <= state.last_nonce {
+        return Err(format!(
+            "Nonce {} was already used or is out of order; the next nonce must exceed {}",
+            nonce, state.last_nonce
+        ));
+    }
+    Ok(Some(nonce))
+}
+
+/// Step 4b for sensitive admin mutations. The nonce is spent even if the call fails later.
+pub fn require_admin_nonce(caller: &Principal, nonce: Option<u64>, operation: &str) -> Result<(), String> {
+    let nonces_enabled = crate::feature_flags::is_feature_enabled(crate::feature_flags::FEATURE_ADMIN_NONCES);
+    let state = crate::storage::get_admin_nonce_state(*caller);
+    let accepted = check_admin_nonce(state.as_ref(), nonces_enabled, nonce)
+        .map_err(|e| format!("{} rejected: {}", operation, e))?;
+    if let (Some(accepted), Some(mut state)) = (accepted, state) {
+        state.last_nonce = accepted;
+        state.updated_at = ic_cdk::api::time();
+        crate::storage::store_admin_nonce_state(state);
+    }
+    Ok(())
+}